@@ -0,0 +1,79 @@
+//! Style computation on a large generated document: a full restyle against
+//! the incremental recalc after a class or hover change.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rust_browser::css::{parse_css, StyleComputer, Stylesheet};
+use rust_browser::dom::{parse_html, Document, ElementState, NodeId};
+use std::rc::Rc;
+
+const SECTIONS: usize = 200;
+const ITEMS: usize = 20;
+
+const CSS: &str = r#"
+    body { font-family: sans-serif; color: #222; }
+    section { margin: 8px; padding: 4px; border: 1px solid #ccc; }
+    section.open { background: #eef; }
+    section.open .item { font-weight: bold; }
+    h2 + ul { margin-top: 0; }
+    li:nth-child(2n) { background: #f8f8f8; }
+    .item span { color: #555; }
+    a:hover { color: red; text-decoration: underline; }
+    #section-0 h2 { font-size: 2em; }
+"#;
+
+/// Sections of lists of linked items, about 20 elements per item
+fn document() -> Document {
+    let mut html = String::from("<html><body>");
+    for section in 0..SECTIONS {
+        html.push_str(&format!(r#"<section id="section-{section}"><h2>Section {section}</h2><ul>"#));
+        for item in 0..ITEMS {
+            html.push_str(&format!(
+                r#"<li class="item"><a href="/{section}/{item}">Item {item}</a> <span>note</span></li>"#
+            ));
+        }
+        html.push_str("</ul></section>");
+    }
+    html.push_str("</body></html>");
+    parse_html(&html)
+}
+
+fn style_computer(document: &Document) -> StyleComputer {
+    let mut computer = StyleComputer::new(1280.0, 800.0);
+    computer.add_stylesheet(Rc::new(Stylesheet::user_agent()));
+    computer.add_stylesheet(Rc::new(parse_css(CSS)));
+    computer.compute_styles(document);
+    computer
+}
+
+fn bench_style(c: &mut Criterion) {
+    let mut document = document();
+    let mut computer = style_computer(&document);
+
+    c.bench_function("full restyle", |b| b.iter(|| computer.compute_styles(&document)));
+
+    let section: NodeId = document.get_element_by_id("section-100").unwrap();
+    let mut open = false;
+    c.bench_function("class change", |b| {
+        b.iter(|| {
+            let old = open.then_some("open");
+            open = !open;
+            document.set_attribute(section, "class", if open { "open" } else { "" });
+            computer.invalidate_attribute(&document, section, "class", old);
+            computer.recalc_styles(&document)
+        })
+    });
+
+    let link = document.get_elements_by_tag_name("a")[ITEMS * 100];
+    let mut hovered = false;
+    c.bench_function("hover change", |b| {
+        b.iter(|| {
+            hovered = !hovered;
+            document.set_element_state(link, ElementState::HOVER, hovered);
+            computer.invalidate_state(&document, link, ElementState::HOVER);
+            computer.recalc_styles(&document)
+        })
+    });
+}
+
+criterion_group!(benches, bench_style);
+criterion_main!(benches);
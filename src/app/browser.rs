@@ -1,11 +1,15 @@
 use super::settings::BrowserSettings;
 use super::tab::Tab;
 use crate::layout::tree::ScrollbarHitArea;
-use crate::net::{AsyncLoader, LoadProgress, Loader};
+use crate::net::cookies::COOKIE_FILE;
+use crate::net::{AsyncLoader, CookieJar, LoadProgress, Loader};
+use crate::render::filter::{BlendMode, Effects};
 use crate::render::gpu::GpuContext;
-use crate::render::painter::{Color, DisplayCommand, Painter, Rect};
-use crate::render::text::TextRenderer;
+use crate::render::painter::{Color, DisplayCommand, Layer, Painter, Rect, RectPaint, Tiling};
+use crate::render::text::{TextRenderer, TextRun};
+use crate::render::transform::Transform;
 use crate::ui::Chrome;
+use std::sync::Arc;
 use tokio::runtime::Runtime;
 use tokio::sync::mpsc;
 use wgpu::*;
@@ -23,27 +27,66 @@ pub struct Browser {
     pub runtime: Runtime,
     progress_rx: Option<mpsc::UnboundedReceiver<LoadProgress>>,
     /// Cached display commands to avoid rebuilding every frame
-    cached_rects: Vec<(Rect, Color)>,
-    cached_texts: Vec<(String, f32, f32, Color, f32)>,
+    cached_rects: Vec<RectPaint>,
+    cached_texts: Vec<TextRun>,
     cached_images: Vec<(Rect, usize, f32)>,
     /// Whether the cache needs to be rebuilt
     display_list_dirty: bool,
 }
 
+/// What is drawn between the rects, in order
+enum Interleaved {
+    /// A background image or gradient
+    Background(DisplayCommand),
+    Layer(LayerGroup),
+}
+
+/// Content drawn into a layer of its own, then composited transformed, or
+/// changed as a whole by effects
+struct LayerGroup {
+    /// Takes the display list's coordinates to the target's
+    transform: Transform,
+    /// What the commands cover, in the display list's coordinates
+    bounds: Rect,
+    commands: Vec<DisplayCommand>,
+    effects: Effects,
+    /// Where the backdrop filter shows through, in the display list's
+    /// coordinates
+    backdrop: Rect,
+}
+
+/// A layer drawn and filtered, ready to composite
+struct RenderedLayer {
+    layer: Layer,
+    /// Takes the layer's coordinates to the target's
+    transform: Transform,
+    /// In logical pixels
+    size: (f32, f32),
+    effects: Effects,
+    /// In the target's coordinates
+    backdrop: Rect,
+}
+
 impl Browser {
     pub fn new(viewport_width: f32, viewport_height: f32, settings: BrowserSettings) -> Self {
         let chrome_height = 50.0;
         let content_height = viewport_height - chrome_height;
 
-        let tab = Tab::new(0, viewport_width, content_height, settings.clone());
+        // One jar for every loader and tab, so cookies agree everywhere
+        let cookie_jar = Arc::new(match &settings.profile_dir {
+            Some(dir) => CookieJar::open(dir.join(COOKIE_FILE)),
+            None => CookieJar::new(),
+        });
+
+        let tab = Tab::new(0, viewport_width, content_height, settings.clone(), Arc::clone(&cookie_jar));
 
         let runtime = Runtime::new().expect("Failed to create Tokio runtime");
 
         Self {
             tabs: vec![tab],
             active_tab: 0,
-            loader: Loader::new(),
-            async_loader: runtime.block_on(async { AsyncLoader::new() }),
+            loader: Loader::with_cookie_jar(Arc::clone(&cookie_jar)),
+            async_loader: runtime.block_on(async { AsyncLoader::with_cookie_jar(cookie_jar) }),
             chrome: Chrome::new(viewport_width, chrome_height, &settings),
             viewport_width,
             viewport_height,
@@ -87,6 +130,26 @@ impl Browser {
         self.reload_current_page(text_renderer);
     }
 
+    pub fn toggle_dark_theme(&mut self, text_renderer: &mut TextRenderer) {
+        self.settings.dark_theme = !self.settings.dark_theme;
+        self.update_color_preferences(text_renderer);
+    }
+
+    pub fn toggle_forced_colors(&mut self, text_renderer: &mut TextRenderer) {
+        self.settings.forced_colors = !self.settings.forced_colors;
+        self.update_color_preferences(text_renderer);
+    }
+
+    /// Restyles every tab for the current color settings, without reloading
+    fn update_color_preferences(&mut self, text_renderer: &mut TextRenderer) {
+        for tab in &mut self.tabs {
+            tab.settings.dark_theme = self.settings.dark_theme;
+            tab.settings.forced_colors = self.settings.forced_colors;
+            tab.update_color_preferences(text_renderer);
+        }
+        self.display_list_dirty = true;
+    }
+
     fn reload_current_page(&mut self, text_renderer: &mut TextRenderer) {
         let tab = &self.tabs[self.active_tab];
         if let Some(url) = tab.url.clone() {
@@ -132,6 +195,11 @@ impl Browser {
         }
     }
 
+    /// Writes cookie changes to the profile once they have settled
+    pub fn save_cookies_if_idle(&self) {
+        self.loader.cookie_jar().save_if_idle();
+    }
+
     /// Poll for loading progress updates
     pub fn poll_loading(&mut self, text_renderer: &mut TextRenderer) {
         if let Some(rx) = &mut self.progress_rx {
@@ -228,6 +296,18 @@ impl Browser {
         self.chrome.set_render_stats(fps, render_time_ms);
     }
 
+    /// Check if there are `@font-face` fonts to load
+    pub fn has_pending_fonts(&self) -> bool {
+        self.tabs[self.active_tab].has_pending_fonts()
+    }
+
+    /// Load pending web fonts and re-layout in them
+    pub fn load_pending_fonts(&mut self, text_renderer: &mut TextRenderer) {
+        let tab = &mut self.tabs[self.active_tab];
+        tab.load_fonts_sync(&self.loader, text_renderer);
+        self.display_list_dirty = true;
+    }
+
     /// Check if there are pending images to load
     pub fn has_pending_images(&self) -> bool {
         self.tabs[self.active_tab].has_pending_images()
@@ -305,8 +385,15 @@ impl Browser {
                 ScrollbarHitArea::None => {}
             }
 
+            let hit = self.active_tab().hit_test(x, content_y);
+            let tab = &mut self.tabs[self.active_tab];
+            let active_changed = tab.set_active(hit, text_renderer);
+            if tab.focus_at(hit, text_renderer) || active_changed {
+                self.display_list_dirty = true;
+            }
+
             let tab = &self.tabs[self.active_tab];
-            if let Some(node_id) = tab.hit_test(x, content_y) {
+            if let Some(node_id) = hit {
                 if let Some(href) = tab.get_link_at(node_id) {
                     let full_url = if href.starts_with("http") {
                         href
@@ -327,11 +414,14 @@ impl Browser {
         self.display_list_dirty = true;
     }
 
-    pub fn handle_mouse_move(&mut self, x: i32, y: i32) {
+    pub fn handle_mouse_move(&mut self, x: i32, y: i32, text_renderer: &mut TextRenderer) {
         let x = x as f32;
         let y = y as f32;
 
         if y < self.chrome_height {
+            if self.active_tab_mut().set_hovered(None, text_renderer) {
+                self.display_list_dirty = true;
+            }
             return;
         }
 
@@ -344,10 +434,18 @@ impl Browser {
         }
 
         self.active_tab_mut().layout_tree.update_scrollbar_hover(x, content_y);
+
+        let hit = self.active_tab().hit_test(x, content_y);
+        if self.active_tab_mut().set_hovered(hit, text_renderer) {
+            self.display_list_dirty = true;
+        }
     }
 
-    pub fn handle_mouse_up(&mut self) {
+    pub fn handle_mouse_up(&mut self, text_renderer: &mut TextRenderer) {
         self.active_tab_mut().layout_tree.end_thumb_drag();
+        if self.active_tab_mut().set_active(None, text_renderer) {
+            self.display_list_dirty = true;
+        }
     }
 
     pub fn update_scroll(&mut self, dt: f32) -> bool {
@@ -358,8 +456,24 @@ impl Browser {
         animating
     }
 
+    /// Runs the active tab's transitions and animations on by `dt` seconds
+    pub fn update_animations(&mut self, dt: f32, text_renderer: &mut TextRenderer) -> bool {
+        let changed = self.active_tab_mut().update_animations(dt, text_renderer);
+        if changed {
+            self.display_list_dirty = true;
+        }
+        changed
+    }
+
+    /// Stops the active tab's animation clock at `time` seconds
+    pub fn seek_animations(&mut self, time: f32, text_renderer: &mut TextRenderer) {
+        if self.active_tab_mut().seek_animations(time, text_renderer) {
+            self.display_list_dirty = true;
+        }
+    }
+
     pub fn is_animating(&self) -> bool {
-        self.active_tab().is_scroll_animating() || self.is_loading()
+        self.active_tab().is_scroll_animating() || self.active_tab().is_animating() || self.is_loading()
     }
 
     pub fn handle_text_input(&mut self, text: &str) {
@@ -368,6 +482,11 @@ impl Browser {
     }
 
     pub fn handle_key(&mut self, keycode: sdl2::keyboard::Keycode, text_renderer: &mut TextRenderer) {
+        match keycode {
+            sdl2::keyboard::Keycode::F8 => return self.toggle_dark_theme(text_renderer),
+            sdl2::keyboard::Keycode::F9 => return self.toggle_forced_colors(text_renderer),
+            _ => {}
+        }
         if let Some(action) = self.chrome.handle_key(keycode) {
             match action {
                 crate::ui::ChromeAction::Navigate(url) => self.navigate(&url, text_renderer),
@@ -411,7 +530,12 @@ impl Browser {
             label: Some("Render Encoder"),
         });
 
-        // Clear pass
+        // Clear pass, with the page's canvas color
+        let canvas = if self.settings.forced_colors {
+            crate::css::color::forced_color("canvas").unwrap_or(Color::BLACK)
+        } else {
+            Color::WHITE
+        };
         {
             let _clear_pass = encoder.begin_render_pass(&RenderPassDescriptor {
                 label: Some("Clear Pass"),
@@ -420,9 +544,9 @@ impl Browser {
                     resolve_target: None,
                     ops: Operations {
                         load: LoadOp::Clear(wgpu::Color {
-                            r: 1.0,
-                            g: 1.0,
-                            b: 1.0,
+                            r: canvas.r as f64,
+                            g: canvas.g as f64,
+                            b: canvas.b as f64,
                             a: 1.0,
                         }),
                         store: StoreOp::Store,
@@ -471,16 +595,18 @@ impl Browser {
         let mut content_rects = Vec::new();
         let mut content_texts = Vec::new();
         let mut content_images = Vec::new();
+        let mut content_interleaved = Vec::new();
 
         let y_offset = if include_chrome { self.chrome_height } else { 0.0 };
         let content_list = self.active_tab().build_display_list();
 
         Self::collect_display_commands(
             &content_list.commands,
-            y_offset,
+            (0.0, y_offset),
             &mut content_rects,
             &mut content_texts,
             &mut content_images,
+            &mut content_interleaved,
         );
 
         // Chrome display list (if included)
@@ -488,7 +614,7 @@ impl Browser {
         let mut chrome_texts = Vec::new();
         if include_chrome {
             let chrome_list = self.chrome.build_display_list();
-            Self::collect_display_commands(&chrome_list.commands, 0.0, &mut chrome_rects, &mut chrome_texts, &mut Vec::new());
+            Self::collect_display_commands(&chrome_list.commands, (0.0, 0.0), &mut chrome_rects, &mut chrome_texts, &mut Vec::new(), &mut Vec::new());
         }
 
         let chrome_height = if include_chrome { self.chrome_height } else { 0.0 };
@@ -503,6 +629,7 @@ impl Browser {
             &content_rects,
             &content_texts,
             &content_images,
+            &content_interleaved,
             &chrome_rects,
             &chrome_texts,
             &self.active_tab().image_cache,
@@ -521,34 +648,74 @@ impl Browser {
         text_renderer: &mut TextRenderer,
         encoder: &mut CommandEncoder,
         view: &TextureView,
-        content_rects: &[(Rect, Color)],
-        content_texts: &[(String, f32, f32, Color, f32)],
+        content_rects: &[RectPaint],
+        content_texts: &[TextRun],
         content_images: &[(Rect, usize, f32)],
-        chrome_rects: &[(Rect, Color)],
-        chrome_texts: &[(String, f32, f32, Color, f32)],
+        content_interleaved: &[(usize, Interleaved)],
+        chrome_rects: &[RectPaint],
+        chrome_texts: &[TextRun],
         image_cache: &crate::render::ImageCache,
         viewport_width: f32,
         viewport_height: f32,
         scale_factor: f32,
         chrome_height: f32,
     ) {
+        let viewport = (viewport_width, viewport_height, scale_factor);
+
+        // Layers are drawn first, each submitted before the frame writes
+        // the rect and text buffers they share with it
+        let mut layers = Vec::new();
+        for (_, item) in content_interleaved {
+            if let Interleaved::Layer(layer) = item {
+                layers.push(Self::render_layer(gpu, painter, text_renderer, layer, image_cache, viewport));
+            }
+        }
+        let mut layers = layers.into_iter();
+
+        // Blending and backdrop filters read what is already drawn, so then
+        // the frame is drawn into a canvas of its own, composited at the end
+        let needs_backdrop = content_interleaved
+            .iter()
+            .any(|(_, item)| matches!(item, Interleaved::Layer(layer) if layer.effects.needs_backdrop()));
+        let canvas = needs_backdrop.then(|| {
+            let width = (viewport_width * scale_factor).round() as u32;
+            let height = (viewport_height * scale_factor).round() as u32;
+            painter.create_layer(gpu, encoder, width, height)
+        });
+        let target = canvas.as_ref().map_or(view, |canvas| &canvas.view);
+
         // Combine all rects into a single draw call to avoid buffer synchronization issues
         // Content rects first (behind), then chrome rects on top
-        let mut all_rects: Vec<(Rect, Color)> = Vec::with_capacity(content_rects.len() + chrome_rects.len());
+        let mut all_rects: Vec<RectPaint> = Vec::with_capacity(content_rects.len() + chrome_rects.len());
         all_rects.extend_from_slice(content_rects);
         all_rects.extend_from_slice(chrome_rects);
 
-        if !all_rects.is_empty() {
-            painter.draw_rects(
-                gpu,
-                encoder,
-                view,
-                &all_rects,
-                viewport_width,
-                viewport_height,
-                scale_factor,
-            );
+        // Background layers and transformed content paint between the rects
+        // before and after them
+        painter.upload_rects(gpu, &all_rects, viewport_width, viewport_height, scale_factor);
+        let mut drawn = 0;
+        for (rects_before, item) in content_interleaved {
+            painter.draw_rect_range(encoder, target, drawn..*rects_before);
+            drawn = *rects_before;
+
+            match item {
+                Interleaved::Background(DisplayCommand::BackgroundImage { clip, tiling, texture_id, opacity }) => {
+                    if let Some(texture_view) = image_cache.get_texture_view(*texture_id) {
+                        painter.draw_tiled_image(gpu, encoder, target, texture_view, clip, tiling, *opacity, viewport);
+                    }
+                }
+                Interleaved::Background(DisplayCommand::Gradient { clip, tiling, gradient, opacity }) => {
+                    painter.draw_gradient(gpu, encoder, target, clip, tiling, gradient, *opacity, viewport);
+                }
+                Interleaved::Background(_) => {}
+                Interleaved::Layer(_) => {
+                    if let Some(Some(rendered)) = layers.next() {
+                        Self::composite_layer(gpu, painter, encoder, target, canvas.as_ref(), &rendered, viewport);
+                    }
+                }
+            }
         }
+        painter.draw_rect_range(encoder, target, drawn..all_rects.len());
 
         // Render images with viewport culling
         for (rect, texture_id, opacity) in content_images {
@@ -561,7 +728,7 @@ impl Browser {
                 painter.draw_image(
                     gpu,
                     encoder,
-                    view,
+                    target,
                     texture_view,
                     rect,
                     *opacity,
@@ -577,7 +744,7 @@ impl Browser {
         let physical_width = (viewport_width * scale_factor) as u32;
         let physical_height = (viewport_height * scale_factor) as u32;
 
-        let text_groups: Vec<(&[(String, f32, f32, Color, f32)], u32)> = if !chrome_texts.is_empty() {
+        let text_groups: Vec<(&[TextRun], u32)> = if !chrome_texts.is_empty() {
             vec![
                 (content_texts, content_clip_top),
                 (chrome_texts, 0),
@@ -589,54 +756,216 @@ impl Browser {
         text_renderer.render_all(
             gpu,
             encoder,
-            view,
+            target,
             &text_groups,
             physical_width,
             physical_height,
+            scale_factor,
         );
+
+        if let Some(canvas) = &canvas {
+            let size = (viewport_width, viewport_height);
+            painter.draw_layer(gpu, encoder, view, canvas, &Transform::IDENTITY, size, 1.0, viewport);
+        }
     }
 
+    /// Composites a rendered layer into `view`. With a blend mode or a
+    /// backdrop filter, what is behind it is read from `canvas`, which
+    /// `view` draws into.
+    fn composite_layer(
+        gpu: &GpuContext,
+        painter: &Painter,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        canvas: Option<&Layer>,
+        rendered: &RenderedLayer,
+        viewport: (f32, f32, f32),
+    ) {
+        let RenderedLayer { layer, transform, size, effects, backdrop } = rendered;
+        let scale_factor = viewport.2;
+        if let Some(canvas) = canvas {
+            // What is behind the box shows through it filtered
+            if !effects.backdrop_filter.is_empty() {
+                if let Some(region) = Painter::layer_region(canvas, backdrop, scale_factor) {
+                    let behind = painter.copy_layer_region(gpu, encoder, canvas, region);
+                    let filtered = painter.apply_filters(gpu, encoder, behind, &effects.backdrop_filter, scale_factor);
+                    let (x, y, width, height) = region;
+                    let logical = |v: u32| v as f32 / scale_factor;
+                    let transform = Transform::translate(logical(x), logical(y), 0.0);
+                    let size = (logical(width), logical(height));
+                    painter.draw_layer(gpu, encoder, view, &filtered, &transform, size, effects.opacity, viewport);
+                }
+            }
+            // Groups with effects are only ever moved, never transformed
+            match transform.as_translation() {
+                Some((x, y)) if effects.blend_mode != BlendMode::Normal => {
+                    let area = Rect::new(x, y, size.0, size.1);
+                    let mode = effects.blend_mode;
+                    painter.blend_layer(gpu, encoder, canvas, layer, &area, effects.opacity, mode, scale_factor);
+                    return;
+                }
+                _ => {}
+            }
+        }
+        painter.draw_layer(gpu, encoder, view, layer, transform, *size, effects.opacity, viewport);
+    }
+
+    /// Draws a layer's commands into a texture of its own and filters it,
+    /// in an encoder submitted before returning. Only the part that shows
+    /// in the target is drawn; `None` if none of it does.
+    fn render_layer(
+        gpu: &GpuContext,
+        painter: &Painter,
+        text_renderer: &mut TextRenderer,
+        layer: &LayerGroup,
+        image_cache: &crate::render::ImageCache,
+        (viewport_width, viewport_height, scale_factor): (f32, f32, f32),
+    ) -> Option<RenderedLayer> {
+        // The target's corners taken back through the transform; with
+        // perspective, some may not come from anywhere on the layer
+        let corners = [(0.0, 0.0), (viewport_width, 0.0), (viewport_width, viewport_height), (0.0, viewport_height)]
+            .map(|(x, y)| layer.transform.inverse_map(x, y));
+        let area = match corners {
+            [Some(a), Some(b), Some(c), Some(d)] => {
+                let visible = Rect::new(a.0, a.1, 0.0, 0.0)
+                    .union(&Rect::new(b.0, b.1, 0.0, 0.0))
+                    .union(&Rect::new(c.0, c.1, 0.0, 0.0))
+                    .union(&Rect::new(d.0, d.1, 0.0, 0.0));
+                // Filters spread content into view from as far outside it
+                let reach = layer.effects.filter_bounds(&visible);
+                let grow_x = (visible.x - reach.x).max(reach.x + reach.width - visible.x - visible.width);
+                let grow_y = (visible.y - reach.y).max(reach.y + reach.height - visible.y - visible.height);
+                let visible = Rect::new(
+                    visible.x - grow_x,
+                    visible.y - grow_y,
+                    visible.width + grow_x * 2.0,
+                    visible.height + grow_y * 2.0,
+                );
+                layer.bounds.intersect(&visible)?
+            }
+            _ => layer.bounds,
+        };
+        // A pixel of transparent margin softens the edges once transformed
+        let area = Rect::new(area.x - 1.0, area.y - 1.0, area.width + 2.0, area.height + 2.0);
+
+        // Drawn sharp enough to be enlarged, within what the device takes
+        let max_size = Painter::max_layer_size(gpu) as f32;
+        let scale = (scale_factor * layer.transform.max_scale().clamp(1.0, 4.0))
+            .min(max_size / area.width)
+            .min(max_size / area.height);
+        let width = (area.width * scale).ceil() as u32;
+        let height = (area.height * scale).ceil() as u32;
+
+        let mut rects = Vec::new();
+        let mut texts = Vec::new();
+        let mut images = Vec::new();
+        let mut interleaved = Vec::new();
+        Self::collect_display_commands(
+            &layer.commands,
+            (-area.x, -area.y),
+            &mut rects,
+            &mut texts,
+            &mut images,
+            &mut interleaved,
+        );
+
+        let mut encoder = gpu.device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Layer Encoder"),
+        });
+        let texture = painter.create_layer(gpu, &mut encoder, width, height);
+        let size = (width as f32 / scale, height as f32 / scale);
+        Self::draw_frame(
+            gpu,
+            painter,
+            text_renderer,
+            &mut encoder,
+            &texture.view,
+            &rects,
+            &texts,
+            &images,
+            &interleaved,
+            &[],
+            &[],
+            image_cache,
+            size.0,
+            size.1,
+            scale,
+            0.0,
+        );
+        let texture = painter.apply_filters(gpu, &mut encoder, texture, &layer.effects.filter, scale);
+        gpu.queue.submit(std::iter::once(encoder.finish()));
+
+        let (x, y) = layer.transform.as_translation().unwrap_or_default();
+        let backdrop = &layer.backdrop;
+        Some(RenderedLayer {
+            layer: texture,
+            transform: layer.transform.multiply(&Transform::translate(area.x, area.y, 0.0)),
+            size,
+            effects: layer.effects.clone(),
+            backdrop: Rect::new(backdrop.x + x, backdrop.y + y, backdrop.width, backdrop.height),
+        })
+    }
+
+    /// Sorts commands by how they're drawn, moved by `origin`
     fn collect_display_commands(
         commands: &[DisplayCommand],
-        y_offset: f32,
-        rects: &mut Vec<(Rect, Color)>,
-        texts: &mut Vec<(String, f32, f32, Color, f32)>,
+        origin: (f32, f32),
+        rects: &mut Vec<RectPaint>,
+        texts: &mut Vec<TextRun>,
         images: &mut Vec<(Rect, usize, f32)>,
+        interleaved: &mut Vec<(usize, Interleaved)>,
     ) {
-        for cmd in commands {
+        let (x_offset, y_offset) = origin;
+        let offset = |rect: &Rect| Rect::new(rect.x + x_offset, rect.y + y_offset, rect.width, rect.height);
+        let mut i = 0;
+        while i < commands.len() {
+            let cmd = &commands[i];
+            i += 1;
             match cmd {
-                DisplayCommand::SolidRect { rect, color, opacity, .. } => {
+                DisplayCommand::SolidRect { rect, color, radii, opacity } => {
                     // Apply opacity to the color
                     let mut c = *color;
                     c.a *= opacity;
-                    rects.push((
-                        Rect::new(rect.x, rect.y + y_offset, rect.width, rect.height),
-                        c,
-                    ));
+                    rects.push(RectPaint::rounded(offset(rect), c, *radii));
+                }
+                DisplayCommand::Text { run, opacity } => {
+                    let mut run = run.clone();
+                    run.x += x_offset;
+                    run.y += y_offset;
+                    run.color.a *= opacity;
+                    texts.push(run);
+                }
+                DisplayCommand::TextShadow { run, blur_radius, opacity } => {
+                    let mut run = run.clone();
+                    run.x += x_offset;
+                    run.y += y_offset;
+                    run.color.a *= opacity;
+                    if *blur_radius > 0.0 {
+                        run.color.a *= 0.5; // Simplified blur effect, as for box shadows
+                    }
+                    texts.push(run);
                 }
-                DisplayCommand::Text { text, x, y, color, font_size, opacity } => {
+                DisplayCommand::TextDecoration { rect, color, style, opacity } => {
                     let mut c = *color;
                     c.a *= opacity;
-                    texts.push((text.clone(), *x, *y + y_offset, c, *font_size));
+                    rects.push(RectPaint::line(offset(rect), c, *style));
                 }
-                DisplayCommand::Border { rect, color, width, .. } => {
-                    let r = Rect::new(rect.x, rect.y + y_offset, rect.width, rect.height);
-                    rects.push((Rect::new(r.x, r.y, r.width, *width), *color));
-                    rects.push((Rect::new(r.x, r.bottom() - *width, r.width, *width), *color));
-                    rects.push((Rect::new(r.x, r.y, *width, r.height), *color));
-                    rects.push((Rect::new(r.right() - *width, r.y, *width, r.height), *color));
+                DisplayCommand::Border { rect, sides, radii, opacity } => {
+                    let mut sides = *sides;
+                    for color in &mut sides.colors {
+                        color.a *= opacity;
+                    }
+                    if sides.is_visible() {
+                        rects.push(RectPaint::border(offset(rect), sides, *radii));
+                    }
                 }
                 DisplayCommand::Image { rect, texture_id, opacity } => {
-                    images.push((
-                        Rect::new(rect.x, rect.y + y_offset, rect.width, rect.height),
-                        *texture_id,
-                        *opacity,
-                    ));
+                    images.push((offset(rect), *texture_id, *opacity));
                 }
-                DisplayCommand::BoxShadow { rect, color, offset_x, offset_y, blur_radius, spread_radius, .. } => {
+                DisplayCommand::BoxShadow { rect, color, offset_x, offset_y, blur_radius, spread_radius, radii } => {
                     // Render shadow as a slightly larger rect behind the element
                     let shadow_rect = Rect::new(
-                        rect.x + offset_x - spread_radius,
+                        rect.x + x_offset + offset_x - spread_radius,
                         rect.y + y_offset + offset_y - spread_radius,
                         rect.width + spread_radius * 2.0,
                         rect.height + spread_radius * 2.0,
@@ -646,9 +975,123 @@ impl Browser {
                     if *blur_radius > 0.0 {
                         c.a *= 0.5; // Simplified blur effect
                     }
-                    rects.push((shadow_rect, c));
+                    rects.push(RectPaint::rounded(shadow_rect, c, radii.grow(*spread_radius)));
                 }
+                DisplayCommand::BackgroundImage { clip, tiling, texture_id, opacity } => {
+                    let tiling = Tiling { tile: offset(&tiling.tile), ..*tiling };
+                    interleaved.push((
+                        rects.len(),
+                        Interleaved::Background(DisplayCommand::BackgroundImage {
+                            clip: offset(clip),
+                            tiling,
+                            texture_id: *texture_id,
+                            opacity: *opacity,
+                        }),
+                    ));
+                }
+                DisplayCommand::Gradient { clip, tiling, gradient, opacity } => {
+                    let tiling = Tiling { tile: offset(&tiling.tile), ..*tiling };
+                    interleaved.push((
+                        rects.len(),
+                        Interleaved::Background(DisplayCommand::Gradient {
+                            clip: offset(clip),
+                            tiling,
+                            gradient: gradient.clone(),
+                            opacity: *opacity,
+                        }),
+                    ));
+                }
+                DisplayCommand::PushTransform { transform, bounds } => {
+                    let end = i + Self::group_len(&commands[i..]);
+                    let inner = &commands[i..end];
+                    i = end + 1;
+                    if !transform.is_invertible() {
+                        continue;
+                    }
+                    match transform.as_translation() {
+                        // Moved, but otherwise drawn as it is
+                        Some((dx, dy)) => Self::collect_display_commands(
+                            inner,
+                            (x_offset + dx, y_offset + dy),
+                            rects,
+                            texts,
+                            images,
+                            interleaved,
+                        ),
+                        None => interleaved.push((
+                            rects.len(),
+                            Interleaved::Layer(LayerGroup {
+                                transform: Transform::translate(x_offset, y_offset, 0.0).multiply(transform),
+                                bounds: *bounds,
+                                commands: inner.to_vec(),
+                                effects: Effects::default(),
+                                backdrop: *bounds,
+                            }),
+                        )),
+                    }
+                }
+                DisplayCommand::PushEffects { effects, bounds, backdrop } => {
+                    let end = i + Self::group_len(&commands[i..]);
+                    let inner = &commands[i..end];
+                    i = end + 1;
+                    if effects.opacity <= 0.0 {
+                        continue;
+                    }
+                    // A single command fades the same on its own, with
+                    // nothing else of the group under it
+                    if let ([command], true) = (inner, effects.is_opacity_only()) {
+                        if let Some(faded) = Self::faded(command, effects.opacity) {
+                            Self::collect_display_commands(&[faded], origin, rects, texts, images, interleaved);
+                            continue;
+                        }
+                    }
+                    interleaved.push((
+                        rects.len(),
+                        Interleaved::Layer(LayerGroup {
+                            transform: Transform::translate(x_offset, y_offset, 0.0),
+                            bounds: *bounds,
+                            commands: inner.to_vec(),
+                            effects: effects.clone(),
+                            backdrop: *backdrop,
+                        }),
+                    ));
+                }
+                // Only unmatched ones are left, as matched ones are skipped
+                DisplayCommand::PopTransform | DisplayCommand::PopEffects => {}
+            }
+        }
+    }
+
+    /// `command` faded by `opacity`, if it's one that draws by itself
+    fn faded(command: &DisplayCommand, opacity: f32) -> Option<DisplayCommand> {
+        let mut command = command.clone();
+        match &mut command {
+            DisplayCommand::SolidRect { opacity: own, .. }
+            | DisplayCommand::Text { opacity: own, .. }
+            | DisplayCommand::TextShadow { opacity: own, .. }
+            | DisplayCommand::TextDecoration { opacity: own, .. }
+            | DisplayCommand::Border { opacity: own, .. }
+            | DisplayCommand::Image { opacity: own, .. }
+            | DisplayCommand::BackgroundImage { opacity: own, .. }
+            | DisplayCommand::Gradient { opacity: own, .. } => *own *= opacity,
+            DisplayCommand::BoxShadow { color, .. } => color.a *= opacity,
+            _ => return None,
+        }
+        Some(command)
+    }
+
+    /// The number of commands a `PushTransform` or `PushEffects` applies
+    /// to, before its matching pop; all of them if it has none
+    fn group_len(commands: &[DisplayCommand]) -> usize {
+        let mut depth = 0;
+        for (i, command) in commands.iter().enumerate() {
+            match command {
+                DisplayCommand::PushTransform { .. } | DisplayCommand::PushEffects { .. } => depth += 1,
+                DisplayCommand::PopTransform | DisplayCommand::PopEffects if depth == 0 => return i,
+                DisplayCommand::PopTransform | DisplayCommand::PopEffects => depth -= 1,
+                _ => {}
             }
         }
+        commands.len()
     }
 }
@@ -54,6 +54,11 @@ impl History {
         }
     }
 
+    /// Whether `url` was visited in this session
+    pub fn contains(&self, url: &str) -> bool {
+        self.entries.iter().any(|entry| entry == url)
+    }
+
     pub fn len(&self) -> usize {
         self.entries.len()
     }
@@ -1,7 +1,36 @@
+use std::path::PathBuf;
+
+/// The user stylesheet's file name in the profile directory
+pub const USER_STYLESHEET_FILE: &str = "user.css";
+
 #[derive(Debug, Clone)]
 pub struct BrowserSettings {
     pub js_enabled: bool,
     pub css_enabled: bool,
+    /// Where cookies and the user stylesheet are kept; `None` keeps
+    /// everything in memory
+    pub profile_dir: Option<PathBuf>,
+    /// Report `prefers-color-scheme: dark` to pages
+    pub dark_theme: bool,
+    /// Replace the author's colors with a high-contrast palette
+    pub forced_colors: bool,
+}
+
+impl BrowserSettings {
+    /// `$XDG_DATA_HOME/rust-browser`, falling back to `~/.local/share/rust-browser`
+    pub fn default_profile_dir() -> Option<PathBuf> {
+        let data_home = std::env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+        Some(data_home.join("rust-browser"))
+    }
+
+    /// The user stylesheet from the profile directory, if there is one
+    pub fn user_stylesheet(&self) -> Option<String> {
+        let path = self.profile_dir.as_ref()?.join(USER_STYLESHEET_FILE);
+        std::fs::read_to_string(path).ok()
+    }
 }
 
 impl Default for BrowserSettings {
@@ -9,6 +38,11 @@ impl Default for BrowserSettings {
         Self {
             js_enabled: true,
             css_enabled: true,
+            // Persisting is opt-in, so tests and embedders never touch the
+            // user's real profile
+            profile_dir: None,
+            dark_theme: false,
+            forced_colors: false,
         }
     }
 }
@@ -1,15 +1,22 @@
 use super::history::History;
 use super::settings::BrowserSettings;
-use crate::css::{parse_css, StyleComputer, Stylesheet};
-use crate::dom::{parse_html, Document, NodeId};
+use crate::css::animation::Invalidation;
+use crate::css::media::ColorScheme;
+use crate::css::{parse_css, FontFace, FontSource, MediaEnvironment, MediaQueryList, StyleComputer, Stylesheet};
+use crate::dom::node::ElementData;
+use crate::dom::{parse_html, Document, ElementState, MutationKind, MutationRecord, NodeId};
+use crate::js::dom_bindings::DomBindings;
 use crate::js::{Interpreter, Lexer, Parser};
 use crate::layout::LayoutTree;
-use crate::net::{CancelToken, Loader};
+use crate::net::{CancelToken, CookieJar, Loader};
 use crate::render::painter::{Color, DisplayList, Rect};
 use crate::render::text::TextRenderer;
-use crate::render::{ImageCache, ImageSize, decode_image, decode_data_url};
+use crate::render::font::{decode_font, is_supported_format};
+use crate::render::{ImageCache, ImageSize, decode_image, decode_data_url, data_url_bytes};
 use crate::render::gpu::GpuContext;
+use std::cell::RefCell;
 use std::rc::Rc;
+use std::sync::Arc;
 use std::time::Instant;
 use url::Url;
 
@@ -55,13 +62,23 @@ pub struct Tab {
     pub loading_progress: Option<LoadingProgress>,
     pub cancel_token: Option<CancelToken>,
     pub image_cache: ImageCache,
+    /// Shared with the loaders; backs `document.cookie`
+    pub cookie_jar: Arc<CookieJar>,
     /// URLs of images that need to be loaded
     pending_images: Vec<String>,
+    /// `@font-face` rules whose fonts have not been tried yet
+    pending_fonts: Vec<FontFace>,
 }
 
 impl Tab {
-    pub fn new(id: usize, viewport_width: f32, viewport_height: f32, settings: BrowserSettings) -> Self {
-        Self {
+    pub fn new(
+        id: usize,
+        viewport_width: f32,
+        viewport_height: f32,
+        settings: BrowserSettings,
+        cookie_jar: Arc<CookieJar>,
+    ) -> Self {
+        let mut tab = Self {
             id,
             title: String::from("New Tab"),
             url: None,
@@ -76,7 +93,43 @@ impl Tab {
             loading_progress: None,
             cancel_token: None,
             image_cache: ImageCache::new(),
+            cookie_jar,
             pending_images: Vec::new(),
+            pending_fonts: Vec::new(),
+        };
+        let media = tab.preferred_media();
+        tab.style_computer.set_media_environment(media);
+        tab
+    }
+
+    /// The media environment with the color scheme and forced colors the
+    /// settings ask for
+    fn preferred_media(&self) -> MediaEnvironment {
+        MediaEnvironment {
+            color_scheme: if self.settings.dark_theme { ColorScheme::Dark } else { ColorScheme::Light },
+            forced_colors: self.settings.forced_colors,
+            ..self.style_computer.media_environment().clone()
+        }
+    }
+
+    /// Applies changed color settings, restyling the page. Forced colors
+    /// change computed colors even where no `@media` rule depends on them.
+    pub fn update_color_preferences(&mut self, text_renderer: &mut TextRenderer) {
+        let media = self.preferred_media();
+        if media == *self.style_computer.media_environment() {
+            return;
+        }
+        self.style_computer.set_media_environment(media);
+        self.style_computer.compute_styles(&self.document);
+        self.layout_tree.build(&self.document, &self.style_computer, text_renderer);
+    }
+
+    /// Starts the page's sheets with the built-in defaults and the user
+    /// stylesheet from the profile, which cascade as their own origins
+    fn push_default_stylesheets(&mut self) {
+        self.stylesheets.push(Rc::new(Stylesheet::user_agent()));
+        if let Some(css) = self.settings.user_stylesheet() {
+            self.stylesheets.push(Rc::new(Stylesheet::user(&css)));
         }
     }
 
@@ -165,7 +218,7 @@ impl Tab {
             }
         };
 
-        match loader.fetch(&url) {
+        match loader.fetch(&url, None) {
             Ok(html) => {
                 self.url = Some(url.clone());
                 self.history.push(url.to_string());
@@ -188,14 +241,14 @@ impl Tab {
         let parse_start = Instant::now();
         self.document = parse_html(html);
         let parse_time = parse_start.elapsed();
+        self.mark_visited_links();
+        self.style_computer.timeline_mut().clear();
 
         self.stylesheets.clear();
-
-        let default_css = include_str!("../../assets/default.css");
-        self.stylesheets.push(Rc::new(parse_css(default_css)));
+        self.push_default_stylesheets();
 
         if self.settings.css_enabled {
-            self.extract_styles();
+            self.extract_styles(Some(loader));
             self.load_external_stylesheets(loader);
         }
 
@@ -203,6 +256,7 @@ impl Tab {
         for stylesheet in &self.stylesheets {
             self.style_computer.add_stylesheet(Rc::clone(stylesheet));
         }
+        self.collect_pending_fonts();
 
         let style_start = Instant::now();
         self.style_computer.compute_styles(&self.document);
@@ -216,7 +270,7 @@ impl Tab {
         self.collect_pending_images();
 
         if self.settings.js_enabled {
-            self.execute_scripts();
+            self.execute_scripts(text_renderer);
         }
 
         let total_time = total_start.elapsed();
@@ -237,14 +291,14 @@ impl Tab {
         let parse_start = Instant::now();
         self.document = parse_html(html);
         let parse_time = parse_start.elapsed();
+        self.mark_visited_links();
+        self.style_computer.timeline_mut().clear();
 
         self.stylesheets.clear();
-
-        let default_css = include_str!("../../assets/default.css");
-        self.stylesheets.push(Rc::new(parse_css(default_css)));
+        self.push_default_stylesheets();
 
         if self.settings.css_enabled {
-            self.extract_styles();
+            self.extract_styles(None);
             // Skip external stylesheets for now in async mode
         }
 
@@ -252,6 +306,7 @@ impl Tab {
         for stylesheet in &self.stylesheets {
             self.style_computer.add_stylesheet(Rc::clone(stylesheet));
         }
+        self.collect_pending_fonts();
 
         let style_start = Instant::now();
         self.style_computer.compute_styles(&self.document);
@@ -262,7 +317,7 @@ impl Tab {
         let layout_time = layout_start.elapsed();
 
         if self.settings.js_enabled {
-            self.execute_scripts();
+            self.execute_scripts(text_renderer);
         }
 
         let total_time = total_start.elapsed();
@@ -276,16 +331,39 @@ impl Tab {
         );
     }
 
-    fn extract_styles(&mut self) {
+    /// Parses the `<style>` elements; their `@import`s are loaded through
+    /// `loader`, relative to the page, when there is one
+    fn extract_styles(&mut self, loader: Option<&Loader>) {
         let style_elements = self.document.get_elements_by_tag_name("style");
         for node_id in style_elements {
             let css_text = self.document.get_text_content(node_id);
             if !css_text.is_empty() {
-                self.stylesheets.push(Rc::new(parse_css(&css_text)));
+                let stylesheet = self.parse_stylesheet(node_id, &css_text, self.url.as_ref().zip(loader));
+                self.stylesheets.push(Rc::new(stylesheet));
             }
         }
     }
 
+    /// Parses the CSS of a `<style>` or `<link>` element, honoring its
+    /// `media` attribute, and loads its `@import`s given where it came from
+    /// and a loader
+    fn parse_stylesheet(&self, node_id: NodeId, css_text: &str, source: Option<(&Url, &Loader)>) -> Stylesheet {
+        let mut stylesheet = parse_css(css_text);
+        if let Some((base, loader)) = source {
+            let site = self.url.as_ref();
+            stylesheet.load_imports(base, &mut |url| loader.fetch(url, site).ok());
+        }
+        let media = self
+            .document
+            .get_node(node_id)
+            .and_then(|node| node.as_element())
+            .and_then(|elem| elem.get_attribute("media"));
+        if let Some(media) = media {
+            stylesheet.restrict_to_media(MediaQueryList::parse(media));
+        }
+        stylesheet
+    }
+
     fn load_external_stylesheets(&mut self, loader: &Loader) {
         let link_elements = self.document.get_elements_by_tag_name("link");
         for node_id in link_elements {
@@ -309,8 +387,10 @@ impl Tab {
                             };
 
                             if let Some(url) = css_url {
-                                if let Ok(css_text) = loader.fetch(&url) {
-                                    self.stylesheets.push(Rc::new(parse_css(&css_text)));
+                                if let Ok(css_text) = loader.fetch(&url, self.url.as_ref()) {
+                                    let mut stylesheet = self.parse_stylesheet(node_id, &css_text, Some((&url, loader)));
+                                    stylesheet.resolve_urls(&url);
+                                    self.stylesheets.push(Rc::new(stylesheet));
                                 }
                             }
                         }
@@ -320,13 +400,23 @@ impl Tab {
         }
     }
 
-    fn execute_scripts(&mut self) {
+    fn execute_scripts(&mut self, text_renderer: &mut TextRenderer) {
         let script_elements = self.document.get_elements_by_tag_name("script");
+
+        // Scripts share the document with the DOM bindings while they run
+        let subscription = self.document.subscribe_mutations();
+        let document = Rc::new(RefCell::new(std::mem::take(&mut self.document)));
+        let mut bindings = DomBindings::new(Rc::clone(&document));
+        if let Some(url) = &self.url {
+            bindings = bindings.with_cookies(Arc::clone(&self.cookie_jar), url.clone());
+        }
+        let bindings = Rc::new(bindings);
         let mut interpreter = Interpreter::new();
+        bindings.setup_globals(&mut interpreter);
 
         for node_id in script_elements {
             // Skip external scripts (src attribute) for now
-            if let Some(node) = self.document.get_node(node_id) {
+            if let Some(node) = document.borrow().get_node(node_id) {
                 if let Some(elem) = node.as_element() {
                     // Skip scripts with src attribute
                     if elem.get_attribute("src").is_some() {
@@ -335,7 +425,7 @@ impl Tab {
                 }
             }
 
-            let script_content = self.document.get_text_content(node_id);
+            let script_content = document.borrow().get_text_content(node_id);
             if !script_content.trim().is_empty() {
                 let mut lexer = Lexer::new(&script_content);
                 let tokens = lexer.tokenize();
@@ -344,18 +434,200 @@ impl Tab {
                 interpreter.execute(&statements);
             }
         }
+
+        drop(interpreter);
+        drop(bindings);
+        self.document = std::mem::take(&mut *document.borrow_mut());
+
+        let records = self.document.take_mutations(subscription);
+        self.document.unsubscribe_mutations(subscription);
+        self.apply_mutations(&records, text_renderer);
+    }
+
+    /// Brings styles and layout up to date after DOM mutations, restyling
+    /// the elements the records may affect and rebuilding boxes only for
+    /// the subtrees they touch
+    pub fn apply_mutations(&mut self, records: &[MutationRecord], text_renderer: &mut TextRenderer) {
+        if records.is_empty() {
+            return;
+        }
+
+        let mut layout_roots = Vec::new();
+        for record in records {
+            let parent = self.document.parent(record.target).unwrap_or(record.target);
+            match record.kind {
+                MutationKind::ChildList => {
+                    for &removed in &record.removed_nodes {
+                        self.style_computer.forget_subtree(&self.document, removed);
+                    }
+                    self.style_computer
+                        .invalidate_children(&self.document, record.target, &record.added_nodes);
+                    layout_roots.push(record.target);
+                }
+                MutationKind::Attributes => {
+                    if let Some(name) = &record.attribute_name {
+                        self.style_computer.invalidate_attribute(
+                            &self.document,
+                            record.target,
+                            name,
+                            record.old_value.as_deref(),
+                        );
+                    }
+                    // Attributes such as src change the boxes themselves
+                    layout_roots.push(parent);
+                }
+                MutationKind::CharacterData => layout_roots.push(parent),
+            }
+        }
+
+        let (restyled, rebuilt) = self.update_styles(layout_roots, text_renderer);
+        log::debug!(
+            "Applied {} mutations: restyled {} elements, rebuilt {} subtrees",
+            records.len(),
+            restyled,
+            rebuilt
+        );
+    }
+
+    /// Recomputes the styles marked out of date, then rebuilds boxes for
+    /// the subtrees under `layout_roots` and around the elements whose
+    /// style changed. Returns how many elements were restyled and how many
+    /// subtrees rebuilt.
+    fn update_styles(&mut self, mut layout_roots: Vec<NodeId>, text_renderer: &mut TextRenderer) -> (usize, usize) {
+        let restyled = self.style_computer.recalc_styles(&self.document);
+        // A display change can alter how the parent wraps its children
+        layout_roots.extend(restyled.iter().map(|&id| self.document.parent(id).unwrap_or(id)));
+        let rebuilt = self.rebuild_layout(layout_roots, text_renderer);
+        (restyled.len(), rebuilt)
+    }
+
+    /// Rebuilds boxes for the subtrees under `layout_roots`. Returns how
+    /// many subtrees were rebuilt.
+    fn rebuild_layout(&mut self, layout_roots: Vec<NodeId>, text_renderer: &mut TextRenderer) -> usize {
+        // Only what is rendered needs new boxes; nodes in <head> have none
+        let body = self.document.get_body();
+        let layout_roots: Vec<NodeId> = outermost_roots(&self.document, layout_roots)
+            .into_iter()
+            .filter(|&id| body.is_some_and(|body| self.document.contains(body, id) || self.document.contains(id, body)))
+            .collect();
+        if !layout_roots.is_empty() {
+            self.layout_tree
+                .rebuild_subtrees(&self.document, &self.style_computer, &layout_roots, text_renderer);
+            self.collect_pending_images();
+        }
+        layout_roots.len()
+    }
+
+    /// Moves the pointer over `node` (from `hit_test`), putting it and its
+    /// ancestors in the hover state. Returns true when styles changed.
+    pub fn set_hovered(&mut self, node: Option<NodeId>, text_renderer: &mut TextRenderer) -> bool {
+        let chain = self.element_chain(node);
+        self.set_state_chain(ElementState::HOVER, &chain, text_renderer)
+    }
+
+    /// Presses or releases (`None`) the pointer on `node`, putting it and its
+    /// ancestors in the active state. Returns true when styles changed.
+    pub fn set_active(&mut self, node: Option<NodeId>, text_renderer: &mut TextRenderer) -> bool {
+        let chain = self.element_chain(node);
+        self.set_state_chain(ElementState::ACTIVE, &chain, text_renderer)
+    }
+
+    /// Focuses the closest focusable element around a clicked `node`, or
+    /// removes focus if there is none. Returns true when styles changed.
+    pub fn focus_at(&mut self, node: Option<NodeId>, text_renderer: &mut TextRenderer) -> bool {
+        let focused = self
+            .element_chain(node)
+            .into_iter()
+            .find(|&id| self.document.get_node(id).and_then(|n| n.as_element()).is_some_and(is_focusable));
+        // Pointer focus is only drawn for controls that take text input
+        let visible = focused
+            .and_then(|id| self.document.get_node(id))
+            .and_then(|n| n.as_element())
+            .is_some_and(is_text_entry);
+
+        let focus: Vec<NodeId> = focused.into_iter().collect();
+        let focus_visible: Vec<NodeId> = focused.filter(|_| visible).into_iter().collect();
+        let focus_within = self.element_chain(focused);
+
+        let mut changed = self.set_state_chain(ElementState::FOCUS, &focus, text_renderer);
+        changed |= self.set_state_chain(ElementState::FOCUS_VISIBLE, &focus_visible, text_renderer);
+        changed |= self.set_state_chain(ElementState::FOCUS_WITHIN, &focus_within, text_renderer);
+        changed
+    }
+
+    /// The element `node` is or is inside of, followed by its ancestors
+    fn element_chain(&self, node: Option<NodeId>) -> Vec<NodeId> {
+        let Some(node) = node else {
+            return Vec::new();
+        };
+        std::iter::once(node)
+            .chain(self.document.ancestors(node))
+            .filter(|&id| self.document.get_node(id).is_some_and(|n| n.is_element()))
+            .collect()
+    }
+
+    /// Gives `flag` to exactly the elements in `nodes`, restyling what
+    /// selectors depending on it match differently
+    fn set_state_chain(&mut self, flag: ElementState, nodes: &[NodeId], text_renderer: &mut TextRenderer) -> bool {
+        let mut changed = Vec::new();
+        for id in self.document.elements_with_state(flag) {
+            if !nodes.contains(&id) && self.document.set_element_state(id, flag, false) {
+                changed.push(id);
+            }
+        }
+        for &id in nodes {
+            if self.document.set_element_state(id, flag, true) {
+                changed.push(id);
+            }
+        }
+
+        for id in changed {
+            self.style_computer.invalidate_state(&self.document, id, flag);
+        }
+        if !self.style_computer.needs_recalc() {
+            return false;
+        }
+        let (restyled, _) = self.update_styles(Vec::new(), text_renderer);
+        restyled > 0
+    }
+
+    /// Flags links to pages in this tab's history as visited, before styles
+    /// are computed
+    fn mark_visited_links(&mut self) {
+        let links: Vec<NodeId> = ["a", "area"]
+            .iter()
+            .flat_map(|tag| self.document.get_elements_by_tag_name(tag))
+            .collect();
+        for id in links {
+            let href = self
+                .document
+                .get_node(id)
+                .and_then(|n| n.as_element())
+                .and_then(|elem| elem.get_attribute("href"));
+            let target = match (&self.url, href) {
+                (Some(base), Some(href)) => base.join(href).ok(),
+                (None, Some(href)) => Url::parse(href).ok(),
+                _ => None,
+            };
+            if target.is_some_and(|url| self.history.contains(url.as_str())) {
+                self.document.set_element_state(id, ElementState::VISITED, true);
+            }
+        }
     }
 
     pub fn resize(&mut self, width: f32, height: f32, text_renderer: &mut TextRenderer) {
         let start = Instant::now();
 
-        self.style_computer.set_viewport(width, height);
+        let media_changed = self.style_computer.set_viewport(width, height);
         self.layout_tree.viewport_width = width;
         self.layout_tree.viewport_height = height;
 
-        // Skip style recomputation on resize - styles are viewport-independent
-        // (vh/vw units are applied during layout, not during style computation)
-        // This significantly improves resize performance for large documents
+        // Styles only need recomputing when a different set of @media rules
+        // applies (vh/vw units are applied during layout, not during style
+        // computation). Skipping it keeps resizing large documents fast.
+        if media_changed {
+            self.style_computer.compute_styles(&self.document);
+        }
         self.layout_tree.build(&self.document, &self.style_computer, text_renderer);
 
         log::debug!(
@@ -391,6 +663,55 @@ impl Tab {
         self.layout_tree.is_scroll_animating()
     }
 
+    /// Moves the animation clock on by `dt` seconds and brings animated
+    /// styles up to date. Returns true when anything changed.
+    pub fn update_animations(&mut self, dt: f32, text_renderer: &mut TextRenderer) -> bool {
+        self.style_computer.timeline_mut().advance(dt);
+        self.apply_animation_updates(text_renderer)
+    }
+
+    /// Stops the animation clock at `time` seconds, so what is drawn no
+    /// longer depends on when frames happen. Returns true when anything
+    /// changed.
+    pub fn seek_animations(&mut self, time: f32, text_renderer: &mut TextRenderer) -> bool {
+        let timeline = self.style_computer.timeline_mut();
+        timeline.set_paused(true);
+        timeline.seek(time);
+        self.apply_animation_updates(text_renderer)
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.style_computer.timeline().is_animating()
+    }
+
+    /// Restyles the elements whose animated values changed, then lays out
+    /// again only if one of them changed geometry; otherwise the boxes just
+    /// take the new styles for painting
+    fn apply_animation_updates(&mut self, text_renderer: &mut TextRenderer) -> bool {
+        let updates = self.style_computer.timeline_mut().take_updates();
+        if updates.is_empty() {
+            return false;
+        }
+
+        let needing = |level: Invalidation| -> Vec<NodeId> {
+            updates
+                .iter()
+                .filter(|(_, invalidation)| *invalidation == level)
+                .map(|(id, _)| *id)
+                .collect()
+        };
+        let (repaint, relayout) = (needing(Invalidation::Repaint), needing(Invalidation::Relayout));
+        for (id, _) in &updates {
+            self.style_computer.invalidate_style(&self.document, *id);
+        }
+        self.style_computer.recalc_styles(&self.document);
+        self.rebuild_layout(relayout, text_renderer);
+        if !repaint.is_empty() {
+            self.layout_tree.restyle_boxes(&self.style_computer, &repaint);
+        }
+        true
+    }
+
     pub fn hit_test(&self, x: f32, y: f32) -> Option<NodeId> {
         self.layout_tree.hit_test(x, y)
     }
@@ -417,7 +738,7 @@ impl Tab {
     pub fn go_back(&mut self, loader: &Loader, text_renderer: &mut TextRenderer) -> bool {
         if let Some(url) = self.history.go_back().map(|s| s.to_string()) {
             if let Ok(parsed) = Url::parse(&url) {
-                if let Ok(html) = loader.fetch(&parsed) {
+                if let Ok(html) = loader.fetch(&parsed, None) {
                     self.url = Some(parsed);
                     self.load_html(&html, loader, text_renderer);
                     return true;
@@ -430,7 +751,7 @@ impl Tab {
     pub fn go_forward(&mut self, loader: &Loader, text_renderer: &mut TextRenderer) -> bool {
         if let Some(url) = self.history.go_forward().map(|s| s.to_string()) {
             if let Ok(parsed) = Url::parse(&url) {
-                if let Ok(html) = loader.fetch(&parsed) {
+                if let Ok(html) = loader.fetch(&parsed, None) {
                     self.url = Some(parsed);
                     self.load_html(&html, loader, text_renderer);
                     return true;
@@ -558,7 +879,7 @@ impl Tab {
                 // Fetch from network
                 match Url::parse(&url) {
                     Ok(parsed_url) => {
-                        match loader.fetch_bytes(&parsed_url) {
+                        match loader.fetch_bytes(&parsed_url, self.url.as_ref()) {
                             Ok(bytes) => decode_image(&bytes),
                             Err(e) => Err(format!("Failed to fetch image: {}", e)),
                         }
@@ -594,15 +915,108 @@ impl Tab {
                 // Re-resolve URLs to update texture IDs after rebuild
                 self.layout_tree.resolve_image_urls(self.url.as_ref());
             }
-            // Re-apply texture IDs from cache
-            for url in self.image_cache.urls() {
-                if let Some(texture_id) = self.image_cache.get_texture_id(url) {
-                    if let Some(size) = self.image_cache.get_size(url) {
-                        self.layout_tree.update_image_texture(url, texture_id, size);
+            self.apply_image_textures();
+        }
+    }
+
+    /// Re-apply texture IDs from cache
+    fn apply_image_textures(&mut self) {
+        for url in self.image_cache.urls() {
+            if let Some(texture_id) = self.image_cache.get_texture_id(url) {
+                if let Some(size) = self.image_cache.get_size(url) {
+                    self.layout_tree.update_image_texture(url, texture_id, size);
+                }
+            }
+        }
+    }
+
+    /// Collect the `@font-face` rules of the page's stylesheets. Sources in
+    /// `<style>` elements resolve against the page URL; those of external
+    /// sheets were resolved against the sheet's when it loaded.
+    fn collect_pending_fonts(&mut self) {
+        self.pending_fonts = self
+            .stylesheets
+            .iter()
+            .flat_map(|stylesheet| stylesheet.font_faces.iter().cloned())
+            .collect();
+        if let Some(base) = &self.url {
+            for face in &mut self.pending_fonts {
+                face.resolve_urls(base);
+            }
+        }
+    }
+
+    /// Check if there are `@font-face` fonts to load
+    pub fn has_pending_fonts(&self) -> bool {
+        !self.pending_fonts.is_empty()
+    }
+
+    /// Load the fonts of pending `@font-face` rules, each from the first of
+    /// its sources that works, then lay the page out again in them. Until
+    /// then text shows in the next family of its `font-family`.
+    pub fn load_fonts_sync(&mut self, loader: &Loader, text_renderer: &mut TextRenderer) {
+        let pending = std::mem::take(&mut self.pending_fonts);
+        let mut loaded = false;
+
+        for face in pending {
+            let descriptors = &face.descriptors;
+            for source in &face.sources {
+                let result = match source {
+                    FontSource::Local(name) => {
+                        if text_renderer.has_web_font(descriptors, name) {
+                            break;
+                        }
+                        if text_renderer.register_local_font(name, descriptors) {
+                            Ok(())
+                        } else {
+                            Err(format!("{} is not installed", name))
+                        }
+                    }
+                    FontSource::Url { url, format } => {
+                        if format.as_deref().is_some_and(|format| !is_supported_format(format)) {
+                            continue;
+                        }
+                        if text_renderer.has_web_font(descriptors, url) {
+                            break;
+                        }
+                        let bytes = if url.starts_with("data:") {
+                            data_url_bytes(url)
+                        } else {
+                            Url::parse(url)
+                                .map_err(|e| format!("Invalid URL: {}", e))
+                                .and_then(|parsed_url| {
+                                    loader
+                                        .fetch_bytes(&parsed_url, self.url.as_ref())
+                                        .map_err(|e| format!("Failed to fetch font: {}", e))
+                                })
+                        };
+                        bytes.and_then(|bytes| decode_font(&bytes)).and_then(|data| {
+                            if text_renderer.register_font(url, data, descriptors) {
+                                Ok(())
+                            } else {
+                                Err("No faces in font".to_string())
+                            }
+                        })
+                    }
+                };
+                match result {
+                    Ok(()) => {
+                        log::info!("Loaded font for {}", descriptors.family);
+                        loaded = true;
+                        break;
                     }
+                    Err(e) => log::warn!("Failed to load font for {}: {}", descriptors.family, e),
                 }
             }
         }
+
+        // Swap the loaded fonts in
+        if loaded {
+            self.layout_tree.build(&self.document, &self.style_computer, text_renderer);
+            // Re-resolve URLs to update texture IDs after rebuild
+            self.layout_tree.resolve_image_urls(self.url.as_ref());
+            self.apply_image_textures();
+        }
     }
 
     /// Check if there are pending images to load
@@ -615,3 +1029,43 @@ impl Tab {
         self.pending_images.len()
     }
 }
+
+/// Elements that take focus when clicked
+fn is_focusable(element: &ElementData) -> bool {
+    if element.get_attribute("disabled").is_some() {
+        return false;
+    }
+    match element.tag_name.to_ascii_lowercase().as_str() {
+        "a" | "area" => element.get_attribute("href").is_some(),
+        "input" => !element.get_attribute("type").is_some_and(|t| t.eq_ignore_ascii_case("hidden")),
+        "button" | "select" | "textarea" => true,
+        _ => element.get_attribute("tabindex").is_some(),
+    }
+}
+
+/// Controls that show a focus ring even when focused by the pointer
+fn is_text_entry(element: &ElementData) -> bool {
+    match element.tag_name.to_ascii_lowercase().as_str() {
+        "textarea" => true,
+        "input" => {
+            let kind = element.get_attribute("type").unwrap_or("text").to_ascii_lowercase();
+            matches!(
+                kind.as_str(),
+                "text" | "search" | "email" | "password" | "tel" | "url" | "number" | "date" | "time"
+            )
+        }
+        _ => false,
+    }
+}
+
+/// Connected nodes from `roots` that are not inside another root
+fn outermost_roots(document: &Document, mut roots: Vec<NodeId>) -> Vec<NodeId> {
+    roots.sort_unstable();
+    roots.dedup();
+    roots.retain(|&id| document.contains(document.root, id));
+    roots
+        .iter()
+        .copied()
+        .filter(|&id| !roots.iter().any(|&other| other != id && document.contains(other, id)))
+        .collect()
+}
@@ -0,0 +1,1099 @@
+//! Transitions and `@keyframes` animations: the `transition-*` and
+//! `animation-*` properties, timing functions, and blending the computed
+//! values of animatable properties between two styles.
+
+use super::computed::{ComputedStyle, CornerRadius, LengthOrPercentage};
+use super::filter::FilterFunction;
+use super::properties;
+use super::stylesheet::{Declaration, Value};
+use super::transform::TransformFunction;
+use crate::render::painter::Color;
+use std::rc::Rc;
+
+/// An easing function, mapping how far through its duration an animation
+/// is to how far its values have moved
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimingFunction {
+    Linear,
+    /// The curve from (0, 0) to (1, 1) with these two control points
+    CubicBezier(f32, f32, f32, f32),
+    /// Jumps between this many flat steps
+    Steps(u32, StepPosition),
+}
+
+/// Where the jumps of `steps()` fall
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StepPosition {
+    /// `jump-start` or `start`: at the start of each step
+    Start,
+    /// `jump-end` or `end`: at the end of each step
+    End,
+    /// `jump-none`: the first step starts at 0, the last is at 1
+    None,
+    /// `jump-both`: at both ends
+    Both,
+}
+
+impl TimingFunction {
+    pub const EASE: TimingFunction = TimingFunction::CubicBezier(0.25, 0.1, 0.25, 1.0);
+
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let function = match value {
+            Value::Keyword(keyword) => match keyword.to_ascii_lowercase().as_str() {
+                "linear" => TimingFunction::Linear,
+                "ease" => Self::EASE,
+                "ease-in" => TimingFunction::CubicBezier(0.42, 0.0, 1.0, 1.0),
+                "ease-out" => TimingFunction::CubicBezier(0.0, 0.0, 0.58, 1.0),
+                "ease-in-out" => TimingFunction::CubicBezier(0.42, 0.0, 0.58, 1.0),
+                "step-start" => TimingFunction::Steps(1, StepPosition::Start),
+                "step-end" => TimingFunction::Steps(1, StepPosition::End),
+                _ => return None,
+            },
+            Value::Function(name, args) if name == "cubic-bezier" => match args.as_slice() {
+                // The curve must move forward in time
+                [Value::Number(x1), Value::Number(y1), Value::Number(x2), Value::Number(y2)]
+                    if (0.0..=1.0).contains(x1) && (0.0..=1.0).contains(x2) =>
+                {
+                    TimingFunction::CubicBezier(*x1, *y1, *x2, *y2)
+                }
+                _ => return None,
+            },
+            Value::Function(name, args) if name == "steps" => {
+                let position = match args.get(1) {
+                    None => StepPosition::End,
+                    Some(value) => match value.as_keyword()?.to_ascii_lowercase().as_str() {
+                        "jump-start" | "start" => StepPosition::Start,
+                        "jump-end" | "end" => StepPosition::End,
+                        "jump-none" => StepPosition::None,
+                        "jump-both" => StepPosition::Both,
+                        _ => return None,
+                    },
+                };
+                let min_steps = if position == StepPosition::None { 2.0 } else { 1.0 };
+                match args.first()? {
+                    Value::Number(steps) if steps.fract() == 0.0 && *steps >= min_steps => {
+                        TimingFunction::Steps(*steps as u32, position)
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        Some(function)
+    }
+
+    /// The output progress for input progress `t`, from 0 to 1
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            TimingFunction::Linear => t,
+            TimingFunction::CubicBezier(x1, y1, x2, y2) => {
+                let u = solve_bezier(t, x1, x2);
+                bezier(u, y1, y2)
+            }
+            TimingFunction::Steps(steps, position) => {
+                let steps = steps as f32;
+                let (step, jumps) = match position {
+                    StepPosition::Start => ((t * steps).floor() + 1.0, steps),
+                    StepPosition::End => ((t * steps).floor(), steps),
+                    StepPosition::None => ((t * steps).floor(), steps - 1.0),
+                    StepPosition::Both => ((t * steps).floor() + 1.0, steps + 1.0),
+                };
+                step.min(jumps) / jumps
+            }
+        }
+    }
+}
+
+/// One coordinate of the bezier curve with control points `p1` and `p2`
+fn bezier(u: f32, p1: f32, p2: f32) -> f32 {
+    let v = 1.0 - u;
+    3.0 * v * v * u * p1 + 3.0 * v * u * u * p2 + u * u * u
+}
+
+/// The curve parameter where the x coordinate is `x`, by Newton's method,
+/// falling back to bisection where the curve is too flat
+fn solve_bezier(x: f32, x1: f32, x2: f32) -> f32 {
+    let mut u = x;
+    for _ in 0..8 {
+        let error = bezier(u, x1, x2) - x;
+        if error.abs() < 1e-5 {
+            return u;
+        }
+        let v = 1.0 - u;
+        let slope = 3.0 * v * v * x1 + 6.0 * v * u * (x2 - x1) + 3.0 * u * u * (1.0 - x2);
+        if slope.abs() < 1e-6 {
+            break;
+        }
+        u -= error / slope;
+    }
+
+    let (mut low, mut high) = (0.0, 1.0);
+    u = x;
+    for _ in 0..32 {
+        let error = bezier(u, x1, x2) - x;
+        if error.abs() < 1e-5 {
+            break;
+        }
+        if error > 0.0 {
+            high = u;
+        } else {
+            low = u;
+        }
+        u = (low + high) / 2.0;
+    }
+    u
+}
+
+/// `transition-property` items
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransitionProperty {
+    All,
+    None,
+    Name(String),
+}
+
+/// The `transition-*` properties. The lists are matched up by index with
+/// `property`, the others repeating as needed; empty lists are the
+/// initial values.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transitions {
+    pub property: Vec<TransitionProperty>,
+    /// Durations and delays, in seconds
+    pub duration: Vec<f32>,
+    pub timing_function: Vec<TimingFunction>,
+    pub delay: Vec<f32>,
+}
+
+/// How a change to a property transitions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransitionTiming {
+    pub duration: f32,
+    pub delay: f32,
+    pub timing_function: TimingFunction,
+}
+
+/// Entry `index` of a list property, which repeats as needed
+fn nth<T: Clone>(list: &[T], index: usize, initial: T) -> T {
+    if list.is_empty() {
+        initial
+    } else {
+        list[index % list.len()].clone()
+    }
+}
+
+impl Transitions {
+    /// Whether any change could transition at all
+    pub fn is_active(&self) -> bool {
+        self.duration.iter().chain(&self.delay).any(|time| *time > 0.0)
+    }
+
+    /// How changes to the longhand `property` transition; `None` when they
+    /// apply at once. The last item naming it wins.
+    pub fn for_property(&self, property: &str) -> Option<TransitionTiming> {
+        let items = if self.property.is_empty() {
+            &[TransitionProperty::All][..]
+        } else {
+            &self.property
+        };
+        let index = items.iter().rposition(|item| match item {
+            TransitionProperty::All => true,
+            TransitionProperty::None => false,
+            TransitionProperty::Name(name) => {
+                name == property || properties::expand(name).iter().any(|longhand| longhand.name == property)
+            }
+        })?;
+        let timing = TransitionTiming {
+            duration: nth(&self.duration, index, 0.0),
+            delay: nth(&self.delay, index, 0.0),
+            timing_function: nth(&self.timing_function, index, TimingFunction::EASE),
+        };
+        (timing.duration > 0.0 || timing.delay > 0.0).then_some(timing)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum AnimationDirection {
+    #[default]
+    Normal,
+    Reverse,
+    Alternate,
+    AlternateReverse,
+}
+
+/// Whether an animation's values apply before it starts and after it ends
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum FillMode {
+    #[default]
+    None,
+    Forwards,
+    Backwards,
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum PlayState {
+    #[default]
+    Running,
+    Paused,
+}
+
+impl AnimationDirection {
+    pub(super) fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword.to_ascii_lowercase().as_str() {
+            "normal" => Some(AnimationDirection::Normal),
+            "reverse" => Some(AnimationDirection::Reverse),
+            "alternate" => Some(AnimationDirection::Alternate),
+            "alternate-reverse" => Some(AnimationDirection::AlternateReverse),
+            _ => None,
+        }
+    }
+}
+
+impl FillMode {
+    pub(super) fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword.to_ascii_lowercase().as_str() {
+            "forwards" => Some(FillMode::Forwards),
+            "backwards" => Some(FillMode::Backwards),
+            "both" => Some(FillMode::Both),
+            _ => None,
+        }
+    }
+}
+
+impl PlayState {
+    pub(super) fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword.to_ascii_lowercase().as_str() {
+            "running" => Some(PlayState::Running),
+            "paused" => Some(PlayState::Paused),
+            _ => None,
+        }
+    }
+}
+
+/// The `animation-*` properties. `name` sets the number of animations;
+/// the other lists repeat to match it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Animations {
+    /// `None` for a `none` item, which runs nothing
+    pub name: Vec<Option<String>>,
+    /// Durations and delays, in seconds
+    pub duration: Vec<f32>,
+    pub timing_function: Vec<TimingFunction>,
+    pub delay: Vec<f32>,
+    /// `infinite` is `f32::INFINITY`
+    pub iteration_count: Vec<f32>,
+    pub direction: Vec<AnimationDirection>,
+    pub fill_mode: Vec<FillMode>,
+    pub play_state: Vec<PlayState>,
+}
+
+/// One named animation with its settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub name: String,
+    pub duration: f32,
+    pub timing_function: TimingFunction,
+    pub delay: f32,
+    pub iteration_count: f32,
+    pub direction: AnimationDirection,
+    pub fill_mode: FillMode,
+    pub play_state: PlayState,
+}
+
+impl Animations {
+    pub fn is_none(&self) -> bool {
+        self.name.iter().all(Option::is_none)
+    }
+
+    /// The named animations, in order
+    pub fn list(&self) -> Vec<Animation> {
+        self.name
+            .iter()
+            .enumerate()
+            .filter_map(|(i, name)| {
+                Some(Animation {
+                    name: name.clone()?,
+                    duration: nth(&self.duration, i, 0.0),
+                    timing_function: nth(&self.timing_function, i, TimingFunction::EASE),
+                    delay: nth(&self.delay, i, 0.0),
+                    iteration_count: nth(&self.iteration_count, i, 1.0),
+                    direction: nth(&self.direction, i, AnimationDirection::Normal),
+                    fill_mode: nth(&self.fill_mode, i, FillMode::None),
+                    play_state: nth(&self.play_state, i, PlayState::Running),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Animation {
+    /// How long it runs after its delay, in seconds
+    pub fn active_duration(&self) -> f32 {
+        if self.duration <= 0.0 {
+            0.0
+        } else {
+            self.duration * self.iteration_count
+        }
+    }
+
+    /// How far through its keyframes the animation is `elapsed` seconds
+    /// after it started, counting its delay, direction and fill mode;
+    /// `None` when its values don't apply
+    pub fn progress(&self, elapsed: f32) -> Option<f32> {
+        let active_time = elapsed - self.delay;
+        let active_duration = self.active_duration();
+        let (iteration, t) = if active_time < 0.0 {
+            if !matches!(self.fill_mode, FillMode::Backwards | FillMode::Both) {
+                return None;
+            }
+            (0.0, 0.0)
+        } else if active_time >= active_duration {
+            if !matches!(self.fill_mode, FillMode::Forwards | FillMode::Both) {
+                return None;
+            }
+            // Where the last iteration stopped; a whole number of them ends
+            // at the end of the last rather than the start of the next
+            let iterations = self.iteration_count;
+            if iterations > 0.0 && iterations.fract() == 0.0 {
+                (iterations - 1.0, 1.0)
+            } else {
+                (iterations.floor(), iterations.fract())
+            }
+        } else {
+            let overall = active_time / self.duration;
+            (overall.floor(), overall.fract())
+        };
+
+        let odd = iteration % 2.0 == 1.0;
+        let reversed = match self.direction {
+            AnimationDirection::Normal => false,
+            AnimationDirection::Reverse => true,
+            AnimationDirection::Alternate => odd,
+            AnimationDirection::AlternateReverse => !odd,
+        };
+        Some(if reversed { 1.0 - t } else { t })
+    }
+}
+
+/// Parses each comma-separated item of `value` with `parse`; `None` if any
+/// item is invalid
+fn parse_list<T>(value: &Value, mut parse: impl FnMut(&[Value]) -> Option<T>) -> Option<Vec<T>> {
+    value.as_comma_list().iter().map(|item| parse(item.as_slice())).collect()
+}
+
+pub fn parse_times(value: &Value) -> Option<Vec<f32>> {
+    parse_list(value, |item| match item {
+        [Value::Time(seconds)] => Some(*seconds),
+        _ => None,
+    })
+}
+
+/// Durations, which can't be negative
+pub fn parse_durations(value: &Value) -> Option<Vec<f32>> {
+    parse_times(value).filter(|times| times.iter().all(|time| *time >= 0.0))
+}
+
+pub fn parse_timing_functions(value: &Value) -> Option<Vec<TimingFunction>> {
+    parse_list(value, |item| match item {
+        [function] => TimingFunction::from_value(function),
+        _ => None,
+    })
+}
+
+pub fn parse_transition_properties(value: &Value) -> Option<Vec<TransitionProperty>> {
+    let properties = parse_list(value, |item| match item {
+        [Value::None] => Some(TransitionProperty::None),
+        [Value::Keyword(keyword)] => Some(transition_property(keyword)),
+        _ => None,
+    })?;
+    // `none` is only valid on its own
+    let lone_none = properties.len() == 1 || !properties.contains(&TransitionProperty::None);
+    lone_none.then_some(properties)
+}
+
+fn transition_property(keyword: &str) -> TransitionProperty {
+    match keyword.to_ascii_lowercase().as_str() {
+        "all" => TransitionProperty::All,
+        name => TransitionProperty::Name(name.to_string()),
+    }
+}
+
+pub fn parse_names(value: &Value) -> Option<Vec<Option<String>>> {
+    parse_list(value, |item| match item {
+        [Value::None] => Some(None),
+        [Value::Keyword(name)] if !name.is_empty() => Some(Some(name.clone())),
+        [Value::String(name)] => Some(Some(name.clone())),
+        _ => None,
+    })
+}
+
+pub fn parse_iteration_counts(value: &Value) -> Option<Vec<f32>> {
+    parse_list(value, |item| match item {
+        [value] => iteration_count(value),
+        _ => None,
+    })
+}
+
+pub(super) fn iteration_count(value: &Value) -> Option<f32> {
+    match value {
+        Value::Number(count) if *count >= 0.0 => Some(*count),
+        Value::Keyword(keyword) if keyword.eq_ignore_ascii_case("infinite") => Some(f32::INFINITY),
+        _ => None,
+    }
+}
+
+pub fn parse_directions(value: &Value) -> Option<Vec<AnimationDirection>> {
+    parse_list(value, |item| match item {
+        [Value::Keyword(keyword)] => AnimationDirection::from_keyword(keyword),
+        _ => None,
+    })
+}
+
+pub fn parse_fill_modes(value: &Value) -> Option<Vec<FillMode>> {
+    parse_list(value, |item| match item {
+        [Value::None] => Some(FillMode::None),
+        [Value::Keyword(keyword)] => FillMode::from_keyword(keyword),
+        _ => None,
+    })
+}
+
+pub fn parse_play_states(value: &Value) -> Option<Vec<PlayState>> {
+    parse_list(value, |item| match item {
+        [Value::Keyword(keyword)] => PlayState::from_keyword(keyword),
+        _ => None,
+    })
+}
+
+/// What has to be redone when a property's value changes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Invalidation {
+    /// Only painting, e.g. for colors and opacity
+    Repaint,
+    /// Layout, for anything that moves or resizes boxes
+    Relayout,
+}
+
+/// Computed values that can blend between two others
+pub trait Interpolate: Clone + PartialEq {
+    /// The value `progress` of the way from `self` to `to`; progress may
+    /// overshoot 0 and 1 with some timing functions
+    fn interpolate(&self, to: &Self, progress: f32) -> Self;
+}
+
+/// For values that can't blend: the start value until halfway, then the end
+fn discrete<T: Clone>(from: &T, to: &T, progress: f32) -> T {
+    if progress < 0.5 {
+        from.clone()
+    } else {
+        to.clone()
+    }
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        self + (to - self) * progress
+    }
+}
+
+impl Interpolate for Color {
+    /// Blends with premultiplied alpha, so fading from `transparent`
+    /// doesn't pass through black
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        let a = self.a.interpolate(&to.a, progress).clamp(0.0, 1.0);
+        if a == 0.0 {
+            return Color::TRANSPARENT;
+        }
+        let channel = |from: f32, to_channel: f32| {
+            ((from * self.a).interpolate(&(to_channel * to.a), progress) / a).clamp(0.0, 1.0)
+        };
+        Color {
+            r: channel(self.r, to.r),
+            g: channel(self.g, to.g),
+            b: channel(self.b, to.b),
+            a,
+        }
+    }
+}
+
+impl Interpolate for LengthOrPercentage {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        match (self, to) {
+            (LengthOrPercentage::Px(from), LengthOrPercentage::Px(to)) => {
+                LengthOrPercentage::Px(from.interpolate(to, progress))
+            }
+            (LengthOrPercentage::Percent(from), LengthOrPercentage::Percent(to)) => {
+                LengthOrPercentage::Percent(from.interpolate(to, progress))
+            }
+            _ => discrete(self, to, progress),
+        }
+    }
+}
+
+impl Interpolate for CornerRadius {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        CornerRadius {
+            x: self.x.interpolate(&to.x, progress),
+            y: self.y.interpolate(&to.y, progress),
+        }
+    }
+}
+
+impl Interpolate for TransformFunction {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        match (self, to) {
+            (TransformFunction::Translate(x0, y0, z0), TransformFunction::Translate(x1, y1, z1)) => {
+                TransformFunction::Translate(
+                    x0.interpolate(x1, progress),
+                    y0.interpolate(y1, progress),
+                    z0.interpolate(z1, progress),
+                )
+            }
+            (TransformFunction::Scale(x0, y0, z0), TransformFunction::Scale(x1, y1, z1)) => TransformFunction::Scale(
+                x0.interpolate(x1, progress),
+                y0.interpolate(y1, progress),
+                z0.interpolate(z1, progress),
+            ),
+            // Around the axis of the one that turns at all
+            (TransformFunction::Rotate(axis0, angle0), TransformFunction::Rotate(axis1, angle1))
+                if axis0 == axis1 || *angle0 == 0.0 || *angle1 == 0.0 =>
+            {
+                let axis = if *angle0 == 0.0 { axis1 } else { axis0 };
+                TransformFunction::Rotate(*axis, angle0.interpolate(angle1, progress))
+            }
+            (TransformFunction::Skew(x0, y0), TransformFunction::Skew(x1, y1)) => {
+                TransformFunction::Skew(x0.interpolate(x1, progress), y0.interpolate(y1, progress))
+            }
+            (TransformFunction::Perspective(from), TransformFunction::Perspective(to)) => {
+                TransformFunction::Perspective(from.interpolate(to, progress))
+            }
+            _ => discrete(self, to, progress),
+        }
+    }
+}
+
+/// Transform lists blend function by function when they have the same
+/// kinds of functions in the same order, `none` as the other list doing
+/// nothing; other pairs swap discretely
+impl Interpolate for Vec<TransformFunction> {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        let identity = |list: &Self| list.iter().map(TransformFunction::identity).collect::<Self>();
+        let (from, to) = match (self.is_empty(), to.is_empty()) {
+            (true, false) => (identity(to), to.clone()),
+            (false, true) => (self.clone(), identity(self)),
+            _ => (self.clone(), to.clone()),
+        };
+        let same_kinds = from.len() == to.len()
+            && from
+                .iter()
+                .zip(&to)
+                .all(|(a, b)| std::mem::discriminant(a) == std::mem::discriminant(b));
+        if !same_kinds {
+            return discrete(&from, &to, progress);
+        }
+        from.iter().zip(&to).map(|(a, b)| a.interpolate(b, progress)).collect()
+    }
+}
+
+impl Interpolate for FilterFunction {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        use FilterFunction::*;
+        let amount = |from: &f32, to: &f32| from.interpolate(to, progress);
+        match (self, to) {
+            (Blur(from), Blur(to)) => Blur(amount(from, to)),
+            (Brightness(from), Brightness(to)) => Brightness(amount(from, to)),
+            (Contrast(from), Contrast(to)) => Contrast(amount(from, to)),
+            (Grayscale(from), Grayscale(to)) => Grayscale(amount(from, to)),
+            (HueRotate(from), HueRotate(to)) => HueRotate(amount(from, to)),
+            (Invert(from), Invert(to)) => Invert(amount(from, to)),
+            (Opacity(from), Opacity(to)) => Opacity(amount(from, to)),
+            (Saturate(from), Saturate(to)) => Saturate(amount(from, to)),
+            (Sepia(from), Sepia(to)) => Sepia(amount(from, to)),
+            (
+                DropShadow { offset_x: x0, offset_y: y0, blur: blur0, color: color0 },
+                DropShadow { offset_x: x1, offset_y: y1, blur: blur1, color: color1 },
+            ) => DropShadow {
+                offset_x: amount(x0, x1),
+                offset_y: amount(y0, y1),
+                blur: amount(blur0, blur1),
+                color: color0.interpolate(color1, progress),
+            },
+            _ => discrete(self, to, progress),
+        }
+    }
+}
+
+/// Filter lists blend function by function when one starts with the same
+/// kinds of functions as the other, the shorter one padded with functions
+/// that do nothing; other pairs swap discretely
+impl Interpolate for Vec<FilterFunction> {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        let (shorter, longer) = if self.len() <= to.len() { (self, to) } else { (to, self) };
+        let same_kinds = shorter
+            .iter()
+            .zip(longer)
+            .all(|(a, b)| std::mem::discriminant(a) == std::mem::discriminant(b));
+        if !same_kinds {
+            return discrete(self, to, progress);
+        }
+        let padded = |list: &Self| {
+            let padding = longer[list.len()..].iter().map(FilterFunction::identity);
+            list.iter().cloned().chain(padding).collect::<Self>()
+        };
+        padded(self)
+            .iter()
+            .zip(&padded(to))
+            .map(|(a, b)| a.interpolate(b, progress))
+            .collect()
+    }
+}
+
+/// `None` stands for a keyword such as `auto` or `currentcolor`, which
+/// only swaps discretely with a value
+impl<T: Interpolate> Interpolate for Option<T> {
+    fn interpolate(&self, to: &Self, progress: f32) -> Self {
+        match (self, to) {
+            (Some(from), Some(to)) => Some(from.interpolate(to, progress)),
+            _ => discrete(self, to, progress),
+        }
+    }
+}
+
+/// A property animations and transitions can change
+pub struct AnimatableProperty {
+    pub name: &'static str,
+    pub invalidation: Invalidation,
+    /// Sets this property on a style to the value `progress` of the way
+    /// from one style's value to another's
+    blend: fn(&mut ComputedStyle, &ComputedStyle, &ComputedStyle, f32),
+    differs: fn(&ComputedStyle, &ComputedStyle) -> bool,
+}
+
+impl AnimatableProperty {
+    pub fn blend(&self, style: &mut ComputedStyle, from: &ComputedStyle, to: &ComputedStyle, progress: f32) {
+        (self.blend)(style, from, to, progress)
+    }
+
+    /// Whether two styles have different values for this property
+    pub fn differs(&self, a: &ComputedStyle, b: &ComputedStyle) -> bool {
+        (self.differs)(a, b)
+    }
+}
+
+impl std::fmt::Debug for AnimatableProperty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+macro_rules! animatable {
+    ($($name:literal, $invalidation:ident => $($field:ident).+;)*) => {
+        const ANIMATABLE: &[AnimatableProperty] = &[$(
+            AnimatableProperty {
+                name: $name,
+                invalidation: Invalidation::$invalidation,
+                blend: |style, from, to, progress| {
+                    style.$($field).+ = from.$($field).+.interpolate(&to.$($field).+, progress);
+                },
+                differs: |a, b| a.$($field).+ != b.$($field).+,
+            },
+        )*];
+    };
+}
+
+animatable! {
+    "color", Repaint => color;
+    "background-color", Repaint => background_color;
+    "opacity", Repaint => opacity;
+    "border-top-color", Repaint => border_top_color;
+    "border-right-color", Repaint => border_right_color;
+    "border-bottom-color", Repaint => border_bottom_color;
+    "border-left-color", Repaint => border_left_color;
+    "border-top-left-radius", Repaint => border_top_left_radius;
+    "border-top-right-radius", Repaint => border_top_right_radius;
+    "border-bottom-left-radius", Repaint => border_bottom_left_radius;
+    "border-bottom-right-radius", Repaint => border_bottom_right_radius;
+    "text-decoration-color", Repaint => text_decoration_color;
+    "translate", Repaint => translate;
+    "rotate", Repaint => rotate;
+    "scale", Repaint => scale;
+    "transform", Repaint => transform;
+    "filter", Repaint => filter;
+    "backdrop-filter", Repaint => backdrop_filter;
+
+    "width", Relayout => width;
+    "height", Relayout => height;
+    "min-width", Relayout => min_width;
+    "min-height", Relayout => min_height;
+    "max-width", Relayout => max_width;
+    "max-height", Relayout => max_height;
+    "top", Relayout => top;
+    "right", Relayout => right;
+    "bottom", Relayout => bottom;
+    "left", Relayout => left;
+    "margin-top", Relayout => margin_top;
+    "margin-right", Relayout => margin_right;
+    "margin-bottom", Relayout => margin_bottom;
+    "margin-left", Relayout => margin_left;
+    "padding-top", Relayout => padding_top;
+    "padding-right", Relayout => padding_right;
+    "padding-bottom", Relayout => padding_bottom;
+    "padding-left", Relayout => padding_left;
+    "border-top-width", Relayout => border_top_width;
+    "border-right-width", Relayout => border_right_width;
+    "border-bottom-width", Relayout => border_bottom_width;
+    "border-left-width", Relayout => border_left_width;
+    "font-size", Relayout => font_size;
+    "line-height", Relayout => line_height;
+    "letter-spacing", Relayout => letter_spacing;
+    "word-spacing", Relayout => word_spacing;
+    "text-indent", Relayout => text_indent;
+    "gap", Relayout => gap;
+    "row-gap", Relayout => row_gap;
+    "column-gap", Relayout => column_gap;
+    "flex-grow", Relayout => flex_grow;
+    "flex-shrink", Relayout => flex_shrink;
+    "flex-basis", Relayout => flex_basis;
+}
+
+pub fn animatable(name: &str) -> Option<&'static AnimatableProperty> {
+    ANIMATABLE.iter().find(|property| property.name == name)
+}
+
+pub fn all_animatable() -> &'static [AnimatableProperty] {
+    ANIMATABLE
+}
+
+/// The animatable longhands a declaration of `name` sets
+pub fn animatable_longhands(name: &str) -> impl Iterator<Item = &'static AnimatableProperty> {
+    properties::expand(name).into_iter().filter_map(|property| animatable(property.name))
+}
+
+/// Keeps blended values in range where a timing function overshoots
+pub fn clamp_animated(style: &mut ComputedStyle) {
+    style.opacity = style.opacity.clamp(0.0, 1.0);
+    style.font_size = style.font_size.max(0.0);
+    for width in [
+        &mut style.padding_top,
+        &mut style.padding_right,
+        &mut style.padding_bottom,
+        &mut style.padding_left,
+        &mut style.border_top_width,
+        &mut style.border_right_width,
+        &mut style.border_bottom_width,
+        &mut style.border_left_width,
+    ] {
+        *width = width.max(0.0);
+    }
+}
+
+/// A keyframe of a `@keyframes` rule computed for one element
+#[derive(Debug, Clone)]
+pub struct ComputedKeyframe {
+    /// Where it falls in the animation, from 0 to 1
+    pub offset: f32,
+    /// The element's style with the keyframe's declarations applied
+    pub style: Rc<ComputedStyle>,
+    /// The animatable properties the keyframe sets
+    pub properties: Vec<&'static AnimatableProperty>,
+    /// The `animation-timing-function` the keyframe gives the way to the
+    /// next one
+    pub timing_function: Option<TimingFunction>,
+}
+
+impl ComputedKeyframe {
+    /// A keyframe from its declarations and the style they were applied
+    /// to; `animation-*` declarations are left out of `style`
+    pub fn new(offset: f32, declarations: &[Declaration], style: ComputedStyle) -> Self {
+        let mut properties: Vec<&'static AnimatableProperty> = Vec::new();
+        let mut timing_function = None;
+        for declaration in declarations {
+            if declaration.property == "animation-timing-function" {
+                timing_function = TimingFunction::from_value(&declaration.value);
+            }
+            for property in animatable_longhands(&declaration.property) {
+                if !properties.iter().any(|known| std::ptr::eq(*known, property)) {
+                    properties.push(property);
+                }
+            }
+        }
+        Self {
+            offset,
+            style: Rc::new(style),
+            properties,
+            timing_function,
+        }
+    }
+}
+
+/// Sets the properties `keyframes` animate on `style` to their values
+/// `progress` of the way through the animation. Each property runs
+/// between the keyframes that set it, with `style`'s own value standing
+/// in at 0 and 1 where no keyframe is given.
+pub fn apply_keyframes(
+    style: &mut ComputedStyle,
+    keyframes: &[ComputedKeyframe],
+    progress: f32,
+    timing_function: TimingFunction,
+) {
+    let base = Rc::new(style.clone());
+    let mut done: Vec<&'static AnimatableProperty> = Vec::new();
+    for property in keyframes.iter().flat_map(|keyframe| &keyframe.properties) {
+        if done.iter().any(|known| std::ptr::eq(*known, *property)) {
+            continue;
+        }
+        done.push(property);
+
+        let mut frames: Vec<(f32, &Rc<ComputedStyle>, TimingFunction)> = keyframes
+            .iter()
+            .filter(|keyframe| keyframe.properties.iter().any(|p| std::ptr::eq(*p, *property)))
+            .map(|keyframe| (keyframe.offset, &keyframe.style, keyframe.timing_function.unwrap_or(timing_function)))
+            .collect();
+        if frames.first().is_some_and(|(offset, _, _)| *offset > 0.0) {
+            frames.insert(0, (0.0, &base, timing_function));
+        }
+        if frames.last().is_some_and(|(offset, _, _)| *offset < 1.0) {
+            frames.push((1.0, &base, timing_function));
+        }
+
+        // The segment progress falls in; the later keyframe wins a tie
+        let next = frames
+            .iter()
+            .position(|(offset, _, _)| *offset > progress)
+            .unwrap_or(frames.len() - 1)
+            .max(1);
+        let (start, from, timing) = frames[next - 1];
+        let (end, to, _) = frames[next];
+        let local = if end > start { (progress - start) / (end - start) } else { 1.0 };
+        property.blend(style, from, to, timing.apply(local));
+    }
+    clamp_animated(style);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::parse_css;
+    use crate::css::shorthand::shorthand;
+
+    fn value(css_value: &str) -> Value {
+        let stylesheet = parse_css(&format!("a {{ x: {}; }}", css_value));
+        stylesheet.rules[0].declarations[0].value.clone()
+    }
+
+    /// The longhand values `name` expands `css_value` to
+    fn longhands(name: &str, css_value: &str) -> Option<Vec<Value>> {
+        let longhands = shorthand(name).unwrap().expand(&value(css_value))?;
+        Some(longhands.into_iter().map(|(_, value)| value).collect())
+    }
+
+    fn parse_transition_shorthand(css_value: &str) -> Option<Transitions> {
+        let values = longhands("transition", css_value)?;
+        Some(Transitions {
+            property: parse_transition_properties(&values[0])?,
+            duration: parse_durations(&values[1])?,
+            timing_function: parse_timing_functions(&values[2])?,
+            delay: parse_times(&values[3])?,
+        })
+    }
+
+    fn parse_animation_shorthand(css_value: &str) -> Option<Animations> {
+        let values = longhands("animation", css_value)?;
+        Some(Animations {
+            name: parse_names(&values[0])?,
+            duration: parse_durations(&values[1])?,
+            timing_function: parse_timing_functions(&values[2])?,
+            delay: parse_times(&values[3])?,
+            iteration_count: parse_iteration_counts(&values[4])?,
+            direction: parse_directions(&values[5])?,
+            fill_mode: parse_fill_modes(&values[6])?,
+            play_state: parse_play_states(&values[7])?,
+        })
+    }
+
+    fn approx(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.001
+    }
+
+    #[test]
+    fn test_timing_functions() {
+        let function = |css: &str| TimingFunction::from_value(&value(css)).unwrap();
+
+        let linear = function("linear");
+        assert_eq!(linear.apply(0.3), 0.3);
+
+        // Ease-in starts slow and ends at 1; ease starts fast
+        let ease_in = function("ease-in");
+        assert!(ease_in.apply(0.25) < 0.25);
+        assert!(approx(ease_in.apply(1.0), 1.0));
+        assert!(function("ease").apply(0.25) > 0.25);
+        assert!(approx(function("cubic-bezier(0, 0, 1, 1)").apply(0.7), 0.7));
+        assert!(TimingFunction::from_value(&value("cubic-bezier(2, 0, 1, 1)")).is_none());
+
+        let steps = function("steps(4)");
+        assert_eq!(steps.apply(0.3), 0.25);
+        assert_eq!(steps.apply(1.0), 1.0);
+        assert_eq!(function("steps(4, jump-start)").apply(0.3), 0.5);
+        assert_eq!(function("steps(3, jump-none)").apply(0.5), 0.5);
+        assert_eq!(function("step-end").apply(0.99), 0.0);
+        assert!(TimingFunction::from_value(&value("steps(1, jump-none)")).is_none());
+    }
+
+    #[test]
+    fn test_parse_transition() {
+        let transitions = parse_transition_shorthand("opacity 0.5s ease-in, margin 200ms 1s").unwrap();
+        assert_eq!(
+            transitions.property,
+            vec![TransitionProperty::Name("opacity".to_string()), TransitionProperty::Name("margin".to_string())]
+        );
+        assert_eq!(transitions.duration, vec![0.5, 0.2]);
+        assert_eq!(transitions.delay, vec![0.0, 1.0]);
+
+        let timing = transitions.for_property("margin-left").unwrap();
+        assert_eq!(timing.duration, 0.2);
+        assert_eq!(timing.timing_function, TimingFunction::EASE);
+        assert!(transitions.for_property("color").is_none());
+        assert!(parse_transition_shorthand("all 1s").unwrap().for_property("color").is_some());
+
+        // Zero durations don't transition, and lists repeat
+        let transitions = Transitions {
+            property: parse_transition_properties(&value("color, width")).unwrap(),
+            duration: parse_durations(&value("1s")).unwrap(),
+            ..Transitions::default()
+        };
+        assert_eq!(transitions.for_property("width").unwrap().duration, 1.0);
+        assert!(Transitions::default().for_property("width").is_none());
+        assert!(parse_durations(&value("-1s")).is_none());
+    }
+
+    #[test]
+    fn test_parse_animation() {
+        let animations = parse_animation_shorthand("spin 2s linear infinite reverse, fade 1s 0.5s both").unwrap();
+        let list = animations.list();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].name, "spin");
+        assert_eq!(list[0].timing_function, TimingFunction::Linear);
+        assert_eq!(list[0].iteration_count, f32::INFINITY);
+        assert_eq!(list[0].direction, AnimationDirection::Reverse);
+        assert_eq!(list[1].delay, 0.5);
+        assert_eq!(list[1].fill_mode, FillMode::Both);
+
+        assert!(parse_animation_shorthand("none").unwrap().is_none());
+        assert_eq!(parse_names(&value("a, none")).unwrap(), vec![Some("a".to_string()), None]);
+    }
+
+    #[test]
+    fn test_animation_progress() {
+        let animation = Animation {
+            name: "a".to_string(),
+            duration: 2.0,
+            timing_function: TimingFunction::Linear,
+            delay: 1.0,
+            iteration_count: 2.0,
+            direction: AnimationDirection::Alternate,
+            fill_mode: FillMode::None,
+            play_state: PlayState::Running,
+        };
+        assert_eq!(animation.progress(0.5), None);
+        assert_eq!(animation.progress(2.0), Some(0.5));
+        // The second iteration runs backwards
+        assert_eq!(animation.progress(3.5), Some(0.75));
+        assert_eq!(animation.progress(5.0), None);
+
+        let filled = Animation {
+            fill_mode: FillMode::Both,
+            ..animation
+        };
+        assert_eq!(filled.progress(0.0), Some(0.0));
+        assert_eq!(filled.progress(10.0), Some(0.0));
+    }
+
+    #[test]
+    fn test_interpolate_values() {
+        let from = Color::rgba(255, 0, 0, 255);
+        let mid = from.interpolate(&Color::TRANSPARENT, 0.5);
+        // Fading out keeps the color rather than darkening it
+        assert!(approx(mid.r, 1.0));
+        assert!(approx(mid.a, 0.5));
+
+        let px = LengthOrPercentage::Px(10.0).interpolate(&LengthOrPercentage::Px(20.0), 0.25);
+        assert_eq!(px, LengthOrPercentage::Px(12.5));
+        let mixed = LengthOrPercentage::Px(10.0).interpolate(&LengthOrPercentage::Percent(50.0), 0.4);
+        assert_eq!(mixed, LengthOrPercentage::Px(10.0));
+        assert_eq!(None::<f32>.interpolate(&Some(4.0), 0.5), Some(4.0));
+    }
+
+    #[test]
+    fn test_interpolate_transforms() {
+        use crate::css::transform::TransformFunction::{Rotate, Scale, Translate};
+        let px = LengthOrPercentage::Px;
+
+        // `none` blends as the other list doing nothing
+        let to = vec![Translate(px(100.0), px(0.0), 0.0), Rotate([0.0, 0.0, 1.0], 90.0)];
+        assert_eq!(
+            Vec::new().interpolate(&to, 0.5),
+            vec![Translate(px(50.0), px(0.0), 0.0), Rotate([0.0, 0.0, 1.0], 45.0)]
+        );
+        assert_eq!(
+            to.interpolate(&Vec::new(), 1.0),
+            vec![Translate(px(0.0), px(0.0), 0.0), Rotate([0.0, 0.0, 1.0], 0.0)]
+        );
+
+        // Lists of different kinds of functions swap halfway
+        let scaled = vec![Scale(2.0, 2.0, 1.0)];
+        assert_eq!(to.interpolate(&scaled, 0.4), to);
+        assert_eq!(to.interpolate(&scaled, 0.6), scaled);
+
+        // Starting from no rotation, it turns around the other's axis
+        let tilted = TransformFunction::NO_ROTATE.interpolate(&Rotate([1.0, 0.0, 0.0], 60.0), 0.5);
+        assert_eq!(tilted, Rotate([1.0, 0.0, 0.0], 30.0));
+        assert_eq!(animatable("scale").unwrap().invalidation, Invalidation::Repaint);
+    }
+
+    #[test]
+    fn test_interpolate_filters() {
+        use crate::css::filter::FilterFunction::{Blur, Grayscale, Sepia};
+
+        // The shorter list is padded with functions that do nothing
+        let from = vec![Blur(4.0)];
+        let to = vec![Blur(0.0), Grayscale(1.0)];
+        assert_eq!(from.interpolate(&to, 0.25), vec![Blur(3.0), Grayscale(0.25)]);
+        assert_eq!(Vec::new().interpolate(&from, 0.5), vec![Blur(2.0)]);
+
+        // Lists of different kinds of functions swap halfway
+        let toned = vec![Sepia(1.0)];
+        assert_eq!(from.interpolate(&toned, 0.4), from);
+        assert_eq!(from.interpolate(&toned, 0.6), toned);
+        assert_eq!(animatable("filter").unwrap().invalidation, Invalidation::Repaint);
+    }
+
+    #[test]
+    fn test_apply_keyframes() {
+        let base = ComputedStyle::default();
+        let keyframe = |offset: f32, opacity: f32, margin: f32| {
+            let declarations = parse_css(&format!("a {{ opacity: {}; margin-left: {}px }}", opacity, margin)).rules[0]
+                .declarations
+                .clone();
+            let style = ComputedStyle {
+                opacity,
+                margin_left: margin,
+                ..base.clone()
+            };
+            ComputedKeyframe::new(offset, &declarations, style)
+        };
+        let keyframes = vec![keyframe(0.0, 0.0, 0.0), keyframe(0.5, 1.0, 100.0)];
+        assert_eq!(keyframes[0].properties.iter().map(|p| p.name).collect::<Vec<_>>(), vec!["opacity", "margin-left"]);
+        assert_eq!(animatable("margin-left").unwrap().invalidation, Invalidation::Relayout);
+
+        let mut style = base.clone();
+        apply_keyframes(&mut style, &keyframes, 0.25, TimingFunction::Linear);
+        assert!(approx(style.opacity, 0.5));
+        assert!(approx(style.margin_left, 50.0));
+
+        // Past the last keyframe the element's own values are the end
+        let mut style = base.clone();
+        apply_keyframes(&mut style, &keyframes, 0.75, TimingFunction::Linear);
+        assert!(approx(style.opacity, 1.0));
+        assert!(approx(style.margin_left, 50.0));
+    }
+}
@@ -0,0 +1,744 @@
+//! Background layers: `background-image` with `url()` and gradients, and the
+//! per-layer properties that size, position, repeat and clip them. Each
+//! property is a comma-separated list with an entry per layer.
+
+use super::computed::LengthOrPercentage;
+use super::stylesheet::{LengthContext, Value};
+use crate::render::painter::Color;
+use std::rc::Rc;
+
+/// The image of a background layer
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackgroundImage {
+    Url(String),
+    Gradient(Rc<Gradient>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    pub stops: Vec<ColorStop>,
+    /// A `repeating-*` gradient, whose stops repeat past the last one
+    pub repeating: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GradientKind {
+    Linear(LinearDirection),
+    Radial {
+        shape: RadialShape,
+        size: RadialSize,
+        position: Position,
+    },
+    /// Starting `from` degrees clockwise from the top
+    Conic { from: f32, position: Position },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinearDirection {
+    /// Degrees clockwise from `to top`
+    Angle(f32),
+    /// `to` a corner, whose angle depends on the shape of the box
+    Corner { right: bool, bottom: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RadialShape {
+    Circle,
+    Ellipse,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RadialSize {
+    ClosestSide,
+    FarthestSide,
+    ClosestCorner,
+    FarthestCorner,
+    /// Horizontal and vertical radii; a circle's are the same length
+    Radii(LengthOrPercentage, LengthOrPercentage),
+}
+
+/// A color stop. The positions of a conic gradient's stops are percentages
+/// of a turn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorStop {
+    pub color: Color,
+    pub position: Option<LengthOrPercentage>,
+}
+
+/// One coordinate of a position: an offset from the start edge, or from the
+/// end edge for `right` and `bottom`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionComponent {
+    pub offset: LengthOrPercentage,
+    pub from_end: bool,
+}
+
+impl PositionComponent {
+    fn percent(pct: f32) -> Self {
+        Self {
+            offset: LengthOrPercentage::Percent(pct),
+            from_end: false,
+        }
+    }
+
+    /// Pixels from the start edge, with percentages of `size`
+    pub fn resolve(&self, size: f32) -> f32 {
+        let offset = self.offset.to_px(size);
+        if self.from_end {
+            size - offset
+        } else {
+            offset
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub x: PositionComponent,
+    pub y: PositionComponent,
+}
+
+impl Position {
+    pub fn center() -> Self {
+        Self {
+            x: PositionComponent::percent(50.0),
+            y: PositionComponent::percent(50.0),
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self {
+            x: PositionComponent::percent(0.0),
+            y: PositionComponent::percent(0.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackgroundSize {
+    Cover,
+    Contain,
+    /// Width and height, `None` for `auto`
+    Explicit(Option<LengthOrPercentage>, Option<LengthOrPercentage>),
+}
+
+impl Default for BackgroundSize {
+    fn default() -> Self {
+        BackgroundSize::Explicit(None, None)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RepeatStyle {
+    #[default]
+    Repeat,
+    NoRepeat,
+    /// As many whole copies as fit, spaced out to fill the area
+    Space,
+    /// Copies scaled so a whole number of them fill the area
+    Round,
+}
+
+impl RepeatStyle {
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword.to_ascii_lowercase().as_str() {
+            "repeat" => Some(RepeatStyle::Repeat),
+            "no-repeat" => Some(RepeatStyle::NoRepeat),
+            "space" => Some(RepeatStyle::Space),
+            "round" => Some(RepeatStyle::Round),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BackgroundRepeat {
+    pub x: RepeatStyle,
+    pub y: RepeatStyle,
+}
+
+/// The box a layer is positioned in (`background-origin`) or clipped to
+/// (`background-clip`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackgroundBox {
+    BorderBox,
+    PaddingBox,
+    ContentBox,
+}
+
+impl BackgroundBox {
+    pub(super) fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword.to_ascii_lowercase().as_str() {
+            "border-box" => Some(BackgroundBox::BorderBox),
+            "padding-box" => Some(BackgroundBox::PaddingBox),
+            "content-box" => Some(BackgroundBox::ContentBox),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BackgroundAttachment {
+    /// Fixed to the box
+    #[default]
+    Scroll,
+    /// Fixed to the viewport
+    Fixed,
+    /// Scrolls with the box's contents; the same as `scroll` for boxes that
+    /// don't scroll, which none here do
+    Local,
+}
+
+impl BackgroundAttachment {
+    pub(super) fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword.to_ascii_lowercase().as_str() {
+            "scroll" => Some(BackgroundAttachment::Scroll),
+            "fixed" => Some(BackgroundAttachment::Fixed),
+            "local" => Some(BackgroundAttachment::Local),
+            _ => None,
+        }
+    }
+}
+
+/// The background layer properties. `image` sets the number of layers,
+/// first layer on top; the other lists repeat to match it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Backgrounds {
+    /// `None` for a `none` layer
+    pub image: Vec<Option<BackgroundImage>>,
+    pub position: Vec<Position>,
+    pub size: Vec<BackgroundSize>,
+    pub repeat: Vec<BackgroundRepeat>,
+    pub origin: Vec<BackgroundBox>,
+    pub clip: Vec<BackgroundBox>,
+    pub attachment: Vec<BackgroundAttachment>,
+}
+
+/// A background layer with an image
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackgroundLayer<'a> {
+    pub image: &'a BackgroundImage,
+    pub position: Position,
+    pub size: BackgroundSize,
+    pub repeat: BackgroundRepeat,
+    pub origin: BackgroundBox,
+    pub clip: BackgroundBox,
+    pub attachment: BackgroundAttachment,
+}
+
+/// Entry `index` of a layer property list, which repeats as needed
+fn nth<T: Clone>(list: &[T], index: usize, initial: T) -> T {
+    if list.is_empty() {
+        initial
+    } else {
+        list[index % list.len()].clone()
+    }
+}
+
+impl Backgrounds {
+    /// The layers that have an image, top layer first
+    pub fn layers(&self) -> Vec<BackgroundLayer<'_>> {
+        self.image
+            .iter()
+            .enumerate()
+            .filter_map(|(i, image)| {
+                Some(BackgroundLayer {
+                    image: image.as_ref()?,
+                    position: nth(&self.position, i, Position::default()),
+                    size: nth(&self.size, i, BackgroundSize::default()),
+                    repeat: nth(&self.repeat, i, BackgroundRepeat::default()),
+                    origin: nth(&self.origin, i, BackgroundBox::PaddingBox),
+                    clip: nth(&self.clip, i, BackgroundBox::BorderBox),
+                    attachment: nth(&self.attachment, i, BackgroundAttachment::default()),
+                })
+            })
+            .collect()
+    }
+
+    /// The box the background color is clipped to: that of the bottom layer
+    pub fn color_clip(&self) -> BackgroundBox {
+        let bottom = self.image.len().max(1) - 1;
+        nth(&self.clip, bottom, BackgroundBox::BorderBox)
+    }
+}
+
+/// Parses each comma-separated item of `value` with `parse`; `None` if any
+/// item is invalid
+fn parse_list<T>(value: &Value, mut parse: impl FnMut(&[Value]) -> Option<T>) -> Option<Vec<T>> {
+    value.as_comma_list().iter().map(|item| parse(item.as_slice())).collect()
+}
+
+pub fn parse_images(value: &Value, ctx: &LengthContext) -> Option<Vec<Option<BackgroundImage>>> {
+    parse_list(value, |item| match item {
+        [Value::None] => Some(None),
+        [image] => parse_image(image, ctx).map(Some),
+        _ => None,
+    })
+}
+
+pub fn parse_positions(value: &Value, ctx: &LengthContext) -> Option<Vec<Position>> {
+    parse_list(value, |item| parse_position(item, ctx))
+}
+
+pub fn parse_sizes(value: &Value, ctx: &LengthContext) -> Option<Vec<BackgroundSize>> {
+    parse_list(value, |item| parse_size(item, ctx))
+}
+
+pub fn parse_repeats(value: &Value) -> Option<Vec<BackgroundRepeat>> {
+    parse_list(value, parse_repeat)
+}
+
+pub fn parse_boxes(value: &Value) -> Option<Vec<BackgroundBox>> {
+    parse_list(value, |item| match item {
+        [Value::Keyword(keyword)] => BackgroundBox::from_keyword(keyword),
+        _ => None,
+    })
+}
+
+pub fn parse_attachments(value: &Value) -> Option<Vec<BackgroundAttachment>> {
+    parse_list(value, |item| match item {
+        [Value::Keyword(keyword)] => BackgroundAttachment::from_keyword(keyword),
+        _ => None,
+    })
+}
+
+/// A `url()` or gradient
+pub fn parse_image(value: &Value, ctx: &LengthContext) -> Option<BackgroundImage> {
+    match value {
+        Value::Url(url) => Some(BackgroundImage::Url(url.clone())),
+        Value::Function(name, args) => parse_gradient(name, args, ctx).map(|gradient| BackgroundImage::Gradient(Rc::new(gradient))),
+        _ => None,
+    }
+}
+
+fn parse_gradient(name: &str, args: &[Value], ctx: &LengthContext) -> Option<Gradient> {
+    let (name, repeating) = match name.strip_prefix("repeating-") {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+    let first = args.first()?.as_slice();
+    // The first argument configures the gradient when it isn't a color stop
+    let (kind, prelude) = match name {
+        "linear-gradient" => match parse_linear_direction(first) {
+            Some(direction) => (GradientKind::Linear(direction), true),
+            None => (GradientKind::Linear(LinearDirection::Angle(180.0)), false),
+        },
+        "radial-gradient" => match parse_radial_prelude(first, ctx) {
+            Some((shape, size, position)) => (GradientKind::Radial { shape, size, position }, true),
+            None => (
+                GradientKind::Radial {
+                    shape: RadialShape::Ellipse,
+                    size: RadialSize::FarthestCorner,
+                    position: Position::center(),
+                },
+                false,
+            ),
+        },
+        "conic-gradient" => match parse_conic_prelude(first, ctx) {
+            Some((from, position)) => (GradientKind::Conic { from, position }, true),
+            None => (GradientKind::Conic { from: 0.0, position: Position::center() }, false),
+        },
+        _ => return None,
+    };
+
+    let conic = matches!(kind, GradientKind::Conic { .. });
+    let stop_args = if prelude { &args[1..] } else { args };
+    let mut stops = Vec::new();
+    for arg in stop_args {
+        match arg.as_slice() {
+            [color, positions @ ..] if positions.len() <= 2 => {
+                let color = color.to_color()?;
+                if positions.is_empty() {
+                    stops.push(ColorStop { color, position: None });
+                }
+                for position in positions {
+                    let position = parse_stop_position(position, conic, ctx)?;
+                    stops.push(ColorStop { color, position: Some(position) });
+                }
+            }
+            _ => return None,
+        }
+    }
+    if stops.len() < 2 {
+        return None;
+    }
+
+    Some(Gradient { kind, stops, repeating })
+}
+
+/// A stop's position; conic gradients take angles, as percentages of a turn
+fn parse_stop_position(value: &Value, conic: bool, ctx: &LengthContext) -> Option<LengthOrPercentage> {
+    match value {
+        Value::Angle(degrees) if conic => Some(LengthOrPercentage::Percent(degrees / 360.0 * 100.0)),
+        Value::Percentage(pct) => Some(LengthOrPercentage::Percent(*pct)),
+        _ if conic => None,
+        _ => LengthOrPercentage::from_value(value, ctx),
+    }
+}
+
+/// An angle in degrees; unitless only for zero
+pub fn parse_angle(value: &Value) -> Option<f32> {
+    match value {
+        Value::Angle(degrees) => Some(*degrees),
+        Value::Number(n) if *n == 0.0 => Some(0.0),
+        _ => None,
+    }
+}
+
+fn parse_linear_direction(values: &[Value]) -> Option<LinearDirection> {
+    match values {
+        [angle] => parse_angle(angle).map(LinearDirection::Angle),
+        [Value::Keyword(to), sides @ ..] if to.eq_ignore_ascii_case("to") => {
+            let mut horizontal = None;
+            let mut vertical = None;
+            for side in sides {
+                match side.as_keyword()?.to_ascii_lowercase().as_str() {
+                    "left" if horizontal.is_none() => horizontal = Some(false),
+                    "right" if horizontal.is_none() => horizontal = Some(true),
+                    "top" if vertical.is_none() => vertical = Some(false),
+                    "bottom" if vertical.is_none() => vertical = Some(true),
+                    _ => return None,
+                }
+            }
+            match (horizontal, vertical) {
+                (Some(right), Some(bottom)) => Some(LinearDirection::Corner { right, bottom }),
+                (Some(right), None) => Some(LinearDirection::Angle(if right { 90.0 } else { 270.0 })),
+                (None, Some(bottom)) => Some(LinearDirection::Angle(if bottom { 180.0 } else { 0.0 })),
+                (None, None) => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Splits off `at <position>`, giving the values before it and the position
+fn split_at_position<'a>(values: &'a [Value], ctx: &LengthContext) -> Option<(&'a [Value], Option<Position>)> {
+    match values.iter().position(|v| v.as_keyword().is_some_and(|kw| kw.eq_ignore_ascii_case("at"))) {
+        Some(at) => Some((&values[..at], Some(parse_position(&values[at + 1..], ctx)?))),
+        None => Some((values, None)),
+    }
+}
+
+fn parse_radial_prelude(values: &[Value], ctx: &LengthContext) -> Option<(RadialShape, RadialSize, Position)> {
+    let (values, position) = split_at_position(values, ctx)?;
+    if values.is_empty() && position.is_none() {
+        return None;
+    }
+
+    let mut shape = None;
+    let mut size = None;
+    let mut radii = Vec::new();
+    for value in values {
+        match value.as_keyword().map(|kw| kw.to_ascii_lowercase()).as_deref() {
+            Some("circle") if shape.is_none() => shape = Some(RadialShape::Circle),
+            Some("ellipse") if shape.is_none() => shape = Some(RadialShape::Ellipse),
+            Some("closest-side") if size.is_none() => size = Some(RadialSize::ClosestSide),
+            Some("farthest-side") if size.is_none() => size = Some(RadialSize::FarthestSide),
+            Some("closest-corner") if size.is_none() => size = Some(RadialSize::ClosestCorner),
+            Some("farthest-corner") if size.is_none() => size = Some(RadialSize::FarthestCorner),
+            Some(_) => return None,
+            None => radii.push(LengthOrPercentage::from_value(value, ctx)?),
+        }
+    }
+
+    let (shape, size) = match (shape, size, radii.as_slice()) {
+        // A circle's radius can't be a percentage
+        (None | Some(RadialShape::Circle), None, [radius]) if radius.as_px().is_some() => {
+            (RadialShape::Circle, RadialSize::Radii(radius.clone(), radius.clone()))
+        }
+        (None | Some(RadialShape::Ellipse), None, [rx, ry]) => (RadialShape::Ellipse, RadialSize::Radii(rx.clone(), ry.clone())),
+        (shape, size, []) => (shape.unwrap_or(RadialShape::Ellipse), size.unwrap_or(RadialSize::FarthestCorner)),
+        _ => return None,
+    };
+    Some((shape, size, position.unwrap_or_else(Position::center)))
+}
+
+fn parse_conic_prelude(values: &[Value], ctx: &LengthContext) -> Option<(f32, Position)> {
+    let (values, position) = split_at_position(values, ctx)?;
+    let from = match values {
+        [] if position.is_some() => 0.0,
+        [Value::Keyword(from), angle] if from.eq_ignore_ascii_case("from") => parse_angle(angle)?,
+        _ => return None,
+    };
+    Some((from, position.unwrap_or_else(Position::center)))
+}
+
+/// A part of a position: an edge keyword or `center`, or an offset
+enum PositionPart<'a> {
+    Keyword(&'a str),
+    Offset(LengthOrPercentage),
+}
+
+/// Which axis a keyword is for, if it says (`center` doesn't), and whether
+/// it's the end edge
+fn edge(keyword: &str) -> Option<(Option<bool>, bool)> {
+    match keyword.to_ascii_lowercase().as_str() {
+        "left" => Some((Some(true), false)),
+        "right" => Some((Some(true), true)),
+        "top" => Some((Some(false), false)),
+        "bottom" => Some((Some(false), true)),
+        "center" => Some((None, false)),
+        _ => None,
+    }
+}
+
+/// A `<position>` of one to four values, e.g. `center`, `10px 50%` or
+/// `right 10px bottom`
+pub fn parse_position(values: &[Value], ctx: &LengthContext) -> Option<Position> {
+    let parts: Vec<PositionPart> = values
+        .iter()
+        .map(|value| match value {
+            Value::Keyword(keyword) => Some(PositionPart::Keyword(keyword)),
+            _ => LengthOrPercentage::from_value(value, ctx).map(PositionPart::Offset),
+        })
+        .collect::<Option<_>>()?;
+
+    let from_keyword = |keyword: &str| {
+        let (axis, from_end) = edge(keyword)?;
+        let pct = if axis.is_none() { 50.0 } else { 0.0 };
+        Some(PositionComponent {
+            offset: LengthOrPercentage::Percent(pct),
+            from_end,
+        })
+    };
+    let axis = |part: &PositionPart| match part {
+        PositionPart::Keyword(keyword) => edge(keyword).map(|(axis, _)| axis),
+        PositionPart::Offset(_) => Some(None),
+    };
+    let component = |part: &PositionPart| match part {
+        PositionPart::Keyword(keyword) => from_keyword(keyword),
+        PositionPart::Offset(offset) => Some(PositionComponent {
+            offset: offset.clone(),
+            from_end: false,
+        }),
+    };
+
+    match parts.as_slice() {
+        [part] => {
+            let value = component(part)?;
+            let center = PositionComponent::percent(50.0);
+            Some(match axis(part)? {
+                Some(false) => Position { x: center, y: value },
+                _ => Position { x: value, y: center },
+            })
+        }
+        [a, b] => {
+            // Two keywords may come in either order; with an offset, x is first
+            let swapped = axis(a)? == Some(false) || axis(b)? == Some(true);
+            let both_keywords = matches!((a, b), (PositionPart::Keyword(_), PositionPart::Keyword(_)));
+            let (x, y) = if swapped { (b, a) } else { (a, b) };
+            if (swapped && !both_keywords) || axis(x)? == Some(false) || axis(y)? == Some(true) {
+                return None;
+            }
+            Some(Position { x: component(x)?, y: component(y)? })
+        }
+        [_, _, _] | [_, _, _, _] => {
+            // Keywords, each edge keyword possibly followed by an offset
+            let mut x = None;
+            let mut y = None;
+            let mut centers = 0;
+            let mut i = 0;
+            while i < parts.len() {
+                let PositionPart::Keyword(keyword) = &parts[i] else {
+                    return None;
+                };
+                let (keyword_axis, from_end) = edge(keyword)?;
+                let offset = match parts.get(i + 1) {
+                    Some(PositionPart::Offset(offset)) if keyword_axis.is_some() => {
+                        i += 1;
+                        Some(offset.clone())
+                    }
+                    _ => None,
+                };
+                let value = match offset {
+                    Some(offset) => PositionComponent { offset, from_end },
+                    None => from_keyword(keyword)?,
+                };
+                let slot = match keyword_axis {
+                    Some(true) => &mut x,
+                    Some(false) => &mut y,
+                    None => {
+                        centers += 1;
+                        i += 1;
+                        continue;
+                    }
+                };
+                if slot.replace(value).is_some() {
+                    return None;
+                }
+                i += 1;
+            }
+            if centers + x.is_some() as usize + y.is_some() as usize != 2 {
+                return None;
+            }
+            Some(Position {
+                x: x.unwrap_or_else(|| PositionComponent::percent(50.0)),
+                y: y.unwrap_or_else(|| PositionComponent::percent(50.0)),
+            })
+        }
+        _ => None,
+    }
+}
+
+pub(super) fn parse_size(values: &[Value], ctx: &LengthContext) -> Option<BackgroundSize> {
+    let length = |value: &Value| match value {
+        Value::Auto => Some(None),
+        _ => LengthOrPercentage::from_value(value, ctx).map(Some),
+    };
+    match values {
+        [Value::Keyword(keyword)] if keyword.eq_ignore_ascii_case("cover") => Some(BackgroundSize::Cover),
+        [Value::Keyword(keyword)] if keyword.eq_ignore_ascii_case("contain") => Some(BackgroundSize::Contain),
+        [width] => Some(BackgroundSize::Explicit(length(width)?, None)),
+        [width, height] => Some(BackgroundSize::Explicit(length(width)?, length(height)?)),
+        _ => None,
+    }
+}
+
+pub(super) fn parse_repeat(values: &[Value]) -> Option<BackgroundRepeat> {
+    let keyword = |value: &Value| value.as_keyword().map(|kw| kw.to_ascii_lowercase());
+    match values {
+        [value] => match keyword(value)?.as_str() {
+            "repeat-x" => Some(BackgroundRepeat { x: RepeatStyle::Repeat, y: RepeatStyle::NoRepeat }),
+            "repeat-y" => Some(BackgroundRepeat { x: RepeatStyle::NoRepeat, y: RepeatStyle::Repeat }),
+            keyword => {
+                let style = RepeatStyle::from_keyword(keyword)?;
+                Some(BackgroundRepeat { x: style, y: style })
+            }
+        },
+        [x, y] => Some(BackgroundRepeat {
+            x: RepeatStyle::from_keyword(&keyword(x)?)?,
+            y: RepeatStyle::from_keyword(&keyword(y)?)?,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::parse_css;
+    use crate::css::shorthand::shorthand;
+
+    fn value(css_value: &str) -> Value {
+        let stylesheet = parse_css(&format!("a {{ x: {}; }}", css_value));
+        stylesheet.rules[0].declarations[0].value.clone()
+    }
+
+    /// The `background` shorthand's layers and color, as its longhands parse
+    fn parse_shorthand(value: &Value, ctx: &LengthContext, current_color: Color) -> Option<(Backgrounds, Color)> {
+        let longhands = shorthand("background").unwrap().expand(value)?;
+        let values: Vec<&Value> = longhands.iter().map(|(_, value)| value).collect();
+        let backgrounds = Backgrounds {
+            image: parse_images(values[1], ctx)?,
+            position: parse_positions(values[2], ctx)?,
+            size: parse_sizes(values[3], ctx)?,
+            repeat: parse_repeats(values[4])?,
+            origin: parse_boxes(values[5])?,
+            clip: parse_boxes(values[6])?,
+            attachment: parse_attachments(values[7])?,
+        };
+        Some((backgrounds, values[0].resolve_color(current_color).unwrap_or(Color::TRANSPARENT)))
+    }
+
+    fn gradient(css_value: &str) -> Gradient {
+        let ctx = LengthContext::new(800.0, 600.0);
+        match parse_image(&value(css_value), &ctx) {
+            Some(BackgroundImage::Gradient(gradient)) => (*gradient).clone(),
+            other => panic!("expected a gradient, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_gradients() {
+        let linear = gradient("linear-gradient(to top right, red, blue 80%)");
+        assert_eq!(linear.kind, GradientKind::Linear(LinearDirection::Corner { right: true, bottom: false }));
+        assert_eq!(linear.stops.len(), 2);
+        assert_eq!(linear.stops[0].position, None);
+        assert_eq!(linear.stops[1].position, Some(LengthOrPercentage::Percent(80.0)));
+        assert!(!linear.repeating);
+
+        let angled = gradient("repeating-linear-gradient(0.25turn, red 0 10px, blue 10px 20px)");
+        assert_eq!(angled.kind, GradientKind::Linear(LinearDirection::Angle(90.0)));
+        // A stop with two positions is two stops
+        assert_eq!(angled.stops.len(), 4);
+        assert!(angled.repeating);
+
+        let radial = gradient("radial-gradient(circle closest-side at left 10px top, red, blue)");
+        let GradientKind::Radial { shape, size, position } = radial.kind else {
+            panic!("expected a radial gradient");
+        };
+        assert_eq!(shape, RadialShape::Circle);
+        assert_eq!(size, RadialSize::ClosestSide);
+        assert_eq!(position.x.offset, LengthOrPercentage::Px(10.0));
+        assert_eq!(position.y.offset, LengthOrPercentage::Percent(0.0));
+
+        let conic = gradient("conic-gradient(from 90deg, red, blue 180deg)");
+        assert_eq!(conic.kind, GradientKind::Conic { from: 90.0, position: Position::center() });
+        assert_eq!(conic.stops[1].position, Some(LengthOrPercentage::Percent(50.0)));
+
+        assert!(parse_image(&value("linear-gradient(nonsense, red)"), &LengthContext::new(800.0, 600.0)).is_none());
+    }
+
+    #[test]
+    fn test_parse_positions() {
+        let ctx = LengthContext::new(800.0, 600.0);
+        let positions = parse_positions(&value("right 20px bottom, center, 25% 2em"), &ctx).unwrap();
+        assert_eq!(positions.len(), 3);
+        assert_eq!(positions[0].x.resolve(100.0), 80.0);
+        assert_eq!(positions[0].y.resolve(100.0), 100.0);
+        assert_eq!(positions[1], Position::center());
+        assert_eq!(positions[2].x.resolve(100.0), 25.0);
+        assert_eq!(positions[2].y.resolve(100.0), 32.0);
+        // A lone vertical keyword centers horizontally
+        let top = parse_positions(&value("top"), &ctx).unwrap();
+        assert_eq!(top[0].x.resolve(100.0), 50.0);
+        assert_eq!(top[0].y.resolve(100.0), 0.0);
+    }
+
+    #[test]
+    fn test_parse_shorthand() {
+        let ctx = LengthContext::new(800.0, 600.0);
+        let (backgrounds, color) = parse_shorthand(
+            &value("url(a.png) center / cover no-repeat fixed content-box, linear-gradient(red, blue) padding-box border-box green"),
+            &ctx,
+            Color::BLACK,
+        )
+        .unwrap();
+        assert_eq!(color, Color::rgb(0, 128, 0));
+        assert_eq!(backgrounds.image.len(), 2);
+
+        let layers = backgrounds.layers();
+        assert_eq!(layers[0].image, &BackgroundImage::Url("a.png".to_string()));
+        assert_eq!(layers[0].position, Position::center());
+        assert_eq!(layers[0].size, BackgroundSize::Cover);
+        assert_eq!(layers[0].repeat, BackgroundRepeat { x: RepeatStyle::NoRepeat, y: RepeatStyle::NoRepeat });
+        assert_eq!(layers[0].attachment, BackgroundAttachment::Fixed);
+        assert_eq!((layers[0].origin, layers[0].clip), (BackgroundBox::ContentBox, BackgroundBox::ContentBox));
+        assert_eq!((layers[1].origin, layers[1].clip), (BackgroundBox::PaddingBox, BackgroundBox::BorderBox));
+        assert_eq!(backgrounds.color_clip(), BackgroundBox::BorderBox);
+
+        // The color must come last
+        assert!(parse_shorthand(&value("red, url(a.png)"), &ctx, Color::BLACK).is_none());
+
+        let (_, color) = parse_shorthand(&value("url(a.png) currentcolor"), &ctx, Color::BLUE).unwrap();
+        assert_eq!(color, Color::BLUE);
+    }
+
+    #[test]
+    fn test_layer_lists_repeat() {
+        let ctx = LengthContext::new(800.0, 600.0);
+        let backgrounds = Backgrounds {
+            image: parse_images(&value("url(a.png), none, url(b.png)"), &ctx).unwrap(),
+            repeat: parse_repeats(&value("repeat-x, space")).unwrap(),
+            ..Default::default()
+        };
+        let layers = backgrounds.layers();
+        // The none layer has nothing to paint
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0].repeat, BackgroundRepeat { x: RepeatStyle::Repeat, y: RepeatStyle::NoRepeat });
+        assert_eq!(layers[1].repeat, BackgroundRepeat { x: RepeatStyle::Repeat, y: RepeatStyle::NoRepeat });
+    }
+}
@@ -1,18 +1,84 @@
-use super::computed::ComputedStyle;
+use super::animation::{Animation, ComputedKeyframe};
+use super::computed::{ComputedStyle, Display};
+use crate::render::painter::BorderStyle;
 use super::index::{IndexedRule, SelectorIndex};
-use super::selector::Specificity;
-use super::stylesheet::Stylesheet;
-use crate::dom::{Document, NodeId};
-use std::collections::HashMap;
+use super::invalidation::{Feature, RestyleFlags};
+use super::media::MediaEnvironment;
+use super::properties::{self, CssWideKeyword, Defaulting};
+use super::selector::{PseudoElement, Specificity};
+use super::shorthand::shorthand;
+use super::stylesheet::{Declaration, Keyframes, LengthContext, Origin, Stylesheet, Value};
+use super::timeline::AnimationTimeline;
+use super::variables::{resolve_custom_properties, substitute, CustomProperties};
+use crate::dom::node::ElementData;
+use crate::dom::{Document, ElementState, NodeId};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
+/// The attributes `apply_presentational_attributes` reads, which also key
+/// style sharing and restyle the element when they change
+const PRESENTATIONAL_ATTRIBUTES: [&str; 9] =
+    ["bgcolor", "width", "height", "border", "cellpadding", "cellspacing", "valign", "align", "color"];
+
 pub struct StyleComputer {
     stylesheets: Vec<Rc<Stylesheet>>,
     /// Lazily-built selector index for O(1) rule lookups
     selector_index: Option<SelectorIndex>,
-    computed_styles: HashMap<NodeId, ComputedStyle>,
+    /// Elements whose styles come out the same share one, as do text nodes
+    /// and their parents, and the boxes laid out for them
+    computed_styles: HashMap<NodeId, Rc<ComputedStyle>>,
+    /// Styles of the `::before`, `::after` and `::marker` boxes elements
+    /// generate, for those that have one
+    pseudo_styles: HashMap<(NodeId, PseudoElement), Rc<ComputedStyle>>,
+    /// What of the computed styles is out of date, by node
+    dirty: HashMap<NodeId, RestyleFlags>,
+    /// Elements setting a non-inherited property to `inherit`, which
+    /// depend on more of their parent's style than inheritance covers
+    explicit_inheritance: HashSet<NodeId>,
     viewport_width: f32,
     viewport_height: f32,
+    /// What `@media` rules are evaluated against
+    media: MediaEnvironment,
+    /// Root element's font size and line height in px, for `rem` and `rlh`
+    root_font_size: f32,
+    root_line_height: f32,
+    /// The transitions and animations running on elements
+    timeline: AnimationTimeline,
+}
+
+/// Styles of earlier siblings, for later ones bound to compute the same
+/// style to share without going through the cascade
+#[derive(Default)]
+struct SharingCache {
+    entries: Vec<(SharingKey, Rc<ComputedStyle>)>,
+}
+
+/// What an element's style depends on besides its parent's style
+#[derive(PartialEq)]
+struct SharingKey {
+    tag: String,
+    /// Source order of each matched rule, in cascade order
+    rules: Vec<usize>,
+    /// Values of the presentational attributes
+    attributes: Vec<Option<String>>,
+    is_link: bool,
+}
+
+impl SharingCache {
+    /// How many siblings' styles are kept
+    const SIZE: usize = 8;
+
+    fn get(&self, key: &SharingKey) -> Option<Rc<ComputedStyle>> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, style)| Rc::clone(style))
+    }
+
+    fn insert(&mut self, key: SharingKey, style: Rc<ComputedStyle>) {
+        if self.entries.len() == Self::SIZE {
+            self.entries.remove(0);
+        }
+        self.entries.push((key, style));
+    }
 }
 
 impl StyleComputer {
@@ -21,8 +87,15 @@ impl StyleComputer {
             stylesheets: Vec::new(),
             selector_index: None,
             computed_styles: HashMap::new(),
+            pseudo_styles: HashMap::new(),
+            dirty: HashMap::new(),
+            explicit_inheritance: HashSet::new(),
             viewport_width,
             viewport_height,
+            media: MediaEnvironment::screen(viewport_width, viewport_height),
+            root_font_size: 16.0,
+            root_line_height: 16.0 * 1.2,
+            timeline: AnimationTimeline::new(),
         }
     }
 
@@ -37,138 +110,654 @@ impl StyleComputer {
         self.selector_index = None;
     }
 
-    pub fn set_viewport(&mut self, width: f32, height: f32) {
+    /// Returns true when the new size changes which `@media` rules apply,
+    /// meaning styles must be recomputed
+    pub fn set_viewport(&mut self, width: f32, height: f32) -> bool {
         self.viewport_width = width;
         self.viewport_height = height;
+        let media = MediaEnvironment {
+            width,
+            height,
+            ..self.media.clone()
+        };
+        self.set_media_environment(media)
+    }
+
+    pub fn media_environment(&self) -> &MediaEnvironment {
+        &self.media
+    }
+
+    /// Switches media type or user preferences. Returns true when a
+    /// different set of rules now applies.
+    pub fn set_media_environment(&mut self, media: MediaEnvironment) -> bool {
+        let changed = self
+            .stylesheets
+            .iter()
+            .flat_map(|stylesheet| &stylesheet.rules)
+            .filter(|rule| !rule.media.is_empty())
+            .any(|rule| rule.matches_media(&self.media) != rule.matches_media(&media));
+        self.media = media;
+        if changed {
+            self.selector_index = None;
+        }
+        changed
     }
 
     /// Ensure the selector index is built
     fn ensure_index(&mut self) {
         if self.selector_index.is_none() {
-            self.selector_index = Some(SelectorIndex::build(&self.stylesheets));
+            self.selector_index = Some(SelectorIndex::build(&self.stylesheets, &self.media));
         }
     }
 
+    /// Computes the styles of the whole document from scratch
     pub fn compute_styles(&mut self, document: &Document) {
         self.computed_styles.clear();
+        self.pseudo_styles.clear();
+        self.dirty.clear();
+        self.explicit_inheritance.clear();
+        self.invalidate_subtree(document, document.root);
+        self.recalc_styles(document);
+    }
+
+    /// Marks the style of `node_id` out of date. Its descendants are
+    /// restyled too only if what they inherit from it changes.
+    pub fn invalidate_style(&mut self, document: &Document, node_id: NodeId) {
+        self.mark_dirty(document, node_id, RestyleFlags::STYLE);
+    }
+
+    /// Marks the styles of `node_id` and everything inside it out of date
+    pub fn invalidate_subtree(&mut self, document: &Document, node_id: NodeId) {
+        self.mark_dirty(document, node_id, RestyleFlags::SUBTREE);
+    }
+
+    /// Marks the elements that may match differently now that the
+    /// attribute `name` of `node_id` changed from `old_value`
+    pub fn invalidate_attribute(&mut self, document: &Document, node_id: NodeId, name: &str, old_value: Option<&str>) {
+        let Some(element) = document.get_node(node_id).and_then(|node| node.as_element()) else {
+            return;
+        };
+        let name = name.to_ascii_lowercase();
+        // These style the element whatever the selectors
+        if name == "style" || name == "href" || PRESENTATIONAL_ATTRIBUTES.contains(&name.as_str()) {
+            self.invalidate_style(document, node_id);
+        }
+
+        let mut features = vec![Feature::Attribute(name.clone())];
+        match name.as_str() {
+            "class" => {
+                let old: Vec<&str> = old_value.unwrap_or_default().split_whitespace().collect();
+                let new = element.classes();
+                let removed = old.iter().filter(|class| !new.iter().any(|new| new == *class));
+                let added = new.iter().filter(|class| !old.contains(&class.as_str()));
+                features.extend(removed.map(|class| Feature::Class(class.to_string())));
+                features.extend(added.map(|class| Feature::Class(class.clone())));
+            }
+            "id" => features.extend(old_value.into_iter().chain(element.id()).map(|id| Feature::Id(id.to_string()))),
+            _ => {}
+        }
+        self.invalidate_features(document, node_id, &features);
+    }
+
+    /// Marks the elements that may match differently now that `node_id`
+    /// gained or lost the states in `flags`
+    pub fn invalidate_state(&mut self, document: &Document, node_id: NodeId, flags: ElementState) {
+        let features: Vec<Feature> = [
+            ElementState::HOVER,
+            ElementState::ACTIVE,
+            ElementState::FOCUS,
+            ElementState::FOCUS_VISIBLE,
+            ElementState::FOCUS_WITHIN,
+            ElementState::VISITED,
+        ]
+        .into_iter()
+        .filter(|&flag| flags.intersects(flag))
+        .map(Feature::State)
+        .collect();
+        self.invalidate_features(document, node_id, &features);
+    }
+
+    /// Marks the elements that may be styled differently now that the
+    /// children of `parent` changed, `added` being the new ones
+    pub fn invalidate_children(&mut self, document: &Document, parent: NodeId, added: &[NodeId]) {
         self.ensure_index();
-        self.compute_node_styles(document, document.root, None);
+        let Some(invalidation) = self.selector_index.as_ref().map(SelectorIndex::invalidation) else {
+            return;
+        };
+        if invalidation.uses_relative_selectors() {
+            self.invalidate_subtree(document, document.root);
+        } else if invalidation.depends_on_structure() {
+            // Structural pseudo-classes and sibling combinators look at
+            // the other children
+            self.invalidate_subtree(document, parent);
+        } else {
+            for &child in added {
+                self.invalidate_subtree(document, child);
+            }
+        }
     }
 
-    fn compute_node_styles(
+    fn invalidate_features(&mut self, document: &Document, node_id: NodeId, features: &[Feature]) {
+        self.ensure_index();
+        let mut marks = Vec::new();
+        if let Some(index) = &self.selector_index {
+            for set in features.iter().filter_map(|feature| index.invalidation().get(feature)) {
+                set.collect(document, node_id, &mut marks);
+            }
+        }
+        for (id, flags) in marks {
+            self.mark_dirty(document, id, flags);
+        }
+    }
+
+    /// Flags `node_id` and its ancestors, so that recalc finds it
+    fn mark_dirty(&mut self, document: &Document, node_id: NodeId, flags: RestyleFlags) {
+        self.dirty.entry(node_id).or_default().insert(flags);
+        let mut current = document.parent(node_id);
+        while let Some(id) = current {
+            let ancestor = self.dirty.entry(id).or_default();
+            if ancestor.contains(RestyleFlags::DESCENDANTS) {
+                break;
+            }
+            ancestor.insert(RestyleFlags::DESCENDANTS);
+            current = document.parent(id);
+        }
+    }
+
+    /// Whether some style is marked out of date
+    pub fn needs_recalc(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    /// Brings the styles marked out of date up to date. Returns the
+    /// elements whose style or pseudo-element styles changed.
+    pub fn recalc_styles(&mut self, document: &Document) -> Vec<NodeId> {
+        let mut changed = Vec::new();
+        if self.dirty.is_empty() {
+            return changed;
+        }
+        self.ensure_index();
+        self.recalc_node(document, document.root, None, false, &mut SharingCache::default(), &mut changed);
+        // Anything left was marked inside a subtree that left the document
+        self.dirty.clear();
+        changed
+    }
+
+    /// Drops computed styles for a subtree that left the document
+    pub fn forget_subtree(&mut self, document: &Document, root: NodeId) {
+        self.computed_styles.remove(&root);
+        self.dirty.remove(&root);
+        self.explicit_inheritance.remove(&root);
+        self.timeline.forget(root);
+        for pseudo in [PseudoElement::Before, PseudoElement::After, PseudoElement::Marker] {
+            self.pseudo_styles.remove(&(root, pseudo));
+        }
+        for &child in document.children(root) {
+            self.forget_subtree(document, child);
+        }
+    }
+
+    /// Restyles `node_id` if it is marked or `force`d, then those of its
+    /// children that are marked or that its new style affects
+    fn recalc_node(
         &mut self,
         document: &Document,
         node_id: NodeId,
-        parent_style: Option<&ComputedStyle>,
+        parent_style: Option<&Rc<ComputedStyle>>,
+        force: bool,
+        siblings: &mut SharingCache,
+        changed: &mut Vec<NodeId>,
     ) {
-        let node = match document.get_node(node_id) {
-            Some(n) => n,
-            None => return,
+        let flags = self.dirty.remove(&node_id).unwrap_or_default();
+        let Some(node) = document.get_node(node_id) else {
+            return;
         };
+        let old = self.computed_styles.get(&node_id).cloned();
+        let mut force_children = force || flags.contains(RestyleFlags::SUBTREE);
+        // Whether children inheriting non-inherited properties are affected
+        let mut force_explicit = false;
 
-        let style = if let Some(element) = node.as_element() {
-            let mut style = ComputedStyle::for_tag(&element.tag_name);
+        let style = match node.as_element() {
+            Some(element) if force || flags.intersects(RestyleFlags::STYLE | RestyleFlags::SUBTREE) || old.is_none() => {
+                let (style, pseudo_changed) =
+                    self.compute_element_style(document, node_id, element, parent_style, siblings);
+                match old {
+                    Some(old) if *old == *style => {
+                        if pseudo_changed {
+                            changed.push(node_id);
+                        }
+                        old
+                    }
+                    old => {
+                        force_children |= !old.is_some_and(|old| properties::inherited_equal(&old, &style));
+                        force_explicit = true;
+                        changed.push(node_id);
+                        self.computed_styles.insert(node_id, Rc::clone(&style));
+                        style
+                    }
+                }
+            }
+            Some(_) => old.unwrap_or_default(),
+            // Other nodes take their parent's style
+            None => {
+                let style = parent_style.cloned().unwrap_or_default();
+                self.computed_styles.insert(node_id, Rc::clone(&style));
+                style
+            }
+        };
 
-            if let Some(parent) = parent_style {
-                style.color = parent.color;
-                style.font_size = parent.font_size;
-                style.line_height = parent.line_height;
+        if !force_children && !force_explicit && !flags.contains(RestyleFlags::DESCENDANTS) {
+            return;
+        }
+        let mut cache = SharingCache::default();
+        for &child in document.children(node_id) {
+            // Text nodes share their parent's style, whatever changed
+            let child_force = force_children
+                || (force_explicit && !document.get_node(child).is_some_and(|child| child.is_element()))
+                || (force_explicit && self.explicit_inheritance.contains(&child));
+            if child_force || self.dirty.contains_key(&child) {
+                self.recalc_node(document, child, Some(&style), child_force, &mut cache, changed);
             }
+        }
+    }
 
-            // Use the selector index for O(1) candidate lookup
-            let mut matching_rules: Vec<(Specificity, usize, &IndexedRule)> = Vec::new();
-
-            if let Some(index) = &self.selector_index {
-                let candidates = index.get_candidate_rules(
-                    element.id(),
-                    element.classes(),
-                    &element.tag_name,
-                );
-
-                for indexed_rule in candidates {
-                    // Verify the selector actually matches (handles compound selectors)
-                    if indexed_rule.selector.matches(document, node_id) {
-                        matching_rules.push((
-                            indexed_rule.specificity,
-                            indexed_rule.source_order,
-                            indexed_rule,
-                        ));
-                    }
+    /// Cascades the style of `element`, or shares that of a sibling bound
+    /// to come out the same, and computes its pseudo-elements' styles.
+    /// Also returns whether the pseudo-element styles changed.
+    fn compute_element_style(
+        &mut self,
+        document: &Document,
+        node_id: NodeId,
+        element: &ElementData,
+        parent_style: Option<&Rc<ComputedStyle>>,
+        siblings: &mut SharingCache,
+    ) -> (Rc<ComputedStyle>, bool) {
+        let parent_style = parent_style.map(|style| &**style);
+
+        // Use the selector index for O(1) candidate lookup
+        let mut matching_rules: Vec<(Origin, usize, Specificity, usize, &IndexedRule)> = Vec::new();
+        let mut pseudo_rules: Vec<(PseudoElement, Origin, usize, Specificity, usize, &IndexedRule)> = Vec::new();
+
+        if let Some(index) = &self.selector_index {
+            let candidates = index.get_candidate_rules(
+                element.id(),
+                element.classes(),
+                &element.tag_name,
+            );
+
+            for indexed_rule in candidates {
+                // Verify the selector actually matches (handles compound selectors)
+                let selector = &indexed_rule.selector.complex;
+                match selector.pseudo_element {
+                    None if selector.matches(document, node_id) => matching_rules.push((
+                        indexed_rule.origin,
+                        indexed_rule.layer,
+                        indexed_rule.specificity,
+                        indexed_rule.source_order,
+                        indexed_rule,
+                    )),
+                    Some(pseudo) if selector.matches_originating_element(document, node_id) => pseudo_rules.push((
+                        pseudo,
+                        indexed_rule.origin,
+                        indexed_rule.layer,
+                        indexed_rule.specificity,
+                        indexed_rule.source_order,
+                        indexed_rule,
+                    )),
+                    _ => {}
                 }
             }
+        }
 
-            // Sort by specificity, then by source order for stable ordering
-            matching_rules.sort_by_key(|(spec, order, _)| (*spec, *order));
-
-            let parent_font_size = parent_style.map(|p| p.font_size).unwrap_or(16.0);
-
-            // Apply presentational HTML attributes (lowest priority, before CSS)
-            self.apply_presentational_attributes(&mut style, element, parent_font_size);
-
-            // First apply all non-important declarations
-            for (_, _, indexed_rule) in &matching_rules {
-                for decl in &indexed_rule.rule.declarations {
-                    if !decl.important {
-                        style.apply_value(
-                            &decl.property,
-                            &decl.value,
-                            parent_font_size,
-                            self.viewport_width,
-                            self.viewport_height,
-                        );
-                    }
+        // Sort by origin, cascade layer, specificity, then source order
+        matching_rules.sort_by_key(|(origin, layer, spec, order, _)| (*origin, *layer, *spec, *order));
+        pseudo_rules.sort_by_key(|(_, origin, layer, spec, order, _)| (*origin, *layer, *spec, *order));
+
+        let is_root = document
+            .parent(node_id)
+            .and_then(|parent| document.get_node(parent))
+            .and_then(|parent| parent.as_element())
+            .is_none();
+        let is_link = element.tag_name == "a" && element.get_attribute("href").is_some();
+        let ctx = self.length_context(parent_style, is_root);
+
+        // The style attribute's declarations are the author's, above
+        // any selector
+        let inline_styles = element
+            .get_attribute("style")
+            .map(|style_attr| super::parser::parse_css(&format!("* {{ {} }}", style_attr)));
+        let inline: Vec<&Declaration> =
+            inline_styles.iter().flat_map(|sheet| &sheet.rules).flat_map(|rule| &rule.declarations).collect();
+        let rules: Vec<&IndexedRule> = matching_rules.iter().map(|(_, _, _, _, rule)| *rule).collect();
+        let declarations = cascade_order(&rules, &inline);
+
+        let inherits_explicitly = declarations.iter().any(|decl| {
+            CssWideKeyword::from_value(&decl.value) == Some(CssWideKeyword::Inherit)
+                && !properties::is_inherited(&decl.property)
+        });
+        if inherits_explicitly {
+            self.explicit_inheritance.insert(node_id);
+        } else {
+            self.explicit_inheritance.remove(&node_id);
+        }
+
+        // Siblings matching the same rules share a style, unless something
+        // only this element has goes into it
+        let sharing_key = (!is_root && inline_styles.is_none() && !self.timeline.contains(node_id)).then(|| SharingKey {
+            tag: element.tag_name.clone(),
+            rules: rules.iter().map(|rule| rule.source_order).collect(),
+            attributes: PRESENTATIONAL_ATTRIBUTES
+                .iter()
+                .map(|name| element.get_attribute(name).map(str::to_string))
+                .collect(),
+            is_link,
+        });
+        let shared = sharing_key.as_ref().and_then(|key| siblings.get(key));
+
+        let style = match shared {
+            Some(style) => style,
+            None => {
+                let mut style = ComputedStyle::default();
+                if let Some(parent) = parent_style {
+                    inherit(&mut style, parent);
                 }
+                style.apply_tag_defaults(&element.tag_name);
+
+                // `revert` goes back to what the user agent and user sheets
+                // give, before any presentational attributes or author sheets
+                let user_agent = declarations
+                    .iter()
+                    .any(|decl| CssWideKeyword::from_value(&decl.value) == Some(CssWideKeyword::Revert))
+                    .then(|| {
+                        let mut user_agent = style.clone();
+                        let rules: Vec<&IndexedRule> =
+                            rules.iter().copied().filter(|rule| rule.origin != Origin::Author).collect();
+                        let defaulting = Defaulting {
+                            parent: parent_style,
+                            user_agent: None,
+                        };
+                        self.apply_declarations(&mut user_agent, &cascade_order(&rules, &[]), ctx, is_root, &defaulting);
+                        user_agent
+                    });
+
+                // Apply presentational HTML attributes (lowest priority, before CSS)
+                self.apply_presentational_attributes(&mut style, element, ctx.font_size);
+
+                let defaulting = Defaulting {
+                    parent: parent_style,
+                    user_agent: user_agent.as_ref(),
+                };
+                self.apply_declarations(&mut style, &declarations, ctx, is_root, &defaulting);
+                style.clear_unstyled_border_widths();
+
+                // Transitions and animations override the cascaded values
+                let animated = !style.animation.is_none() || style.transition.is_active();
+                if animated || self.timeline.contains(node_id) {
+                    let animations = self.keyframe_styles(&style, ctx, is_root, &defaulting);
+                    let previous = self.computed_styles.get(&node_id).map(|style| &**style);
+                    self.timeline.update(node_id, previous, &mut style, &animations);
+                }
+
+                if self.media.forced_colors {
+                    style.force_colors(parent_style, is_link, is_root);
+                }
+
+                let style = Rc::new(style);
+                // Running animations make a style this element's own
+                if let Some(key) = sharing_key.filter(|_| !animated) {
+                    siblings.insert(key, Rc::clone(&style));
+                }
+                style
             }
+        };
 
-            // Then apply all !important declarations (they override normal ones)
-            for (_, _, indexed_rule) in &matching_rules {
-                for decl in &indexed_rule.rule.declarations {
-                    if decl.important {
-                        style.apply_value(
-                            &decl.property,
-                            &decl.value,
-                            parent_font_size,
-                            self.viewport_width,
-                            self.viewport_height,
-                        );
-                    }
+        if is_root {
+            self.root_font_size = style.font_size;
+            self.root_line_height = style.line_height * style.font_size;
+        }
+
+        // Pseudo-elements inherit from their element. List items always
+        // get a marker; the others only when some rule styles them.
+        let mut pseudo_changed = false;
+        let pseudo_ctx = self.length_context(Some(&style), false);
+        let pseudo_initial = ComputedStyle {
+            display: Display::Inline,
+            ..ComputedStyle::default()
+        };
+        for pseudo in [PseudoElement::Before, PseudoElement::After, PseudoElement::Marker] {
+            let rules: Vec<&IndexedRule> = pseudo_rules
+                .iter()
+                .filter(|(p, _, _, _, _, _)| *p == pseudo)
+                .map(|(_, _, _, _, _, rule)| *rule)
+                .collect();
+            let is_marker = pseudo == PseudoElement::Marker && style.display == Display::ListItem;
+            if rules.is_empty() && !is_marker {
+                pseudo_changed |= self.pseudo_styles.remove(&(node_id, pseudo)).is_some();
+                continue;
+            }
+            let mut pseudo_style = pseudo_initial.clone();
+            inherit(&mut pseudo_style, &style);
+            let defaulting = Defaulting {
+                parent: Some(&style),
+                user_agent: None,
+            };
+            self.apply_declarations(&mut pseudo_style, &cascade_order(&rules, &[]), pseudo_ctx, false, &defaulting);
+            pseudo_style.clear_unstyled_border_widths();
+            if self.media.forced_colors {
+                pseudo_style.force_colors(Some(&style), false, false);
+            }
+            match self.pseudo_styles.get(&(node_id, pseudo)) {
+                Some(old) if **old == pseudo_style => {}
+                _ => {
+                    pseudo_changed = true;
+                    self.pseudo_styles.insert((node_id, pseudo), Rc::new(pseudo_style));
                 }
             }
+        }
 
-            if let Some(style_attr) = element.get_attribute("style") {
-                let inline_styles = super::parser::parse_css(&format!("* {{ {} }}", style_attr));
-                for rule in &inline_styles.rules {
-                    for decl in &rule.declarations {
-                        style.apply_value(
-                            &decl.property,
-                            &decl.value,
-                            parent_font_size,
-                            self.viewport_width,
-                            self.viewport_height,
-                        );
+        (style, pseudo_changed)
+    }
+
+    /// What relative lengths resolve against for a child of `parent`. The
+    /// root element resolves `rem` and `rlh` against initial values.
+    fn length_context(&self, parent: Option<&ComputedStyle>, is_root: bool) -> LengthContext {
+        let mut ctx = LengthContext::new(self.viewport_width, self.viewport_height);
+        if let Some(parent) = parent {
+            ctx.font_size = parent.font_size;
+            ctx.line_height = parent.line_height * parent.font_size;
+        }
+        if !is_root {
+            ctx.root_font_size = self.root_font_size;
+            ctx.root_line_height = self.root_line_height;
+        }
+        ctx
+    }
+
+    /// Applies declarations, given in cascade order, to `style`. `ctx` is
+    /// the context for the parent's font size; other properties' lengths
+    /// resolve against the element's own once its font size is known.
+    fn apply_declarations(
+        &self,
+        style: &mut ComputedStyle,
+        declarations: &[&Declaration],
+        mut ctx: LengthContext,
+        is_root: bool,
+        defaulting: &Defaulting,
+    ) {
+        self.apply_custom_properties(style, declarations, defaulting);
+
+        // var() is substituted now; a declaration whose references can't
+        // be resolved is invalid at computed-value time, and the property
+        // is unset even if earlier declarations set it. A shorthand using
+        // var() is only split into its longhands now.
+        let mut resolved: Vec<(&str, Option<Cow<Value>>)> = Vec::new();
+        for decl in declarations.iter().filter(|decl| !decl.property.starts_with("--")) {
+            let value = resolve_value(&decl.value, &style.custom_properties);
+            match shorthand(&decl.property) {
+                Some(shorthand) if matches!(decl.value, Value::Unresolved(_)) => {
+                    match value.and_then(|value| shorthand.expand(&value)) {
+                        Some(longhands) => resolved.extend(
+                            longhands.into_iter().map(|(longhand, value)| (longhand, Some(Cow::Owned(value)))),
+                        ),
+                        None => resolved.extend(shorthand.longhands.iter().map(|longhand| (*longhand, None))),
                     }
                 }
+                _ => resolved.push((decl.property.as_str(), value)),
             }
+        }
+        let mut invalid: HashMap<&str, bool> = HashMap::new();
+        for (property, value) in &resolved {
+            invalid.insert(property, value.is_none());
+        }
 
-            style
-        } else {
-            parent_style.cloned().unwrap_or_default()
-        };
+        // Font size first, as em and lh resolve against it, then line
+        // height for lh and the color for currentcolor; the sort is stable,
+        // keeping cascade order within each group
+        resolved.sort_by_key(|(property, _)| property_priority(property));
 
-        self.computed_styles.insert(node_id, style.clone());
+        let unset = Value::Keyword("unset".to_string());
+        let mut priority = 0;
+        for (property, value) in &resolved {
+            while priority < property_priority(property) {
+                priority += 1;
+                if priority == 1 {
+                    ctx.font_size = style.font_size;
+                    if is_root {
+                        ctx.root_font_size = style.font_size;
+                    }
+                } else {
+                    ctx.line_height = style.line_height * style.font_size;
+                    if is_root {
+                        ctx.root_line_height = ctx.line_height;
+                    }
+                }
+            }
+            let value = match value {
+                Some(value) if !invalid[property] => value,
+                _ => {
+                    style.apply_value(property, &unset, &ctx, defaulting);
+                    continue;
+                }
+            };
+            // Only substitution gives owned values. One the property rejects
+            // is invalid at computed-value time too, so unset it first
+            // rather than leave an earlier declaration's value in place.
+            if let Cow::Owned(_) = value {
+                style.apply_value(property, &unset, &ctx, defaulting);
+            }
+            style.apply_value(property, value, &ctx, defaulting);
+        }
+    }
 
-        let children: Vec<_> = document.children(node_id).to_vec();
-        for child_id in children {
-            self.compute_node_styles(document, child_id, Some(&style));
+    /// Applies `--name` declarations on top of the inherited custom
+    /// properties, resolving references between them
+    fn apply_custom_properties(
+        &self,
+        style: &mut ComputedStyle,
+        declarations: &[&Declaration],
+        defaulting: &Defaulting,
+    ) {
+        let custom: Vec<(&str, &str)> = declarations
+            .iter()
+            .filter_map(|decl| match &decl.value {
+                Value::Unresolved(raw) if decl.property.starts_with("--") => Some((decl.property.as_str(), raw.as_str())),
+                _ => None,
+            })
+            .collect();
+        if custom.is_empty() {
+            return;
         }
+
+        let properties = Rc::make_mut(&mut style.custom_properties);
+        let mut declared = Vec::new();
+        for (name, raw) in custom {
+            match raw.to_ascii_lowercase().as_str() {
+                // Custom properties inherit, so both take the parent's value,
+                // replacing any the element declared earlier
+                "inherit" | "unset" => {
+                    match defaulting.parent.and_then(|parent| parent.custom_properties.get(name)) {
+                        Some(inherited) => properties.insert(name.to_string(), inherited.clone()),
+                        None => properties.remove(name),
+                    };
+                    declared.retain(|declared| declared != name);
+                }
+                "initial" => {
+                    properties.remove(name);
+                    declared.retain(|declared| declared != name);
+                }
+                _ => {
+                    properties.insert(name.to_string(), raw.to_string());
+                    declared.push(name.to_string());
+                }
+            }
+        }
+        resolve_custom_properties(properties, &declared);
+    }
+
+    /// An element's named animations, with their keyframes computed on top
+    /// of its cascaded `style`
+    fn keyframe_styles(
+        &self,
+        style: &ComputedStyle,
+        ctx: LengthContext,
+        is_root: bool,
+        defaulting: &Defaulting,
+    ) -> Vec<(Animation, Vec<ComputedKeyframe>)> {
+        style
+            .animation
+            .list()
+            .into_iter()
+            .filter_map(|animation| {
+                let keyframes = self.keyframes(&animation.name)?;
+                let frames = keyframes
+                    .frames
+                    .iter()
+                    .map(|frame| {
+                        let declarations: Vec<&Declaration> = frame
+                            .declarations
+                            .iter()
+                            .filter(|decl| !decl.property.starts_with("animation") && !decl.property.starts_with("transition"))
+                            .collect();
+                        let mut frame_style = style.clone();
+                        self.apply_declarations(&mut frame_style, &declarations, ctx, is_root, defaulting);
+                        ComputedKeyframe::new(frame.offset, &frame.declarations, frame_style)
+                    })
+                    .collect();
+                Some((animation, frames))
+            })
+            .collect()
+    }
+
+    /// The last `@keyframes` rule named `name` whose media matches
+    fn keyframes(&self, name: &str) -> Option<&Keyframes> {
+        self.stylesheets
+            .iter()
+            .rev()
+            .flat_map(|stylesheet| stylesheet.keyframes.iter().rev())
+            .find(|keyframes| keyframes.name == name && keyframes.matches_media(&self.media))
+    }
+
+    pub fn timeline(&self) -> &AnimationTimeline {
+        &self.timeline
     }
 
-    pub fn get_style(&self, node_id: NodeId) -> Option<&ComputedStyle> {
+    pub fn timeline_mut(&mut self) -> &mut AnimationTimeline {
+        &mut self.timeline
+    }
+
+    /// The style computed for `node_id`, which other nodes may share
+    pub fn get_style(&self, node_id: NodeId) -> Option<&Rc<ComputedStyle>> {
         self.computed_styles.get(&node_id)
     }
 
+    /// The style of a pseudo-element `node_id` generates, if it has one
+    pub fn get_pseudo_style(&self, node_id: NodeId, pseudo: PseudoElement) -> Option<&Rc<ComputedStyle>> {
+        self.pseudo_styles.get(&(node_id, pseudo))
+    }
+
+    /// The style of `node_id` for it alone, copied if it was shared
     pub fn get_style_mut(&mut self, node_id: NodeId) -> Option<&mut ComputedStyle> {
-        self.computed_styles.get_mut(&node_id)
+        self.computed_styles.get_mut(&node_id).map(Rc::make_mut)
     }
 
     /// Apply HTML presentational attributes to the computed style.
@@ -176,110 +765,178 @@ impl StyleComputer {
     fn apply_presentational_attributes(
         &self,
         style: &mut ComputedStyle,
-        element: &crate::dom::node::ElementData,
+        element: &ElementData,
         parent_font_size: f32,
     ) {
         use crate::render::painter::Color;
 
-        // bgcolor attribute (used by HN for table backgrounds)
-        if let Some(bgcolor) = element.attributes.get("bgcolor") {
-            if let Some(color) = Color::from_hex(bgcolor) {
-                style.background_color = color;
-            } else {
-                // Try as named color
-                let value = super::stylesheet::Value::Keyword(bgcolor.to_string());
-                if let Some(color) = value.to_color() {
-                    style.background_color = color;
+        for name in PRESENTATIONAL_ATTRIBUTES {
+            let Some(value) = element.attributes.get(name) else {
+                continue;
+            };
+            match name {
+                // Used by HN for table backgrounds
+                "bgcolor" => {
+                    if let Some(color) = Color::from_hex(value) {
+                        style.background_color = color;
+                    } else {
+                        // Try as named color
+                        let value = super::stylesheet::Value::Keyword(value.to_string());
+                        if let Some(color) = value.to_color() {
+                            style.background_color = color;
+                        }
+                    }
                 }
-            }
-        }
 
-        // width attribute
-        if let Some(width) = element.attributes.get("width") {
-            if let Ok(px) = width.trim_end_matches("px").parse::<f32>() {
-                style.width = Some(super::computed::LengthOrPercentage::Px(px));
-            } else if let Ok(percent) = width.trim_end_matches('%').parse::<f32>() {
-                style.width = Some(super::computed::LengthOrPercentage::Percent(percent));
-            }
-        }
+                "width" => {
+                    if let Ok(px) = value.trim_end_matches("px").parse::<f32>() {
+                        style.width = Some(super::computed::LengthOrPercentage::Px(px));
+                    } else if let Ok(percent) = value.trim_end_matches('%').parse::<f32>() {
+                        style.width = Some(super::computed::LengthOrPercentage::Percent(percent));
+                    }
+                }
 
-        // height attribute
-        if let Some(height) = element.attributes.get("height") {
-            if let Ok(px) = height.trim_end_matches("px").parse::<f32>() {
-                style.height = Some(super::computed::LengthOrPercentage::Px(px));
-            }
-        }
+                "height" => {
+                    if let Ok(px) = value.trim_end_matches("px").parse::<f32>() {
+                        style.height = Some(super::computed::LengthOrPercentage::Px(px));
+                    }
+                }
 
-        // border attribute
-        if let Some(border) = element.attributes.get("border") {
-            if let Ok(px) = border.parse::<f32>() {
-                style.border_top_width = px;
-                style.border_right_width = px;
-                style.border_bottom_width = px;
-                style.border_left_width = px;
-                // Also set border color to black if not set
-                if style.border_color == Color::TRANSPARENT {
-                    style.border_color = Color::BLACK;
+                "border" => {
+                    if let Ok(px) = value.parse::<f32>() {
+                        style.border_top_width = px;
+                        style.border_right_width = px;
+                        style.border_bottom_width = px;
+                        style.border_left_width = px;
+                        // Give the widths a style to keep them, unless one is set
+                        for side_style in [
+                            &mut style.border_top_style,
+                            &mut style.border_right_style,
+                            &mut style.border_bottom_style,
+                            &mut style.border_left_style,
+                        ] {
+                            if *side_style == BorderStyle::None {
+                                *side_style = BorderStyle::Outset;
+                            }
+                        }
+                    }
                 }
-            }
-        }
 
-        // cellpadding attribute (for tables)
-        if let Some(cellpadding) = element.attributes.get("cellpadding") {
-            if let Ok(px) = cellpadding.parse::<f32>() {
-                style.padding_top = px;
-                style.padding_right = px;
-                style.padding_bottom = px;
-                style.padding_left = px;
-            }
-        }
+                // For tables
+                "cellpadding" => {
+                    if let Ok(px) = value.parse::<f32>() {
+                        style.padding_top = px;
+                        style.padding_right = px;
+                        style.padding_bottom = px;
+                        style.padding_left = px;
+                    }
+                }
+
+                // For tables
+                "cellspacing" => {
+                    if let Ok(px) = value.parse::<f32>() {
+                        style.border_spacing = px;
+                    }
+                }
+
+                // Vertical alignment in table cells
+                "valign" => {
+                    style.vertical_align = match value.to_lowercase().as_str() {
+                        "top" => super::computed::VerticalAlign::Top,
+                        "middle" => super::computed::VerticalAlign::Middle,
+                        "bottom" => super::computed::VerticalAlign::Bottom,
+                        "baseline" => super::computed::VerticalAlign::Baseline,
+                        _ => style.vertical_align,
+                    };
+                }
+
+                // Text alignment
+                "align" => {
+                    style.text_align = match value.to_lowercase().as_str() {
+                        "left" => super::computed::TextAlign::Left,
+                        "center" => super::computed::TextAlign::Center,
+                        "right" => super::computed::TextAlign::Right,
+                        "justify" => super::computed::TextAlign::Justify,
+                        _ => style.text_align,
+                    };
+                }
+
+                // For font elements
+                "color" => {
+                    if let Some(color) = Color::from_hex(value) {
+                        style.color = color;
+                    } else {
+                        let value = super::stylesheet::Value::Keyword(value.to_string());
+                        if let Some(color) = value.to_color() {
+                            style.color = color;
+                        }
+                    }
+                }
 
-        // cellspacing attribute (for tables)
-        if let Some(cellspacing) = element.attributes.get("cellspacing") {
-            if let Ok(px) = cellspacing.parse::<f32>() {
-                style.border_spacing = px;
+                _ => {}
             }
         }
+    }
+}
 
-        // valign attribute (vertical alignment in table cells)
-        if let Some(valign) = element.attributes.get("valign") {
-            style.vertical_align = match valign.to_lowercase().as_str() {
-                "top" => super::computed::VerticalAlign::Top,
-                "middle" => super::computed::VerticalAlign::Middle,
-                "bottom" => super::computed::VerticalAlign::Bottom,
-                "baseline" => super::computed::VerticalAlign::Baseline,
-                _ => style.vertical_align,
-            };
-        }
+/// Copies the inherited properties from `parent`
+fn inherit(style: &mut ComputedStyle, parent: &ComputedStyle) {
+    properties::inherit_all(style, parent);
+    style.custom_properties = Rc::clone(&parent.custom_properties);
+}
 
-        // align attribute (text alignment)
-        if let Some(align) = element.attributes.get("align") {
-            style.text_align = match align.to_lowercase().as_str() {
-                "left" => super::computed::TextAlign::Left,
-                "center" => super::computed::TextAlign::Center,
-                "right" => super::computed::TextAlign::Right,
-                "justify" => super::computed::TextAlign::Justify,
-                _ => style.text_align,
-            };
+/// When a property is computed: font sizes first, then line heights and
+/// the color, which `currentcolor` stands for, then everything else
+fn property_priority(property: &str) -> u8 {
+    match property {
+        "font-size" => 0,
+        "line-height" | "color" => 1,
+        _ => 2,
+    }
+}
+
+/// The declarations of sorted matching rules and the style attribute's
+/// `inline` ones in cascade order: normal declarations, then `!important`
+/// ones, for which the order of origins and of cascade layers is reversed.
+/// Inline declarations come after the author's rules of the same importance.
+fn cascade_order<'a>(rules: &[&'a IndexedRule], inline: &[&'a Declaration]) -> Vec<&'a Declaration> {
+    let mut declarations: Vec<&Declaration> =
+        rules.iter().flat_map(|rule| &rule.rule.declarations).filter(|d| !d.important).collect();
+    declarations.extend(inline.iter().filter(|d| !d.important));
+    for origin in [Origin::Author, Origin::User, Origin::UserAgent] {
+        let mut important: Vec<&IndexedRule> = rules.iter().copied().filter(|rule| rule.origin == origin).collect();
+        important.sort_by_key(|rule| std::cmp::Reverse(rule.layer));
+        declarations.extend(important.iter().flat_map(|rule| &rule.rule.declarations).filter(|d| d.important));
+        if origin == Origin::Author {
+            declarations.extend(inline.iter().filter(|d| d.important));
         }
+    }
+    declarations
+}
 
-        // color attribute (for font elements)
-        if let Some(color_attr) = element.attributes.get("color") {
-            if let Some(color) = Color::from_hex(color_attr) {
-                style.color = color;
-            } else {
-                let value = super::stylesheet::Value::Keyword(color_attr.to_string());
-                if let Some(color) = value.to_color() {
-                    style.color = color;
-                }
-            }
+/// A declared value with its `var()` references substituted; `None` when
+/// it is invalid at computed-value time
+fn resolve_value<'a>(value: &'a Value, custom_properties: &CustomProperties) -> Option<Cow<'a, Value>> {
+    match value {
+        Value::Unresolved(raw) => {
+            let text = substitute(raw, &mut |name| custom_properties.get(name).cloned())?;
+            super::parser::parse_value(&text).map(Cow::Owned)
         }
+        value => Some(Cow::Borrowed(value)),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::css::animation::Invalidation;
+    use crate::css::background::{BackgroundImage, BackgroundSize, RepeatStyle};
+    use crate::css::computed::{
+        AlignItems, Content, ContentItem, FontWeight, LengthOrPercentage, ListStylePosition, ListStyleType, TextAlign,
+        TextDecorationLine, TextTransform, Visibility, WhiteSpace,
+    };
+    use crate::render::font::{FontFamily, FontStretch, FontStyle};
+    use crate::render::painter::{BorderStyle, Color, TextDecorationStyle};
     use crate::css::parse_css;
     use crate::dom::parse_html;
 
@@ -326,4 +983,776 @@ mod tests {
         assert_eq!(style.color.g, 0.0);
         assert_eq!(style.color.b, 0.0);
     }
+
+    #[test]
+    fn test_restyle_subtree_after_class_change() {
+        let html = r#"<html><body><div id="a"><p>One</p></div><div id="b"><p>Two</p></div></body></html>"#;
+        let mut doc = parse_html(html);
+
+        let stylesheet = Rc::new(parse_css(".red p { color: red; }"));
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(stylesheet);
+        computer.compute_styles(&doc);
+
+        let a = doc.get_element_by_id("a").unwrap();
+        let b = doc.get_element_by_id("b").unwrap();
+        doc.set_attribute(a, "class", "red");
+        doc.set_attribute(b, "class", "red");
+        computer.invalidate_attribute(&doc, a, "class", None);
+
+        // Only the paragraph the new class reaches is restyled
+        let paragraphs = doc.get_elements_by_tag_name("p");
+        assert_eq!(computer.recalc_styles(&doc), vec![paragraphs[0]]);
+        assert_eq!(computer.get_style(paragraphs[0]).unwrap().color.r, 1.0);
+        // Not invalidated, so still has its old style
+        assert_eq!(computer.get_style(paragraphs[1]).unwrap().color.r, 0.0);
+    }
+
+    #[test]
+    fn test_media_rules_follow_viewport() {
+        let doc = parse_html("<html><body><p>Hi</p></body></html>");
+        let css = "p { color: red; } @media (max-width: 600px) { p { color: blue; } }";
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        let p = doc.get_elements_by_tag_name("p")[0];
+        assert_eq!(computer.get_style(p).unwrap().color.r, 1.0);
+
+        assert!(!computer.set_viewport(700.0, 500.0));
+        assert!(computer.set_viewport(500.0, 500.0));
+        computer.compute_styles(&doc);
+        assert_eq!(computer.get_style(p).unwrap().color.b, 1.0);
+    }
+
+    #[test]
+    fn test_custom_properties_inherit_and_substitute() {
+        let html = r#"<html><body><div id="outer"><p id="inner" style="--pad: 6px">Hi</p></div></body></html>"#;
+        let doc = parse_html(html);
+        let css = r#"
+            html { --accent: #00ff00; --pad: 2px; }
+            #outer { --loop-a: var(--loop-b); --loop-b: var(--loop-a); }
+            p { color: var(--accent); padding: var(--pad) 4px; }
+            p { margin-top: 9px; margin-top: var(--missing); }
+            p { background-color: var(--loop-a, blue); }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        let p = doc.get_element_by_id("inner").unwrap();
+        let style = computer.get_style(p).unwrap();
+        assert_eq!(style.color.g, 1.0);
+        assert_eq!(style.padding_top, 6.0);
+        assert_eq!(style.padding_right, 4.0);
+        // Invalid at computed-value time acts as unset: the initial value,
+        // not 9px or the element default
+        assert_eq!(style.margin_top, 0.0);
+        // Cyclic properties are invalid, so the fallback applies
+        assert_eq!(style.background_color.b, 1.0);
+    }
+
+    #[test]
+    fn test_custom_property_keywords_and_failed_substitution() {
+        let html = r#"<html><body><div id="outer"><p id="inner" class="note">Hi</p></div></body></html>"#;
+        let doc = parse_html(html);
+        let css = r#"
+            #outer { --gap: 3px; color: #0000ff; }
+            p { --gap: 8px; --gap: inherit; --size: 5px; --size: unset; --tone: red; --tone: initial; }
+            p { padding-left: var(--gap); padding-right: var(--size, 7px); }
+            p { color: #ff0000; margin-left: 9px; }
+            #inner.note { color: var(--tone); margin-left: var(--word); --word: red; }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        let p = doc.get_element_by_id("inner").unwrap();
+        let style = computer.get_style(p).unwrap();
+        // inherit and unset take the parent's value over the element's own
+        assert_eq!(style.padding_left, 3.0);
+        assert_eq!(style.padding_right, 7.0);
+        // A failed substitution, or one giving a value the property
+        // rejects, unsets it instead of letting the earlier, less specific
+        // rule win: inherited for color, initial for margin
+        assert_eq!(style.color.r, 0.0);
+        assert_eq!(style.color.b, 1.0);
+        assert_eq!(style.margin_left, 0.0);
+    }
+
+    #[test]
+    fn test_math_functions_compute_and_resolve_in_layout() {
+        let doc = parse_html(r#"<html><body><div id="box">Hi</div></body></html>"#);
+        let css = r#"
+            #box {
+                font-size: clamp(10px, 2vw, 30px);
+                padding-left: calc(1em + 2px);
+                width: calc(100% - 20px);
+                height: max(2em, 10px);
+            }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        let div = doc.get_element_by_id("box").unwrap();
+        let style = computer.get_style(div).unwrap();
+        assert_eq!(style.font_size, 16.0);
+        assert_eq!(style.padding_left, 18.0);
+        assert_eq!(style.height, Some(LengthOrPercentage::Px(32.0)));
+        let width = style.width.as_ref().unwrap();
+        assert!(matches!(width, LengthOrPercentage::Calc(_)));
+        assert_eq!(width.to_px(300.0), 280.0);
+    }
+
+    #[test]
+    fn test_relative_units_use_root_and_viewport() {
+        let html = r#"<html><body><div id="box"><p id="text">Hi</p></div></body></html>"#;
+        let doc = parse_html(html);
+        let css = r#"
+            html { font-size: 20px; }
+            #box { font-size: 12pt; margin-left: 2rem; width: 10vmin; min-width: 50%; max-height: 2in; }
+            #text { font-size: 0.5rem; padding-left: 2ch; }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        let div = computer.get_style(doc.get_element_by_id("box").unwrap()).unwrap();
+        assert_eq!(div.font_size, 16.0);
+        assert_eq!(div.margin_left, 40.0);
+        assert_eq!(div.width, Some(LengthOrPercentage::Px(60.0)));
+        assert_eq!(div.min_width, Some(LengthOrPercentage::Percent(50.0)));
+        assert_eq!(div.max_height, Some(LengthOrPercentage::Px(192.0)));
+
+        let p = computer.get_style(doc.get_element_by_id("text").unwrap()).unwrap();
+        assert_eq!(p.font_size, 10.0);
+        // ch falls back to half the element's own font size
+        assert_eq!(p.padding_left, 10.0);
+    }
+
+    #[test]
+    fn test_inherited_properties() {
+        let html = r#"<html><body><div id="outer"><p id="inner">Hi</p></div></body></html>"#;
+        let doc = parse_html(html);
+        let css = r#"
+            #outer { font-weight: bold; white-space: pre; text-align: center; visibility: hidden; margin-left: 10px; }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        let p = computer.get_style(doc.get_element_by_id("inner").unwrap()).unwrap();
+        assert_eq!(p.font_weight, FontWeight::Bold);
+        assert_eq!(p.white_space, WhiteSpace::Pre);
+        assert_eq!(p.text_align, TextAlign::Center);
+        assert_eq!(p.visibility, Visibility::Hidden);
+        assert_eq!(p.margin_left, 0.0);
+    }
+
+    #[test]
+    fn test_css_wide_keywords() {
+        let html = r#"<html><body><div id="parent"><p id="a">A</p><p id="b">B</p><h1 id="c">C</h1></div></body></html>"#;
+        let doc = parse_html(html);
+        let css = r#"
+            #parent { color: red; margin-left: 10px; font-weight: bold; }
+            p { color: blue; margin-top: 5px; }
+            #a { margin-left: inherit; color: unset; margin-top: unset; }
+            #b { color: initial; font-weight: INITIAL; margin: inherit; }
+            h1 { font-size: 40px; margin-top: 0; }
+            #c { font-size: revert; margin-top: revert; color: revert; }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        let red = Color::rgb(255, 0, 0);
+        let a = computer.get_style(doc.get_element_by_id("a").unwrap()).unwrap();
+        assert_eq!(a.margin_left, 10.0);
+        // unset inherits inherited properties and resets the others
+        assert_eq!(a.color, red);
+        assert_eq!(a.margin_top, 0.0);
+
+        let b = computer.get_style(doc.get_element_by_id("b").unwrap()).unwrap();
+        assert_eq!(b.color, Color::BLACK);
+        assert_eq!(b.font_weight, FontWeight::Normal);
+        // inherit on a shorthand inherits each of its properties
+        assert_eq!(b.margin_left, 10.0);
+        assert_eq!(b.margin_top, 0.0);
+
+        // revert goes back to the built-in defaults for h1
+        let c = computer.get_style(doc.get_element_by_id("c").unwrap()).unwrap();
+        assert_eq!(c.font_size, 32.0);
+        assert_eq!(c.margin_top, 21.44);
+        assert_eq!(c.color, red);
+    }
+
+    #[test]
+    fn test_em_resolves_against_own_font_size() {
+        let html = r#"<html><body><div id="box">Hi</div></body></html>"#;
+        let doc = parse_html(html);
+        let css = r#"
+            body { font-size: 10px; }
+            #box { margin-left: 2em; font-size: 2em; line-height: 1lh; padding-top: 1lh; }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        let style = computer.get_style(doc.get_element_by_id("box").unwrap()).unwrap();
+        // font-size uses the parent's font size, everything else the box's own
+        assert_eq!(style.font_size, 20.0);
+        assert_eq!(style.margin_left, 40.0);
+        // line-height's lh is the parent's line height, later lh its own
+        assert_eq!(style.line_height, 0.6);
+        assert_eq!(style.padding_top, 12.0);
+    }
+
+    #[test]
+    fn test_font_properties() {
+        let html = r#"<html><body><div id="outer"><p id="inner">Hi</p><em id="short">Yo</em></div></body></html>"#;
+        let doc = parse_html(html);
+        let css = r#"
+            #outer { font-family: "Open Sans", Georgia, serif; font-style: italic; font-weight: 300; font-stretch: 80%; }
+            #inner { font-weight: bolder; }
+            #short { font: bold condensed 20px/2 monospace; }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        let p = computer.get_style(doc.get_element_by_id("inner").unwrap()).unwrap();
+        assert_eq!(
+            &*p.font_family,
+            &[FontFamily::Named("Open Sans".to_string()), FontFamily::Named("Georgia".to_string()), FontFamily::Serif]
+        );
+        assert_eq!(p.font_style, FontStyle::Italic);
+        assert_eq!(p.font_stretch, FontStretch::Condensed);
+        // bolder goes from the parent's 300 to 400
+        assert_eq!(p.font_weight.value(), 400);
+        assert_eq!(p.font().weight, 400);
+
+        // The shorthand resets the style it doesn't mention
+        let em = computer.get_style(doc.get_element_by_id("short").unwrap()).unwrap();
+        assert_eq!(&*em.font_family, &[FontFamily::Monospace]);
+        assert_eq!(em.font_style, FontStyle::Normal);
+        assert_eq!(em.font_weight, FontWeight::Bold);
+        assert_eq!(em.font_stretch, FontStretch::Condensed);
+        assert_eq!(em.font_size, 20.0);
+        assert_eq!(em.line_height, 2.0);
+    }
+
+    #[test]
+    fn test_text_properties() {
+        let html = r#"<html><body><p id="p">Hi <a id="a" href="/">there</a></p><a id="link" href="/">Plain</a><em id="em">X</em></body></html>"#;
+        let doc = parse_html(html);
+        let css = r#"
+            p {
+                text-decoration: underline wavy red 3px;
+                text-transform: capitalize;
+                letter-spacing: 2px;
+                word-spacing: 0.5em;
+                text-indent: 10%;
+                text-shadow: 1px 2px 3px blue, red 0 0;
+            }
+            #a { text-decoration: none; letter-spacing: normal; text-shadow: none; }
+            em { text-decoration-line: overline line-through; text-decoration-thickness: 50%; }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        let p = computer.get_style(doc.get_element_by_id("p").unwrap()).unwrap();
+        assert_eq!(p.text_decoration_line, TextDecorationLine::UNDERLINE);
+        assert_eq!(p.text_decoration_style, TextDecorationStyle::Wavy);
+        assert_eq!(p.text_decoration_color, Some(Color::RED));
+        assert_eq!(p.text_decoration_thickness, Some(3.0));
+        assert_eq!(p.text_transform, TextTransform::Capitalize);
+        assert_eq!((p.letter_spacing, p.word_spacing), (2.0, 8.0));
+        assert_eq!(p.text_indent, LengthOrPercentage::Percent(10.0));
+        assert_eq!(p.text_shadow.len(), 2);
+        assert_eq!(p.text_shadow[0].blur_radius, 3.0);
+        assert_eq!(p.text_shadow[0].color, Some(Color::rgb(0, 0, 255)));
+        assert_eq!((p.text_shadow[1].offset_x, p.text_shadow[1].color), (0.0, Some(Color::RED)));
+
+        // The shorthand resets the longhands it doesn't mention
+        let a = computer.get_style(doc.get_element_by_id("a").unwrap()).unwrap();
+        assert!(a.text_decoration_line.is_none());
+        assert_eq!(a.text_decoration_color, None);
+        assert_eq!(a.letter_spacing, 0.0);
+        assert!(a.text_shadow.is_empty());
+        assert_eq!(a.text_transform, TextTransform::Capitalize);
+
+        // Links are underlined by default
+        let link = computer.get_style(doc.get_element_by_id("link").unwrap()).unwrap();
+        assert_eq!(link.text_decoration_line, TextDecorationLine::UNDERLINE);
+
+        let em = computer.get_style(doc.get_element_by_id("em").unwrap()).unwrap();
+        assert!(em.text_decoration_line.overline && em.text_decoration_line.line_through);
+        assert!(!em.text_decoration_line.underline);
+        assert_eq!(em.text_decoration_thickness, Some(8.0));
+
+        assert_eq!(TextTransform::Capitalize.apply("hello (big) world 2nd"), "Hello (Big) World 2nd");
+        assert_eq!(TextTransform::Uppercase.apply("straße"), "STRASSE");
+    }
+
+    #[test]
+    fn test_background_properties() {
+        let html = r#"<html><body><div id="a">A</div><div id="b">B</div></body></html>"#;
+        let doc = parse_html(html);
+        let css = r#"
+            div { background-size: 10px auto; background-repeat: repeat-y; }
+            #a { background: url(a.png) no-repeat, linear-gradient(red, blue) blue; }
+            #b { background-image: url(b.png), url(c.png); background-color: red; }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        // The shorthand resets the longhands it doesn't mention
+        let a = computer.get_style(doc.get_element_by_id("a").unwrap()).unwrap();
+        assert_eq!(a.background_color, Color::rgb(0, 0, 255));
+        let layers = a.background.layers();
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0].repeat.x, RepeatStyle::NoRepeat);
+        assert_eq!(layers[1].repeat.y, RepeatStyle::Repeat);
+        assert_eq!(layers[1].size, BackgroundSize::default());
+        assert!(matches!(layers[1].image, BackgroundImage::Gradient(_)));
+
+        let b = computer.get_style(doc.get_element_by_id("b").unwrap()).unwrap();
+        let layers = b.background.layers();
+        assert_eq!(layers[1].image, &BackgroundImage::Url("c.png".to_string()));
+        assert_eq!(layers[1].size, BackgroundSize::Explicit(Some(LengthOrPercentage::Px(10.0)), None));
+        assert_eq!(layers[1].repeat.x, RepeatStyle::NoRepeat);
+
+        // Not inherited
+        let body = computer.get_style(doc.get_body().unwrap()).unwrap();
+        assert!(body.background.image.is_empty());
+    }
+
+    #[test]
+    fn test_current_color() {
+        let html = r#"<html><body><div id="a"><p id="b">B</p></div></body></html>"#;
+        let doc = parse_html(html);
+        let css = r#"
+            #a { background-color: currentColor; color: rgb(0 0 255); text-shadow: 1px 1px currentcolor; }
+            #b { color: currentcolor; background: color-mix(in srgb, currentColor, white); }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        // The color is computed first, whatever the declaration order
+        let a = computer.get_style(doc.get_element_by_id("a").unwrap()).unwrap();
+        assert_eq!(a.background_color, Color::BLUE);
+        assert_eq!(a.text_shadow[0].color, None);
+
+        // In color, currentcolor is the parent's color
+        let b = computer.get_style(doc.get_element_by_id("b").unwrap()).unwrap();
+        assert_eq!(b.color, Color::BLUE);
+        assert_eq!(b.background_color, Color { r: 0.5, g: 0.5, b: 1.0, a: 1.0 });
+    }
+
+    #[test]
+    fn test_cascade_layers_and_nesting() {
+        let html = r#"<html><body><div id="a" class="card"><p class="title">A</p></div></body></html>"#;
+        let doc = parse_html(html);
+        // Later layers win, and unlayered rules win over all, whatever the
+        // specificity; for !important declarations the order is reversed
+        let first = r#"
+            @layer base, theme;
+            @layer theme { #a { color: red; margin-top: 1px !important; } }
+            @layer base {
+                #a { margin-top: 2px !important; padding-top: 1px !important; }
+                @layer reset { #a { padding-top: 2px !important; } }
+            }
+        "#;
+        let second = r#"
+            div { color: blue; margin-top: 3px; }
+            @layer base { #a.card { color: green; } }
+            .card { & > .title { color: green; } &:hover { color: red; } }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(first)));
+        computer.add_stylesheet(Rc::new(parse_css(second)));
+        computer.compute_styles(&doc);
+
+        let a = computer.get_style(doc.get_element_by_id("a").unwrap()).unwrap();
+        assert_eq!(a.color, Color::BLUE);
+        assert_eq!(a.margin_top, 2.0);
+        // base.reset comes before base
+        assert_eq!(a.padding_top, 2.0);
+
+        let p = computer.get_style(doc.get_elements_by_tag_name("p")[0]).unwrap();
+        assert_eq!(p.color, Color::rgb(0, 128, 0));
+    }
+
+    #[test]
+    fn test_cascade_origins() {
+        let html = r#"<html><body><div id="a" style="padding-top: 4px; padding-left: 4px !important">A</div>"#;
+        let doc = parse_html(html);
+        // Normal author declarations beat the user's, which beat the user
+        // agent's, whatever the specificity; !important reverses that
+        let user_agent = r#"
+            #a { margin-top: 1px; margin-left: 1px !important; }
+            @layer base { div { margin-bottom: 1px !important; } }
+        "#;
+        let user = r#"
+            #a { margin-top: 2px; margin-right: 2px !important; color: red; }
+            div { padding-left: 2px !important; }
+        "#;
+        let author = r#"
+            div { margin-top: 3px; margin-right: 3px !important; }
+            div { margin-left: 3px !important; margin-bottom: 3px !important; }
+            @layer base { #a { color: green; } }
+            #a { padding-top: 3px !important; padding-left: 3px !important; }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(author)));
+        computer.add_stylesheet(Rc::new(Stylesheet::user(user)));
+        computer.add_stylesheet(Rc::new(Stylesheet {
+            origin: Origin::UserAgent,
+            ..parse_css(user_agent)
+        }));
+        computer.compute_styles(&doc);
+
+        let a = computer.get_style(doc.get_element_by_id("a").unwrap()).unwrap();
+        assert_eq!(a.margin_top, 3.0);
+        assert_eq!(a.color, Color::rgb(0, 128, 0));
+        assert_eq!(a.margin_right, 2.0);
+        assert_eq!(a.margin_left, 1.0);
+        // Each origin has its own layers
+        assert_eq!(a.margin_bottom, 1.0);
+        // Inline declarations beat the author's rules of the same importance
+        assert_eq!(a.padding_top, 3.0);
+        // ...but not the user's important ones
+        assert_eq!(a.padding_left, 2.0);
+    }
+
+    #[test]
+    fn test_revert_rolls_back_to_user_styles() {
+        let html = r#"<html><body><p>A</p></body></html>"#;
+        let doc = parse_html(html);
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(Stylesheet::user_agent()));
+        computer.add_stylesheet(Rc::new(Stylesheet::user("p { color: blue; }")));
+        let author = "p { color: red; margin-top: 0; } p { color: revert; margin-top: revert; }";
+        computer.add_stylesheet(Rc::new(parse_css(author)));
+        computer.compute_styles(&doc);
+
+        let p = computer.get_style(doc.get_elements_by_tag_name("p")[0]).unwrap();
+        assert_eq!(p.color, Color::BLUE);
+        assert_eq!(p.margin_top, 16.0);
+    }
+
+    #[test]
+    fn test_forced_colors() {
+        let html = r#"<html><body><div id="a"><a href="/">link <b>bold</b></a></div><p id="keep">P</p></body></html>"#;
+        let doc = parse_html(html);
+        let css = r#"
+            #a { color: red; background-color: rgb(255 255 0 / 0.5); border: 1px solid green; box-shadow: 2px 2px red; }
+            #keep { forced-color-adjust: none; color: red; }
+            @media (forced-colors: active) { body { margin-left: 5px; } }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        let media = MediaEnvironment {
+            forced_colors: true,
+            ..computer.media_environment().clone()
+        };
+        assert!(computer.set_media_environment(media));
+        computer.compute_styles(&doc);
+
+        let canvas = crate::css::color::forced_color("canvas").unwrap();
+        let canvas_text = crate::css::color::forced_color("canvastext").unwrap();
+        let link_text = crate::css::color::forced_color("linktext").unwrap();
+
+        let a = computer.get_style(doc.get_element_by_id("a").unwrap()).unwrap();
+        assert_eq!(a.color, canvas_text);
+        assert_eq!(a.background_color, Color { a: 0.5, ..canvas });
+        assert_eq!(a.border_colors(), [canvas_text; 4]);
+        assert_eq!(a.box_shadow, None);
+        let html = computer.get_style(doc.get_elements_by_tag_name("html")[0]).unwrap();
+        assert_eq!(html.background_color, canvas);
+
+        let link = computer.get_style(doc.get_elements_by_tag_name("a")[0]).unwrap();
+        assert_eq!(link.color, link_text);
+        let bold = computer.get_style(doc.get_elements_by_tag_name("b")[0]).unwrap();
+        assert_eq!(bold.color, link_text);
+
+        let keep = computer.get_style(doc.get_element_by_id("keep").unwrap()).unwrap();
+        assert_eq!(keep.color, Color::RED);
+        let body = computer.get_style(doc.get_elements_by_tag_name("body")[0]).unwrap();
+        assert_eq!(body.margin_left, 5.0);
+    }
+
+    #[test]
+    fn test_border_sides() {
+        let html = r#"<html><body><div id="a">A</div><div id="b">B</div><table id="t" border="2"></table></body></html>"#;
+        let doc = parse_html(html);
+        let css = r#"
+            #a {
+                color: green;
+                border-width: 1px 2px 3px;
+                border-style: solid dashed;
+                border-color: red currentcolor;
+                border-left-style: none;
+                border-radius: 10px 20% / 5px;
+            }
+            #b { border: 4px double blue; border-top-color: red; border-bottom-style: hidden; }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        // Sides without a style have no width
+        let a = computer.get_style(doc.get_element_by_id("a").unwrap()).unwrap();
+        assert_eq!(a.border_widths(), [1.0, 2.0, 3.0, 0.0]);
+        assert_eq!(
+            a.border_styles(),
+            [BorderStyle::Solid, BorderStyle::Dashed, BorderStyle::Solid, BorderStyle::None]
+        );
+        let green = Color::rgb(0, 128, 0);
+        assert_eq!(a.border_colors(), [Color::RED, green, Color::RED, green]);
+
+        // Percentages resolve per axis, then corners too big for the box shrink
+        let radii = a.corner_radii(100.0, 40.0);
+        assert_eq!(radii.top_left, [10.0, 5.0]);
+        assert_eq!(radii.top_right, [20.0, 5.0]);
+        assert_eq!(a.corner_radii(100.0, 5.0).top_right, [10.0, 2.5]);
+
+        let b = computer.get_style(doc.get_element_by_id("b").unwrap()).unwrap();
+        assert_eq!(b.border_widths(), [4.0, 4.0, 0.0, 4.0]);
+        assert_eq!(b.border_styles()[0], BorderStyle::Double);
+        assert_eq!(b.border_colors()[0], Color::RED);
+        assert_eq!(b.border_colors()[1], Color::rgb(0, 0, 255));
+
+        // The border attribute draws an outset border
+        let t = computer.get_style(doc.get_element_by_id("t").unwrap()).unwrap();
+        assert_eq!(t.border_widths(), [2.0; 4]);
+        assert_eq!(t.border_styles(), [BorderStyle::Outset; 4]);
+    }
+
+    #[test]
+    fn test_shorthands_set_longhands() {
+        let html = r#"<html><body><div id="a">A</div><div id="b">B</div></body></html>"#;
+        let doc = parse_html(html);
+        let css = r#"
+            #a { margin: 1px !important; outline: dotted blue; flex: 2; inset: 5px auto; }
+            #a { margin-left: 9px; outline-offset: 3px; grid-area: 2 / 3; place-items: center; }
+            #b { --w: 6px; border: var(--w) solid; padding: var(--missing) 1px; list-style: inside; }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        // The shorthand's !important carries over to each longhand
+        let a = computer.get_style(doc.get_element_by_id("a").unwrap()).unwrap();
+        assert_eq!(a.margin_left, 1.0);
+        assert_eq!(a.outline_style, BorderStyle::Dotted);
+        assert_eq!(a.outline_width, 3.0);
+        assert_eq!(a.outline().0.colors[0], Color::rgb(0, 0, 255));
+        assert_eq!(a.outline().1, 6.0);
+        assert_eq!((a.flex_grow, a.flex_shrink, a.flex_basis), (2.0, 1.0, Some(0.0)));
+        assert_eq!((a.top, a.right, a.bottom), (Some(5.0), None, Some(5.0)));
+        assert_eq!((a.grid_row.start, a.grid_column.start, a.grid_row.end), (Some(2), Some(3), None));
+        assert_eq!(a.align_items, AlignItems::Center);
+
+        // A shorthand using var() is split once it's substituted; one that
+        // can't be leaves all its longhands unset
+        let b = computer.get_style(doc.get_element_by_id("b").unwrap()).unwrap();
+        assert_eq!(b.border_widths(), [6.0; 4]);
+        assert_eq!(b.border_colors(), [Color::BLACK; 4]);
+        assert_eq!((b.padding_top, b.padding_right), (0.0, 0.0));
+        assert_eq!(b.list_style_position, ListStylePosition::Inside);
+        assert_eq!(b.list_style_type, ListStyleType::Disc);
+    }
+
+    #[test]
+    fn test_incremental_restyle_shares_styles() {
+        let mut doc = parse_html(r#"<html><body><div id="box"><p id="inner">A</p><p>B</p><p>C</p></div></body></html>"#);
+        let css = ".wide { margin-left: 30px; } .big { font-size: 20px; } #inner { margin-left: inherit; }";
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        // Siblings matching the same rules share a style, as text does with its parent
+        let div = doc.get_element_by_id("box").unwrap();
+        let p = doc.get_elements_by_tag_name("p");
+        let shared =
+            |computer: &StyleComputer, a, b| Rc::ptr_eq(computer.get_style(a).unwrap(), computer.get_style(b).unwrap());
+        assert!(shared(&computer, p[1], p[2]));
+        assert!(!shared(&computer, p[0], p[1]));
+        assert!(shared(&computer, p[1], doc.children(p[1])[0]));
+
+        // A non-inherited change reaches only the child inheriting it explicitly
+        doc.set_attribute(div, "class", "wide");
+        computer.invalidate_attribute(&doc, div, "class", None);
+        assert_eq!(computer.recalc_styles(&doc), vec![div, p[0]]);
+        assert_eq!(computer.get_style(p[0]).unwrap().margin_left, 30.0);
+        assert_eq!(computer.get_style(p[1]).unwrap().margin_left, 0.0);
+
+        // An inherited one reaches all of them, which still share
+        doc.set_attribute(div, "class", "wide big");
+        computer.invalidate_attribute(&doc, div, "class", Some("wide"));
+        assert_eq!(computer.recalc_styles(&doc).len(), 4);
+        assert_eq!(computer.get_style(p[1]).unwrap().font_size, 20.0);
+        assert!(shared(&computer, p[1], p[2]));
+    }
+
+    #[test]
+    fn test_font_color_attributes_are_not_shared() {
+        let doc = parse_html(r#"<html><body><font color="red">A</font><font color="blue">B</font></body></html>"#);
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.compute_styles(&doc);
+
+        let fonts = doc.get_elements_by_tag_name("font");
+        assert_eq!(computer.get_style(fonts[0]).unwrap().color, Color::RED);
+        assert_eq!(computer.get_style(fonts[1]).unwrap().color, Color::rgb(0, 0, 255));
+    }
+
+    #[test]
+    fn test_restyle_after_color_attribute_change() {
+        let mut doc = parse_html(r#"<html><body><font id="f" color="red">A</font></body></html>"#);
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.compute_styles(&doc);
+
+        let font = doc.get_element_by_id("f").unwrap();
+        doc.set_attribute(font, "color", "blue");
+        computer.invalidate_attribute(&doc, font, "color", Some("red"));
+        assert_eq!(computer.recalc_styles(&doc), vec![font]);
+        assert_eq!(computer.get_style(font).unwrap().color, Color::rgb(0, 0, 255));
+    }
+
+    #[test]
+    fn test_restyle_reaches_following_siblings() {
+        let mut doc = parse_html(r#"<html><body><h1 id="title">T</h1><p id="text">Hi</p></body></html>"#);
+        let css = "h1.on + p { color: red; }";
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        let title = doc.get_element_by_id("title").unwrap();
+        let text = doc.get_element_by_id("text").unwrap();
+        assert_eq!(computer.get_style(text).unwrap().color.r, 0.0);
+
+        doc.set_attribute(title, "class", "on");
+        computer.invalidate_attribute(&doc, title, "class", None);
+        computer.recalc_styles(&doc);
+        assert_eq!(computer.get_style(text).unwrap().color.r, 1.0);
+    }
+
+    #[test]
+    fn test_restyle_after_state_change() {
+        let mut doc = parse_html(r#"<html><body><ul><li id="a">A</li><li id="b">B</li></ul></body></html>"#);
+        let css = "li:hover { color: red; } ul:focus-within li { color: blue; }";
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        // No selector looks at these states
+        let a = doc.get_element_by_id("a").unwrap();
+        let b = doc.get_element_by_id("b").unwrap();
+        computer.invalidate_state(&doc, a, ElementState::ACTIVE | ElementState::VISITED);
+        assert!(!computer.needs_recalc());
+
+        assert!(doc.set_element_state(a, ElementState::HOVER, true));
+        computer.invalidate_state(&doc, a, ElementState::HOVER);
+        assert_eq!(computer.recalc_styles(&doc), vec![a]);
+        assert_eq!(computer.get_style(a).unwrap().color.r, 1.0);
+        assert_eq!(computer.get_style(b).unwrap().color.r, 0.0);
+
+        assert!(doc.set_element_state(a, ElementState::HOVER, false));
+        computer.invalidate_state(&doc, a, ElementState::HOVER);
+        computer.recalc_styles(&doc);
+        assert_eq!(computer.get_style(a).unwrap().color.r, 0.0);
+    }
+
+    #[test]
+    fn test_transitions_and_animations() {
+        let mut doc = parse_html(r#"<html><body><a id="a">A</a><p id="p">P</p></body></html>"#);
+        let css = r#"
+            a { opacity: 1; transition: opacity 1s linear; }
+            a:hover { opacity: 0; }
+            p { animation: grow 2s linear; }
+            @keyframes grow { from { margin-left: 0; color: red } to { margin-left: 100px } }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+        let a = doc.get_element_by_id("a").unwrap();
+        let p = doc.get_element_by_id("p").unwrap();
+
+        // The animation starts with the element's first style
+        let style = computer.get_style(p).unwrap();
+        assert_eq!(style.margin_left, 0.0);
+        assert_eq!(style.color, Color::RED);
+
+        // Hovering starts a transition from the value shown
+        doc.set_element_state(a, ElementState::HOVER, true);
+        computer.invalidate_state(&doc, a, ElementState::HOVER);
+        computer.recalc_styles(&doc);
+        assert_eq!(computer.get_style(a).unwrap().opacity, 1.0);
+
+        computer.timeline_mut().advance(0.5);
+        let updates = computer.timeline_mut().take_updates();
+        assert_eq!(updates, vec![(a, Invalidation::Repaint), (p, Invalidation::Relayout)]);
+        computer.invalidate_style(&doc, a);
+        computer.invalidate_style(&doc, p);
+        computer.recalc_styles(&doc);
+        assert_eq!(computer.get_style(a).unwrap().opacity, 0.5);
+        let style = computer.get_style(p).unwrap();
+        assert_eq!(style.margin_left, 25.0);
+        // Red runs back to the element's own black over the whole animation
+        assert!((style.color.r - 0.75).abs() < 0.001);
+
+        // Leaving halfway reverses from where it got to
+        doc.set_element_state(a, ElementState::HOVER, false);
+        computer.invalidate_state(&doc, a, ElementState::HOVER);
+        computer.recalc_styles(&doc);
+        assert_eq!(computer.get_style(a).unwrap().opacity, 0.5);
+
+        // Once done, the cascaded values are back
+        computer.timeline_mut().advance(2.0);
+        computer.invalidate_style(&doc, a);
+        computer.invalidate_style(&doc, p);
+        computer.recalc_styles(&doc);
+        assert_eq!(computer.get_style(a).unwrap().opacity, 1.0);
+        assert_eq!(computer.get_style(p).unwrap().margin_left, 0.0);
+        assert!(!computer.timeline().is_animating());
+    }
+
+    #[test]
+    fn test_pseudo_element_styles() {
+        let mut doc = parse_html(r#"<html><body><ol><li id="a">A</li></ol><p id="p">P</p></body></html>"#);
+        let css = r#"
+            li { color: green; list-style: upper-roman inside; }
+            li::marker { color: red; }
+            p.quote::before { content: open-quote "> "; font-size: 2em; }
+        "#;
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        let a = doc.get_element_by_id("a").unwrap();
+        let p = doc.get_element_by_id("p").unwrap();
+        assert_eq!(computer.get_style(a).unwrap().color, Color::rgb(0, 128, 0));
+        let marker = computer.get_pseudo_style(a, PseudoElement::Marker).unwrap();
+        assert_eq!(marker.color, Color::rgb(255, 0, 0));
+        assert_eq!(marker.list_style_type, ListStyleType::UpperRoman);
+        assert_eq!(marker.list_style_position, ListStylePosition::Inside);
+        assert!(computer.get_pseudo_style(p, PseudoElement::Before).is_none());
+
+        doc.set_attribute(p, "class", "quote");
+        computer.invalidate_attribute(&doc, p, "class", None);
+        // Only the pseudo-element's style changed
+        assert_eq!(computer.recalc_styles(&doc), vec![p]);
+        let before = computer.get_pseudo_style(p, PseudoElement::Before).unwrap();
+        assert_eq!(before.display, Display::Inline);
+        assert_eq!(before.font_size, 32.0);
+        assert_eq!(
+            before.content,
+            Content::Items(vec![ContentItem::OpenQuote, ContentItem::String("> ".to_string())])
+        );
+    }
 }
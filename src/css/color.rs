@@ -0,0 +1,1081 @@
+//! CSS colors: the named and system colors, and the color functions of CSS
+//! Color 4 and 5.
+//!
+//! Functions are converted to sRGB when parsed, mapping colors outside its
+//! gamut into it by reducing their OKLCh chroma. A `color-mix()` that takes
+//! `currentcolor` is kept as a function until the current color is known.
+//! Colors are always held in sRGB, so mixing two colors outside it mixes
+//! their mapped colors.
+
+use super::parser::angle_to_degrees;
+use super::stylesheet::Value;
+use crate::render::painter::Color;
+use cssparser::{ParseError, Parser, Token};
+
+/// The named colors, sorted by name
+const NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+/// The system colors, lowercased, as a light color scheme shows them
+const SYSTEM_COLORS: [(&str, u32); 19] = [
+    ("accentcolor", 0x0075ff),
+    ("accentcolortext", 0xffffff),
+    ("activetext", 0xff0000),
+    ("buttonborder", 0x767676),
+    ("buttonface", 0xefefef),
+    ("buttontext", 0x000000),
+    ("canvas", 0xffffff),
+    ("canvastext", 0x000000),
+    ("field", 0xffffff),
+    ("fieldtext", 0x000000),
+    ("graytext", 0x808080),
+    ("highlight", 0xb5d5ff),
+    ("highlighttext", 0x000000),
+    ("linktext", 0x0000ee),
+    ("mark", 0xffff00),
+    ("marktext", 0x000000),
+    ("selecteditem", 0x0075ff),
+    ("selecteditemtext", 0xffffff),
+    ("visitedtext", 0x551a8b),
+];
+
+/// The system colors forced colors mode paints pages with: light text on
+/// black, in the manner of a high-contrast theme
+const FORCED_COLORS: [(&str, u32); 19] = [
+    ("accentcolor", 0x1aebff),
+    ("accentcolortext", 0x000000),
+    ("activetext", 0xffff00),
+    ("buttonborder", 0xffffff),
+    ("buttonface", 0x000000),
+    ("buttontext", 0xffffff),
+    ("canvas", 0x000000),
+    ("canvastext", 0xffffff),
+    ("field", 0x000000),
+    ("fieldtext", 0xffffff),
+    ("graytext", 0x3ff23f),
+    ("highlight", 0x1aebff),
+    ("highlighttext", 0x000000),
+    ("linktext", 0xffff00),
+    ("mark", 0xffff00),
+    ("marktext", 0x000000),
+    ("selecteditem", 0x1aebff),
+    ("selecteditemtext", 0x000000),
+    ("visitedtext", 0xffff00),
+];
+
+fn from_rgb24(rgb: u32) -> Color {
+    Color::rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
+
+fn lookup(table: &[(&str, u32)], name: &str) -> Option<Color> {
+    let name = name.to_ascii_lowercase();
+    let index = table.binary_search_by(|(entry, _)| (*entry).cmp(name.as_str())).ok()?;
+    Some(from_rgb24(table[index].1))
+}
+
+/// One of the 148 named colors, or `transparent`
+pub fn named_color(name: &str) -> Option<Color> {
+    if name.eq_ignore_ascii_case("transparent") {
+        return Some(Color::TRANSPARENT);
+    }
+    lookup(&NAMED_COLORS, name)
+}
+
+/// A system color such as `Canvas` or `LinkText`
+pub fn system_color(name: &str) -> Option<Color> {
+    lookup(&SYSTEM_COLORS, name)
+}
+
+/// A system color as forced colors mode shows it
+pub fn forced_color(name: &str) -> Option<Color> {
+    lookup(&FORCED_COLORS, name)
+}
+
+/// The color `value` stands for, with `currentcolor` as `current_color`;
+/// without one, `currentcolor` and mixes of it aren't colors
+pub fn resolve(value: &Value, current_color: Option<Color>) -> Option<Color> {
+    match value {
+        Value::Color(color) => Some(*color),
+        Value::Keyword(keyword) if keyword.eq_ignore_ascii_case("currentcolor") => current_color,
+        Value::Keyword(keyword) => named_color(keyword).or_else(|| system_color(keyword)),
+        Value::Function(name, args) if name == "color-mix" => mix(args, current_color),
+        _ => None,
+    }
+}
+
+/// A color space colors are given or mixed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    Srgb,
+    SrgbLinear,
+    DisplayP3,
+    A98Rgb,
+    ProphotoRgb,
+    Rec2020,
+    XyzD50,
+    XyzD65,
+    Lab,
+    Lch,
+    Oklab,
+    Oklch,
+    Hsl,
+    Hwb,
+}
+
+impl ColorSpace {
+    /// The spaces `color()` takes; `xyz` is `xyz-d65`
+    fn from_predefined(name: &str) -> Option<ColorSpace> {
+        let space = match name.to_ascii_lowercase().as_str() {
+            "srgb" => ColorSpace::Srgb,
+            "srgb-linear" => ColorSpace::SrgbLinear,
+            "display-p3" => ColorSpace::DisplayP3,
+            "a98-rgb" => ColorSpace::A98Rgb,
+            "prophoto-rgb" => ColorSpace::ProphotoRgb,
+            "rec2020" => ColorSpace::Rec2020,
+            "xyz" | "xyz-d65" => ColorSpace::XyzD65,
+            "xyz-d50" => ColorSpace::XyzD50,
+            _ => return None,
+        };
+        Some(space)
+    }
+
+    /// The spaces `color-mix()` interpolates in
+    fn from_interpolation(name: &str) -> Option<ColorSpace> {
+        let space = match name.to_ascii_lowercase().as_str() {
+            "lab" => ColorSpace::Lab,
+            "lch" => ColorSpace::Lch,
+            "oklab" => ColorSpace::Oklab,
+            "oklch" => ColorSpace::Oklch,
+            "hsl" => ColorSpace::Hsl,
+            "hwb" => ColorSpace::Hwb,
+            name => return ColorSpace::from_predefined(name),
+        };
+        Some(space)
+    }
+
+    /// Which component is a hue, in the spaces with one
+    fn hue_index(self) -> Option<usize> {
+        match self {
+            ColorSpace::Lch | ColorSpace::Oklch => Some(2),
+            ColorSpace::Hsl | ColorSpace::Hwb => Some(0),
+            _ => None,
+        }
+    }
+}
+
+type Matrix = [[f32; 3]; 3];
+
+fn multiply(m: &Matrix, v: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+const SRGB_TO_XYZ: Matrix = [
+    [0.412_390_8, 0.357_584_33, 0.180_480_8],
+    [0.212_639, 0.715_168_7, 0.072_192_32],
+    [0.019_330_818, 0.119_194_78, 0.950_532_14],
+];
+const XYZ_TO_SRGB: Matrix = [
+    [3.240_97, -1.537_383_2, -0.498_610_76],
+    [-0.969_243_65, 1.875_967_5, 0.041_555_06],
+    [0.055_630_08, -0.203_976_96, 1.056_971_5],
+];
+const P3_TO_XYZ: Matrix = [
+    [0.486_570_95, 0.265_667_7, 0.198_217_29],
+    [0.228_974_56, 0.691_738_5, 0.079_286_91],
+    [0.0, 0.045_113_38, 1.043_944_4],
+];
+const XYZ_TO_P3: Matrix = [
+    [2.493_497, -0.931_383_6, -0.402_710_78],
+    [-0.829_489, 1.762_664_1, 0.023_624_686],
+    [0.035_845_83, -0.076_172_39, 0.956_884_5],
+];
+const A98_TO_XYZ: Matrix = [
+    [0.576_669, 0.185_558_24, 0.188_228_65],
+    [0.297_344_98, 0.627_363_6, 0.075_291_46],
+    [0.027_031_36, 0.070_688_85, 0.991_337_5],
+];
+const XYZ_TO_A98: Matrix = [
+    [2.041_588, -0.565_007, -0.344_731_35],
+    [-0.969_243_65, 1.875_967_5, 0.041_555_06],
+    [0.013_444_28, -0.118_362_39, 1.015_175],
+];
+/// In D50, like the profile connection space it comes from
+const PROPHOTO_TO_XYZ_D50: Matrix = [
+    [0.797_766_6, 0.135_181_3, 0.031_347_733],
+    [0.288_074_83, 0.711_835_2, 0.000_089_936_94],
+    [0.0, 0.0, 0.825_104_6],
+];
+const XYZ_D50_TO_PROPHOTO: Matrix = [
+    [1.345_786_9, -0.255_572_1, -0.051_101_866],
+    [-0.544_630_7, 1.508_247_8, 0.020_527_447],
+    [0.0, 0.0, 1.211_967_5],
+];
+const REC2020_TO_XYZ: Matrix = [
+    [0.636_958_05, 0.144_616_9, 0.168_880_98],
+    [0.262_700_2, 0.677_998_1, 0.059_301_716],
+    [0.0, 0.028_072_693, 1.060_985_1],
+];
+const XYZ_TO_REC2020: Matrix = [
+    [1.716_651_2, -0.355_670_78, -0.253_366_3],
+    [-0.666_684_3, 1.616_481_2, 0.015_768_545],
+    [0.017_639_857, -0.042_770_613, 0.942_103_1],
+];
+/// Bradford chromatic adaptation between the D65 and D50 white points
+const D65_TO_D50: Matrix = [
+    [1.047_929_8, 0.022_946_793, -0.050_192_23],
+    [0.029_627_815, 0.990_434_5, -0.017_073_825],
+    [-0.009_243_058, 0.015_055_145, 0.751_874_3],
+];
+const D50_TO_D65: Matrix = [
+    [0.955_473_4, -0.023_098_537, 0.063_259_31],
+    [-0.028_369_706, 1.009_995_5, 0.021_041_399],
+    [0.012_314_002, -0.020_507_697, 1.330_366],
+];
+const XYZ_TO_LMS: Matrix = [
+    [0.819_022_4, 0.361_906_25, -0.128_873_78],
+    [0.032_983_654, 0.929_286_9, 0.036_144_666],
+    [0.048_177_19, 0.264_239_53, 0.633_547_8],
+];
+const LMS_TO_OKLAB: Matrix = [
+    [0.210_454_26, 0.793_617_8, -0.004_072_043],
+    [1.977_998_5, -2.428_592_2, 0.450_593_7],
+    [0.025_904_042, 0.782_771_7, -0.808_675_77],
+];
+const OKLAB_TO_LMS: Matrix = [
+    [1.0, 0.396_337_78, 0.215_803_76],
+    [1.0, -0.105_561_346, -0.063_854_17],
+    [1.0, -0.089_484_18, -1.291_485_5],
+];
+const LMS_TO_XYZ: Matrix = [
+    [1.226_88, -0.557_815, 0.281_391_05],
+    [-0.040_575_745, 1.112_286_8, -0.071_711_06],
+    [-0.076_372_94, -0.421_493_33, 1.586_924],
+];
+const D50_WHITE: [f32; 3] = [0.3457 / 0.3585, 1.0, (1.0 - 0.3457 - 0.3585) / 0.3585];
+const LAB_EPSILON: f32 = 216.0 / 24389.0;
+const LAB_KAPPA: f32 = 24389.0 / 27.0;
+
+fn map(v: [f32; 3], f: impl Fn(f32) -> f32) -> [f32; 3] {
+    [f(v[0]), f(v[1]), f(v[2])]
+}
+
+/// A transfer function applied to the magnitude, keeping the sign, so
+/// values outside 0 to 1 extend it
+fn signed(v: f32, f: impl Fn(f32) -> f32) -> f32 {
+    f(v.abs()).copysign(v)
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    signed(v, |v| if v <= 0.04045 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) })
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    signed(v, |v| if v > 0.003_130_8 { 1.055 * v.powf(1.0 / 2.4) - 0.055 } else { 12.92 * v })
+}
+
+fn a98_to_linear(v: f32) -> f32 {
+    signed(v, |v| v.powf(563.0 / 256.0))
+}
+
+fn linear_to_a98(v: f32) -> f32 {
+    signed(v, |v| v.powf(256.0 / 563.0))
+}
+
+fn prophoto_to_linear(v: f32) -> f32 {
+    signed(v, |v| if v <= 16.0 / 512.0 { v / 16.0 } else { v.powf(1.8) })
+}
+
+fn linear_to_prophoto(v: f32) -> f32 {
+    signed(v, |v| if v >= 1.0 / 512.0 { v.powf(1.0 / 1.8) } else { 16.0 * v })
+}
+
+const REC2020_ALPHA: f32 = 1.099_296_8;
+const REC2020_BETA: f32 = 0.018_053_97;
+
+fn rec2020_to_linear(v: f32) -> f32 {
+    signed(v, |v| {
+        if v < REC2020_BETA * 4.5 {
+            v / 4.5
+        } else {
+            ((v + REC2020_ALPHA - 1.0) / REC2020_ALPHA).powf(1.0 / 0.45)
+        }
+    })
+}
+
+fn linear_to_rec2020(v: f32) -> f32 {
+    signed(v, |v| {
+        if v > REC2020_BETA {
+            REC2020_ALPHA * v.powf(0.45) - (REC2020_ALPHA - 1.0)
+        } else {
+            4.5 * v
+        }
+    })
+}
+
+fn lab_to_xyz_d50([l, a, b]: [f32; 3]) -> [f32; 3] {
+    let fy = (l + 16.0) / 116.0;
+    let fx = a / 500.0 + fy;
+    let fz = fy - b / 200.0;
+    let inverse = |f: f32| if f.powi(3) > LAB_EPSILON { f.powi(3) } else { (116.0 * f - 16.0) / LAB_KAPPA };
+    let y = if l > LAB_KAPPA * LAB_EPSILON { fy.powi(3) } else { l / LAB_KAPPA };
+    [inverse(fx) * D50_WHITE[0], y, inverse(fz) * D50_WHITE[2]]
+}
+
+fn xyz_d50_to_lab(xyz: [f32; 3]) -> [f32; 3] {
+    let f = |v: f32| if v > LAB_EPSILON { v.cbrt() } else { (LAB_KAPPA * v + 16.0) / 116.0 };
+    let [fx, fy, fz] = [f(xyz[0] / D50_WHITE[0]), f(xyz[1]), f(xyz[2] / D50_WHITE[2])];
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn oklab_to_xyz(oklab: [f32; 3]) -> [f32; 3] {
+    multiply(&LMS_TO_XYZ, map(multiply(&OKLAB_TO_LMS, oklab), |v| v.powi(3)))
+}
+
+fn xyz_to_oklab(xyz: [f32; 3]) -> [f32; 3] {
+    multiply(&LMS_TO_OKLAB, map(multiply(&XYZ_TO_LMS, xyz), f32::cbrt))
+}
+
+/// Lightness, chroma and hue in degrees from the rectangular form
+fn to_polar([l, a, b]: [f32; 3]) -> [f32; 3] {
+    [l, a.hypot(b), b.atan2(a).to_degrees().rem_euclid(360.0)]
+}
+
+fn from_polar([l, c, h]: [f32; 3]) -> [f32; 3] {
+    let h = h.to_radians();
+    [l, c * h.cos(), c * h.sin()]
+}
+
+/// sRGB from hue in degrees, saturation and lightness from 0 to 1
+fn hsl_to_srgb([h, s, l]: [f32; 3]) -> [f32; 3] {
+    let a = s * l.min(1.0 - l);
+    let f = |n: f32| {
+        let k = (n + h / 30.0).rem_euclid(12.0);
+        l - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    [f(0.0), f(8.0), f(4.0)]
+}
+
+fn srgb_to_hsl([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    let d = max - min;
+    if d <= 0.0 {
+        return [0.0, 0.0, l];
+    }
+    let s = if l == 0.0 || l == 1.0 { 0.0 } else { (max - l) / l.min(1.0 - l) };
+    let h = if max == r {
+        (g - b) / d + if g < b { 6.0 } else { 0.0 }
+    } else if max == g {
+        (b - r) / d + 2.0
+    } else {
+        (r - g) / d + 4.0
+    };
+    [h * 60.0, s, l]
+}
+
+/// sRGB from hue in degrees, whiteness and blackness from 0 to 1
+fn hwb_to_srgb([h, w, b]: [f32; 3]) -> [f32; 3] {
+    if w + b >= 1.0 {
+        let gray = w / (w + b);
+        return [gray; 3];
+    }
+    map(hsl_to_srgb([h, 1.0, 0.5]), |v| v * (1.0 - w - b) + w)
+}
+
+fn srgb_to_hwb(rgb: [f32; 3]) -> [f32; 3] {
+    let [h, _, _] = srgb_to_hsl(rgb);
+    [h, rgb[0].min(rgb[1]).min(rgb[2]), 1.0 - rgb[0].max(rgb[1]).max(rgb[2])]
+}
+
+/// Components in `space` converted to XYZ with a D65 white point
+fn to_xyz(space: ColorSpace, c: [f32; 3]) -> [f32; 3] {
+    match space {
+        ColorSpace::Srgb => multiply(&SRGB_TO_XYZ, map(c, srgb_to_linear)),
+        ColorSpace::SrgbLinear => multiply(&SRGB_TO_XYZ, c),
+        ColorSpace::DisplayP3 => multiply(&P3_TO_XYZ, map(c, srgb_to_linear)),
+        ColorSpace::A98Rgb => multiply(&A98_TO_XYZ, map(c, a98_to_linear)),
+        ColorSpace::ProphotoRgb => multiply(&D50_TO_D65, multiply(&PROPHOTO_TO_XYZ_D50, map(c, prophoto_to_linear))),
+        ColorSpace::Rec2020 => multiply(&REC2020_TO_XYZ, map(c, rec2020_to_linear)),
+        ColorSpace::XyzD50 => multiply(&D50_TO_D65, c),
+        ColorSpace::XyzD65 => c,
+        ColorSpace::Lab => multiply(&D50_TO_D65, lab_to_xyz_d50(c)),
+        ColorSpace::Lch => to_xyz(ColorSpace::Lab, from_polar(c)),
+        ColorSpace::Oklab => oklab_to_xyz(c),
+        ColorSpace::Oklch => oklab_to_xyz(from_polar(c)),
+        ColorSpace::Hsl => to_xyz(ColorSpace::Srgb, hsl_to_srgb(c)),
+        ColorSpace::Hwb => to_xyz(ColorSpace::Srgb, hwb_to_srgb(c)),
+    }
+}
+
+/// XYZ with a D65 white point converted to components in `space`
+fn from_xyz(space: ColorSpace, xyz: [f32; 3]) -> [f32; 3] {
+    match space {
+        ColorSpace::Srgb => map(multiply(&XYZ_TO_SRGB, xyz), linear_to_srgb),
+        ColorSpace::SrgbLinear => multiply(&XYZ_TO_SRGB, xyz),
+        ColorSpace::DisplayP3 => map(multiply(&XYZ_TO_P3, xyz), linear_to_srgb),
+        ColorSpace::A98Rgb => map(multiply(&XYZ_TO_A98, xyz), linear_to_a98),
+        ColorSpace::ProphotoRgb => map(multiply(&XYZ_D50_TO_PROPHOTO, multiply(&D65_TO_D50, xyz)), linear_to_prophoto),
+        ColorSpace::Rec2020 => map(multiply(&XYZ_TO_REC2020, xyz), linear_to_rec2020),
+        ColorSpace::XyzD50 => multiply(&D65_TO_D50, xyz),
+        ColorSpace::XyzD65 => xyz,
+        ColorSpace::Lab => xyz_d50_to_lab(multiply(&D65_TO_D50, xyz)),
+        ColorSpace::Lch => to_polar(from_xyz(ColorSpace::Lab, xyz)),
+        ColorSpace::Oklab => xyz_to_oklab(xyz),
+        ColorSpace::Oklch => to_polar(xyz_to_oklab(xyz)),
+        ColorSpace::Hsl => srgb_to_hsl(from_xyz(ColorSpace::Srgb, xyz)),
+        ColorSpace::Hwb => srgb_to_hwb(from_xyz(ColorSpace::Srgb, xyz)),
+    }
+}
+
+/// Components in `space` as an sRGB color. Colors outside sRGB's gamut
+/// are brought into it by lowering their OKLCh chroma until clipping them
+/// changes them by less than a just noticeable difference, as CSS Color 4
+/// describes.
+pub fn to_srgb(space: ColorSpace, components: [f32; 3], alpha: f32) -> Color {
+    let alpha = alpha.clamp(0.0, 1.0);
+    let color = |[r, g, b]: [f32; 3]| Color {
+        r: r.clamp(0.0, 1.0),
+        g: g.clamp(0.0, 1.0),
+        b: b.clamp(0.0, 1.0),
+        a: alpha,
+    };
+    let xyz = to_xyz(space, components);
+    let rgb = if space == ColorSpace::Srgb { components } else { from_xyz(ColorSpace::Srgb, xyz) };
+    if in_gamut(rgb) {
+        return color(rgb);
+    }
+
+    const JND: f32 = 0.02;
+    const EPSILON: f32 = 0.0001;
+    let origin = from_xyz(ColorSpace::Oklch, xyz);
+    if origin[0] >= 1.0 {
+        return color([1.0; 3]);
+    }
+    if origin[0] <= 0.0 {
+        return color([0.0; 3]);
+    }
+    let to_rgb = |oklch: [f32; 3]| from_xyz(ColorSpace::Srgb, to_xyz(ColorSpace::Oklch, oklch));
+    let clip = |oklch: [f32; 3]| map(to_rgb(oklch), |v| v.clamp(0.0, 1.0));
+    let delta = |rgb: [f32; 3], oklch: [f32; 3]| {
+        let [l1, a1, b1] = from_xyz(ColorSpace::Oklab, to_xyz(ColorSpace::Srgb, rgb));
+        let [l2, a2, b2] = from_polar(oklch);
+        ((l1 - l2).powi(2) + (a1 - a2).powi(2) + (b1 - b2).powi(2)).sqrt()
+    };
+
+    let mut current = origin;
+    let mut clipped = clip(current);
+    if delta(clipped, current) < JND {
+        return color(clipped);
+    }
+    let (mut min, mut max) = (0.0, origin[1]);
+    let mut min_in_gamut = true;
+    while max - min > EPSILON {
+        current[1] = (min + max) / 2.0;
+        if min_in_gamut && in_gamut(to_rgb(current)) {
+            min = current[1];
+            continue;
+        }
+        clipped = clip(current);
+        let e = delta(clipped, current);
+        if e < JND {
+            if JND - e < EPSILON {
+                break;
+            }
+            min_in_gamut = false;
+            min = current[1];
+        } else {
+            max = current[1];
+        }
+    }
+    color(clipped)
+}
+
+fn in_gamut(rgb: [f32; 3]) -> bool {
+    const EPSILON: f32 = 0.000_1;
+    rgb.iter().all(|v| (-EPSILON..=1.0 + EPSILON).contains(v))
+}
+
+/// One component of a color function; `none` is a missing component,
+/// which counts as zero
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Component {
+    Number(f32),
+    /// A fraction: 50% is 0.5
+    Percentage(f32),
+    /// Degrees
+    Angle(f32),
+    None,
+}
+
+impl Component {
+    /// The value of a number, with 100% as `full`
+    fn value(self, full: f32) -> Option<f32> {
+        match self {
+            Component::Number(n) => Some(n),
+            Component::Percentage(fraction) => Some(fraction * full),
+            Component::Angle(_) => None,
+            Component::None => Some(0.0),
+        }
+    }
+
+    /// A hue in degrees; numbers are degrees
+    fn hue(self) -> Option<f32> {
+        match self {
+            Component::Number(degrees) | Component::Angle(degrees) => Some(degrees),
+            Component::Percentage(_) => None,
+            Component::None => Some(0.0),
+        }
+    }
+}
+
+/// The components of a color function, and its alpha
+struct Components {
+    channels: [Component; 3],
+    alpha: Component,
+    /// Separated by commas, which only `rgb()` and `hsl()` allow
+    legacy: bool,
+}
+
+impl Components {
+    fn alpha(&self) -> Option<f32> {
+        self.alpha.value(1.0)
+    }
+}
+
+/// Three components separated by spaces, then optionally `/` and the
+/// alpha; or the legacy syntax of three or four separated by commas
+fn parse_components<'i>(parser: &mut Parser<'i, '_>) -> Result<Components, ParseError<'i, ()>> {
+    let mut channels = Vec::new();
+    let mut alpha = None;
+    let mut commas = 0;
+    let mut slash = false;
+    while !parser.is_exhausted() {
+        let token = parser.next()?.clone();
+        let component = match token {
+            Token::Comma => {
+                commas += 1;
+                continue;
+            }
+            Token::Delim('/') if !slash => {
+                slash = true;
+                continue;
+            }
+            Token::Number { value, .. } => Component::Number(value),
+            Token::Percentage { unit_value, .. } => Component::Percentage(unit_value),
+            Token::Dimension { value, ref unit, .. } => match angle_to_degrees(value, unit) {
+                Some(degrees) => Component::Angle(degrees),
+                None => return Err(parser.new_custom_error(())),
+            },
+            Token::Ident(ref ident) if ident.eq_ignore_ascii_case("none") => Component::None,
+            _ => return Err(parser.new_custom_error(())),
+        };
+        if slash {
+            if alpha.is_some() {
+                return Err(parser.new_custom_error(()));
+            }
+            alpha = Some(component);
+        } else {
+            channels.push(component);
+        }
+    }
+
+    let legacy = commas > 0;
+    if legacy {
+        // Every component is separated by a comma, and none is missing
+        let valid = !slash
+            && commas + 1 == channels.len()
+            && (3..=4).contains(&channels.len())
+            && !channels.contains(&Component::None);
+        if !valid {
+            return Err(parser.new_custom_error(()));
+        }
+        if channels.len() == 4 {
+            alpha = channels.pop();
+        }
+    } else if slash && alpha.is_none() {
+        return Err(parser.new_custom_error(()));
+    }
+    let Ok(channels) = <[Component; 3]>::try_from(channels) else {
+        return Err(parser.new_custom_error(()));
+    };
+    Ok(Components { channels, alpha: alpha.unwrap_or(Component::Number(1.0)), legacy })
+}
+
+/// Whether `name` is a color function this module parses
+pub fn is_color_function(name: &str) -> bool {
+    matches!(
+        name.to_ascii_lowercase().as_str(),
+        "rgb" | "rgba" | "hsl" | "hsla" | "hwb" | "lab" | "lch" | "oklab" | "oklch" | "color"
+    )
+}
+
+/// Parses the contents of the color function `name` into a color. An
+/// invalid one is kept as the function without arguments, which no
+/// property takes.
+pub fn parse_function(name: &str, parser: &mut Parser) -> Value {
+    let name = name.to_ascii_lowercase();
+    match parse_color(&name, parser) {
+        Ok(color) => Value::Color(color),
+        Err(_) => Value::Function(name, Vec::new()),
+    }
+}
+
+fn parse_color<'i>(name: &str, parser: &mut Parser<'i, '_>) -> Result<Color, ParseError<'i, ()>> {
+    let space = if name == "color" {
+        let ident = parser.expect_ident()?.clone();
+        Some(ColorSpace::from_predefined(&ident).ok_or_else(|| parser.new_custom_error::<(), ()>(()))?)
+    } else {
+        None
+    };
+    let components = parse_components(parser)?;
+    if components.legacy && !matches!(name, "rgb" | "rgba" | "hsl" | "hsla") {
+        return Err(parser.new_custom_error(()));
+    }
+    function_color(name, space, &components).ok_or_else(|| parser.new_custom_error(()))
+}
+
+fn function_color(name: &str, space: Option<ColorSpace>, components: &Components) -> Option<Color> {
+    let [c0, c1, c2] = components.channels;
+    let alpha = components.alpha()?;
+    let color = match name {
+        "rgb" | "rgba" => {
+            let channel = |c: Component| Some(c.value(255.0)? / 255.0);
+            let rgb = [channel(c0)?, channel(c1)?, channel(c2)?];
+            // Out of range channels are clamped, not mapped
+            to_srgb(ColorSpace::Srgb, map(rgb, |v| v.clamp(0.0, 1.0)), alpha)
+        }
+        "hsl" | "hsla" | "hwb" => {
+            // Saturation, lightness, whiteness and blackness given as numbers are percentages
+            let fraction = |c: Component| Some((c.value(100.0)? / 100.0).clamp(0.0, 1.0));
+            let hsl = [c0.hue()?, fraction(c1)?, fraction(c2)?];
+            let rgb = if name == "hwb" { hwb_to_srgb(hsl) } else { hsl_to_srgb(hsl) };
+            to_srgb(ColorSpace::Srgb, rgb, alpha)
+        }
+        "lab" => to_srgb(
+            ColorSpace::Lab,
+            [c0.value(100.0)?.clamp(0.0, 100.0), c1.value(125.0)?, c2.value(125.0)?],
+            alpha,
+        ),
+        "lch" => to_srgb(
+            ColorSpace::Lch,
+            [c0.value(100.0)?.clamp(0.0, 100.0), c1.value(150.0)?.max(0.0), c2.hue()?],
+            alpha,
+        ),
+        "oklab" => to_srgb(
+            ColorSpace::Oklab,
+            [c0.value(1.0)?.clamp(0.0, 1.0), c1.value(0.4)?, c2.value(0.4)?],
+            alpha,
+        ),
+        "oklch" => to_srgb(
+            ColorSpace::Oklch,
+            [c0.value(1.0)?.clamp(0.0, 1.0), c1.value(0.4)?.max(0.0), c2.hue()?],
+            alpha,
+        ),
+        "color" => to_srgb(space?, [c0.value(1.0)?, c1.value(1.0)?, c2.value(1.0)?], alpha),
+        _ => return None,
+    };
+    Some(color)
+}
+
+/// How hues are interpolated between two colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HueMethod {
+    Shorter,
+    Longer,
+    Increasing,
+    Decreasing,
+}
+
+/// `color-mix()`, from its comma-separated arguments: the interpolation
+/// method, then two colors each optionally with a percentage. `None` when
+/// the arguments are invalid, or take `currentcolor` and `current_color`
+/// isn't given.
+pub fn mix(args: &[Value], current_color: Option<Color>) -> Option<Color> {
+    let [method, first, second] = args else {
+        return None;
+    };
+    let (space, hue_method) = parse_interpolation(method.as_slice())?;
+    let (first, p1) = mix_color(first, current_color)?;
+    let (second, p2) = mix_color(second, current_color)?;
+
+    let (p1, p2) = match (p1, p2) {
+        (None, None) => (0.5, 0.5),
+        (Some(p1), None) => (p1, 1.0 - p1),
+        (None, Some(p2)) => (1.0 - p2, p2),
+        (Some(p1), Some(p2)) => (p1, p2),
+    };
+    let sum = p1 + p2;
+    if sum <= 0.0 {
+        return None;
+    }
+    // Percentages adding up to less than 100% make the mix transparent
+    // by as much
+    let alpha_scale = sum.min(1.0);
+    let t = p2 / sum;
+
+    let to_space = |color: Color| {
+        let rgb = [color.r, color.g, color.b];
+        let mut c = if space == ColorSpace::Srgb { rgb } else { from_xyz(space, to_xyz(ColorSpace::Srgb, rgb)) };
+        // A gray has no hue; it takes the other color's
+        let powerless = match space {
+            ColorSpace::Lch => c[1] < 0.001,
+            ColorSpace::Oklch => c[1] < 0.000_01,
+            ColorSpace::Hsl => c[1] <= 0.0,
+            ColorSpace::Hwb => c[1] + c[2] >= 1.0,
+            _ => false,
+        };
+        let hue = space.hue_index().filter(|_| !powerless).map(|i| c[i]);
+        if let Some(i) = space.hue_index() {
+            c[i] = 0.0;
+        }
+        (c, hue)
+    };
+    let (mut c1, h1) = to_space(first);
+    let (mut c2, h2) = to_space(second);
+    let (h1, h2) = match (h1, h2) {
+        (Some(h1), Some(h2)) => fix_hues(h1, h2, hue_method),
+        (Some(h), None) | (None, Some(h)) => (h, h),
+        (None, None) => (0.0, 0.0),
+    };
+
+    // Components are interpolated premultiplied by alpha, hues aside
+    let hue_index = space.hue_index();
+    for (c, alpha) in [(&mut c1, first.a), (&mut c2, second.a)] {
+        for (i, v) in c.iter_mut().enumerate() {
+            if Some(i) != hue_index {
+                *v *= alpha;
+            }
+        }
+    }
+    let alpha = first.a * (1.0 - t) + second.a * t;
+    let mut mixed = [0.0; 3];
+    for (i, v) in mixed.iter_mut().enumerate() {
+        *v = c1[i] * (1.0 - t) + c2[i] * t;
+        if Some(i) != hue_index && alpha > 0.0 {
+            *v /= alpha;
+        }
+    }
+    if let Some(i) = hue_index {
+        mixed[i] = (h1 * (1.0 - t) + h2 * t).rem_euclid(360.0);
+    }
+    Some(to_srgb(space, mixed, alpha * alpha_scale))
+}
+
+/// `in <space>`, then for spaces with a hue, optionally `<method> hue`
+fn parse_interpolation(values: &[Value]) -> Option<(ColorSpace, HueMethod)> {
+    let keyword = |value: &Value| value.as_keyword().map(str::to_ascii_lowercase);
+    let (space, method) = match values {
+        [first, space, method @ ..] if keyword(first)?.as_str() == "in" => (keyword(space)?, method),
+        _ => return None,
+    };
+    let space = ColorSpace::from_interpolation(&space)?;
+    let hue_method = match method {
+        [] => HueMethod::Shorter,
+        [method, hue] if space.hue_index().is_some() && keyword(hue)?.as_str() == "hue" => {
+            match keyword(method)?.as_str() {
+                "shorter" => HueMethod::Shorter,
+                "longer" => HueMethod::Longer,
+                "increasing" => HueMethod::Increasing,
+                "decreasing" => HueMethod::Decreasing,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    Some((space, hue_method))
+}
+
+/// A color and its percentage, in either order, as a fraction
+fn mix_color(value: &Value, current_color: Option<Color>) -> Option<(Color, Option<f32>)> {
+    let percentage = |value: &Value| match value {
+        Value::Percentage(pct) if (0.0..=100.0).contains(pct) => Some(pct / 100.0),
+        _ => None,
+    };
+    match value.as_slice() {
+        [color] => Some((resolve(color, current_color)?, None)),
+        [pct @ Value::Percentage(_), color] | [color, pct] => {
+            Some((resolve(color, current_color)?, Some(percentage(pct)?)))
+        }
+        _ => None,
+    }
+}
+
+/// The two hues adjusted so that interpolating between them goes the way
+/// `method` asks
+fn fix_hues(h1: f32, h2: f32, method: HueMethod) -> (f32, f32) {
+    let (h1, mut h2) = (h1.rem_euclid(360.0), h2.rem_euclid(360.0));
+    let delta = h2 - h1;
+    match method {
+        HueMethod::Shorter if delta > 180.0 => h2 -= 360.0,
+        HueMethod::Shorter if delta < -180.0 => h2 += 360.0,
+        HueMethod::Longer if (0.0..180.0).contains(&delta) && delta != 0.0 => h2 -= 360.0,
+        HueMethod::Longer if (-180.0..0.0).contains(&delta) => h2 += 360.0,
+        HueMethod::Increasing if delta < 0.0 => h2 += 360.0,
+        HueMethod::Decreasing if delta > 0.0 => h2 -= 360.0,
+        _ => {}
+    }
+    (h1, h2)
+}
+
+/// A parsed `color-mix()`: its color when nothing in it depends on the
+/// current color, or else the function, to be mixed once it is known
+pub fn mix_value(args: Vec<Value>) -> Value {
+    match mix(&args, None) {
+        Some(color) => Value::Color(color),
+        None => Value::Function("color-mix".to_string(), args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::parse_css;
+
+    fn value(css_value: &str) -> Value {
+        let stylesheet = parse_css(&format!("a {{ x: {}; }}", css_value));
+        stylesheet.rules[0].declarations.first().map_or(Value::None, |d| d.value.clone())
+    }
+
+    fn color(css_value: &str) -> Option<Color> {
+        resolve(&value(css_value), Some(Color::BLUE))
+    }
+
+    /// A color as 8-bit channels, to compare against known values
+    fn rgba8(css_value: &str) -> Option<[u8; 4]> {
+        let c = color(css_value)?;
+        Some([c.r, c.g, c.b, c.a].map(|v| (v * 255.0).round() as u8))
+    }
+
+    #[test]
+    fn test_named_colors() {
+        assert!(NAMED_COLORS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(SYSTEM_COLORS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(rgba8("RebeccaPurple"), Some([102, 51, 153, 255]));
+        assert_eq!(rgba8("lightgoldenrodyellow"), Some([250, 250, 210, 255]));
+        assert_eq!(color("transparent"), Some(Color::TRANSPARENT));
+        assert_eq!(rgba8("LinkText"), Some([0, 0, 238, 255]));
+        assert_eq!(color("Canvas"), Some(Color::WHITE));
+        assert_eq!(color("currentColor"), Some(Color::BLUE));
+        assert_eq!(resolve(&value("currentcolor"), None), None);
+        assert_eq!(color("notacolor"), None);
+    }
+
+    #[test]
+    fn test_parse_rgb_and_hsl() {
+        assert_eq!(rgba8("rgb(255 128 0)"), Some([255, 128, 0, 255]));
+        assert_eq!(rgba8("rgb(100% 50% 0% / 50%)"), Some([255, 128, 0, 128]));
+        assert_eq!(rgba8("rgba(0, 0, 255, 0.25)"), Some([0, 0, 255, 64]));
+        assert_eq!(rgba8("rgb(300 -20 none)"), Some([255, 0, 0, 255]));
+        assert_eq!(rgba8("hsl(120 100% 25%)"), Some([0, 128, 0, 255]));
+        assert_eq!(rgba8("hsla(0.5turn, 100%, 50%, 0.5)"), Some([0, 255, 255, 128]));
+        assert_eq!(rgba8("hwb(0 0% 0%)"), Some([255, 0, 0, 255]));
+        assert_eq!(rgba8("hwb(90 60 60)"), Some([128, 128, 128, 255]));
+
+        // Commas and slashes don't mix, and only rgb() and hsl() take commas
+        assert_eq!(color("rgb(1, 2 3)"), None);
+        assert_eq!(color("rgb(1, 2, 3 / 1)"), None);
+        assert_eq!(color("rgb(1 2)"), None);
+        assert_eq!(color("rgb(1 2 3 /)"), None);
+        assert_eq!(color("hwb(0, 0%, 0%)"), None);
+        assert_eq!(color("rgb(red 0 0)"), None);
+    }
+
+    #[test]
+    fn test_parse_lab_and_color() {
+        assert_eq!(rgba8("lab(54.29 80.82 69.88)"), Some([255, 0, 0, 255]));
+        assert_eq!(rgba8("lch(54.29 106.84 40.85)"), Some([255, 0, 0, 255]));
+        assert_eq!(rgba8("oklab(0.628 0.2249 0.1259)"), Some([255, 0, 0, 255]));
+        assert_eq!(rgba8("oklch(62.8% 0.2577 29.23 / 0.5)"), Some([255, 0, 0, 128]));
+        assert_eq!(rgba8("lab(100 0 0)"), Some([255, 255, 255, 255]));
+        assert_eq!(rgba8("color(srgb 1 0.5 0)"), Some([255, 128, 0, 255]));
+        assert_eq!(rgba8("color(srgb-linear 0.2159 0.2159 0.2159)"), Some([128, 128, 128, 255]));
+        assert_eq!(rgba8("color(xyz 0.4124 0.2126 0.0193)"), Some([255, 0, 0, 255]));
+        assert_eq!(rgba8("color(display-p3 0.9175 0.2003 0.1386)"), Some([255, 0, 0, 255]));
+        assert_eq!(color("color(cmyk 1 0 0)"), None);
+
+        // P3 red is outside sRGB; mapping it keeps it a bright red with
+        // its hue rather than clipping its channels
+        let [r, g, b, _] = rgba8("color(display-p3 1 0 0)").unwrap();
+        assert_eq!(r, 255);
+        assert!(g < 40 && b < 40);
+        // Lightness past white is white
+        assert_eq!(rgba8("oklch(1.2 0.4 30)"), Some([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn test_color_mix() {
+        assert_eq!(rgba8("color-mix(in srgb, red, blue)"), Some([128, 0, 128, 255]));
+        assert_eq!(rgba8("color-mix(in srgb, red 75%, blue)"), Some([191, 0, 64, 255]));
+        assert_eq!(rgba8("color-mix(in srgb, 25% red, blue 25%)"), Some([128, 0, 128, 128]));
+        assert_eq!(rgba8("color-mix(in srgb, white, transparent)"), Some([255, 255, 255, 128]));
+        assert_eq!(rgba8("color-mix(in oklab, white, black)"), Some([99, 99, 99, 255]));
+        // Hues go the short way round unless asked otherwise
+        assert_eq!(rgba8("color-mix(in hsl, red, blue)"), Some([255, 0, 255, 255]));
+        assert_eq!(rgba8("color-mix(in hsl longer hue, red, blue)"), Some([0, 255, 0, 255]));
+        // A gray takes the other color's hue
+        assert_eq!(rgba8("color-mix(in hsl, red, white)"), Some([223, 159, 159, 255]));
+
+        // Mixes without the current color are colors once parsed
+        assert!(matches!(value("color-mix(in srgb, red, blue)"), Value::Color(_)));
+        assert!(matches!(value("color-mix(in srgb, currentcolor, red)"), Value::Function(..)));
+        assert_eq!(rgba8("color-mix(in srgb, currentcolor, red)"), Some([128, 0, 128, 255]));
+
+        assert_eq!(color("color-mix(red, blue)"), None);
+        assert_eq!(color("color-mix(in srgb longer hue, red, blue)"), None);
+        assert_eq!(color("color-mix(in srgb, red 0%, blue 0%)"), None);
+        assert_eq!(color("color-mix(in srgb, red 150%, blue)"), None);
+    }
+}
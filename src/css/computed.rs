@@ -1,7 +1,20 @@
-use super::stylesheet::Value;
-use crate::render::painter::Color;
+use super::animation::{self, Animations, Transitions};
+use super::background::{self, Backgrounds};
+use super::color;
+use super::filter::{self, FilterFunction};
+use super::math::CalcExpr;
+use super::properties::{self, CssWideKeyword, Defaulting};
+use super::stylesheet::{LengthContext, Value};
+use super::transform::{self, TransformFunction, TransformOrigin};
+use super::variables::CustomProperties;
+use crate::render::filter::{BlendMode, Effects};
+use crate::render::font::{Font, FontFamily, FontStretch, FontStyle};
+use crate::render::painter::{BorderSides, BorderStyle, Color, CornerRadii, Rect, TextDecorationStyle};
+use crate::render::text::TextSpacing;
+use crate::render::transform::Transform;
+use std::rc::Rc;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ComputedStyle {
     // Display
     pub display: Display,
@@ -21,26 +34,49 @@ pub struct ComputedStyle {
     pub border_right_width: f32,
     pub border_bottom_width: f32,
     pub border_left_width: f32,
-    pub border_color: Color,
+    /// Side colors, `None` for `currentcolor`
+    pub border_top_color: Option<Color>,
+    pub border_right_color: Option<Color>,
+    pub border_bottom_color: Option<Color>,
+    pub border_left_color: Option<Color>,
 
     // Dimensions (can be absolute px or percentage of containing block)
     pub width: Option<LengthOrPercentage>,
     pub height: Option<LengthOrPercentage>,
-    pub min_width: Option<f32>,
-    pub min_height: Option<f32>,
-    pub max_width: Option<f32>,
-    pub max_height: Option<f32>,
+    pub min_width: Option<LengthOrPercentage>,
+    pub min_height: Option<LengthOrPercentage>,
+    pub max_width: Option<LengthOrPercentage>,
+    pub max_height: Option<LengthOrPercentage>,
 
     // Colors
     pub color: Color,
     pub background_color: Color,
+    /// `background-image` and the properties laying out its layers
+    pub background: Backgrounds,
+    /// `forced-color-adjust: auto`; `none` keeps the author's colors in
+    /// forced colors mode
+    pub forced_color_adjust: bool,
 
     // Text
     pub font_size: f32,
     pub font_weight: FontWeight,
+    pub font_family: Rc<[FontFamily]>,
+    pub font_style: FontStyle,
+    pub font_stretch: FontStretch,
     pub text_align: TextAlign,
     pub line_height: f32,
-    pub text_decoration: TextDecoration,
+    pub text_decoration_line: TextDecorationLine,
+    pub text_decoration_style: TextDecorationStyle,
+    /// `None` for `currentcolor`
+    pub text_decoration_color: Option<Color>,
+    /// `None` for `auto`, a thickness from the font size
+    pub text_decoration_thickness: Option<f32>,
+    pub text_transform: TextTransform,
+    pub letter_spacing: f32,
+    pub word_spacing: f32,
+    pub text_indent: LengthOrPercentage,
+    /// Shadows drawn behind the text, the first on top
+    pub text_shadow: Vec<TextShadow>,
 
     // Position
     pub position: Position,
@@ -80,11 +116,10 @@ pub struct ComputedStyle {
     pub grid_row: GridPlacement,
 
     // Visual properties
-    pub border_radius: f32,
-    pub border_top_left_radius: f32,
-    pub border_top_right_radius: f32,
-    pub border_bottom_left_radius: f32,
-    pub border_bottom_right_radius: f32,
+    pub border_top_left_radius: CornerRadius,
+    pub border_top_right_radius: CornerRadius,
+    pub border_bottom_left_radius: CornerRadius,
+    pub border_bottom_right_radius: CornerRadius,
     pub box_shadow: Option<BoxShadow>,
     pub opacity: f32,
     pub overflow: Overflow,
@@ -103,12 +138,18 @@ pub struct ComputedStyle {
     pub z_index: Option<i32>,
 
     // Border styles
-    pub border_style: BorderStyle,
     pub border_top_style: BorderStyle,
     pub border_right_style: BorderStyle,
     pub border_bottom_style: BorderStyle,
     pub border_left_style: BorderStyle,
 
+    // Outline, drawn outside the border box without taking up space
+    pub outline_width: f32,
+    pub outline_style: BorderStyle,
+    /// `None` for `currentcolor`
+    pub outline_color: Option<Color>,
+    pub outline_offset: f32,
+
     // Table-specific properties
     pub border_collapse: bool,
     pub border_spacing: f32,
@@ -116,6 +157,38 @@ pub struct ComputedStyle {
     // Float and clear
     pub float: Float,
     pub clear: Clear,
+
+    // Lists and generated content
+    pub list_style_type: ListStyleType,
+    pub list_style_position: ListStylePosition,
+    /// What a `::before`, `::after` or `::marker` box contains
+    pub content: Content,
+    /// `counter-reset`, `counter-increment` and `counter-set`: counter
+    /// names with the value to reset to, add, or set
+    pub counter_reset: Vec<(String, i32)>,
+    pub counter_increment: Vec<(String, i32)>,
+    pub counter_set: Vec<(String, i32)>,
+    pub quotes: Quotes,
+
+    // Transforms, applied in this order, then the `transform` list
+    pub translate: TransformFunction,
+    pub rotate: TransformFunction,
+    pub scale: TransformFunction,
+    pub transform: Vec<TransformFunction>,
+    pub transform_origin: TransformOrigin,
+
+    // Effects on the box and its descendants as a group, with `opacity`
+    pub filter: Vec<FilterFunction>,
+    pub backdrop_filter: Vec<FilterFunction>,
+    pub mix_blend_mode: BlendMode,
+
+    // Animation
+    pub transition: Transitions,
+    pub animation: Animations,
+
+    /// Custom properties (`--name`), inherited by sharing until an element
+    /// declares its own
+    pub custom_properties: Rc<CustomProperties>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
@@ -127,10 +200,12 @@ pub enum Float {
 }
 
 /// A length that can be either absolute pixels or a percentage
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum LengthOrPercentage {
     Px(f32),
     Percent(f32),
+    /// A math expression mixing pixels and percentages
+    Calc(Rc<CalcExpr>),
 }
 
 impl LengthOrPercentage {
@@ -139,6 +214,30 @@ impl LengthOrPercentage {
         match self {
             LengthOrPercentage::Px(px) => *px,
             LengthOrPercentage::Percent(pct) => containing_size * pct / 100.0,
+            // Relative lengths were made absolute at computed-value time
+            LengthOrPercentage::Calc(expr) => expr
+                .to_px(&LengthContext::new(0.0, 0.0), Some(containing_size))
+                .unwrap_or(0.0),
+        }
+    }
+
+    /// The length in pixels when it doesn't depend on the containing block
+    pub fn as_px(&self) -> Option<f32> {
+        match self {
+            LengthOrPercentage::Px(px) => Some(*px),
+            LengthOrPercentage::Percent(_) | LengthOrPercentage::Calc(_) => None,
+        }
+    }
+
+    /// Computed value of a length, percentage or math function; other
+    /// values give `None`
+    pub fn from_value(value: &Value, ctx: &LengthContext) -> Option<Self> {
+        match value {
+            Value::Percentage(pct) => Some(LengthOrPercentage::Percent(*pct)),
+            Value::Calc(expr) if expr.has_percentage() => {
+                Some(LengthOrPercentage::Calc(Rc::new(expr.absolutize(ctx))))
+            }
+            _ => value.to_px(ctx).map(LengthOrPercentage::Px),
         }
     }
 }
@@ -169,6 +268,143 @@ pub enum Display {
     TableCaption,
     TableColumn,
     TableColumnGroup,
+    /// A block box with a `::marker`
+    ListItem,
+}
+
+/// Marker style of a list item, also used by `counter()` and `counters()`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ListStyleType {
+    None,
+    #[default]
+    Disc,
+    Circle,
+    Square,
+    Decimal,
+    DecimalLeadingZero,
+    LowerRoman,
+    UpperRoman,
+    LowerAlpha,
+    UpperAlpha,
+}
+
+impl ListStyleType {
+    pub fn from_keyword(kw: &str) -> Option<Self> {
+        let style = match kw {
+            "none" => ListStyleType::None,
+            "disc" => ListStyleType::Disc,
+            "circle" => ListStyleType::Circle,
+            "square" => ListStyleType::Square,
+            "decimal" => ListStyleType::Decimal,
+            "decimal-leading-zero" => ListStyleType::DecimalLeadingZero,
+            "lower-roman" => ListStyleType::LowerRoman,
+            "upper-roman" => ListStyleType::UpperRoman,
+            "lower-alpha" | "lower-latin" => ListStyleType::LowerAlpha,
+            "upper-alpha" | "upper-latin" => ListStyleType::UpperAlpha,
+            _ => return None,
+        };
+        Some(style)
+    }
+
+    pub(super) fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::None => Some(ListStyleType::None),
+            value => value.as_keyword().and_then(Self::from_keyword),
+        }
+    }
+}
+
+/// Whether a list item's marker sits outside its box or starts its first line
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ListStylePosition {
+    #[default]
+    Outside,
+    Inside,
+}
+
+/// The `content` property
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Content {
+    /// No `::before`/`::after` box; the default marker for `::marker`
+    #[default]
+    Normal,
+    None,
+    Items(Vec<ContentItem>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContentItem {
+    String(String),
+    /// `attr(name)`: the element's attribute, or nothing
+    Attr(String),
+    /// `counter(name, style)`: the innermost counter's value
+    Counter(String, ListStyleType),
+    /// `counters(name, separator, style)`: all nested counters' values
+    Counters(String, String, ListStyleType),
+    OpenQuote,
+    CloseQuote,
+    /// Nest quotes one level deeper without inserting a mark
+    NoOpenQuote,
+    NoCloseQuote,
+}
+
+impl ContentItem {
+    fn from_value(value: &Value) -> Option<Self> {
+        let item = match value {
+            Value::String(s) => ContentItem::String(s.clone()),
+            Value::Keyword(kw) => match kw.as_str() {
+                "open-quote" => ContentItem::OpenQuote,
+                "close-quote" => ContentItem::CloseQuote,
+                "no-open-quote" => ContentItem::NoOpenQuote,
+                "no-close-quote" => ContentItem::NoCloseQuote,
+                _ => return None,
+            },
+            Value::Function(name, args) => {
+                let style = |arg: Option<&Value>| match arg {
+                    Some(value) => ListStyleType::from_value(value),
+                    None => Some(ListStyleType::Decimal),
+                };
+                match (name.as_str(), args.as_slice()) {
+                    ("attr", [Value::Keyword(attr)]) => ContentItem::Attr(attr.to_ascii_lowercase()),
+                    ("counter", [Value::Keyword(counter), rest @ ..]) if rest.len() <= 1 => {
+                        ContentItem::Counter(counter.clone(), style(rest.first())?)
+                    }
+                    ("counters", [Value::Keyword(counter), Value::String(separator), rest @ ..]) if rest.len() <= 1 => {
+                        ContentItem::Counters(counter.clone(), separator.clone(), style(rest.first())?)
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        Some(item)
+    }
+}
+
+/// Quotation marks inserted by `open-quote` and `close-quote`, by nesting level
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Quotes {
+    /// Curly double quotes, then curly single quotes when nested
+    #[default]
+    Auto,
+    None,
+    Pairs(Rc<Vec<(String, String)>>),
+}
+
+impl Quotes {
+    /// The open and close marks at nesting `depth`; levels deeper than the
+    /// list of pairs use the last one
+    pub fn pair(&self, depth: usize) -> Option<(&str, &str)> {
+        match self {
+            Quotes::Auto if depth == 0 => Some(("\u{201c}", "\u{201d}")),
+            Quotes::Auto => Some(("\u{2018}", "\u{2019}")),
+            Quotes::None => None,
+            Quotes::Pairs(pairs) => pairs
+                .get(depth)
+                .or(pairs.last())
+                .map(|(open, close)| (open.as_str(), close.as_str())),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
@@ -312,20 +548,41 @@ pub enum Visibility {
     Collapse,
 }
 
-/// Border style
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
-pub enum BorderStyle {
-    #[default]
-    None,
-    Solid,
-    Dashed,
-    Dotted,
-    Double,
-    Groove,
-    Ridge,
-    Inset,
-    Outset,
-    Hidden,
+/// The top, right, bottom and left values of a 1 to 4 value shorthand like
+/// `margin`; for corners, clockwise from the top left
+pub(super) fn sides<T: Clone>(values: &[T]) -> Option<[T; 4]> {
+    match values {
+        [all] => Some([all.clone(), all.clone(), all.clone(), all.clone()]),
+        [vertical, horizontal] => Some([vertical.clone(), horizontal.clone(), vertical.clone(), horizontal.clone()]),
+        [top, horizontal, bottom] => Some([top.clone(), horizontal.clone(), bottom.clone(), horizontal.clone()]),
+        [top, right, bottom, left] => Some([top.clone(), right.clone(), bottom.clone(), left.clone()]),
+        _ => None,
+    }
+}
+
+/// The horizontal and vertical radii of a rounded corner
+#[derive(Debug, Clone, PartialEq)]
+pub struct CornerRadius {
+    pub x: LengthOrPercentage,
+    pub y: LengthOrPercentage,
+}
+
+impl Default for CornerRadius {
+    fn default() -> Self {
+        Self {
+            x: LengthOrPercentage::Px(0.0),
+            y: LengthOrPercentage::Px(0.0),
+        }
+    }
+}
+
+impl CornerRadius {
+    fn circular(radius: LengthOrPercentage) -> Self {
+        Self {
+            x: radius.clone(),
+            y: radius,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -335,6 +592,32 @@ pub enum FontWeight {
     Numeric(u16),
 }
 
+impl FontWeight {
+    pub fn value(self) -> u16 {
+        match self {
+            FontWeight::Normal => 400,
+            FontWeight::Bold => 700,
+            FontWeight::Numeric(weight) => weight,
+        }
+    }
+
+    /// `bolder` or `lighter` relative to the parent's weight
+    fn relative(keyword: &str, parent: FontWeight) -> Option<Self> {
+        let parent = parent.value();
+        let weight = match keyword {
+            "bolder" if parent < 350 => 400,
+            "bolder" if parent < 550 => 700,
+            "bolder" => parent.max(900),
+            "lighter" if parent < 100 => parent,
+            "lighter" if parent < 550 => 100,
+            "lighter" if parent < 750 => 400,
+            "lighter" => 700,
+            _ => return None,
+        };
+        Some(FontWeight::Numeric(weight))
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TextAlign {
     Left,
@@ -343,12 +626,117 @@ pub enum TextAlign {
     Justify,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum TextDecoration {
+/// The lines `text-decoration-line` draws across text
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TextDecorationLine {
+    pub underline: bool,
+    pub overline: bool,
+    pub line_through: bool,
+}
+
+impl TextDecorationLine {
+    pub const UNDERLINE: Self = Self {
+        underline: true,
+        overline: false,
+        line_through: false,
+    };
+
+    pub fn is_none(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Adds the line a keyword names; false if it names none
+    fn add(&mut self, keyword: &str) -> bool {
+        match keyword {
+            "underline" => self.underline = true,
+            "overline" => self.overline = true,
+            "line-through" => self.line_through = true,
+            // Not drawn, but valid
+            "blink" => {}
+            _ => return false,
+        }
+        true
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TextTransform {
+    #[default]
     None,
-    Underline,
-    LineThrough,
-    Overline,
+    Uppercase,
+    Lowercase,
+    Capitalize,
+}
+
+impl TextTransform {
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "none" => Some(TextTransform::None),
+            "uppercase" => Some(TextTransform::Uppercase),
+            "lowercase" => Some(TextTransform::Lowercase),
+            "capitalize" => Some(TextTransform::Capitalize),
+            _ => None,
+        }
+    }
+
+    /// The text as it's laid out and drawn
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            TextTransform::None => text.to_string(),
+            TextTransform::Uppercase => text.to_uppercase(),
+            TextTransform::Lowercase => text.to_lowercase(),
+            // The first letter of each word; the rest are left alone
+            TextTransform::Capitalize => {
+                let mut result = String::with_capacity(text.len());
+                let mut word_start = true;
+                for ch in text.chars() {
+                    if word_start && ch.is_alphanumeric() {
+                        result.extend(ch.to_uppercase());
+                    } else {
+                        result.push(ch);
+                    }
+                    word_start = ch.is_whitespace() || (word_start && !ch.is_alphanumeric());
+                }
+                result
+            }
+        }
+    }
+}
+
+/// A `text-shadow`: the text drawn again, offset and blurred
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextShadow {
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub blur_radius: f32,
+    /// `None` for `currentcolor`
+    pub color: Option<Color>,
+}
+
+impl TextShadow {
+    /// Two or three lengths and a color, in either order
+    pub(super) fn from_value(value: &Value, ctx: &LengthContext) -> Option<Self> {
+        let mut lengths = Vec::new();
+        let mut color = None;
+        for v in value.as_slice() {
+            if let Some(c) = ComputedStyle::parse_border_color(v) {
+                if color.is_some() {
+                    return None;
+                }
+                color = Some(c);
+            } else {
+                lengths.push(v.to_px(ctx)?);
+            }
+        }
+        let color = color.flatten();
+        match lengths[..] {
+            [offset_x, offset_y] => Some(TextShadow { offset_x, offset_y, blur_radius: 0.0, color }),
+            [offset_x, offset_y, blur_radius] if blur_radius >= 0.0 => {
+                Some(TextShadow { offset_x, offset_y, blur_radius, color })
+            }
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -378,7 +766,10 @@ impl Default for ComputedStyle {
             border_right_width: 0.0,
             border_bottom_width: 0.0,
             border_left_width: 0.0,
-            border_color: Color::BLACK,
+            border_top_color: None,
+            border_right_color: None,
+            border_bottom_color: None,
+            border_left_color: None,
 
             width: None,
             height: None,
@@ -389,12 +780,25 @@ impl Default for ComputedStyle {
 
             color: Color::BLACK,
             background_color: Color::TRANSPARENT,
+            background: Backgrounds::default(),
+            forced_color_adjust: true,
 
             font_size: 16.0,
             font_weight: FontWeight::Normal,
+            font_family: Font::default().families,
+            font_style: FontStyle::Normal,
+            font_stretch: FontStretch::Normal,
             text_align: TextAlign::Left,
             line_height: 1.2,
-            text_decoration: TextDecoration::None,
+            text_decoration_line: TextDecorationLine::default(),
+            text_decoration_style: TextDecorationStyle::default(),
+            text_decoration_color: None,
+            text_decoration_thickness: None,
+            text_transform: TextTransform::default(),
+            letter_spacing: 0.0,
+            word_spacing: 0.0,
+            text_indent: LengthOrPercentage::Px(0.0),
+            text_shadow: Vec::new(),
 
             position: Position::Static,
             top: None,
@@ -433,11 +837,10 @@ impl Default for ComputedStyle {
             grid_row: GridPlacement::default(),
 
             // Visual properties
-            border_radius: 0.0,
-            border_top_left_radius: 0.0,
-            border_top_right_radius: 0.0,
-            border_bottom_left_radius: 0.0,
-            border_bottom_right_radius: 0.0,
+            border_top_left_radius: CornerRadius::default(),
+            border_top_right_radius: CornerRadius::default(),
+            border_bottom_left_radius: CornerRadius::default(),
+            border_bottom_right_radius: CornerRadius::default(),
             box_shadow: None,
             opacity: 1.0,
             overflow: Overflow::default(),
@@ -456,12 +859,17 @@ impl Default for ComputedStyle {
             z_index: None,
 
             // Border styles
-            border_style: BorderStyle::default(),
             border_top_style: BorderStyle::default(),
             border_right_style: BorderStyle::default(),
             border_bottom_style: BorderStyle::default(),
             border_left_style: BorderStyle::default(),
 
+            // Outline
+            outline_width: 3.0,
+            outline_style: BorderStyle::None,
+            outline_color: None,
+            outline_offset: 0.0,
+
             // Table-specific properties
             border_collapse: false,
             border_spacing: 0.0,
@@ -469,12 +877,53 @@ impl Default for ComputedStyle {
             // Float and clear
             float: Float::default(),
             clear: Clear::default(),
+
+            // Lists and generated content
+            list_style_type: ListStyleType::default(),
+            list_style_position: ListStylePosition::default(),
+            content: Content::default(),
+            counter_reset: Vec::new(),
+            counter_increment: Vec::new(),
+            counter_set: Vec::new(),
+            quotes: Quotes::default(),
+
+            // Transforms
+            translate: TransformFunction::NO_TRANSLATE,
+            rotate: TransformFunction::NO_ROTATE,
+            scale: TransformFunction::NO_SCALE,
+            transform: Vec::new(),
+            transform_origin: TransformOrigin::default(),
+
+            // Effects
+            filter: Vec::new(),
+            backdrop_filter: Vec::new(),
+            mix_blend_mode: BlendMode::Normal,
+
+            // Animation
+            transition: Transitions::default(),
+            animation: Animations::default(),
+
+            custom_properties: Rc::default(),
         }
     }
 }
 
 impl ComputedStyle {
-    pub fn apply_value(&mut self, property: &str, value: &Value, parent_font_size: f32, vw: f32, vh: f32) {
+    /// Initial values of every property, as for an element no style
+    /// sheet or user agent default applies to
+    pub fn initial() -> Self {
+        Self {
+            display: Display::Inline,
+            ..Self::default()
+        }
+    }
+
+    pub fn apply_value(&mut self, property: &str, value: &Value, ctx: &LengthContext, defaulting: &Defaulting) {
+        if let Some(keyword) = CssWideKeyword::from_value(value) {
+            self.apply_css_wide_keyword(property, keyword, defaulting);
+            return;
+        }
+
         match property {
             "display" => {
                 if let Some(kw) = value.as_keyword() {
@@ -494,41 +943,117 @@ impl ComputedStyle {
                         "table-caption" => Display::TableCaption,
                         "table-column" => Display::TableColumn,
                         "table-column-group" => Display::TableColumnGroup,
+                        "list-item" => Display::ListItem,
                         _ => Display::Block,
                     };
                 }
             }
 
             "color" => {
-                if let Some(c) = value.to_color() {
+                // currentcolor here is the inherited color
+                let inherited = defaulting.parent.map_or(Color::BLACK, |parent| parent.color);
+                if let Some(c) = value.resolve_color(inherited) {
                     self.color = c;
                 }
             }
 
-            "background-color" | "background" => {
-                if let Some(c) = value.to_color() {
+            "background-color" => {
+                if let Some(c) = value.resolve_color(self.color) {
                     self.background_color = c;
                 }
             }
 
+            "forced-color-adjust" => match value {
+                Value::None => self.forced_color_adjust = false,
+                Value::Keyword(kw) if kw == "auto" => self.forced_color_adjust = true,
+                _ => {}
+            },
+
+            "background-image" => {
+                if let Some(images) = background::parse_images(value, ctx) {
+                    self.background.image = images;
+                }
+            }
+
+            "background-position" => {
+                if let Some(positions) = background::parse_positions(value, ctx) {
+                    self.background.position = positions;
+                }
+            }
+
+            "background-size" => {
+                if let Some(sizes) = background::parse_sizes(value, ctx) {
+                    self.background.size = sizes;
+                }
+            }
+
+            "background-repeat" => {
+                if let Some(repeats) = background::parse_repeats(value) {
+                    self.background.repeat = repeats;
+                }
+            }
+
+            "background-origin" => {
+                if let Some(boxes) = background::parse_boxes(value) {
+                    self.background.origin = boxes;
+                }
+            }
+
+            "background-clip" => {
+                if let Some(boxes) = background::parse_boxes(value) {
+                    self.background.clip = boxes;
+                }
+            }
+
+            "background-attachment" => {
+                if let Some(attachments) = background::parse_attachments(value) {
+                    self.background.attachment = attachments;
+                }
+            }
+
             "font-size" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                    self.font_size = px;
+                // Percentages in font-size are of the parent's font size
+                if let Some(len) = LengthOrPercentage::from_value(value, ctx) {
+                    self.font_size = len.to_px(ctx.font_size);
                 }
             }
 
             "font-weight" => {
                 if let Some(kw) = value.as_keyword() {
+                    let parent = defaulting.parent.map_or(FontWeight::Normal, |parent| parent.font_weight);
                     self.font_weight = match kw {
                         "bold" => FontWeight::Bold,
                         "normal" => FontWeight::Normal,
-                        _ => FontWeight::Normal,
+                        _ => FontWeight::relative(kw, parent).unwrap_or(FontWeight::Normal),
                     };
                 } else if let Value::Number(n) = value {
-                    self.font_weight = FontWeight::Numeric(*n as u16);
+                    self.font_weight = FontWeight::Numeric(n.clamp(1.0, 1000.0) as u16);
                 }
             }
 
+            "font-family" => {
+                let items: Vec<&[Value]> = value.as_comma_list().iter().map(Value::as_slice).collect();
+                if let Some(families) = Self::parse_font_family(&items) {
+                    self.font_family = families;
+                }
+            }
+
+            "font-style" => {
+                // An oblique angle is ignored; fonts only have one
+                if let Some(style) = value.as_slice().first().and_then(Value::as_keyword).and_then(FontStyle::from_keyword) {
+                    self.font_style = style;
+                }
+            }
+
+            "font-stretch" => match value {
+                Value::Percentage(p) => self.font_stretch = FontStretch::from_percentage(*p),
+                value => {
+                    if let Some(stretch) = value.as_keyword().and_then(FontStretch::from_keyword) {
+                        self.font_stretch = stretch;
+                    }
+                }
+            },
+
             "text-align" => {
                 if let Some(kw) = value.as_keyword() {
                     self.text_align = match kw {
@@ -541,240 +1066,146 @@ impl ComputedStyle {
                 }
             }
 
-            "text-decoration" => {
-                if let Some(kw) = value.as_keyword() {
-                    self.text_decoration = match kw {
-                        "none" => TextDecoration::None,
-                        "underline" => TextDecoration::Underline,
-                        "line-through" => TextDecoration::LineThrough,
-                        "overline" => TextDecoration::Overline,
-                        _ => TextDecoration::None,
-                    };
+            "text-decoration-line" => {
+                if let Some(line) = Self::parse_decoration_line(value) {
+                    self.text_decoration_line = line;
                 }
             }
-
-            "line-height" => {
-                if let Value::Number(n) = value {
-                    self.line_height = *n;
-                } else if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                    self.line_height = px / self.font_size;
+            "text-decoration-style" => {
+                if let Some(style) = value.as_keyword().and_then(TextDecorationStyle::from_keyword) {
+                    self.text_decoration_style = style;
+                }
+            }
+            "text-decoration-color" => {
+                if let Some(color) = Self::parse_border_color(value) {
+                    self.text_decoration_color = color;
+                }
+            }
+            "text-decoration-thickness" => {
+                if let Some(thickness) = Self::parse_decoration_thickness(value, ctx) {
+                    self.text_decoration_thickness = thickness;
+                }
+            }
+            "text-transform" => {
+                let keyword = if let Value::None = value { Some("none") } else { value.as_keyword() };
+                if let Some(transform) = keyword.and_then(TextTransform::from_keyword) {
+                    self.text_transform = transform;
                 }
             }
 
-            // font shorthand: font: [style] [variant] [weight] size[/line-height] family
-            // Simplified parsing - we primarily care about font-size and line-height
-            "font" => {
-                match value {
-                    Value::List(values) => {
-                        let mut found_size = false;
-                        for (i, v) in values.iter().enumerate() {
-                            // Try to extract font-size (will be a length value)
-                            if let Value::Length(px, _) = v {
-                                if *px >= 1.0 && *px <= 200.0 {
-                                    self.font_size = *px;
-                                    found_size = true;
-                                    // Check if next non-empty value is a number (line-height)
-                                    // The / delimiter becomes an empty keyword, so skip it
-                                    for next_v in values.iter().skip(i + 1) {
-                                        match next_v {
-                                            Value::Number(n) if *n > 0.0 && *n < 10.0 => {
-                                                self.line_height = *n;
-                                                break;
-                                            }
-                                            Value::Keyword(k) if k.is_empty() => continue,
-                                            _ => break,
-                                        }
-                                    }
-                                    break;
-                                }
-                            }
-                        }
-                        // If no length found, try numbers (for unitless font-size)
-                        if !found_size {
-                            for v in values {
-                                if let Value::Number(n) = v {
-                                    if *n >= 1.0 && *n <= 200.0 {
-                                        self.font_size = *n;
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Value::Length(px, _) => {
-                        self.font_size = *px;
-                    }
-                    Value::Number(n) => {
-                        if *n >= 1.0 && *n <= 200.0 {
-                            self.font_size = *n;
-                        }
+            "letter-spacing" | "word-spacing" => {
+                let spacing = match value {
+                    Value::Keyword(kw) if kw == "normal" => Some(0.0),
+                    value => value.to_px(ctx),
+                };
+                if let Some(px) = spacing {
+                    match property {
+                        "letter-spacing" => self.letter_spacing = px,
+                        _ => self.word_spacing = px,
                     }
-                    _ => {}
                 }
             }
 
-            "margin" => {
-                match value {
-                    Value::List(values) => {
-                        let vals: Vec<f32> = values
-                            .iter()
-                            .filter_map(|v| v.to_px(parent_font_size, vw, vh))
-                            .collect();
-                        match vals.len() {
-                            1 => {
-                                self.margin_top = vals[0];
-                                self.margin_right = vals[0];
-                                self.margin_bottom = vals[0];
-                                self.margin_left = vals[0];
-                            }
-                            2 => {
-                                self.margin_top = vals[0];
-                                self.margin_bottom = vals[0];
-                                self.margin_right = vals[1];
-                                self.margin_left = vals[1];
-                            }
-                            3 => {
-                                self.margin_top = vals[0];
-                                self.margin_right = vals[1];
-                                self.margin_left = vals[1];
-                                self.margin_bottom = vals[2];
-                            }
-                            4 => {
-                                self.margin_top = vals[0];
-                                self.margin_right = vals[1];
-                                self.margin_bottom = vals[2];
-                                self.margin_left = vals[3];
-                            }
-                            _ => {}
-                        }
-                    }
-                    _ => {
-                        if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                            self.margin_top = px;
-                            self.margin_right = px;
-                            self.margin_bottom = px;
-                            self.margin_left = px;
-                        }
-                    }
+            "text-indent" => {
+                if let Some(indent) = LengthOrPercentage::from_value(value, ctx) {
+                    self.text_indent = indent;
+                }
+            }
+
+            "text-shadow" => {
+                self.text_shadow = match value {
+                    Value::None => Vec::new(),
+                    // One shadow that isn't understood makes the whole value invalid
+                    value => match value.as_comma_list().iter().map(|v| TextShadow::from_value(v, ctx)).collect() {
+                        Some(shadows) => shadows,
+                        None => return,
+                    },
+                };
+            }
+
+            "line-height" => {
+                if let Value::Number(n) = value {
+                    self.line_height = *n;
+                } else if let Some(px) = value.to_px(ctx) {
+                    self.line_height = px / self.font_size;
                 }
             }
+
             "margin-top" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.margin_top = px;
                 }
             }
             "margin-right" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.margin_right = px;
                 }
             }
             "margin-bottom" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.margin_bottom = px;
                 }
             }
             "margin-left" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.margin_left = px;
                 }
             }
 
-            "padding" => {
-                match value {
-                    Value::List(values) => {
-                        let vals: Vec<f32> = values
-                            .iter()
-                            .filter_map(|v| v.to_px(parent_font_size, vw, vh))
-                            .collect();
-                        match vals.len() {
-                            1 => {
-                                self.padding_top = vals[0];
-                                self.padding_right = vals[0];
-                                self.padding_bottom = vals[0];
-                                self.padding_left = vals[0];
-                            }
-                            2 => {
-                                self.padding_top = vals[0];
-                                self.padding_bottom = vals[0];
-                                self.padding_right = vals[1];
-                                self.padding_left = vals[1];
-                            }
-                            3 => {
-                                self.padding_top = vals[0];
-                                self.padding_right = vals[1];
-                                self.padding_left = vals[1];
-                                self.padding_bottom = vals[2];
-                            }
-                            4 => {
-                                self.padding_top = vals[0];
-                                self.padding_right = vals[1];
-                                self.padding_bottom = vals[2];
-                                self.padding_left = vals[3];
-                            }
-                            _ => {}
-                        }
-                    }
-                    _ => {
-                        if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                            self.padding_top = px;
-                            self.padding_right = px;
-                            self.padding_bottom = px;
-                            self.padding_left = px;
-                        }
-                    }
-                }
-            }
             "padding-top" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.padding_top = px;
                 }
             }
             "padding-right" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.padding_right = px;
                 }
             }
             "padding-bottom" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.padding_bottom = px;
                 }
             }
             "padding-left" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.padding_left = px;
                 }
             }
 
-            "border-width" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                    self.border_top_width = px;
-                    self.border_right_width = px;
-                    self.border_bottom_width = px;
-                    self.border_left_width = px;
+            "border-top-width" | "border-right-width" | "border-bottom-width" | "border-left-width" => {
+                if let Some(px) = Self::parse_border_width(value, ctx) {
+                    match property {
+                        "border-top-width" => self.border_top_width = px,
+                        "border-right-width" => self.border_right_width = px,
+                        "border-bottom-width" => self.border_bottom_width = px,
+                        _ => self.border_left_width = px,
+                    }
                 }
             }
-            "border-color" => {
-                if let Some(c) = value.to_color() {
-                    self.border_color = c;
+            "border-top-color" | "border-right-color" | "border-bottom-color" | "border-left-color" => {
+                if let Some(color) = Self::parse_border_color(value) {
+                    match property {
+                        "border-top-color" => self.border_top_color = color,
+                        "border-right-color" => self.border_right_color = color,
+                        "border-bottom-color" => self.border_bottom_color = color,
+                        _ => self.border_left_color = color,
+                    }
                 }
             }
 
             "width" => {
                 if let Value::Auto = value {
                     self.width = None;
-                } else if let Value::Percentage(pct) = value {
-                    self.width = Some(LengthOrPercentage::Percent(*pct));
-                } else if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                    self.width = Some(LengthOrPercentage::Px(px));
+                } else if let Some(len) = LengthOrPercentage::from_value(value, ctx) {
+                    self.width = Some(len);
                 }
             }
             "height" => {
                 if let Value::Auto = value {
                     self.height = None;
-                } else if let Value::Percentage(pct) = value {
-                    self.height = Some(LengthOrPercentage::Percent(*pct));
-                } else if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                    self.height = Some(LengthOrPercentage::Px(px));
+                } else if let Some(len) = LengthOrPercentage::from_value(value, ctx) {
+                    self.height = Some(len);
                 }
             }
 
@@ -791,22 +1222,22 @@ impl ComputedStyle {
             }
 
             "top" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.top = Some(px);
                 }
             }
             "right" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.right = Some(px);
                 }
             }
             "bottom" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.bottom = Some(px);
                 }
             }
             "left" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.left = Some(px);
                 }
             }
@@ -877,7 +1308,7 @@ impl ComputedStyle {
             }
 
             "gap" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.gap = px;
                     self.row_gap = px;
                     self.column_gap = px;
@@ -885,13 +1316,13 @@ impl ComputedStyle {
             }
 
             "row-gap" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.row_gap = px;
                 }
             }
 
             "column-gap" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.column_gap = px;
                 }
             }
@@ -912,35 +1343,11 @@ impl ComputedStyle {
             "flex-basis" => {
                 if let Value::Auto = value {
                     self.flex_basis = None;
-                } else if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                } else if let Some(px) = value.to_px(ctx) {
                     self.flex_basis = Some(px);
                 }
             }
 
-            "flex" => {
-                // Shorthand: flex: <grow> <shrink>? <basis>?
-                // Common values: flex: 1 means flex: 1 1 0
-                if let Value::Number(n) = value {
-                    self.flex_grow = *n;
-                    self.flex_shrink = 1.0;
-                    self.flex_basis = Some(0.0);
-                } else if let Some(kw) = value.as_keyword() {
-                    match kw {
-                        "auto" => {
-                            self.flex_grow = 1.0;
-                            self.flex_shrink = 1.0;
-                            self.flex_basis = None;
-                        }
-                        "none" => {
-                            self.flex_grow = 0.0;
-                            self.flex_shrink = 0.0;
-                            self.flex_basis = None;
-                        }
-                        _ => {}
-                    }
-                }
-            }
-
             "align-self" => {
                 if let Some(kw) = value.as_keyword() {
                     self.align_self = Some(match kw {
@@ -965,23 +1372,19 @@ impl ComputedStyle {
             "grid-template-columns" => {
                 // Parse is handled separately for complex track lists
                 // For simple cases, parse keywords
-                if let Some(kw) = value.as_keyword() {
-                    if kw == "none" {
-                        self.grid_template_columns.clear();
-                    }
+                if let Value::None = value {
+                    self.grid_template_columns.clear();
                 }
             }
 
             "grid-template-rows" => {
-                if let Some(kw) = value.as_keyword() {
-                    if kw == "none" {
-                        self.grid_template_rows.clear();
-                    }
+                if let Value::None = value {
+                    self.grid_template_rows.clear();
                 }
             }
 
             "grid-gap" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.grid_gap = px;
                     self.grid_row_gap = px;
                     self.grid_column_gap = px;
@@ -989,73 +1392,57 @@ impl ComputedStyle {
             }
 
             "grid-row-gap" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.grid_row_gap = px;
                 }
             }
 
             "grid-column-gap" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.grid_column_gap = px;
                 }
             }
 
-            "grid-column-start" => {
-                if let Value::Number(n) = value {
-                    self.grid_column.start = Some(*n as i32);
-                }
-            }
+            "grid-column-start" => match value {
+                Value::Number(n) => self.grid_column.start = Some(*n as i32),
+                Value::Auto => self.grid_column.start = None,
+                _ => {}
+            },
 
-            "grid-column-end" => {
-                if let Value::Number(n) = value {
-                    self.grid_column.end = Some(*n as i32);
-                }
-            }
+            "grid-column-end" => match value {
+                Value::Number(n) => self.grid_column.end = Some(*n as i32),
+                Value::Auto => self.grid_column.end = None,
+                _ => {}
+            },
 
-            "grid-row-start" => {
-                if let Value::Number(n) = value {
-                    self.grid_row.start = Some(*n as i32);
-                }
-            }
+            "grid-row-start" => match value {
+                Value::Number(n) => self.grid_row.start = Some(*n as i32),
+                Value::Auto => self.grid_row.start = None,
+                _ => {}
+            },
 
-            "grid-row-end" => {
-                if let Value::Number(n) = value {
-                    self.grid_row.end = Some(*n as i32);
-                }
-            }
+            "grid-row-end" => match value {
+                Value::Number(n) => self.grid_row.end = Some(*n as i32),
+                Value::Auto => self.grid_row.end = None,
+                _ => {}
+            },
 
             // Visual properties
-            "border-radius" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                    self.border_radius = px;
-                    self.border_top_left_radius = px;
-                    self.border_top_right_radius = px;
-                    self.border_bottom_left_radius = px;
-                    self.border_bottom_right_radius = px;
-                }
-            }
-
-            "border-top-left-radius" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                    self.border_top_left_radius = px;
-                }
-            }
-
-            "border-top-right-radius" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                    self.border_top_right_radius = px;
-                }
-            }
-
-            "border-bottom-left-radius" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                    self.border_bottom_left_radius = px;
-                }
-            }
-
-            "border-bottom-right-radius" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                    self.border_bottom_right_radius = px;
+            // One radius for a circular corner, or horizontal then vertical
+            "border-top-left-radius" | "border-top-right-radius" | "border-bottom-left-radius"
+            | "border-bottom-right-radius" => {
+                let radius = match value.as_slice() {
+                    [r] => Self::parse_radius(r, ctx).map(CornerRadius::circular),
+                    [x, y] => Self::parse_radius(x, ctx).zip(Self::parse_radius(y, ctx)).map(|(x, y)| CornerRadius { x, y }),
+                    _ => None,
+                };
+                if let Some(radius) = radius {
+                    match property {
+                        "border-top-left-radius" => self.border_top_left_radius = radius,
+                        "border-top-right-radius" => self.border_top_right_radius = radius,
+                        "border-bottom-left-radius" => self.border_bottom_left_radius = radius,
+                        _ => self.border_bottom_right_radius = radius,
+                    }
                 }
             }
 
@@ -1171,70 +1558,68 @@ impl ComputedStyle {
 
             // Min/max dimensions
             "min-width" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                    self.min_width = Some(px);
+                if let Value::Auto = value {
+                    self.min_width = None;
+                } else if let Some(len) = LengthOrPercentage::from_value(value, ctx) {
+                    self.min_width = Some(len);
                 }
             }
             "max-width" => {
                 if let Value::None = value {
                     self.max_width = None;
-                } else if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                    self.max_width = Some(px);
+                } else if let Some(len) = LengthOrPercentage::from_value(value, ctx) {
+                    self.max_width = Some(len);
                 }
             }
             "min-height" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                    self.min_height = Some(px);
+                if let Value::Auto = value {
+                    self.min_height = None;
+                } else if let Some(len) = LengthOrPercentage::from_value(value, ctx) {
+                    self.min_height = Some(len);
                 }
             }
             "max-height" => {
                 if let Value::None = value {
                     self.max_height = None;
-                } else if let Some(px) = value.to_px(parent_font_size, vw, vh) {
-                    self.max_height = Some(px);
+                } else if let Some(len) = LengthOrPercentage::from_value(value, ctx) {
+                    self.max_height = Some(len);
                 }
             }
 
-            // Border shorthand: border: <width> <style> <color>
-            "border" => {
-                self.apply_border_shorthand(value, parent_font_size, vw, vh);
-            }
-            "border-top" => {
-                if let Some((width, style, color)) = Self::parse_border_shorthand(value, parent_font_size, vw, vh) {
-                    if let Some(w) = width { self.border_top_width = w; }
-                    if let Some(s) = style { self.border_top_style = s; }
-                    if let Some(c) = color { self.border_color = c; }
+            "border-top-style" | "border-right-style" | "border-bottom-style" | "border-left-style" => {
+                if let Some(style) = Self::parse_border_style(value) {
+                    match property {
+                        "border-top-style" => self.border_top_style = style,
+                        "border-right-style" => self.border_right_style = style,
+                        "border-bottom-style" => self.border_bottom_style = style,
+                        _ => self.border_left_style = style,
+                    }
                 }
             }
-            "border-right" => {
-                if let Some((width, style, color)) = Self::parse_border_shorthand(value, parent_font_size, vw, vh) {
-                    if let Some(w) = width { self.border_right_width = w; }
-                    if let Some(s) = style { self.border_right_style = s; }
-                    if let Some(c) = color { self.border_color = c; }
+
+            "outline-width" => {
+                if let Some(px) = Self::parse_border_width(value, ctx) {
+                    self.outline_width = px;
                 }
             }
-            "border-bottom" => {
-                if let Some((width, style, color)) = Self::parse_border_shorthand(value, parent_font_size, vw, vh) {
-                    if let Some(w) = width { self.border_bottom_width = w; }
-                    if let Some(s) = style { self.border_bottom_style = s; }
-                    if let Some(c) = color { self.border_color = c; }
+            "outline-style" => {
+                // auto draws the platform's focus ring, for which solid stands in
+                let style = match value {
+                    Value::Auto => Some(BorderStyle::Solid),
+                    value => Self::parse_border_style(value).filter(|style| *style != BorderStyle::Hidden),
+                };
+                if let Some(style) = style {
+                    self.outline_style = style;
                 }
             }
-            "border-left" => {
-                if let Some((width, style, color)) = Self::parse_border_shorthand(value, parent_font_size, vw, vh) {
-                    if let Some(w) = width { self.border_left_width = w; }
-                    if let Some(s) = style { self.border_left_style = s; }
-                    if let Some(c) = color { self.border_color = c; }
+            "outline-color" => {
+                if let Some(color) = Self::parse_border_color(value) {
+                    self.outline_color = color;
                 }
             }
-            "border-style" => {
-                if let Some(kw) = value.as_keyword() {
-                    let style = Self::parse_border_style(kw);
-                    self.border_style = style;
-                    self.border_top_style = style;
-                    self.border_right_style = style;
-                    self.border_bottom_style = style;
-                    self.border_left_style = style;
+            "outline-offset" => {
+                if let Some(px) = value.to_px(ctx) {
+                    self.outline_offset = px;
                 }
             }
 
@@ -1245,7 +1630,7 @@ impl ComputedStyle {
                 }
             }
             "border-spacing" => {
-                if let Some(px) = value.to_px(parent_font_size, vw, vh) {
+                if let Some(px) = value.to_px(ctx) {
                     self.border_spacing = px;
                 }
             }
@@ -1273,220 +1658,567 @@ impl ComputedStyle {
                 }
             }
 
-            _ => {}
-        }
-    }
-
-    fn apply_border_shorthand(&mut self, value: &Value, parent_font_size: f32, vw: f32, vh: f32) {
-        if let Some((width, style, color)) = Self::parse_border_shorthand(value, parent_font_size, vw, vh) {
-            if let Some(w) = width {
-                self.border_top_width = w;
-                self.border_right_width = w;
-                self.border_bottom_width = w;
-                self.border_left_width = w;
+            "list-style-type" => {
+                if let Some(style) = ListStyleType::from_value(value) {
+                    self.list_style_type = style;
+                }
             }
-            if let Some(s) = style {
-                self.border_style = s;
-                self.border_top_style = s;
-                self.border_right_style = s;
-                self.border_bottom_style = s;
-                self.border_left_style = s;
+            "list-style-position" => {
+                if let Some(position) = value.as_keyword().and_then(Self::parse_list_style_position) {
+                    self.list_style_position = position;
+                }
             }
-            if let Some(c) = color {
-                self.border_color = c;
+            "content" => {
+                self.content = match value {
+                    Value::None => Content::None,
+                    Value::Keyword(kw) if kw == "normal" => Content::Normal,
+                    // An item that isn't understood makes the whole value invalid
+                    value => match value.as_slice().iter().map(ContentItem::from_value).collect() {
+                        Some(items) => Content::Items(items),
+                        None => return,
+                    },
+                };
             }
-        }
-    }
 
-    fn parse_border_shorthand(value: &Value, parent_font_size: f32, vw: f32, vh: f32) -> Option<(Option<f32>, Option<BorderStyle>, Option<Color>)> {
-        let mut width = None;
-        let mut style = None;
-        let mut color = None;
+            "counter-reset" => self.counter_reset = Self::parse_counter_list(value, 0),
+            "counter-increment" => self.counter_increment = Self::parse_counter_list(value, 1),
+            "counter-set" => self.counter_set = Self::parse_counter_list(value, 0),
 
-        let values = match value {
-            Value::List(vs) => vs.as_slice(),
-            _ => std::slice::from_ref(value),
-        };
+            "quotes" => {
+                self.quotes = match value {
+                    Value::Auto => Quotes::Auto,
+                    Value::None => Quotes::None,
+                    Value::List(values) if values.len() % 2 == 0 => {
+                        let strings: Option<Vec<String>> = values
+                            .iter()
+                            .map(|v| match v {
+                                Value::String(s) => Some(s.clone()),
+                                _ => None,
+                            })
+                            .collect();
+                        match strings {
+                            Some(strings) => Quotes::Pairs(Rc::new(
+                                strings.chunks(2).map(|pair| (pair[0].clone(), pair[1].clone())).collect(),
+                            )),
+                            None => return,
+                        }
+                    }
+                    _ => return,
+                };
+            }
 
-        for v in values {
-            // Try as border style first (keywords like solid, dashed)
-            if let Some(kw) = v.as_keyword() {
-                let parsed_style = Self::parse_border_style(kw);
-                if parsed_style != BorderStyle::None || kw == "none" {
-                    style = Some(parsed_style);
-                    continue;
+            "transform" => {
+                if let Some(functions) = transform::parse_transform_list(value, ctx) {
+                    self.transform = functions;
+                }
+            }
+            "transform-origin" => {
+                if let Some(origin) = transform::parse_origin(value, ctx) {
+                    self.transform_origin = origin;
+                }
+            }
+            "translate" => {
+                if let Some(translate) = transform::parse_translate(value, ctx) {
+                    self.translate = translate;
+                }
+            }
+            "rotate" => {
+                if let Some(rotate) = transform::parse_rotate(value) {
+                    self.rotate = rotate;
+                }
+            }
+            "scale" => {
+                if let Some(scale) = transform::parse_scale(value) {
+                    self.scale = scale;
                 }
             }
 
-            // Try as color
-            if let Some(c) = v.to_color() {
-                color = Some(c);
-                continue;
+            "filter" => {
+                if let Some(functions) = filter::parse_filter_list(value, ctx) {
+                    self.filter = functions;
+                }
+            }
+            "backdrop-filter" => {
+                if let Some(functions) = filter::parse_filter_list(value, ctx) {
+                    self.backdrop_filter = functions;
+                }
+            }
+            "mix-blend-mode" => {
+                if let Some(mode) = filter::parse_blend_mode(value) {
+                    self.mix_blend_mode = mode;
+                }
             }
 
-            // Try as width
-            if let Some(px) = v.to_px(parent_font_size, vw, vh) {
-                width = Some(px);
-                continue;
+            "transition-property" => {
+                if let Some(properties) = animation::parse_transition_properties(value) {
+                    self.transition.property = properties;
+                }
+            }
+            "transition-duration" => {
+                if let Some(durations) = animation::parse_durations(value) {
+                    self.transition.duration = durations;
+                }
+            }
+            "transition-timing-function" => {
+                if let Some(functions) = animation::parse_timing_functions(value) {
+                    self.transition.timing_function = functions;
+                }
+            }
+            "transition-delay" => {
+                if let Some(delays) = animation::parse_times(value) {
+                    self.transition.delay = delays;
+                }
             }
 
-            // Handle keyword widths
-            if let Some(kw) = v.as_keyword() {
-                match kw {
-                    "thin" => { width = Some(1.0); continue; }
-                    "medium" => { width = Some(3.0); continue; }
-                    "thick" => { width = Some(5.0); continue; }
-                    _ => {}
+            "animation-name" => {
+                if let Some(names) = animation::parse_names(value) {
+                    self.animation.name = names;
+                }
+            }
+            "animation-duration" => {
+                if let Some(durations) = animation::parse_durations(value) {
+                    self.animation.duration = durations;
+                }
+            }
+            "animation-timing-function" => {
+                if let Some(functions) = animation::parse_timing_functions(value) {
+                    self.animation.timing_function = functions;
                 }
             }
+            "animation-delay" => {
+                if let Some(delays) = animation::parse_times(value) {
+                    self.animation.delay = delays;
+                }
+            }
+            "animation-iteration-count" => {
+                if let Some(counts) = animation::parse_iteration_counts(value) {
+                    self.animation.iteration_count = counts;
+                }
+            }
+            "animation-direction" => {
+                if let Some(directions) = animation::parse_directions(value) {
+                    self.animation.direction = directions;
+                }
+            }
+            "animation-fill-mode" => {
+                if let Some(fill_modes) = animation::parse_fill_modes(value) {
+                    self.animation.fill_mode = fill_modes;
+                }
+            }
+            "animation-play-state" => {
+                if let Some(play_states) = animation::parse_play_states(value) {
+                    self.animation.play_state = play_states;
+                }
+            }
+
+            _ => {}
         }
+    }
+
+    /// A border width: a length or `thin`, `medium` or `thick`
+    fn parse_border_width(value: &Value, ctx: &LengthContext) -> Option<f32> {
+        match value.as_keyword() {
+            Some("thin") => Some(1.0),
+            Some("medium") => Some(3.0),
+            Some("thick") => Some(5.0),
+            _ => value.to_px(ctx).filter(|px| *px >= 0.0),
+        }
+    }
+
+    /// A border color, `None` for `currentcolor`
+    fn parse_border_color(value: &Value) -> Option<Option<Color>> {
+        match value.as_keyword() {
+            Some(kw) if kw.eq_ignore_ascii_case("currentcolor") => Some(None),
+            _ => value.to_color().map(Some),
+        }
+    }
 
-        Some((width, style, color))
+    /// A corner radius: a length or a percentage of the border box
+    fn parse_radius(value: &Value, ctx: &LengthContext) -> Option<LengthOrPercentage> {
+        LengthOrPercentage::from_value(value, ctx).filter(|radius| radius.as_px().is_none_or(|px| px >= 0.0))
     }
 
-    fn parse_border_style(kw: &str) -> BorderStyle {
+    fn parse_list_style_position(kw: &str) -> Option<ListStylePosition> {
         match kw {
-            "none" => BorderStyle::None,
-            "solid" => BorderStyle::Solid,
-            "dashed" => BorderStyle::Dashed,
-            "dotted" => BorderStyle::Dotted,
-            "double" => BorderStyle::Double,
-            "groove" => BorderStyle::Groove,
-            "ridge" => BorderStyle::Ridge,
-            "inset" => BorderStyle::Inset,
-            "outset" => BorderStyle::Outset,
-            "hidden" => BorderStyle::Hidden,
-            _ => BorderStyle::None,
+            "outside" => Some(ListStylePosition::Outside),
+            "inside" => Some(ListStylePosition::Inside),
+            _ => None,
+        }
+    }
+
+    /// A `counter-*` value: counter names, each optionally followed by an
+    /// integer, `default` when it's missing
+    fn parse_counter_list(value: &Value, default: i32) -> Vec<(String, i32)> {
+        let mut counters: Vec<(String, i32)> = Vec::new();
+        for v in value.as_slice() {
+            match v {
+                Value::Keyword(name) if !name.is_empty() => counters.push((name.clone(), default)),
+                Value::Number(n) => {
+                    if let Some(counter) = counters.last_mut() {
+                        counter.1 = *n as i32;
+                    }
+                }
+                _ => {}
+            }
+        }
+        counters
+    }
+
+    /// `none`, or any of `underline`, `overline` and `line-through`
+    fn parse_decoration_line(value: &Value) -> Option<TextDecorationLine> {
+        let mut line = TextDecorationLine::default();
+        if let Value::None = value {
+            return Some(line);
+        }
+        for v in value.as_slice() {
+            if !v.as_keyword().is_some_and(|kw| line.add(kw)) {
+                return None;
+            }
+        }
+        Some(line)
+    }
+
+    /// A length, a percentage of the font size, or `auto`/`from-font` as
+    /// `Some(None)`
+    fn parse_decoration_thickness(value: &Value, ctx: &LengthContext) -> Option<Option<f32>> {
+        match value {
+            Value::Auto => Some(None),
+            Value::Keyword(kw) if kw == "from-font" => Some(None),
+            Value::Percentage(p) => Some(Some(ctx.font_size * p / 100.0)),
+            value => value.to_px(ctx).filter(|px| *px >= 0.0).map(Some),
+        }
+    }
+
+    pub(super) fn parse_border_style(value: &Value) -> Option<BorderStyle> {
+        if let Value::None = value {
+            return Some(BorderStyle::None);
+        }
+        match value.as_keyword()? {
+            "solid" => Some(BorderStyle::Solid),
+            "dashed" => Some(BorderStyle::Dashed),
+            "dotted" => Some(BorderStyle::Dotted),
+            "double" => Some(BorderStyle::Double),
+            "groove" => Some(BorderStyle::Groove),
+            "ridge" => Some(BorderStyle::Ridge),
+            "inset" => Some(BorderStyle::Inset),
+            "outset" => Some(BorderStyle::Outset),
+            "hidden" => Some(BorderStyle::Hidden),
+            _ => None,
+        }
+    }
+
+    /// Sets `property` and, for a shorthand, each property it covers from
+    /// the style `keyword` takes its values from
+    fn apply_css_wide_keyword(&mut self, property: &str, keyword: CssWideKeyword, defaulting: &Defaulting) {
+        let initial = Self::initial();
+        for longhand in properties::expand(property) {
+            let source = match keyword {
+                CssWideKeyword::Inherit => defaulting.parent,
+                CssWideKeyword::Initial => None,
+                CssWideKeyword::Revert if defaulting.user_agent.is_some() => defaulting.user_agent,
+                CssWideKeyword::Unset | CssWideKeyword::Revert => defaulting.parent.filter(|_| longhand.inherited),
+            };
+            longhand.copy(self, source.unwrap_or(&initial));
+        }
+    }
+
+    /// `font-family` items: quoted names, or identifiers joined by spaces,
+    /// where a single identifier may be a generic family
+    fn parse_font_family(items: &[&[Value]]) -> Option<Rc<[FontFamily]>> {
+        let families = items
+            .iter()
+            .map(|item| match item {
+                [Value::String(name)] => Some(FontFamily::Named(name.clone())),
+                [Value::Keyword(name)] if !name.is_empty() => Some(FontFamily::from_keyword(name)),
+                words => words
+                    .iter()
+                    .map(|word| word.as_keyword().filter(|k| !k.is_empty()))
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|words| words.len() > 1)
+                    .map(|words| FontFamily::Named(words.join(" "))),
+            })
+            .collect::<Option<Vec<_>>>()?;
+        (!families.is_empty()).then(|| Rc::from(families))
+    }
+
+    /// The font text in this style is drawn in
+    pub fn font(&self) -> Font {
+        Font {
+            families: Rc::clone(&self.font_family),
+            weight: self.font_weight.value(),
+            style: self.font_style,
+            stretch: self.font_stretch,
+        }
+    }
+
+    /// Border widths: top, right, bottom, left
+    pub fn border_widths(&self) -> [f32; 4] {
+        [self.border_top_width, self.border_right_width, self.border_bottom_width, self.border_left_width]
+    }
+
+    /// Border colors: top, right, bottom, left, with `currentcolor` resolved
+    pub fn border_colors(&self) -> [Color; 4] {
+        [self.border_top_color, self.border_right_color, self.border_bottom_color, self.border_left_color]
+            .map(|color| color.unwrap_or(self.color))
+    }
+
+    /// The spacing text is laid out with, indenting the first line by
+    /// `indent`
+    pub fn text_spacing(&self, indent: f32) -> TextSpacing {
+        TextSpacing {
+            letter: self.letter_spacing,
+            word: self.word_spacing,
+            indent,
+        }
+    }
+
+    /// Border styles: top, right, bottom, left
+    pub fn border_styles(&self) -> [BorderStyle; 4] {
+        [self.border_top_style, self.border_right_style, self.border_bottom_style, self.border_left_style]
+    }
+
+    /// The outline's sides, with `currentcolor` resolved, and how far
+    /// outside the border box it reaches
+    pub fn outline(&self) -> (BorderSides, f32) {
+        let sides = BorderSides {
+            widths: [self.outline_width; 4],
+            colors: [self.outline_color.unwrap_or(self.color); 4],
+            styles: [self.outline_style; 4],
+        };
+        (sides, self.outline_offset + self.outline_width)
+    }
+
+    /// Replaces the author's colors with the forced colors palette, unless
+    /// `forced-color-adjust: none`. Text takes `LinkText` in links and the
+    /// color it inherits under another forced element, backgrounds keep
+    /// only their transparency, and shadows are dropped.
+    pub fn force_colors(&mut self, parent: Option<&ComputedStyle>, is_link: bool, is_root: bool) {
+        if !self.forced_color_adjust {
+            return;
+        }
+        let forced = |name| color::forced_color(name).unwrap_or(Color::BLACK);
+        self.color = match parent {
+            _ if is_link => forced("linktext"),
+            Some(parent) if parent.forced_color_adjust && !is_root => parent.color,
+            _ => forced("canvastext"),
+        };
+        let alpha = if is_root { 1.0 } else { self.background_color.a };
+        self.background_color = Color {
+            a: alpha,
+            ..forced("canvas")
+        };
+        self.border_top_color = None;
+        self.border_right_color = None;
+        self.border_bottom_color = None;
+        self.border_left_color = None;
+        self.outline_color = None;
+        self.text_decoration_color = None;
+        self.box_shadow = None;
+        self.text_shadow.clear();
+    }
+
+    /// Corner radii in pixels for a `width` by `height` border box
+    pub fn corner_radii(&self, width: f32, height: f32) -> CornerRadii {
+        let resolve = |radius: &CornerRadius| [radius.x.to_px(width).max(0.0), radius.y.to_px(height).max(0.0)];
+        CornerRadii {
+            top_left: resolve(&self.border_top_left_radius),
+            top_right: resolve(&self.border_top_right_radius),
+            bottom_right: resolve(&self.border_bottom_right_radius),
+            bottom_left: resolve(&self.border_bottom_left_radius),
+        }
+        .fit(width, height)
+    }
+
+    pub fn has_transform(&self) -> bool {
+        !self.transform.is_empty()
+            || !self.translate.is_identity()
+            || !self.rotate.is_identity()
+            || !self.scale.is_identity()
+    }
+
+    /// The transform the box is drawn with, for its border box at
+    /// `border_box`, around its transform origin; `None` when it has none
+    pub fn transform_matrix(&self, border_box: &Rect) -> Option<Transform> {
+        if !self.has_transform() {
+            return None;
+        }
+        let (width, height) = (border_box.width, border_box.height);
+        let matrix = [&self.translate, &self.rotate, &self.scale]
+            .into_iter()
+            .chain(&self.transform)
+            .fold(Transform::IDENTITY, |matrix, function| {
+                matrix.multiply(&function.to_transform(width, height))
+            });
+        let origin = &self.transform_origin;
+        let x = border_box.x + origin.position.x.resolve(width);
+        let y = border_box.y + origin.position.y.resolve(height);
+        Some(
+            Transform::translate(x, y, origin.z)
+                .multiply(&matrix)
+                .multiply(&Transform::translate(-x, -y, -origin.z)),
+        )
+    }
+
+    /// What the box and its descendants are drawn with as a group; `None`
+    /// when they can be drawn as they are
+    pub fn effects(&self) -> Option<Effects> {
+        let effects = Effects {
+            opacity: self.opacity,
+            filter: self.filter.iter().map(|function| function.to_op(self.color)).collect(),
+            backdrop_filter: self.backdrop_filter.iter().map(|function| function.to_op(self.color)).collect(),
+            blend_mode: self.mix_blend_mode,
+        };
+        (!effects.is_none()).then_some(effects)
+    }
+
+    /// A side whose border style is `none` or `hidden` has no border, so
+    /// its width computes to 0. Done once all declarations are applied, as
+    /// the width and style may be set in either order.
+    pub fn clear_unstyled_border_widths(&mut self) {
+        let sides = [
+            (self.border_top_style, &mut self.border_top_width),
+            (self.border_right_style, &mut self.border_right_width),
+            (self.border_bottom_style, &mut self.border_bottom_width),
+            (self.border_left_style, &mut self.border_left_width),
+        ];
+        for (style, width) in sides {
+            if matches!(style, BorderStyle::None | BorderStyle::Hidden) {
+                *width = 0.0;
+            }
         }
     }
 
     pub fn for_tag(tag: &str) -> Self {
         let mut style = Self::default();
+        style.apply_tag_defaults(tag);
+        style
+    }
 
+    /// Applies the user agent's built-in defaults for elements named `tag`
+    pub fn apply_tag_defaults(&mut self, tag: &str) {
         match tag {
             "h1" => {
-                style.font_size = 32.0;
-                style.font_weight = FontWeight::Bold;
-                style.margin_top = 21.44;
-                style.margin_bottom = 21.44;
+                self.font_size = 32.0;
+                self.font_weight = FontWeight::Bold;
+                self.margin_top = 21.44;
+                self.margin_bottom = 21.44;
             }
             "h2" => {
-                style.font_size = 24.0;
-                style.font_weight = FontWeight::Bold;
-                style.margin_top = 19.92;
-                style.margin_bottom = 19.92;
+                self.font_size = 24.0;
+                self.font_weight = FontWeight::Bold;
+                self.margin_top = 19.92;
+                self.margin_bottom = 19.92;
             }
             "h3" => {
-                style.font_size = 18.72;
-                style.font_weight = FontWeight::Bold;
-                style.margin_top = 18.72;
-                style.margin_bottom = 18.72;
+                self.font_size = 18.72;
+                self.font_weight = FontWeight::Bold;
+                self.margin_top = 18.72;
+                self.margin_bottom = 18.72;
             }
             "h4" => {
-                style.font_size = 16.0;
-                style.font_weight = FontWeight::Bold;
-                style.margin_top = 21.28;
-                style.margin_bottom = 21.28;
+                self.font_size = 16.0;
+                self.font_weight = FontWeight::Bold;
+                self.margin_top = 21.28;
+                self.margin_bottom = 21.28;
             }
             "h5" => {
-                style.font_size = 13.28;
-                style.font_weight = FontWeight::Bold;
-                style.margin_top = 22.17;
-                style.margin_bottom = 22.17;
+                self.font_size = 13.28;
+                self.font_weight = FontWeight::Bold;
+                self.margin_top = 22.17;
+                self.margin_bottom = 22.17;
             }
             "h6" => {
-                style.font_size = 10.72;
-                style.font_weight = FontWeight::Bold;
-                style.margin_top = 24.97;
-                style.margin_bottom = 24.97;
+                self.font_size = 10.72;
+                self.font_weight = FontWeight::Bold;
+                self.margin_top = 24.97;
+                self.margin_bottom = 24.97;
             }
             "p" => {
-                style.margin_top = 16.0;
-                style.margin_bottom = 16.0;
+                self.margin_top = 16.0;
+                self.margin_bottom = 16.0;
             }
             "a" => {
-                style.display = Display::Inline;
-                style.color = Color::rgb(0, 0, 238);
-                style.text_decoration = TextDecoration::Underline;
+                self.display = Display::Inline;
+                self.color = Color::rgb(0, 0, 238);
+                self.text_decoration_line = TextDecorationLine::UNDERLINE;
             }
             "strong" | "b" => {
-                style.display = Display::Inline;
-                style.font_weight = FontWeight::Bold;
+                self.display = Display::Inline;
+                self.font_weight = FontWeight::Bold;
             }
             "em" | "i" => {
-                style.display = Display::Inline;
+                self.display = Display::Inline;
             }
             "span" => {
-                style.display = Display::Inline;
+                self.display = Display::Inline;
             }
             "div" => {
-                style.display = Display::Block;
+                self.display = Display::Block;
             }
             "ul" | "ol" => {
-                style.margin_top = 16.0;
-                style.margin_bottom = 16.0;
-                style.padding_left = 40.0;
+                self.margin_top = 16.0;
+                self.margin_bottom = 16.0;
+                self.padding_left = 40.0;
+                self.counter_reset = vec![("list-item".to_string(), 0)];
             }
             "li" => {
-                style.display = Display::Block;
+                self.display = Display::ListItem;
             }
             "img" => {
-                style.display = Display::InlineBlock;
+                self.display = Display::InlineBlock;
             }
             "br" => {
-                style.display = Display::Block;
+                self.display = Display::Block;
             }
             // Table elements
             "table" => {
-                style.display = Display::Table;
-                style.border_collapse = false;
-                style.border_spacing = 2.0;
+                self.display = Display::Table;
+                self.border_collapse = false;
+                self.border_spacing = 2.0;
             }
             "tr" => {
-                style.display = Display::TableRow;
+                self.display = Display::TableRow;
             }
             "td" => {
-                style.display = Display::TableCell;
-                style.padding_top = 1.0;
-                style.padding_right = 1.0;
-                style.padding_bottom = 1.0;
-                style.padding_left = 1.0;
+                self.display = Display::TableCell;
+                self.padding_top = 1.0;
+                self.padding_right = 1.0;
+                self.padding_bottom = 1.0;
+                self.padding_left = 1.0;
             }
             "th" => {
-                style.display = Display::TableCell;
-                style.font_weight = FontWeight::Bold;
-                style.text_align = TextAlign::Center;
-                style.padding_top = 1.0;
-                style.padding_right = 1.0;
-                style.padding_bottom = 1.0;
-                style.padding_left = 1.0;
+                self.display = Display::TableCell;
+                self.font_weight = FontWeight::Bold;
+                self.text_align = TextAlign::Center;
+                self.padding_top = 1.0;
+                self.padding_right = 1.0;
+                self.padding_bottom = 1.0;
+                self.padding_left = 1.0;
             }
             "thead" => {
-                style.display = Display::TableHeaderGroup;
+                self.display = Display::TableHeaderGroup;
             }
             "tbody" => {
-                style.display = Display::TableRowGroup;
+                self.display = Display::TableRowGroup;
             }
             "tfoot" => {
-                style.display = Display::TableFooterGroup;
+                self.display = Display::TableFooterGroup;
             }
             "caption" => {
-                style.display = Display::TableCaption;
-                style.text_align = TextAlign::Center;
+                self.display = Display::TableCaption;
+                self.text_align = TextAlign::Center;
             }
             "col" => {
-                style.display = Display::TableColumn;
+                self.display = Display::TableColumn;
             }
             "colgroup" => {
-                style.display = Display::TableColumnGroup;
+                self.display = Display::TableColumnGroup;
             }
             // Form elements
             "input" | "button" | "select" | "textarea" => {
-                style.display = Display::InlineBlock;
+                self.display = Display::InlineBlock;
             }
             _ => {}
         }
-
-        style
     }
 }
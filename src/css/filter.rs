@@ -0,0 +1,254 @@
+//! CSS filter effects: the `filter` and `backdrop-filter` function lists,
+//! and `mix-blend-mode`. Functions are kept as computed values, so they can
+//! be animated, and become the renderer's filter steps when painted.
+
+use super::computed::TextShadow;
+use super::stylesheet::{LengthContext, Value};
+use crate::render::filter::{BlendMode, FilterOp};
+use crate::render::painter::Color;
+
+/// One function of a `filter` or `backdrop-filter` list
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterFunction {
+    /// The standard deviation, in pixels
+    Blur(f32),
+    Brightness(f32),
+    Contrast(f32),
+    /// Clamped to 1
+    Grayscale(f32),
+    /// Degrees
+    HueRotate(f32),
+    /// Clamped to 1
+    Invert(f32),
+    /// Clamped to 1
+    Opacity(f32),
+    Saturate(f32),
+    /// Clamped to 1
+    Sepia(f32),
+    /// `color` is `None` for `currentcolor`
+    DropShadow { offset_x: f32, offset_y: f32, blur: f32, color: Option<Color> },
+}
+
+impl FilterFunction {
+    /// The function of the same kind that does nothing, which a missing
+    /// function blends as
+    pub fn identity(&self) -> FilterFunction {
+        match self {
+            FilterFunction::Blur(_) => FilterFunction::Blur(0.0),
+            FilterFunction::Brightness(_) => FilterFunction::Brightness(1.0),
+            FilterFunction::Contrast(_) => FilterFunction::Contrast(1.0),
+            FilterFunction::Grayscale(_) => FilterFunction::Grayscale(0.0),
+            FilterFunction::HueRotate(_) => FilterFunction::HueRotate(0.0),
+            FilterFunction::Invert(_) => FilterFunction::Invert(0.0),
+            FilterFunction::Opacity(_) => FilterFunction::Opacity(1.0),
+            FilterFunction::Saturate(_) => FilterFunction::Saturate(1.0),
+            FilterFunction::Sepia(_) => FilterFunction::Sepia(0.0),
+            FilterFunction::DropShadow { .. } => FilterFunction::DropShadow {
+                offset_x: 0.0,
+                offset_y: 0.0,
+                blur: 0.0,
+                color: Some(Color::TRANSPARENT),
+            },
+        }
+    }
+
+    /// The renderer's step for it, with `currentcolor` as `current_color`
+    pub fn to_op(&self, current_color: Color) -> FilterOp {
+        match *self {
+            FilterFunction::Blur(sigma) => FilterOp::Blur(sigma),
+            FilterFunction::Brightness(amount) => FilterOp::brightness(amount),
+            FilterFunction::Contrast(amount) => FilterOp::contrast(amount),
+            FilterFunction::Grayscale(amount) => FilterOp::grayscale(amount),
+            FilterFunction::HueRotate(degrees) => FilterOp::hue_rotate(degrees),
+            FilterFunction::Invert(amount) => FilterOp::invert(amount),
+            FilterFunction::Opacity(amount) => FilterOp::opacity(amount),
+            FilterFunction::Saturate(amount) => FilterOp::saturate(amount),
+            FilterFunction::Sepia(amount) => FilterOp::sepia(amount),
+            FilterFunction::DropShadow { offset_x, offset_y, blur, color } => FilterOp::DropShadow {
+                offset_x,
+                offset_y,
+                blur,
+                color: color.unwrap_or(current_color),
+            },
+        }
+    }
+}
+
+/// A non-negative number or percentage, `default` if left out
+fn amount(args: &[Value], default: f32) -> Option<f32> {
+    let amount = match args {
+        [] => default,
+        [Value::Number(n)] => *n,
+        [Value::Percentage(pct)] => pct / 100.0,
+        _ => return None,
+    };
+    (amount >= 0.0).then_some(amount)
+}
+
+/// `filter` and `backdrop-filter`: `none`, or a space-separated list of
+/// functions. `url()` references to SVG filters aren't supported, which
+/// makes the whole value invalid.
+pub fn parse_filter_list(value: &Value, ctx: &LengthContext) -> Option<Vec<FilterFunction>> {
+    if let Value::None = value {
+        return Some(Vec::new());
+    }
+    value
+        .as_slice()
+        .iter()
+        .map(|function| match function {
+            Value::Function(name, args) => parse_function(name, args, ctx),
+            _ => None,
+        })
+        .collect()
+}
+
+fn parse_function(name: &str, args: &[Value], ctx: &LengthContext) -> Option<FilterFunction> {
+    // Empty parentheses come as one empty keyword
+    let args = match args {
+        [Value::Keyword(keyword)] if keyword.is_empty() => &[],
+        args => args,
+    };
+    let function = match name {
+        "blur" => {
+            let sigma = match args {
+                [] => 0.0,
+                [Value::Number(n)] if *n == 0.0 => 0.0,
+                [length @ (Value::Length(..) | Value::Calc(_))] => length.to_px(ctx)?,
+                _ => return None,
+            };
+            if sigma < 0.0 {
+                return None;
+            }
+            FilterFunction::Blur(sigma)
+        }
+        "brightness" => FilterFunction::Brightness(amount(args, 1.0)?),
+        "contrast" => FilterFunction::Contrast(amount(args, 1.0)?),
+        "grayscale" => FilterFunction::Grayscale(amount(args, 1.0)?.min(1.0)),
+        "invert" => FilterFunction::Invert(amount(args, 1.0)?.min(1.0)),
+        "opacity" => FilterFunction::Opacity(amount(args, 1.0)?.min(1.0)),
+        "saturate" => FilterFunction::Saturate(amount(args, 1.0)?),
+        "sepia" => FilterFunction::Sepia(amount(args, 1.0)?.min(1.0)),
+        "hue-rotate" => match args {
+            [] => FilterFunction::HueRotate(0.0),
+            [angle] => FilterFunction::HueRotate(super::background::parse_angle(angle)?),
+            _ => return None,
+        },
+        "drop-shadow" => {
+            // The same lengths and color as a text shadow
+            let [shadow] = args else {
+                return None;
+            };
+            let TextShadow { offset_x, offset_y, blur_radius, color } = TextShadow::from_value(shadow, ctx)?;
+            FilterFunction::DropShadow { offset_x, offset_y, blur: blur_radius, color }
+        }
+        _ => return None,
+    };
+    Some(function)
+}
+
+/// `mix-blend-mode`: one of the blend mode keywords
+pub fn parse_blend_mode(value: &Value) -> Option<BlendMode> {
+    let Value::Keyword(keyword) = value else {
+        return None;
+    };
+    BlendMode::ALL
+        .into_iter()
+        .find(|mode| mode.name().eq_ignore_ascii_case(keyword))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::parse_css;
+
+    fn value(css_value: &str) -> Value {
+        let stylesheet = parse_css(&format!("a {{ x: {}; }}", css_value));
+        stylesheet.rules[0].declarations[0].value.clone()
+    }
+
+    fn list(css_value: &str) -> Option<Vec<FilterFunction>> {
+        parse_filter_list(&value(css_value), &LengthContext::new(800.0, 600.0))
+    }
+
+    #[test]
+    fn test_parse_filter_list() {
+        assert_eq!(list("none"), Some(Vec::new()));
+        assert_eq!(
+            list("blur(2px) brightness(150%) contrast(0.5) grayscale() hue-rotate(0.5turn)"),
+            Some(vec![
+                FilterFunction::Blur(2.0),
+                FilterFunction::Brightness(1.5),
+                FilterFunction::Contrast(0.5),
+                FilterFunction::Grayscale(1.0),
+                FilterFunction::HueRotate(180.0),
+            ])
+        );
+        // Amounts that can only go so far are clamped
+        assert_eq!(
+            list("invert(2) opacity(300%) saturate(3) sepia(0)"),
+            Some(vec![
+                FilterFunction::Invert(1.0),
+                FilterFunction::Opacity(1.0),
+                FilterFunction::Saturate(3.0),
+                FilterFunction::Sepia(0.0),
+            ])
+        );
+        assert_eq!(
+            list("drop-shadow(2px 4px 1em red)"),
+            Some(vec![FilterFunction::DropShadow {
+                offset_x: 2.0,
+                offset_y: 4.0,
+                blur: 16.0,
+                color: Some(Color::RED),
+            }])
+        );
+        assert_eq!(
+            list("drop-shadow(1px 1px)"),
+            Some(vec![FilterFunction::DropShadow { offset_x: 1.0, offset_y: 1.0, blur: 0.0, color: None }])
+        );
+
+        // Negative amounts, lengths without units and unknown or SVG
+        // filters make the whole value invalid
+        assert_eq!(list("brightness(-1)"), None);
+        assert_eq!(list("blur(-2px)"), None);
+        assert_eq!(list("blur(2)"), None);
+        assert_eq!(list("blur(2px) url(#glow)"), None);
+        assert_eq!(list("sharpen(1)"), None);
+        assert_eq!(list("drop-shadow(1px)"), None);
+    }
+
+    #[test]
+    fn test_parse_blend_mode() {
+        assert_eq!(parse_blend_mode(&value("multiply")), Some(BlendMode::Multiply));
+        assert_eq!(parse_blend_mode(&value("Color-Dodge")), Some(BlendMode::ColorDodge));
+        assert_eq!(parse_blend_mode(&value("normal")), Some(BlendMode::Normal));
+        assert_eq!(parse_blend_mode(&value("add")), None);
+    }
+
+    #[test]
+    fn test_box_effects() {
+        use crate::css::ComputedStyle;
+
+        assert_eq!(ComputedStyle::default().effects(), None);
+
+        let style = ComputedStyle {
+            opacity: 0.5,
+            color: Color::BLUE,
+            filter: list("drop-shadow(1px 2px) grayscale(1)").unwrap(),
+            mix_blend_mode: BlendMode::Screen,
+            ..ComputedStyle::default()
+        };
+        let effects = style.effects().unwrap();
+        assert_eq!(effects.opacity, 0.5);
+        assert_eq!(effects.blend_mode, BlendMode::Screen);
+        assert!(effects.backdrop_filter.is_empty());
+        // The shadow is in the text color when it has none of its own
+        assert_eq!(
+            effects.filter,
+            vec![
+                FilterOp::DropShadow { offset_x: 1.0, offset_y: 2.0, blur: 0.0, color: Color::BLUE },
+                FilterOp::grayscale(1.0),
+            ]
+        );
+    }
+}
@@ -4,8 +4,11 @@
 //! this module indexes rules by their most restrictive selector component
 //! (id > class > tag > universal) for O(1) candidate lookup.
 
-use super::selector::{Selector, SimpleSelector, Specificity};
-use super::stylesheet::{Rule, Stylesheet};
+use super::invalidation::InvalidationMap;
+use super::media::MediaEnvironment;
+use super::selector::{CompoundSelector, PseudoClass, Selector, SimpleSelector, Specificity};
+use super::stylesheet::{Origin, Rule, Stylesheet};
+use crate::dom::ElementState;
 use std::collections::HashMap;
 use std::rc::Rc;
 
@@ -20,6 +23,12 @@ pub struct IndexedRule {
     pub specificity: Specificity,
     /// Order in which the rule appeared (for stable sorting)
     pub source_order: usize,
+    /// The origin of the rule's sheet, which comes before cascade layers
+    pub origin: Origin,
+    /// Rank of the rule's cascade layer among its origin's layers, which
+    /// normal declarations are sorted by before specificity; `usize::MAX`
+    /// for unlayered rules, which win over layered ones
+    pub layer: usize,
 }
 
 /// Index structure for fast selector matching.
@@ -44,28 +53,47 @@ pub struct SelectorIndex {
     by_tag: HashMap<String, Vec<IndexedRule>>,
     /// Rules with only universal selectors
     universal: Vec<IndexedRule>,
+    /// Element state flags some selector looks at
+    state_dependencies: ElementState,
+    /// What changing a class, id, attribute or state on an element restyles
+    invalidation: InvalidationMap,
 }
 
 impl SelectorIndex {
-    /// Build an index from a list of stylesheets.
-    pub fn build(stylesheets: &[Rc<Stylesheet>]) -> Self {
+    /// Build an index from a list of stylesheets, leaving out rules whose
+    /// `@media` conditions don't match `media`.
+    pub fn build(stylesheets: &[Rc<Stylesheet>], media: &MediaEnvironment) -> Self {
         let mut by_id: HashMap<String, Vec<IndexedRule>> = HashMap::new();
         let mut by_class: HashMap<String, Vec<IndexedRule>> = HashMap::new();
         let mut by_tag: HashMap<String, Vec<IndexedRule>> = HashMap::new();
         let mut universal: Vec<IndexedRule> = Vec::new();
+        let mut state_dependencies = ElementState::EMPTY;
+        let mut invalidation = InvalidationMap::new();
 
         let mut source_order = 0;
+        // Each origin has its own layers, even where the names agree
+        let layer_ranks: HashMap<Origin, HashMap<Rc<str>, usize>> = [Origin::UserAgent, Origin::User, Origin::Author]
+            .into_iter()
+            .map(|origin| (origin, layer_ranks(stylesheets.iter().filter(|sheet| sheet.origin == origin))))
+            .collect();
 
         for stylesheet in stylesheets {
-            for rule in &stylesheet.rules {
+            let origin = stylesheet.origin;
+            for rule in stylesheet.rules.iter().filter(|rule| rule.matches_media(media)) {
                 let rule_rc = Rc::new(rule.clone());
+                let layer = rule.layer.as_ref().map_or(usize::MAX, |layer| layer_ranks[&origin][layer]);
 
                 for selector in &rule.selectors {
+                    state_dependencies = state_dependencies | selector.complex.state_dependencies();
+                    invalidation.add_selector(&selector.complex);
+
                     let indexed_rule = IndexedRule {
                         rule: Rc::clone(&rule_rc),
                         selector: selector.clone(),
                         specificity: selector.specificity(),
                         source_order,
+                        origin,
+                        layer,
                     };
                     source_order += 1;
 
@@ -95,33 +123,72 @@ impl SelectorIndex {
             by_class,
             by_tag,
             universal,
+            state_dependencies,
+            invalidation,
         }
     }
 
-    /// Determine the best index key for a selector.
+    /// What changing a feature of an element restyles
+    pub fn invalidation(&self) -> &InvalidationMap {
+        &self.invalidation
+    }
+
+    /// Element state flags that some selector looks at, so changing other
+    /// flags never needs a restyle
+    pub fn state_dependencies(&self) -> ElementState {
+        self.state_dependencies
+    }
+
+    /// Determine the best index key for a selector, looking only at its
+    /// subject since that is the element candidates are looked up for.
     /// Priority: ID > Class > Tag > Universal
     fn get_index_key(selector: &Selector) -> IndexKey {
+        selector
+            .complex
+            .subject()
+            .map(Self::compound_key)
+            .unwrap_or(IndexKey::Universal)
+    }
+
+    fn compound_key(compound: &CompoundSelector) -> IndexKey {
+        let subject = &compound.simple_selectors;
+
         // First, look for ID selectors (most specific)
-        for simple in &selector.simple_selectors() {
+        for simple in subject {
             if let SimpleSelector::Id(id) = simple {
                 return IndexKey::Id(id.clone());
             }
         }
 
         // Then, look for class selectors
-        for simple in &selector.simple_selectors() {
+        for simple in subject {
             if let SimpleSelector::Class(class) = simple {
                 return IndexKey::Class(class.clone());
             }
         }
 
         // Then, look for tag selectors
-        for simple in &selector.simple_selectors() {
+        for simple in subject {
             if let SimpleSelector::Tag(tag) = simple {
                 return IndexKey::Tag(tag.clone());
             }
         }
 
+        // `:is(.a)` or `:where(.a)` with a single argument must match that
+        // argument's subject. Other functional pseudo-classes give no key:
+        // `:not(.a)` matches elements without the class.
+        for simple in subject {
+            if let SimpleSelector::PseudoClass(PseudoClass::Is(args) | PseudoClass::Where(args)) = simple {
+                if let [arg] = args.as_slice() {
+                    if let Some(key) = arg.subject().map(Self::compound_key) {
+                        if !matches!(key, IndexKey::Universal) {
+                            return key;
+                        }
+                    }
+                }
+            }
+        }
+
         // Fall back to universal
         IndexKey::Universal
     }
@@ -179,6 +246,38 @@ enum IndexKey {
     Universal,
 }
 
+/// Ranks of the sheets' cascade layers, from the first to lose. Layers are
+/// ordered by where they first appear, each after its sublayers, so the
+/// rules directly in a layer win over those in its sublayers.
+fn layer_ranks<'a>(stylesheets: impl Iterator<Item = &'a Rc<Stylesheet>>) -> HashMap<Rc<str>, usize> {
+    // Names in first-appearance order; a layer's ancestors come before it
+    let mut layers = Stylesheet::new();
+    for stylesheet in stylesheets {
+        for layer in &stylesheet.layers {
+            layers.declare_layer(Rc::clone(layer));
+        }
+    }
+
+    // Each layer's path of positions among its siblings, ended by one
+    // after them all so that a layer sorts after its sublayers
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    let mut sibling_counts: HashMap<&str, usize> = HashMap::new();
+    let mut keys: Vec<(Vec<usize>, Rc<str>)> = Vec::new();
+    for layer in &layers.layers {
+        let parent = layer.rfind('.').map_or("", |dot| &layer[..dot]);
+        let count = sibling_counts.entry(parent).or_default();
+        positions.insert(layer, *count);
+        *count += 1;
+
+        let mut key: Vec<usize> = layer.match_indices('.').map(|(dot, _)| positions[&layer[..dot]]).collect();
+        key.push(positions[&**layer]);
+        key.push(usize::MAX);
+        keys.push((key, Rc::clone(layer)));
+    }
+    keys.sort();
+    keys.into_iter().enumerate().map(|(rank, (_, layer))| (layer, rank)).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -188,7 +287,7 @@ mod tests {
     fn test_index_by_id() {
         let css = "#main { color: red; }";
         let stylesheet = Rc::new(parse_css(css));
-        let index = SelectorIndex::build(&[stylesheet]);
+        let index = SelectorIndex::build(&[stylesheet], &MediaEnvironment::default());
 
         assert!(index.by_id.contains_key("main"));
         assert_eq!(index.by_id.get("main").unwrap().len(), 1);
@@ -201,7 +300,7 @@ mod tests {
     fn test_index_by_class() {
         let css = ".container { margin: 10px; }";
         let stylesheet = Rc::new(parse_css(css));
-        let index = SelectorIndex::build(&[stylesheet]);
+        let index = SelectorIndex::build(&[stylesheet], &MediaEnvironment::default());
 
         assert!(index.by_id.is_empty());
         assert!(index.by_class.contains_key("container"));
@@ -212,7 +311,7 @@ mod tests {
     fn test_index_by_tag() {
         let css = "div { display: block; }";
         let stylesheet = Rc::new(parse_css(css));
-        let index = SelectorIndex::build(&[stylesheet]);
+        let index = SelectorIndex::build(&[stylesheet], &MediaEnvironment::default());
 
         assert!(index.by_id.is_empty());
         assert!(index.by_class.is_empty());
@@ -224,7 +323,7 @@ mod tests {
         // Compound selector div#main.container should be indexed by ID
         let css = "div#main.container { color: blue; }";
         let stylesheet = Rc::new(parse_css(css));
-        let index = SelectorIndex::build(&[stylesheet]);
+        let index = SelectorIndex::build(&[stylesheet], &MediaEnvironment::default());
 
         // Should be indexed by ID (most specific)
         assert!(index.by_id.contains_key("main"));
@@ -241,7 +340,7 @@ mod tests {
             * { box-sizing: border-box; }
         "#;
         let stylesheet = Rc::new(parse_css(css));
-        let index = SelectorIndex::build(&[stylesheet]);
+        let index = SelectorIndex::build(&[stylesheet], &MediaEnvironment::default());
 
         // Element with id="main", class="container", tag="div"
         let classes = vec!["container".to_string()];
@@ -251,6 +350,19 @@ mod tests {
         assert_eq!(candidates.len(), 4);
     }
 
+    #[test]
+    fn test_functional_pseudo_classes_indexed_by_subject() {
+        let css = ":is(.card) p { color: red; } :where(#nav) { color: blue; } div:not(.a) { color: green; } :is(.a, .b) { color: red; }";
+        let stylesheet = Rc::new(parse_css(css));
+        let index = SelectorIndex::build(&[stylesheet], &MediaEnvironment::default());
+
+        assert!(index.by_tag.contains_key("p"));
+        assert!(index.by_id.contains_key("nav"));
+        assert!(index.by_tag.contains_key("div"));
+        assert!(!index.by_class.contains_key("a"));
+        assert_eq!(index.universal.len(), 1);
+    }
+
     #[test]
     fn test_multiple_stylesheets() {
         let css1 = ".a { color: red; }";
@@ -259,7 +371,7 @@ mod tests {
             Rc::new(parse_css(css1)),
             Rc::new(parse_css(css2)),
         ];
-        let index = SelectorIndex::build(&stylesheets);
+        let index = SelectorIndex::build(&stylesheets, &MediaEnvironment::default());
 
         assert!(index.by_class.contains_key("a"));
         assert!(index.by_class.contains_key("b"));
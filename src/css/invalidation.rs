@@ -0,0 +1,388 @@
+//! Invalidation sets for incremental restyling.
+//!
+//! For each class, id, attribute and element state some selector looks at,
+//! the invalidation map records which elements may match differently when
+//! that feature changes on an element: the element itself, descendants of
+//! certain kinds, following siblings or, through `:has()`, anything. A
+//! change then marks only those elements dirty instead of whole subtrees.
+
+use super::selector::{AttributeSelector, Combinator, ComplexSelector, CompoundSelector, PseudoClass, SimpleSelector};
+use crate::dom::{Document, ElementState, NodeId};
+use std::collections::{HashMap, HashSet};
+use std::ops::BitOr;
+
+/// What of an element's styles is out of date
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RestyleFlags(u8);
+
+impl RestyleFlags {
+    pub const EMPTY: Self = Self(0);
+    /// The element's own style; its children follow only if what they
+    /// inherit changes
+    pub const STYLE: Self = Self(1);
+    /// The styles of the element and everything inside it
+    pub const SUBTREE: Self = Self(1 << 1);
+    /// Some descendant has one of the other flags
+    pub const DESCENDANTS: Self = Self(1 << 2);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+impl BitOr for RestyleFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// Something about an element that can change and that selectors look at
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Feature {
+    Class(String),
+    Id(String),
+    /// An attribute, by lowercase name
+    Attribute(String),
+    /// A single element state flag
+    State(ElementState),
+}
+
+/// The elements around a changed one that may match differently
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InvalidationSet {
+    /// The element itself
+    pub invalidates_self: bool,
+    /// Descendants with one of these classes
+    pub descendant_classes: HashSet<String>,
+    /// Descendants with one of these ids
+    pub descendant_ids: HashSet<String>,
+    /// Descendants of one of these types, lowercase
+    pub descendant_tags: HashSet<String>,
+    /// Every descendant
+    pub whole_subtree: bool,
+    /// Following siblings and everything inside them
+    pub siblings: bool,
+    /// Any element at all, for features inside `:has()`
+    pub whole_document: bool,
+}
+
+impl InvalidationSet {
+    fn self_only() -> Self {
+        Self {
+            invalidates_self: true,
+            ..Self::default()
+        }
+    }
+
+    fn merge(&mut self, other: &InvalidationSet) {
+        self.invalidates_self |= other.invalidates_self;
+        self.descendant_classes.extend(other.descendant_classes.iter().cloned());
+        self.descendant_ids.extend(other.descendant_ids.iter().cloned());
+        self.descendant_tags.extend(other.descendant_tags.iter().cloned());
+        self.whole_subtree |= other.whole_subtree;
+        self.siblings |= other.siblings;
+        self.whole_document |= other.whole_document;
+    }
+
+    /// The elements a change on `node_id` makes dirty, with how
+    pub fn collect(&self, document: &Document, node_id: NodeId, marks: &mut Vec<(NodeId, RestyleFlags)>) {
+        if self.whole_document {
+            marks.push((document.root, RestyleFlags::SUBTREE));
+            return;
+        }
+        if self.invalidates_self {
+            marks.push((node_id, RestyleFlags::STYLE));
+        }
+        if self.whole_subtree {
+            marks.extend(document.element_children(node_id).into_iter().map(|child| (child, RestyleFlags::SUBTREE)));
+        } else if !self.descendant_classes.is_empty()
+            || !self.descendant_ids.is_empty()
+            || !self.descendant_tags.is_empty()
+        {
+            let mut stack = document.element_children(node_id);
+            while let Some(id) = stack.pop() {
+                let Some(element) = document.get_node(id).and_then(|node| node.as_element()) else {
+                    continue;
+                };
+                let matches = element.id().is_some_and(|id| self.descendant_ids.contains(id))
+                    || element.classes().iter().any(|class| self.descendant_classes.contains(class))
+                    || self.descendant_tags.contains(&element.tag_name.to_ascii_lowercase());
+                if matches {
+                    marks.push((id, RestyleFlags::STYLE));
+                }
+                stack.extend(document.element_children(id));
+            }
+        }
+        if self.siblings {
+            if let Some(parent) = document.parent(node_id) {
+                let following = document.element_children(parent).into_iter().skip_while(|&id| id != node_id).skip(1);
+                marks.extend(following.map(|sibling| (sibling, RestyleFlags::SUBTREE)));
+            }
+        }
+    }
+}
+
+/// The invalidation sets of every feature the selectors look at
+#[derive(Debug, Default)]
+pub struct InvalidationMap {
+    sets: HashMap<Feature, InvalidationSet>,
+    /// Some selector depends on an element's position among its siblings
+    /// or on whether it is empty
+    structural: bool,
+    /// Some selector uses `:has()`
+    relative: bool,
+}
+
+impl InvalidationMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_selector(&mut self, selector: &ComplexSelector) {
+        self.add_complex(selector, &InvalidationSet::self_only());
+    }
+
+    /// What changing `feature` on an element invalidates, if any selector
+    /// looks at it
+    pub fn get(&self, feature: &Feature) -> Option<&InvalidationSet> {
+        self.sets.get(feature)
+    }
+
+    /// Whether adding or removing children can change which selectors
+    /// their siblings or parent match
+    pub fn depends_on_structure(&self) -> bool {
+        self.structural
+    }
+
+    pub fn uses_relative_selectors(&self) -> bool {
+        self.relative
+    }
+
+    /// Adds `selector`, whose subject is an element that `reach` covers
+    /// when it changes
+    fn add_complex(&mut self, selector: &ComplexSelector, reach: &InvalidationSet) {
+        let subject_only = *reach == InvalidationSet::self_only();
+        // Each part's combinator joins it to the part on its right
+        for (i, (compound, combinator)) in selector.parts.iter().enumerate() {
+            let sibling = matches!(combinator, Some(Combinator::NextSibling | Combinator::SubsequentSibling));
+            self.structural |= sibling;
+            let part_reach = if i == 0 {
+                reach.clone()
+            } else if sibling {
+                InvalidationSet {
+                    siblings: true,
+                    whole_document: reach.whole_document,
+                    ..InvalidationSet::default()
+                }
+            } else if subject_only {
+                descendants_matching(selector.subject())
+            } else {
+                InvalidationSet {
+                    whole_subtree: true,
+                    whole_document: reach.whole_document,
+                    ..InvalidationSet::default()
+                }
+            };
+            self.add_compound(compound, &part_reach);
+        }
+    }
+
+    fn add_compound(&mut self, compound: &CompoundSelector, reach: &InvalidationSet) {
+        for simple in &compound.simple_selectors {
+            match simple {
+                SimpleSelector::Universal | SimpleSelector::Tag(_) => {}
+                SimpleSelector::Class(class) => self.add(Feature::Class(class.clone()), reach),
+                SimpleSelector::Id(id) => self.add(Feature::Id(id.clone()), reach),
+                SimpleSelector::Attribute(attribute) => {
+                    self.add(Feature::Attribute(attribute_name(attribute).to_ascii_lowercase()), reach)
+                }
+                SimpleSelector::PseudoClass(pseudo) => self.add_pseudo_class(pseudo, reach),
+            }
+        }
+    }
+
+    fn add_pseudo_class(&mut self, pseudo: &PseudoClass, reach: &InvalidationSet) {
+        let attribute = |name: &str| Feature::Attribute(name.to_string());
+        match pseudo {
+            PseudoClass::Hover => self.add(Feature::State(ElementState::HOVER), reach),
+            PseudoClass::Active => self.add(Feature::State(ElementState::ACTIVE), reach),
+            PseudoClass::Focus => self.add(Feature::State(ElementState::FOCUS), reach),
+            PseudoClass::FocusVisible => self.add(Feature::State(ElementState::FOCUS_VISIBLE), reach),
+            PseudoClass::FocusWithin => self.add(Feature::State(ElementState::FOCUS_WITHIN), reach),
+            PseudoClass::Link | PseudoClass::Visited => {
+                self.add(Feature::State(ElementState::VISITED), reach);
+                self.add(attribute("href"), reach);
+            }
+            PseudoClass::AnyLink => self.add(attribute("href"), reach),
+            PseudoClass::Checked => {
+                for name in ["checked", "selected", "type"] {
+                    self.add(attribute(name), reach);
+                }
+            }
+            // A disabled <fieldset> disables the controls inside it
+            PseudoClass::Disabled | PseudoClass::Enabled => {
+                let reach = InvalidationSet {
+                    whole_subtree: true,
+                    ..reach.clone()
+                };
+                self.add(attribute("disabled"), &reach);
+            }
+            PseudoClass::FirstChild
+            | PseudoClass::LastChild
+            | PseudoClass::NthChild(..)
+            | PseudoClass::NthLastChild(..)
+            | PseudoClass::OnlyChild
+            | PseudoClass::FirstOfType
+            | PseudoClass::LastOfType
+            | PseudoClass::NthOfType(..)
+            | PseudoClass::NthLastOfType(..)
+            | PseudoClass::OnlyOfType
+            | PseudoClass::Empty => self.structural = true,
+            PseudoClass::Root => {}
+            PseudoClass::Not(args) | PseudoClass::Is(args) | PseudoClass::Where(args) => {
+                for arg in args {
+                    self.add_complex(arg, reach);
+                }
+            }
+            // What is inside decides about elements around it, anywhere
+            PseudoClass::Has(args) => {
+                self.relative = true;
+                let reach = InvalidationSet {
+                    whole_document: true,
+                    ..InvalidationSet::default()
+                };
+                for arg in args {
+                    self.structural |=
+                        matches!(arg.combinator, Combinator::NextSibling | Combinator::SubsequentSibling);
+                    self.add_complex(&arg.selector, &reach);
+                }
+            }
+        }
+    }
+
+    fn add(&mut self, feature: Feature, reach: &InvalidationSet) {
+        self.sets.entry(feature).or_default().merge(reach);
+    }
+}
+
+/// Descendants that can be the subject of a selector: those with its id,
+/// else one of its classes, else its type; all of them when it has none
+fn descendants_matching(subject: Option<&CompoundSelector>) -> InvalidationSet {
+    let simple_selectors = subject.map_or(&[][..], |compound| &compound.simple_selectors[..]);
+    let mut set = InvalidationSet::default();
+    if let Some(id) = simple_selectors.iter().find_map(|simple| match simple {
+        SimpleSelector::Id(id) => Some(id),
+        _ => None,
+    }) {
+        set.descendant_ids.insert(id.clone());
+    } else if let Some(class) = simple_selectors.iter().find_map(|simple| match simple {
+        SimpleSelector::Class(class) => Some(class),
+        _ => None,
+    }) {
+        set.descendant_classes.insert(class.clone());
+    } else if let Some(tag) = simple_selectors.iter().find_map(|simple| match simple {
+        SimpleSelector::Tag(tag) => Some(tag),
+        _ => None,
+    }) {
+        set.descendant_tags.insert(tag.to_ascii_lowercase());
+    } else {
+        set.whole_subtree = true;
+    }
+    set
+}
+
+fn attribute_name(attribute: &AttributeSelector) -> &str {
+    match attribute {
+        AttributeSelector::Exists(name)
+        | AttributeSelector::Equals(name, _)
+        | AttributeSelector::Contains(name, _)
+        | AttributeSelector::StartsWith(name, _)
+        | AttributeSelector::EndsWith(name, _)
+        | AttributeSelector::WordMatch(name, _) => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::parse_selector;
+    use crate::dom::parse_html;
+
+    fn map(selectors: &[&str]) -> InvalidationMap {
+        let mut map = InvalidationMap::new();
+        for selector in selectors {
+            map.add_selector(&parse_selector(selector).unwrap().complex);
+        }
+        map
+    }
+
+    fn class(name: &str) -> Feature {
+        Feature::Class(name.to_string())
+    }
+
+    #[test]
+    fn test_subject_features_invalidate_self() {
+        let map = map(&["div.card[data-open]", "#main:hover"]);
+        assert_eq!(map.get(&class("card")), Some(&InvalidationSet::self_only()));
+        assert_eq!(map.get(&Feature::Attribute("data-open".to_string())), Some(&InvalidationSet::self_only()));
+        assert_eq!(map.get(&Feature::Id("main".to_string())), Some(&InvalidationSet::self_only()));
+        assert_eq!(map.get(&Feature::State(ElementState::HOVER)), Some(&InvalidationSet::self_only()));
+        assert!(map.get(&class("other")).is_none());
+        assert!(!map.depends_on_structure());
+    }
+
+    #[test]
+    fn test_ancestor_features_invalidate_descendants() {
+        let map = map(&[".open .item", ".open > p", ".dark :focus", ".x:not(.y) span.z"]);
+        let open = map.get(&class("open")).unwrap();
+        assert!(!open.invalidates_self);
+        assert!(open.descendant_classes.contains("item"));
+        assert!(open.descendant_tags.contains("p"));
+        assert!(!open.whole_subtree);
+
+        assert!(map.get(&class("dark")).unwrap().whole_subtree);
+        let y = map.get(&class("y")).unwrap();
+        assert!(y.descendant_classes.contains("z"));
+        assert!(map.get(&Feature::State(ElementState::FOCUS)).unwrap().invalidates_self);
+    }
+
+    #[test]
+    fn test_sibling_and_relative_selectors() {
+        let map = map(&["h1.on + p", "li:first-child", "div:has(> .done)"]);
+        let on = map.get(&class("on")).unwrap();
+        assert!(on.siblings);
+        assert!(!on.invalidates_self);
+        assert!(map.depends_on_structure());
+        assert!(map.uses_relative_selectors());
+        assert!(map.get(&class("done")).unwrap().whole_document);
+    }
+
+    #[test]
+    fn test_collect_marks_affected_elements() {
+        let doc = parse_html(
+            r#"<div id="a"><p class="item">1</p><span><b class="item">2</b></span><i>3</i></div><p id="next">N</p>"#,
+        );
+        let map = map(&[".open .item", ".open ~ p"]);
+        let a = doc.get_element_by_id("a").unwrap();
+        let mut marks = Vec::new();
+        map.get(&class("open")).unwrap().collect(&doc, a, &mut marks);
+
+        let items = doc.get_elements_by_class_name("item");
+        let next = doc.get_element_by_id("next").unwrap();
+        assert_eq!(marks.len(), 3);
+        for item in items {
+            assert!(marks.contains(&(item, RestyleFlags::STYLE)));
+        }
+        assert!(marks.contains(&(next, RestyleFlags::SUBTREE)));
+    }
+}
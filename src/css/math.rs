@@ -0,0 +1,286 @@
+//! CSS math functions: `calc()`, `min()`, `max()` and `clamp()`.
+//!
+//! Expressions are parsed into a tree and evaluated at computed-value time.
+//! When they contain percentages they can only be evaluated once the
+//! percentage basis is known, which for widths and heights is in layout.
+
+use super::stylesheet::{LengthContext, Unit};
+use cssparser::{ParseError, Parser, Token};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcExpr {
+    Length(f32, Unit),
+    Percentage(f32),
+    Number(f32),
+    Add(Box<CalcExpr>, Box<CalcExpr>),
+    Sub(Box<CalcExpr>, Box<CalcExpr>),
+    Mul(Box<CalcExpr>, Box<CalcExpr>),
+    Div(Box<CalcExpr>, Box<CalcExpr>),
+    Min(Vec<CalcExpr>),
+    Max(Vec<CalcExpr>),
+    /// `clamp(min, value, max)`
+    Clamp(Box<CalcExpr>, Box<CalcExpr>, Box<CalcExpr>),
+}
+
+/// Result of evaluating part of an expression: numbers and lengths can't be
+/// added to each other, and only numbers can be multiplied or divided by
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Quantity {
+    Number(f32),
+    Length(f32),
+}
+
+impl Quantity {
+    fn value(self) -> f32 {
+        match self {
+            Quantity::Number(v) | Quantity::Length(v) => v,
+        }
+    }
+
+    fn same_kind(self, other: Quantity) -> bool {
+        matches!(
+            (self, other),
+            (Quantity::Number(_), Quantity::Number(_)) | (Quantity::Length(_), Quantity::Length(_))
+        )
+    }
+
+    fn map(self, f: impl FnOnce(f32) -> f32) -> Quantity {
+        match self {
+            Quantity::Number(v) => Quantity::Number(f(v)),
+            Quantity::Length(v) => Quantity::Length(f(v)),
+        }
+    }
+}
+
+impl CalcExpr {
+    /// Whether the math function `name` is one this module parses
+    pub fn is_math_function(name: &str) -> bool {
+        matches!(name.to_ascii_lowercase().as_str(), "calc" | "min" | "max" | "clamp")
+    }
+
+    /// Parses the contents of the math function `name`
+    pub fn parse_function<'i>(name: &str, parser: &mut Parser<'i, '_>) -> Result<CalcExpr, ParseError<'i, ()>> {
+        let expr = match name.to_ascii_lowercase().as_str() {
+            "calc" => parse_sum(parser)?,
+            "min" => CalcExpr::Min(parser.parse_comma_separated(parse_sum)?),
+            "max" => CalcExpr::Max(parser.parse_comma_separated(parse_sum)?),
+            "clamp" => {
+                let min = parse_sum(parser)?;
+                parser.expect_comma()?;
+                let value = parse_sum(parser)?;
+                parser.expect_comma()?;
+                let max = parse_sum(parser)?;
+                CalcExpr::Clamp(Box::new(min), Box::new(value), Box::new(max))
+            }
+            _ => return Err(parser.new_custom_error(())),
+        };
+        parser.expect_exhausted()?;
+        Ok(expr)
+    }
+
+    pub fn has_percentage(&self) -> bool {
+        match self {
+            CalcExpr::Percentage(_) => true,
+            CalcExpr::Length(_, unit) => *unit == Unit::Percent,
+            CalcExpr::Number(_) => false,
+            CalcExpr::Add(a, b) | CalcExpr::Sub(a, b) | CalcExpr::Mul(a, b) | CalcExpr::Div(a, b) => {
+                a.has_percentage() || b.has_percentage()
+            }
+            CalcExpr::Min(args) | CalcExpr::Max(args) => args.iter().any(CalcExpr::has_percentage),
+            CalcExpr::Clamp(min, value, max) => {
+                min.has_percentage() || value.has_percentage() || max.has_percentage()
+            }
+        }
+    }
+
+    /// Evaluates the expression to pixels (or a plain number). Percentages
+    /// resolve against `percent_base`; without one they make the result
+    /// `None`, as does mixing numbers and lengths.
+    pub fn to_px(&self, ctx: &LengthContext, percent_base: Option<f32>) -> Option<f32> {
+        self.eval(ctx, percent_base).map(Quantity::value)
+    }
+
+    /// Replaces font- and viewport-relative lengths with pixels, leaving
+    /// percentages for layout to resolve
+    pub fn absolutize(&self, ctx: &LengthContext) -> CalcExpr {
+        let absolutize = |expr: &CalcExpr| Box::new(expr.absolutize(ctx));
+        match self {
+            CalcExpr::Length(v, unit) if *unit != Unit::Percent => {
+                CalcExpr::Length(unit.to_px(*v, ctx).unwrap_or(0.0), Unit::Px)
+            }
+            CalcExpr::Length(..) | CalcExpr::Percentage(_) | CalcExpr::Number(_) => self.clone(),
+            CalcExpr::Add(a, b) => CalcExpr::Add(absolutize(a), absolutize(b)),
+            CalcExpr::Sub(a, b) => CalcExpr::Sub(absolutize(a), absolutize(b)),
+            CalcExpr::Mul(a, b) => CalcExpr::Mul(absolutize(a), absolutize(b)),
+            CalcExpr::Div(a, b) => CalcExpr::Div(absolutize(a), absolutize(b)),
+            CalcExpr::Min(args) => CalcExpr::Min(args.iter().map(|a| a.absolutize(ctx)).collect()),
+            CalcExpr::Max(args) => CalcExpr::Max(args.iter().map(|a| a.absolutize(ctx)).collect()),
+            CalcExpr::Clamp(min, value, max) => CalcExpr::Clamp(absolutize(min), absolutize(value), absolutize(max)),
+        }
+    }
+
+    fn eval(&self, ctx: &LengthContext, percent_base: Option<f32>) -> Option<Quantity> {
+        let eval = |expr: &CalcExpr| expr.eval(ctx, percent_base);
+        match self {
+            CalcExpr::Number(v) => Some(Quantity::Number(*v)),
+            CalcExpr::Percentage(pct) | CalcExpr::Length(pct, Unit::Percent) => {
+                percent_base.map(|base| Quantity::Length(base * pct / 100.0))
+            }
+            CalcExpr::Length(v, unit) => unit.to_px(*v, ctx).map(Quantity::Length),
+            CalcExpr::Add(a, b) | CalcExpr::Sub(a, b) => {
+                let (a, b) = (eval(a)?, eval(b)?);
+                if !a.same_kind(b) {
+                    return None;
+                }
+                let sign = if matches!(self, CalcExpr::Sub(..)) { -1.0 } else { 1.0 };
+                Some(a.map(|a| a + sign * b.value()))
+            }
+            CalcExpr::Mul(a, b) => match (eval(a)?, eval(b)?) {
+                (Quantity::Number(n), q) | (q, Quantity::Number(n)) => Some(q.map(|v| v * n)),
+                _ => None,
+            },
+            CalcExpr::Div(a, b) => match (eval(a)?, eval(b)?) {
+                (q, Quantity::Number(n)) if n != 0.0 => Some(q.map(|v| v / n)),
+                _ => None,
+            },
+            CalcExpr::Min(args) | CalcExpr::Max(args) => {
+                let is_min = matches!(self, CalcExpr::Min(_));
+                let mut result: Option<Quantity> = None;
+                for arg in args {
+                    let q = eval(arg)?;
+                    result = match result {
+                        None => Some(q),
+                        Some(r) if !r.same_kind(q) => return None,
+                        Some(r) if (q.value() < r.value()) == is_min => Some(q),
+                        keep => keep,
+                    };
+                }
+                result
+            }
+            CalcExpr::Clamp(min, value, max) => {
+                let (min, value, max) = (eval(min)?, eval(value)?, eval(max)?);
+                if !min.same_kind(value) || !value.same_kind(max) {
+                    return None;
+                }
+                // The minimum wins over the maximum when they conflict
+                Some(value.map(|v| v.min(max.value()).max(min.value())))
+            }
+        }
+    }
+}
+
+/// `product [ ['+' | '-'] product ]*`
+fn parse_sum<'i>(parser: &mut Parser<'i, '_>) -> Result<CalcExpr, ParseError<'i, ()>> {
+    let mut expr = parse_product(parser)?;
+    loop {
+        let op = parser.try_parse(|p| match p.next()?.clone() {
+            Token::Delim(c @ ('+' | '-')) => Ok(c),
+            _ => Err(p.new_custom_error::<(), ()>(())),
+        });
+        let Ok(op) = op else {
+            return Ok(expr);
+        };
+        let rhs = Box::new(parse_product(parser)?);
+        expr = match op {
+            '+' => CalcExpr::Add(Box::new(expr), rhs),
+            _ => CalcExpr::Sub(Box::new(expr), rhs),
+        };
+    }
+}
+
+/// `value [ ['*' | '/'] value ]*`
+fn parse_product<'i>(parser: &mut Parser<'i, '_>) -> Result<CalcExpr, ParseError<'i, ()>> {
+    let mut expr = parse_calc_value(parser)?;
+    loop {
+        let op = parser.try_parse(|p| match p.next()?.clone() {
+            Token::Delim(c @ ('*' | '/')) => Ok(c),
+            _ => Err(p.new_custom_error::<(), ()>(())),
+        });
+        let Ok(op) = op else {
+            return Ok(expr);
+        };
+        let rhs = Box::new(parse_calc_value(parser)?);
+        expr = match op {
+            '*' => CalcExpr::Mul(Box::new(expr), rhs),
+            _ => CalcExpr::Div(Box::new(expr), rhs),
+        };
+    }
+}
+
+fn parse_calc_value<'i>(parser: &mut Parser<'i, '_>) -> Result<CalcExpr, ParseError<'i, ()>> {
+    let token = parser.next()?.clone();
+    match token {
+        Token::Number { value, .. } => Ok(CalcExpr::Number(value)),
+        Token::Percentage { unit_value, .. } => Ok(CalcExpr::Percentage(unit_value * 100.0)),
+        Token::Dimension { value, unit, .. } => match Unit::from_name(&unit) {
+            Some(unit) => Ok(CalcExpr::Length(value, unit)),
+            None => Err(parser.new_custom_error(())),
+        },
+        Token::ParenthesisBlock => parser.parse_nested_block(|p| {
+            let expr = parse_sum(p)?;
+            p.expect_exhausted()?;
+            Ok(expr)
+        }),
+        Token::Function(name) if CalcExpr::is_math_function(&name) => {
+            parser.parse_nested_block(|p| CalcExpr::parse_function(&name, p))
+        }
+        _ => Err(parser.new_custom_error(())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cssparser::ParserInput;
+
+    fn parse(function: &str, contents: &str) -> Option<CalcExpr> {
+        let mut input = ParserInput::new(contents);
+        let mut parser = Parser::new(&mut input);
+        CalcExpr::parse_function(function, &mut parser).ok()
+    }
+
+    fn context() -> LengthContext {
+        LengthContext {
+            font_size: 20.0,
+            ..LengthContext::new(1000.0, 500.0)
+        }
+    }
+
+    fn px(function: &str, contents: &str, percent_base: Option<f32>) -> Option<f32> {
+        parse(function, contents)?.to_px(&context(), percent_base)
+    }
+
+    #[test]
+    fn test_calc_precedence_and_units() {
+        assert_eq!(px("calc", "10px + 2 * 5px", None), Some(20.0));
+        assert_eq!(px("calc", "(10px + 2em) / 2", None), Some(25.0));
+        assert_eq!(px("calc", "10vw - 1rem", None), Some(84.0));
+        assert_eq!(px("calc", "100% - 20px", Some(300.0)), Some(280.0));
+        assert_eq!(px("calc", "100% - 20px", None), None);
+    }
+
+    #[test]
+    fn test_min_max_clamp() {
+        assert_eq!(px("min", "50%, 200px", Some(600.0)), Some(200.0));
+        assert_eq!(px("max", "1em, 10px, 2vh", None), Some(20.0));
+        assert_eq!(px("clamp", "1rem, 2vw, 2rem", None), Some(20.0));
+        assert_eq!(px("clamp", "1rem, 0.5vw, 2rem", None), Some(16.0));
+        assert_eq!(px("calc", "min(10px, 5px) * 3", None), Some(15.0));
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        assert!(parse("calc", "10px +").is_none());
+        assert!(parse("calc", "10deg").is_none());
+        assert_eq!(px("calc", "10px * 2px", None), None);
+        assert_eq!(px("calc", "10px + 2", None), None);
+        assert_eq!(px("calc", "10px / 0", None), None);
+    }
+
+    #[test]
+    fn test_absolutize_keeps_percentages() {
+        let expr = parse("calc", "50% + 2em").unwrap().absolutize(&context());
+        assert!(expr.has_percentage());
+        assert_eq!(expr.to_px(&LengthContext::new(0.0, 0.0), Some(100.0)), Some(90.0));
+    }
+}
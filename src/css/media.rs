@@ -0,0 +1,564 @@
+//! Media queries: parsing `@media` preludes and evaluating them against the
+//! current viewport and user preferences.
+
+use cssparser::{Delimiter, ParseError, Parser, ParserInput, Token};
+
+type ParseResult<'i, T> = Result<T, ParseError<'i, ()>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaType {
+    All,
+    Screen,
+    Print,
+    /// Any other (or deprecated) type, which never matches
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// Everything a media query can ask about
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaEnvironment {
+    /// Screen or print; never `All` or `Other`
+    pub media_type: MediaType,
+    pub width: f32,
+    pub height: f32,
+    /// Device pixels per CSS pixel
+    pub resolution: f32,
+    pub color_scheme: ColorScheme,
+    pub reduced_motion: bool,
+    /// The browser replaces the page's colors with its own palette
+    pub forced_colors: bool,
+}
+
+impl MediaEnvironment {
+    pub fn screen(width: f32, height: f32) -> Self {
+        Self {
+            media_type: MediaType::Screen,
+            width,
+            height,
+            resolution: 1.0,
+            color_scheme: ColorScheme::Light,
+            reduced_motion: false,
+            forced_colors: false,
+        }
+    }
+}
+
+impl Default for MediaEnvironment {
+    fn default() -> Self {
+        Self::screen(800.0, 600.0)
+    }
+}
+
+/// A comma-separated list of queries; matches if any query does. An empty
+/// list (e.g. `@media {}`) matches everything.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MediaQueryList {
+    pub queries: Vec<MediaQuery>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQuery {
+    pub negated: bool,
+    pub media_type: MediaType,
+    pub condition: Option<MediaCondition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaCondition {
+    Feature(MediaFeature),
+    Not(Box<MediaCondition>),
+    And(Vec<MediaCondition>),
+    Or(Vec<MediaCondition>),
+    /// Syntax we could not parse; evaluates to false
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaFeature {
+    /// `(color)`: true when the feature has a non-zero / non-`none` value
+    Boolean(String),
+    /// `(min-width: 600px)`, `(orientation: portrait)`
+    Plain(String, MediaValue),
+    /// `(400px <= width < 800px)`, stored as `feature <op> value` constraints
+    Range(String, Vec<(Comparison, MediaValue)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    /// The operator seen from the other side, for `value <op> feature`
+    fn flip(self) -> Self {
+        match self {
+            Comparison::Less => Comparison::Greater,
+            Comparison::LessOrEqual => Comparison::GreaterOrEqual,
+            Comparison::Equal => Comparison::Equal,
+            Comparison::GreaterOrEqual => Comparison::LessOrEqual,
+            Comparison::Greater => Comparison::Less,
+        }
+    }
+
+    fn holds(self, left: f32, right: f32) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Equal => (left - right).abs() < f32::EPSILON,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Greater => left > right,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaValue {
+    /// Lengths are resolved to px when parsed (em/rem against 16px, as
+    /// media queries do not see the page's font size)
+    Length(f32),
+    Number(f32),
+    Ratio(f32),
+    /// Dots per CSS pixel
+    Resolution(f32),
+    Ident(String),
+}
+
+impl MediaValue {
+    fn as_number(&self) -> Option<f32> {
+        match self {
+            MediaValue::Length(v) | MediaValue::Number(v) | MediaValue::Ratio(v) | MediaValue::Resolution(v) => {
+                Some(*v)
+            }
+            MediaValue::Ident(_) => None,
+        }
+    }
+}
+
+impl MediaQueryList {
+    /// Parses a query list from text, such as a `media` attribute
+    pub fn parse(text: &str) -> Self {
+        let mut input = ParserInput::new(text);
+        let mut parser = Parser::new(&mut input);
+        parse_media_query_list(&mut parser)
+    }
+
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        self.queries.is_empty() || self.queries.iter().any(|query| query.matches(env))
+    }
+}
+
+impl MediaQuery {
+    /// Stands in for a query that failed to parse
+    fn not_all() -> Self {
+        Self {
+            negated: true,
+            media_type: MediaType::All,
+            condition: None,
+        }
+    }
+
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        let type_matches = match self.media_type {
+            MediaType::All => true,
+            MediaType::Other => false,
+            media_type => media_type == env.media_type,
+        };
+        let result = type_matches && self.condition.as_ref().is_none_or(|c| c.matches(env));
+        result != self.negated
+    }
+}
+
+impl MediaCondition {
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        match self {
+            MediaCondition::Feature(feature) => feature.matches(env),
+            MediaCondition::Not(inner) => !inner.matches(env),
+            MediaCondition::And(items) => items.iter().all(|c| c.matches(env)),
+            MediaCondition::Or(items) => items.iter().any(|c| c.matches(env)),
+            MediaCondition::Unknown => false,
+        }
+    }
+}
+
+impl MediaFeature {
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        match self {
+            MediaFeature::Boolean(name) => match feature_value(name, env) {
+                Some(MediaValue::Ident(value)) => value != "none" && value != "no-preference",
+                Some(value) => value.as_number().is_some_and(|v| v != 0.0),
+                None => false,
+            },
+            MediaFeature::Plain(name, expected) => {
+                let (comparison, name) = if let Some(name) = name.strip_prefix("min-") {
+                    (Comparison::GreaterOrEqual, name)
+                } else if let Some(name) = name.strip_prefix("max-") {
+                    (Comparison::LessOrEqual, name)
+                } else {
+                    (Comparison::Equal, name.as_str())
+                };
+                compare(name, comparison, expected, env)
+            }
+            MediaFeature::Range(name, constraints) => constraints
+                .iter()
+                .all(|(comparison, value)| compare(name, *comparison, value, env)),
+        }
+    }
+}
+
+/// `feature <comparison> expected` for the current environment
+fn compare(name: &str, comparison: Comparison, expected: &MediaValue, env: &MediaEnvironment) -> bool {
+    let Some(actual) = feature_value(name, env) else {
+        return false;
+    };
+    match (&actual, expected) {
+        (MediaValue::Ident(actual), MediaValue::Ident(expected)) => {
+            comparison == Comparison::Equal && actual.eq_ignore_ascii_case(expected)
+        }
+        // `0` is a valid length without a unit
+        (MediaValue::Length(actual), MediaValue::Number(expected)) if *expected == 0.0 => {
+            comparison.holds(*actual, 0.0)
+        }
+        (MediaValue::Ratio(actual), MediaValue::Number(expected)) => comparison.holds(*actual, *expected),
+        _ if std::mem::discriminant(&actual) == std::mem::discriminant(expected) => {
+            match (actual.as_number(), expected.as_number()) {
+                (Some(actual), Some(expected)) => comparison.holds(actual, expected),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+fn feature_value(name: &str, env: &MediaEnvironment) -> Option<MediaValue> {
+    let ident = |s: &str| Some(MediaValue::Ident(s.to_string()));
+    match name {
+        "width" | "device-width" => Some(MediaValue::Length(env.width)),
+        "height" | "device-height" => Some(MediaValue::Length(env.height)),
+        "aspect-ratio" | "device-aspect-ratio" => Some(MediaValue::Ratio(env.width / env.height.max(1.0))),
+        "orientation" => ident(if env.height >= env.width { "portrait" } else { "landscape" }),
+        "resolution" => Some(MediaValue::Resolution(env.resolution)),
+        "prefers-color-scheme" => ident(match env.color_scheme {
+            ColorScheme::Light => "light",
+            ColorScheme::Dark => "dark",
+        }),
+        "prefers-reduced-motion" => ident(if env.reduced_motion { "reduce" } else { "no-preference" }),
+        "forced-colors" => ident(if env.forced_colors { "active" } else { "none" }),
+        "prefers-contrast" => ident(if env.forced_colors { "more" } else { "no-preference" }),
+        "hover" | "any-hover" => ident(if env.media_type == MediaType::Print { "none" } else { "hover" }),
+        "pointer" | "any-pointer" => ident(if env.media_type == MediaType::Print { "none" } else { "fine" }),
+        "color" => Some(MediaValue::Number(8.0)),
+        "monochrome" | "grid" => Some(MediaValue::Number(0.0)),
+        _ => None,
+    }
+}
+
+/// Parses the prelude of an `@media` rule, stopping before its block.
+/// Queries that fail to parse become `not all`, as the spec requires.
+pub fn parse_media_query_list(parser: &mut Parser) -> MediaQueryList {
+    let mut queries = Vec::new();
+    loop {
+        let query = parser.parse_until_before(Delimiter::Comma | Delimiter::CurlyBracketBlock | Delimiter::Semicolon, |p| {
+            if p.is_exhausted() {
+                return Ok(None);
+            }
+            parse_media_query(p).map(Some)
+        });
+        let at_comma = parser.try_parse(|p| p.expect_comma()).is_ok();
+        match query {
+            Ok(Some(query)) => queries.push(query),
+            // An empty prelude is an empty list, which matches everything
+            Ok(None) if !at_comma && queries.is_empty() => {}
+            _ => queries.push(MediaQuery::not_all()),
+        }
+        if !at_comma {
+            break;
+        }
+    }
+    MediaQueryList { queries }
+}
+
+fn parse_media_query<'i>(parser: &mut Parser<'i, '_>) -> ParseResult<'i, MediaQuery> {
+    if let Ok(condition) = parser.try_parse(|p| {
+        let condition = parse_condition(p, true)?;
+        p.expect_exhausted()?;
+        Ok::<_, ParseError<'i, ()>>(condition)
+    }) {
+        return Ok(MediaQuery {
+            negated: false,
+            media_type: MediaType::All,
+            condition: Some(condition),
+        });
+    }
+
+    let mut negated = false;
+    let mut ident = parser.expect_ident_cloned()?;
+    if ident.eq_ignore_ascii_case("not") {
+        negated = true;
+        ident = parser.expect_ident_cloned()?;
+    } else if ident.eq_ignore_ascii_case("only") {
+        ident = parser.expect_ident_cloned()?;
+    }
+    let media_type = match ident.to_ascii_lowercase().as_str() {
+        "all" => MediaType::All,
+        "screen" => MediaType::Screen,
+        "print" => MediaType::Print,
+        _ => MediaType::Other,
+    };
+
+    let condition = if parser.try_parse(|p| p.expect_ident_matching("and")).is_ok() {
+        Some(parse_condition(parser, false)?)
+    } else {
+        None
+    };
+    parser.expect_exhausted()?;
+
+    Ok(MediaQuery {
+        negated,
+        media_type,
+        condition,
+    })
+}
+
+/// `<media-condition>`, or `<media-condition-without-or>` after a media type
+fn parse_condition<'i>(parser: &mut Parser<'i, '_>, allow_or: bool) -> ParseResult<'i, MediaCondition> {
+    if parser.try_parse(|p| p.expect_ident_matching("not")).is_ok() {
+        return Ok(MediaCondition::Not(Box::new(parse_in_parens(parser)?)));
+    }
+
+    let mut items = vec![parse_in_parens(parser)?];
+    let mut is_or = None;
+    loop {
+        let operator = parser.try_parse(|p| {
+            let ident = p.expect_ident_cloned()?;
+            match ident.to_ascii_lowercase().as_str() {
+                "and" => Ok(false),
+                "or" if allow_or => Ok(true),
+                _ => Err(p.new_custom_error::<_, ()>(())),
+            }
+        });
+        let Ok(operator) = operator else {
+            break;
+        };
+        // `and` and `or` cannot be mixed without parentheses
+        if is_or.is_some_and(|is_or| is_or != operator) {
+            return Err(parser.new_custom_error(()));
+        }
+        is_or = Some(operator);
+        items.push(parse_in_parens(parser)?);
+    }
+
+    Ok(match (items.len(), is_or) {
+        (1, _) => items.pop().unwrap(),
+        (_, Some(true)) => MediaCondition::Or(items),
+        _ => MediaCondition::And(items),
+    })
+}
+
+fn parse_in_parens<'i>(parser: &mut Parser<'i, '_>) -> ParseResult<'i, MediaCondition> {
+    parser.expect_parenthesis_block()?;
+    parser.parse_nested_block(|p| {
+        if let Ok(condition) = p.try_parse(|p| {
+            let condition = parse_condition(p, true)?;
+            p.expect_exhausted()?;
+            Ok::<_, ParseError<'i, ()>>(condition)
+        }) {
+            return Ok(condition);
+        }
+        if let Ok(feature) = p.try_parse(parse_feature) {
+            return Ok(MediaCondition::Feature(feature));
+        }
+        // <general-enclosed>: valid syntax that is simply false
+        while p.next().is_ok() {}
+        Ok(MediaCondition::Unknown)
+    })
+}
+
+fn parse_feature<'i>(parser: &mut Parser<'i, '_>) -> ParseResult<'i, MediaFeature> {
+    // `name`, `name: value` or `name <op> value`
+    if let Ok(name) = parser.try_parse(|p| p.expect_ident_cloned()) {
+        let name = name.to_ascii_lowercase();
+        if parser.is_exhausted() {
+            return Ok(MediaFeature::Boolean(name));
+        }
+        if parser.try_parse(|p| p.expect_colon()).is_ok() {
+            let value = parse_media_value(parser)?;
+            parser.expect_exhausted()?;
+            return Ok(MediaFeature::Plain(name, value));
+        }
+        let comparison = parse_comparison(parser)?;
+        let value = parse_media_value(parser)?;
+        parser.expect_exhausted()?;
+        return Ok(MediaFeature::Range(name, vec![(comparison, value)]));
+    }
+
+    // `value <op> name` or `value <op> name <op> value`
+    let first = parse_media_value(parser)?;
+    let first_comparison = parse_comparison(parser)?;
+    let name = parser.expect_ident_cloned()?.to_ascii_lowercase();
+    let mut constraints = vec![(first_comparison.flip(), first)];
+    if !parser.is_exhausted() {
+        let comparison = parse_comparison(parser)?;
+        let value = parse_media_value(parser)?;
+        constraints.push((comparison, value));
+    }
+    parser.expect_exhausted()?;
+    Ok(MediaFeature::Range(name, constraints))
+}
+
+fn parse_comparison<'i>(parser: &mut Parser<'i, '_>) -> ParseResult<'i, Comparison> {
+    let operator = match parser.next()?.clone() {
+        Token::Delim('<') => Comparison::Less,
+        Token::Delim('>') => Comparison::Greater,
+        Token::Delim('=') => return Ok(Comparison::Equal),
+        _ => return Err(parser.new_custom_error(())),
+    };
+    // `<=` and `>=` arrive as two delimiters with nothing in between
+    let state = parser.state();
+    match parser.next_including_whitespace() {
+        Ok(Token::Delim('=')) => Ok(match operator {
+            Comparison::Less => Comparison::LessOrEqual,
+            _ => Comparison::GreaterOrEqual,
+        }),
+        _ => {
+            parser.reset(&state);
+            Ok(operator)
+        }
+    }
+}
+
+fn parse_media_value<'i>(parser: &mut Parser<'i, '_>) -> ParseResult<'i, MediaValue> {
+    let value = match parser.next()?.clone() {
+        Token::Ident(ident) => MediaValue::Ident(ident.to_ascii_lowercase()),
+        Token::Number { value, .. } => {
+            // A ratio such as `16/9`
+            if parser.try_parse(|p| p.expect_delim('/')).is_ok() {
+                let denominator = parser.expect_number()?;
+                MediaValue::Ratio(value / denominator.max(f32::EPSILON))
+            } else {
+                MediaValue::Number(value)
+            }
+        }
+        Token::Dimension { value, unit, .. } => {
+            let unit = unit.to_ascii_lowercase();
+            let px_per_unit = match unit.as_str() {
+                "px" => Some(1.0),
+                "em" | "rem" => Some(16.0),
+                "in" => Some(96.0),
+                "cm" => Some(96.0 / 2.54),
+                "mm" => Some(96.0 / 25.4),
+                "pt" => Some(96.0 / 72.0),
+                "pc" => Some(16.0),
+                _ => None,
+            };
+            let dots_per_px = match unit.as_str() {
+                "dppx" | "x" => Some(1.0),
+                "dpi" => Some(1.0 / 96.0),
+                "dpcm" => Some(2.54 / 96.0),
+                _ => None,
+            };
+            match (px_per_unit, dots_per_px) {
+                (Some(factor), _) => MediaValue::Length(value * factor),
+                (_, Some(factor)) => MediaValue::Resolution(value * factor),
+                _ => return Err(parser.new_custom_error(())),
+            }
+        }
+        _ => return Err(parser.new_custom_error(())),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(query: &str, env: &MediaEnvironment) -> bool {
+        MediaQueryList::parse(query).matches(env)
+    }
+
+    #[test]
+    fn test_width_features() {
+        let narrow = MediaEnvironment::screen(400.0, 800.0);
+        let wide = MediaEnvironment::screen(1200.0, 800.0);
+
+        assert!(matches("(max-width: 600px)", &narrow));
+        assert!(!matches("(max-width: 600px)", &wide));
+        assert!(matches("screen and (min-width: 40em)", &wide));
+        assert!(matches("(600px <= width < 1300px)", &wide));
+        assert!(!matches("(600px <= width < 1300px)", &narrow));
+        assert!(matches("(width > 300px) and (height >= 800px)", &narrow));
+        assert!(matches("(orientation: portrait)", &narrow));
+        assert!(matches("(min-aspect-ratio: 4/3)", &wide));
+    }
+
+    #[test]
+    fn test_media_types_and_negation() {
+        let screen = MediaEnvironment::default();
+        let print = MediaEnvironment {
+            media_type: MediaType::Print,
+            ..MediaEnvironment::default()
+        };
+
+        assert!(matches("screen", &screen));
+        assert!(!matches("print", &screen));
+        assert!(matches("print", &print));
+        assert!(matches("not print", &screen));
+        assert!(matches("only screen and (min-width: 100px)", &screen));
+        assert!(matches("print, (min-width: 100px)", &screen));
+        assert!(!matches("tv", &screen));
+        assert!(matches("", &screen));
+    }
+
+    #[test]
+    fn test_preferences_and_resolution() {
+        let env = MediaEnvironment {
+            color_scheme: ColorScheme::Dark,
+            reduced_motion: true,
+            resolution: 2.0,
+            ..MediaEnvironment::default()
+        };
+
+        assert!(matches("(prefers-color-scheme: dark)", &env));
+        assert!(!matches("(prefers-color-scheme: light)", &env));
+        assert!(matches("(prefers-reduced-motion)", &env));
+        assert!(matches("(min-resolution: 2dppx)", &env));
+        assert!(matches("(min-resolution: 192dpi)", &env));
+        assert!(!matches("(prefers-reduced-motion: no-preference)", &env));
+        assert!(!matches("(prefers-reduced-motion)", &MediaEnvironment::default()));
+    }
+
+    #[test]
+    fn test_forced_colors() {
+        let env = MediaEnvironment {
+            forced_colors: true,
+            ..MediaEnvironment::default()
+        };
+
+        assert!(matches("(forced-colors: active)", &env));
+        assert!(matches("(forced-colors)", &env));
+        assert!(matches("(prefers-contrast: more)", &env));
+        assert!(!matches("(forced-colors)", &MediaEnvironment::default()));
+        assert!(matches("(forced-colors: none)", &MediaEnvironment::default()));
+        assert!(!matches("(prefers-contrast)", &MediaEnvironment::default()));
+    }
+
+    #[test]
+    fn test_invalid_queries_do_not_match() {
+        let env = MediaEnvironment::default();
+        assert!(!matches("(unknown-feature: 3)", &env));
+        assert!(!matches("screen and", &env));
+        assert!(matches("screen and, (min-width: 1px)", &env));
+        assert!(!matches("(min-width: 1px) and (max-width: 9999px) or (color)", &env));
+        assert!(matches("not ((max-width: 10px) or (print-thing))", &env));
+    }
+}
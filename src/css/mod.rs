@@ -1,16 +1,31 @@
+pub mod animation;
+pub mod background;
 pub mod cascade;
+pub mod color;
 pub mod computed;
+pub mod filter;
 pub mod index;
+pub mod invalidation;
+pub mod math;
+pub mod media;
 pub mod parser;
+pub mod properties;
 pub mod selector;
+pub mod shorthand;
 pub mod stylesheet;
+pub mod supports;
+pub mod timeline;
+pub mod transform;
+pub mod variables;
 
 pub use cascade::StyleComputer;
 pub use computed::ComputedStyle;
 pub use index::SelectorIndex;
+pub use media::{MediaEnvironment, MediaQueryList};
 pub use parser::parse_css;
 pub use selector::{
     parse_selector, AttributeSelector, Combinator, CompoundSelector, ComplexSelector, PseudoClass,
-    Selector, SimpleSelector, Specificity,
+    PseudoElement, Selector, SimpleSelector, Specificity,
 };
-pub use stylesheet::{Rule, Stylesheet};
+pub use stylesheet::{FontFace, FontSource, Import, Keyframes, Origin, Rule, Stylesheet};
+pub use timeline::AnimationTimeline;
@@ -1,94 +1,571 @@
-use super::selector::parse_selector;
-use super::stylesheet::{Declaration, Rule, Stylesheet, Unit, Value};
+use super::color;
+use super::math::CalcExpr;
+use super::media::{parse_media_query_list, MediaQueryList};
+use super::selector::{parse_selector, parse_selector_list, split_selector_list, Selector};
+use super::shorthand::expand_declaration;
+use super::stylesheet::{Declaration, FontFace, Import, Keyframe, Keyframes, Rule, Stylesheet, Unit, Value};
+use super::supports::{parse_supports_condition, parse_supports_function};
+use super::variables::contains_var;
 use crate::render::painter::Color;
-use cssparser::{Parser, ParserInput, Token};
+use cssparser::{Delimiter, Parser, ParserInput, Token};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 
 pub fn parse_css(css: &str) -> Stylesheet {
     let mut stylesheet = Stylesheet::new();
     let mut input = ParserInput::new(css);
     let mut parser = Parser::new(&mut input);
 
+    parse_rule_list(&mut parser, &Nesting::default(), &mut stylesheet);
+
+    stylesheet
+}
+
+/// What the rules being parsed are nested in
+#[derive(Debug, Clone, Default)]
+struct Nesting {
+    /// Conditions of the enclosing `@media` blocks
+    media: Vec<Rc<MediaQueryList>>,
+    /// The full name of the enclosing `@layer`
+    layer: Option<Rc<str>>,
+    /// Inside a style rule, the selector `&` stands for
+    parent: Option<Rc<str>>,
+}
+
+impl Nesting {
+    fn is_top_level(&self) -> bool {
+        self.media.is_empty() && self.layer.is_none() && self.parent.is_none()
+    }
+
+    /// A rule with the conditions and layer it is nested in
+    fn rule(&self, selectors: Vec<Selector>) -> Rule {
+        Rule {
+            selectors,
+            declarations: Vec::new(),
+            media: self.media.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+
+    /// The full name of the layer `name` declared in this one
+    fn layer_name(&self, name: &str) -> Rc<str> {
+        match &self.layer {
+            Some(layer) => Rc::from(format!("{}.{}", layer, name)),
+            None => Rc::from(name),
+        }
+    }
+}
+
+/// A name for each `@layer` block without one, which no named layer can have
+fn anonymous_layer_name() -> String {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    format!("#{}", COUNT.fetch_add(1, AtomicOrdering::Relaxed))
+}
+
+/// Parses rules at the top level or inside a conditional group rule
+fn parse_rule_list(parser: &mut Parser, nesting: &Nesting, stylesheet: &mut Stylesheet) {
     while !parser.is_exhausted() {
-        if let Ok(rule) = parse_rule(&mut parser) {
-            stylesheet.add_rule(rule);
-        } else {
+        let state = parser.state();
+        if let Ok(Token::AtKeyword(name)) = parser.next() {
+            let name = name.to_ascii_lowercase();
+            parse_at_rule(parser, &name, nesting, stylesheet);
+            continue;
+        }
+        parser.reset(&state);
+
+        if parse_rule(parser, nesting, stylesheet).is_err() {
             let _ = parser.next();
         }
     }
-
-    stylesheet
 }
 
-fn parse_rule<'i>(parser: &mut Parser<'i, '_>) -> Result<Rule, cssparser::ParseError<'i, ()>> {
-    let mut rule = Rule::new();
+/// Parses the block of a group rule, which inside a style rule holds
+/// declarations for the parent's selector as well as rules
+fn parse_group_block(parser: &mut Parser, nesting: &Nesting, stylesheet: &mut Stylesheet) {
+    if parser.expect_curly_bracket_block().is_err() {
+        skip_at_rule(parser);
+        return;
+    }
+    let _ = parser.parse_nested_block(|p| {
+        if nesting.parent.is_some() {
+            parse_style_block(p, nesting, stylesheet, None);
+        } else {
+            parse_rule_list(p, nesting, stylesheet);
+        }
+        Ok::<_, cssparser::ParseError<()>>(())
+    });
+}
 
-    let selector_str = parse_selector_string(parser)?;
-    for sel_part in selector_str.split(',') {
-        if let Some(selector) = parse_selector(sel_part.trim()) {
-            rule.selectors.push(selector);
+fn parse_at_rule(parser: &mut Parser, name: &str, nesting: &Nesting, stylesheet: &mut Stylesheet) {
+    match name {
+        "media" => {
+            let query = parse_media_query_list(parser);
+            let mut nesting = nesting.clone();
+            nesting.media.push(Rc::new(query));
+            parse_group_block(parser, &nesting, stylesheet);
+        }
+        "supports" => {
+            if parse_supports_condition(parser).matches() {
+                parse_group_block(parser, nesting, stylesheet);
+            } else {
+                skip_at_rule(parser);
+            }
         }
+        "layer" => parse_layer_rule(parser, nesting, stylesheet),
+        // Only before any other rules, but for layer statements
+        "import" if nesting.is_top_level() && is_before_rules(stylesheet) => {
+            let import = parser
+                .parse_until_after(Delimiter::Semicolon, |p| {
+                    Ok::<_, cssparser::ParseError<()>>(parse_import_prelude(p, stylesheet))
+                })
+                .ok()
+                .flatten();
+            stylesheet.imports.extend(import);
+        }
+        "font-face" if nesting.parent.is_none() => {
+            if parser.expect_curly_bracket_block().is_err() {
+                skip_at_rule(parser);
+                return;
+            }
+            let declarations = parser
+                .parse_nested_block(|p| Ok::<_, cssparser::ParseError<()>>(parse_declaration_list(p)))
+                .unwrap_or_default();
+            if let Some(face) = FontFace::from_declarations(&declarations) {
+                stylesheet.font_faces.push(face);
+            }
+        }
+        "keyframes" | "-webkit-keyframes" if nesting.parent.is_none() => {
+            let name = match parser.next() {
+                Ok(Token::Ident(name)) | Ok(Token::QuotedString(name)) => name.to_string(),
+                _ => {
+                    skip_at_rule(parser);
+                    return;
+                }
+            };
+            if parser.expect_curly_bracket_block().is_err() {
+                skip_at_rule(parser);
+                return;
+            }
+            let frames = parser
+                .parse_nested_block(|p| Ok::<_, cssparser::ParseError<()>>(parse_keyframe_list(p)))
+                .unwrap_or_default();
+            stylesheet.keyframes.push(Keyframes {
+                name,
+                frames,
+                media: nesting.media.clone(),
+            });
+        }
+        _ => skip_at_rule(parser),
     }
+}
 
-    parser.expect_curly_bracket_block()?;
-    parser.parse_nested_block(|p| {
+/// `@layer a, b.c;` declares the order of layers; `@layer name { ... }` and
+/// `@layer { ... }` put rules in a named or an anonymous layer
+fn parse_layer_rule(parser: &mut Parser, nesting: &Nesting, stylesheet: &mut Stylesheet) {
+    let names = parser.parse_until_before(Delimiter::CurlyBracketBlock | Delimiter::Semicolon, |p| {
+        let mut names = Vec::new();
         while !p.is_exhausted() {
-            if let Ok(decl) = parse_declaration(p) {
-                rule.declarations.push(decl);
-            } else {
-                let _ = p.next();
+            names.push(parse_layer_name(p)?);
+            if !p.is_exhausted() {
+                p.expect_comma()?;
             }
         }
-        Ok(())
-    })?;
+        Ok::<_, cssparser::ParseError<()>>(names)
+    });
+    let Ok(names) = names else {
+        skip_at_rule(parser);
+        return;
+    };
+
+    let state = parser.state();
+    let is_block = matches!(parser.next(), Ok(Token::CurlyBracketBlock));
+    parser.reset(&state);
+    if !is_block {
+        for name in names {
+            stylesheet.declare_layer(nesting.layer_name(&name));
+        }
+        skip_at_rule(parser);
+        return;
+    }
 
-    Ok(rule)
+    let name = match &names[..] {
+        [] => anonymous_layer_name(),
+        [name] => name.clone(),
+        _ => {
+            skip_at_rule(parser);
+            return;
+        }
+    };
+    let mut nesting = nesting.clone();
+    nesting.layer = Some(nesting.layer_name(&name));
+    stylesheet.declare_layer(nesting.layer.clone().unwrap());
+    parse_group_block(parser, &nesting, stylesheet);
 }
 
-fn parse_selector_string<'i>(parser: &mut Parser<'i, '_>) -> Result<String, cssparser::ParseError<'i, ()>> {
-    let mut selector = String::new();
+/// A layer name: identifiers joined by dots
+fn parse_layer_name<'i>(parser: &mut Parser<'i, '_>) -> Result<String, cssparser::ParseError<'i, ()>> {
+    let mut name = parser.expect_ident()?.to_string();
+    while let Ok(part) = parser.try_parse(|p| {
+        p.expect_delim('.')?;
+        // No space may come between the parts
+        match p.next_including_whitespace()? {
+            Token::Ident(part) => Ok(part.to_string()),
+            token => {
+                let token = token.clone();
+                Err(p.new_unexpected_token_error::<()>(token))
+            }
+        }
+    }) {
+        name.push('.');
+        name.push_str(&part);
+    }
+    Ok(name)
+}
 
-    loop {
+/// Whether only `@import`s and `@layer` statements have come so far, as
+/// must before an `@import`
+fn is_before_rules(stylesheet: &Stylesheet) -> bool {
+    stylesheet.rules.is_empty() && stylesheet.font_faces.is_empty() && stylesheet.keyframes.is_empty()
+}
+
+/// The prelude of `@import url [layer | layer(name)] [supports(...)] [media]`;
+/// `None` when invalid, or when the `supports()` condition is false
+fn parse_import_prelude(parser: &mut Parser, stylesheet: &mut Stylesheet) -> Option<Import> {
+    let url = match parser.next().ok()?.clone() {
+        Token::UnquotedUrl(url) | Token::QuotedString(url) => url.to_string(),
+        Token::Function(name) if name.eq_ignore_ascii_case("url") => parser
+            .parse_nested_block(|p| Ok::<_, cssparser::ParseError<()>>(p.expect_string()?.to_string()))
+            .ok()?,
+        _ => return None,
+    };
+
+    let layer: Option<Rc<str>> = if parser.try_parse(|p| p.expect_ident_matching("layer")).is_ok() {
+        Some(Rc::from(anonymous_layer_name()))
+    } else if parser.try_parse(|p| p.expect_function_matching("layer")).is_ok() {
+        let name = parser
+            .parse_nested_block(|p| {
+                let name = parse_layer_name(p)?;
+                p.expect_exhausted()?;
+                Ok::<_, cssparser::ParseError<()>>(name)
+            })
+            .ok()?;
+        Some(Rc::from(name))
+    } else {
+        None
+    };
+
+    if parser.try_parse(|p| p.expect_function_matching("supports")).is_ok() {
+        let condition = parser
+            .parse_nested_block(|p| Ok::<_, cssparser::ParseError<()>>(parse_supports_function(p)))
+            .ok()?;
+        if !condition.matches() {
+            return None;
+        }
+    }
+
+    let media = parse_media_query_list(parser);
+    if !parser.is_exhausted() {
+        return None;
+    }
+
+    let layer_position = stylesheet.layers.len();
+    if let Some(layer) = &layer {
+        stylesheet.declare_layer(Rc::clone(layer));
+    }
+    Some(Import {
+        url,
+        media,
+        layer,
+        layer_position,
+    })
+}
+
+/// The keyframes of a `@keyframes` block, sorted by offset. Keyframes
+/// with an invalid selector are dropped, as are `!important` declarations.
+fn parse_keyframe_list(parser: &mut Parser) -> Vec<Keyframe> {
+    let mut frames = Vec::new();
+    while !parser.is_exhausted() {
+        let mut offsets = Vec::new();
+        let mut valid = true;
+        loop {
+            match parser.next() {
+                Ok(Token::CurlyBracketBlock) => break,
+                Ok(Token::Ident(ident)) if ident.eq_ignore_ascii_case("from") => offsets.push(0.0),
+                Ok(Token::Ident(ident)) if ident.eq_ignore_ascii_case("to") => offsets.push(1.0),
+                Ok(Token::Percentage { unit_value, .. }) if (0.0..=1.0).contains(unit_value) => offsets.push(*unit_value),
+                Ok(Token::Comma) => {}
+                Ok(_) => valid = false,
+                Err(_) => return frames,
+            }
+        }
+        let declarations = parser
+            .parse_nested_block(|p| Ok::<_, cssparser::ParseError<()>>(parse_declaration_list(p)))
+            .unwrap_or_default();
+        if valid {
+            let declarations: Vec<Declaration> =
+                declarations.into_iter().filter(|decl| !decl.important).flat_map(expand_declaration).collect();
+            frames.extend(offsets.into_iter().map(|offset| Keyframe {
+                offset,
+                declarations: declarations.clone(),
+            }));
+        }
+    }
+    // Stable, so keyframes at the same offset keep their order
+    frames.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+    frames
+}
+
+/// Skips the rest of an unsupported at-rule: its prelude and its block or `;`
+fn skip_at_rule(parser: &mut Parser) {
+    while let Ok(token) = parser.next() {
+        if matches!(token, Token::Semicolon | Token::CurlyBracketBlock) {
+            break;
+        }
+    }
+}
+
+/// Parses a style rule, and the rules nested in it, into `stylesheet`
+fn parse_rule<'i>(
+    parser: &mut Parser<'i, '_>,
+    nesting: &Nesting,
+    stylesheet: &mut Stylesheet,
+) -> Result<(), cssparser::ParseError<'i, ()>> {
+    let selector_str = parse_selector_string(parser)?;
+    let selector_str = match &nesting.parent {
+        Some(parent) => resolve_nested_selector(&selector_str, parent),
+        None => selector_str,
+    };
+    parser.expect_curly_bracket_block()?;
+
+    let index = stylesheet.rules.len();
+    stylesheet.add_rule(nesting.rule(parse_selector_list(&selector_str)));
+    let mut nesting = nesting.clone();
+    nesting.parent = Some(Rc::from(selector_str));
+    parser.parse_nested_block(|p| {
+        parse_style_block(p, &nesting, stylesheet, Some(index));
+        Ok(())
+    })
+}
+
+/// Parses the contents of a style rule: declarations, and nested style and
+/// group rules. Declarations go in the rule at `index` until a nested rule
+/// appears; the ones after it go in a new rule for the same selector, so
+/// the cascade sees everything in source order.
+fn parse_style_block(parser: &mut Parser, nesting: &Nesting, stylesheet: &mut Stylesheet, mut index: Option<usize>) {
+    let parent = nesting.parent.as_deref().unwrap_or_default();
+    while !parser.is_exhausted() {
         let state = parser.state();
-        let token = parser.next_including_whitespace()?;
-        match token {
-            Token::CurlyBracketBlock => {
-                parser.reset(&state);
-                break;
+        match parser.next() {
+            Ok(Token::AtKeyword(name)) => {
+                let name = name.to_ascii_lowercase();
+                parse_at_rule(parser, &name, nesting, stylesheet);
+                index = None;
+                continue;
             }
-            Token::Ident(ident) => selector.push_str(ident),
-            Token::IDHash(id) => {
-                selector.push('#');
-                selector.push_str(id);
+            Ok(Token::Semicolon) => continue,
+            _ => parser.reset(&state),
+        }
+
+        if is_nested_rule(parser) {
+            if parse_rule(parser, nesting, stylesheet).is_err() {
+                let _ = parser.next();
             }
-            Token::Delim('.') => selector.push('.'),
-            Token::Delim('#') => selector.push('#'),
-            Token::Delim('*') => selector.push('*'),
-            Token::Delim(',') => selector.push(','),
-            Token::WhiteSpace(_) => selector.push(' '),
-            _ => {}
+            index = None;
+        } else if let Ok(decl) = parse_declaration(parser) {
+            let index = *index.get_or_insert_with(|| {
+                stylesheet.add_rule(nesting.rule(parse_selector_list(parent)));
+                stylesheet.rules.len() - 1
+            });
+            stylesheet.rules[index].declarations.extend(expand_declaration(decl));
+        } else {
+            let _ = parser.next();
         }
+    }
+}
 
-        if parser.is_exhausted() {
+/// Whether a style rule, rather than a declaration, comes next: a block
+/// follows before the end of the declaration
+fn is_nested_rule(parser: &mut Parser) -> bool {
+    let state = parser.state();
+    let mut is_rule = false;
+    // Custom properties can hold blocks
+    if !matches!(parser.next(), Ok(Token::Ident(name)) if name.starts_with("--")) {
+        loop {
+            match parser.next() {
+                Ok(Token::CurlyBracketBlock) => {
+                    is_rule = true;
+                    break;
+                }
+                Ok(Token::Semicolon) | Err(_) => break,
+                Ok(_) => {}
+            }
+        }
+    }
+    parser.reset(&state);
+    is_rule
+}
+
+/// The selector of a rule nested in a rule with the selector `parent`: each
+/// `&` stands for the parent, which a selector without one is relative to
+/// as a descendant. Where the parent can't simply be written in place,
+/// it's wrapped in `:is()`.
+fn resolve_nested_selector(selector: &str, parent: &str) -> String {
+    let is_single = split_selector_list(parent).len() == 1;
+    let is_compound = is_single
+        && parse_selector(parent)
+            .is_some_and(|parent| parent.complex.parts.len() == 1 && parent.complex.pseudo_element.is_none());
+    let wrapped = if is_compound {
+        parent.to_string()
+    } else {
+        format!(":is({})", parent)
+    };
+
+    split_selector_list(selector)
+        .into_iter()
+        .map(|selector| {
+            let selector = selector.trim();
+            let selector = if selector.contains('&') {
+                selector.to_string()
+            } else {
+                format!("& {}", selector)
+            };
+            // A leading `&` is followed by the rest of its compound, or a
+            // combinator, so the parent's text can go there as it is
+            match selector.strip_prefix('&') {
+                Some(rest) if is_single => format!("{}{}", parent, rest.replace('&', &wrapped)),
+                _ => selector.replace('&', &wrapped),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The declarations of a block, skipping any that fail to parse
+fn parse_declaration_list(parser: &mut Parser) -> Vec<Declaration> {
+    let mut declarations = Vec::new();
+    while !parser.is_exhausted() {
+        if let Ok(decl) = parse_declaration(parser) {
+            declarations.push(decl);
+        } else {
+            let _ = parser.next();
+        }
+    }
+    declarations
+}
+
+/// The source text of a rule's selector list, up to its block
+fn parse_selector_string<'i>(parser: &mut Parser<'i, '_>) -> Result<String, cssparser::ParseError<'i, ()>> {
+    let start = parser.position();
+
+    loop {
+        let state = parser.state();
+        if let Token::CurlyBracketBlock = parser.next_including_whitespace()? {
+            parser.reset(&state);
             break;
         }
     }
 
-    Ok(selector.trim().to_string())
+    Ok(normalize_selector(parser.slice_from(start)))
 }
 
-fn parse_declaration<'i>(parser: &mut Parser<'i, '_>) -> Result<Declaration, cssparser::ParseError<'i, ()>> {
+/// Selector source with comments removed and each run of whitespace made a
+/// single space, the only descendant combinator the selector parser knows.
+/// Quoted strings are kept as they are.
+fn normalize_selector(source: &str) -> String {
+    let mut normalized = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut quote = None;
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (_, '\\') => {
+                normalized.push(c);
+                normalized.extend(chars.next());
+            }
+            (Some(open), _) => {
+                if c == open {
+                    quote = None;
+                }
+                normalized.push(c);
+            }
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                normalized.push(c);
+            }
+            (None, '/') if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = None;
+                for c in chars.by_ref() {
+                    if previous == Some('*') && c == '/' {
+                        break;
+                    }
+                    previous = Some(c);
+                }
+            }
+            (None, ' ' | '\t' | '\n' | '\r' | '\x0C') => {
+                if !normalized.is_empty() && !normalized.ends_with(' ') {
+                    normalized.push(' ');
+                }
+            }
+            _ => normalized.push(c),
+        }
+    }
+    normalized.trim_end().to_string()
+}
+
+pub(super) fn parse_declaration<'i>(parser: &mut Parser<'i, '_>) -> Result<Declaration, cssparser::ParseError<'i, ()>> {
     let property = parser.expect_ident()?.to_string();
     parser.expect_colon()?;
 
-    let (value, important) = parse_value_list(parser)?;
+    // Custom properties and values using var() can only be interpreted once
+    // the element's custom properties are known, so keep their raw tokens
+    let state = parser.state();
+    let (raw, important) = parse_raw_value(parser);
+    let (value, important) = if property.starts_with("--") || contains_var(&raw) {
+        (Value::Unresolved(raw), important)
+    } else {
+        parser.reset(&state);
+        parse_value_list(parser)?
+    };
 
     let _ = parser.try_parse(|p| p.expect_semicolon());
 
     Ok(Declaration { property, value, important })
 }
 
-/// Parse a list of values (for shorthand properties) and check for !important
+/// The source text of a declaration value, without a trailing `!important`
+fn parse_raw_value(parser: &mut Parser) -> (String, bool) {
+    let raw = parser
+        .parse_until_before(Delimiter::Semicolon, |p| {
+            let start = p.position();
+            while p.next().is_ok() {}
+            Ok::<_, cssparser::ParseError<()>>(p.slice_from(start).trim().to_string())
+        })
+        .unwrap_or_default();
+
+    let lower = raw.to_ascii_lowercase();
+    if let Some(rest) = lower.strip_suffix("important") {
+        if let Some(rest) = rest.trim_end().strip_suffix('!') {
+            return (raw[..rest.len()].trim_end().to_string(), true);
+        }
+    }
+    (raw, false)
+}
+
+/// Parses a declaration value from text, as produced by `var()` substitution.
+/// Returns `None` for an empty value.
+pub fn parse_value(text: &str) -> Option<Value> {
+    if text.trim().is_empty() {
+        return None;
+    }
+    let mut input = ParserInput::new(text);
+    let mut parser = Parser::new(&mut input);
+    parse_value_list(&mut parser).ok().map(|(value, _)| value)
+}
+
+/// Parse a list of values (for shorthand properties) and check for !important.
+/// Commas split it into a `CommaList` of space-separated items.
 fn parse_value_list<'i>(parser: &mut Parser<'i, '_>) -> Result<(Value, bool), cssparser::ParseError<'i, ()>> {
+    let mut items = Vec::new();
     let mut values = Vec::new();
     let mut important = false;
 
@@ -113,6 +590,10 @@ fn parse_value_list<'i>(parser: &mut Parser<'i, '_>) -> Result<(Value, bool), cs
                     }
                 }
             }
+            Ok(Token::Comma) => {
+                items.push(join_values(std::mem::take(&mut values)));
+                continue;
+            }
             _ => {
                 parser.reset(&state);
             }
@@ -133,16 +614,26 @@ fn parse_value_list<'i>(parser: &mut Parser<'i, '_>) -> Result<(Value, bool), cs
         }
     }
 
-    // Return single value or list
-    let value = match values.len() {
-        0 => Value::Keyword(String::new()),
-        1 => values.pop().unwrap(),
-        _ => Value::List(values),
+    let value = join_values(values);
+    let value = if items.is_empty() {
+        value
+    } else {
+        items.push(value);
+        Value::CommaList(items)
     };
 
     Ok((value, important))
 }
 
+/// A single value, or a list of space-separated ones
+fn join_values(mut values: Vec<Value>) -> Value {
+    match values.len() {
+        0 => Value::Keyword(String::new()),
+        1 => values.pop().unwrap(),
+        _ => Value::List(values),
+    }
+}
+
 fn parse_single_value<'i>(parser: &mut Parser<'i, '_>) -> Result<Value, cssparser::ParseError<'i, ()>> {
     let token = parser.next()?.clone();
 
@@ -164,78 +655,87 @@ fn parse_single_value<'i>(parser: &mut Parser<'i, '_>) -> Result<Value, cssparse
                 Ok(Value::Keyword(hash.to_string()))
             }
         }
-        Token::Dimension { value, unit, .. } => {
-            let unit = match unit.as_ref() {
-                "px" => Unit::Px,
-                "em" => Unit::Em,
-                "rem" => Unit::Rem,
-                "%" => Unit::Percent,
-                "vh" => Unit::Vh,
-                "vw" => Unit::Vw,
-                _ => Unit::Px,
-            };
-            Ok(Value::Length(value, unit))
-        }
+        Token::Dimension { value, unit, .. } => match Unit::from_name(&unit) {
+            Some(unit) => Ok(Value::Length(value, unit)),
+            None => match angle_to_degrees(value, &unit) {
+                Some(degrees) => Ok(Value::Angle(degrees)),
+                None => match time_to_seconds(value, &unit) {
+                    Some(seconds) => Ok(Value::Time(seconds)),
+                    // An unknown unit is never a valid length
+                    None => Ok(Value::Keyword(format!("{}{}", value, unit))),
+                },
+            },
+        },
         Token::Percentage { unit_value, .. } => {
             Ok(Value::Percentage(unit_value * 100.0))
         }
         Token::Number { value, .. } => {
             Ok(Value::Number(value))
         }
+        Token::Function(name) if CalcExpr::is_math_function(&name) => {
+            let expr = parser.parse_nested_block(|p| CalcExpr::parse_function(&name, p))?;
+            Ok(Value::Calc(Rc::new(expr)))
+        }
+        Token::Function(name) if color::is_color_function(&name) => {
+            parser.parse_nested_block(|p| Ok(color::parse_function(&name, p)))
+        }
         Token::Function(name) => {
-            let name = name.to_string();
+            let name = name.to_ascii_lowercase();
             parser.parse_nested_block(|p| {
-                if name == "rgb" || name == "rgba" {
-                    parse_rgb_function(p, name == "rgba")
-                } else {
-                    Ok(Value::Keyword(name))
-                }
+                parse_function_arguments(p).map(|args| match (name.as_str(), args.as_slice()) {
+                    ("url", [Value::String(url)]) => Value::Url(url.clone()),
+                    ("color-mix", _) => color::mix_value(args),
+                    _ => Value::Function(name, args),
+                })
             })
         }
+        Token::UnquotedUrl(url) => Ok(Value::Url(url.to_string())),
+        Token::QuotedString(s) => Ok(Value::String(s.to_string())),
         _ => Ok(Value::Keyword(String::new())),
     }
 }
 
-fn parse_rgb_function<'i>(parser: &mut Parser<'i, '_>, has_alpha: bool) -> Result<Value, cssparser::ParseError<'i, ()>> {
-    let r = parse_color_component(parser)?;
-    let _ = parser.try_parse(|p| p.expect_comma());
-
-    let g = parse_color_component(parser)?;
-    let _ = parser.try_parse(|p| p.expect_comma());
-
-    let b = parse_color_component(parser)?;
-
-    let a = if has_alpha {
-        let _ = parser.try_parse(|p| p.expect_comma());
-        parse_alpha_component(parser).unwrap_or(1.0)
-    } else {
-        1.0
+/// An angle in `unit` converted to degrees
+pub(super) fn angle_to_degrees(value: f32, unit: &str) -> Option<f32> {
+    let degrees = match unit.to_ascii_lowercase().as_str() {
+        "deg" => value,
+        "rad" => value.to_degrees(),
+        "grad" => value * 0.9,
+        "turn" => value * 360.0,
+        _ => return None,
     };
-
-    Ok(Value::Color(Color::rgba(r, g, b, (a * 255.0) as u8)))
+    Some(degrees)
 }
 
-fn parse_color_component<'i>(parser: &mut Parser<'i, '_>) -> Result<u8, cssparser::ParseError<'i, ()>> {
-    let token = parser.next()?.clone();
-    match token {
-        Token::Number { value, .. } => Ok(value.clamp(0.0, 255.0) as u8),
-        Token::Percentage { unit_value, .. } => Ok((unit_value * 255.0).clamp(0.0, 255.0) as u8),
-        _ => Ok(0),
+/// A time in `unit` converted to seconds
+fn time_to_seconds(value: f32, unit: &str) -> Option<f32> {
+    match unit.to_ascii_lowercase().as_str() {
+        "s" => Some(value),
+        "ms" => Some(value / 1000.0),
+        _ => None,
     }
 }
 
-fn parse_alpha_component<'i>(parser: &mut Parser<'i, '_>) -> Result<f32, cssparser::ParseError<'i, ()>> {
-    let token = parser.next()?.clone();
-    match token {
-        Token::Number { value, .. } => Ok(value.clamp(0.0, 1.0)),
-        Token::Percentage { unit_value, .. } => Ok(unit_value.clamp(0.0, 1.0)),
-        _ => Ok(1.0),
+/// Comma-separated function arguments, each of which may be a list of
+/// space-separated values
+fn parse_function_arguments<'i>(parser: &mut Parser<'i, '_>) -> Result<Vec<Value>, cssparser::ParseError<'i, ()>> {
+    let mut args = Vec::new();
+    loop {
+        let arg = parser.parse_until_before(Delimiter::Comma, |p| parse_value_list(p).map(|(value, _)| value))?;
+        args.push(arg);
+        if parser.is_exhausted() {
+            return Ok(args);
+        }
+        parser.expect_comma()?;
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::css::stylesheet::FontSource;
+    use crate::render::font::FontStyle;
+    use url::Url;
 
     #[test]
     fn test_parse_simple_rule() {
@@ -283,10 +783,12 @@ mod tests {
         let stylesheet = parse_css(css);
 
         assert_eq!(stylesheet.rules.len(), 1);
-        assert_eq!(stylesheet.rules[0].declarations.len(), 3);
+        // margin sets its four longhands
+        assert_eq!(stylesheet.rules[0].declarations.len(), 6);
         assert_eq!(stylesheet.rules[0].declarations[0].property, "color");
         assert_eq!(stylesheet.rules[0].declarations[1].property, "font-size");
-        assert_eq!(stylesheet.rules[0].declarations[2].property, "margin");
+        assert_eq!(stylesheet.rules[0].declarations[2].property, "margin-top");
+        assert_eq!(stylesheet.rules[0].declarations[5].property, "margin-left");
     }
 
     #[test]
@@ -387,6 +889,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_units() {
+        let css = "p { a: 12pt; b: 2CH; c: 50dvh; d: 3vmin; e: 1rlh; f: 4parsecs; g: 0.25turn; h: 100grad; }";
+        let stylesheet = parse_css(css);
+        let values: Vec<&Value> = stylesheet.rules[0].declarations.iter().map(|d| &d.value).collect();
+
+        assert!(matches!(values[0], Value::Length(_, Unit::Pt)));
+        assert!(matches!(values[1], Value::Length(_, Unit::Ch)));
+        assert!(matches!(values[2], Value::Length(_, Unit::Vh)));
+        assert!(matches!(values[3], Value::Length(_, Unit::Vmin)));
+        assert!(matches!(values[4], Value::Length(_, Unit::Rlh)));
+        // Unknown units are not lengths
+        assert!(matches!(values[5], Value::Keyword(_)));
+        // Angles are kept in degrees
+        assert!(matches!(values[6], Value::Angle(deg) if *deg == 90.0));
+        assert!(matches!(values[7], Value::Angle(deg) if *deg == 90.0));
+    }
+
     #[test]
     fn test_parse_rgb_function() {
         let css = "div { color: rgb(255, 128, 0); }";
@@ -400,6 +920,14 @@ mod tests {
         } else {
             panic!("Expected color value");
         }
+
+        // Space-separated components with a slash before the alpha; an
+        // invalid color isn't one
+        let stylesheet = parse_css("div { color: rgb(0 255 0 / 25%); box-shadow: 1px 1px rgb(1 2, 3); }");
+        let declarations = &stylesheet.rules[0].declarations;
+        assert_eq!(declarations[0].value.to_color(), Some(Color { r: 0.0, g: 1.0, b: 0.0, a: 0.25 }));
+        let invalid = &declarations[1].value.as_slice()[2];
+        assert!(matches!(invalid, Value::Function(name, args) if name == "rgb" && args.is_empty()));
     }
 
     #[test]
@@ -429,17 +957,31 @@ mod tests {
         assert_eq!(stylesheet.rules.len(), 1);
     }
 
+    #[test]
+    fn test_parse_multiline_selectors() {
+        let css = ".nav\n  a,\n\tul/* list */ >\n li[title=\"a  b\"] { color: red; }";
+        let stylesheet = parse_css(css);
+        let doc = crate::dom::parse_html(r#"<div class="nav"><a id="a">A</a></div><ul><li id="li" title="a  b">B</li></ul>"#);
+
+        let selectors = &stylesheet.rules[0].selectors;
+        assert_eq!(selectors.len(), 2);
+        assert!(selectors[0].complex.matches(&doc, doc.get_element_by_id("a").unwrap()));
+        assert!(selectors[1].complex.matches(&doc, doc.get_element_by_id("li").unwrap()));
+        assert_eq!(normalize_selector("div /* x */\r\n p[title='a\tb']"), "div p[title='a\tb']");
+    }
+
     #[test]
     fn test_parse_multi_value_margin() {
         let css = "div { margin: 10px 20px; }";
         let stylesheet = parse_css(css);
 
-        let decl = &stylesheet.rules[0].declarations[0];
-        assert_eq!(decl.property, "margin");
-        assert!(matches!(decl.value, Value::List(_)));
-        if let Value::List(values) = &decl.value {
-            assert_eq!(values.len(), 2);
-        }
+        let declarations = &stylesheet.rules[0].declarations;
+        let properties: Vec<&str> = declarations.iter().map(|decl| decl.property.as_str()).collect();
+        assert_eq!(properties, ["margin-top", "margin-right", "margin-bottom", "margin-left"]);
+        assert!(matches!(declarations[0].value, Value::Length(10.0, Unit::Px)));
+        assert!(matches!(declarations[1].value, Value::Length(20.0, Unit::Px)));
+        assert!(matches!(declarations[2].value, Value::Length(10.0, Unit::Px)));
+        assert!(matches!(declarations[3].value, Value::Length(20.0, Unit::Px)));
     }
 
     #[test]
@@ -447,12 +989,10 @@ mod tests {
         let css = "div { padding: 1px 2px 3px 4px; }";
         let stylesheet = parse_css(css);
 
-        let decl = &stylesheet.rules[0].declarations[0];
-        if let Value::List(values) = &decl.value {
-            assert_eq!(values.len(), 4);
-        } else {
-            panic!("Expected list value");
-        }
+        let declarations = &stylesheet.rules[0].declarations;
+        assert_eq!(declarations.len(), 4);
+        assert_eq!(declarations[3].property, "padding-left");
+        assert!(matches!(declarations[3].value, Value::Length(4.0, Unit::Px)));
     }
 
     #[test]
@@ -487,8 +1027,246 @@ mod tests {
         let css = "div { border: 1px solid black; }";
         let stylesheet = parse_css(css);
 
-        let decl = &stylesheet.rules[0].declarations[0];
-        assert_eq!(decl.property, "border");
-        assert!(matches!(decl.value, Value::List(_)));
+        // The width, style and color of each side
+        let declarations = &stylesheet.rules[0].declarations;
+        assert_eq!(declarations.len(), 12);
+        assert_eq!(declarations[0].property, "border-top-width");
+        assert_eq!(declarations[4].property, "border-top-style");
+        assert_eq!(declarations[11].property, "border-left-color");
+        assert_eq!(declarations[11].value.to_color(), Some(Color::BLACK));
+    }
+
+    #[test]
+    fn test_parse_media_block() {
+        let css = r#"
+            p { color: red; }
+            @media (max-width: 600px) {
+                p { color: blue; }
+                @media print { .nav { display: none; } }
+            }
+            @font-face { font-family: X; src: url(x.woff); }
+            @charset "utf-8";
+            div { color: green; }
+        "#;
+        let stylesheet = parse_css(css);
+
+        assert_eq!(stylesheet.rules.len(), 4);
+        assert!(stylesheet.rules[0].media.is_empty());
+        assert_eq!(stylesheet.rules[1].media.len(), 1);
+        assert_eq!(stylesheet.rules[2].media.len(), 2);
+        assert_eq!(stylesheet.rules[3].declarations[0].property, "color");
+        assert!(stylesheet.rules[3].media.is_empty());
+        assert_eq!(stylesheet.font_faces.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_font_face() {
+        let css = r#"
+            @font-face {
+                font-family: "Open Sans";
+                src: local("Open Sans Regular"), url("fonts/open.woff2") format("woff2"),
+                     url(fonts/open.woff) format("woff");
+                font-weight: bold;
+                font-style: italic;
+            }
+            @font-face { src: url(missing-family.ttf); }
+            p { font-family: "Open Sans", Arial, sans-serif; }
+        "#;
+        let mut stylesheet = parse_css(css);
+        assert_eq!(stylesheet.font_faces.len(), 1);
+        assert_eq!(stylesheet.rules.len(), 1);
+
+        let face = &stylesheet.font_faces[0];
+        assert_eq!(face.descriptors.family, "Open Sans");
+        assert_eq!(face.descriptors.weight, Some(700));
+        assert_eq!(face.descriptors.style, Some(FontStyle::Italic));
+        assert_eq!(
+            face.sources,
+            vec![
+                FontSource::Local("Open Sans Regular".to_string()),
+                FontSource::Url { url: "fonts/open.woff2".to_string(), format: Some("woff2".to_string()) },
+                FontSource::Url { url: "fonts/open.woff".to_string(), format: Some("woff".to_string()) },
+            ]
+        );
+
+        stylesheet.resolve_urls(&Url::parse("https://example.com/css/site.css").unwrap());
+        assert_eq!(
+            stylesheet.font_faces[0].sources[2],
+            FontSource::Url {
+                url: "https://example.com/css/fonts/open.woff".to_string(),
+                format: Some("woff".to_string())
+            }
+        );
+
+        // Commas in a value split it into a list
+        match &stylesheet.rules[0].declarations[0].value {
+            Value::CommaList(items) => {
+                assert_eq!(items.len(), 3);
+                assert!(matches!(&items[0], Value::String(name) if name == "Open Sans"));
+                assert_eq!(items[2].as_keyword(), Some("sans-serif"));
+            }
+            other => panic!("expected a comma list, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_keyframes() {
+        let css = r#"
+            @keyframes pulse {
+                to { opacity: 0.5; }
+                from, 50% { opacity: 1; color: red !important; }
+                200% { opacity: 0; }
+            }
+            @media print { @keyframes "quoted" { from { opacity: 0 } } }
+            p { animation: pulse 300ms; }
+        "#;
+        let stylesheet = parse_css(css);
+        assert_eq!(stylesheet.rules.len(), 1);
+        assert_eq!(stylesheet.keyframes.len(), 2);
+
+        // Sorted by offset; the out-of-range keyframe is dropped, as is
+        // the !important declaration
+        let pulse = &stylesheet.keyframes[0];
+        assert_eq!(pulse.name, "pulse");
+        let offsets: Vec<f32> = pulse.frames.iter().map(|frame| frame.offset).collect();
+        assert_eq!(offsets, vec![0.0, 0.5, 1.0]);
+        assert_eq!(pulse.frames[0].declarations.len(), 1);
+
+        assert_eq!(stylesheet.keyframes[1].name, "quoted");
+        assert_eq!(stylesheet.keyframes[1].media.len(), 1);
+
+        let duration = &stylesheet.rules[0].declarations[1];
+        assert_eq!(duration.property, "animation-duration");
+        assert!(matches!(duration.value, Value::Time(seconds) if (seconds - 0.3).abs() < 1e-6));
+    }
+
+    /// How many selectors and declarations each rule has
+    fn rule_sizes(stylesheet: &Stylesheet) -> Vec<(usize, usize)> {
+        stylesheet.rules.iter().map(|rule| (rule.selectors.len(), rule.declarations.len())).collect()
+    }
+
+    #[test]
+    fn test_parse_nesting() {
+        let css = r#"
+            .card, .panel {
+                color: red;
+                &:hover { color: blue; }
+                > h2 { margin: 0; }
+                .title & { color: green; }
+                @media print { display: none; a { color: black; } }
+                padding: 1px;
+            }
+            nav a { &.active { color: red; } }
+        "#;
+        let stylesheet = parse_css(css);
+        let sizes = vec![(2, 1), (1, 1), (1, 4), (1, 1), (2, 1), (1, 1), (2, 4), (1, 0), (1, 1)];
+        assert_eq!(rule_sizes(&stylesheet), sizes);
+
+        assert_eq!(resolve_nested_selector("&:hover", ".card, .panel"), ":is(.card, .panel):hover");
+        assert_eq!(resolve_nested_selector("> h2, &.x", ".card"), ".card > h2, .card.x");
+        assert_eq!(resolve_nested_selector(".title &", "nav a"), ".title :is(nav a)");
+        assert_eq!(resolve_nested_selector("&.active", "nav a"), "nav a.active");
+
+        // Declarations in a nested @media apply to the parent's selector
+        let print = &stylesheet.rules[4];
+        assert_eq!(print.media.len(), 1);
+        assert_eq!(print.declarations[0].property, "display");
+        assert_eq!(stylesheet.rules[5].media.len(), 1);
+        // Declarations after nested rules come after them
+        assert_eq!(stylesheet.rules[6].declarations[0].property, "padding-top");
+        assert!(stylesheet.rules[6].media.is_empty());
+    }
+
+    #[test]
+    fn test_parse_layers() {
+        let css = r#"
+            @layer base, components.buttons;
+            @layer base { p { color: red; } @layer reset { * { margin: 0; } } }
+            @layer { p { color: blue; } }
+            @layer a, b { p { color: red; } }
+            p { color: green; }
+        "#;
+        let stylesheet = parse_css(css);
+        assert_eq!(stylesheet.rules.len(), 4);
+        let layers: Vec<&str> = stylesheet.layers.iter().map(|layer| &**layer).collect();
+        assert_eq!(&layers[..4], ["base", "components", "components.buttons", "base.reset"]);
+        assert!(layers[4].starts_with('#'));
+        assert_eq!(stylesheet.rules[0].layer.as_deref(), Some("base"));
+        assert_eq!(stylesheet.rules[1].layer.as_deref(), Some("base.reset"));
+        assert_eq!(stylesheet.rules[2].layer.as_deref(), Some(layers[4]));
+        assert_eq!(stylesheet.rules[3].layer, None);
+    }
+
+    #[test]
+    fn test_parse_supports() {
+        let css = r#"
+            @supports (display: grid) and (not (frobnicate: 1)) { .a { display: grid; } }
+            @supports (frobnicate: 1) { .b { color: red; } }
+            @supports selector(:has(> img)) { @media print { .c { color: red; } } }
+            @supports (flex: 1 2 3) or (not (border: 1px solid)) { .d { color: red; } }
+        "#;
+        let stylesheet = parse_css(css);
+        assert_eq!(stylesheet.rules.len(), 2);
+        assert_eq!(stylesheet.rules[0].declarations[0].property, "display");
+        assert_eq!(stylesheet.rules[1].media.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_imports() {
+        let css = r#"
+            @layer first;
+            @import "a.css" print;
+            @import url(b.css) layer(lib) supports(display: grid) screen and (min-width: 100px);
+            @import url("c.css") supports(frobnicate: 1);
+            p { color: red; }
+            @import "late.css";
+        "#;
+        let stylesheet = parse_css(css);
+        assert_eq!(stylesheet.imports.len(), 2);
+        assert_eq!(stylesheet.imports[0].url, "a.css");
+        assert_eq!(stylesheet.imports[0].media.queries.len(), 1);
+        assert_eq!(stylesheet.imports[1].url, "b.css");
+        assert_eq!(stylesheet.imports[1].layer.as_deref(), Some("lib"));
+        assert_eq!(stylesheet.imports[1].layer_position, 1);
+        assert_eq!(stylesheet.rules.len(), 1);
+    }
+
+    #[test]
+    fn test_load_imports() {
+        let css = r#"
+            @import "theme/a.css" print;
+            @import url(b.css) layer(lib);
+            @layer own;
+            p { color: red; }
+        "#;
+        let mut stylesheet = parse_css(css);
+        let base = Url::parse("http://example.com/css/main.css").unwrap();
+        let mut fetched = Vec::new();
+        stylesheet.load_imports(&base, &mut |url| {
+            fetched.push(url.to_string());
+            match url.path() {
+                "/css/theme/a.css" => Some("@import '../main.css'; a { background-image: url(bg.png); }".to_string()),
+                "/css/b.css" => Some("@layer x { b { color: blue; } } i { color: red; }".to_string()),
+                _ => None,
+            }
+        });
+
+        // The import back to the main sheet is not followed
+        assert_eq!(fetched, vec!["http://example.com/css/theme/a.css", "http://example.com/css/b.css"]);
+        assert!(stylesheet.imports.is_empty());
+        assert_eq!(stylesheet.rules.len(), 4);
+
+        // Imported rules come first, within the import's media and layer
+        let a = &stylesheet.rules[0];
+        assert_eq!(a.media.len(), 1);
+        let Value::Url(url) = &a.declarations[0].value else {
+            panic!("expected a url");
+        };
+        assert_eq!(url, "http://example.com/css/theme/bg.png");
+        assert_eq!(stylesheet.rules[1].layer.as_deref(), Some("lib.x"));
+        assert_eq!(stylesheet.rules[2].layer.as_deref(), Some("lib"));
+        assert_eq!(stylesheet.rules[3].layer, None);
+        let layers: Vec<&str> = stylesheet.layers.iter().map(|layer| &**layer).collect();
+        assert_eq!(layers, ["lib", "lib.x", "own"]);
     }
 }
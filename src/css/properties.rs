@@ -0,0 +1,298 @@
+//! The property table: for each property `ComputedStyle` holds, whether it
+//! is inherited and which computed values it covers. This drives inheritance
+//! and the CSS-wide keywords `inherit`, `initial`, `unset` and `revert`.
+
+use super::computed::ComputedStyle;
+use super::shorthand;
+use super::stylesheet::Value;
+
+/// A property and the computed values it sets
+pub struct Property {
+    pub name: &'static str,
+    pub inherited: bool,
+    /// Copies this property's computed values from one style to another
+    copy: fn(&mut ComputedStyle, &ComputedStyle),
+    /// Whether two styles have the same computed values for it
+    same: fn(&ComputedStyle, &ComputedStyle) -> bool,
+}
+
+impl Property {
+    pub fn copy(&self, style: &mut ComputedStyle, from: &ComputedStyle) {
+        (self.copy)(style, from)
+    }
+
+    pub fn same(&self, style: &ComputedStyle, other: &ComputedStyle) -> bool {
+        (self.same)(style, other)
+    }
+}
+
+macro_rules! properties {
+    ($($name:literal, $inherited:literal => $($($field:ident).+),+;)*) => {
+        const PROPERTIES: &[Property] = &[$(
+            Property {
+                name: $name,
+                inherited: $inherited,
+                copy: |style, from| {
+                    $(style.$($field).+ = from.$($field).+.clone();)+
+                },
+                same: |style, other| true $(&& style.$($field).+ == other.$($field).+)+,
+            },
+        )*];
+    };
+}
+
+properties! {
+    "display", false => display;
+    "position", false => position;
+    "top", false => top;
+    "right", false => right;
+    "bottom", false => bottom;
+    "left", false => left;
+    "float", false => float;
+    "clear", false => clear;
+    "z-index", false => z_index;
+
+    "width", false => width;
+    "height", false => height;
+    "min-width", false => min_width;
+    "min-height", false => min_height;
+    "max-width", false => max_width;
+    "max-height", false => max_height;
+    "box-sizing", false => box_sizing;
+
+    "margin-top", false => margin_top;
+    "margin-right", false => margin_right;
+    "margin-bottom", false => margin_bottom;
+    "margin-left", false => margin_left;
+    "padding-top", false => padding_top;
+    "padding-right", false => padding_right;
+    "padding-bottom", false => padding_bottom;
+    "padding-left", false => padding_left;
+
+    "border-top-width", false => border_top_width;
+    "border-right-width", false => border_right_width;
+    "border-bottom-width", false => border_bottom_width;
+    "border-left-width", false => border_left_width;
+    "border-top-style", false => border_top_style;
+    "border-right-style", false => border_right_style;
+    "border-bottom-style", false => border_bottom_style;
+    "border-left-style", false => border_left_style;
+    "border-top-color", false => border_top_color;
+    "border-right-color", false => border_right_color;
+    "border-bottom-color", false => border_bottom_color;
+    "border-left-color", false => border_left_color;
+    "border-top-left-radius", false => border_top_left_radius;
+    "border-top-right-radius", false => border_top_right_radius;
+    "border-bottom-left-radius", false => border_bottom_left_radius;
+    "border-bottom-right-radius", false => border_bottom_right_radius;
+    "outline-width", false => outline_width;
+    "outline-style", false => outline_style;
+    "outline-color", false => outline_color;
+    "outline-offset", false => outline_offset;
+    "border-collapse", true => border_collapse;
+    "border-spacing", true => border_spacing;
+
+    "color", true => color;
+    "background-color", false => background_color;
+    "background-image", false => background.image;
+    "background-position", false => background.position;
+    "background-size", false => background.size;
+    "background-repeat", false => background.repeat;
+    "background-origin", false => background.origin;
+    "background-clip", false => background.clip;
+    "background-attachment", false => background.attachment;
+    "forced-color-adjust", true => forced_color_adjust;
+    "box-shadow", false => box_shadow;
+    "opacity", false => opacity;
+    "visibility", true => visibility;
+    "overflow", false => overflow, overflow_x, overflow_y;
+    "overflow-x", false => overflow_x;
+    "overflow-y", false => overflow_y;
+
+    "font-size", true => font_size;
+    "font-weight", true => font_weight;
+    "font-family", true => font_family;
+    "font-style", true => font_style;
+    "font-stretch", true => font_stretch;
+    "line-height", true => line_height;
+    "text-align", true => text_align;
+    // Not inherited in the spec, but a decoration is drawn across all of an
+    // element's inline descendants, which inheriting stands in for
+    "text-decoration-line", true => text_decoration_line;
+    "text-decoration-style", true => text_decoration_style;
+    "text-decoration-color", true => text_decoration_color;
+    "text-decoration-thickness", true => text_decoration_thickness;
+    "text-transform", true => text_transform;
+    "letter-spacing", true => letter_spacing;
+    "word-spacing", true => word_spacing;
+    "text-indent", true => text_indent;
+    "text-shadow", true => text_shadow;
+    "white-space", true => white_space;
+    "vertical-align", false => vertical_align;
+
+    "flex-direction", false => flex_direction;
+    "flex-wrap", false => flex_wrap;
+    "justify-content", false => justify_content;
+    "align-items", false => align_items;
+    "align-content", false => align_content;
+    "gap", false => gap, row_gap, column_gap;
+    "row-gap", false => row_gap;
+    "column-gap", false => column_gap;
+    "flex-grow", false => flex_grow;
+    "flex-shrink", false => flex_shrink;
+    "flex-basis", false => flex_basis;
+    "align-self", false => align_self;
+    "order", false => order;
+
+    "grid-template-columns", false => grid_template_columns;
+    "grid-template-rows", false => grid_template_rows;
+    "grid-auto-columns", false => grid_auto_columns;
+    "grid-auto-rows", false => grid_auto_rows;
+    "grid-gap", false => grid_gap, grid_row_gap, grid_column_gap;
+    "grid-row-gap", false => grid_row_gap;
+    "grid-column-gap", false => grid_column_gap;
+    "grid-column-start", false => grid_column.start;
+    "grid-column-end", false => grid_column.end;
+    "grid-row-start", false => grid_row.start;
+    "grid-row-end", false => grid_row.end;
+
+    "list-style-type", true => list_style_type;
+    "list-style-position", true => list_style_position;
+    "content", false => content;
+    "counter-reset", false => counter_reset;
+    "counter-increment", false => counter_increment;
+    "counter-set", false => counter_set;
+    "quotes", true => quotes;
+
+    "translate", false => translate;
+    "rotate", false => rotate;
+    "scale", false => scale;
+    "transform", false => transform;
+    "transform-origin", false => transform_origin;
+
+    "filter", false => filter;
+    "backdrop-filter", false => backdrop_filter;
+    "mix-blend-mode", false => mix_blend_mode;
+
+    "transition-property", false => transition.property;
+    "transition-duration", false => transition.duration;
+    "transition-timing-function", false => transition.timing_function;
+    "transition-delay", false => transition.delay;
+    "animation-name", false => animation.name;
+    "animation-duration", false => animation.duration;
+    "animation-timing-function", false => animation.timing_function;
+    "animation-delay", false => animation.delay;
+    "animation-iteration-count", false => animation.iteration_count;
+    "animation-direction", false => animation.direction;
+    "animation-fill-mode", false => animation.fill_mode;
+    "animation-play-state", false => animation.play_state;
+}
+
+pub fn property(name: &str) -> Option<&'static Property> {
+    PROPERTIES.iter().find(|property| property.name == name)
+}
+
+/// The properties a declaration of `name` sets: those of a shorthand, or
+/// the property itself
+pub fn expand(name: &str) -> Vec<&'static Property> {
+    match shorthand::shorthand(name) {
+        Some(shorthand) => shorthand.longhands.iter().filter_map(|longhand| property(longhand)).collect(),
+        None => property(name).into_iter().collect(),
+    }
+}
+
+pub fn is_inherited(name: &str) -> bool {
+    property(name).is_some_and(|property| property.inherited)
+}
+
+/// Gives `style` the parent's value of every inherited property
+pub fn inherit_all(style: &mut ComputedStyle, parent: &ComputedStyle) {
+    for property in PROPERTIES.iter().filter(|property| property.inherited) {
+        property.copy(style, parent);
+    }
+}
+
+/// Whether two styles agree on every inherited property, so that children
+/// inheriting from either get the same styles
+pub fn inherited_equal(style: &ComputedStyle, other: &ComputedStyle) -> bool {
+    PROPERTIES.iter().filter(|property| property.inherited).all(|property| property.same(style, other))
+        && style.custom_properties == other.custom_properties
+}
+
+/// The keywords every property accepts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CssWideKeyword {
+    Inherit,
+    Initial,
+    /// `inherit` for inherited properties, `initial` for the others
+    Unset,
+    /// The value the user agent's styles alone would give
+    Revert,
+}
+
+impl CssWideKeyword {
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let keyword = value.as_keyword()?.to_ascii_lowercase();
+        match keyword.as_str() {
+            "inherit" => Some(CssWideKeyword::Inherit),
+            "initial" => Some(CssWideKeyword::Initial),
+            "unset" => Some(CssWideKeyword::Unset),
+            "revert" => Some(CssWideKeyword::Revert),
+            _ => None,
+        }
+    }
+}
+
+/// The styles the CSS-wide keywords take values from
+#[derive(Clone, Copy, Default)]
+pub struct Defaulting<'a> {
+    /// The parent's style, for `inherit`; `None` at the root, where it
+    /// means `initial`
+    pub parent: Option<&'a ComputedStyle>,
+    /// The style from the user agent's defaults alone, for `revert`; when
+    /// `None`, `revert` acts as `unset`
+    pub user_agent: Option<&'a ComputedStyle>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::shorthand::SHORTHANDS;
+
+    #[test]
+    fn test_property_table() {
+        assert!(is_inherited("color"));
+        assert!(is_inherited("font-weight"));
+        assert!(is_inherited("white-space"));
+        assert!(!is_inherited("margin-top"));
+        assert!(!is_inherited("margin"));
+
+        let names = |name| expand(name).iter().map(|property| property.name).collect::<Vec<_>>();
+        assert_eq!(names("list-style"), vec!["list-style-type", "list-style-position"]);
+        assert_eq!(names("opacity"), vec!["opacity"]);
+        assert!(names("no-such-property").is_empty());
+
+        // Every shorthand expands to known properties
+        for shorthand in SHORTHANDS {
+            assert_eq!(expand(shorthand.name).len(), shorthand.longhands.len(), "{}", shorthand.name);
+        }
+    }
+
+    #[test]
+    fn test_copy_and_inherit() {
+        let parent = ComputedStyle {
+            font_size: 20.0,
+            margin_top: 5.0,
+            ..ComputedStyle::default()
+        };
+        let mut style = ComputedStyle::default();
+        inherit_all(&mut style, &parent);
+        assert_eq!(style.font_size, 20.0);
+        assert_eq!(style.margin_top, 0.0);
+
+        for property in expand("margin") {
+            property.copy(&mut style, &parent);
+        }
+        assert_eq!(style.margin_top, 5.0);
+    }
+}
@@ -1,4 +1,6 @@
-use crate::dom::{Document, NodeId};
+use crate::dom::node::ElementData;
+use crate::dom::{Document, ElementState, NodeId};
+use std::ops::Add;
 
 /// Combinators between compound selectors
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -7,15 +9,78 @@ pub enum Combinator {
     Descendant,
     /// `>`: `div > p` - direct child
     Child,
+    /// `+`: `h1 + p` - immediately following sibling
+    NextSibling,
+    /// `~`: `h1 ~ p` - any following sibling
+    SubsequentSibling,
 }
 
-/// Structural pseudo-classes
+/// Structural, logical, form-state and user-action pseudo-classes
 #[derive(Debug, Clone, PartialEq)]
 pub enum PseudoClass {
     FirstChild,
     LastChild,
     NthChild(i32, i32), // An+B formula: (a, b)
+    NthLastChild(i32, i32),
     OnlyChild,
+    FirstOfType,
+    LastOfType,
+    NthOfType(i32, i32),
+    NthLastOfType(i32, i32),
+    OnlyOfType,
+    Empty,
+    Root,
+    Checked,
+    Disabled,
+    Enabled,
+    Hover,
+    Active,
+    Focus,
+    FocusVisible,
+    FocusWithin,
+    /// An unvisited link
+    Link,
+    /// A link, visited or not
+    AnyLink,
+    Visited,
+    /// `:not(a, b)` - matches none of the arguments
+    Not(Vec<ComplexSelector>),
+    /// `:is(a, b)` - matches any argument
+    Is(Vec<ComplexSelector>),
+    /// `:where(a, b)` - like `:is()`, without specificity
+    Where(Vec<ComplexSelector>),
+    /// `:has(> a, b)` - some element relative to this one matches
+    Has(Vec<RelativeSelector>),
+}
+
+/// Generated boxes a selector can style instead of the element itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PseudoElement {
+    /// `::before` - content inserted as the element's first child
+    Before,
+    /// `::after` - content inserted as the element's last child
+    After,
+    /// `::marker` - the bullet or number of a list item
+    Marker,
+}
+
+impl PseudoElement {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "before" => Some(PseudoElement::Before),
+            "after" => Some(PseudoElement::After),
+            "marker" => Some(PseudoElement::Marker),
+            _ => None,
+        }
+    }
+}
+
+/// An argument of `:has()`: a selector anchored to the element being
+/// matched by a leading combinator (descendant when omitted)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelativeSelector {
+    pub combinator: Combinator,
+    pub selector: ComplexSelector,
 }
 
 /// Attribute selector types
@@ -96,20 +161,7 @@ impl CompoundSelector {
                         })
                     }
                 },
-                SimpleSelector::PseudoClass(pseudo) => match pseudo {
-                    PseudoClass::FirstChild => document.element_index(node_id) == Some(1),
-                    PseudoClass::LastChild => document.is_last_element_child(node_id),
-                    PseudoClass::OnlyChild => {
-                        document.element_index(node_id) == Some(1) && document.is_last_element_child(node_id)
-                    }
-                    PseudoClass::NthChild(a, b) => {
-                        if let Some(index) = document.element_index(node_id) {
-                            nth_child_matches(*a, *b, index as i32)
-                        } else {
-                            false
-                        }
-                    }
-                },
+                SimpleSelector::PseudoClass(pseudo) => pseudo.matches(document, node_id, element),
             };
             if !matches {
                 return false;
@@ -123,10 +175,20 @@ impl CompoundSelector {
         let mut ids = 0;
         let mut classes = 0;
         let mut tags = 0;
+        let mut nested = Specificity::new();
 
         for selector in &self.simple_selectors {
             match selector {
                 SimpleSelector::Id(_) => ids += 1,
+                // :is(), :not() and :has() count as their most specific
+                // argument, :where() counts as nothing
+                SimpleSelector::PseudoClass(PseudoClass::Not(args) | PseudoClass::Is(args)) => {
+                    nested = nested + max_specificity(args.iter());
+                }
+                SimpleSelector::PseudoClass(PseudoClass::Has(args)) => {
+                    nested = nested + max_specificity(args.iter().map(|arg| &arg.selector));
+                }
+                SimpleSelector::PseudoClass(PseudoClass::Where(_)) => {}
                 SimpleSelector::Class(_) | SimpleSelector::Attribute(_) | SimpleSelector::PseudoClass(_) => {
                     classes += 1
                 }
@@ -135,10 +197,120 @@ impl CompoundSelector {
             }
         }
 
-        Specificity { ids, classes, tags }
+        Specificity { ids, classes, tags } + nested
     }
 }
 
+fn max_specificity<'a>(selectors: impl Iterator<Item = &'a ComplexSelector>) -> Specificity {
+    selectors.map(ComplexSelector::specificity).max().unwrap_or_default()
+}
+
+impl PseudoClass {
+    fn matches(&self, document: &Document, node_id: NodeId, element: &ElementData) -> bool {
+        match self {
+            PseudoClass::FirstChild => document.element_index(node_id) == Some(1),
+            PseudoClass::LastChild => document.is_last_element_child(node_id),
+            PseudoClass::OnlyChild => {
+                document.element_index(node_id) == Some(1) && document.is_last_element_child(node_id)
+            }
+            PseudoClass::NthChild(a, b) => {
+                if let Some(index) = document.element_index(node_id) {
+                    nth_child_matches(*a, *b, index as i32)
+                } else {
+                    false
+                }
+            }
+            PseudoClass::NthLastChild(a, b) => sibling_position(document, node_id, None)
+                .is_some_and(|(_, from_end)| nth_child_matches(*a, *b, from_end)),
+            PseudoClass::FirstOfType => {
+                sibling_position(document, node_id, Some(&element.tag_name)).is_some_and(|(index, _)| index == 1)
+            }
+            PseudoClass::LastOfType => sibling_position(document, node_id, Some(&element.tag_name))
+                .is_some_and(|(_, from_end)| from_end == 1),
+            PseudoClass::OnlyOfType => sibling_position(document, node_id, Some(&element.tag_name))
+                .is_some_and(|position| position == (1, 1)),
+            PseudoClass::NthOfType(a, b) => sibling_position(document, node_id, Some(&element.tag_name))
+                .is_some_and(|(index, _)| nth_child_matches(*a, *b, index)),
+            PseudoClass::NthLastOfType(a, b) => sibling_position(document, node_id, Some(&element.tag_name))
+                .is_some_and(|(_, from_end)| nth_child_matches(*a, *b, from_end)),
+            PseudoClass::Empty => document.children(node_id).iter().all(|&child| {
+                document.get_node(child).is_some_and(|node| match node.as_text() {
+                    Some(text) => text.is_empty(),
+                    None => !node.is_element(),
+                })
+            }),
+            PseudoClass::Root => !document
+                .parent(node_id)
+                .and_then(|parent| document.get_node(parent))
+                .is_some_and(|parent| parent.is_element()),
+            PseudoClass::Checked => match element.tag_name.to_ascii_lowercase().as_str() {
+                "input" => {
+                    let kind = element.get_attribute("type").unwrap_or("").to_ascii_lowercase();
+                    (kind == "checkbox" || kind == "radio") && element.get_attribute("checked").is_some()
+                }
+                "option" => element.get_attribute("selected").is_some(),
+                _ => false,
+            },
+            PseudoClass::Disabled => is_form_control(element) && is_disabled(document, node_id, element),
+            PseudoClass::Enabled => is_form_control(element) && !is_disabled(document, node_id, element),
+            PseudoClass::Hover => document.element_state(node_id).contains(ElementState::HOVER),
+            PseudoClass::Active => document.element_state(node_id).contains(ElementState::ACTIVE),
+            PseudoClass::Focus => document.element_state(node_id).contains(ElementState::FOCUS),
+            PseudoClass::FocusVisible => document.element_state(node_id).contains(ElementState::FOCUS_VISIBLE),
+            PseudoClass::FocusWithin => document.element_state(node_id).contains(ElementState::FOCUS_WITHIN),
+            PseudoClass::Link => is_link(element) && !document.element_state(node_id).contains(ElementState::VISITED),
+            PseudoClass::AnyLink => is_link(element),
+            PseudoClass::Visited => is_link(element) && document.element_state(node_id).contains(ElementState::VISITED),
+            PseudoClass::Not(args) => !args.iter().any(|arg| arg.matches(document, node_id)),
+            PseudoClass::Is(args) | PseudoClass::Where(args) => args.iter().any(|arg| arg.matches(document, node_id)),
+            PseudoClass::Has(args) => args.iter().any(|arg| arg.matches_relative_to(document, node_id)),
+        }
+    }
+}
+
+/// 1-based position of an element among its element siblings, counted from
+/// the start and from the end, optionally only counting siblings of one type
+fn sibling_position(document: &Document, node_id: NodeId, tag_name: Option<&str>) -> Option<(i32, i32)> {
+    let parent = document.parent(node_id)?;
+    let siblings: Vec<NodeId> = document
+        .element_children(parent)
+        .into_iter()
+        .filter(|&sibling| match tag_name {
+            Some(tag) => document
+                .get_node(sibling)
+                .and_then(|node| node.tag_name())
+                .is_some_and(|name| name.eq_ignore_ascii_case(tag)),
+            None => true,
+        })
+        .collect();
+    let index = siblings.iter().position(|&sibling| sibling == node_id)? as i32;
+    Some((index + 1, siblings.len() as i32 - index))
+}
+
+fn is_link(element: &ElementData) -> bool {
+    matches!(element.tag_name.to_ascii_lowercase().as_str(), "a" | "area") && element.get_attribute("href").is_some()
+}
+
+fn is_form_control(element: &ElementData) -> bool {
+    matches!(
+        element.tag_name.to_ascii_lowercase().as_str(),
+        "button" | "input" | "select" | "textarea" | "optgroup" | "option" | "fieldset"
+    )
+}
+
+/// Disabled by its own attribute, or by a disabled `<fieldset>` around it
+fn is_disabled(document: &Document, node_id: NodeId, element: &ElementData) -> bool {
+    element.get_attribute("disabled").is_some()
+        || document.ancestors(node_id).into_iter().any(|ancestor| {
+            document
+                .get_node(ancestor)
+                .and_then(|node| node.as_element())
+                .is_some_and(|ancestor| {
+                    ancestor.tag_name.eq_ignore_ascii_case("fieldset") && ancestor.get_attribute("disabled").is_some()
+                })
+        })
+}
+
 impl Default for CompoundSelector {
     fn default() -> Self {
         Self::new()
@@ -166,20 +338,32 @@ pub struct ComplexSelector {
     /// The first element is the subject (rightmost in CSS), no combinator
     /// Subsequent elements have the combinator that connects them to the previous
     pub parts: Vec<(CompoundSelector, Option<Combinator>)>,
+    /// A trailing `::before`, `::after` or `::marker`: the selector then
+    /// styles that pseudo-element of the matched element
+    pub pseudo_element: Option<PseudoElement>,
 }
 
 impl ComplexSelector {
     pub fn new(parts: Vec<(CompoundSelector, Option<Combinator>)>) -> Self {
-        Self { parts }
+        Self {
+            parts,
+            pseudo_element: None,
+        }
     }
 
     pub fn from_compound(compound: CompoundSelector) -> Self {
-        Self {
-            parts: vec![(compound, None)],
-        }
+        Self::new(vec![(compound, None)])
     }
 
+    /// Whether this selector matches the element itself. Selectors for a
+    /// pseudo-element never do.
     pub fn matches(&self, document: &Document, node_id: NodeId) -> bool {
+        self.pseudo_element.is_none() && self.matches_originating_element(document, node_id)
+    }
+
+    /// Matches everything but the pseudo-element, i.e. the element that a
+    /// pseudo-element selector's pseudo-element belongs to
+    pub fn matches_originating_element(&self, document: &Document, node_id: NodeId) -> bool {
         if self.parts.is_empty() {
             return false;
         }
@@ -195,41 +379,32 @@ impl ComplexSelector {
         }
 
         // Check ancestor chain for remaining parts
-        self.match_ancestors(document, node_id, 1)
+        self.match_ancestors(document, node_id, 1, None)
     }
 
-    fn match_ancestors(&self, document: &Document, node_id: NodeId, part_index: usize) -> bool {
+    /// Matches the remaining parts, starting from the element `node_id`
+    /// matched by the previous part. With an `anchor`, the element matched by
+    /// the last part must also be related to it by the given combinator.
+    fn match_ancestors(
+        &self,
+        document: &Document,
+        node_id: NodeId,
+        part_index: usize,
+        anchor: Option<(Combinator, NodeId)>,
+    ) -> bool {
         if part_index >= self.parts.len() {
-            return true;
+            return anchor.is_none_or(|(combinator, anchor_id)| {
+                related_elements(document, node_id, combinator).any(|id| id == anchor_id)
+            });
         }
 
         let (compound, combinator) = &self.parts[part_index];
         let combinator = combinator.unwrap_or(Combinator::Descendant);
 
-        match combinator {
-            Combinator::Child => {
-                // Must match direct parent
-                if let Some(parent_id) = document.parent(node_id) {
-                    if compound.matches(document, parent_id) {
-                        return self.match_ancestors(document, parent_id, part_index + 1);
-                    }
-                }
-                false
-            }
-            Combinator::Descendant => {
-                // Match any ancestor
-                let mut current = document.parent(node_id);
-                while let Some(ancestor_id) = current {
-                    if compound.matches(document, ancestor_id) {
-                        if self.match_ancestors(document, ancestor_id, part_index + 1) {
-                            return true;
-                        }
-                    }
-                    current = document.parent(ancestor_id);
-                }
-                false
-            }
-        }
+        related_elements(document, node_id, combinator).any(|related_id| {
+            compound.matches(document, related_id)
+                && self.match_ancestors(document, related_id, part_index + 1, anchor)
+        })
     }
 
     pub fn specificity(&self) -> Specificity {
@@ -240,15 +415,163 @@ impl ComplexSelector {
             total.classes += spec.classes;
             total.tags += spec.tags;
         }
+        if self.pseudo_element.is_some() {
+            total.tags += 1;
+        }
         total
     }
 
+    /// Whether this selector has a combinator looking at siblings, anywhere
+    /// including inside functional pseudo-classes
+    pub fn has_sibling_combinator(&self) -> bool {
+        self.parts.iter().any(|(compound, combinator)| {
+            matches!(combinator, Some(Combinator::NextSibling | Combinator::SubsequentSibling))
+                || compound.simple_selectors.iter().any(|simple| match simple {
+                    SimpleSelector::PseudoClass(
+                        PseudoClass::Not(args) | PseudoClass::Is(args) | PseudoClass::Where(args),
+                    ) => args.iter().any(ComplexSelector::has_sibling_combinator),
+                    SimpleSelector::PseudoClass(PseudoClass::Has(args)) => args.iter().any(|arg| {
+                        matches!(arg.combinator, Combinator::NextSibling | Combinator::SubsequentSibling)
+                            || arg.selector.has_sibling_combinator()
+                    }),
+                    _ => false,
+                })
+        })
+    }
+
+    /// Whether this selector uses `:has()`, anywhere
+    pub fn has_relative_selector(&self) -> bool {
+        self.parts.iter().any(|(compound, _)| {
+            compound.simple_selectors.iter().any(|simple| match simple {
+                SimpleSelector::PseudoClass(PseudoClass::Has(_)) => true,
+                SimpleSelector::PseudoClass(
+                    PseudoClass::Not(args) | PseudoClass::Is(args) | PseudoClass::Where(args),
+                ) => args.iter().any(ComplexSelector::has_relative_selector),
+                _ => false,
+            })
+        })
+    }
+
+    /// The element state flags this selector looks at, anywhere
+    pub fn state_dependencies(&self) -> ElementState {
+        let mut flags = ElementState::EMPTY;
+        for (compound, _) in &self.parts {
+            for simple in &compound.simple_selectors {
+                let SimpleSelector::PseudoClass(pseudo) = simple else {
+                    continue;
+                };
+                flags = flags
+                    | match pseudo {
+                        PseudoClass::Hover => ElementState::HOVER,
+                        PseudoClass::Active => ElementState::ACTIVE,
+                        PseudoClass::Focus => ElementState::FOCUS,
+                        PseudoClass::FocusVisible => ElementState::FOCUS_VISIBLE,
+                        PseudoClass::FocusWithin => ElementState::FOCUS_WITHIN,
+                        PseudoClass::Link | PseudoClass::Visited => ElementState::VISITED,
+                        PseudoClass::Not(args) | PseudoClass::Is(args) | PseudoClass::Where(args) => args
+                            .iter()
+                            .fold(ElementState::EMPTY, |flags, arg| flags | arg.state_dependencies()),
+                        PseudoClass::Has(args) => args
+                            .iter()
+                            .fold(ElementState::EMPTY, |flags, arg| flags | arg.selector.state_dependencies()),
+                        _ => ElementState::EMPTY,
+                    };
+            }
+        }
+        flags
+    }
+
     /// Get the rightmost (subject) compound selector for indexing
     pub fn subject(&self) -> Option<&CompoundSelector> {
         self.parts.first().map(|(c, _)| c)
     }
 }
 
+/// The elements reached from `node_id` by following `combinator` leftwards:
+/// its parent, ancestors, previous sibling or previous siblings
+fn related_elements(document: &Document, node_id: NodeId, combinator: Combinator) -> impl Iterator<Item = NodeId> + '_ {
+    let (first, repeat) = match combinator {
+        Combinator::Child => (document.parent(node_id), false),
+        Combinator::Descendant => (document.parent(node_id), true),
+        Combinator::NextSibling => (document.previous_element_sibling(node_id), false),
+        Combinator::SubsequentSibling => (document.previous_element_sibling(node_id), true),
+    };
+    std::iter::successors(first, move |&current| {
+        if !repeat {
+            return None;
+        }
+        match combinator {
+            Combinator::Descendant => document.parent(current),
+            _ => document.previous_element_sibling(current),
+        }
+    })
+}
+
+/// The elements reached from `node_id` by following `combinator`
+/// rightwards, as `:has()` does: its children, its descendants in document
+/// order, its next sibling or its following siblings. Elements are visited
+/// lazily, so matching stops at the first that matches.
+fn reached_elements(
+    document: &Document,
+    node_id: NodeId,
+    combinator: Combinator,
+) -> Box<dyn Iterator<Item = NodeId> + '_> {
+    let is_element = move |id: &NodeId| document.get_node(*id).is_some_and(|node| node.is_element());
+    match combinator {
+        Combinator::Child => Box::new(document.children(node_id).iter().copied().filter(is_element)),
+        Combinator::Descendant => {
+            let mut stack = vec![document.children(node_id).iter()];
+            Box::new(std::iter::from_fn(move || loop {
+                let Some(&id) = stack.last_mut()?.next() else {
+                    stack.pop();
+                    continue;
+                };
+                stack.push(document.children(id).iter());
+                if is_element(&id) {
+                    return Some(id);
+                }
+            }))
+        }
+        Combinator::NextSibling | Combinator::SubsequentSibling => {
+            let siblings = document.parent(node_id).map_or(&[][..], |parent| document.children(parent));
+            let following = siblings.iter().skip_while(move |&&id| id != node_id).skip(1).copied().filter(is_element);
+            let count = if combinator == Combinator::NextSibling { 1 } else { usize::MAX };
+            Box::new(following.take(count))
+        }
+    }
+}
+
+impl RelativeSelector {
+    /// Whether some element related to `anchor` by the leading combinator
+    /// matches the selector. Matching runs left to right from the anchor,
+    /// so each combinator only walks the elements it can reach.
+    fn matches_relative_to(&self, document: &Document, anchor: NodeId) -> bool {
+        match self.selector.parts.len() {
+            0 => false,
+            len => self.match_rightwards(document, anchor, self.combinator, len - 1),
+        }
+    }
+
+    /// Whether an element reached from `node_id` by `combinator` matches
+    /// the part at `part_index`, with the parts to its right matching on
+    fn match_rightwards(
+        &self,
+        document: &Document,
+        node_id: NodeId,
+        combinator: Combinator,
+        part_index: usize,
+    ) -> bool {
+        let (compound, _) = &self.selector.parts[part_index];
+        reached_elements(document, node_id, combinator).any(|id| {
+            compound.matches(document, id)
+                && (part_index == 0 || {
+                    let next = self.selector.parts[part_index].1.unwrap_or(Combinator::Descendant);
+                    self.match_rightwards(document, id, next, part_index - 1)
+                })
+        })
+    }
+}
+
 /// Legacy Selector type for backward compatibility
 #[derive(Debug, Clone, PartialEq)]
 pub struct Selector {
@@ -327,6 +650,18 @@ impl Specificity {
     }
 }
 
+impl Add for Specificity {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            ids: self.ids + other.ids,
+            classes: self.classes + other.classes,
+            tags: self.tags + other.tags,
+        }
+    }
+}
+
 impl Default for Specificity {
     fn default() -> Self {
         Self::new()
@@ -335,6 +670,16 @@ impl Default for Specificity {
 
 /// Parse a CSS selector string
 pub fn parse_selector(input: &str) -> Option<Selector> {
+    parse_complex_selector(input).map(|complex| Selector { complex })
+}
+
+/// Parse a comma-separated selector list, skipping selectors that are
+/// invalid or use unsupported features
+pub fn parse_selector_list(input: &str) -> Vec<Selector> {
+    split_selector_list(input).into_iter().filter_map(parse_selector).collect()
+}
+
+fn parse_complex_selector(input: &str) -> Option<ComplexSelector> {
     let input = input.trim();
     if input.is_empty() {
         return None;
@@ -343,21 +688,26 @@ pub fn parse_selector(input: &str) -> Option<Selector> {
     let mut parts: Vec<(CompoundSelector, Option<Combinator>)> = Vec::new();
     let mut current_compound = CompoundSelector::new();
     let mut pending_combinator: Option<Combinator> = None;
+    let mut pseudo_element = None;
 
     let mut chars = input.chars().peekable();
     let mut current = String::new();
     let mut selector_type = SelectorType::Tag;
 
     while let Some(c) = chars.next() {
+        // Nothing may follow a pseudo-element
+        if pseudo_element.is_some() {
+            return None;
+        }
         match c {
             ' ' => {
                 flush_current(&mut current, &mut selector_type, &mut current_compound);
-                // Check if next non-space is '>'
+                // Check if next non-space is an explicit combinator
                 while chars.peek() == Some(&' ') {
                     chars.next();
                 }
-                if chars.peek() == Some(&'>') {
-                    // Will be handled by '>' case
+                if matches!(chars.peek(), Some('>' | '+' | '~')) {
+                    // Will be handled by the combinator case
                     continue;
                 }
                 // It's a descendant combinator
@@ -367,7 +717,7 @@ pub fn parse_selector(input: &str) -> Option<Selector> {
                     pending_combinator = Some(Combinator::Descendant);
                 }
             }
-            '>' => {
+            '>' | '+' | '~' => {
                 flush_current(&mut current, &mut selector_type, &mut current_compound);
                 // Skip trailing spaces
                 while chars.peek() == Some(&' ') {
@@ -376,7 +726,11 @@ pub fn parse_selector(input: &str) -> Option<Selector> {
                 if !current_compound.simple_selectors.is_empty() {
                     parts.push((current_compound, pending_combinator));
                     current_compound = CompoundSelector::new();
-                    pending_combinator = Some(Combinator::Child);
+                    pending_combinator = Some(match c {
+                        '>' => Combinator::Child,
+                        '+' => Combinator::NextSibling,
+                        _ => Combinator::SubsequentSibling,
+                    });
                 }
             }
             '.' => {
@@ -404,16 +758,43 @@ pub fn parse_selector(input: &str) -> Option<Selector> {
             }
             ':' => {
                 flush_current(&mut current, &mut selector_type, &mut current_compound);
-                // Parse pseudo-class
-                let mut pseudo_str = String::new();
+                let double_colon = chars.peek() == Some(&':');
+                if double_colon {
+                    chars.next();
+                }
+                let mut name = String::new();
                 while let Some(&c) = chars.peek() {
-                    if c == ' ' || c == '>' || c == '.' || c == '#' || c == '[' || c == ':' {
+                    if !(c.is_alphanumeric() || c == '-' || c == '_') {
                         break;
                     }
-                    pseudo_str.push(chars.next().unwrap());
+                    name.push(c);
+                    chars.next();
+                }
+                let argument = if chars.peek() == Some(&'(') {
+                    chars.next();
+                    Some(take_parenthesized(&mut chars)?)
+                } else {
+                    None
+                };
+                // `:before` and `:after` are the legacy single-colon forms
+                let legacy = matches!(name.to_ascii_lowercase().as_str(), "before" | "after");
+                if double_colon || legacy {
+                    if argument.is_some() {
+                        return None;
+                    }
+                    // Unsupported pseudo-elements invalidate the selector
+                    pseudo_element = Some(PseudoElement::from_name(&name)?);
+                    continue;
                 }
-                if let Some(pseudo) = parse_pseudo_class(&pseudo_str) {
-                    current_compound.simple_selectors.push(SimpleSelector::PseudoClass(pseudo));
+                // An unknown pseudo-class makes the whole selector invalid,
+                // rather than matching more than intended
+                let pseudo = parse_pseudo_class(&name, argument.as_deref())?;
+                current_compound.simple_selectors.push(SimpleSelector::PseudoClass(pseudo));
+            }
+            '\\' => {
+                // Escaped character, e.g. `.sm\:flex`
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
                 }
             }
             '*' if current.is_empty() => {
@@ -427,6 +808,10 @@ pub fn parse_selector(input: &str) -> Option<Selector> {
 
     flush_current(&mut current, &mut selector_type, &mut current_compound);
 
+    // `::before` alone is `*::before`
+    if current_compound.simple_selectors.is_empty() && pseudo_element.is_some() {
+        current_compound.simple_selectors.push(SimpleSelector::Universal);
+    }
     if !current_compound.simple_selectors.is_empty() {
         parts.push((current_compound, pending_combinator));
     }
@@ -451,8 +836,71 @@ pub fn parse_selector(input: &str) -> Option<Selector> {
         }
     }
 
-    Some(Selector {
-        complex: ComplexSelector::new(parts),
+    Some(ComplexSelector {
+        parts,
+        pseudo_element,
+    })
+}
+
+/// A selector inside a functional pseudo-class, where pseudo-elements are
+/// not allowed
+fn parse_nested_selector(input: &str) -> Option<ComplexSelector> {
+    parse_complex_selector(input).filter(|selector| selector.pseudo_element.is_none())
+}
+
+/// Splits a selector list at commas outside of brackets and strings
+pub(super) fn split_selector_list(input: &str) -> Vec<&str> {
+    let mut selectors = Vec::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in input.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                selectors.push(&input[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    selectors.push(&input[start..]);
+    selectors
+}
+
+/// Reads up to the `)` closing an already consumed `(`, returning what is
+/// between them, or `None` if it is never closed
+fn take_parenthesized(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<String> {
+    let mut content = String::new();
+    let mut depth = 0;
+    for c in chars.by_ref() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(content),
+            ')' => depth -= 1,
+            _ => {}
+        }
+        content.push(c);
+    }
+    None
+}
+
+/// A `:has()` argument, such as `> img` or `+ p`
+fn parse_relative_selector(input: &str) -> Option<RelativeSelector> {
+    let input = input.trim();
+    let (combinator, rest) = match input.chars().next()? {
+        '>' => (Combinator::Child, &input[1..]),
+        '+' => (Combinator::NextSibling, &input[1..]),
+        '~' => (Combinator::SubsequentSibling, &input[1..]),
+        _ => (Combinator::Descendant, input),
+    };
+    Some(RelativeSelector {
+        combinator,
+        selector: parse_nested_selector(rest)?,
     })
 }
 
@@ -530,22 +978,58 @@ fn extract_attr_value(input: &str) -> String {
     }
 }
 
-fn parse_pseudo_class(input: &str) -> Option<PseudoClass> {
-    if input == "first-child" {
-        return Some(PseudoClass::FirstChild);
-    }
-    if input == "last-child" {
-        return Some(PseudoClass::LastChild);
-    }
-    if input == "only-child" {
-        return Some(PseudoClass::OnlyChild);
-    }
-    if input.starts_with("nth-child(") && input.ends_with(')') {
-        let formula = &input[10..input.len() - 1];
-        return parse_nth_formula(formula).map(|(a, b)| PseudoClass::NthChild(a, b));
-    }
-
-    None
+fn parse_pseudo_class(name: &str, argument: Option<&str>) -> Option<PseudoClass> {
+    let pseudo = match (name.to_ascii_lowercase().as_str(), argument) {
+        ("first-child", None) => PseudoClass::FirstChild,
+        ("last-child", None) => PseudoClass::LastChild,
+        ("only-child", None) => PseudoClass::OnlyChild,
+        ("first-of-type", None) => PseudoClass::FirstOfType,
+        ("last-of-type", None) => PseudoClass::LastOfType,
+        ("only-of-type", None) => PseudoClass::OnlyOfType,
+        ("empty", None) => PseudoClass::Empty,
+        ("root", None) => PseudoClass::Root,
+        ("checked", None) => PseudoClass::Checked,
+        ("disabled", None) => PseudoClass::Disabled,
+        ("enabled", None) => PseudoClass::Enabled,
+        ("hover", None) => PseudoClass::Hover,
+        ("active", None) => PseudoClass::Active,
+        ("focus", None) => PseudoClass::Focus,
+        ("focus-visible", None) => PseudoClass::FocusVisible,
+        ("focus-within", None) => PseudoClass::FocusWithin,
+        ("link", None) => PseudoClass::Link,
+        ("any-link", None) => PseudoClass::AnyLink,
+        ("visited", None) => PseudoClass::Visited,
+        ("nth-child", Some(formula)) => parse_nth_formula(formula).map(|(a, b)| PseudoClass::NthChild(a, b))?,
+        ("nth-last-child", Some(formula)) => {
+            parse_nth_formula(formula).map(|(a, b)| PseudoClass::NthLastChild(a, b))?
+        }
+        ("nth-of-type", Some(formula)) => parse_nth_formula(formula).map(|(a, b)| PseudoClass::NthOfType(a, b))?,
+        ("nth-last-of-type", Some(formula)) => {
+            parse_nth_formula(formula).map(|(a, b)| PseudoClass::NthLastOfType(a, b))?
+        }
+        // :not() and :has() are invalid if any argument is; :is() and
+        // :where() take forgiving lists that skip invalid arguments
+        ("not", Some(list)) => PseudoClass::Not(
+            split_selector_list(list)
+                .into_iter()
+                .map(parse_nested_selector)
+                .collect::<Option<_>>()?,
+        ),
+        ("has", Some(list)) => PseudoClass::Has(
+            split_selector_list(list)
+                .into_iter()
+                .map(parse_relative_selector)
+                .collect::<Option<_>>()?,
+        ),
+        ("is" | "matches", Some(list)) => {
+            PseudoClass::Is(split_selector_list(list).into_iter().filter_map(parse_nested_selector).collect())
+        }
+        ("where", Some(list)) => {
+            PseudoClass::Where(split_selector_list(list).into_iter().filter_map(parse_nested_selector).collect())
+        }
+        _ => return None,
+    };
+    Some(pseudo)
 }
 
 fn parse_nth_formula(input: &str) -> Option<(i32, i32)> {
@@ -593,6 +1077,7 @@ fn parse_nth_formula(input: &str) -> Option<(i32, i32)> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dom::parse_html;
 
     #[test]
     fn test_parse_tag_selector() {
@@ -758,6 +1243,129 @@ mod tests {
         assert_eq!(parse_nth_formula("-n+3"), Some((-1, 3)));
     }
 
+    #[test]
+    fn test_parse_sibling_combinators() {
+        let sel = parse_selector("h1 + p ~ ul").unwrap();
+        assert_eq!(sel.complex.parts.len(), 3);
+        assert_eq!(sel.complex.parts[1].1, Some(Combinator::SubsequentSibling));
+        assert_eq!(sel.complex.parts[2].1, Some(Combinator::NextSibling));
+    }
+
+    #[test]
+    fn test_parse_selector_list_keeps_functional_arguments() {
+        let selectors = parse_selector_list("a:is(.x, .y), p:not([data-a=\"1,2\"]), b:unknown, i::selection");
+        assert_eq!(selectors.len(), 2);
+        assert!(matches!(
+            &selectors[0].complex.parts[0].0.simple_selectors[1],
+            SimpleSelector::PseudoClass(PseudoClass::Is(args)) if args.len() == 2
+        ));
+    }
+
+    #[test]
+    fn test_parse_pseudo_elements() {
+        let sel = parse_selector("ul > li.item::marker").unwrap();
+        assert_eq!(sel.complex.pseudo_element, Some(PseudoElement::Marker));
+        assert_eq!(sel.specificity(), Specificity { ids: 0, classes: 1, tags: 3 });
+
+        let legacy = parse_selector("p:after").unwrap();
+        assert_eq!(legacy.complex.pseudo_element, Some(PseudoElement::After));
+        let bare = parse_selector("::before").unwrap();
+        assert_eq!(bare.complex.parts[0].0.simple_selectors, vec![SimpleSelector::Universal]);
+
+        // Nothing may follow a pseudo-element, and none may be nested
+        assert!(parse_selector("p::before span").is_none());
+        assert!(parse_selector("p::first-line").is_none());
+        assert!(parse_selector("p:not(::before)").is_none());
+    }
+
+    #[test]
+    fn test_pseudo_element_matches_only_originating_element() {
+        let doc = parse_html("<p class=\"note\">text</p>");
+        let p = doc.get_elements_by_tag_name("p")[0];
+        let sel = parse_selector("p.note::before").unwrap();
+        assert!(!sel.matches(&doc, p));
+        assert!(sel.complex.matches_originating_element(&doc, p));
+    }
+
+    #[test]
+    fn test_functional_pseudo_class_specificity() {
+        let spec = |input: &str| parse_selector(input).unwrap().specificity();
+        assert_eq!(spec(":is(#a, .b) p"), Specificity { ids: 1, classes: 0, tags: 1 });
+        assert_eq!(spec(":where(#a, .b) p"), Specificity { ids: 0, classes: 0, tags: 1 });
+        assert_eq!(spec("p:not(.x, span)"), Specificity { ids: 0, classes: 1, tags: 1 });
+        assert_eq!(spec("div:has(> img)"), Specificity { ids: 0, classes: 0, tags: 2 });
+        assert_eq!(spec("li:nth-of-type(2)"), Specificity { ids: 0, classes: 1, tags: 1 });
+    }
+
+    #[test]
+    fn test_match_siblings_and_functional_pseudo_classes() {
+        let doc = parse_html(
+            r#"<html><body>
+                <h1 id="title">T</h1><p id="first">A</p><span id="gap"></span><p id="second">B</p>
+                <div id="card"><img></div><div id="plain"><p>x</p></div>
+                <input id="box" type="checkbox" checked><fieldset disabled><button id="btn"></button></fieldset>
+            </body></html>"#,
+        );
+        let id = |name: &str| doc.get_element_by_id(name).unwrap();
+        let matches = |selector: &str, name: &str| parse_selector(selector).unwrap().matches(&doc, id(name));
+
+        assert!(matches("h1 + p", "first"));
+        assert!(!matches("h1 + p", "second"));
+        assert!(matches("h1 ~ p", "second"));
+        assert!(matches("p:nth-of-type(2)", "second"));
+        assert!(matches("p:last-of-type", "second"));
+        assert!(matches("p:first-of-type:not(:last-of-type)", "first"));
+        assert!(matches(":is(h2, p)#first", "first"));
+        assert!(!matches(":where(.missing)", "first"));
+        assert!(matches("div:has(> img)", "card"));
+        assert!(!matches("div:has(> img)", "plain"));
+        assert!(matches("h1:has(+ p)", "title"));
+        assert!(!matches("h1:has(+ span)", "title"));
+        assert!(matches("h1:has(+ p ~ p)", "title"));
+        assert!(matches("h1:has(~ div img)", "title"));
+        assert!(!matches("h1:has(~ div > p + img)", "title"));
+        assert!(matches("div:not(:has(p))", "card"));
+        assert!(matches(":root body span:empty", "gap"));
+        assert!(!matches("div:empty", "card"));
+        assert!(matches("input:checked", "box"));
+        assert!(matches("button:disabled", "btn"));
+        assert!(!matches("button:enabled", "btn"));
+    }
+
+    #[test]
+    fn test_match_element_state() {
+        let mut doc = parse_html(
+            r#"<html><body><nav id="nav"><a id="home" href="/">Home</a><a id="anchor">No href</a></nav></body></html>"#,
+        );
+        let home = doc.get_element_by_id("home").unwrap();
+        let nav = doc.get_element_by_id("nav").unwrap();
+        let matches = |doc: &Document, selector: &str, node: NodeId| parse_selector(selector).unwrap().matches(doc, node);
+
+        assert!(matches(&doc, "a:link", home));
+        assert!(!matches(&doc, ":any-link", doc.get_element_by_id("anchor").unwrap()));
+        assert!(!matches(&doc, "nav:hover", nav));
+
+        doc.set_element_state(nav, ElementState::HOVER | ElementState::FOCUS_WITHIN, true);
+        doc.set_element_state(home, ElementState::VISITED | ElementState::FOCUS, true);
+        assert!(matches(&doc, "nav:hover > a:visited:focus", home));
+        assert!(matches(&doc, "nav:focus-within", nav));
+        assert!(!matches(&doc, "a:link", home));
+        assert!(!matches(&doc, "a:focus-visible", home));
+
+        let selector = parse_selector("nav:hover :is(a:focus, a:active)").unwrap();
+        assert_eq!(
+            selector.complex.state_dependencies(),
+            ElementState::HOVER | ElementState::FOCUS | ElementState::ACTIVE
+        );
+    }
+
+    #[test]
+    fn test_unknown_pseudo_class_invalidates_selector() {
+        assert!(parse_selector("a:unknown-state").is_none());
+        assert!(parse_selector("p::first-line").is_none());
+        assert!(parse_selector("p:not(").is_none());
+    }
+
     #[test]
     fn test_specificity() {
         let sel = parse_selector("div.container#main").unwrap();
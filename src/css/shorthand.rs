@@ -0,0 +1,907 @@
+//! Shorthand properties. As a style sheet is parsed, each shorthand
+//! declaration is split into declarations of the longhands it sets, so
+//! the cascade and `ComputedStyle::apply_value` only see longhands.
+//! Longhands a shorthand leaves out are reset to their initial values.
+//!
+//! A shorthand using `var()` can only be split once the reference is
+//! substituted, which the cascade does with the same table.
+
+use super::animation::{self, AnimationDirection, FillMode, PlayState, TimingFunction};
+use super::background::{self, BackgroundAttachment, BackgroundBox};
+use super::computed::{sides, ComputedStyle, ListStyleType};
+use super::properties::CssWideKeyword;
+use super::stylesheet::{Declaration, LengthContext, Unit, Value};
+use crate::render::font::{FontStretch, FontStyle};
+use crate::render::painter::{Color, TextDecorationStyle};
+
+/// A shorthand property and the longhands it sets
+pub struct Shorthand {
+    pub name: &'static str,
+    pub longhands: &'static [&'static str],
+    /// A value for each longhand, in order; `None` if the value is invalid
+    split: fn(&Value) -> Option<Vec<Value>>,
+}
+
+impl Shorthand {
+    /// The longhands and their values for the shorthand's `value`; `None`
+    /// if it is invalid. A CSS-wide keyword goes to every longhand.
+    pub fn expand(&self, value: &Value) -> Option<Vec<(&'static str, Value)>> {
+        let values = match CssWideKeyword::from_value(value) {
+            Some(_) => vec![value.clone(); self.longhands.len()],
+            None => (self.split)(value)?,
+        };
+        debug_assert_eq!(values.len(), self.longhands.len(), "{}", self.name);
+        Some(self.longhands.iter().copied().zip(values).collect())
+    }
+}
+
+pub(super) const SHORTHANDS: &[Shorthand] = &[
+    Shorthand {
+        name: "margin",
+        longhands: &["margin-top", "margin-right", "margin-bottom", "margin-left"],
+        split: split_sides,
+    },
+    Shorthand {
+        name: "padding",
+        longhands: &["padding-top", "padding-right", "padding-bottom", "padding-left"],
+        split: split_sides,
+    },
+    Shorthand {
+        name: "inset",
+        longhands: &["top", "right", "bottom", "left"],
+        split: split_sides,
+    },
+    Shorthand {
+        name: "border-width",
+        longhands: &["border-top-width", "border-right-width", "border-bottom-width", "border-left-width"],
+        split: split_sides,
+    },
+    Shorthand {
+        name: "border-style",
+        longhands: &["border-top-style", "border-right-style", "border-bottom-style", "border-left-style"],
+        split: split_sides,
+    },
+    Shorthand {
+        name: "border-color",
+        longhands: &["border-top-color", "border-right-color", "border-bottom-color", "border-left-color"],
+        split: split_sides,
+    },
+    Shorthand {
+        name: "border-radius",
+        longhands: &[
+            "border-top-left-radius",
+            "border-top-right-radius",
+            "border-bottom-right-radius",
+            "border-bottom-left-radius",
+        ],
+        split: split_border_radius,
+    },
+    Shorthand {
+        name: "border",
+        longhands: &[
+            "border-top-width",
+            "border-right-width",
+            "border-bottom-width",
+            "border-left-width",
+            "border-top-style",
+            "border-right-style",
+            "border-bottom-style",
+            "border-left-style",
+            "border-top-color",
+            "border-right-color",
+            "border-bottom-color",
+            "border-left-color",
+        ],
+        split: |value| {
+            let [width, style, color] = line_parts(value, is_border_style)?;
+            let mut values = vec![width; 4];
+            values.extend([style.clone(), style.clone(), style.clone(), style]);
+            values.extend([color.clone(), color.clone(), color.clone(), color]);
+            Some(values)
+        },
+    },
+    Shorthand {
+        name: "border-top",
+        longhands: &["border-top-width", "border-top-style", "border-top-color"],
+        split: |value| line_parts(value, is_border_style).map(Vec::from),
+    },
+    Shorthand {
+        name: "border-right",
+        longhands: &["border-right-width", "border-right-style", "border-right-color"],
+        split: |value| line_parts(value, is_border_style).map(Vec::from),
+    },
+    Shorthand {
+        name: "border-bottom",
+        longhands: &["border-bottom-width", "border-bottom-style", "border-bottom-color"],
+        split: |value| line_parts(value, is_border_style).map(Vec::from),
+    },
+    Shorthand {
+        name: "border-left",
+        longhands: &["border-left-width", "border-left-style", "border-left-color"],
+        split: |value| line_parts(value, is_border_style).map(Vec::from),
+    },
+    Shorthand {
+        name: "outline",
+        longhands: &["outline-width", "outline-style", "outline-color"],
+        split: |value| line_parts(value, is_outline_style).map(Vec::from),
+    },
+    Shorthand {
+        name: "background",
+        longhands: &[
+            "background-color",
+            "background-image",
+            "background-position",
+            "background-size",
+            "background-repeat",
+            "background-origin",
+            "background-clip",
+            "background-attachment",
+        ],
+        split: split_background,
+    },
+    Shorthand {
+        name: "font",
+        longhands: &["font-style", "font-weight", "font-stretch", "font-size", "line-height", "font-family"],
+        split: split_font,
+    },
+    Shorthand {
+        name: "flex",
+        longhands: &["flex-grow", "flex-shrink", "flex-basis"],
+        split: split_flex,
+    },
+    Shorthand {
+        name: "grid-area",
+        longhands: &["grid-row-start", "grid-column-start", "grid-row-end", "grid-column-end"],
+        split: |value| split_grid_lines(value, 4),
+    },
+    Shorthand {
+        name: "grid-row",
+        longhands: &["grid-row-start", "grid-row-end"],
+        split: |value| split_grid_lines(value, 2),
+    },
+    Shorthand {
+        name: "grid-column",
+        longhands: &["grid-column-start", "grid-column-end"],
+        split: |value| split_grid_lines(value, 2),
+    },
+    Shorthand {
+        name: "grid-template",
+        longhands: &["grid-template-rows", "grid-template-columns"],
+        split: split_grid_template,
+    },
+    Shorthand {
+        name: "place-content",
+        longhands: &["align-content", "justify-content"],
+        split: |value| match components(value)? {
+            [both] => Some(vec![both.clone(), both.clone()]),
+            [align, justify] => Some(vec![align.clone(), justify.clone()]),
+            _ => None,
+        },
+    },
+    // justify-items and justify-self aren't supported, so only the align
+    // value of these applies
+    Shorthand {
+        name: "place-items",
+        longhands: &["align-items"],
+        split: |value| match components(value)? {
+            [align] | [align, _] => Some(vec![align.clone()]),
+            _ => None,
+        },
+    },
+    Shorthand {
+        name: "place-self",
+        longhands: &["align-self"],
+        split: |value| match components(value)? {
+            [align] | [align, _] => Some(vec![align.clone()]),
+            _ => None,
+        },
+    },
+    Shorthand {
+        name: "list-style",
+        longhands: &["list-style-type", "list-style-position"],
+        split: split_list_style,
+    },
+    Shorthand {
+        name: "text-decoration",
+        longhands: &[
+            "text-decoration-line",
+            "text-decoration-style",
+            "text-decoration-color",
+            "text-decoration-thickness",
+        ],
+        split: split_text_decoration,
+    },
+    Shorthand {
+        name: "transition",
+        longhands: &[
+            "transition-property",
+            "transition-duration",
+            "transition-timing-function",
+            "transition-delay",
+        ],
+        split: split_transition,
+    },
+    Shorthand {
+        name: "animation",
+        longhands: &[
+            "animation-name",
+            "animation-duration",
+            "animation-timing-function",
+            "animation-delay",
+            "animation-iteration-count",
+            "animation-direction",
+            "animation-fill-mode",
+            "animation-play-state",
+        ],
+        split: split_animation,
+    },
+];
+
+pub fn shorthand(name: &str) -> Option<&'static Shorthand> {
+    SHORTHANDS.iter().find(|shorthand| shorthand.name == name)
+}
+
+/// The declarations a parsed declaration stands for: its longhands, with
+/// its `!important`, for a shorthand, or the declaration itself. An
+/// invalid shorthand gives none; one using `var()` is kept whole.
+pub fn expand_declaration(declaration: Declaration) -> Vec<Declaration> {
+    let Some(shorthand) = shorthand(&declaration.property) else {
+        return vec![declaration];
+    };
+    if let Value::Unresolved(_) = declaration.value {
+        return vec![declaration];
+    }
+    shorthand
+        .expand(&declaration.value)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(property, value)| Declaration {
+            property: property.to_string(),
+            value,
+            important: declaration.important,
+        })
+        .collect()
+}
+
+fn keyword(keyword: &str) -> Value {
+    Value::Keyword(keyword.to_string())
+}
+
+/// For a longhand the shorthand leaves out
+fn initial() -> Value {
+    keyword("initial")
+}
+
+/// The `/` between parts of a value, which parses as an empty keyword
+fn is_slash(value: &Value) -> bool {
+    matches!(value, Value::Keyword(keyword) if keyword.is_empty())
+}
+
+/// One value as it is, or several as a space-separated list
+fn join(values: &[Value]) -> Value {
+    match values {
+        [value] => value.clone(),
+        values => Value::List(values.to_vec()),
+    }
+}
+
+/// One item as it is, or several as a comma-separated list
+fn comma_list(mut items: Vec<Value>) -> Value {
+    match items.len() {
+        1 => items.pop().unwrap(),
+        _ => Value::CommaList(items),
+    }
+}
+
+/// The values of a shorthand that isn't a comma-separated list
+fn components(value: &Value) -> Option<&[Value]> {
+    match value {
+        Value::CommaList(_) => None,
+        value => Some(value.as_slice()),
+    }
+}
+
+fn is_length(value: &Value) -> bool {
+    match value {
+        Value::Length(..) | Value::Calc(_) => true,
+        Value::Number(n) => *n == 0.0,
+        _ => false,
+    }
+}
+
+fn is_color(value: &Value) -> bool {
+    value.resolve_color(Color::BLACK).is_some()
+}
+
+/// 1 to 4 values for the top, right, bottom and left, or the corners
+fn split_sides(value: &Value) -> Option<Vec<Value>> {
+    sides(components(value)?).map(Vec::from)
+}
+
+/// Horizontal radii, then optionally `/` and vertical ones
+fn split_border_radius(value: &Value) -> Option<Vec<Value>> {
+    let values = components(value)?;
+    let Some(slash) = values.iter().position(is_slash) else {
+        return split_sides(value);
+    };
+    let horizontal = sides(&values[..slash])?;
+    let vertical = sides(&values[slash + 1..])?;
+    Some(horizontal.into_iter().zip(vertical).map(|(x, y)| Value::List(vec![x, y])).collect())
+}
+
+fn is_border_style(value: &Value) -> bool {
+    ComputedStyle::parse_border_style(value).is_some()
+}
+
+fn is_outline_style(value: &Value) -> bool {
+    matches!(value, Value::Auto) || (is_border_style(value) && value.as_keyword() != Some("hidden"))
+}
+
+/// The width, style and color of a border or an outline, in any order
+fn line_parts(value: &Value, is_style: fn(&Value) -> bool) -> Option<[Value; 3]> {
+    let mut width = None;
+    let mut style = None;
+    let mut color = None;
+    for v in components(value)? {
+        let is_width = is_length(v) || matches!(v.as_keyword(), Some("thin" | "medium" | "thick"));
+        if style.is_none() && is_style(v) {
+            style = Some(v.clone());
+        } else if width.is_none() && is_width {
+            width = Some(v.clone());
+        } else if color.is_none() && is_color(v) {
+            color = Some(v.clone());
+        } else {
+            return None;
+        }
+    }
+    // The initial width is medium, though an unstyled border's is 0
+    Some([
+        width.unwrap_or_else(|| keyword("medium")),
+        style.unwrap_or_else(initial),
+        color.unwrap_or_else(initial),
+    ])
+}
+
+/// Comma-separated layers of an image, position with an optional size,
+/// repeat, one or two boxes and attachment, in any order, and a color in
+/// the last layer
+fn split_background(value: &Value) -> Option<Vec<Value>> {
+    // Only whether values parse matters here, not what they compute to
+    let ctx = LengthContext::new(0.0, 0.0);
+    let items = value.as_comma_list();
+    let mut color = None;
+    let mut layers: [Vec<Value>; 7] = Default::default();
+
+    for (i, item) in items.iter().enumerate() {
+        let mut values = item.as_slice();
+        let mut image = None;
+        let mut position = None;
+        let mut size = None;
+        let mut repeat = None;
+        let mut boxes = Vec::new();
+        let mut attachment = None;
+
+        while let Some(first) = values.first() {
+            // The longest run of values that makes a position
+            let position_len = (1..=values.len().min(4))
+                .rev()
+                .find(|&len| position.is_none() && background::parse_position(&values[..len], &ctx).is_some());
+            if let Some(len) = position_len {
+                position = Some(join(&values[..len]));
+                values = &values[len..];
+                // Then an optional / size
+                if let [slash, rest @ ..] = values {
+                    if is_slash(slash) {
+                        let size_len = (1..=rest.len().min(2))
+                            .rev()
+                            .find(|&len| background::parse_size(&rest[..len], &ctx).is_some())?;
+                        size = Some(join(&rest[..size_len]));
+                        values = &rest[size_len..];
+                    }
+                }
+                continue;
+            }
+
+            let repeat_len =
+                (1..=values.len().min(2)).rev().find(|&len| background::parse_repeat(&values[..len]).is_some());
+            if let (Some(len), None) = (repeat_len, &repeat) {
+                repeat = Some(join(&values[..len]));
+                values = &values[len..];
+                continue;
+            }
+
+            let keyword = first.as_keyword().unwrap_or_default();
+            match first {
+                Value::None if image.is_none() => image = Some(Value::None),
+                _ if BackgroundBox::from_keyword(keyword).is_some() && boxes.len() < 2 => boxes.push(first.clone()),
+                _ if BackgroundAttachment::from_keyword(keyword).is_some() && attachment.is_none() => {
+                    attachment = Some(first.clone())
+                }
+                _ if image.is_none() && background::parse_image(first, &ctx).is_some() => image = Some(first.clone()),
+                _ if i == items.len() - 1 && color.is_none() && is_color(first) => color = Some(first.clone()),
+                _ => return None,
+            }
+            values = &values[1..];
+        }
+
+        layers[0].push(image.unwrap_or(Value::None));
+        layers[1].push(position.unwrap_or_else(|| Value::List(vec![Value::Percentage(0.0), Value::Percentage(0.0)])));
+        layers[2].push(size.unwrap_or(Value::Auto));
+        layers[3].push(repeat.unwrap_or_else(|| keyword("repeat")));
+        // One box sets both the origin and the clip
+        layers[4].push(boxes.first().cloned().unwrap_or_else(|| keyword("padding-box")));
+        layers[5].push(boxes.last().cloned().unwrap_or_else(|| keyword("border-box")));
+        layers[6].push(attachment.unwrap_or_else(|| keyword("scroll")));
+    }
+
+    let mut values = vec![color.unwrap_or_else(initial)];
+    values.extend(layers.map(comma_list));
+    Some(values)
+}
+
+/// `[style] [variant] [weight] [stretch] size[/line-height] family`
+fn split_font(value: &Value) -> Option<Vec<Value>> {
+    let items = value.as_comma_list();
+    let values = items[0].as_slice();
+    // The size is the first length, or failing that a plausible unitless
+    // number, taken as pixels
+    let size = values
+        .iter()
+        .position(|v| matches!(v, Value::Length(..) | Value::Percentage(_) | Value::Calc(_)))
+        .or_else(|| values.iter().position(|v| matches!(v, Value::Number(n) if (1.0..=200.0).contains(n))))?;
+
+    let mut style = None;
+    let mut weight = None;
+    let mut stretch = None;
+    for v in &values[..size] {
+        let keyword = v.as_keyword().unwrap_or_default().to_ascii_lowercase();
+        match v {
+            Value::Number(n) if weight.is_none() && (1.0..=1000.0).contains(n) => weight = Some(v.clone()),
+            // Fonts have one oblique, so its angle is ignored
+            Value::Angle(_) if style.is_some() => {}
+            // normal leaves the others at their initial values too, and
+            // font-variant isn't supported
+            _ if keyword == "normal" || keyword == "small-caps" => {}
+            _ if style.is_none() && FontStyle::from_keyword(&keyword).is_some() => style = Some(v.clone()),
+            _ if weight.is_none() && keyword == "bold" => weight = Some(v.clone()),
+            _ if stretch.is_none() && FontStretch::from_keyword(&keyword).is_some() => stretch = Some(v.clone()),
+            _ => return None,
+        }
+    }
+
+    let font_size = match &values[size] {
+        Value::Number(px) => Value::Length(*px, Unit::Px),
+        size => size.clone(),
+    };
+    let (line_height, family) = match &values[size + 1..] {
+        [slash, line_height, family @ ..] if is_slash(slash) => (line_height.clone(), family),
+        family => (initial(), family),
+    };
+    if family.is_empty() {
+        return None;
+    }
+    let mut families = vec![join(family)];
+    families.extend(items[1..].iter().cloned());
+
+    Some(vec![
+        style.unwrap_or_else(initial),
+        weight.unwrap_or_else(initial),
+        stretch.unwrap_or_else(initial),
+        font_size,
+        line_height,
+        comma_list(families),
+    ])
+}
+
+/// `none`, `auto`, or a grow factor and an optional shrink factor,
+/// and a basis, in either order
+fn split_flex(value: &Value) -> Option<Vec<Value>> {
+    let values = components(value)?;
+    match values {
+        [Value::None] => return Some(vec![Value::Number(0.0), Value::Number(0.0), Value::Auto]),
+        [Value::Auto] => return Some(vec![Value::Number(1.0), Value::Number(1.0), Value::Auto]),
+        _ => {}
+    }
+
+    let mut factors = Vec::new();
+    let mut basis = None;
+    for v in values {
+        // The factors go together, before or after the basis
+        let factor_fits = factors.len() < 2 && (basis.is_none() || factors.is_empty());
+        match v {
+            Value::Number(n) if factor_fits && *n >= 0.0 => factors.push(v.clone()),
+            _ if basis.is_none() && (is_length(v) || matches!(v, Value::Percentage(_) | Value::Auto)) => {
+                basis = Some(v.clone())
+            }
+            Value::Keyword(keyword) if basis.is_none() && keyword == "content" => basis = Some(v.clone()),
+            _ => return None,
+        }
+    }
+    if factors.is_empty() && basis.is_none() {
+        return None;
+    }
+    Some(vec![
+        factors.first().cloned().unwrap_or(Value::Number(1.0)),
+        factors.get(1).cloned().unwrap_or(Value::Number(1.0)),
+        // With factors alone, the basis is 0
+        basis.unwrap_or(Value::Length(0.0, Unit::Px)),
+    ])
+}
+
+/// Up to `count` grid lines separated by `/`; those left out are `auto`
+fn split_grid_lines(value: &Value, count: usize) -> Option<Vec<Value>> {
+    let parts: Vec<&[Value]> = components(value)?.split(is_slash).collect();
+    if parts.len() > count || parts.iter().any(|part| part.is_empty()) {
+        return None;
+    }
+    let mut lines: Vec<Value> = parts.into_iter().map(join).collect();
+    lines.resize(count, Value::Auto);
+    Some(lines)
+}
+
+/// `none`, or rows and columns separated by `/`. The form with area
+/// names isn't supported.
+fn split_grid_template(value: &Value) -> Option<Vec<Value>> {
+    let values = components(value)?;
+    if let [Value::None] = values {
+        return Some(vec![Value::None, Value::None]);
+    }
+    match values.split(is_slash).collect::<Vec<_>>()[..] {
+        [rows, columns] if !rows.is_empty() && !columns.is_empty() => {
+            let has_areas = values.iter().any(|v| matches!(v, Value::String(_)));
+            (!has_areas).then(|| vec![join(rows), join(columns)])
+        }
+        _ => None,
+    }
+}
+
+/// A type and a position, in any order. list-style-image isn't supported,
+/// so an image is ignored.
+fn split_list_style(value: &Value) -> Option<Vec<Value>> {
+    let mut list_type = None;
+    let mut position = None;
+    let mut image = false;
+    let mut none = false;
+    for v in components(value)? {
+        match v {
+            // none is the image, or the type when there is no other
+            Value::None if !none => none = true,
+            Value::Url(_) | Value::Function(..) if !image => image = true,
+            Value::Keyword(keyword) if position.is_none() && matches!(keyword.as_str(), "inside" | "outside") => {
+                position = Some(v.clone())
+            }
+            _ if list_type.is_none() && ListStyleType::from_value(v).is_some() => list_type = Some(v.clone()),
+            _ => return None,
+        }
+    }
+    if none && list_type.is_none() {
+        list_type = Some(Value::None);
+    }
+    Some(vec![list_type.unwrap_or_else(initial), position.unwrap_or_else(initial)])
+}
+
+/// Lines, a style, a color and a thickness, in any order
+fn split_text_decoration(value: &Value) -> Option<Vec<Value>> {
+    let mut lines = Vec::new();
+    let mut style = None;
+    let mut color = None;
+    let mut thickness = None;
+    for v in components(value)? {
+        let keyword = v.as_keyword().unwrap_or_default();
+        let is_line = matches!(keyword, "underline" | "overline" | "line-through" | "blink");
+        let is_thickness = is_length(v) || matches!(v, Value::Percentage(_) | Value::Auto) || keyword == "from-font";
+        match v {
+            Value::None if lines.is_empty() => lines.push(Value::None),
+            _ if is_line && !lines.iter().any(|line| matches!(line, Value::None)) => lines.push(v.clone()),
+            _ if style.is_none() && TextDecorationStyle::from_keyword(keyword).is_some() => style = Some(v.clone()),
+            _ if color.is_none() && is_color(v) => color = Some(v.clone()),
+            _ if thickness.is_none() && is_thickness => thickness = Some(v.clone()),
+            _ => return None,
+        }
+    }
+    Some(vec![
+        if lines.is_empty() { initial() } else { join(&lines) },
+        style.unwrap_or_else(initial),
+        color.unwrap_or_else(initial),
+        thickness.unwrap_or_else(initial),
+    ])
+}
+
+/// Each item's property, duration, timing function and delay in any
+/// order, the first time being the duration
+fn split_transition(value: &Value) -> Option<Vec<Value>> {
+    let items = value.as_comma_list();
+    let mut longhands: [Vec<Value>; 4] = Default::default();
+    for item in items {
+        let mut property = None;
+        let mut times = Vec::new();
+        let mut timing_function = None;
+        for v in item.as_slice() {
+            match v {
+                Value::Time(_) if times.len() < 2 => times.push(v.clone()),
+                // none is only valid on its own
+                Value::None if property.is_none() && items.len() == 1 => property = Some(v.clone()),
+                _ if timing_function.is_none() && TimingFunction::from_value(v).is_some() => {
+                    timing_function = Some(v.clone())
+                }
+                Value::Keyword(keyword) if property.is_none() && !keyword.is_empty() => property = Some(v.clone()),
+                _ => return None,
+            }
+        }
+        if matches!(times.first(), Some(Value::Time(duration)) if *duration < 0.0) {
+            return None;
+        }
+        longhands[0].push(property.unwrap_or_else(|| keyword("all")));
+        longhands[1].push(times.first().cloned().unwrap_or(Value::Time(0.0)));
+        longhands[2].push(timing_function.unwrap_or_else(|| keyword("ease")));
+        longhands[3].push(times.get(1).cloned().unwrap_or(Value::Time(0.0)));
+    }
+    Some(Vec::from(longhands.map(comma_list)))
+}
+
+/// Each item's name, times, timing function, iteration count, direction,
+/// fill mode and play state. Keywords go to the first property that
+/// accepts them and is still unset, so a name that is also a keyword must
+/// come after it.
+fn split_animation(value: &Value) -> Option<Vec<Value>> {
+    let mut longhands: [Vec<Value>; 8] = Default::default();
+    for item in value.as_comma_list() {
+        let mut name = None;
+        let mut times = Vec::new();
+        let mut timing_function = None;
+        let mut iteration_count = None;
+        let mut direction = None;
+        let mut fill_mode = None;
+        let mut play_state = None;
+        for v in item.as_slice() {
+            let keyword = v.as_keyword().unwrap_or_default();
+            match v {
+                Value::Time(_) if times.len() < 2 => times.push(v.clone()),
+                _ if timing_function.is_none() && TimingFunction::from_value(v).is_some() => {
+                    timing_function = Some(v.clone())
+                }
+                _ if iteration_count.is_none() && animation::iteration_count(v).is_some() => {
+                    iteration_count = Some(v.clone())
+                }
+                _ if direction.is_none() && AnimationDirection::from_keyword(keyword).is_some() => {
+                    direction = Some(v.clone())
+                }
+                _ if fill_mode.is_none() && FillMode::from_keyword(keyword).is_some() => fill_mode = Some(v.clone()),
+                _ if play_state.is_none() && PlayState::from_keyword(keyword).is_some() => {
+                    play_state = Some(v.clone())
+                }
+                Value::None | Value::String(_) if name.is_none() => name = Some(v.clone()),
+                Value::Keyword(keyword) if name.is_none() && !keyword.is_empty() => name = Some(v.clone()),
+                _ => return None,
+            }
+        }
+        if matches!(times.first(), Some(Value::Time(duration)) if *duration < 0.0) {
+            return None;
+        }
+        longhands[0].push(name.unwrap_or(Value::None));
+        longhands[1].push(times.first().cloned().unwrap_or(Value::Time(0.0)));
+        longhands[2].push(timing_function.unwrap_or_else(|| keyword("ease")));
+        longhands[3].push(times.get(1).cloned().unwrap_or(Value::Time(0.0)));
+        longhands[4].push(iteration_count.unwrap_or(Value::Number(1.0)));
+        longhands[5].push(direction.unwrap_or_else(|| keyword("normal")));
+        longhands[6].push(fill_mode.unwrap_or(Value::None));
+        longhands[7].push(play_state.unwrap_or_else(|| keyword("running")));
+    }
+    Some(Vec::from(longhands.map(comma_list)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::parse_css;
+
+    /// The declarations of a rule with `css` as its block, with
+    /// shorthands expanded
+    fn expand(css: &str) -> Vec<Declaration> {
+        let stylesheet = parse_css(&format!("a {{ {} }}", css));
+        stylesheet.rules.first().map(|rule| rule.declarations.clone()).unwrap_or_default()
+    }
+
+    fn properties(declarations: &[Declaration]) -> Vec<&str> {
+        declarations.iter().map(|decl| decl.property.as_str()).collect()
+    }
+
+    fn is_px(value: &Value, px: f32) -> bool {
+        matches!(value, Value::Length(v, Unit::Px) if *v == px)
+    }
+
+    fn is_keyword(value: &Value, expected: &str) -> bool {
+        value.as_keyword() == Some(expected)
+    }
+
+    #[test]
+    fn test_expand_sides() {
+        let margin = expand("margin: 1px 2px 3px !important;");
+        assert_eq!(properties(&margin), ["margin-top", "margin-right", "margin-bottom", "margin-left"]);
+        assert!(margin.iter().all(|decl| decl.important));
+        assert!(is_px(&margin[0].value, 1.0) && is_px(&margin[2].value, 3.0));
+        assert!(is_px(&margin[1].value, 2.0) && is_px(&margin[3].value, 2.0));
+
+        let inset = expand("inset: 0 auto;");
+        assert_eq!(properties(&inset), ["top", "right", "bottom", "left"]);
+        assert!(matches!(inset[1].value, Value::Auto));
+        assert!(!inset[0].important);
+
+        // Too many values drop the declaration; a CSS-wide keyword goes to
+        // every longhand
+        assert!(expand("padding: 1px 2px 3px 4px 5px;").is_empty());
+        let inherited = expand("border-color: inherit;");
+        assert_eq!(inherited.len(), 4);
+        assert!(inherited.iter().all(|decl| is_keyword(&decl.value, "inherit")));
+    }
+
+    #[test]
+    fn test_expand_border_and_outline() {
+        // Any order; the width left out is medium and the color initial
+        let border = expand("border: red solid;");
+        assert_eq!(border.len(), 12);
+        assert!(border[..4].iter().all(|decl| is_keyword(&decl.value, "medium")));
+        assert!(border[4..8].iter().all(|decl| is_keyword(&decl.value, "solid")));
+        assert!(border[8..].iter().all(|decl| decl.value.to_color() == Some(Color::RED)));
+
+        let top = expand("border-top: 2px dashed;");
+        assert_eq!(properties(&top), ["border-top-width", "border-top-style", "border-top-color"]);
+        assert!(is_px(&top[0].value, 2.0));
+        assert!(is_keyword(&top[2].value, "initial"));
+        assert!(expand("border: 1px 2px solid;").is_empty());
+
+        let outline = expand("outline: auto 1px currentcolor;");
+        assert_eq!(properties(&outline), ["outline-width", "outline-style", "outline-color"]);
+        assert!(matches!(outline[1].value, Value::Auto));
+        assert!(expand("outline: hidden;").is_empty());
+
+        let radius = expand("border-radius: 10px 20px / 5px;");
+        assert_eq!(radius[0].property, "border-top-left-radius");
+        let Value::List(corner) = &radius[1].value else {
+            panic!("expected horizontal and vertical radii");
+        };
+        assert!(is_px(&corner[0], 20.0) && is_px(&corner[1], 5.0));
+    }
+
+    #[test]
+    fn test_expand_background() {
+        let background = expand("background: url(a.png) center / cover no-repeat, green;");
+        assert_eq!(background.len(), 8);
+        assert_eq!(background[0].property, "background-color");
+        assert_eq!(background[0].value.to_color(), Some(Color::rgb(0, 128, 0)));
+        // A list per layer property, with the defaults filled in
+        let Value::CommaList(images) = &background[1].value else {
+            panic!("expected an image per layer");
+        };
+        assert!(matches!(&images[0], Value::Url(url) if url == "a.png"));
+        assert!(matches!(images[1], Value::None));
+        assert!(is_keyword(&background[3].value.as_comma_list()[0], "cover"));
+        assert!(is_keyword(&background[4].value.as_comma_list()[1], "repeat"));
+
+        // A single box sets the origin and the clip
+        let boxed = expand("background: content-box red;");
+        assert!(is_keyword(&boxed[5].value, "content-box"));
+        assert!(is_keyword(&boxed[6].value, "content-box"));
+
+        // The color must come last
+        assert!(expand("background: red, url(a.png);").is_empty());
+    }
+
+    #[test]
+    fn test_expand_font() {
+        let font = expand("font: italic bold 12px/1.5 \"Open Sans\", serif;");
+        assert_eq!(
+            properties(&font),
+            ["font-style", "font-weight", "font-stretch", "font-size", "line-height", "font-family"]
+        );
+        assert!(is_keyword(&font[0].value, "italic"));
+        assert!(is_keyword(&font[1].value, "bold"));
+        assert!(is_keyword(&font[2].value, "initial"));
+        assert!(is_px(&font[3].value, 12.0));
+        assert!(matches!(font[4].value, Value::Number(n) if n == 1.5));
+        assert_eq!(font[5].value.as_comma_list().len(), 2);
+
+        // Left out, line-height is reset
+        let plain = expand("font: 16px serif;");
+        assert!(is_keyword(&plain[4].value, "initial"));
+
+        // The size and family are required
+        assert!(expand("font: bold serif;").is_empty());
+        assert!(expand("font: 12px;").is_empty());
+        assert!(expand("font: wobbly 12px serif;").is_empty());
+    }
+
+    #[test]
+    fn test_expand_flex() {
+        let numbers = |css: &str| -> Vec<Value> { expand(css).into_iter().map(|decl| decl.value).collect() };
+
+        let one = numbers("flex: 2;");
+        assert!(matches!(one[..], [Value::Number(g), Value::Number(s), _] if g == 2.0 && s == 1.0));
+        assert!(is_px(&one[2], 0.0));
+        let none = numbers("flex: none;");
+        assert!(matches!(none[..], [Value::Number(g), Value::Number(s), Value::Auto] if g == 0.0 && s == 0.0));
+        let full = numbers("flex: 1 0 10px;");
+        assert!(matches!(full[1], Value::Number(s) if s == 0.0) && is_px(&full[2], 10.0));
+        let basis = numbers("flex: 50%;");
+        assert!(matches!(basis[0], Value::Number(g) if g == 1.0) && matches!(basis[2], Value::Percentage(_)));
+
+        assert!(expand("flex: 1 2 3;").is_empty());
+    }
+
+    #[test]
+    fn test_expand_grid() {
+        let area = expand("grid-area: 1 / 2 / 3;");
+        assert_eq!(
+            properties(&area),
+            ["grid-row-start", "grid-column-start", "grid-row-end", "grid-column-end"]
+        );
+        assert!(matches!(area[2].value, Value::Number(n) if n == 3.0));
+        assert!(matches!(area[3].value, Value::Auto));
+
+        let row = expand("grid-row: 2;");
+        assert_eq!(properties(&row), ["grid-row-start", "grid-row-end"]);
+        assert!(matches!(row[1].value, Value::Auto));
+        assert!(expand("grid-column: 1 / 2 / 3;").is_empty());
+
+        let template = expand("grid-template: none;");
+        assert_eq!(properties(&template), ["grid-template-rows", "grid-template-columns"]);
+        assert!(template.iter().all(|decl| matches!(decl.value, Value::None)));
+        assert_eq!(expand("grid-template: 10px 20px / 30px;").len(), 2);
+        assert!(expand("grid-template: \"a b\" 10px / 30px;").is_empty());
+    }
+
+    #[test]
+    fn test_expand_place_and_lists() {
+        let content = expand("place-content: center space-between;");
+        assert_eq!(properties(&content), ["align-content", "justify-content"]);
+        assert!(is_keyword(&content[1].value, "space-between"));
+        let items = expand("place-items: end center;");
+        assert_eq!(properties(&items), ["align-items"]);
+        assert!(is_keyword(&items[0].value, "end"));
+
+        let list = expand("list-style: inside square;");
+        assert!(is_keyword(&list[0].value, "square"));
+        assert!(is_keyword(&list[1].value, "inside"));
+        let none = expand("list-style: none;");
+        assert!(matches!(none[0].value, Value::None));
+        assert!(is_keyword(&none[1].value, "initial"));
+
+        let decoration = expand("text-decoration: underline overline wavy red;");
+        assert_eq!(decoration.len(), 4);
+        assert!(matches!(&decoration[0].value, Value::List(lines) if lines.len() == 2));
+        assert!(is_keyword(&decoration[1].value, "wavy"));
+        assert!(is_keyword(&decoration[3].value, "initial"));
+        assert!(expand("text-decoration: underline 1px 2px;").is_empty());
+    }
+
+    #[test]
+    fn test_expand_transition_and_animation() {
+        let transition = expand("transition: opacity 1s, color;");
+        assert_eq!(transition[0].property, "transition-property");
+        let Value::CommaList(durations) = &transition[1].value else {
+            panic!("expected a duration per transition");
+        };
+        assert!(matches!(durations[..], [Value::Time(a), Value::Time(b)] if a == 1.0 && b == 0.0));
+        assert!(is_keyword(&transition[2].value.as_comma_list()[1], "ease"));
+        assert!(expand("transition: none, color;").is_empty());
+        assert!(expand("transition: opacity -1s;").is_empty());
+
+        let animation = expand("animation: spin 2s infinite;");
+        assert_eq!(animation.len(), 8);
+        assert!(is_keyword(&animation[0].value, "spin"));
+        assert!(is_keyword(&animation[4].value, "infinite"));
+        assert!(matches!(animation[6].value, Value::None));
+        assert!(is_keyword(&animation[7].value, "running"));
+    }
+
+    #[test]
+    fn test_var_is_expanded_later() {
+        let declarations = expand("margin: var(--m) 1px;");
+        assert_eq!(properties(&declarations), ["margin"]);
+        assert!(matches!(declarations[0].value, Value::Unresolved(_)));
+
+        let substituted = shorthand("margin").unwrap().expand(&expand("x: 2px 1px;")[0].value).unwrap();
+        assert_eq!(substituted[0].0, "margin-top");
+        assert!(is_px(&substituted[0].1, 2.0));
+    }
+}
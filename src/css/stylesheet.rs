@@ -1,5 +1,10 @@
+use super::math::CalcExpr;
+use super::media::{MediaEnvironment, MediaQueryList};
 use super::selector::Selector;
+use crate::render::font::{FaceDescriptors, FontStretch, FontStyle};
 use crate::render::painter::Color;
+use std::rc::Rc;
+use url::Url;
 
 #[derive(Debug, Clone)]
 pub struct Declaration {
@@ -15,10 +20,29 @@ pub enum Value {
     Color(Color),
     Number(f32),
     Percentage(f32),
+    /// A quoted string, e.g. in `content: "> "`
+    String(String),
+    /// `url(...)`, unresolved
+    Url(String),
+    /// An angle, in degrees
+    Angle(f32),
+    /// A time, in seconds
+    Time(f32),
     Auto,
     None,
     /// Multiple values for shorthand properties (e.g., margin: 10px 20px)
     List(Vec<Value>),
+    /// Comma-separated values, e.g. `font-family: Inter, sans-serif`; each
+    /// may be a space-separated `List`
+    CommaList(Vec<Value>),
+    /// `calc()`, `min()`, `max()` or `clamp()`
+    Calc(Rc<CalcExpr>),
+    /// Any other function, with its comma-separated arguments, e.g.
+    /// `counter(item, upper-roman)`
+    Function(String, Vec<Value>),
+    /// Raw tokens of a custom property, or of a value using `var()`,
+    /// resolved at computed-value time
+    Unresolved(String),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -29,192 +53,130 @@ pub enum Unit {
     Percent,
     Vh,
     Vw,
+    Vmin,
+    Vmax,
+    // Absolute units
+    Pt,
+    Pc,
+    In,
+    Cm,
+    Mm,
+    Q,
+    // Font-relative units
+    Ex,
+    Ch,
+    Lh,
+    Rlh,
+}
+
+impl Unit {
+    /// Parses a dimension unit, case-insensitively. The small, large and
+    /// dynamic viewport units map to the plain ones, since the viewport has
+    /// no browser UI that expands or retracts.
+    pub fn from_name(name: &str) -> Option<Unit> {
+        let unit = match name.to_ascii_lowercase().as_str() {
+            "px" => Unit::Px,
+            "em" => Unit::Em,
+            "rem" => Unit::Rem,
+            "%" => Unit::Percent,
+            "vh" | "svh" | "lvh" | "dvh" | "vb" | "svb" | "lvb" | "dvb" => Unit::Vh,
+            "vw" | "svw" | "lvw" | "dvw" | "vi" | "svi" | "lvi" | "dvi" => Unit::Vw,
+            "vmin" | "svmin" | "lvmin" | "dvmin" => Unit::Vmin,
+            "vmax" | "svmax" | "lvmax" | "dvmax" => Unit::Vmax,
+            "pt" => Unit::Pt,
+            "pc" => Unit::Pc,
+            "in" => Unit::In,
+            "cm" => Unit::Cm,
+            "mm" => Unit::Mm,
+            "q" => Unit::Q,
+            "ex" => Unit::Ex,
+            "ch" => Unit::Ch,
+            "lh" => Unit::Lh,
+            "rlh" => Unit::Rlh,
+            _ => return None,
+        };
+        Some(unit)
+    }
+
+    /// Converts `value` in this unit to pixels. Percentages depend on the
+    /// property, so they give `None`.
+    pub fn to_px(self, value: f32, ctx: &LengthContext) -> Option<f32> {
+        let px = match self {
+            Unit::Px => value,
+            Unit::Em => value * ctx.font_size,
+            Unit::Rem => value * ctx.root_font_size,
+            Unit::Percent => return None,
+            Unit::Vh => value * ctx.viewport_height / 100.0,
+            Unit::Vw => value * ctx.viewport_width / 100.0,
+            Unit::Vmin => value * ctx.viewport_width.min(ctx.viewport_height) / 100.0,
+            Unit::Vmax => value * ctx.viewport_width.max(ctx.viewport_height) / 100.0,
+            Unit::Pt => value * 96.0 / 72.0,
+            Unit::Pc => value * 16.0,
+            Unit::In => value * 96.0,
+            Unit::Cm => value * 96.0 / 2.54,
+            Unit::Mm => value * 96.0 / 25.4,
+            Unit::Q => value * 96.0 / 101.6,
+            // Without font metrics here, use the spec's fallback of 0.5em
+            Unit::Ex | Unit::Ch => value * ctx.font_size * 0.5,
+            Unit::Lh => value * ctx.line_height,
+            Unit::Rlh => value * ctx.root_line_height,
+        };
+        Some(px)
+    }
+}
+
+/// What relative lengths resolve against when computing an element's style
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LengthContext {
+    /// Font size `em` is relative to: the parent's while computing
+    /// `font-size` itself, the element's own for everything else
+    pub font_size: f32,
+    /// Font size of the root element, for `rem`
+    pub root_font_size: f32,
+    /// Line height in pixels `lh` is relative to, the parent's or the
+    /// element's own like `font_size`
+    pub line_height: f32,
+    pub root_line_height: f32,
+    pub viewport_width: f32,
+    pub viewport_height: f32,
+}
+
+impl LengthContext {
+    /// Context for the root element: everything relative to initial values
+    pub fn new(viewport_width: f32, viewport_height: f32) -> Self {
+        Self {
+            font_size: 16.0,
+            root_font_size: 16.0,
+            line_height: 16.0 * 1.2,
+            root_line_height: 16.0 * 1.2,
+            viewport_width,
+            viewport_height,
+        }
+    }
 }
 
 impl Value {
-    pub fn to_px(&self, parent_font_size: f32, viewport_width: f32, viewport_height: f32) -> Option<f32> {
+    pub fn to_px(&self, ctx: &LengthContext) -> Option<f32> {
         match self {
-            Value::Length(v, unit) => {
-                let px = match unit {
-                    Unit::Px => *v,
-                    Unit::Em => *v * parent_font_size,
-                    Unit::Rem => *v * 16.0,
-                    Unit::Percent => return None,
-                    Unit::Vh => *v * viewport_height / 100.0,
-                    Unit::Vw => *v * viewport_width / 100.0,
-                };
-                Some(px)
-            }
+            Value::Length(v, unit) => unit.to_px(*v, ctx),
             Value::Number(v) => Some(*v),
             Value::Percentage(p) => Some(*p),
+            Value::Calc(expr) => expr.to_px(ctx, None),
             _ => None,
         }
     }
 
+    /// The color this value stands for: a color, a named or system
+    /// color, or a mix of them. `currentcolor` only has a color with
+    /// `resolve_color`.
     pub fn to_color(&self) -> Option<Color> {
-        match self {
-            Value::Color(c) => Some(*c),
-            Value::Keyword(kw) => match kw.to_lowercase().as_str() {
-                // Basic colors
-                "black" => Some(Color::BLACK),
-                "white" => Some(Color::WHITE),
-                "red" => Some(Color::rgb(255, 0, 0)),
-                "green" => Some(Color::rgb(0, 128, 0)),
-                "blue" => Some(Color::rgb(0, 0, 255)),
-                "yellow" => Some(Color::rgb(255, 255, 0)),
-                "cyan" | "aqua" => Some(Color::rgb(0, 255, 255)),
-                "magenta" | "fuchsia" => Some(Color::rgb(255, 0, 255)),
-                "transparent" => Some(Color::TRANSPARENT),
-
-                // Grays
-                "gray" | "grey" => Some(Color::rgb(128, 128, 128)),
-                "darkgray" | "darkgrey" => Some(Color::rgb(169, 169, 169)),
-                "lightgray" | "lightgrey" => Some(Color::rgb(211, 211, 211)),
-                "dimgray" | "dimgrey" => Some(Color::rgb(105, 105, 105)),
-                "silver" => Some(Color::rgb(192, 192, 192)),
-                "gainsboro" => Some(Color::rgb(220, 220, 220)),
-                "whitesmoke" => Some(Color::rgb(245, 245, 245)),
-
-                // Reds and pinks
-                "maroon" => Some(Color::rgb(128, 0, 0)),
-                "darkred" => Some(Color::rgb(139, 0, 0)),
-                "firebrick" => Some(Color::rgb(178, 34, 34)),
-                "crimson" => Some(Color::rgb(220, 20, 60)),
-                "indianred" => Some(Color::rgb(205, 92, 92)),
-                "lightcoral" => Some(Color::rgb(240, 128, 128)),
-                "salmon" => Some(Color::rgb(250, 128, 114)),
-                "lightsalmon" => Some(Color::rgb(255, 160, 122)),
-                "coral" => Some(Color::rgb(255, 127, 80)),
-                "tomato" => Some(Color::rgb(255, 99, 71)),
-                "orangered" => Some(Color::rgb(255, 69, 0)),
-                "pink" => Some(Color::rgb(255, 192, 203)),
-                "lightpink" => Some(Color::rgb(255, 182, 193)),
-                "hotpink" => Some(Color::rgb(255, 105, 180)),
-                "deeppink" => Some(Color::rgb(255, 20, 147)),
-                "mediumvioletred" => Some(Color::rgb(199, 21, 133)),
-                "palevioletred" => Some(Color::rgb(219, 112, 147)),
-
-                // Oranges
-                "orange" => Some(Color::rgb(255, 165, 0)),
-                "darkorange" => Some(Color::rgb(255, 140, 0)),
-                "gold" => Some(Color::rgb(255, 215, 0)),
-
-                // Yellows
-                "lightyellow" => Some(Color::rgb(255, 255, 224)),
-                "lemonchiffon" => Some(Color::rgb(255, 250, 205)),
-                "papayawhip" => Some(Color::rgb(255, 239, 213)),
-                "moccasin" => Some(Color::rgb(255, 228, 181)),
-                "peachpuff" => Some(Color::rgb(255, 218, 185)),
-                "palegoldenrod" => Some(Color::rgb(238, 232, 170)),
-                "khaki" => Some(Color::rgb(240, 230, 140)),
-                "darkkhaki" => Some(Color::rgb(189, 183, 107)),
-
-                // Purples
-                "purple" => Some(Color::rgb(128, 0, 128)),
-                "indigo" => Some(Color::rgb(75, 0, 130)),
-                "darkmagenta" => Some(Color::rgb(139, 0, 139)),
-                "darkviolet" => Some(Color::rgb(148, 0, 211)),
-                "darkorchid" => Some(Color::rgb(153, 50, 204)),
-                "mediumorchid" => Some(Color::rgb(186, 85, 211)),
-                "orchid" => Some(Color::rgb(218, 112, 214)),
-                "violet" => Some(Color::rgb(238, 130, 238)),
-                "plum" => Some(Color::rgb(221, 160, 221)),
-                "lavender" => Some(Color::rgb(230, 230, 250)),
-                "thistle" => Some(Color::rgb(216, 191, 216)),
-                "blueviolet" => Some(Color::rgb(138, 43, 226)),
-                "mediumpurple" => Some(Color::rgb(147, 112, 219)),
-                "mediumslateblue" => Some(Color::rgb(123, 104, 238)),
-                "slateblue" => Some(Color::rgb(106, 90, 205)),
-                "darkslateblue" => Some(Color::rgb(72, 61, 139)),
-                "rebeccapurple" => Some(Color::rgb(102, 51, 153)),
-
-                // Blues
-                "navy" => Some(Color::rgb(0, 0, 128)),
-                "darkblue" => Some(Color::rgb(0, 0, 139)),
-                "mediumblue" => Some(Color::rgb(0, 0, 205)),
-                "royalblue" => Some(Color::rgb(65, 105, 225)),
-                "cornflowerblue" => Some(Color::rgb(100, 149, 237)),
-                "lightsteelblue" => Some(Color::rgb(176, 196, 222)),
-                "steelblue" => Some(Color::rgb(70, 130, 180)),
-                "dodgerblue" => Some(Color::rgb(30, 144, 255)),
-                "deepskyblue" => Some(Color::rgb(0, 191, 255)),
-                "skyblue" => Some(Color::rgb(135, 206, 235)),
-                "lightskyblue" => Some(Color::rgb(135, 206, 250)),
-                "lightblue" => Some(Color::rgb(173, 216, 230)),
-                "powderblue" => Some(Color::rgb(176, 224, 230)),
-                "midnightblue" => Some(Color::rgb(25, 25, 112)),
-                "cadetblue" => Some(Color::rgb(95, 158, 160)),
-                "slategray" | "slategrey" => Some(Color::rgb(112, 128, 144)),
-                "lightslategray" | "lightslategrey" => Some(Color::rgb(119, 136, 153)),
-                "aliceblue" => Some(Color::rgb(240, 248, 255)),
-                "azure" => Some(Color::rgb(240, 255, 255)),
-
-                // Greens
-                "lime" => Some(Color::rgb(0, 255, 0)),
-                "limegreen" => Some(Color::rgb(50, 205, 50)),
-                "forestgreen" => Some(Color::rgb(34, 139, 34)),
-                "darkgreen" => Some(Color::rgb(0, 100, 0)),
-                "seagreen" => Some(Color::rgb(46, 139, 87)),
-                "mediumseagreen" => Some(Color::rgb(60, 179, 113)),
-                "springgreen" => Some(Color::rgb(0, 255, 127)),
-                "mediumspringgreen" => Some(Color::rgb(0, 250, 154)),
-                "lightgreen" => Some(Color::rgb(144, 238, 144)),
-                "palegreen" => Some(Color::rgb(152, 251, 152)),
-                "darkseagreen" => Some(Color::rgb(143, 188, 143)),
-                "greenyellow" => Some(Color::rgb(173, 255, 47)),
-                "chartreuse" => Some(Color::rgb(127, 255, 0)),
-                "lawngreen" => Some(Color::rgb(124, 252, 0)),
-                "yellowgreen" => Some(Color::rgb(154, 205, 50)),
-                "olivedrab" => Some(Color::rgb(107, 142, 35)),
-                "olive" => Some(Color::rgb(128, 128, 0)),
-                "darkolivegreen" => Some(Color::rgb(85, 107, 47)),
-                "mediumaquamarine" => Some(Color::rgb(102, 205, 170)),
-                "honeydew" => Some(Color::rgb(240, 255, 240)),
-                "mintcream" => Some(Color::rgb(245, 255, 250)),
-
-                // Cyan/Teal
-                "teal" => Some(Color::rgb(0, 128, 128)),
-                "darkcyan" => Some(Color::rgb(0, 139, 139)),
-                "lightcyan" => Some(Color::rgb(224, 255, 255)),
-                "aquamarine" => Some(Color::rgb(127, 255, 212)),
-                "turquoise" => Some(Color::rgb(64, 224, 208)),
-                "mediumturquoise" => Some(Color::rgb(72, 209, 204)),
-                "darkturquoise" => Some(Color::rgb(0, 206, 209)),
-                "paleturquoise" => Some(Color::rgb(175, 238, 238)),
-
-                // Browns
-                "brown" => Some(Color::rgb(165, 42, 42)),
-                "saddlebrown" => Some(Color::rgb(139, 69, 19)),
-                "sienna" => Some(Color::rgb(160, 82, 45)),
-                "chocolate" => Some(Color::rgb(210, 105, 30)),
-                "peru" => Some(Color::rgb(205, 133, 63)),
-                "sandybrown" => Some(Color::rgb(244, 164, 96)),
-                "burlywood" => Some(Color::rgb(222, 184, 135)),
-                "tan" => Some(Color::rgb(210, 180, 140)),
-                "rosybrown" => Some(Color::rgb(188, 143, 143)),
-                "goldenrod" => Some(Color::rgb(218, 165, 32)),
-                "darkgoldenrod" => Some(Color::rgb(184, 134, 11)),
-                "navajowhite" => Some(Color::rgb(255, 222, 173)),
-                "wheat" => Some(Color::rgb(245, 222, 179)),
-                "blanchedalmond" => Some(Color::rgb(255, 235, 205)),
-                "bisque" => Some(Color::rgb(255, 228, 196)),
-                "cornsilk" => Some(Color::rgb(255, 248, 220)),
-                "beige" => Some(Color::rgb(245, 245, 220)),
-                "antiquewhite" => Some(Color::rgb(250, 235, 215)),
-                "linen" => Some(Color::rgb(250, 240, 230)),
-                "floralwhite" => Some(Color::rgb(255, 250, 240)),
-                "oldlace" => Some(Color::rgb(253, 245, 230)),
-                "ivory" => Some(Color::rgb(255, 255, 240)),
-                "seashell" => Some(Color::rgb(255, 245, 238)),
-                "snow" => Some(Color::rgb(255, 250, 250)),
-                "mistyrose" => Some(Color::rgb(255, 228, 225)),
-                "lavenderblush" => Some(Color::rgb(255, 240, 245)),
-                "ghostwhite" => Some(Color::rgb(248, 248, 255)),
-
-                _ => None,
-            },
-            _ => None,
-        }
+        super::color::resolve(self, None)
+    }
+
+    /// The color this value stands for, with `currentcolor` as
+    /// `current_color`
+    pub fn resolve_color(&self, current_color: Color) -> Option<Color> {
+        super::color::resolve(self, Some(current_color))
     }
 
     pub fn as_keyword(&self) -> Option<&str> {
@@ -223,12 +185,54 @@ impl Value {
             _ => None,
         }
     }
+
+    /// The values of a space-separated list, or this value alone
+    pub fn as_slice(&self) -> &[Value] {
+        match self {
+            Value::List(values) => values,
+            value => std::slice::from_ref(value),
+        }
+    }
+
+    /// The items of a comma-separated list, or this value alone
+    pub fn as_comma_list(&self) -> &[Value] {
+        match self {
+            Value::CommaList(values) => values,
+            value => std::slice::from_ref(value),
+        }
+    }
+
+    /// Makes the `url()`s in this value absolute against `base`
+    pub fn resolve_urls(&mut self, base: &Url) {
+        match self {
+            Value::Url(url) => {
+                if let Ok(resolved) = base.join(url) {
+                    *url = resolved.to_string();
+                }
+            }
+            Value::List(values) | Value::CommaList(values) | Value::Function(_, values) => {
+                for value in values {
+                    value.resolve_urls(base);
+                }
+            }
+            _ => {}
+        }
+    }
 }
 
+/// A style rule. Rules nested in other rules or in at-rules are flattened
+/// into the sheet in source order, each keeping what it was nested in: the
+/// `@media` conditions, the cascade layer, and for nested style rules, the
+/// parent's selector, which `&` has been replaced with.
 #[derive(Debug, Clone)]
 pub struct Rule {
     pub selectors: Vec<Selector>,
     pub declarations: Vec<Declaration>,
+    /// Conditions of the enclosing `@media` blocks; all must match
+    pub media: Vec<Rc<MediaQueryList>>,
+    /// The full name of the enclosing `@layer`, such as `base.reset`;
+    /// `None` for unlayered rules
+    pub layer: Option<Rc<str>>,
 }
 
 impl Rule {
@@ -236,8 +240,14 @@ impl Rule {
         Self {
             selectors: Vec::new(),
             declarations: Vec::new(),
+            media: Vec::new(),
+            layer: None,
         }
     }
+
+    pub fn matches_media(&self, env: &MediaEnvironment) -> bool {
+        self.media.iter().all(|media| media.matches(env))
+    }
 }
 
 impl Default for Rule {
@@ -246,14 +256,63 @@ impl Default for Rule {
     }
 }
 
+/// Where a sheet comes from. Normal declarations from later origins win;
+/// for `!important` ones the order is reversed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Origin {
+    /// The browser's built-in defaults
+    UserAgent,
+    /// The user's own sheet, from the profile directory
+    User,
+    /// The page's sheets
+    #[default]
+    Author,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct Stylesheet {
+    pub origin: Origin,
     pub rules: Vec<Rule>,
+    /// `@font-face` rules, in source order
+    pub font_faces: Vec<FontFace>,
+    /// `@keyframes` rules, in source order
+    pub keyframes: Vec<Keyframes>,
+    /// Full names of the cascade layers the sheet declares, in the order
+    /// they first appear; a layer's ancestors come before it
+    pub layers: Vec<Rc<str>>,
+    /// `@import` rules not yet loaded, in source order
+    pub imports: Vec<Import>,
 }
 
+/// How deeply `@import`s are followed
+const MAX_IMPORT_DEPTH: usize = 8;
+
 impl Stylesheet {
     pub fn new() -> Self {
-        Self { rules: Vec::new() }
+        Self {
+            origin: Origin::Author,
+            rules: Vec::new(),
+            font_faces: Vec::new(),
+            keyframes: Vec::new(),
+            layers: Vec::new(),
+            imports: Vec::new(),
+        }
+    }
+
+    /// The built-in default styles
+    pub fn user_agent() -> Self {
+        Self {
+            origin: Origin::UserAgent,
+            ..super::parser::parse_css(include_str!("../../assets/default.css"))
+        }
+    }
+
+    /// A user stylesheet, e.g. the profile's `user.css`
+    pub fn user(css: &str) -> Self {
+        Self {
+            origin: Origin::User,
+            ..super::parser::parse_css(css)
+        }
     }
 
     pub fn add_rule(&mut self, rule: Rule) {
@@ -262,5 +321,254 @@ impl Stylesheet {
 
     pub fn merge(&mut self, other: Stylesheet) {
         self.rules.extend(other.rules);
+        self.font_faces.extend(other.font_faces);
+        self.keyframes.extend(other.keyframes);
+        for layer in other.layers {
+            self.declare_layer(layer);
+        }
+        self.imports.extend(other.imports);
+    }
+
+    /// Adds a layer to the declared order, after its ancestors, unless it
+    /// is already there
+    pub fn declare_layer(&mut self, name: Rc<str>) {
+        for (i, _) in name.match_indices('.') {
+            if !self.layers.iter().any(|layer| **layer == name[..i]) {
+                self.layers.push(Rc::from(&name[..i]));
+            }
+        }
+        if !self.layers.contains(&name) {
+            self.layers.push(name);
+        }
+    }
+
+    /// Fetches the sheet's `@import`s through `fetch`, relative to `base`,
+    /// which is where the sheet came from, and puts their rules before the
+    /// sheet's own, as if written in place of the `@import`s. Imported
+    /// sheets' imports are followed, though never back to a sheet already
+    /// being imported.
+    pub fn load_imports(&mut self, base: &Url, fetch: &mut dyn FnMut(&Url) -> Option<String>) {
+        self.load_imports_from(base, fetch, &mut vec![base.clone()]);
+    }
+
+    fn load_imports_from(&mut self, base: &Url, fetch: &mut dyn FnMut(&Url) -> Option<String>, chain: &mut Vec<Url>) {
+        if self.imports.is_empty() {
+            return;
+        }
+        let own_layers = std::mem::take(&mut self.layers);
+        let mut imported = Stylesheet::new();
+        let mut declared = 0;
+        for import in std::mem::take(&mut self.imports) {
+            // Layers declared before the import come before the ones it brings
+            for layer in &own_layers[declared..import.layer_position] {
+                imported.declare_layer(Rc::clone(layer));
+            }
+            declared = import.layer_position;
+
+            let Ok(url) = base.join(&import.url) else {
+                continue;
+            };
+            if chain.contains(&url) || chain.len() > MAX_IMPORT_DEPTH {
+                continue;
+            }
+            let Some(css) = fetch(&url) else {
+                continue;
+            };
+            let mut sheet = super::parser::parse_css(&css);
+            sheet.resolve_urls(&url);
+            chain.push(url.clone());
+            sheet.load_imports_from(&url, fetch, chain);
+            chain.pop();
+
+            if let Some(layer) = &import.layer {
+                sheet.nest_in_layer(layer);
+            }
+            if !import.media.queries.is_empty() {
+                sheet.restrict_to_media(import.media);
+            }
+            imported.merge(sheet);
+        }
+        for layer in &own_layers[declared..] {
+            imported.declare_layer(Rc::clone(layer));
+        }
+
+        imported.rules.append(&mut self.rules);
+        imported.font_faces.append(&mut self.font_faces);
+        imported.keyframes.append(&mut self.keyframes);
+        imported.origin = self.origin;
+        *self = imported;
+    }
+
+    /// Puts the whole sheet in the layer `name`, as `@import ... layer(name)`
+    /// does: unlayered rules go in it, and its layers become sublayers
+    fn nest_in_layer(&mut self, name: &Rc<str>) {
+        for rule in &mut self.rules {
+            rule.layer = Some(match &rule.layer {
+                Some(layer) => Rc::from(format!("{}.{}", name, layer)),
+                None => Rc::clone(name),
+            });
+        }
+        let layers = std::mem::take(&mut self.layers);
+        self.declare_layer(Rc::clone(name));
+        for layer in layers {
+            self.declare_layer(Rc::from(format!("{}.{}", name, layer)));
+        }
+    }
+
+    /// Makes URLs in the sheet absolute, for a sheet loaded from `base`
+    pub fn resolve_urls(&mut self, base: &Url) {
+        for declaration in self.rules.iter_mut().flat_map(|rule| &mut rule.declarations) {
+            declaration.value.resolve_urls(base);
+        }
+        for face in &mut self.font_faces {
+            face.resolve_urls(base);
+        }
+        let keyframe_declarations = self.keyframes.iter_mut().flat_map(|rule| &mut rule.frames).flat_map(|frame| &mut frame.declarations);
+        for declaration in keyframe_declarations {
+            declaration.value.resolve_urls(base);
+        }
+    }
+
+    /// Applies the whole sheet only where `media` matches, as for
+    /// `<link media="print">`
+    pub fn restrict_to_media(&mut self, media: MediaQueryList) {
+        let media = Rc::new(media);
+        for rule in &mut self.rules {
+            rule.media.insert(0, Rc::clone(&media));
+        }
+        for keyframes in &mut self.keyframes {
+            keyframes.media.insert(0, Rc::clone(&media));
+        }
+    }
+}
+
+/// An `@import` rule, loaded by `Stylesheet::load_imports`. Imports whose
+/// `supports()` condition fails are dropped when parsed.
+#[derive(Debug, Clone)]
+pub struct Import {
+    /// As written; resolved against the importing sheet's URL when loaded
+    pub url: String,
+    /// Where the imported rules apply; empty for everywhere
+    pub media: MediaQueryList,
+    /// The full name of the layer the imported rules go in
+    pub layer: Option<Rc<str>>,
+    /// How many of the importing sheet's layers were declared before it
+    pub layer_position: usize,
+}
+
+/// A `@keyframes` rule
+#[derive(Debug, Clone)]
+pub struct Keyframes {
+    pub name: String,
+    /// By offset; a keyframe listing several offsets appears at each
+    pub frames: Vec<Keyframe>,
+    /// Conditions of the enclosing `@media` blocks; all must match
+    pub media: Vec<Rc<MediaQueryList>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keyframe {
+    /// Where it falls in the animation, from 0 to 1
+    pub offset: f32,
+    pub declarations: Vec<Declaration>,
+}
+
+impl Keyframes {
+    pub fn matches_media(&self, env: &MediaEnvironment) -> bool {
+        self.media.iter().all(|media| media.matches(env))
+    }
+}
+
+/// An `@font-face` rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFace {
+    pub descriptors: FaceDescriptors,
+    /// `src`, in order of preference
+    pub sources: Vec<FontSource>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FontSource {
+    /// A font file to download, with its `format()` hint if given
+    Url { url: String, format: Option<String> },
+    /// An installed font, by name
+    Local(String),
+}
+
+impl FontFace {
+    /// The rule from its descriptors; `None` without a family or sources
+    pub fn from_declarations(declarations: &[Declaration]) -> Option<Self> {
+        let mut descriptors = FaceDescriptors::default();
+        let mut sources = Vec::new();
+        for decl in declarations {
+            let value = &decl.value;
+            match decl.property.to_ascii_lowercase().as_str() {
+                "font-family" => descriptors.family = Self::family_name(value.as_slice())?,
+                "src" => sources = value.as_comma_list().iter().filter_map(Self::source).collect(),
+                // A variable font's weight range; its lower end stands in
+                "font-weight" => {
+                    descriptors.weight = match value.as_slice().first()? {
+                        Value::Number(n) => Some(n.clamp(1.0, 1000.0) as u16),
+                        Value::Keyword(k) if k.eq_ignore_ascii_case("bold") => Some(700),
+                        _ => Some(400),
+                    }
+                }
+                "font-style" => {
+                    descriptors.style = value.as_slice().first()?.as_keyword().and_then(FontStyle::from_keyword)
+                }
+                "font-stretch" => {
+                    descriptors.stretch = match value.as_slice().first()? {
+                        Value::Percentage(p) => Some(FontStretch::from_percentage(*p)),
+                        value => value.as_keyword().and_then(FontStretch::from_keyword),
+                    }
+                }
+                _ => {}
+            }
+        }
+        if descriptors.family.is_empty() || sources.is_empty() {
+            return None;
+        }
+        Some(Self { descriptors, sources })
+    }
+
+    /// A quoted family name, or identifiers joined by spaces
+    fn family_name(values: &[Value]) -> Option<String> {
+        match values {
+            [Value::String(name)] => Some(name.clone()),
+            _ => values
+                .iter()
+                .map(|value| value.as_keyword().filter(|k| !k.is_empty()))
+                .collect::<Option<Vec<_>>>()
+                .map(|words| words.join(" ")),
+        }
+    }
+
+    fn source(item: &Value) -> Option<FontSource> {
+        match item.as_slice() {
+            [Value::Url(url), hints @ ..] => {
+                let format = hints.iter().find_map(|hint| match hint {
+                    Value::Function(name, args) if name == "format" => match args.first()? {
+                        Value::String(format) => Some(format.to_ascii_lowercase()),
+                        value => value.as_keyword().map(|k| k.to_ascii_lowercase()),
+                    },
+                    _ => None,
+                });
+                Some(FontSource::Url { url: url.clone(), format })
+            }
+            [Value::Function(name, args)] if name == "local" => {
+                Self::family_name(args.first()?.as_slice()).map(FontSource::Local)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn resolve_urls(&mut self, base: &Url) {
+        for source in &mut self.sources {
+            if let FontSource::Url { url, .. } = source {
+                if let Ok(resolved) = base.join(url) {
+                    *url = resolved.to_string();
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,210 @@
+//! Feature queries: parsing `@supports` preludes and `supports()` in
+//! `@import`, and evaluating them against what this engine implements.
+//!
+//! Unlike media queries, the answer never changes, so conditions are
+//! evaluated as the style sheet is parsed.
+
+use super::parser::parse_declaration;
+use super::properties;
+use super::selector::{parse_selector_list, split_selector_list};
+use super::shorthand;
+use super::stylesheet::{Declaration, Value};
+use cssparser::{Delimiter, ParseError, Parser, Token};
+
+type ParseResult<'i, T> = Result<T, ParseError<'i, ()>>;
+
+#[derive(Debug, Clone)]
+pub enum SupportsCondition {
+    /// `(display: grid)`
+    Declaration(Declaration),
+    /// `selector(a > b)`, with the selector's source text
+    Selector(String),
+    Not(Box<SupportsCondition>),
+    And(Vec<SupportsCondition>),
+    Or(Vec<SupportsCondition>),
+    /// Syntax we could not parse, or a function we don't know, such as
+    /// `font-tech()`; evaluates to false
+    Unknown,
+}
+
+impl SupportsCondition {
+    pub fn matches(&self) -> bool {
+        match self {
+            SupportsCondition::Declaration(declaration) => supports_declaration(declaration),
+            SupportsCondition::Selector(selector) => {
+                // Every selector of the list must be one we can match
+                let count = split_selector_list(selector).len();
+                parse_selector_list(selector).len() == count
+            }
+            SupportsCondition::Not(condition) => !condition.matches(),
+            SupportsCondition::And(conditions) => conditions.iter().all(SupportsCondition::matches),
+            SupportsCondition::Or(conditions) => conditions.iter().any(SupportsCondition::matches),
+            SupportsCondition::Unknown => false,
+        }
+    }
+}
+
+/// A declaration is supported when `ComputedStyle` applies its property,
+/// as a longhand or a shorthand, and its value parses. Custom properties
+/// take any value.
+fn supports_declaration(declaration: &Declaration) -> bool {
+    if declaration.property.starts_with("--") {
+        return true;
+    }
+    let property = declaration.property.to_ascii_lowercase();
+    if properties::expand(&property).is_empty() {
+        return false;
+    }
+    // A shorthand's value is only valid if it splits into its longhands
+    match shorthand::shorthand(&property) {
+        Some(shorthand) if !matches!(declaration.value, Value::Unresolved(_)) => {
+            shorthand.expand(&declaration.value).is_some()
+        }
+        _ => is_valid_value(&declaration.value),
+    }
+}
+
+/// Whether a value parsed: not empty, and without a color function that
+/// failed to parse
+fn is_valid_value(value: &Value) -> bool {
+    match value {
+        Value::Keyword(keyword) => !keyword.is_empty(),
+        Value::Function(_, args) if args.is_empty() => false,
+        Value::List(values) | Value::CommaList(values) | Value::Function(_, values) => {
+            values.iter().all(is_valid_value)
+        }
+        _ => true,
+    }
+}
+
+/// Parses the prelude of an `@supports` rule, stopping before its block.
+/// A prelude that fails to parse is false.
+pub fn parse_supports_condition(parser: &mut Parser) -> SupportsCondition {
+    parser
+        .parse_until_before(Delimiter::CurlyBracketBlock | Delimiter::Semicolon, |p| {
+            let condition = parse_condition(p)?;
+            p.expect_exhausted()?;
+            Ok::<_, ParseError<()>>(condition)
+        })
+        .unwrap_or(SupportsCondition::Unknown)
+}
+
+/// The contents of `supports()` in an `@import`: a condition, or a bare
+/// declaration
+pub fn parse_supports_function(parser: &mut Parser) -> SupportsCondition {
+    let condition = parser.try_parse(|p| {
+        let condition = parse_condition(p)?;
+        p.expect_exhausted()?;
+        Ok::<_, ParseError<()>>(condition)
+    });
+    if let Ok(condition) = condition {
+        return condition;
+    }
+    match parse_declaration(parser) {
+        Ok(declaration) if parser.is_exhausted() => SupportsCondition::Declaration(declaration),
+        _ => SupportsCondition::Unknown,
+    }
+}
+
+fn parse_condition<'i>(parser: &mut Parser<'i, '_>) -> ParseResult<'i, SupportsCondition> {
+    if parser.try_parse(|p| p.expect_ident_matching("not")).is_ok() {
+        return Ok(SupportsCondition::Not(Box::new(parse_in_parens(parser)?)));
+    }
+
+    let mut items = vec![parse_in_parens(parser)?];
+    let mut is_or = None;
+    loop {
+        let operator = parser.try_parse(|p| {
+            let ident = p.expect_ident_cloned()?;
+            match ident.to_ascii_lowercase().as_str() {
+                "and" => Ok(false),
+                "or" => Ok(true),
+                _ => Err(p.new_custom_error::<_, ()>(())),
+            }
+        });
+        let Ok(operator) = operator else {
+            break;
+        };
+        // `and` and `or` cannot be mixed without parentheses
+        if is_or.is_some_and(|is_or| is_or != operator) {
+            return Err(parser.new_custom_error(()));
+        }
+        is_or = Some(operator);
+        items.push(parse_in_parens(parser)?);
+    }
+
+    Ok(match (items.len(), is_or) {
+        (1, _) => items.pop().unwrap(),
+        (_, Some(true)) => SupportsCondition::Or(items),
+        _ => SupportsCondition::And(items),
+    })
+}
+
+fn parse_in_parens<'i>(parser: &mut Parser<'i, '_>) -> ParseResult<'i, SupportsCondition> {
+    match parser.next()?.clone() {
+        Token::ParenthesisBlock => parser.parse_nested_block(|p| {
+            if let Ok(condition) = p.try_parse(|p| {
+                let condition = parse_condition(p)?;
+                p.expect_exhausted()?;
+                Ok::<_, ParseError<'i, ()>>(condition)
+            }) {
+                return Ok(condition);
+            }
+            if let Ok(declaration) = p.try_parse(|p| {
+                let declaration = parse_declaration(p)?;
+                p.expect_exhausted()?;
+                Ok::<_, ParseError<'i, ()>>(declaration)
+            }) {
+                return Ok(SupportsCondition::Declaration(declaration));
+            }
+            // <general-enclosed>: valid syntax that is simply false
+            while p.next().is_ok() {}
+            Ok(SupportsCondition::Unknown)
+        }),
+        Token::Function(name) if name.eq_ignore_ascii_case("selector") => parser.parse_nested_block(|p| {
+            let start = p.position();
+            while p.next().is_ok() {}
+            Ok(SupportsCondition::Selector(p.slice_from(start).trim().to_string()))
+        }),
+        Token::Function(_) => parser.parse_nested_block(|p| {
+            while p.next().is_ok() {}
+            Ok(SupportsCondition::Unknown)
+        }),
+        _ => Err(parser.new_custom_error(())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cssparser::ParserInput;
+
+    fn supports(prelude: &str) -> bool {
+        let mut input = ParserInput::new(prelude);
+        let mut parser = Parser::new(&mut input);
+        parse_supports_condition(&mut parser).matches()
+    }
+
+    #[test]
+    fn test_supports_conditions() {
+        assert!(supports("(display: grid)"));
+        assert!(supports("(margin: 0 auto)"));
+        assert!(supports("(--anything: { odd })"));
+        assert!(supports("(color: oklch(70% 0.1 200))"));
+        assert!(!supports("(colour: red)"));
+        assert!(!supports("(color: rgb(1 2, 3))"));
+        assert!(!supports("(display:)"));
+
+        assert!(supports("not (frobnicate: 1)"));
+        assert!(supports("(display: flex) and ((gap: 1px) or (frobnicate: 1))"));
+        assert!(!supports("(display: flex) and (frobnicate: 1)"));
+        assert!(supports("(frobnicate: 1) or (opacity: 0.5)"));
+        // and and or don't mix without parentheses
+        assert!(!supports("(opacity: 1) and (opacity: 1) or (opacity: 1)"));
+
+        assert!(supports("selector(a > b:hover)"));
+        assert!(!supports("selector(a::frobnicate)"));
+        assert!(!supports("font-tech(color-COLRv1)"));
+        assert!(!supports("display: grid"));
+    }
+}
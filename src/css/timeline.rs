@@ -0,0 +1,381 @@
+//! The animation timeline: the clock transitions and animations run on,
+//! and the ones running on each element. Styles are computed against it,
+//! so animated values end up in `ComputedStyle` like any other.
+
+use super::animation::{
+    all_animatable, apply_keyframes, clamp_animated, AnimatableProperty, Animation, ComputedKeyframe, Invalidation,
+    PlayState, TransitionTiming,
+};
+use super::computed::ComputedStyle;
+use crate::dom::NodeId;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+pub struct AnimationTimeline {
+    /// Seconds since the timeline was created
+    now: f32,
+    /// A paused clock only moves when set, for deterministic screenshots
+    paused: bool,
+    /// When animated values were last brought up to date
+    updated_at: f32,
+    elements: HashMap<NodeId, ElementAnimations>,
+}
+
+#[derive(Default)]
+struct ElementAnimations {
+    /// The style before animated values, whose changes start transitions;
+    /// only kept while the element has transitions
+    base: Option<Rc<ComputedStyle>>,
+    transitions: Vec<RunningTransition>,
+    animations: Vec<RunningAnimation>,
+}
+
+impl ElementAnimations {
+    fn is_empty(&self) -> bool {
+        self.base.is_none() && self.transitions.is_empty() && self.animations.is_empty()
+    }
+}
+
+struct RunningTransition {
+    property: &'static AnimatableProperty,
+    from: Rc<ComputedStyle>,
+    to: Rc<ComputedStyle>,
+    /// When the value changed; it starts moving after the delay
+    start: f32,
+    timing: TransitionTiming,
+}
+
+impl RunningTransition {
+    fn end(&self) -> f32 {
+        self.start + self.timing.delay.max(0.0) + self.timing.duration
+    }
+
+    fn progress(&self, now: f32) -> f32 {
+        let elapsed = now - self.start - self.timing.delay;
+        let t = if self.timing.duration > 0.0 {
+            elapsed / self.timing.duration
+        } else if elapsed >= 0.0 {
+            1.0
+        } else {
+            0.0
+        };
+        self.timing.timing_function.apply(t)
+    }
+}
+
+struct RunningAnimation {
+    name: String,
+    /// When it started, counting its delay; moved on while paused
+    start: f32,
+    /// How long it had run when `animation-play-state` paused it
+    paused_at: Option<f32>,
+    /// When its values stop changing, `f32::INFINITY` if they never do
+    end: f32,
+    /// What its keyframes' properties need redone as they change
+    invalidation: Invalidation,
+}
+
+impl Default for AnimationTimeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnimationTimeline {
+    pub fn new() -> Self {
+        Self {
+            now: 0.0,
+            paused: false,
+            updated_at: 0.0,
+            elements: HashMap::new(),
+        }
+    }
+
+    /// The current time, in seconds
+    pub fn now(&self) -> f32 {
+        self.now
+    }
+
+    /// Moves the clock on by `dt` seconds, unless it is paused
+    pub fn advance(&mut self, dt: f32) {
+        if !self.paused {
+            self.now += dt;
+        }
+    }
+
+    /// Sets the clock to `time` seconds, paused or not
+    pub fn seek(&mut self, time: f32) {
+        self.now = time;
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Whether any animated value would change if the clock moved on
+    pub fn is_animating(&self) -> bool {
+        !self.paused && self.elements.values().any(|element| element.changes_between(self.now, f32::INFINITY).is_some())
+    }
+
+    /// Whether anything runs on `node_id`, or may once its style changes
+    pub fn contains(&self, node_id: NodeId) -> bool {
+        self.elements.contains_key(&node_id)
+    }
+
+    /// Stops everything running on a node that left the document
+    pub fn forget(&mut self, node_id: NodeId) {
+        self.elements.remove(&node_id);
+    }
+
+    /// Stops everything, for a new document
+    pub fn clear(&mut self) {
+        self.elements.clear();
+    }
+
+    /// The elements whose animated values changed since they were last
+    /// brought up to date, with what each needs redone. Their styles are
+    /// then expected to be recomputed.
+    pub fn take_updates(&mut self) -> Vec<(NodeId, Invalidation)> {
+        let (since, until) = (self.updated_at.min(self.now), self.updated_at.max(self.now));
+        self.updated_at = self.now;
+        if since == until {
+            return Vec::new();
+        }
+        let mut updates: Vec<(NodeId, Invalidation)> = self
+            .elements
+            .iter()
+            .filter_map(|(&node_id, element)| Some((node_id, element.changes_between(since, until)?)))
+            .collect();
+        updates.sort_unstable_by_key(|(node_id, _)| *node_id);
+        updates
+    }
+
+    /// Applies the transitions and animations running on `node_id` to its
+    /// freshly cascaded `style`. Changes from `previous`, the style it had
+    /// before, start transitions; `animations` are its named animations
+    /// with their keyframes computed for it.
+    pub fn update(
+        &mut self,
+        node_id: NodeId,
+        previous: Option<&ComputedStyle>,
+        style: &mut ComputedStyle,
+        animations: &[(Animation, Vec<ComputedKeyframe>)],
+    ) {
+        let now = self.now;
+        let element = self.elements.entry(node_id).or_default();
+        element.start_transitions(previous, style, now);
+        element.run_animations(style, animations, now);
+
+        // Transitions override animations
+        element.transitions.retain(|transition| transition.end() > now);
+        for transition in &element.transitions {
+            let progress = transition.progress(now);
+            transition.property.blend(style, &transition.from, &transition.to, progress);
+        }
+        clamp_animated(style);
+
+        if element.is_empty() {
+            self.elements.remove(&node_id);
+        }
+    }
+}
+
+impl ElementAnimations {
+    /// What its values need redone if they change between two times;
+    /// `None` when they don't
+    fn changes_between(&self, since: f32, until: f32) -> Option<Invalidation> {
+        let transitions = self
+            .transitions
+            .iter()
+            .filter(|transition| transition.start <= until && transition.end() >= since)
+            .map(|transition| transition.property.invalidation);
+        let animations = self
+            .animations
+            .iter()
+            .filter(|animation| animation.paused_at.is_none() && animation.start <= until && animation.end >= since)
+            .map(|animation| animation.invalidation);
+        transitions.chain(animations).max()
+    }
+
+    fn start_transitions(&mut self, previous: Option<&ComputedStyle>, style: &ComputedStyle, now: f32) {
+        // Transitions whose property no longer transitions stop
+        self.transitions
+            .retain(|transition| style.transition.for_property(transition.property.name).is_some());
+
+        let base = self.base.take();
+        let changed: Vec<&'static AnimatableProperty> = match &base {
+            Some(base) => all_animatable().iter().filter(|property| property.differs(base, style)).collect(),
+            None => Vec::new(),
+        };
+        if let (Some(base), false) = (base, changed.is_empty()) {
+            // They start from the values shown before
+            let from = previous.map(|previous| Rc::new(previous.clone())).unwrap_or(base);
+            let mut to = None;
+            for property in changed {
+                self.transitions.retain(|transition| !std::ptr::eq(transition.property, property));
+                let Some(timing) = style.transition.for_property(property.name) else {
+                    continue;
+                };
+                if !property.differs(&from, style) {
+                    continue;
+                }
+                let to = to.get_or_insert_with(|| Rc::new(style.clone()));
+                self.transitions.push(RunningTransition {
+                    property,
+                    from: Rc::clone(&from),
+                    to: Rc::clone(to),
+                    start: now,
+                    timing,
+                });
+            }
+        }
+
+        if style.transition.is_active() {
+            self.base = Some(Rc::new(style.clone()));
+        }
+    }
+
+    fn run_animations(&mut self, style: &mut ComputedStyle, animations: &[(Animation, Vec<ComputedKeyframe>)], now: f32) {
+        // An animation keeps running while its name stays in the list
+        self.animations
+            .retain(|running| animations.iter().any(|(animation, _)| animation.name == running.name));
+
+        for (animation, keyframes) in animations {
+            let index = match self.animations.iter().position(|running| running.name == animation.name) {
+                Some(index) => index,
+                None => {
+                    self.animations.push(RunningAnimation {
+                        name: animation.name.clone(),
+                        start: now,
+                        paused_at: None,
+                        end: f32::INFINITY,
+                        invalidation: Invalidation::Repaint,
+                    });
+                    self.animations.len() - 1
+                }
+            };
+            let running = &mut self.animations[index];
+            match (animation.play_state, running.paused_at) {
+                (PlayState::Paused, None) => running.paused_at = Some(now - running.start),
+                (PlayState::Running, Some(elapsed)) => {
+                    running.start = now - elapsed;
+                    running.paused_at = None;
+                }
+                _ => {}
+            }
+            running.end = running.start + animation.delay.max(0.0) + animation.active_duration();
+            running.invalidation = keyframes
+                .iter()
+                .flat_map(|keyframe| &keyframe.properties)
+                .map(|property| property.invalidation)
+                .max()
+                .unwrap_or(Invalidation::Repaint);
+
+            let elapsed = running.paused_at.unwrap_or(now - running.start);
+            if let Some(progress) = animation.progress(elapsed) {
+                apply_keyframes(style, keyframes, progress, animation.timing_function);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::animation::{FillMode, TimingFunction, TransitionProperty, Transitions};
+    use crate::render::painter::Color;
+
+    fn transitioning(opacity: f32) -> ComputedStyle {
+        ComputedStyle {
+            opacity,
+            transition: Transitions {
+                property: vec![TransitionProperty::Name("opacity".to_string())],
+                duration: vec![1.0],
+                timing_function: vec![TimingFunction::Linear],
+                delay: Vec::new(),
+            },
+            ..ComputedStyle::default()
+        }
+    }
+
+    #[test]
+    fn test_transition_runs_on_the_clock() {
+        let mut timeline = AnimationTimeline::new();
+        let mut style = transitioning(1.0);
+        timeline.update(1, None, &mut style, &[]);
+        assert!(!timeline.is_animating());
+
+        // A changed value starts from the one shown before
+        let previous = style.clone();
+        let mut style = transitioning(0.0);
+        timeline.update(1, Some(&previous), &mut style, &[]);
+        assert_eq!(style.opacity, 1.0);
+        assert!(timeline.is_animating());
+
+        timeline.advance(0.25);
+        assert_eq!(timeline.take_updates(), vec![(1, Invalidation::Repaint)]);
+        let mut style = transitioning(0.0);
+        timeline.update(1, Some(&previous), &mut style, &[]);
+        assert_eq!(style.opacity, 0.75);
+
+        // Once it ends the element needs one last update, then none
+        timeline.advance(1.0);
+        assert_eq!(timeline.take_updates().len(), 1);
+        let mut style = transitioning(0.0);
+        timeline.update(1, Some(&previous), &mut style, &[]);
+        assert_eq!(style.opacity, 0.0);
+        timeline.advance(1.0);
+        assert!(timeline.take_updates().is_empty());
+        assert!(!timeline.is_animating());
+    }
+
+    #[test]
+    fn test_animation_and_paused_clock() {
+        let declarations = crate::css::parse_css("a { color: red; width: 10px }").rules[0].declarations.clone();
+        let keyframes = vec![ComputedKeyframe::new(
+            0.0,
+            &declarations,
+            ComputedStyle {
+                color: Color::RED,
+                ..ComputedStyle::default()
+            },
+        )];
+        let animation = Animation {
+            name: "flash".to_string(),
+            duration: 2.0,
+            timing_function: TimingFunction::Linear,
+            delay: 0.0,
+            iteration_count: 1.0,
+            direction: Default::default(),
+            fill_mode: FillMode::None,
+            play_state: PlayState::Running,
+        };
+        let animations = [(animation, keyframes)];
+
+        let mut timeline = AnimationTimeline::new();
+        timeline.set_paused(true);
+        let mut style = ComputedStyle::default();
+        timeline.update(7, None, &mut style, &animations);
+        assert_eq!(style.color, Color::RED);
+
+        // A paused clock only moves when set
+        timeline.advance(5.0);
+        assert!(timeline.take_updates().is_empty());
+        timeline.seek(1.0);
+        // Width makes it need layout
+        assert_eq!(timeline.take_updates(), vec![(7, Invalidation::Relayout)]);
+        let mut style = ComputedStyle::default();
+        timeline.update(7, None, &mut style, &animations);
+        assert!((style.color.r - 0.5).abs() < 0.001);
+
+        // Past its end it no longer applies
+        timeline.seek(3.0);
+        let mut style = ComputedStyle::default();
+        timeline.update(7, None, &mut style, &animations);
+        assert_eq!(style.color, Color::BLACK);
+    }
+}
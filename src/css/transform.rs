@@ -0,0 +1,335 @@
+//! CSS transforms: the `transform` function list, the individual
+//! `translate`, `rotate` and `scale` properties, and `transform-origin`.
+//! Percentages resolve against the border box, so functions are kept as
+//! computed values until a box's size is known.
+
+use super::background::{self, Position};
+use super::computed::LengthOrPercentage;
+use super::stylesheet::{LengthContext, Value};
+use crate::render::transform::Transform;
+
+/// One function of a `transform` list, or the value of an individual
+/// transform property
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransformFunction {
+    /// `matrix()` or `matrix3d()`
+    Matrix(Transform),
+    /// x and y may be percentages of the border box's size; z may not
+    Translate(LengthOrPercentage, LengthOrPercentage, f32),
+    Scale(f32, f32, f32),
+    /// Degrees clockwise around an axis
+    Rotate([f32; 3], f32),
+    /// Degrees along x and y
+    Skew(f32, f32),
+    /// The distance to the viewer, `None` for `perspective(none)`
+    Perspective(Option<f32>),
+}
+
+impl TransformFunction {
+    pub const NO_TRANSLATE: TransformFunction =
+        TransformFunction::Translate(LengthOrPercentage::Px(0.0), LengthOrPercentage::Px(0.0), 0.0);
+    pub const NO_ROTATE: TransformFunction = TransformFunction::Rotate([0.0, 0.0, 1.0], 0.0);
+    pub const NO_SCALE: TransformFunction = TransformFunction::Scale(1.0, 1.0, 1.0);
+
+    /// The matrix for a border box `width` by `height`
+    pub fn to_transform(&self, width: f32, height: f32) -> Transform {
+        match self {
+            TransformFunction::Matrix(matrix) => *matrix,
+            TransformFunction::Translate(x, y, z) => Transform::translate(x.to_px(width), y.to_px(height), *z),
+            TransformFunction::Scale(x, y, z) => Transform::scale(*x, *y, *z),
+            TransformFunction::Rotate([x, y, z], angle) => Transform::rotate(*x, *y, *z, angle.to_radians()),
+            TransformFunction::Skew(x, y) => Transform::skew(x.to_radians(), y.to_radians()),
+            TransformFunction::Perspective(Some(distance)) => Transform::perspective(*distance),
+            TransformFunction::Perspective(None) => Transform::IDENTITY,
+        }
+    }
+
+    /// The function of the same kind that does nothing, which `none`
+    /// blends as
+    pub fn identity(&self) -> TransformFunction {
+        match self {
+            TransformFunction::Matrix(_) => TransformFunction::Matrix(Transform::IDENTITY),
+            TransformFunction::Translate(..) => TransformFunction::NO_TRANSLATE,
+            TransformFunction::Scale(..) => TransformFunction::NO_SCALE,
+            TransformFunction::Rotate(axis, _) => TransformFunction::Rotate(*axis, 0.0),
+            TransformFunction::Skew(..) => TransformFunction::Skew(0.0, 0.0),
+            TransformFunction::Perspective(_) => TransformFunction::Perspective(None),
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == self.identity()
+    }
+}
+
+/// The point transforms happen around, relative to the border box
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformOrigin {
+    pub position: Position,
+    pub z: f32,
+}
+
+impl Default for TransformOrigin {
+    fn default() -> Self {
+        Self {
+            position: Position::center(),
+            z: 0.0,
+        }
+    }
+}
+
+/// A length or percentage, unitless only for zero
+fn length_or_percentage(value: &Value, ctx: &LengthContext) -> Option<LengthOrPercentage> {
+    match value {
+        Value::Number(n) if *n != 0.0 => None,
+        _ => LengthOrPercentage::from_value(value, ctx),
+    }
+}
+
+fn length(value: &Value, ctx: &LengthContext) -> Option<f32> {
+    length_or_percentage(value, ctx)?.as_px()
+}
+
+fn number(value: &Value) -> Option<f32> {
+    match value {
+        Value::Number(n) => Some(*n),
+        _ => None,
+    }
+}
+
+/// A scale factor; a percentage is a hundredth
+fn factor(value: &Value) -> Option<f32> {
+    match value {
+        Value::Percentage(pct) => Some(pct / 100.0),
+        _ => number(value),
+    }
+}
+
+/// `transform`: `none`, or a space-separated list of functions
+pub fn parse_transform_list(value: &Value, ctx: &LengthContext) -> Option<Vec<TransformFunction>> {
+    if let Value::None = value {
+        return Some(Vec::new());
+    }
+    value
+        .as_slice()
+        .iter()
+        .map(|function| match function {
+            Value::Function(name, args) => parse_function(name, args, ctx),
+            _ => None,
+        })
+        .collect()
+}
+
+fn parse_function(name: &str, args: &[Value], ctx: &LengthContext) -> Option<TransformFunction> {
+    let lp = |value: &Value| length_or_percentage(value, ctx);
+    let zero = LengthOrPercentage::Px(0.0);
+    let angle = background::parse_angle;
+    let function = match (name, args) {
+        ("matrix", [..]) if args.len() == 6 => {
+            let [a, b, c, d, e, f] = <[f32; 6]>::try_from(args.iter().map(number).collect::<Option<Vec<_>>>()?).ok()?;
+            TransformFunction::Matrix(Transform::affine(a, b, c, d, e, f))
+        }
+        ("matrix3d", [..]) if args.len() == 16 => {
+            let values = <[f32; 16]>::try_from(args.iter().map(number).collect::<Option<Vec<_>>>()?).ok()?;
+            TransformFunction::Matrix(Transform::from_columns(values))
+        }
+        ("translate", [x]) => TransformFunction::Translate(lp(x)?, zero, 0.0),
+        ("translate", [x, y]) => TransformFunction::Translate(lp(x)?, lp(y)?, 0.0),
+        ("translatex", [x]) => TransformFunction::Translate(lp(x)?, zero, 0.0),
+        ("translatey", [y]) => TransformFunction::Translate(zero, lp(y)?, 0.0),
+        ("translatez", [z]) => TransformFunction::Translate(zero.clone(), zero, length(z, ctx)?),
+        ("translate3d", [x, y, z]) => TransformFunction::Translate(lp(x)?, lp(y)?, length(z, ctx)?),
+        ("scale", [x]) => TransformFunction::Scale(factor(x)?, factor(x)?, 1.0),
+        ("scale", [x, y]) => TransformFunction::Scale(factor(x)?, factor(y)?, 1.0),
+        ("scalex", [x]) => TransformFunction::Scale(factor(x)?, 1.0, 1.0),
+        ("scaley", [y]) => TransformFunction::Scale(1.0, factor(y)?, 1.0),
+        ("scalez", [z]) => TransformFunction::Scale(1.0, 1.0, factor(z)?),
+        ("scale3d", [x, y, z]) => TransformFunction::Scale(factor(x)?, factor(y)?, factor(z)?),
+        ("rotate" | "rotatez", [a]) => TransformFunction::Rotate([0.0, 0.0, 1.0], angle(a)?),
+        ("rotatex", [a]) => TransformFunction::Rotate([1.0, 0.0, 0.0], angle(a)?),
+        ("rotatey", [a]) => TransformFunction::Rotate([0.0, 1.0, 0.0], angle(a)?),
+        ("rotate3d", [x, y, z, a]) => TransformFunction::Rotate([number(x)?, number(y)?, number(z)?], angle(a)?),
+        ("skew", [x]) => TransformFunction::Skew(angle(x)?, 0.0),
+        ("skew", [x, y]) => TransformFunction::Skew(angle(x)?, angle(y)?),
+        ("skewx", [x]) => TransformFunction::Skew(angle(x)?, 0.0),
+        ("skewy", [y]) => TransformFunction::Skew(0.0, angle(y)?),
+        ("perspective", [Value::None]) => TransformFunction::Perspective(None),
+        ("perspective", [distance]) => {
+            let distance = length(distance, ctx)?;
+            if distance < 0.0 {
+                return None;
+            }
+            TransformFunction::Perspective(Some(distance))
+        }
+        _ => return None,
+    };
+    Some(function)
+}
+
+/// `translate`: `none`, or x, then optionally y and z
+pub fn parse_translate(value: &Value, ctx: &LengthContext) -> Option<TransformFunction> {
+    let lp = |value: &Value| length_or_percentage(value, ctx);
+    let zero = LengthOrPercentage::Px(0.0);
+    match value.as_slice() {
+        [Value::None] => Some(TransformFunction::NO_TRANSLATE),
+        [x] => Some(TransformFunction::Translate(lp(x)?, zero, 0.0)),
+        [x, y] => Some(TransformFunction::Translate(lp(x)?, lp(y)?, 0.0)),
+        [x, y, z] => Some(TransformFunction::Translate(lp(x)?, lp(y)?, length(z, ctx)?)),
+        _ => None,
+    }
+}
+
+/// `rotate`: `none`, or an angle, optionally with an axis before or after
+/// it: `x`, `y`, `z` or three numbers
+pub fn parse_rotate(value: &Value) -> Option<TransformFunction> {
+    let axis = |values: &[Value]| match values {
+        [Value::Keyword(keyword)] => match keyword.to_ascii_lowercase().as_str() {
+            "x" => Some([1.0, 0.0, 0.0]),
+            "y" => Some([0.0, 1.0, 0.0]),
+            "z" => Some([0.0, 0.0, 1.0]),
+            _ => None,
+        },
+        [x, y, z] => Some([number(x)?, number(y)?, number(z)?]),
+        _ => None,
+    };
+    match value.as_slice() {
+        [Value::None] => Some(TransformFunction::NO_ROTATE),
+        [angle] => Some(TransformFunction::Rotate([0.0, 0.0, 1.0], background::parse_angle(angle)?)),
+        [first, rest @ ..] if matches!(first, Value::Angle(_)) => {
+            Some(TransformFunction::Rotate(axis(rest)?, background::parse_angle(first)?))
+        }
+        [rest @ .., last] => Some(TransformFunction::Rotate(axis(rest)?, background::parse_angle(last)?)),
+        [] => None,
+    }
+}
+
+/// `scale`: `none`, or x, then optionally y and z; y is x if left out
+pub fn parse_scale(value: &Value) -> Option<TransformFunction> {
+    match value.as_slice() {
+        [Value::None] => Some(TransformFunction::NO_SCALE),
+        [x] => Some(TransformFunction::Scale(factor(x)?, factor(x)?, 1.0)),
+        [x, y] => Some(TransformFunction::Scale(factor(x)?, factor(y)?, 1.0)),
+        [x, y, z] => Some(TransformFunction::Scale(factor(x)?, factor(y)?, factor(z)?)),
+        _ => None,
+    }
+}
+
+/// `transform-origin`: a position of one or two values, then optionally
+/// a length along z
+pub fn parse_origin(value: &Value, ctx: &LengthContext) -> Option<TransformOrigin> {
+    let values = value.as_slice();
+    let (position, z) = match values {
+        [x, y, z] => (background::parse_position(&[x.clone(), y.clone()], ctx)?, length(z, ctx)?),
+        [_] | [_, _] => (background::parse_position(values, ctx)?, 0.0),
+        _ => return None,
+    };
+    Some(TransformOrigin { position, z })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::parse_css;
+
+    fn value(css_value: &str) -> Value {
+        let stylesheet = parse_css(&format!("a {{ x: {}; }}", css_value));
+        stylesheet.rules[0].declarations[0].value.clone()
+    }
+
+    fn list(css_value: &str) -> Option<Vec<TransformFunction>> {
+        parse_transform_list(&value(css_value), &LengthContext::new(800.0, 600.0))
+    }
+
+    #[test]
+    fn test_parse_transform_list() {
+        assert_eq!(list("none"), Some(Vec::new()));
+        assert_eq!(
+            list("translate(-50%, 10px) rotate(0.25turn) scaleY(2)"),
+            Some(vec![
+                TransformFunction::Translate(LengthOrPercentage::Percent(-50.0), LengthOrPercentage::Px(10.0), 0.0),
+                TransformFunction::Rotate([0.0, 0.0, 1.0], 90.0),
+                TransformFunction::Scale(1.0, 2.0, 1.0),
+            ])
+        );
+        assert_eq!(
+            list("matrix(1, 0, 0, 1, 5, 6) skewX(10deg) perspective(none)"),
+            Some(vec![
+                TransformFunction::Matrix(Transform::translate(5.0, 6.0, 0.0)),
+                TransformFunction::Skew(10.0, 0.0),
+                TransformFunction::Perspective(None),
+            ])
+        );
+        assert_eq!(list("translateZ(2em) rotate3d(1, 0, 0, 0)").unwrap()[0], TransformFunction::Translate(
+            LengthOrPercentage::Px(0.0),
+            LengthOrPercentage::Px(0.0),
+            32.0
+        ));
+
+        // Lengths need units, and z can't be a percentage
+        assert_eq!(list("translate(10)"), None);
+        assert_eq!(list("translate3d(0, 0, 10%)"), None);
+        assert_eq!(list("rotate(45deg) red"), None);
+        assert_eq!(list("matrix(1, 0, 0, 1)"), None);
+    }
+
+    #[test]
+    fn test_parse_individual_properties() {
+        let ctx = LengthContext::new(800.0, 600.0);
+        assert_eq!(parse_translate(&value("none"), &ctx), Some(TransformFunction::NO_TRANSLATE));
+        assert_eq!(
+            parse_translate(&value("10px 50%"), &ctx),
+            Some(TransformFunction::Translate(LengthOrPercentage::Px(10.0), LengthOrPercentage::Percent(50.0), 0.0))
+        );
+        assert_eq!(parse_rotate(&value("45deg")), Some(TransformFunction::Rotate([0.0, 0.0, 1.0], 45.0)));
+        assert_eq!(parse_rotate(&value("x 90deg")), Some(TransformFunction::Rotate([1.0, 0.0, 0.0], 90.0)));
+        assert_eq!(parse_rotate(&value("90deg 1 1 0")), Some(TransformFunction::Rotate([1.0, 1.0, 0.0], 90.0)));
+        assert_eq!(parse_rotate(&value("w 90deg")), None);
+        assert_eq!(parse_scale(&value("150%")), Some(TransformFunction::Scale(1.5, 1.5, 1.0)));
+        assert_eq!(parse_scale(&value("2 0.5")), Some(TransformFunction::Scale(2.0, 0.5, 1.0)));
+
+        let origin = parse_origin(&value("left top 10px"), &ctx).unwrap();
+        assert_eq!(origin.position.x.resolve(200.0), 0.0);
+        assert_eq!(origin.position.y.resolve(100.0), 0.0);
+        assert_eq!(origin.z, 10.0);
+        let origin = parse_origin(&value("bottom"), &ctx).unwrap();
+        assert_eq!((origin.position.x.resolve(200.0), origin.position.y.resolve(100.0)), (100.0, 100.0));
+    }
+
+    #[test]
+    fn test_box_transform() {
+        use crate::css::ComputedStyle;
+        use crate::render::painter::Rect;
+
+        let border_box = Rect::new(100.0, 50.0, 200.0, 100.0);
+        assert_eq!(ComputedStyle::default().transform_matrix(&border_box), None);
+
+        // Centered by half its own size
+        let style = ComputedStyle {
+            transform: list("translate(-50%, -50%)").unwrap(),
+            ..ComputedStyle::default()
+        };
+        let matrix = style.transform_matrix(&border_box).unwrap();
+        assert_eq!(matrix.as_translation(), Some((-100.0, -50.0)));
+
+        // `translate` and `scale` apply before the list, around the origin,
+        // which defaults to the middle of the box
+        let style = ComputedStyle {
+            translate: TransformFunction::Translate(LengthOrPercentage::Px(10.0), LengthOrPercentage::Px(0.0), 0.0),
+            scale: TransformFunction::Scale(2.0, 2.0, 1.0),
+            transform: list("translateX(5px)").unwrap(),
+            ..ComputedStyle::default()
+        };
+        let matrix = style.transform_matrix(&border_box).unwrap();
+        let (x, y) = matrix.map_point(100.0, 50.0).unwrap();
+        assert_eq!((x, y), (20.0, 0.0));
+        let (x, y) = matrix.map_point(200.0, 100.0).unwrap();
+        assert_eq!((x, y), (220.0, 100.0));
+
+        let style = ComputedStyle {
+            transform_origin: parse_origin(&value("top left"), &LengthContext::new(800.0, 600.0)).unwrap(),
+            ..style
+        };
+        let (x, y) = style.transform_matrix(&border_box).unwrap().map_point(100.0, 50.0).unwrap();
+        assert_eq!((x, y), (120.0, 50.0));
+    }
+}
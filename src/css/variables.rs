@@ -0,0 +1,220 @@
+//! CSS custom properties (`--name: ...`) and `var()` substitution.
+//!
+//! Custom property values and declarations that use `var()` are kept as
+//! raw token text by the parser, then resolved at computed-value time
+//! against the element's (inherited) custom properties.
+
+use cssparser::{ParseError, Parser, ParserInput, Token};
+use std::collections::{HashMap, HashSet};
+
+/// Resolved custom properties of an element, by name including the `--`
+pub type CustomProperties = HashMap<String, String>;
+
+/// Resolves `var()` references inside the custom properties an element
+/// declares itself (`declared`); inherited values are already resolved.
+/// Properties that are part of a reference cycle, or reference a missing
+/// property without a fallback, are removed (the guaranteed-invalid value).
+pub fn resolve_custom_properties(properties: &mut CustomProperties, declared: &[String]) {
+    let mut resolver = Resolver {
+        properties,
+        pending: declared.iter().cloned().collect(),
+        stack: Vec::new(),
+        cyclic: HashSet::new(),
+    };
+    for name in declared {
+        resolver.resolve(name);
+    }
+}
+
+struct Resolver<'a> {
+    properties: &'a mut CustomProperties,
+    /// Declared here and not resolved yet
+    pending: HashSet<String>,
+    /// Properties being resolved, to spot cycles
+    stack: Vec<String>,
+    cyclic: HashSet<String>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&mut self, name: &str) {
+        if !self.pending.remove(name) {
+            return;
+        }
+        let Some(raw) = self.properties.get(name).cloned() else {
+            return;
+        };
+
+        self.stack.push(name.to_string());
+        let resolved = substitute(&raw, &mut |reference| {
+            if let Some(start) = self.stack.iter().position(|n| n == reference) {
+                self.cyclic.extend(self.stack[start..].iter().cloned());
+                return None;
+            }
+            self.resolve(reference);
+            self.properties.get(reference).cloned()
+        });
+        self.stack.pop();
+
+        match resolved {
+            Some(value) if !self.cyclic.contains(name) => {
+                self.properties.insert(name.to_string(), value);
+            }
+            _ => {
+                self.properties.remove(name);
+            }
+        }
+    }
+}
+
+/// Replaces every `var()` in `raw` with the value `lookup` finds, or with
+/// its fallback. Returns `None` when a reference has neither, which makes
+/// the declaration invalid at computed-value time.
+pub fn substitute(raw: &str, lookup: &mut dyn FnMut(&str) -> Option<String>) -> Option<String> {
+    let mut input = ParserInput::new(raw);
+    let mut parser = Parser::new(&mut input);
+    let mut output = String::new();
+    substitute_tokens(&mut parser, lookup, &mut output).then(|| output.trim().to_string())
+}
+
+/// Whether a raw value references any custom property
+pub fn contains_var(raw: &str) -> bool {
+    let mut input = ParserInput::new(raw);
+    let mut parser = Parser::new(&mut input);
+    scan_for_var(&mut parser)
+}
+
+fn scan_for_var(parser: &mut Parser) -> bool {
+    while let Ok(token) = parser.next() {
+        match token.clone() {
+            Token::Function(name) if name.eq_ignore_ascii_case("var") => return true,
+            Token::Function(_) | Token::ParenthesisBlock | Token::SquareBracketBlock | Token::CurlyBracketBlock => {
+                let found = parser.parse_nested_block(|p| Ok::<_, ParseError<()>>(scan_for_var(p)));
+                if found.unwrap_or(false) {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+fn substitute_tokens(parser: &mut Parser, lookup: &mut dyn FnMut(&str) -> Option<String>, output: &mut String) -> bool {
+    loop {
+        let start = parser.position();
+        let token = match parser.next_including_whitespace_and_comments() {
+            Ok(token) => token.clone(),
+            Err(_) => return true,
+        };
+
+        let closing = match token {
+            Token::Function(name) if name.eq_ignore_ascii_case("var") => {
+                let value = parser
+                    .parse_nested_block(|p| Ok::<_, ParseError<()>>(substitute_var(p, lookup)))
+                    .ok()
+                    .flatten();
+                let Some(value) = value else {
+                    return false;
+                };
+                // Empty comments keep the value from merging with its neighbours
+                output.push_str("/**/");
+                output.push_str(&value);
+                output.push_str("/**/");
+                continue;
+            }
+            Token::Function(_) | Token::ParenthesisBlock => ')',
+            Token::SquareBracketBlock => ']',
+            Token::CurlyBracketBlock => '}',
+            _ => {
+                output.push_str(parser.slice_from(start));
+                continue;
+            }
+        };
+
+        // The opening of the block, then its contents with references replaced
+        output.push_str(parser.slice_from(start));
+        let substituted = parser.parse_nested_block(|p| Ok::<_, ParseError<()>>(substitute_tokens(p, lookup, output)));
+        if !substituted.unwrap_or(false) {
+            return false;
+        }
+        output.push(closing);
+    }
+}
+
+/// The contents of one `var(--name, fallback)`
+fn substitute_var(parser: &mut Parser, lookup: &mut dyn FnMut(&str) -> Option<String>) -> Option<String> {
+    let name = parser.expect_ident_cloned().ok()?;
+    if !name.starts_with("--") {
+        return None;
+    }
+    let has_fallback = parser.try_parse(|p| p.expect_comma()).is_ok();
+
+    if let Some(value) = lookup(&name) {
+        while parser.next().is_ok() {}
+        return Some(value);
+    }
+    if !has_fallback {
+        return None;
+    }
+    let mut fallback = String::new();
+    substitute_tokens(parser, lookup, &mut fallback).then(|| fallback.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(pairs: &[(&str, &str)]) -> CustomProperties {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn substitute_with(raw: &str, properties: &CustomProperties) -> Option<String> {
+        substitute(raw, &mut |name| properties.get(name).cloned())
+    }
+
+    #[test]
+    fn test_substitute_with_fallbacks() {
+        let props = properties(&[("--gap", "8px"), ("--accent", "#336699")]);
+
+        assert_eq!(substitute_with("var(--gap)", &props).as_deref(), Some("/**/8px/**/"));
+        assert_eq!(
+            substitute_with("var(--gap) solid var(--missing, red)", &props).as_deref(),
+            Some("/**/8px/**/ solid /**/red/**/")
+        );
+        assert_eq!(
+            substitute_with("calc(var(--gap) * 2)", &props).as_deref(),
+            Some("calc(/**/8px/**/ * 2)")
+        );
+        assert_eq!(
+            substitute_with("var(--missing, var(--accent))", &props).as_deref(),
+            Some("/**//**/#336699/**//**/")
+        );
+        assert_eq!(substitute_with("var(--missing)", &props), None);
+    }
+
+    #[test]
+    fn test_resolve_chains_and_cycles() {
+        let mut props = properties(&[
+            ("--base", "4px"),
+            ("--double", "calc(var(--base) * 2)"),
+            ("--a", "var(--b)"),
+            ("--b", "var(--a, 1px)"),
+            ("--uses-cycle", "var(--a, 3px)"),
+        ]);
+        let declared: Vec<String> = ["--double", "--a", "--b", "--uses-cycle"].iter().map(|s| s.to_string()).collect();
+
+        resolve_custom_properties(&mut props, &declared);
+
+        assert_eq!(props["--double"], "calc(/**/4px/**/ * 2)");
+        assert!(!props.contains_key("--a"));
+        assert!(!props.contains_key("--b"));
+        assert_eq!(props["--uses-cycle"], "/**/3px/**/");
+    }
+
+    #[test]
+    fn test_contains_var() {
+        assert!(contains_var("1px solid var(--c)"));
+        assert!(contains_var("rgb(0 0 var(--blue))"));
+        assert!(!contains_var("10px 20px"));
+    }
+}
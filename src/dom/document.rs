@@ -1,9 +1,15 @@
+use super::mutation::{MutationQueue, MutationRecord, SubscriptionId};
 use super::node::{Node, NodeData, NodeId};
+use super::state::ElementState;
+use std::collections::HashMap;
 
 #[derive(Debug)]
 pub struct Document {
     nodes: Vec<Node>,
     pub root: NodeId,
+    mutations: MutationQueue,
+    /// Interaction state of elements that have any
+    states: HashMap<NodeId, ElementState>,
 }
 
 impl Document {
@@ -12,6 +18,61 @@ impl Document {
         Self {
             nodes: vec![root],
             root: 0,
+            mutations: MutationQueue::default(),
+            states: HashMap::new(),
+        }
+    }
+
+    /// Starts recording mutation records for a new consumer
+    pub fn subscribe_mutations(&mut self) -> SubscriptionId {
+        self.mutations.subscribe()
+    }
+
+    pub fn unsubscribe_mutations(&mut self, id: SubscriptionId) {
+        self.mutations.unsubscribe(id);
+    }
+
+    /// Returns and clears the records queued for `id` since the last call
+    pub fn take_mutations(&mut self, id: SubscriptionId) -> Vec<MutationRecord> {
+        self.mutations.take(id)
+    }
+
+    pub fn element_state(&self, node_id: NodeId) -> ElementState {
+        self.states.get(&node_id).copied().unwrap_or_default()
+    }
+
+    /// Adds or removes `flags` on an element. Returns true when its state
+    /// changed.
+    pub fn set_element_state(&mut self, node_id: NodeId, flags: ElementState, on: bool) -> bool {
+        let state = self.states.entry(node_id).or_default();
+        let before = *state;
+        if on {
+            state.insert(flags);
+        } else {
+            state.remove(flags);
+        }
+        let changed = *state != before;
+        if state.is_empty() {
+            self.states.remove(&node_id);
+        }
+        changed
+    }
+
+    /// Elements that have all of `flags`
+    pub fn elements_with_state(&self, flags: ElementState) -> Vec<NodeId> {
+        let mut nodes: Vec<NodeId> = self
+            .states
+            .iter()
+            .filter(|(_, state)| state.contains(flags))
+            .map(|(&id, _)| id)
+            .collect();
+        nodes.sort_unstable();
+        nodes
+    }
+
+    fn record(&mut self, record: impl FnOnce() -> MutationRecord) {
+        if self.mutations.is_recording() {
+            self.mutations.push(record());
         }
     }
 
@@ -37,10 +98,72 @@ impl Document {
     }
 
     pub fn append_child(&mut self, parent_id: NodeId, child_id: NodeId) {
-        if parent_id < self.nodes.len() && child_id < self.nodes.len() {
-            self.nodes[child_id].parent = Some(parent_id);
-            self.nodes[parent_id].children.push(child_id);
+        self.insert_before(parent_id, child_id, None);
+    }
+
+    /// Inserts `child_id` before `reference` (or last when `None`), first
+    /// detaching it from its current parent; returns false without changing
+    /// anything if `reference` is not a child of `parent_id`
+    pub fn insert_before(&mut self, parent_id: NodeId, child_id: NodeId, reference: Option<NodeId>) -> bool {
+        if parent_id >= self.nodes.len() || child_id >= self.nodes.len() || child_id == parent_id {
+            return false;
+        }
+        // A node cannot become its own descendant
+        if self.contains(child_id, parent_id) {
+            return false;
+        }
+        if reference.is_some_and(|r| self.nodes.get(r).and_then(|node| node.parent) != Some(parent_id)) {
+            return false;
+        }
+        if reference == Some(child_id) {
+            return true;
+        }
+        if let Some(old_parent) = self.nodes[child_id].parent {
+            self.remove_child(old_parent, child_id);
+        }
+
+        let children = &self.nodes[parent_id].children;
+        let index = reference
+            .and_then(|r| children.iter().position(|&c| c == r))
+            .unwrap_or(children.len());
+        let previous_sibling = index.checked_sub(1).map(|i| children[i]);
+        let next_sibling = children.get(index).copied();
+
+        self.nodes[child_id].parent = Some(parent_id);
+        self.nodes[parent_id].children.insert(index, child_id);
+        self.record(|| MutationRecord::child_list(parent_id, vec![child_id], Vec::new(), previous_sibling, next_sibling));
+        true
+    }
+
+    /// Detaches `child_id` from `parent_id`; returns false if it was not a child
+    pub fn remove_child(&mut self, parent_id: NodeId, child_id: NodeId) -> bool {
+        let Some(index) = self
+            .nodes
+            .get(parent_id)
+            .and_then(|parent| parent.children.iter().position(|&c| c == child_id))
+        else {
+            return false;
+        };
+
+        let children = &mut self.nodes[parent_id].children;
+        children.remove(index);
+        let previous_sibling = index.checked_sub(1).map(|i| children[i]);
+        let next_sibling = children.get(index).copied();
+        self.nodes[child_id].parent = None;
+        self.record(|| MutationRecord::child_list(parent_id, Vec::new(), vec![child_id], previous_sibling, next_sibling));
+        true
+    }
+
+    /// Whether `node_id` is `ancestor_id` or one of its descendants
+    pub fn contains(&self, ancestor_id: NodeId, node_id: NodeId) -> bool {
+        let mut current = Some(node_id);
+        while let Some(id) = current {
+            if id == ancestor_id {
+                return true;
+            }
+            current = self.parent(id);
         }
+        false
     }
 
     pub fn get_node(&self, id: NodeId) -> Option<&Node> {
@@ -52,11 +175,36 @@ impl Document {
     }
 
     pub fn set_attribute(&mut self, node_id: NodeId, name: &str, value: &str) {
-        if let Some(node) = self.nodes.get_mut(node_id) {
-            if let Some(elem) = node.as_element_mut() {
-                elem.attributes.insert(name.to_string(), value.to_string());
-            }
+        let Some(elem) = self.nodes.get_mut(node_id).and_then(|n| n.as_element_mut()) else {
+            return;
+        };
+        let old_value = elem.attributes.insert(name.to_string(), value.to_string());
+        if name == "class" {
+            elem.invalidate_class_cache();
         }
+        self.record(|| MutationRecord::attributes(node_id, name, old_value));
+    }
+
+    pub fn remove_attribute(&mut self, node_id: NodeId, name: &str) {
+        let Some(elem) = self.nodes.get_mut(node_id).and_then(|n| n.as_element_mut()) else {
+            return;
+        };
+        let Some(old_value) = elem.attributes.remove(name) else {
+            return;
+        };
+        if name == "class" {
+            elem.invalidate_class_cache();
+        }
+        self.record(|| MutationRecord::attributes(node_id, name, Some(old_value)));
+    }
+
+    /// Replaces the text of a text or comment node
+    pub fn set_character_data(&mut self, node_id: NodeId, data: &str) {
+        let old_value = match self.nodes.get_mut(node_id).map(|n| &mut n.data) {
+            Some(NodeData::Text(text)) | Some(NodeData::Comment(text)) => std::mem::replace(text, data.to_string()),
+            _ => return,
+        };
+        self.record(|| MutationRecord::character_data(node_id, old_value));
     }
 
     pub fn get_element_by_id(&self, id: &str) -> Option<NodeId> {
@@ -145,11 +293,29 @@ impl Document {
     }
 
     pub fn set_text_content(&mut self, node_id: NodeId, content: &str) {
-        if let Some(node) = self.get_node_mut(node_id) {
-            node.children.clear();
+        if matches!(self.nodes.get(node_id).map(|n| &n.data), Some(NodeData::Text(_)) | Some(NodeData::Comment(_))) {
+            self.set_character_data(node_id, content);
+            return;
+        }
+        let Some(node) = self.nodes.get_mut(node_id) else {
+            return;
+        };
+
+        // Replacing all children is a single childList change
+        let removed = std::mem::take(&mut node.children);
+        for &child_id in &removed {
+            self.nodes[child_id].parent = None;
+        }
+        let mut added = Vec::new();
+        if !content.is_empty() {
+            let text_id = self.create_text(content);
+            self.nodes[text_id].parent = Some(node_id);
+            self.nodes[node_id].children.push(text_id);
+            added.push(text_id);
+        }
+        if !removed.is_empty() || !added.is_empty() {
+            self.record(|| MutationRecord::child_list(node_id, added, removed, None, None));
         }
-        let text_id = self.create_text(content);
-        self.append_child(node_id, text_id);
     }
 
     /// Get the 1-based index of an element among its element siblings
@@ -180,6 +346,18 @@ impl Document {
         None
     }
 
+    /// The closest element sibling before this node
+    pub fn previous_element_sibling(&self, node_id: NodeId) -> Option<NodeId> {
+        let parent_id = self.get_node(node_id)?.parent?;
+        let siblings = self.children(parent_id);
+        let position = siblings.iter().position(|&sibling| sibling == node_id)?;
+        siblings[..position]
+            .iter()
+            .rev()
+            .copied()
+            .find(|&sibling| self.get_node(sibling).is_some_and(|node| node.is_element()))
+    }
+
     /// Check if node is the last element child of its parent
     pub fn is_last_element_child(&self, node_id: NodeId) -> bool {
         let node = match self.get_node(node_id) {
@@ -1,7 +1,11 @@
 pub mod document;
+pub mod mutation;
 pub mod node;
 pub mod parser;
+pub mod state;
 
 pub use document::Document;
+pub use mutation::{MutationKind, MutationRecord, SubscriptionId};
 pub use node::{Node, NodeData, NodeId};
 pub use parser::parse_html;
+pub use state::ElementState;
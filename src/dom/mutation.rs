@@ -0,0 +1,134 @@
+use super::node::NodeId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MutationKind {
+    ChildList,
+    Attributes,
+    CharacterData,
+}
+
+/// One DOM change, shaped like the DOM's `MutationRecord`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MutationRecord {
+    pub kind: MutationKind,
+    pub target: NodeId,
+    pub added_nodes: Vec<NodeId>,
+    pub removed_nodes: Vec<NodeId>,
+    pub previous_sibling: Option<NodeId>,
+    pub next_sibling: Option<NodeId>,
+    pub attribute_name: Option<String>,
+    /// Previous attribute value or text, before the change
+    pub old_value: Option<String>,
+}
+
+impl MutationRecord {
+    pub fn child_list(
+        target: NodeId,
+        added_nodes: Vec<NodeId>,
+        removed_nodes: Vec<NodeId>,
+        previous_sibling: Option<NodeId>,
+        next_sibling: Option<NodeId>,
+    ) -> Self {
+        Self {
+            kind: MutationKind::ChildList,
+            target,
+            added_nodes,
+            removed_nodes,
+            previous_sibling,
+            next_sibling,
+            attribute_name: None,
+            old_value: None,
+        }
+    }
+
+    pub fn attributes(target: NodeId, name: &str, old_value: Option<String>) -> Self {
+        Self {
+            kind: MutationKind::Attributes,
+            attribute_name: Some(name.to_string()),
+            old_value,
+            ..Self::child_list(target, Vec::new(), Vec::new(), None, None)
+        }
+    }
+
+    pub fn character_data(target: NodeId, old_value: String) -> Self {
+        Self {
+            kind: MutationKind::CharacterData,
+            old_value: Some(old_value),
+            ..Self::child_list(target, Vec::new(), Vec::new(), None, None)
+        }
+    }
+}
+
+/// Handle returned by `Document::subscribe_mutations`
+pub type SubscriptionId = usize;
+
+/// Queues of mutation records, one per subscriber. Nothing is recorded
+/// while there are no subscribers, so parsing a page costs nothing extra.
+#[derive(Debug, Default)]
+pub struct MutationQueue {
+    queues: Vec<Option<Vec<MutationRecord>>>,
+}
+
+impl MutationQueue {
+    pub fn subscribe(&mut self) -> SubscriptionId {
+        self.queues.push(Some(Vec::new()));
+        self.queues.len() - 1
+    }
+
+    pub fn unsubscribe(&mut self, id: SubscriptionId) {
+        if let Some(queue) = self.queues.get_mut(id) {
+            *queue = None;
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.queues.iter().any(Option::is_some)
+    }
+
+    pub fn push(&mut self, record: MutationRecord) {
+        for queue in self.queues.iter_mut().flatten() {
+            queue.push(record.clone());
+        }
+    }
+
+    /// Drains the records queued for one subscriber
+    pub fn take(&mut self, id: SubscriptionId) -> Vec<MutationRecord> {
+        self.queues
+            .get_mut(id)
+            .and_then(Option::as_mut)
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_subscriber_gets_every_record() {
+        let mut queue = MutationQueue::default();
+        let a = queue.subscribe();
+        let b = queue.subscribe();
+
+        queue.push(MutationRecord::attributes(1, "class", None));
+
+        assert_eq!(queue.take(a).len(), 1);
+        assert!(queue.take(a).is_empty());
+        assert_eq!(queue.take(b).len(), 1);
+    }
+
+    #[test]
+    fn test_no_recording_without_subscribers() {
+        let mut queue = MutationQueue::default();
+        assert!(!queue.is_recording());
+
+        let id = queue.subscribe();
+        assert!(queue.is_recording());
+
+        queue.unsubscribe(id);
+        queue.push(MutationRecord::character_data(1, "old".to_string()));
+        assert!(!queue.is_recording());
+        assert!(queue.take(id).is_empty());
+    }
+}
@@ -0,0 +1,69 @@
+use std::ops::BitOr;
+
+/// State an element is in because of user interaction or history, matched
+/// by the dynamic pseudo-classes (`:hover`, `:focus`, `:visited`, ...)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ElementState(u8);
+
+impl ElementState {
+    pub const EMPTY: Self = Self(0);
+    /// The pointer is over the element or one of its descendants
+    pub const HOVER: Self = Self(1);
+    /// The element or one of its descendants is being pressed
+    pub const ACTIVE: Self = Self(1 << 1);
+    pub const FOCUS: Self = Self(1 << 2);
+    /// Focused, and the focus should be drawn (keyboard focus, text entry)
+    pub const FOCUS_VISIBLE: Self = Self(1 << 3);
+    /// The element or one of its descendants has focus
+    pub const FOCUS_WITHIN: Self = Self(1 << 4);
+    /// A link whose target is in the session history
+    pub const VISITED: Self = Self(1 << 5);
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+}
+
+impl BitOr for ElementState {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags() {
+        let mut state = ElementState::HOVER | ElementState::FOCUS;
+        assert!(state.contains(ElementState::HOVER));
+        assert!(!state.contains(ElementState::HOVER | ElementState::ACTIVE));
+        assert!(state.intersects(ElementState::HOVER | ElementState::ACTIVE));
+
+        state.remove(ElementState::HOVER);
+        state.insert(ElementState::VISITED);
+        assert_eq!(state, ElementState::FOCUS | ElementState::VISITED);
+
+        state.remove(ElementState::FOCUS | ElementState::VISITED);
+        assert!(state.is_empty());
+    }
+}
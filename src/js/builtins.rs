@@ -0,0 +1,731 @@
+//! Built-in objects: Symbol, Map, Set, WeakMap, the iterator protocol and
+//! the reflective `Object.*` functions.
+//!
+//! Built-in methods are `Value::NativeFunction`s named after their spec
+//! path (`Map.prototype.set`) and dispatched by `Interpreter::call_builtin`.
+//! Instances keep their internal state in `JsObject::kind`.
+//!
+//! Generator objects are iterators whose `next(v)` resumes the generator
+//! body (see `generator.rs`).
+
+use super::generator::GeneratorState;
+use super::interpreter::{Interpreter, JsSymbol, Value};
+use super::object::{JsObject, Property, PropertySlot};
+use super::regexp::{self, RegExpData};
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+/// Prefix of property keys that name a symbol rather than a string
+pub const SYMBOL_KEY_PREFIX: &str = "@@symbol:";
+
+/// Internal slots of a `JsObject`
+#[derive(Debug, Clone, Default)]
+pub enum ObjectKind {
+    #[default]
+    Ordinary,
+    /// Insertion-ordered key/value pairs
+    Map(Vec<(Value, Value)>),
+    /// Insertion-ordered values
+    Set(Vec<Value>),
+    /// Entries whose keys do not keep their objects alive
+    WeakMap(Vec<(Weak<RefCell<JsObject>>, Value)>),
+    Iterator(IteratorState),
+    RegExp(Rc<RegExpData>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IterationKind {
+    Keys,
+    Values,
+    Entries,
+}
+
+#[derive(Debug, Clone)]
+pub struct IteratorState {
+    pub source: IteratorSource,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum IteratorSource {
+    Array(Rc<RefCell<Vec<Value>>>, IterationKind),
+    /// A Map or Set object, read live so entries added during iteration are visited
+    Collection(Rc<RefCell<JsObject>>, IterationKind),
+    Chars(Vec<char>),
+    Generator(GeneratorState),
+}
+
+/// Objects and symbols shared by every script run by one interpreter
+pub struct Intrinsics {
+    pub symbol_iterator: Rc<JsSymbol>,
+    pub map_prototype: Rc<RefCell<JsObject>>,
+    pub set_prototype: Rc<RefCell<JsObject>>,
+    pub weak_map_prototype: Rc<RefCell<JsObject>>,
+    pub iterator_prototype: Rc<RefCell<JsObject>>,
+    pub regexp_prototype: Rc<RefCell<JsObject>>,
+}
+
+impl Intrinsics {
+    pub fn new() -> Self {
+        let symbol_iterator = Rc::new(JsSymbol {
+            id: 0,
+            description: Some("Symbol.iterator".to_string()),
+        });
+        let iterator_key = symbol_iterator.property_key();
+
+        let map_prototype = prototype(
+            &[
+                ("get", "Map.prototype.get"),
+                ("set", "Map.prototype.set"),
+                ("has", "Map.prototype.has"),
+                ("delete", "Map.prototype.delete"),
+                ("clear", "Map.prototype.clear"),
+                ("forEach", "Map.prototype.forEach"),
+                ("keys", "Map.prototype.keys"),
+                ("values", "Map.prototype.values"),
+                ("entries", "Map.prototype.entries"),
+                (&iterator_key, "Map.prototype.entries"),
+            ],
+        );
+        define_getter(&map_prototype, "size", "get Map.prototype.size");
+        let set_prototype = prototype(
+            &[
+                ("add", "Set.prototype.add"),
+                ("has", "Set.prototype.has"),
+                ("delete", "Set.prototype.delete"),
+                ("clear", "Set.prototype.clear"),
+                ("forEach", "Set.prototype.forEach"),
+                ("keys", "Set.prototype.values"),
+                ("values", "Set.prototype.values"),
+                ("entries", "Set.prototype.entries"),
+                (&iterator_key, "Set.prototype.values"),
+            ],
+        );
+        define_getter(&set_prototype, "size", "get Set.prototype.size");
+        let weak_map_prototype = prototype(
+            &[
+                ("get", "WeakMap.prototype.get"),
+                ("set", "WeakMap.prototype.set"),
+                ("has", "WeakMap.prototype.has"),
+                ("delete", "WeakMap.prototype.delete"),
+            ],
+        );
+        let iterator_prototype = prototype(
+            &[
+                ("next", "Iterator.prototype.next"),
+                (&iterator_key, "Iterator.prototype.@@iterator"),
+            ],
+        );
+
+        Self {
+            symbol_iterator,
+            map_prototype,
+            set_prototype,
+            weak_map_prototype,
+            iterator_prototype,
+            regexp_prototype: regexp::regexp_prototype(),
+        }
+    }
+}
+
+impl Default for Intrinsics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds a prototype whose methods are non-enumerable, as for built-in classes
+pub(crate) fn prototype(methods: &[(&str, &str)]) -> Rc<RefCell<JsObject>> {
+    let mut object = JsObject::new();
+    for (key, native) in methods {
+        object.define(key.to_string(), Property::hidden(Value::NativeFunction(native.to_string())));
+    }
+    Rc::new(RefCell::new(object))
+}
+
+pub(crate) fn define_getter(object: &Rc<RefCell<JsObject>>, key: &str, native: &str) {
+    let getter = Value::NativeFunction(native.to_string());
+    object
+        .borrow_mut()
+        .define(key.to_string(), Property::accessor(Some(getter), None, false));
+}
+
+/// The object holding a value's own properties, if it can have any
+fn property_holder(value: &Value) -> Option<Rc<RefCell<JsObject>>> {
+    match value {
+        Value::Object(obj) => Some(obj.clone()),
+        Value::Function(func) => Some(func.object.clone()),
+        _ => None,
+    }
+}
+
+fn new_object(object: JsObject) -> Value {
+    Value::Object(Rc::new(RefCell::new(object)))
+}
+
+fn new_array(items: Vec<Value>) -> Value {
+    Value::Array(Rc::new(RefCell::new(items)))
+}
+
+/// The object `Object.getOwnPropertyDescriptor` returns
+fn descriptor_object(property: &Property) -> Value {
+    let mut object = JsObject::new();
+    match &property.slot {
+        PropertySlot::Data(value) => {
+            object.set("value".to_string(), value.clone());
+            object.set("writable".to_string(), Value::Boolean(property.writable));
+        }
+        PropertySlot::Accessor { get, set } => {
+            object.set("get".to_string(), get.clone().unwrap_or(Value::Undefined));
+            object.set("set".to_string(), set.clone().unwrap_or(Value::Undefined));
+        }
+    }
+    object.set("enumerable".to_string(), Value::Boolean(property.enumerable));
+    object.set("configurable".to_string(), Value::Boolean(property.configurable));
+    new_object(object)
+}
+
+fn iter_result(value: Value, done: bool) -> Value {
+    let mut object = JsObject::new();
+    object.set("value".to_string(), value);
+    object.set("done".to_string(), Value::Boolean(done));
+    Value::Object(Rc::new(RefCell::new(object)))
+}
+
+fn pair(first: Value, second: Value) -> Value {
+    Value::Array(Rc::new(RefCell::new(vec![first, second])))
+}
+
+fn arg(args: &[Value], index: usize) -> Value {
+    args.get(index).cloned().unwrap_or(Value::Undefined)
+}
+
+fn this_object(this: &Value) -> Option<Rc<RefCell<JsObject>>> {
+    match this {
+        Value::Object(obj) => Some(obj.clone()),
+        _ => None,
+    }
+}
+
+/// SameValueZero comparison used for Map keys and Set values
+pub fn same_value_zero(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Undefined, Value::Undefined) | (Value::Null, Value::Null) => true,
+        (Value::Boolean(a), Value::Boolean(b)) => a == b,
+        (Value::Number(a), Value::Number(b)) => a == b || (a.is_nan() && b.is_nan()),
+        (Value::String(a), Value::String(b)) => a == b,
+        (Value::Symbol(a), Value::Symbol(b)) => Rc::ptr_eq(a, b),
+        (Value::Object(a), Value::Object(b)) => Rc::ptr_eq(a, b),
+        (Value::Array(a), Value::Array(b)) => Rc::ptr_eq(a, b),
+        _ => false,
+    }
+}
+
+/// Map keys normalize -0 to +0
+fn normalize_key(key: Value) -> Value {
+    match key {
+        // -0 + 0 is +0
+        Value::Number(n) => Value::Number(n + 0.0),
+        other => other,
+    }
+}
+
+impl Interpreter {
+    /// Static members of native constructors, e.g. `Symbol.iterator`
+    pub(crate) fn native_constant(&self, name: &str, key: &str) -> Option<Value> {
+        match (name, key) {
+            ("Symbol", "iterator") => Some(Value::Symbol(self.intrinsics.symbol_iterator.clone())),
+            _ => None,
+        }
+    }
+
+    /// `new Map(...)`, `new Set(...)`, `new WeakMap(...)` and `new RegExp(...)`
+    pub(crate) fn construct_native(&mut self, name: &str, args: Vec<Value>) -> Value {
+        let (prototype, kind) = match name {
+            "RegExp" => return self.call_regexp_builtin(name, Value::Undefined, args).unwrap_or(Value::Null),
+            "Map" => (self.intrinsics.map_prototype.clone(), ObjectKind::Map(Vec::new())),
+            "Set" => (self.intrinsics.set_prototype.clone(), ObjectKind::Set(Vec::new())),
+            "WeakMap" => (self.intrinsics.weak_map_prototype.clone(), ObjectKind::WeakMap(Vec::new())),
+            _ => return self.construct_host(name, &args),
+        };
+        let object = Value::Object(Rc::new(RefCell::new(JsObject::with_prototype(prototype, kind))));
+
+        let initial = arg(&args, 0);
+        if matches!(initial, Value::Undefined | Value::Null) {
+            return object;
+        }
+
+        let adder = if name == "Set" {
+            "Set.prototype.add".to_string()
+        } else {
+            format!("{}.prototype.set", name)
+        };
+        for item in self.collect_iterable(&initial) {
+            let item_args = if name == "Set" {
+                vec![item]
+            } else {
+                vec![self.get_index_value(&item, 0), self.get_index_value(&item, 1)]
+            };
+            self.call_builtin(&adder, object.clone(), item_args);
+        }
+
+        object
+    }
+
+    fn get_index_value(&mut self, value: &Value, index: usize) -> Value {
+        self.get_property(value, &index.to_string())
+    }
+
+    /// Dispatches built-in methods; returns `None` for names it does not own
+    pub(crate) fn call_builtin(&mut self, name: &str, this: Value, args: Vec<Value>) -> Option<Value> {
+        let result = match name {
+            "Symbol" => {
+                let description = match arg(&args, 0) {
+                    Value::Undefined => None,
+                    other => Some(self.to_string_value(&other)),
+                };
+                let symbol = JsSymbol {
+                    id: self.next_symbol_id,
+                    description,
+                };
+                self.next_symbol_id += 1;
+                Value::Symbol(Rc::new(symbol))
+            }
+
+            "Map.prototype.get" => {
+                let obj = this_object(&this)?;
+                let key = arg(&args, 0);
+                let obj = obj.borrow();
+                match &obj.kind {
+                    ObjectKind::Map(entries) => entries
+                        .iter()
+                        .find(|(k, _)| same_value_zero(k, &key))
+                        .map(|(_, v)| v.clone())
+                        .unwrap_or(Value::Undefined),
+                    _ => Value::Undefined,
+                }
+            }
+            "Map.prototype.set" => {
+                let obj = this_object(&this)?;
+                let key = normalize_key(arg(&args, 0));
+                let value = arg(&args, 1);
+                if let ObjectKind::Map(entries) = &mut obj.borrow_mut().kind {
+                    match entries.iter_mut().find(|(k, _)| same_value_zero(k, &key)) {
+                        Some(entry) => entry.1 = value,
+                        None => entries.push((key, value)),
+                    }
+                }
+                this
+            }
+            "Map.prototype.has" | "Set.prototype.has" => {
+                let obj = this_object(&this)?;
+                let key = arg(&args, 0);
+                let obj = obj.borrow();
+                Value::Boolean(match &obj.kind {
+                    ObjectKind::Map(entries) => entries.iter().any(|(k, _)| same_value_zero(k, &key)),
+                    ObjectKind::Set(values) => values.iter().any(|v| same_value_zero(v, &key)),
+                    _ => false,
+                })
+            }
+            "Map.prototype.delete" | "Set.prototype.delete" => {
+                let obj = this_object(&this)?;
+                let key = arg(&args, 0);
+                let mut obj = obj.borrow_mut();
+                let position = match &obj.kind {
+                    ObjectKind::Map(entries) => entries.iter().position(|(k, _)| same_value_zero(k, &key)),
+                    ObjectKind::Set(values) => values.iter().position(|v| same_value_zero(v, &key)),
+                    _ => None,
+                };
+                match (&mut obj.kind, position) {
+                    (ObjectKind::Map(entries), Some(i)) => {
+                        entries.remove(i);
+                    }
+                    (ObjectKind::Set(values), Some(i)) => {
+                        values.remove(i);
+                    }
+                    _ => {}
+                }
+                Value::Boolean(position.is_some())
+            }
+            "Map.prototype.clear" | "Set.prototype.clear" => {
+                let obj = this_object(&this)?;
+                match &mut obj.borrow_mut().kind {
+                    ObjectKind::Map(entries) => entries.clear(),
+                    ObjectKind::Set(values) => values.clear(),
+                    _ => {}
+                }
+                Value::Undefined
+            }
+            "Map.prototype.forEach" | "Set.prototype.forEach" => {
+                let obj = this_object(&this)?;
+                let callback = arg(&args, 0);
+                // Snapshot so the callback may mutate the collection
+                let entries: Vec<(Value, Value)> = match &obj.borrow().kind {
+                    ObjectKind::Map(entries) => entries.clone(),
+                    ObjectKind::Set(values) => values.iter().map(|v| (v.clone(), v.clone())).collect(),
+                    _ => Vec::new(),
+                };
+                for (key, value) in entries {
+                    self.call_value(&callback, Value::Undefined, vec![value, key, this.clone()]);
+                }
+                Value::Undefined
+            }
+            "Map.prototype.keys" => self.collection_iterator(&this, IterationKind::Keys)?,
+            "Map.prototype.values" | "Set.prototype.values" => {
+                self.collection_iterator(&this, IterationKind::Values)?
+            }
+            "Map.prototype.entries" | "Set.prototype.entries" => {
+                self.collection_iterator(&this, IterationKind::Entries)?
+            }
+
+            "Set.prototype.add" => {
+                let obj = this_object(&this)?;
+                let value = normalize_key(arg(&args, 0));
+                if let ObjectKind::Set(values) = &mut obj.borrow_mut().kind {
+                    if !values.iter().any(|v| same_value_zero(v, &value)) {
+                        values.push(value);
+                    }
+                }
+                this
+            }
+
+            "WeakMap.prototype.get" | "WeakMap.prototype.has" | "WeakMap.prototype.delete" => {
+                let obj = this_object(&this)?;
+                let key = match arg(&args, 0) {
+                    Value::Object(key) => key,
+                    _ if name.ends_with("get") => return Some(Value::Undefined),
+                    _ => return Some(Value::Boolean(false)),
+                };
+                let mut obj = obj.borrow_mut();
+                let ObjectKind::WeakMap(entries) = &mut obj.kind else {
+                    return Some(Value::Undefined);
+                };
+                let position = entries.iter().position(|(k, _)| std::ptr::eq(k.as_ptr(), Rc::as_ptr(&key)));
+                match name {
+                    "WeakMap.prototype.get" => position.map(|i| entries[i].1.clone()).unwrap_or(Value::Undefined),
+                    "WeakMap.prototype.has" => Value::Boolean(position.is_some()),
+                    _ => {
+                        if let Some(i) = position {
+                            entries.remove(i);
+                        }
+                        Value::Boolean(position.is_some())
+                    }
+                }
+            }
+            "WeakMap.prototype.set" => {
+                let obj = this_object(&this)?;
+                // Only objects can be weakly held
+                let Value::Object(key) = arg(&args, 0) else {
+                    return Some(this);
+                };
+                let value = arg(&args, 1);
+                if let ObjectKind::WeakMap(entries) = &mut obj.borrow_mut().kind {
+                    entries.retain(|(k, _)| k.strong_count() > 0);
+                    match entries.iter_mut().find(|(k, _)| std::ptr::eq(k.as_ptr(), Rc::as_ptr(&key))) {
+                        Some(entry) => entry.1 = value,
+                        None => entries.push((Rc::downgrade(&key), value)),
+                    }
+                }
+                this
+            }
+
+            "Array.prototype.keys" | "Array.prototype.values" | "Array.prototype.entries" => {
+                let array = match &this {
+                    Value::Array(array) => array.clone(),
+                    // Array-likes such as match results iterate a snapshot of their elements
+                    Value::Object(_) => {
+                        let length = self.get_property(&this, "length");
+                        let length = match length {
+                            Value::Number(n) if n > 0.0 => n as usize,
+                            _ => 0,
+                        };
+                        let items = (0..length).map(|i| self.get_index_value(&this, i)).collect();
+                        Rc::new(RefCell::new(items))
+                    }
+                    _ => return Some(Value::Undefined),
+                };
+                let kind = match name {
+                    "Array.prototype.keys" => IterationKind::Keys,
+                    "Array.prototype.values" => IterationKind::Values,
+                    _ => IterationKind::Entries,
+                };
+                self.create_iterator(IteratorSource::Array(array, kind))
+            }
+            "String.prototype.@@iterator" => {
+                let Value::String(s) = &this else {
+                    return Some(Value::Undefined);
+                };
+                self.create_iterator(IteratorSource::Chars(s.chars().collect()))
+            }
+
+            "get Map.prototype.size" | "get Set.prototype.size" => {
+                let obj = this_object(&this)?;
+                let size = match &obj.borrow().kind {
+                    ObjectKind::Map(entries) => entries.len(),
+                    ObjectKind::Set(values) => values.len(),
+                    _ => 0,
+                };
+                Value::Number(size as f64)
+            }
+
+            "Iterator.prototype.next" => self.iterator_next(&this, arg(&args, 0)),
+            "Iterator.prototype.@@iterator" => this,
+
+            "Object.keys" | "Object.values" | "Object.entries" => {
+                let target = arg(&args, 0);
+                let keys = self.own_enumerable_keys(&target);
+                let items = keys
+                    .into_iter()
+                    .map(|key| match name {
+                        "Object.keys" => Value::String(key),
+                        "Object.values" => self.get_property(&target, &key),
+                        _ => {
+                            let value = self.get_property(&target, &key);
+                            pair(Value::String(key), value)
+                        }
+                    })
+                    .collect();
+                new_array(items)
+            }
+            "Object.defineProperty" => {
+                let target = arg(&args, 0);
+                let key = self.property_key(&arg(&args, 1));
+                let descriptor = arg(&args, 2);
+                if let Some(obj) = property_holder(&target) {
+                    self.define_from_descriptor(&obj, key, &descriptor);
+                }
+                target
+            }
+            "Object.getOwnPropertyDescriptor" => {
+                let key = self.property_key(&arg(&args, 1));
+                let property = property_holder(&arg(&args, 0)).and_then(|obj| obj.borrow().get_own(&key).cloned());
+                match property {
+                    Some(property) => descriptor_object(&property),
+                    None => Value::Undefined,
+                }
+            }
+            "Object.getPrototypeOf" => {
+                let prototype = property_holder(&arg(&args, 0)).and_then(|obj| obj.borrow().prototype.clone());
+                prototype.map(Value::Object).unwrap_or(Value::Null)
+            }
+            "Object.create" => {
+                let mut object = JsObject::new();
+                if let Value::Object(prototype) = arg(&args, 0) {
+                    object.prototype = Some(prototype);
+                }
+                new_object(object)
+            }
+
+            _ => return self.call_regexp_builtin(name, this, args),
+        };
+
+        Some(result)
+    }
+
+    fn collection_iterator(&self, this: &Value, kind: IterationKind) -> Option<Value> {
+        let obj = this_object(this)?;
+        Some(self.create_iterator(IteratorSource::Collection(obj, kind)))
+    }
+
+    pub(crate) fn create_iterator(&self, source: IteratorSource) -> Value {
+        let state = IteratorState { source, index: 0 };
+        let object = JsObject::with_prototype(self.intrinsics.iterator_prototype.clone(), ObjectKind::Iterator(state));
+        Value::Object(Rc::new(RefCell::new(object)))
+    }
+
+    fn iterator_next(&mut self, this: &Value, sent: Value) -> Value {
+        let Some(obj) = this_object(this) else {
+            return iter_result(Value::Undefined, true);
+        };
+
+        let is_generator = matches!(
+            &obj.borrow().kind,
+            ObjectKind::Iterator(IteratorState {
+                source: IteratorSource::Generator(_),
+                ..
+            })
+        );
+        if is_generator {
+            let (value, done) = self.generator_next(&obj, sent);
+            return iter_result(value, done);
+        }
+
+        let mut obj = obj.borrow_mut();
+        let ObjectKind::Iterator(state) = &mut obj.kind else {
+            return iter_result(Value::Undefined, true);
+        };
+        let index = state.index;
+        let next = match &mut state.source {
+            IteratorSource::Array(array, kind) => array.borrow().get(index).map(|value| match kind {
+                IterationKind::Keys => Value::Number(index as f64),
+                IterationKind::Values => value.clone(),
+                IterationKind::Entries => pair(Value::Number(index as f64), value.clone()),
+            }),
+            IteratorSource::Collection(collection, kind) => match &collection.borrow().kind {
+                ObjectKind::Map(entries) => entries.get(index).map(|(k, v)| match kind {
+                    IterationKind::Keys => k.clone(),
+                    IterationKind::Values => v.clone(),
+                    IterationKind::Entries => pair(k.clone(), v.clone()),
+                }),
+                ObjectKind::Set(values) => values.get(index).map(|v| match kind {
+                    IterationKind::Entries => pair(v.clone(), v.clone()),
+                    _ => v.clone(),
+                }),
+                _ => None,
+            },
+            IteratorSource::Chars(chars) => chars.get(index).map(|c| Value::String(c.to_string())),
+            IteratorSource::Generator(_) => None,
+        };
+
+        match next {
+            Some(value) => {
+                state.index += 1;
+                iter_result(value, false)
+            }
+            None => iter_result(Value::Undefined, true),
+        }
+    }
+
+    /// Calls `iterable[Symbol.iterator]()`, returning `None` if it is not iterable
+    pub(crate) fn get_iterator(&mut self, iterable: &Value) -> Option<Value> {
+        let key = self.intrinsics.symbol_iterator.property_key();
+        let method = self.get_property(iterable, &key);
+        if !matches!(method, Value::Function(_) | Value::NativeFunction(_)) {
+            return None;
+        }
+        match self.call_value(&method, iterable.clone(), Vec::new()) {
+            iterator @ Value::Object(_) => Some(iterator),
+            _ => None,
+        }
+    }
+
+    /// Advances an iterator, returning `None` once it reports `done`
+    pub(crate) fn iterator_step(&mut self, iterator: &Value) -> Option<Value> {
+        let next = self.get_property(iterator, "next");
+        let result = self.call_value(&next, iterator.clone(), Vec::new());
+        if !matches!(result, Value::Object(_)) {
+            return None;
+        }
+
+        let done = self.get_property(&result, "done");
+        if self.is_truthy(&done) {
+            None
+        } else {
+            Some(self.get_property(&result, "value"))
+        }
+    }
+
+    pub(crate) fn collect_iterable(&mut self, iterable: &Value) -> Vec<Value> {
+        let mut items = Vec::new();
+        if let Some(iterator) = self.get_iterator(iterable) {
+            while let Some(item) = self.iterator_step(&iterator) {
+                items.push(item);
+            }
+        }
+        items
+    }
+
+    /// Own enumerable string keys in property order, as `Object.keys` lists them
+    fn own_enumerable_keys(&self, value: &Value) -> Vec<String> {
+        match property_holder(value) {
+            Some(obj) => {
+                let obj = obj.borrow();
+                obj.own_keys()
+                    .into_iter()
+                    .filter(|key| !key.starts_with(SYMBOL_KEY_PREFIX))
+                    .filter(|key| obj.get_own(key).is_some_and(|p| p.enumerable))
+                    .collect()
+            }
+            None => self.enumerable_keys(value),
+        }
+    }
+
+    /// `Object.defineProperty`: absent descriptor fields keep the existing
+    /// property's attributes, or default to false for a new property
+    fn define_from_descriptor(&mut self, obj: &Rc<RefCell<JsObject>>, key: String, descriptor: &Value) {
+        let existing = obj.borrow().get_own(&key).cloned();
+        if existing.as_ref().is_some_and(|p| !p.configurable) {
+            log::warn!("Cannot redefine non-configurable property '{}'", key);
+            return;
+        }
+
+        let mut field = |name: &str| {
+            let has_field = property_holder(descriptor).is_some_and(|d| d.borrow().lookup(name).is_some());
+            has_field.then(|| self.get_property(descriptor, name))
+        };
+        let value = field("value");
+        let get = field("get");
+        let set = field("set");
+        let writable = field("writable");
+        let enumerable = field("enumerable");
+        let configurable = field("configurable");
+
+        let flag = |field: Option<Value>, current: Option<bool>| match field {
+            Some(value) => self.is_truthy(&value),
+            None => current.unwrap_or(false),
+        };
+        let enumerable = flag(enumerable, existing.as_ref().map(|p| p.enumerable));
+        let configurable = flag(configurable, existing.as_ref().map(|p| p.configurable));
+
+        let property = if get.is_some() || set.is_some() {
+            let (old_get, old_set) = match existing.map(|p| p.slot) {
+                Some(PropertySlot::Accessor { get, set }) => (get, set),
+                _ => (None, None),
+            };
+            let callable = |v: Value| matches!(v, Value::Function(_) | Value::NativeFunction(_)).then_some(v);
+            Property {
+                slot: PropertySlot::Accessor {
+                    get: get.map_or(old_get, callable),
+                    set: set.map_or(old_set, callable),
+                },
+                writable: false,
+                enumerable,
+                configurable,
+            }
+        } else {
+            let (old_value, old_writable) = match &existing {
+                Some(Property {
+                    slot: PropertySlot::Data(value),
+                    writable,
+                    ..
+                }) => (Some(value.clone()), Some(*writable)),
+                _ => (None, None),
+            };
+            Property {
+                slot: PropertySlot::Data(value.or(old_value).unwrap_or(Value::Undefined)),
+                writable: flag(writable, old_writable),
+                enumerable,
+                configurable,
+            }
+        };
+        obj.borrow_mut().define(key, property);
+    }
+
+    /// Keys visited by `for...in`: enumerable string keys, own first and then
+    /// inherited, skipping keys shadowed by an object nearer the start
+    pub(crate) fn enumerable_keys(&self, value: &Value) -> Vec<String> {
+        match value {
+            Value::Object(obj) => {
+                let mut keys = Vec::new();
+                let mut seen = std::collections::HashSet::new();
+                let mut current = Some(obj.clone());
+                while let Some(obj) = current {
+                    let obj = obj.borrow();
+                    for key in obj.own_keys() {
+                        if key.starts_with(SYMBOL_KEY_PREFIX) || !seen.insert(key.clone()) {
+                            continue;
+                        }
+                        if obj.get_own(&key).is_some_and(|p| p.enumerable) {
+                            keys.push(key);
+                        }
+                    }
+                    current = obj.prototype.clone();
+                }
+                keys
+            }
+            Value::Array(arr) => (0..arr.borrow().len()).map(|i| i.to_string()).collect(),
+            Value::String(s) => (0..s.chars().count()).map(|i| i.to_string()).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
@@ -1,26 +1,174 @@
-use super::interpreter::{Interpreter, JsObject, Value};
-use crate::dom::{Document, NodeId};
-use std::cell::RefCell;
+use super::interpreter::{Interpreter, NativeHost, Value};
+use super::object::{JsObject, Property};
+use crate::dom::{Document, MutationKind, MutationRecord, NodeData, NodeId, SubscriptionId};
+use crate::net::{CookieJar, CookieSource};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::rc::Rc;
+use std::sync::Arc;
+use url::Url;
+
+/// Hidden property linking a JS `MutationObserver` to its registration
+const OBSERVER_ID_KEY: &str = "%observerId";
+/// Read-only hidden property linking a node wrapper to its node
+const NODE_ID_KEY: &str = "%nodeId";
+
+/// What one `observe()` call asked to be told about
+#[derive(Debug, Clone, Default)]
+struct ObserveOptions {
+    child_list: bool,
+    attributes: bool,
+    character_data: bool,
+    subtree: bool,
+    attribute_old_value: bool,
+    character_data_old_value: bool,
+    attribute_filter: Option<Vec<String>>,
+}
+
+struct Observer {
+    id: usize,
+    object: Value,
+    callback: Value,
+    targets: Vec<(NodeId, ObserveOptions)>,
+    /// Records waiting for the next notification microtask
+    records: Vec<MutationRecord>,
+}
+
+impl Observer {
+    /// The record as this observer should see it, or `None` if no
+    /// registration is interested
+    fn filter(&self, document: &Document, record: &MutationRecord) -> Option<MutationRecord> {
+        let options = self.targets.iter().find_map(|(target, options)| {
+            let in_scope = record.target == *target || (options.subtree && document.contains(*target, record.target));
+            let wanted = match record.kind {
+                MutationKind::ChildList => options.child_list,
+                MutationKind::Attributes => {
+                    options.attributes
+                        && options.attribute_filter.as_ref().is_none_or(|filter| {
+                            record.attribute_name.as_ref().is_some_and(|name| filter.contains(name))
+                        })
+                }
+                MutationKind::CharacterData => options.character_data,
+            };
+            (in_scope && wanted).then_some(options)
+        })?;
+
+        let mut record = record.clone();
+        let keep_old_value = match record.kind {
+            MutationKind::Attributes => options.attribute_old_value,
+            MutationKind::CharacterData => options.character_data_old_value,
+            MutationKind::ChildList => false,
+        };
+        if !keep_old_value {
+            record.old_value = None;
+        }
+        Some(record)
+    }
+}
 
 pub struct DomBindings {
     document: Rc<RefCell<Document>>,
+    /// Shared prototype of node wrappers; its accessors read the live DOM
+    element_prototype: Rc<RefCell<JsObject>>,
+    observer_prototype: Rc<RefCell<JsObject>>,
+    /// One wrapper per node, so scripts can compare nodes by identity
+    wrappers: RefCell<HashMap<NodeId, Value>>,
+    observers: RefCell<Vec<Observer>>,
+    next_observer_id: Cell<usize>,
+    /// Mutation feed from the document, opened by the first `observe()`
+    subscription: Cell<Option<SubscriptionId>>,
+    notify_scheduled: Cell<bool>,
+    /// Jar and page URL behind `document.cookie`; without them it stays empty
+    cookies: Option<(Arc<CookieJar>, Url)>,
 }
 
 impl DomBindings {
     pub fn new(document: Rc<RefCell<Document>>) -> Self {
-        Self { document }
+        let mut element_prototype = JsObject::new();
+        for key in ["textContent", "innerHTML", "data"] {
+            let get = Value::NativeFunction(format!("get Element.prototype.{}", key));
+            let set = Value::NativeFunction(format!("set Element.prototype.{}", key));
+            element_prototype.define(key.to_string(), Property::accessor(Some(get), Some(set), false));
+        }
+        let parent_getter = Value::NativeFunction("get Node.prototype.parentNode".to_string());
+        element_prototype.define("parentNode".to_string(), Property::accessor(Some(parent_getter), None, false));
+        for method in ["appendChild", "insertBefore", "removeChild"] {
+            define_method(&mut element_prototype, method, &format!("Node.prototype.{}", method));
+        }
+        for method in ["getAttribute", "setAttribute", "removeAttribute"] {
+            define_method(&mut element_prototype, method, &format!("Element.prototype.{}", method));
+        }
+
+        let mut observer_prototype = JsObject::new();
+        for method in ["observe", "disconnect", "takeRecords"] {
+            define_method(&mut observer_prototype, method, &format!("MutationObserver.prototype.{}", method));
+        }
+
+        Self {
+            document,
+            element_prototype: Rc::new(RefCell::new(element_prototype)),
+            observer_prototype: Rc::new(RefCell::new(observer_prototype)),
+            wrappers: RefCell::new(HashMap::new()),
+            observers: RefCell::new(Vec::new()),
+            next_observer_id: Cell::new(0),
+            subscription: Cell::new(None),
+            notify_scheduled: Cell::new(false),
+            cookies: None,
+        }
     }
 
-    pub fn setup_globals(&self, interpreter: &mut Interpreter) {
+    pub fn with_cookies(mut self, cookie_jar: Arc<CookieJar>, url: Url) -> Self {
+        self.cookies = Some((cookie_jar, url));
+        self
+    }
+
+    pub fn setup_globals(self: &Rc<Self>, interpreter: &mut Interpreter) {
+        interpreter.register_host(self.clone());
+
         let document_obj = self.create_document_object();
         interpreter.global.borrow_mut().set("document".to_string(), document_obj);
+        interpreter
+            .global
+            .borrow_mut()
+            .set("MutationObserver".to_string(), Value::NativeFunction("MutationObserver".to_string()));
     }
 
     fn create_document_object(&self) -> Value {
+        let root = self.document.borrow().root;
+        let document_obj = self.node_object(root);
+        if let Value::Object(obj) = &document_obj {
+            let mut obj = obj.borrow_mut();
+            for method in ["getElementById", "createElement", "createTextNode"] {
+                define_method(&mut obj, method, &format!("Document.prototype.{}", method));
+            }
+            let body_getter = Value::NativeFunction("get Document.prototype.body".to_string());
+            obj.define("body".to_string(), Property::accessor(Some(body_getter), None, false));
+            let cookie_getter = Value::NativeFunction("get Document.prototype.cookie".to_string());
+            let cookie_setter = Value::NativeFunction("set Document.prototype.cookie".to_string());
+            obj.define("cookie".to_string(), Property::accessor(Some(cookie_getter), Some(cookie_setter), false));
+        }
+
+        document_obj
+    }
+
+    /// The wrapper object for a node, created on first use
+    fn node_object(&self, node_id: NodeId) -> Value {
+        if let Some(wrapper) = self.wrappers.borrow().get(&node_id) {
+            return wrapper.clone();
+        }
+
         let obj = Rc::new(RefCell::new(JsObject::new()));
+        obj.borrow_mut().prototype = Some(self.element_prototype.clone());
+        obj.borrow_mut()
+            .define(NODE_ID_KEY.to_string(), Property::internal(Value::Number(node_id as f64)));
 
-        Value::Object(obj)
+        let wrapper = Value::Object(obj);
+        self.wrappers.borrow_mut().insert(node_id, wrapper.clone());
+        wrapper
+    }
+
+    fn optional_node_object(&self, node_id: Option<NodeId>) -> Value {
+        node_id.map(|id| self.node_object(id)).unwrap_or(Value::Null)
     }
 
     pub fn get_element_by_id(&self, id: &str) -> Option<NodeId> {
@@ -41,15 +189,605 @@ impl DomBindings {
 
     pub fn set_style_property(&self, _node_id: NodeId, _property: &str, _value: &str) {
     }
+
+    fn observer_index(&self, this: &Value) -> Option<usize> {
+        let Value::Object(obj) = this else {
+            return None;
+        };
+        let Value::Number(id) = obj.borrow().get(OBSERVER_ID_KEY) else {
+            return None;
+        };
+        self.observers.borrow().iter().position(|o| o.id == id as usize)
+    }
+
+    fn read_observe_options(&self, interpreter: &mut Interpreter, options: &Value) -> ObserveOptions {
+        let mut flag = |name: &str| {
+            let value = interpreter.get_property(options, name);
+            (!matches!(value, Value::Undefined)).then(|| interpreter.is_truthy(&value))
+        };
+        let child_list = flag("childList").unwrap_or(false);
+        let attributes = flag("attributes");
+        let character_data = flag("characterData");
+        let subtree = flag("subtree").unwrap_or(false);
+        let attribute_old_value = flag("attributeOldValue");
+        let character_data_old_value = flag("characterDataOldValue");
+
+        let attribute_filter = match interpreter.get_property(options, "attributeFilter") {
+            Value::Array(names) => Some(names.borrow().iter().map(|n| interpreter.to_string_value(n)).collect()),
+            _ => None,
+        };
+
+        // Asking for old values or filtering attributes implies observing them
+        ObserveOptions {
+            child_list,
+            attributes: attributes
+                .unwrap_or(attribute_old_value.is_some() || attribute_filter.is_some()),
+            character_data: character_data.unwrap_or(character_data_old_value.is_some()),
+            subtree,
+            attribute_old_value: attribute_old_value.unwrap_or(false),
+            character_data_old_value: character_data_old_value.unwrap_or(false),
+            attribute_filter,
+        }
+    }
+
+    /// Hands new document mutations to interested observers and schedules
+    /// a notification microtask if any were queued
+    fn queue_mutation_records(&self, interpreter: &mut Interpreter) {
+        let Some(subscription) = self.subscription.get() else {
+            return;
+        };
+        let records = self.document.borrow_mut().take_mutations(subscription);
+        if records.is_empty() {
+            return;
+        }
+
+        let document = self.document.borrow();
+        let mut queued = false;
+        for observer in self.observers.borrow_mut().iter_mut() {
+            for record in &records {
+                if let Some(record) = observer.filter(&document, record) {
+                    observer.records.push(record);
+                    queued = true;
+                }
+            }
+        }
+
+        if queued && !self.notify_scheduled.replace(true) {
+            interpreter.enqueue_microtask(Value::NativeFunction("MutationObserver.notify".to_string()), Vec::new());
+        }
+    }
+
+    fn record_object(&self, record: &MutationRecord) -> Value {
+        let kind = match record.kind {
+            MutationKind::ChildList => "childList",
+            MutationKind::Attributes => "attributes",
+            MutationKind::CharacterData => "characterData",
+        };
+        let nodes = |ids: &[NodeId]| Value::Array(Rc::new(RefCell::new(ids.iter().map(|&id| self.node_object(id)).collect())));
+        let string_or_null = |s: &Option<String>| s.clone().map(Value::String).unwrap_or(Value::Null);
+
+        let mut obj = JsObject::new();
+        obj.set("type".to_string(), Value::String(kind.to_string()));
+        obj.set("target".to_string(), self.node_object(record.target));
+        obj.set("addedNodes".to_string(), nodes(&record.added_nodes));
+        obj.set("removedNodes".to_string(), nodes(&record.removed_nodes));
+        obj.set("previousSibling".to_string(), self.optional_node_object(record.previous_sibling));
+        obj.set("nextSibling".to_string(), self.optional_node_object(record.next_sibling));
+        obj.set("attributeName".to_string(), string_or_null(&record.attribute_name));
+        obj.set("oldValue".to_string(), string_or_null(&record.old_value));
+        Value::Object(Rc::new(RefCell::new(obj)))
+    }
+
+    fn records_array(&self, records: &[MutationRecord]) -> Value {
+        let items = records.iter().map(|r| self.record_object(r)).collect();
+        Value::Array(Rc::new(RefCell::new(items)))
+    }
+
+    /// The notification microtask: calls each observer with its queued records
+    fn notify_observers(&self, interpreter: &mut Interpreter) {
+        self.notify_scheduled.set(false);
+        let pending: Vec<(Value, Value, Vec<MutationRecord>)> = self
+            .observers
+            .borrow_mut()
+            .iter_mut()
+            .filter(|o| !o.records.is_empty())
+            .map(|o| (o.object.clone(), o.callback.clone(), std::mem::take(&mut o.records)))
+            .collect();
+
+        for (object, callback, records) in pending {
+            let records = self.records_array(&records);
+            interpreter.call_value(&callback, object.clone(), vec![records, object]);
+        }
+    }
+
+    fn call_observer_method(&self, interpreter: &mut Interpreter, name: &str, this: &Value, args: &[Value]) -> Value {
+        let Some(index) = self.observer_index(this) else {
+            return Value::Undefined;
+        };
+
+        match name {
+            "MutationObserver.prototype.observe" => {
+                let Some(target) = args.first().and_then(node_id_of) else {
+                    return Value::Undefined;
+                };
+                let options = args.get(1).cloned().unwrap_or(Value::Undefined);
+                let options = self.read_observe_options(interpreter, &options);
+                if !(options.child_list || options.attributes || options.character_data) {
+                    log::warn!("MutationObserver.observe needs childList, attributes or characterData");
+                    return Value::Undefined;
+                }
+
+                if self.subscription.get().is_none() {
+                    self.subscription.set(Some(self.document.borrow_mut().subscribe_mutations()));
+                }
+                let mut observers = self.observers.borrow_mut();
+                let targets = &mut observers[index].targets;
+                // Observing the same node again replaces its options
+                targets.retain(|(node, _)| *node != target);
+                targets.push((target, options));
+                Value::Undefined
+            }
+            "MutationObserver.prototype.disconnect" => {
+                let mut observers = self.observers.borrow_mut();
+                observers[index].targets.clear();
+                observers[index].records.clear();
+                Value::Undefined
+            }
+            "MutationObserver.prototype.takeRecords" => {
+                self.queue_mutation_records(interpreter);
+                let records = std::mem::take(&mut self.observers.borrow_mut()[index].records);
+                self.records_array(&records)
+            }
+            _ => Value::Undefined,
+        }
+    }
+}
+
+impl Drop for DomBindings {
+    fn drop(&mut self) {
+        if let Some(subscription) = self.subscription.get() {
+            if let Ok(mut document) = self.document.try_borrow_mut() {
+                document.unsubscribe_mutations(subscription);
+            }
+        }
+    }
+}
+
+impl NativeHost for DomBindings {
+    fn call(&self, interpreter: &mut Interpreter, name: &str, this: &Value, args: &[Value]) -> Option<Value> {
+        let string_arg = |interpreter: &Interpreter, index: usize| {
+            args.get(index).map(|a| interpreter.to_string_value(a)).unwrap_or_default()
+        };
+        let node_arg = |index: usize| args.get(index).and_then(node_id_of);
+
+        let result = match name {
+            "Document.prototype.getElementById" => {
+                let id = string_arg(interpreter, 0);
+                let node_id = self.get_element_by_id(&id);
+                self.optional_node_object(node_id)
+            }
+            "Document.prototype.createElement" => {
+                let tag_name = string_arg(interpreter, 0);
+                let node_id = self.document.borrow_mut().create_element(&tag_name);
+                self.node_object(node_id)
+            }
+            "Document.prototype.createTextNode" => {
+                let text = string_arg(interpreter, 0);
+                let node_id = self.document.borrow_mut().create_text(&text);
+                self.node_object(node_id)
+            }
+            "get Document.prototype.body" => {
+                let body = self.document.borrow().get_body();
+                self.optional_node_object(body)
+            }
+            "get Document.prototype.cookie" => {
+                let cookies = self.cookies.as_ref().map(|(jar, url)| jar.document_cookie(url));
+                Value::String(cookies.unwrap_or_default())
+            }
+            "set Document.prototype.cookie" => {
+                if let Some((jar, url)) = &self.cookies {
+                    jar.set_cookie(&string_arg(interpreter, 0), url, CookieSource::Script);
+                }
+                Value::Undefined
+            }
+            "get Node.prototype.parentNode" => {
+                let parent = node_id_of(this).and_then(|id| self.document.borrow().parent(id));
+                self.optional_node_object(parent)
+            }
+            "get Element.prototype.textContent" | "get Element.prototype.innerHTML" => match node_id_of(this) {
+                Some(node_id) => Value::String(self.get_inner_html(node_id)),
+                None => Value::Undefined,
+            },
+            "set Element.prototype.textContent" | "set Element.prototype.innerHTML" => {
+                if let Some(node_id) = node_id_of(this) {
+                    let text = string_arg(interpreter, 0);
+                    self.set_inner_html(node_id, &text);
+                }
+                Value::Undefined
+            }
+            "get Element.prototype.data" => {
+                let document = self.document.borrow();
+                match node_id_of(this).and_then(|id| document.get_node(id)).map(|n| &n.data) {
+                    Some(NodeData::Text(text)) | Some(NodeData::Comment(text)) => Value::String(text.clone()),
+                    _ => Value::Undefined,
+                }
+            }
+            "set Element.prototype.data" => {
+                if let Some(node_id) = node_id_of(this) {
+                    let text = string_arg(interpreter, 0);
+                    self.document.borrow_mut().set_character_data(node_id, &text);
+                }
+                Value::Undefined
+            }
+            "Node.prototype.appendChild" | "Node.prototype.insertBefore" => {
+                if let (Some(parent), Some(child)) = (node_id_of(this), node_arg(0)) {
+                    let reference = if name == "Node.prototype.insertBefore" {
+                        node_arg(1)
+                    } else {
+                        None
+                    };
+                    if !self.document.borrow_mut().insert_before(parent, child, reference) {
+                        interpreter.throw_error("NotFoundError", "The node before which to insert is not a child");
+                        return Some(Value::Undefined);
+                    }
+                }
+                args.first().cloned().unwrap_or(Value::Undefined)
+            }
+            "Node.prototype.removeChild" => {
+                if let (Some(parent), Some(child)) = (node_id_of(this), node_arg(0)) {
+                    if !self.document.borrow_mut().remove_child(parent, child) {
+                        interpreter.throw_error("NotFoundError", "The node to be removed is not a child of this node");
+                        return Some(Value::Undefined);
+                    }
+                }
+                args.first().cloned().unwrap_or(Value::Undefined)
+            }
+            "Element.prototype.getAttribute" => {
+                let name = string_arg(interpreter, 0);
+                let document = self.document.borrow();
+                let value = node_id_of(this)
+                    .and_then(|id| document.get_node(id))
+                    .and_then(|n| n.as_element())
+                    .and_then(|e| e.get_attribute(&name));
+                value.map(|v| Value::String(v.to_string())).unwrap_or(Value::Null)
+            }
+            "Element.prototype.setAttribute" => {
+                if let Some(node_id) = node_id_of(this) {
+                    let (name, value) = (string_arg(interpreter, 0), string_arg(interpreter, 1));
+                    self.document.borrow_mut().set_attribute(node_id, &name, &value);
+                }
+                Value::Undefined
+            }
+            "Element.prototype.removeAttribute" => {
+                if let Some(node_id) = node_id_of(this) {
+                    let name = string_arg(interpreter, 0);
+                    self.document.borrow_mut().remove_attribute(node_id, &name);
+                }
+                Value::Undefined
+            }
+            "MutationObserver.prototype.observe"
+            | "MutationObserver.prototype.disconnect"
+            | "MutationObserver.prototype.takeRecords" => self.call_observer_method(interpreter, name, this, args),
+            "MutationObserver.notify" => {
+                self.notify_observers(interpreter);
+                Value::Undefined
+            }
+            _ => return None,
+        };
+
+        self.queue_mutation_records(interpreter);
+        Some(result)
+    }
+
+    fn construct(&self, _interpreter: &mut Interpreter, name: &str, args: &[Value]) -> Option<Value> {
+        if name != "MutationObserver" {
+            return None;
+        }
+
+        let id = self.next_observer_id.get();
+        self.next_observer_id.set(id + 1);
+
+        let mut obj = JsObject::new();
+        obj.prototype = Some(self.observer_prototype.clone());
+        obj.define(OBSERVER_ID_KEY.to_string(), Property::internal(Value::Number(id as f64)));
+        let object = Value::Object(Rc::new(RefCell::new(obj)));
+
+        self.observers.borrow_mut().push(Observer {
+            id,
+            object: object.clone(),
+            callback: args.first().cloned().unwrap_or(Value::Undefined),
+            targets: Vec::new(),
+            records: Vec::new(),
+        });
+        Some(object)
+    }
+}
+
+fn define_method(object: &mut JsObject, key: &str, native: &str) {
+    object.define(key.to_string(), Property::hidden(Value::NativeFunction(native.to_string())));
+}
+
+fn node_id_of(value: &Value) -> Option<NodeId> {
+    match value {
+        Value::Object(obj) => match obj.borrow().get(NODE_ID_KEY) {
+            Value::Number(n) => Some(n as NodeId),
+            _ => None,
+        },
+        _ => None,
+    }
 }
 
 pub fn create_element_object(node_id: NodeId, bindings: &DomBindings) -> Value {
-    let obj = Rc::new(RefCell::new(JsObject::new()));
+    bindings.node_object(node_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::js::{Lexer, Parser};
+
+    fn run(bindings: &Rc<DomBindings>, source: &str) -> Value {
+        let mut interpreter = Interpreter::new();
+        bindings.setup_globals(&mut interpreter);
+        let tokens = Lexer::new(source).tokenize();
+        let program = Parser::new(tokens).parse();
+        interpreter.execute(&program)
+    }
+
+    fn document_with_paragraph() -> (Rc<RefCell<Document>>, NodeId) {
+        let mut document = Document::new();
+        let p = document.create_element("p");
+        document.set_attribute(p, "id", "greeting");
+        let text = document.create_text("hello");
+        document.append_child(document.root, p);
+        document.append_child(p, text);
+        (Rc::new(RefCell::new(document)), p)
+    }
+
+    #[test]
+    fn test_text_content_is_live() {
+        let (document, p) = document_with_paragraph();
+        let bindings = Rc::new(DomBindings::new(document.clone()));
+
+        let result = run(
+            &bindings,
+            r#"
+            let el = document.getElementById("greeting");
+            let before = el.textContent;
+            el.textContent = "bye";
+            before + " " + el.textContent;
+        "#,
+        );
+
+        assert!(matches!(result, Value::String(s) if s == "hello bye"));
+        assert_eq!(document.borrow().get_text_content(p), "bye");
+    }
+
+    #[test]
+    fn test_element_accessors_not_enumerable() {
+        let (document, _) = document_with_paragraph();
+        let bindings = Rc::new(DomBindings::new(document));
+
+        let result = run(&bindings, r#"Object.keys(document.getElementById("greeting")).length;"#);
+
+        assert!(matches!(result, Value::Number(n) if n == 0.0));
+    }
+
+    #[test]
+    fn test_missing_element_is_null() {
+        let (document, _) = document_with_paragraph();
+        let bindings = Rc::new(DomBindings::new(document));
+
+        let result = run(&bindings, r#"document.getElementById("nope");"#);
+
+        assert!(matches!(result, Value::Null));
+    }
+
+    #[test]
+    fn test_wrappers_keep_identity() {
+        let (document, _) = document_with_paragraph();
+        let bindings = Rc::new(DomBindings::new(document));
+
+        let result = run(
+            &bindings,
+            r#"document.getElementById("greeting") === document.getElementById("greeting");"#,
+        );
+
+        assert!(matches!(result, Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_node_id_is_not_exposed_or_writable() {
+        let (document, _) = document_with_paragraph();
+        let bindings = Rc::new(DomBindings::new(document));
+
+        let result = run(
+            &bindings,
+            r#"
+            var p = document.getElementById("greeting");
+            p["%nodeId"] = 0;
+            p.nodeId = 0;
+            var keys = Object.keys(p);
+            keys.length + ":" + keys[0] + ":" + p.getAttribute("id");
+            "#,
+        );
+
+        assert!(matches!(result, Value::String(s) if s == "1:nodeId:greeting"));
+    }
+
+    #[test]
+    fn test_insert_before_foreign_reference_throws() {
+        let (document, p) = document_with_paragraph();
+        let bindings = Rc::new(DomBindings::new(document.clone()));
+        let children_before = document.borrow().get_node(p).unwrap().children.clone();
+
+        let (_, reached) = run_and_get(
+            &bindings,
+            r#"
+            var p = document.getElementById("greeting");
+            var span = document.createElement("span");
+            var stray = document.createElement("em");
+            p.insertBefore(span, stray);
+            var reached = true;
+            "#,
+            "reached",
+        );
+
+        assert!(matches!(reached, Value::Undefined));
+        assert_eq!(document.borrow().get_node(p).unwrap().children, children_before);
+    }
+
+    #[test]
+    fn test_remove_non_child_throws_without_mutation() {
+        let (document, _) = document_with_paragraph();
+        let bindings = Rc::new(DomBindings::new(document));
+        let mut interpreter = Interpreter::new();
+        bindings.setup_globals(&mut interpreter);
+
+        let script = r#"
+            var p = document.getElementById("greeting");
+            var observer = new MutationObserver(function() {});
+            observer.observe(document, { childList: true, subtree: true });
+            p.removeChild(document.createElement("em"));
+            var reached = true;
+        "#;
+        interpreter.execute(&Parser::new(Lexer::new(script).tokenize()).parse());
+        let script = "var taken = observer.takeRecords().length;";
+        interpreter.execute(&Parser::new(Lexer::new(script).tokenize()).parse());
+
+        let global = interpreter.global.borrow();
+        assert!(global.get("reached").is_none());
+        assert!(matches!(global.get("taken"), Some(Value::Number(n)) if n == 0.0));
+    }
+
+    #[test]
+    fn test_document_cookie_hides_http_only() {
+        let (document, _) = document_with_paragraph();
+        let jar = Arc::new(CookieJar::new());
+        let url = Url::parse("https://example.com/").unwrap();
+        jar.set_cookie("session=secret; HttpOnly", &url, CookieSource::Http);
+        let bindings = Rc::new(DomBindings::new(document).with_cookies(Arc::clone(&jar), url.clone()));
+
+        let result = run(
+            &bindings,
+            r#"
+            document.cookie = "theme=dark";
+            document.cookie = "session=stolen";
+            document.cookie;
+        "#,
+        );
+
+        assert!(matches!(result, Value::String(s) if s == "theme=dark"));
+        assert_eq!(jar.cookie_header(&url, None).as_deref(), Some("session=secret; theme=dark"));
+    }
+
+    #[test]
+    fn test_observer_delivers_after_script() {
+        let (document, _) = document_with_paragraph();
+        let bindings = Rc::new(DomBindings::new(document));
 
-    obj.borrow_mut().set("nodeId".to_string(), Value::Number(node_id as f64));
+        let (_, log) = run_and_get(
+            &bindings,
+            r#"
+            var log = "";
+            var el = document.getElementById("greeting");
+            var observer = new MutationObserver(function(records, obs) {
+                log = log + "[" + (obs === observer) + "]";
+                for (const r of records) {
+                    log = log + r.type + ":" + r.attributeName + ":" + r.oldValue + ":" + r.addedNodes.length + ";";
+                }
+            });
+            observer.observe(el, { childList: true, attributes: true, attributeOldValue: true });
+            el.setAttribute("class", "big");
+            el.setAttribute("class", "small");
+            el.appendChild(document.createElement("span"));
+            log = log + "sync;";
+            "#,
+            "log",
+        );
 
-    let inner_html = bindings.get_inner_html(node_id);
-    obj.borrow_mut().set("innerHTML".to_string(), Value::String(inner_html));
+        assert!(matches!(log, Value::String(ref s)
+            if s == "sync;[true]attributes:class:null:0;attributes:class:big:0;childList:null:null:1;"));
+    }
+
+    #[test]
+    fn test_observer_subtree_and_filter() {
+        let (document, _) = document_with_paragraph();
+        let bindings = Rc::new(DomBindings::new(document));
+
+        let (_, count) = run_and_get(
+            &bindings,
+            r#"
+            var count = 0;
+            var observer = new MutationObserver(function(records) { count = count + records.length; });
+            observer.observe(document, { subtree: true, attributeFilter: ["title"], characterData: true });
+            var el = document.getElementById("greeting");
+            el.setAttribute("class", "ignored");
+            el.setAttribute("title", "seen");
+            el.firstText = document.createTextNode("x");
+            el.appendChild(el.firstText);
+            el.firstText.data = "y";
+            "#,
+            "count",
+        );
+
+        assert!(matches!(count, Value::Number(n) if n == 2.0));
+    }
+
+    #[test]
+    fn test_take_records_and_disconnect() {
+        let (document, _) = document_with_paragraph();
+        let bindings = Rc::new(DomBindings::new(document));
+
+        let (_, result) = run_and_get(
+            &bindings,
+            r#"
+            var calls = 0;
+            var observer = new MutationObserver(function() { calls = calls + 1; });
+            var el = document.getElementById("greeting");
+            observer.observe(el, { attributes: true });
+            el.setAttribute("a", "1");
+            var taken = observer.takeRecords().length;
+            observer.disconnect();
+            el.setAttribute("b", "2");
+            var result = taken + ":" + calls;
+            "#,
+            "result",
+        );
 
-    Value::Object(obj)
+        assert!(matches!(result, Value::String(ref s) if s == "1:0"));
+    }
+
+    #[test]
+    fn test_observer_id_is_not_writable() {
+        let (document, _) = document_with_paragraph();
+        let bindings = Rc::new(DomBindings::new(document));
+
+        let (_, count) = run_and_get(
+            &bindings,
+            r#"
+            var count = 0;
+            var el = document.getElementById("greeting");
+            var first = new MutationObserver(function(records) { count = count + records.length; });
+            var second = new MutationObserver(function() {});
+            first.observe(el, { attributes: true });
+            second["%observerId"] = first["%observerId"];
+            second.disconnect();
+            el.setAttribute("title", "still observed");
+            "#,
+            "count",
+        );
+
+        assert!(matches!(count, Value::Number(n) if n == 1.0));
+    }
+
+    fn run_and_get(bindings: &Rc<DomBindings>, source: &str, name: &str) -> (Value, Value) {
+        let mut interpreter = Interpreter::new();
+        bindings.setup_globals(&mut interpreter);
+        let tokens = Lexer::new(source).tokenize();
+        let program = Parser::new(tokens).parse();
+        let result = interpreter.execute(&program);
+        let value = interpreter.global.borrow().get(name).unwrap_or(Value::Undefined);
+        (result, value)
+    }
 }
@@ -0,0 +1,648 @@
+//! Generators that suspend at `yield` and resume where they left off.
+//!
+//! A generator body runs through `generator_statement` and `generator_eval`,
+//! which mirror `execute_statement` and `evaluate` for the statements and
+//! expressions that contain a `yield`; everything else runs as usual. On
+//! suspension each of those nodes on the way out pushes a `Frame` recording
+//! how far it got, innermost first. On resumption the same nodes are entered
+//! again from the outside and each pops its frame, so the body carries on at
+//! the `yield`, which evaluates to the value passed to `next(v)`.
+
+use super::builtins::{IteratorSource, IteratorState, ObjectKind};
+use super::interpreter::{
+    object_operands, target_operands, ControlFlow, Environment, Interpreter, JsFunction, Value,
+};
+use super::object::JsObject;
+use super::parser::{Expr, ForBinding, Stmt};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Debug, Clone)]
+pub enum GeneratorState {
+    /// Created but not started; the body starts on the first `next()` call
+    NotStarted {
+        function: Box<JsFunction>,
+        this: Value,
+        args: Vec<Value>,
+    },
+    /// Paused at a `yield`, with the frames to resume the body from
+    Suspended {
+        function: Box<JsFunction>,
+        frames: Vec<Frame>,
+    },
+    /// Inside a `next()` call; a `next()` from the body itself returns done
+    Running,
+    Done,
+}
+
+/// How far a statement or expression got before its generator suspended
+#[derive(Debug, Clone)]
+pub enum Frame {
+    /// Which part of a statement was running: for `if` the condition or a
+    /// branch, for loops the condition, body or update
+    Step(usize),
+    /// The values of the operands evaluated before the suspended one
+    Operands(Vec<Value>),
+    /// The statement running in a block or body, and its scope
+    Block(usize, Rc<RefCell<Environment>>),
+    /// A `for...of` in its body: the iterator and the iteration's scope
+    ForOf(Value, Rc<RefCell<Environment>>),
+    /// A `for...in` in its body: the keys, the current one and the iteration's scope
+    ForIn(Vec<String>, usize, Rc<RefCell<Environment>>),
+    /// Paused at this `yield`
+    Yielded,
+    /// Paused in a `yield*` forwarding to this iterator
+    Delegating(Value),
+}
+
+/// One `next()` call into a generator body
+#[derive(Debug)]
+pub struct GeneratorRun {
+    /// Frames left to resume through, outermost last; the frames of a
+    /// suspension are pushed here on the way out
+    frames: Vec<Frame>,
+    /// The argument to `next(v)`, which the paused `yield` evaluates to
+    sent: Option<Value>,
+    /// The value the body suspended with
+    yielded: Value,
+}
+
+// The parts of a statement recorded in `Frame::Step`
+const CONDITION: usize = 0;
+const BODY: usize = 1;
+const ELSE: usize = 2;
+const UPDATE: usize = 3;
+const INIT: usize = 4;
+
+impl Interpreter {
+    /// Calling a generator function returns a generator object that has not started
+    pub(crate) fn create_generator(&self, function: JsFunction, this: Value, args: Vec<Value>) -> Value {
+        let state = GeneratorState::NotStarted {
+            function: Box::new(function),
+            this,
+            args,
+        };
+        self.create_iterator(IteratorSource::Generator(state))
+    }
+
+    /// `generator.next(sent)`: runs the body up to its next `yield` or its end
+    pub(crate) fn generator_next(&mut self, generator: &Rc<RefCell<JsObject>>, sent: Value) -> (Value, bool) {
+        // The body may well use the generator object, so no borrow is held
+        // while it runs
+        let state = match &mut generator.borrow_mut().kind {
+            ObjectKind::Iterator(IteratorState {
+                source: IteratorSource::Generator(state),
+                ..
+            }) => std::mem::replace(state, GeneratorState::Running),
+            _ => return (Value::Undefined, true),
+        };
+
+        let (function, frames) = match state {
+            GeneratorState::NotStarted { function, this, args } => {
+                let env = self.function_env(&function, this, args);
+                (function, vec![Frame::Block(0, env)])
+            }
+            GeneratorState::Suspended { function, frames } => (function, frames),
+            state @ (GeneratorState::Running | GeneratorState::Done) => {
+                let running = matches!(state, GeneratorState::Running);
+                self.set_generator_state(generator, state);
+                if running {
+                    log::warn!("Generator resumed while it is running");
+                }
+                return (Value::Undefined, true);
+            }
+        };
+
+        self.generator_runs.push(GeneratorRun {
+            frames,
+            sent: Some(sent),
+            yielded: Value::Undefined,
+        });
+        let old_env = self.current_env.clone();
+        let flow = self.generator_body(&function.body);
+        self.current_env = old_env;
+        let run = self.generator_runs.pop();
+
+        match (flow, run) {
+            (None, Some(GeneratorRun { frames, yielded, .. })) => {
+                self.set_generator_state(generator, GeneratorState::Suspended { function, frames });
+                (yielded, false)
+            }
+            (flow, _) => {
+                self.set_generator_state(generator, GeneratorState::Done);
+                match flow {
+                    Some(ControlFlow::Return(value)) => (value, true),
+                    _ => (Value::Undefined, true),
+                }
+            }
+        }
+    }
+
+    fn set_generator_state(&self, generator: &Rc<RefCell<JsObject>>, new_state: GeneratorState) {
+        if let ObjectKind::Iterator(IteratorState {
+            source: IteratorSource::Generator(state),
+            ..
+        }) = &mut generator.borrow_mut().kind
+        {
+            *state = new_state;
+        }
+    }
+
+    /// The frame of the node being resumed into, while resuming
+    fn resume_frame(&mut self) -> Option<Frame> {
+        self.generator_runs.last_mut()?.frames.pop()
+    }
+
+    fn push_frame(&mut self, frame: Frame) {
+        if let Some(run) = self.generator_runs.last_mut() {
+            run.frames.push(frame);
+        }
+    }
+
+    /// Suspends the running generator with `value`; returns `None` to unwind
+    fn suspend<T>(&mut self, value: Value, frame: Frame) -> Option<T> {
+        if let Some(run) = self.generator_runs.last_mut() {
+            run.yielded = value;
+        }
+        self.push_frame(frame);
+        None
+    }
+
+    /// The value passed to the `next(v)` that resumed the generator
+    fn take_sent(&mut self) -> Value {
+        self.generator_runs
+            .last_mut()
+            .and_then(|run| run.sent.take())
+            .unwrap_or(Value::Undefined)
+    }
+
+    /// Runs or resumes the function body, whose frame is always the outermost
+    fn generator_body(&mut self, body: &[Stmt]) -> Option<ControlFlow> {
+        let Some(Frame::Block(start, env)) = self.resume_frame() else {
+            return Some(ControlFlow::None);
+        };
+        self.generator_statements(body, start, env)
+    }
+
+    /// Runs `statements` from `start` in `env`, like a block
+    fn generator_statements(
+        &mut self,
+        statements: &[Stmt],
+        start: usize,
+        env: Rc<RefCell<Environment>>,
+    ) -> Option<ControlFlow> {
+        let old_env = std::mem::replace(&mut self.current_env, env.clone());
+        let mut result = ControlFlow::None;
+        for (index, stmt) in statements.iter().enumerate().skip(start) {
+            match self.generator_statement(stmt) {
+                Some(ControlFlow::None) => {}
+                Some(flow) => {
+                    result = flow;
+                    break;
+                }
+                None => {
+                    self.current_env = old_env;
+                    return self.suspend_frame(Frame::Block(index, env));
+                }
+            }
+        }
+        self.current_env = old_env;
+        Some(result)
+    }
+
+    /// Adds this node's frame to a suspension already under way
+    fn suspend_frame<T>(&mut self, frame: Frame) -> Option<T> {
+        self.push_frame(frame);
+        None
+    }
+
+    /// `execute_statement` for generator bodies; `None` when suspended
+    fn generator_statement(&mut self, stmt: &Stmt) -> Option<ControlFlow> {
+        if !stmt_yields(stmt) {
+            return Some(self.execute_statement(stmt));
+        }
+        if self.exception.is_some() {
+            return Some(ControlFlow::Throw);
+        }
+
+        let flow = match stmt {
+            Stmt::Expr(expr) => {
+                self.generator_eval(expr)?;
+                ControlFlow::None
+            }
+
+            Stmt::Var(name, init) | Stmt::Let(name, init) => {
+                let value = match init {
+                    Some(init) => self.generator_eval(init)?,
+                    None => Value::Undefined,
+                };
+                self.current_env.borrow_mut().set(name.clone(), value);
+                ControlFlow::None
+            }
+
+            Stmt::Const(name, init) => {
+                let value = self.generator_eval(init)?;
+                self.current_env.borrow_mut().set(name.clone(), value);
+                ControlFlow::None
+            }
+
+            Stmt::Return(value) => {
+                let value = match value {
+                    Some(value) => self.generator_eval(value)?,
+                    None => Value::Undefined,
+                };
+                ControlFlow::Return(value)
+            }
+
+            Stmt::If(condition, then_branch, else_branch) => {
+                let step = match self.resume_frame() {
+                    Some(Frame::Step(step)) if step != CONDITION => step,
+                    _ => {
+                        let Some(value) = self.generator_eval(condition) else {
+                            return self.suspend_frame(Frame::Step(CONDITION));
+                        };
+                        if self.is_truthy(&value) {
+                            BODY
+                        } else {
+                            ELSE
+                        }
+                    }
+                };
+                let branch = if step == BODY { Some(then_branch) } else { else_branch.as_ref() };
+                match branch {
+                    Some(branch) => match self.generator_statement(branch) {
+                        Some(flow) => flow,
+                        None => return self.suspend_frame(Frame::Step(step)),
+                    },
+                    None => ControlFlow::None,
+                }
+            }
+
+            Stmt::While(condition, body) => {
+                let mut step = match self.resume_frame() {
+                    Some(Frame::Step(step)) => step,
+                    _ => CONDITION,
+                };
+                loop {
+                    if step == CONDITION {
+                        let Some(value) = self.generator_eval(condition) else {
+                            return self.suspend_frame(Frame::Step(CONDITION));
+                        };
+                        if !self.is_truthy(&value) {
+                            break;
+                        }
+                    }
+                    step = CONDITION;
+                    match self.generator_statement(body) {
+                        None => return self.suspend_frame(Frame::Step(BODY)),
+                        Some(ControlFlow::Break) => break,
+                        Some(ControlFlow::Continue | ControlFlow::None) => {}
+                        Some(flow) => return Some(flow),
+                    }
+                }
+                ControlFlow::None
+            }
+
+            Stmt::For(init, condition, update, body) => {
+                let mut step = match self.resume_frame() {
+                    Some(Frame::Step(step)) => step,
+                    _ => INIT,
+                };
+                if step == INIT {
+                    if let Some(init) = init {
+                        if self.generator_statement(init).is_none() {
+                            return self.suspend_frame(Frame::Step(INIT));
+                        }
+                    }
+                    step = CONDITION;
+                }
+                loop {
+                    if step == CONDITION {
+                        if let Some(condition) = condition {
+                            let Some(value) = self.generator_eval(condition) else {
+                                return self.suspend_frame(Frame::Step(CONDITION));
+                            };
+                            if !self.is_truthy(&value) {
+                                break;
+                            }
+                        }
+                        step = BODY;
+                    }
+                    if step == BODY {
+                        match self.generator_statement(body) {
+                            None => return self.suspend_frame(Frame::Step(BODY)),
+                            Some(ControlFlow::Break) => break,
+                            Some(ControlFlow::Continue | ControlFlow::None) => {}
+                            Some(flow) => return Some(flow),
+                        }
+                    }
+                    if let Some(update) = update {
+                        if self.generator_eval(update).is_none() {
+                            return self.suspend_frame(Frame::Step(UPDATE));
+                        }
+                    }
+                    step = CONDITION;
+                }
+                ControlFlow::None
+            }
+
+            Stmt::ForOf(binding, iterable, body) => {
+                let (iterator, mut resumed_env) = match self.resume_frame() {
+                    Some(Frame::ForOf(iterator, env)) => (iterator, Some(env)),
+                    _ => {
+                        let Some(iterable) = self.generator_eval(iterable) else {
+                            return self.suspend_frame(Frame::Step(CONDITION));
+                        };
+                        match self.get_iterator(&iterable) {
+                            Some(iterator) => (iterator, None),
+                            None => return Some(ControlFlow::None),
+                        }
+                    }
+                };
+                loop {
+                    let env = match resumed_env.take() {
+                        Some(env) => env,
+                        None => match self.iterator_step(&iterator) {
+                            Some(item) => self.loop_env(binding, item),
+                            None => break,
+                        },
+                    };
+                    let old_env = std::mem::replace(&mut self.current_env, env.clone());
+                    let flow = self.generator_statement(body);
+                    self.current_env = old_env;
+                    match flow {
+                        None => return self.suspend_frame(Frame::ForOf(iterator, env)),
+                        Some(ControlFlow::Break) => break,
+                        Some(ControlFlow::Continue | ControlFlow::None) => {}
+                        Some(flow) => return Some(flow),
+                    }
+                }
+                ControlFlow::None
+            }
+
+            Stmt::ForIn(binding, object, body) => {
+                let (keys, mut index, mut resumed_env) = match self.resume_frame() {
+                    Some(Frame::ForIn(keys, index, env)) => (keys, index, Some(env)),
+                    _ => {
+                        let Some(object) = self.generator_eval(object) else {
+                            return self.suspend_frame(Frame::Step(CONDITION));
+                        };
+                        (self.enumerable_keys(&object), 0, None)
+                    }
+                };
+                while index < keys.len() {
+                    let env = match resumed_env.take() {
+                        Some(env) => env,
+                        None => self.loop_env(binding, Value::String(keys[index].clone())),
+                    };
+                    let old_env = std::mem::replace(&mut self.current_env, env.clone());
+                    let flow = self.generator_statement(body);
+                    self.current_env = old_env;
+                    match flow {
+                        None => return self.suspend_frame(Frame::ForIn(keys, index, env)),
+                        Some(ControlFlow::Break) => break,
+                        Some(ControlFlow::Continue | ControlFlow::None) => {}
+                        Some(flow) => return Some(flow),
+                    }
+                    index += 1;
+                }
+                ControlFlow::None
+            }
+
+            Stmt::Block(statements) => {
+                let (start, env) = match self.resume_frame() {
+                    Some(Frame::Block(start, env)) => (start, env),
+                    _ => (0, Rc::new(RefCell::new(Environment::with_parent(self.current_env.clone())))),
+                };
+                self.generator_statements(statements, start, env)?
+            }
+
+            // `stmt_yields` is false for the rest
+            _ => self.execute_statement(stmt),
+        };
+
+        if self.exception.is_some() {
+            Some(ControlFlow::Throw)
+        } else {
+            Some(flow)
+        }
+    }
+
+    /// The scope of one `for...of` / `for...in` iteration, with the loop
+    /// variable bound to `item`
+    fn loop_env(&mut self, binding: &ForBinding, item: Value) -> Rc<RefCell<Environment>> {
+        match binding {
+            ForBinding::Declaration(name) => {
+                let env = Rc::new(RefCell::new(Environment::with_parent(self.current_env.clone())));
+                env.borrow_mut().set(name.clone(), item);
+                env
+            }
+            ForBinding::Target(name) => {
+                if !self.current_env.borrow_mut().assign(name, item.clone()) {
+                    self.current_env.borrow_mut().set(name.clone(), item);
+                }
+                self.current_env.clone()
+            }
+        }
+    }
+
+    /// `evaluate` for generator bodies; `None` when suspended
+    fn generator_eval(&mut self, expr: &Expr) -> Option<Value> {
+        if !expr_yields(expr) || self.exception.is_some() {
+            return Some(self.evaluate(expr));
+        }
+
+        let frame = self.resume_frame();
+        let value = match expr {
+            Expr::Binary(left, op, right) => {
+                let values = self.generator_operands(frame, &[left, right])?;
+                self.binary_op(&values[0], *op, &values[1])
+            }
+
+            Expr::Unary(op, operand) => {
+                let values = self.generator_operands(frame, &[operand])?;
+                self.unary_op(*op, &values[0])
+            }
+
+            Expr::Member(object, prop) => {
+                let values = self.generator_operands(frame, &[object])?;
+                self.get_property(&values[0], prop)
+            }
+
+            Expr::Index(object, index) => {
+                let values = self.generator_operands(frame, &[object, index])?;
+                self.get_index(&values[0], &values[1])
+            }
+
+            Expr::Assignment(target, value) => {
+                let mut operands = vec![value.as_ref()];
+                operands.extend(target_operands(target));
+                let mut values = self.generator_operands(frame, &operands)?;
+                let value = values.remove(0);
+                self.assign_to(target, &values, value.clone());
+                value
+            }
+
+            Expr::Call(callee, args) => {
+                let args: Vec<&Expr> = args.iter().collect();
+                match callee.as_ref() {
+                    Expr::Super => {
+                        let values = self.generator_operands(frame, &args)?;
+                        self.call_super_constructor(values);
+                        Value::Undefined
+                    }
+                    Expr::Member(object, prop) if matches!(object.as_ref(), Expr::Super) => {
+                        let values = self.generator_operands(frame, &args)?;
+                        let this = self.evaluate(&Expr::This);
+                        let function = self.get_super_property(prop, &this);
+                        self.call_value(&function, this, values)
+                    }
+                    Expr::Member(object, prop) => {
+                        let mut values = self.generator_operands(frame, &[&[object.as_ref()], &args[..]].concat())?;
+                        let this = values.remove(0);
+                        let function = self.get_property(&this, prop);
+                        self.call_value(&function, this, values)
+                    }
+                    Expr::Index(object, index) => {
+                        let operands = [&[object.as_ref(), index.as_ref()], &args[..]].concat();
+                        let mut values = self.generator_operands(frame, &operands)?;
+                        let this = values.remove(0);
+                        let key = values.remove(0);
+                        let function = self.get_index(&this, &key);
+                        self.call_value(&function, this, values)
+                    }
+                    callee => {
+                        let mut values = self.generator_operands(frame, &[&[callee], &args[..]].concat())?;
+                        let function = values.remove(0);
+                        self.call_value(&function, Value::Undefined, values)
+                    }
+                }
+            }
+
+            Expr::New(callee, args) => {
+                let operands: Vec<&Expr> = std::iter::once(callee.as_ref()).chain(args).collect();
+                let mut values = self.generator_operands(frame, &operands)?;
+                let callee = values.remove(0);
+                self.construct(callee, values)
+            }
+
+            Expr::Object(properties) => {
+                let values = self.generator_operands(frame, &object_operands(properties))?;
+                self.build_object(properties, values)
+            }
+
+            Expr::Array(elements) => {
+                let elements: Vec<&Expr> = elements.iter().collect();
+                let values = self.generator_operands(frame, &elements)?;
+                Value::Array(Rc::new(RefCell::new(values)))
+            }
+
+            Expr::Yield(argument) => {
+                if let Some(Frame::Yielded) = frame {
+                    return Some(self.take_sent());
+                }
+                let value = match argument {
+                    Some(argument) => self.generator_operands(frame, &[argument])?.remove(0),
+                    None => Value::Undefined,
+                };
+                return self.suspend(value, Frame::Yielded);
+            }
+
+            Expr::YieldDelegate(iterable) => {
+                let (iterator, sent) = match frame {
+                    Some(Frame::Delegating(iterator)) => (iterator, self.take_sent()),
+                    frame => {
+                        let iterable = self.generator_operands(frame, &[iterable])?.remove(0);
+                        match self.get_iterator(&iterable) {
+                            Some(iterator) => (iterator, Value::Undefined),
+                            None => return Some(Value::Undefined),
+                        }
+                    }
+                };
+                // The value sent to this generator goes on to the inner one
+                let next = self.get_property(&iterator, "next");
+                let result = self.call_value(&next, iterator.clone(), vec![sent]);
+                let done = self.get_property(&result, "done");
+                let value = self.get_property(&result, "value");
+                if self.is_truthy(&done) || !matches!(result, Value::Object(_)) {
+                    value
+                } else {
+                    return self.suspend(value, Frame::Delegating(iterator));
+                }
+            }
+
+            // `expr_yields` is false for the rest
+            _ => self.evaluate(expr),
+        };
+        Some(value)
+    }
+
+    /// Evaluates `operands` in order, carrying on after those already in
+    /// an `Operands` frame
+    fn generator_operands(&mut self, frame: Option<Frame>, operands: &[&Expr]) -> Option<Vec<Value>> {
+        let mut values = match frame {
+            Some(Frame::Operands(values)) => values,
+            _ => Vec::with_capacity(operands.len()),
+        };
+        while let Some(operand) = operands.get(values.len()) {
+            match self.generator_eval(operand) {
+                Some(value) => values.push(value),
+                None => return self.suspend_frame(Frame::Operands(values)),
+            }
+        }
+        Some(values)
+    }
+}
+
+/// Whether running `stmt` can reach a `yield` of the generator it is in;
+/// nested functions and classes have their own
+fn stmt_yields(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Expr(expr) | Stmt::Const(_, expr) => expr_yields(expr),
+        Stmt::Var(_, init) | Stmt::Let(_, init) | Stmt::Return(init) => init.as_ref().is_some_and(expr_yields),
+        Stmt::If(condition, then_branch, else_branch) => {
+            expr_yields(condition) || stmt_yields(then_branch) || else_branch.as_deref().is_some_and(stmt_yields)
+        }
+        Stmt::While(condition, body) => expr_yields(condition) || stmt_yields(body),
+        Stmt::For(init, condition, update, body) => {
+            init.as_deref().is_some_and(stmt_yields)
+                || condition.as_ref().is_some_and(expr_yields)
+                || update.as_ref().is_some_and(expr_yields)
+                || stmt_yields(body)
+        }
+        Stmt::ForOf(_, iterable, body) | Stmt::ForIn(_, iterable, body) => {
+            expr_yields(iterable) || stmt_yields(body)
+        }
+        Stmt::Block(statements) => statements.iter().any(stmt_yields),
+        Stmt::Break
+        | Stmt::Continue
+        | Stmt::Function(..)
+        | Stmt::GeneratorFunction(..)
+        | Stmt::Class(_) => false,
+    }
+}
+
+fn expr_yields(expr: &Expr) -> bool {
+    match expr {
+        Expr::Yield(_) | Expr::YieldDelegate(_) => true,
+        Expr::Binary(left, _, right) => expr_yields(left) || expr_yields(right),
+        Expr::Unary(_, operand) | Expr::Member(operand, _) => expr_yields(operand),
+        Expr::Index(object, index) => expr_yields(object) || expr_yields(index),
+        Expr::Assignment(target, value) => expr_yields(target) || expr_yields(value),
+        Expr::Call(callee, args) | Expr::New(callee, args) => expr_yields(callee) || args.iter().any(expr_yields),
+        Expr::Object(properties) => object_operands(properties).into_iter().any(expr_yields),
+        Expr::Array(elements) => elements.iter().any(expr_yields),
+        Expr::Number(_)
+        | Expr::String(_)
+        | Expr::Regex(..)
+        | Expr::Boolean(_)
+        | Expr::Null
+        | Expr::Undefined
+        | Expr::Identifier(_)
+        | Expr::This
+        | Expr::Super
+        | Expr::Function(..)
+        | Expr::GeneratorFunction(..)
+        | Expr::Class(_) => false,
+    }
+}
@@ -1,6 +1,11 @@
-use super::parser::{BinaryOp, Expr, Stmt, UnaryOp};
+use super::builtins::{Intrinsics, ObjectKind, SYMBOL_KEY_PREFIX};
+use super::generator::GeneratorRun;
+use super::object::{JsObject, Property, PropertySlot};
+use super::parser::{
+    BinaryOp, ClassDef, ClassMemberKind, Expr, ForBinding, PropertyName, PropertyValue, Stmt, UnaryOp,
+};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::rc::Rc;
 
 #[derive(Debug, Clone)]
@@ -10,44 +15,23 @@ pub enum Value {
     Boolean(bool),
     Number(f64),
     String(String),
+    Symbol(Rc<JsSymbol>),
     Object(Rc<RefCell<JsObject>>),
     Array(Rc<RefCell<Vec<Value>>>),
     Function(JsFunction),
     NativeFunction(String),
 }
 
-#[derive(Debug, Clone)]
-pub struct JsObject {
-    pub properties: HashMap<String, Value>,
-    pub prototype: Option<Rc<RefCell<JsObject>>>,
+#[derive(Debug)]
+pub struct JsSymbol {
+    pub id: usize,
+    pub description: Option<String>,
 }
 
-impl JsObject {
-    pub fn new() -> Self {
-        Self {
-            properties: HashMap::new(),
-            prototype: None,
-        }
-    }
-
-    pub fn get(&self, key: &str) -> Value {
-        if let Some(value) = self.properties.get(key) {
-            value.clone()
-        } else if let Some(proto) = &self.prototype {
-            proto.borrow().get(key)
-        } else {
-            Value::Undefined
-        }
-    }
-
-    pub fn set(&mut self, key: String, value: Value) {
-        self.properties.insert(key, value);
-    }
-}
-
-impl Default for JsObject {
-    fn default() -> Self {
-        Self::new()
+impl JsSymbol {
+    /// Key under which properties named by this symbol are stored
+    pub fn property_key(&self) -> String {
+        format!("{}{}", SYMBOL_KEY_PREFIX, self.id)
     }
 }
 
@@ -57,8 +41,61 @@ pub struct JsFunction {
     pub params: Vec<String>,
     pub body: Vec<Stmt>,
     pub closure: Rc<RefCell<Environment>>,
+    pub is_generator: bool,
+    /// The function's own properties (`prototype`, static class members);
+    /// shared by clones, so it also gives the function its identity
+    pub object: Rc<RefCell<JsObject>>,
+    /// Object whose prototype `super.x` looks up, for methods
+    pub home_object: Option<Rc<RefCell<JsObject>>>,
+    /// Set for class constructors
+    pub class: Option<Rc<ClassInfo>>,
+}
+
+impl JsFunction {
+    pub fn new(
+        name: Option<String>,
+        params: Vec<String>,
+        body: Vec<Stmt>,
+        closure: Rc<RefCell<Environment>>,
+        is_generator: bool,
+    ) -> Self {
+        Self {
+            name,
+            params,
+            body,
+            closure,
+            is_generator,
+            object: Rc::new(RefCell::new(JsObject::new())),
+            home_object: None,
+            class: None,
+        }
+    }
+}
+
+/// What `new` needs to know about a class beyond its constructor body
+#[derive(Debug)]
+pub struct ClassInfo {
+    /// The `extends` target
+    pub parent: Option<Value>,
+    /// Instance fields with their initializers, in declaration order
+    pub fields: Vec<(String, Option<Expr>)>,
+    pub has_constructor: bool,
+}
+
+/// Natives provided by the embedder, such as the DOM bindings
+pub trait NativeHost {
+    /// Handles the native called `name`, or returns `None` if it is not ours
+    fn call(&self, interpreter: &mut Interpreter, name: &str, this: &Value, args: &[Value]) -> Option<Value>;
+
+    /// Handles `new name(...)` for constructors the host provides
+    fn construct(&self, _interpreter: &mut Interpreter, _name: &str, _args: &[Value]) -> Option<Value> {
+        None
+    }
 }
 
+/// Environment slot holding the running method or class constructor, used to resolve `super`
+const FUNCTION_SLOT: &str = "%function";
+
 #[derive(Debug, Clone)]
 pub struct Environment {
     pub variables: HashMap<String, Value>,
@@ -117,11 +154,23 @@ pub enum ControlFlow {
     Return(Value),
     Break,
     Continue,
+    /// An exception is propagating; the thrown value is in `Interpreter::exception`
+    Throw,
 }
 
 pub struct Interpreter {
     pub global: Rc<RefCell<Environment>>,
     pub current_env: Rc<RefCell<Environment>>,
+    pub(crate) intrinsics: Intrinsics,
+    pub(crate) next_symbol_id: usize,
+    /// Each generator body currently running, innermost last
+    pub(crate) generator_runs: Vec<GeneratorRun>,
+    /// The exception currently propagating, if any; statements stop running
+    /// until it reaches a script or microtask boundary and is reported
+    pub(crate) exception: Option<Value>,
+    hosts: Vec<Rc<dyn NativeHost>>,
+    /// Callbacks queued to run once the current script finishes
+    microtasks: VecDeque<(Value, Vec<Value>)>,
 }
 
 impl Interpreter {
@@ -130,10 +179,60 @@ impl Interpreter {
 
         global.borrow_mut().set("console".to_string(), Value::NativeFunction("console".to_string()));
         global.borrow_mut().set("document".to_string(), Value::NativeFunction("document".to_string()));
+        for name in ["Object", "Symbol", "Map", "Set", "WeakMap", "RegExp"] {
+            global.borrow_mut().set(name.to_string(), Value::NativeFunction(name.to_string()));
+        }
+
+        let intrinsics = Intrinsics::new();
+        let next_symbol_id = intrinsics.symbol_iterator.id + 1;
 
         Self {
             global: global.clone(),
             current_env: global,
+            intrinsics,
+            next_symbol_id,
+            generator_runs: Vec::new(),
+            exception: None,
+            hosts: Vec::new(),
+            microtasks: VecDeque::new(),
+        }
+    }
+
+    pub fn register_host(&mut self, host: Rc<dyn NativeHost>) {
+        self.hosts.push(host);
+    }
+
+    pub fn enqueue_microtask(&mut self, callback: Value, args: Vec<Value>) {
+        self.microtasks.push_back((callback, args));
+    }
+
+    /// Runs queued microtasks, including any they queue in turn
+    pub fn run_microtasks(&mut self) {
+        while let Some((callback, args)) = self.microtasks.pop_front() {
+            self.call_value(&callback, Value::Undefined, args);
+            self.report_exception();
+        }
+    }
+
+    /// Starts propagating an error object with the given `name` and `message`
+    pub(crate) fn throw_error(&mut self, name: &str, message: &str) {
+        let mut error = JsObject::new();
+        error.set("name".to_string(), Value::String(name.to_string()));
+        error.set("message".to_string(), Value::String(message.to_string()));
+        self.exception = Some(Value::Object(Rc::new(RefCell::new(error))));
+    }
+
+    /// Logs and clears an exception that nothing caught
+    fn report_exception(&mut self) {
+        let Some(exception) = self.exception.take() else {
+            return;
+        };
+        let name = self.get_property(&exception, "name");
+        let message = self.get_property(&exception, "message");
+        if let (Value::String(name), Value::String(message)) = (&name, &message) {
+            log::warn!("Uncaught {}: {}", name, message);
+        } else {
+            log::warn!("Uncaught {}", self.to_string_value(&exception));
         }
     }
 
@@ -141,21 +240,36 @@ impl Interpreter {
         let mut result = Value::Undefined;
 
         for stmt in statements {
-            match self.execute_statement(stmt) {
-                ControlFlow::Return(value) => return value,
-                ControlFlow::Break | ControlFlow::Continue => break,
-                ControlFlow::None => {}
-            }
             if let Stmt::Expr(expr) = stmt {
                 result = self.evaluate(expr);
+                if self.exception.is_some() {
+                    break;
+                }
+                continue;
+            }
+            match self.execute_statement(stmt) {
+                ControlFlow::Return(value) => {
+                    result = value;
+                    break;
+                }
+                ControlFlow::Break | ControlFlow::Continue | ControlFlow::Throw => break,
+                ControlFlow::None => {}
             }
         }
+        self.report_exception();
+
+        // The end of a script is a microtask checkpoint
+        self.run_microtasks();
 
         result
     }
 
-    fn execute_statement(&mut self, stmt: &Stmt) -> ControlFlow {
-        match stmt {
+    pub(crate) fn execute_statement(&mut self, stmt: &Stmt) -> ControlFlow {
+        if self.exception.is_some() {
+            return ControlFlow::Throw;
+        }
+
+        let flow = match stmt {
             Stmt::Expr(expr) => {
                 self.evaluate(expr);
                 ControlFlow::None
@@ -200,6 +314,7 @@ impl Interpreter {
                         ControlFlow::Break => break,
                         ControlFlow::Continue => continue,
                         ControlFlow::Return(v) => return ControlFlow::Return(v),
+                        ControlFlow::Throw => return ControlFlow::Throw,
                         ControlFlow::None => {}
                     }
                 }
@@ -223,6 +338,7 @@ impl Interpreter {
                         ControlFlow::Break => break,
                         ControlFlow::Continue => {}
                         ControlFlow::Return(v) => return ControlFlow::Return(v),
+                        ControlFlow::Throw => return ControlFlow::Throw,
                         ControlFlow::None => {}
                     }
 
@@ -233,6 +349,37 @@ impl Interpreter {
                 ControlFlow::None
             }
 
+            Stmt::ForOf(binding, iterable, body) => {
+                let iterable = self.evaluate(iterable);
+                let iterator = match self.get_iterator(&iterable) {
+                    Some(iterator) => iterator,
+                    None => return ControlFlow::None,
+                };
+
+                while let Some(item) = self.iterator_step(&iterator) {
+                    match self.execute_loop_body(binding, item, body) {
+                        ControlFlow::Break => break,
+                        ControlFlow::Return(v) => return ControlFlow::Return(v),
+                        ControlFlow::Throw => return ControlFlow::Throw,
+                        ControlFlow::Continue | ControlFlow::None => {}
+                    }
+                }
+                ControlFlow::None
+            }
+
+            Stmt::ForIn(binding, object, body) => {
+                let object = self.evaluate(object);
+                for key in self.enumerable_keys(&object) {
+                    match self.execute_loop_body(binding, Value::String(key), body) {
+                        ControlFlow::Break => break,
+                        ControlFlow::Return(v) => return ControlFlow::Return(v),
+                        ControlFlow::Throw => return ControlFlow::Throw,
+                        ControlFlow::Continue | ControlFlow::None => {}
+                    }
+                }
+                ControlFlow::None
+            }
+
             Stmt::Block(statements) => {
                 let new_env = Rc::new(RefCell::new(Environment::with_parent(self.current_env.clone())));
                 let old_env = self.current_env.clone();
@@ -241,9 +388,8 @@ impl Interpreter {
                 let mut result = ControlFlow::None;
                 for stmt in statements {
                     result = self.execute_statement(stmt);
-                    match &result {
-                        ControlFlow::Return(_) | ControlFlow::Break | ControlFlow::Continue => break,
-                        ControlFlow::None => {}
+                    if !matches!(result, ControlFlow::None) {
+                        break;
                     }
                 }
 
@@ -259,27 +405,67 @@ impl Interpreter {
             Stmt::Break => ControlFlow::Break,
             Stmt::Continue => ControlFlow::Continue,
 
-            Stmt::Function(name, params, body) => {
-                let func = JsFunction {
-                    name: Some(name.clone()),
-                    params: params.clone(),
-                    body: body.clone(),
-                    closure: self.current_env.clone(),
-                };
+            Stmt::Function(name, params, body) | Stmt::GeneratorFunction(name, params, body) => {
+                let func = JsFunction::new(
+                    Some(name.clone()),
+                    params.clone(),
+                    body.clone(),
+                    self.current_env.clone(),
+                    matches!(stmt, Stmt::GeneratorFunction(..)),
+                );
                 self.current_env.borrow_mut().set(name.clone(), Value::Function(func));
                 ControlFlow::None
             }
+
+            Stmt::Class(class) => {
+                let constructor = self.evaluate_class(class);
+                if let Some(name) = &class.name {
+                    self.current_env.borrow_mut().set(name.clone(), constructor);
+                }
+                ControlFlow::None
+            }
+        };
+
+        if self.exception.is_some() {
+            ControlFlow::Throw
+        } else {
+            flow
+        }
+    }
+
+    /// Binds the loop variable for one `for...of` / `for...in` iteration and runs the body
+    fn execute_loop_body(&mut self, binding: &ForBinding, item: Value, body: &Stmt) -> ControlFlow {
+        match binding {
+            ForBinding::Declaration(name) => {
+                let iteration_env = Rc::new(RefCell::new(Environment::with_parent(self.current_env.clone())));
+                iteration_env.borrow_mut().set(name.clone(), item);
+
+                let old_env = std::mem::replace(&mut self.current_env, iteration_env);
+                let result = self.execute_statement(body);
+                self.current_env = old_env;
+                result
+            }
+            ForBinding::Target(name) => {
+                if !self.current_env.borrow_mut().assign(name, item.clone()) {
+                    self.current_env.borrow_mut().set(name.clone(), item);
+                }
+                self.execute_statement(body)
+            }
         }
     }
 
     pub fn evaluate(&mut self, expr: &Expr) -> Value {
+        if self.exception.is_some() {
+            return Value::Undefined;
+        }
         match expr {
             Expr::Number(n) => Value::Number(*n),
             Expr::String(s) => Value::String(s.clone()),
+            Expr::Regex(pattern, flags) => self.create_regexp(pattern, flags),
             Expr::Boolean(b) => Value::Boolean(*b),
             Expr::Null => Value::Null,
             Expr::Undefined => Value::Undefined,
-            Expr::This => Value::Undefined,
+            Expr::This => self.current_env.borrow().get("this").unwrap_or(Value::Undefined),
 
             Expr::Identifier(name) => {
                 self.current_env.borrow().get(name).unwrap_or(Value::Undefined)
@@ -298,79 +484,58 @@ impl Interpreter {
 
             Expr::Assignment(target, value) => {
                 let val = self.evaluate(value);
-                match target.as_ref() {
-                    Expr::Identifier(name) => {
-                        if !self.current_env.borrow_mut().assign(name, val.clone()) {
-                            self.current_env.borrow_mut().set(name.clone(), val.clone());
-                        }
+                let operands: Vec<Value> = target_operands(target).into_iter().map(|e| self.evaluate(e)).collect();
+                self.assign_to(target, &operands, val.clone());
+                val
+            }
+
+            Expr::Call(callee, args) => {
+                // Method calls bind the receiver as `this`
+                let (callee_val, this) = match callee.as_ref() {
+                    Expr::Super => {
+                        let arg_vals: Vec<Value> = args.iter().map(|a| self.evaluate(a)).collect();
+                        self.call_super_constructor(arg_vals);
+                        return Value::Undefined;
+                    }
+                    Expr::Member(obj, prop) if matches!(obj.as_ref(), Expr::Super) => {
+                        let this = self.evaluate(&Expr::This);
+                        (self.get_super_property(prop, &this), this)
                     }
                     Expr::Member(obj, prop) => {
-                        if let Value::Object(obj) = self.evaluate(obj) {
-                            obj.borrow_mut().set(prop.clone(), val.clone());
-                        }
+                        let this = self.evaluate(obj);
+                        (self.get_property(&this, prop), this)
                     }
                     Expr::Index(obj, index) => {
-                        let obj_val = self.evaluate(obj);
-                        let idx_val = self.evaluate(index);
-                        if let (Value::Array(arr), Value::Number(n)) = (obj_val, idx_val) {
-                            let idx = n as usize;
-                            let mut arr = arr.borrow_mut();
-                            if idx < arr.len() {
-                                arr[idx] = val.clone();
-                            }
-                        }
+                        let this = self.evaluate(obj);
+                        let key = self.evaluate(index);
+                        (self.get_index(&this, &key), this)
                     }
-                    _ => {}
-                }
-                val
-            }
-
-            Expr::Call(callee, args) => {
-                let callee_val = self.evaluate(callee);
+                    _ => (self.evaluate(callee), Value::Undefined),
+                };
                 let arg_vals: Vec<Value> = args.iter().map(|a| self.evaluate(a)).collect();
 
-                match callee_val {
-                    Value::Function(func) => self.call_function(&func, arg_vals),
-                    Value::NativeFunction(name) => self.call_native(&name, arg_vals),
-                    _ => Value::Undefined,
-                }
+                self.call_value(&callee_val, this, arg_vals)
+            }
+
+            Expr::Member(obj, prop) if matches!(obj.as_ref(), Expr::Super) => {
+                let this = self.evaluate(&Expr::This);
+                self.get_super_property(prop, &this)
             }
 
             Expr::Member(obj, prop) => {
                 let obj_val = self.evaluate(obj);
-                match obj_val {
-                    Value::NativeFunction(name) => {
-                        // Handle console.log, document.getElementById, etc.
-                        Value::NativeFunction(format!("{}.{}", name, prop))
-                    }
-                    Value::Object(obj) => obj.borrow().get(prop),
-                    Value::String(s) if prop == "length" => Value::Number(s.len() as f64),
-                    Value::Array(arr) if prop == "length" => Value::Number(arr.borrow().len() as f64),
-                    _ => Value::Undefined,
-                }
+                self.get_property(&obj_val, prop)
             }
 
             Expr::Index(obj, index) => {
                 let obj_val = self.evaluate(obj);
                 let idx_val = self.evaluate(index);
-                match (obj_val, idx_val) {
-                    (Value::Array(arr), Value::Number(n)) => {
-                        let idx = n as usize;
-                        arr.borrow().get(idx).cloned().unwrap_or(Value::Undefined)
-                    }
-                    (Value::Object(obj), Value::String(key)) => obj.borrow().get(&key),
-                    _ => Value::Undefined,
-                }
+                self.get_index(&obj_val, &idx_val)
             }
 
             Expr::Object(properties) => {
-                let obj = JsObject::new();
-                let obj_ref = Rc::new(RefCell::new(obj));
-                for (key, value) in properties {
-                    let val = self.evaluate(value);
-                    obj_ref.borrow_mut().set(key.clone(), val);
-                }
-                Value::Object(obj_ref)
+                let values = object_operands(properties).into_iter().map(|e| self.evaluate(e)).collect();
+                self.build_object(properties, values)
             }
 
             Expr::Array(elements) => {
@@ -378,48 +543,419 @@ impl Interpreter {
                 Value::Array(Rc::new(RefCell::new(vals)))
             }
 
-            Expr::Function(name, params, body) => {
-                Value::Function(JsFunction {
-                    name: name.clone(),
-                    params: params.clone(),
-                    body: body.clone(),
-                    closure: self.current_env.clone(),
-                })
+            Expr::Function(name, params, body) | Expr::GeneratorFunction(name, params, body) => {
+                Value::Function(JsFunction::new(
+                    name.clone(),
+                    params.clone(),
+                    body.clone(),
+                    self.current_env.clone(),
+                    matches!(expr, Expr::GeneratorFunction(..)),
+                ))
             }
 
+            Expr::Class(class) => self.evaluate_class(class),
+
+            // Bare `super` is only meaningful as a call or member base
+            Expr::Super => Value::Undefined,
+
             Expr::New(callee, args) => {
                 let callee_val = self.evaluate(callee);
                 let arg_vals: Vec<Value> = args.iter().map(|a| self.evaluate(a)).collect();
+                self.construct(callee_val, arg_vals)
+            }
 
-                if let Value::Function(func) = callee_val {
-                    let obj = Rc::new(RefCell::new(JsObject::new()));
-                    let new_env = Rc::new(RefCell::new(Environment::with_parent(func.closure.clone())));
+            // Only generator bodies parse these, and they evaluate them
+            // through `generator_eval` so they can suspend
+            Expr::Yield(_) | Expr::YieldDelegate(_) => {
+                log::warn!("yield outside a generator body is not supported");
+                Value::Undefined
+            }
+        }
+    }
 
-                    new_env.borrow_mut().set("this".to_string(), Value::Object(obj.clone()));
+    /// Stores `value` into an assignment target, given the values of its
+    /// `target_operands`
+    pub(crate) fn assign_to(&mut self, target: &Expr, operands: &[Value], value: Value) {
+        match (target, operands) {
+            (Expr::Identifier(name), _) => {
+                let assigned = self.current_env.borrow_mut().assign(name, value.clone());
+                if !assigned {
+                    self.current_env.borrow_mut().set(name.clone(), value);
+                }
+            }
+            (Expr::Member(_, prop), [object]) => self.set_property(object, prop, value),
+            (Expr::Index(..), [Value::Array(arr), Value::Number(n)]) => {
+                let idx = *n as usize;
+                let mut arr = arr.borrow_mut();
+                if idx < arr.len() {
+                    arr[idx] = value;
+                }
+            }
+            (Expr::Index(..), [object, key]) => {
+                let key = self.property_key(key);
+                self.set_property(object, &key, value);
+            }
+            _ => {}
+        }
+    }
 
-                    for (param, arg) in func.params.iter().zip(arg_vals) {
-                        new_env.borrow_mut().set(param.clone(), arg);
-                    }
+    /// Builds an object literal from the values of its `object_operands`
+    pub(crate) fn build_object(&mut self, properties: &[(PropertyName, PropertyValue)], values: Vec<Value>) -> Value {
+        let obj_ref = Rc::new(RefCell::new(JsObject::new()));
+        let mut values = values.into_iter();
+        for (name, value) in properties {
+            let key = match name {
+                PropertyName::Named(name) => name.clone(),
+                PropertyName::Computed(_) => {
+                    let key = values.next().unwrap_or(Value::Undefined);
+                    self.property_key(&key)
+                }
+            };
+            let val = values.next().unwrap_or(Value::Undefined);
+            match value {
+                PropertyValue::Init(_) => obj_ref.borrow_mut().set(key, val),
+                PropertyValue::Getter(_) => define_accessor(&obj_ref, key, Some(val), None, true),
+                PropertyValue::Setter(_) => define_accessor(&obj_ref, key, None, Some(val), true),
+            }
+        }
+        Value::Object(obj_ref)
+    }
 
-                    let old_env = self.current_env.clone();
-                    self.current_env = new_env;
+    /// `new callee(...args)`
+    pub(crate) fn construct(&mut self, callee: Value, args: Vec<Value>) -> Value {
+        match callee {
+            Value::Function(func) => {
+                let prototype = self.function_prototype(&func);
+                let obj = Rc::new(RefCell::new(JsObject::with_prototype(prototype, ObjectKind::Ordinary)));
+                let this = Value::Object(obj);
 
-                    for stmt in &func.body {
-                        if let ControlFlow::Return(_) = self.execute_statement(stmt) {
-                            break;
-                        }
-                    }
+                // A constructor may return a different object
+                match self.initialize_instance(&func, &this, args) {
+                    result @ (Value::Object(_) | Value::Array(_)) => result,
+                    _ => this,
+                }
+            }
+            Value::NativeFunction(name) => self.construct_native(&name, args),
+            _ => Value::Undefined,
+        }
+    }
 
-                    self.current_env = old_env;
-                    Value::Object(obj)
-                } else {
-                    Value::Undefined
+    /// Looks up a named property, including the built-in members of primitives
+    pub(crate) fn get_property(&mut self, value: &Value, key: &str) -> Value {
+        match value {
+            Value::NativeFunction(name) => {
+                if let Some(constant) = self.native_constant(name, key) {
+                    return constant;
+                }
+                // Handle console.log, document.getElementById, etc.
+                Value::NativeFunction(format!("{}.{}", name, key))
+            }
+            Value::Object(obj) => self.get_object_property(obj, key, value),
+            Value::Function(func) => {
+                if key == "prototype" {
+                    return Value::Object(self.function_prototype(func));
                 }
+                if func.object.borrow().lookup(key).is_some() {
+                    return self.get_object_property(&func.object, key, value);
+                }
+                match key {
+                    "name" => Value::String(func.name.clone().unwrap_or_default()),
+                    "length" => Value::Number(func.params.len() as f64),
+                    _ => Value::Undefined,
+                }
+            }
+            Value::String(s) => match key {
+                "length" => Value::Number(s.len() as f64),
+                "match" | "matchAll" | "replace" | "replaceAll" | "split" | "search" => {
+                    Value::NativeFunction(format!("String.prototype.{}", key))
+                }
+                _ if key == self.intrinsics.symbol_iterator.property_key() => {
+                    Value::NativeFunction("String.prototype.@@iterator".to_string())
+                }
+                _ => match key.parse::<usize>() {
+                    Ok(idx) => s.chars().nth(idx).map(|c| Value::String(c.to_string())).unwrap_or(Value::Undefined),
+                    Err(_) => Value::Undefined,
+                },
+            },
+            Value::Array(arr) => match key {
+                "length" => Value::Number(arr.borrow().len() as f64),
+                "keys" | "values" | "entries" => Value::NativeFunction(format!("Array.prototype.{}", key)),
+                _ if key == self.intrinsics.symbol_iterator.property_key() => {
+                    Value::NativeFunction("Array.prototype.values".to_string())
+                }
+                _ => match key.parse::<usize>() {
+                    Ok(idx) => arr.borrow().get(idx).cloned().unwrap_or(Value::Undefined),
+                    Err(_) => Value::Undefined,
+                },
+            },
+            Value::Symbol(symbol) if key == "description" => {
+                symbol.description.clone().map(Value::String).unwrap_or(Value::Undefined)
+            }
+            _ => Value::Undefined,
+        }
+    }
+
+    /// Reads `key` from an object's prototype chain, running getters with `receiver` as `this`
+    fn get_object_property(&mut self, obj: &Rc<RefCell<JsObject>>, key: &str, receiver: &Value) -> Value {
+        let property = obj.borrow().lookup(key);
+        match property.map(|p| p.slot) {
+            Some(PropertySlot::Data(value)) => value,
+            Some(PropertySlot::Accessor { get: Some(getter), .. }) => {
+                self.call_value(&getter, receiver.clone(), Vec::new())
+            }
+            _ => Value::Undefined,
+        }
+    }
+
+    /// Assigns `target[key] = value`, honoring setters and non-writable properties
+    pub(crate) fn set_property(&mut self, target: &Value, key: &str, value: Value) {
+        let obj = match target {
+            Value::Object(obj) => obj.clone(),
+            Value::Function(func) => func.object.clone(),
+            _ => return,
+        };
+
+        let existing = obj.borrow().lookup(key);
+        match existing {
+            Some(Property {
+                slot: PropertySlot::Accessor { set, .. },
+                ..
+            }) => {
+                if let Some(setter) = set {
+                    self.call_value(&setter, target.clone(), vec![value]);
+                }
+            }
+            Some(Property { writable: false, .. }) => {}
+            _ => obj.borrow_mut().set(key.to_string(), value),
+        }
+    }
+
+    /// The object `new func()` uses as prototype, created on first use
+    pub(crate) fn function_prototype(&mut self, func: &JsFunction) -> Rc<RefCell<JsObject>> {
+        if let Value::Object(prototype) = func.object.borrow().get("prototype") {
+            return prototype;
+        }
+
+        let mut prototype = JsObject::new();
+        prototype.define("constructor".to_string(), Property::hidden(Value::Function(func.clone())));
+        let prototype = Rc::new(RefCell::new(prototype));
+        func.object
+            .borrow_mut()
+            .define("prototype".to_string(), Property::hidden(Value::Object(prototype.clone())));
+        prototype
+    }
+
+    fn evaluate_property_name(&mut self, name: &PropertyName) -> String {
+        match name {
+            PropertyName::Named(name) => name.clone(),
+            PropertyName::Computed(expr) => {
+                let key = self.evaluate(expr);
+                self.property_key(&key)
+            }
+        }
+    }
+
+    /// Creates the constructor function for a class declaration or expression
+    fn evaluate_class(&mut self, class: &ClassDef) -> Value {
+        let parent = class.superclass.as_ref().map(|expr| self.evaluate(expr));
+        let parent_prototype = match &parent {
+            Some(Value::Function(func)) => Some(self.function_prototype(func)),
+            _ => None,
+        };
+
+        // Methods close over a scope where the class name refers to the class
+        let class_env = Rc::new(RefCell::new(Environment::with_parent(self.current_env.clone())));
+        let old_env = std::mem::replace(&mut self.current_env, class_env.clone());
+
+        let (params, body) = match class.constructor.as_deref() {
+            Some(Expr::Function(_, params, body)) => (params.clone(), body.clone()),
+            _ => (Vec::new(), Vec::new()),
+        };
+        let mut constructor = JsFunction::new(class.name.clone(), params, body, class_env.clone(), false);
+
+        let mut prototype = JsObject::new();
+        prototype.prototype = parent_prototype;
+        let prototype = Rc::new(RefCell::new(prototype));
+        if let Some(Value::Function(parent)) = &parent {
+            // Static members are inherited through the constructors' own prototype chain
+            constructor.object.borrow_mut().prototype = Some(parent.object.clone());
+        }
+        constructor.home_object = Some(prototype.clone());
+
+        let mut fields = Vec::new();
+        let mut static_fields = Vec::new();
+        for member in &class.members {
+            let key = self.evaluate_property_name(&member.name);
+            let home = if member.is_static {
+                constructor.object.clone()
+            } else {
+                prototype.clone()
+            };
+
+            match &member.kind {
+                ClassMemberKind::Method(expr) => {
+                    let method = self.evaluate_method(expr, &home);
+                    home.borrow_mut().define(key, Property::hidden(method));
+                }
+                ClassMemberKind::Getter(expr) => {
+                    let getter = self.evaluate_method(expr, &home);
+                    define_accessor(&home, key, Some(getter), None, false);
+                }
+                ClassMemberKind::Setter(expr) => {
+                    let setter = self.evaluate_method(expr, &home);
+                    define_accessor(&home, key, None, Some(setter), false);
+                }
+                ClassMemberKind::Field(init) if member.is_static => static_fields.push((key, init.clone())),
+                ClassMemberKind::Field(init) => fields.push((key, init.clone())),
+            }
+        }
+
+        constructor.class = Some(Rc::new(ClassInfo {
+            parent,
+            fields,
+            has_constructor: class.constructor.is_some(),
+        }));
+
+        let constructor_value = Value::Function(constructor.clone());
+        prototype
+            .borrow_mut()
+            .define("constructor".to_string(), Property::hidden(constructor_value.clone()));
+        constructor
+            .object
+            .borrow_mut()
+            .define("prototype".to_string(), Property::hidden(Value::Object(prototype)));
+        if let Some(name) = &class.name {
+            class_env.borrow_mut().set(name.clone(), constructor_value.clone());
+        }
+
+        self.current_env = old_env;
+
+        // Static fields see the finished class as `this`
+        self.initialize_fields(&constructor, &static_fields, &constructor_value);
+
+        constructor_value
+    }
+
+    fn evaluate_method(&mut self, expr: &Expr, home: &Rc<RefCell<JsObject>>) -> Value {
+        match self.evaluate(expr) {
+            Value::Function(mut func) => {
+                func.home_object = Some(home.clone());
+                Value::Function(func)
+            }
+            other => other,
+        }
+    }
+
+    /// Runs the part of `new` that sets up `this`: field initializers and the
+    /// constructor body, chaining to parent classes through `super(...)`
+    fn initialize_instance(&mut self, func: &JsFunction, this: &Value, args: Vec<Value>) -> Value {
+        let Some(class) = func.class.clone() else {
+            return self.run_function_body(func, this.clone(), args);
+        };
+
+        if class.parent.is_none() {
+            self.initialize_fields(func, &class.fields, this);
+        }
+
+        if class.has_constructor {
+            self.run_function_body(func, this.clone(), args)
+        } else {
+            // Implicit `constructor(...args) { super(...args); }`
+            if let Some(Value::Function(parent)) = &class.parent {
+                self.initialize_instance(parent, this, args);
+                self.initialize_fields(func, &class.fields, this);
+            }
+            Value::Undefined
+        }
+    }
+
+    fn initialize_fields(&mut self, func: &JsFunction, fields: &[(String, Option<Expr>)], this: &Value) {
+        if fields.is_empty() {
+            return;
+        }
+
+        let field_env = Rc::new(RefCell::new(Environment::with_parent(func.closure.clone())));
+        field_env.borrow_mut().set("this".to_string(), this.clone());
+        field_env.borrow_mut().set(FUNCTION_SLOT.to_string(), Value::Function(func.clone()));
+        let old_env = std::mem::replace(&mut self.current_env, field_env);
+
+        for (key, init) in fields {
+            let value = init.as_ref().map(|e| self.evaluate(e)).unwrap_or(Value::Undefined);
+            let target = match this {
+                Value::Object(obj) => obj.clone(),
+                Value::Function(func) => func.object.clone(),
+                _ => continue,
+            };
+            target.borrow_mut().define(key.clone(), Property::data(value));
+        }
+
+        self.current_env = old_env;
+    }
+
+    fn current_function(&self) -> Option<JsFunction> {
+        match self.current_env.borrow().get(FUNCTION_SLOT) {
+            Some(Value::Function(func)) => Some(func),
+            _ => None,
+        }
+    }
+
+    /// `super(...)` inside a derived class constructor
+    pub(crate) fn call_super_constructor(&mut self, args: Vec<Value>) {
+        let Some(func) = self.current_function() else {
+            return;
+        };
+        let Some(class) = func.class.clone() else {
+            return;
+        };
+        let this = self.evaluate(&Expr::This);
+
+        match &class.parent {
+            Some(Value::Function(parent)) => {
+                self.initialize_instance(parent, &this, args);
+            }
+            Some(other) => {
+                log::warn!("super() to non-class parent {} is not supported", self.to_string_value(other));
+            }
+            None => {}
+        }
+        self.initialize_fields(&func, &class.fields, &this);
+    }
+
+    /// `super.key`: looks past the home object's own properties
+    pub(crate) fn get_super_property(&mut self, key: &str, this: &Value) -> Value {
+        let parent = self
+            .current_function()
+            .and_then(|func| func.home_object)
+            .and_then(|home| home.borrow().prototype.clone());
+        match parent {
+            Some(parent) => self.get_object_property(&parent, key, this),
+            None => Value::Undefined,
+        }
+    }
+
+    /// Computed member access (`obj[key]`)
+    pub(crate) fn get_index(&mut self, value: &Value, key: &Value) -> Value {
+        match (value, key) {
+            (Value::Array(arr), Value::Number(n)) => {
+                let idx = *n as usize;
+                arr.borrow().get(idx).cloned().unwrap_or(Value::Undefined)
+            }
+            _ => {
+                let key = self.property_key(key);
+                self.get_property(value, &key)
             }
         }
     }
 
-    fn binary_op(&self, left: &Value, op: BinaryOp, right: &Value) -> Value {
+    /// Converts a value to the string key used in `JsObject::properties`
+    pub(crate) fn property_key(&self, key: &Value) -> String {
+        match key {
+            Value::Symbol(symbol) => symbol.property_key(),
+            other => self.to_string_value(other),
+        }
+    }
+
+    pub(crate) fn binary_op(&self, left: &Value, op: BinaryOp, right: &Value) -> Value {
         match op {
             BinaryOp::Add => match (left, right) {
                 (Value::Number(a), Value::Number(b)) => Value::Number(a + b),
@@ -479,7 +1015,7 @@ impl Interpreter {
         }
     }
 
-    fn unary_op(&self, op: UnaryOp, operand: &Value) -> Value {
+    pub(crate) fn unary_op(&self, op: UnaryOp, operand: &Value) -> Value {
         match op {
             UnaryOp::Not => Value::Boolean(!self.is_truthy(operand)),
             UnaryOp::Neg => match operand {
@@ -492,6 +1028,7 @@ impl Interpreter {
                 Value::Boolean(_) => "boolean",
                 Value::Number(_) => "number",
                 Value::String(_) => "string",
+                Value::Symbol(_) => "symbol",
                 Value::Object(_) => "object",
                 Value::Array(_) => "object",
                 Value::Function(_) | Value::NativeFunction(_) => "function",
@@ -499,7 +1036,7 @@ impl Interpreter {
         }
     }
 
-    fn is_truthy(&self, value: &Value) -> bool {
+    pub(crate) fn is_truthy(&self, value: &Value) -> bool {
         match value {
             Value::Undefined | Value::Null => false,
             Value::Boolean(b) => *b,
@@ -516,18 +1053,25 @@ impl Interpreter {
             (Value::Boolean(a), Value::Boolean(b)) => a == b,
             (Value::Number(a), Value::Number(b)) => a == b,
             (Value::String(a), Value::String(b)) => a == b,
+            (Value::Symbol(a), Value::Symbol(b)) => Rc::ptr_eq(a, b),
+            (Value::Object(a), Value::Object(b)) => Rc::ptr_eq(a, b),
+            (Value::Array(a), Value::Array(b)) => Rc::ptr_eq(a, b),
             _ => false,
         }
     }
 
-    fn to_string_value(&self, value: &Value) -> String {
+    pub(crate) fn to_string_value(&self, value: &Value) -> String {
         match value {
             Value::Undefined => "undefined".to_string(),
             Value::Null => "null".to_string(),
             Value::Boolean(b) => b.to_string(),
             Value::Number(n) => n.to_string(),
             Value::String(s) => s.clone(),
-            Value::Object(_) => "[object Object]".to_string(),
+            Value::Symbol(symbol) => format!("Symbol({})", symbol.description.as_deref().unwrap_or("")),
+            Value::Object(obj) => match &obj.borrow().kind {
+                ObjectKind::RegExp(data) => format!("/{}/{}", data.source, data.flags),
+                _ => "[object Object]".to_string(),
+            },
             Value::Array(arr) => {
                 let items: Vec<String> = arr.borrow().iter().map(|v| self.to_string_value(v)).collect();
                 items.join(",")
@@ -536,13 +1080,41 @@ impl Interpreter {
         }
     }
 
-    fn call_function(&mut self, func: &JsFunction, args: Vec<Value>) -> Value {
+    /// Calls any callable value with an explicit receiver
+    pub(crate) fn call_value(&mut self, callee: &Value, this: Value, args: Vec<Value>) -> Value {
+        if self.exception.is_some() {
+            return Value::Undefined;
+        }
+        match callee {
+            Value::Function(func) => self.call_function(func, this, args),
+            Value::NativeFunction(name) => self.call_native(name, this, args),
+            _ => Value::Undefined,
+        }
+    }
+
+    fn call_function(&mut self, func: &JsFunction, this: Value, args: Vec<Value>) -> Value {
+        if func.is_generator {
+            return self.create_generator(func.clone(), this, args);
+        }
+        self.run_function_body(func, this, args)
+    }
+
+    /// The scope a call to `func` runs its body in
+    pub(crate) fn function_env(&self, func: &JsFunction, this: Value, args: Vec<Value>) -> Rc<RefCell<Environment>> {
         let new_env = Rc::new(RefCell::new(Environment::with_parent(func.closure.clone())));
 
+        new_env.borrow_mut().set("this".to_string(), this);
+        if func.home_object.is_some() || func.class.is_some() {
+            new_env.borrow_mut().set(FUNCTION_SLOT.to_string(), Value::Function(func.clone()));
+        }
         for (param, arg) in func.params.iter().zip(args) {
             new_env.borrow_mut().set(param.clone(), arg);
         }
+        new_env
+    }
 
+    pub(crate) fn run_function_body(&mut self, func: &JsFunction, this: Value, args: Vec<Value>) -> Value {
+        let new_env = self.function_env(func, this, args);
         let old_env = self.current_env.clone();
         self.current_env = new_env;
 
@@ -553,6 +1125,7 @@ impl Interpreter {
                     result = v;
                     break;
                 }
+                ControlFlow::Throw => break,
                 _ => {}
             }
         }
@@ -561,18 +1134,78 @@ impl Interpreter {
         result
     }
 
-    fn call_native(&mut self, name: &str, args: Vec<Value>) -> Value {
+    /// `new name(...)` for constructors provided by a registered host
+    pub(crate) fn construct_host(&mut self, name: &str, args: &[Value]) -> Value {
+        for host in self.hosts.clone() {
+            if let Some(result) = host.construct(self, name, args) {
+                return result;
+            }
+        }
+        Value::Undefined
+    }
+
+    fn call_native(&mut self, name: &str, this: Value, args: Vec<Value>) -> Value {
         match name {
             "console.log" => {
                 let output: Vec<String> = args.iter().map(|a| self.to_string_value(a)).collect();
                 println!("{}", output.join(" "));
                 Value::Undefined
             }
-            _ => Value::Undefined,
+            _ => {
+                if let Some(result) = self.call_builtin(name, this.clone(), args.clone()) {
+                    return result;
+                }
+                for host in self.hosts.clone() {
+                    if let Some(result) = host.call(self, name, &this, &args) {
+                        return result;
+                    }
+                }
+                Value::Undefined
+            }
         }
     }
 }
 
+/// The subexpressions an assignment target evaluates, after the value
+pub(crate) fn target_operands(target: &Expr) -> Vec<&Expr> {
+    match target {
+        Expr::Member(object, _) => vec![object],
+        Expr::Index(object, index) => vec![object, index],
+        _ => Vec::new(),
+    }
+}
+
+/// The subexpressions of an object literal in evaluation order: each
+/// property's computed name, if any, then its value
+pub(crate) fn object_operands(properties: &[(PropertyName, PropertyValue)]) -> Vec<&Expr> {
+    let mut operands = Vec::new();
+    for (name, value) in properties {
+        if let PropertyName::Computed(expr) = name {
+            operands.push(expr.as_ref());
+        }
+        operands.push(match value {
+            PropertyValue::Init(expr) | PropertyValue::Getter(expr) | PropertyValue::Setter(expr) => expr,
+        });
+    }
+    operands
+}
+
+/// Adds a getter or setter, merging with an existing accessor for the same key
+fn define_accessor(
+    obj: &Rc<RefCell<JsObject>>,
+    key: String,
+    get: Option<Value>,
+    set: Option<Value>,
+    enumerable: bool,
+) {
+    let (old_get, old_set) = match obj.borrow().get_own(&key).map(|p| &p.slot) {
+        Some(PropertySlot::Accessor { get, set }) => (get.clone(), set.clone()),
+        _ => (None, None),
+    };
+    obj.borrow_mut()
+        .define(key, Property::accessor(get.or(old_get), set.or(old_set), enumerable));
+}
+
 impl Default for Interpreter {
     fn default() -> Self {
         Self::new()
@@ -930,4 +1563,299 @@ mod tests {
         let result = run("5 >= 3");
         assert!(matches!(result, Value::Boolean(true)));
     }
+
+    #[test]
+    fn test_for_of_array() {
+        let result = run_and_get_var("var sum = 0; for (const x of [1, 2, 3]) { sum = sum + x; }", "sum");
+        assert!(matches!(result, Value::Number(n) if n == 6.0));
+    }
+
+    #[test]
+    fn test_for_of_string() {
+        let result = run_and_get_var("var out = \"\"; for (let c of \"abc\") { out = c + out; }", "out");
+        assert!(matches!(result, Value::String(ref s) if s == "cba"));
+    }
+
+    #[test]
+    fn test_for_of_break_and_target_binding() {
+        let result = run_and_get_var("var x = 0; for (x of [5, 6, 7]) { if (x == 6) { break; } }", "x");
+        assert!(matches!(result, Value::Number(n) if n == 6.0));
+    }
+
+    #[test]
+    fn test_for_in_object_keys() {
+        let result = run_and_get_var("var obj = { b: 2, a: 1 }; var keys = \"\"; for (var k in obj) { keys = keys + k; }", "keys");
+        assert!(matches!(result, Value::String(ref s) if s == "ba"));
+    }
+
+    #[test]
+    fn test_for_in_array_indices() {
+        let result = run_and_get_var("var keys = \"\"; for (var i in [7, 8, 9]) { keys = keys + i; }", "keys");
+        assert!(matches!(result, Value::String(ref s) if s == "012"));
+    }
+
+    #[test]
+    fn test_for_in_skips_symbol_keys() {
+        let result = run_and_get_var(
+            "var obj = { a: 1 }; obj[Symbol(\"hidden\")] = 2; var count = 0; for (var k in obj) { count = count + 1; }",
+            "count"
+        );
+        assert!(matches!(result, Value::Number(n) if n == 1.0));
+    }
+
+    #[test]
+    fn test_symbol_identity() {
+        let result = run_and_get_var("var a = Symbol(\"x\"); var b = Symbol(\"x\"); var r = a.description + (a == b) + (a == a);", "r");
+        assert!(matches!(result, Value::String(ref s) if s == "xfalsetrue"));
+    }
+
+    #[test]
+    fn test_map_basic_operations() {
+        let result = run_and_get_var(
+            "var m = new Map(); var key = {}; m.set(\"a\", 1).set(key, 2); m.delete(\"missing\"); var r = m.get(\"a\") + m.get(key) + m.size;",
+            "r"
+        );
+        assert!(matches!(result, Value::Number(n) if n == 5.0));
+    }
+
+    #[test]
+    fn test_map_iteration_order() {
+        let result = run_and_get_var(
+            "var m = new Map([[\"z\", 1], [\"a\", 2]]); m.set(\"m\", 3); var out = \"\"; for (const e of m) { out = out + e[0] + e[1]; }",
+            "out"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "z1a2m3"));
+    }
+
+    #[test]
+    fn test_set_deduplicates() {
+        let result = run_and_get_var(
+            "var s = new Set([1, 2, 2, 3]); s.add(3); var total = 0; for (const v of s) { total = total + v; } var r = total * 10 + s.size;",
+            "r"
+        );
+        assert!(matches!(result, Value::Number(n) if n == 63.0));
+    }
+
+    #[test]
+    fn test_weak_map() {
+        let result = run_and_get_var(
+            "var w = new WeakMap(); var k = {}; w.set(k, \"v\"); var r = w.get(k) + w.has({}) + w.delete(k) + w.has(k);",
+            "r"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "vfalsetruefalse"));
+    }
+
+    #[test]
+    fn test_array_entries_iterator() {
+        let result = run_and_get_var(
+            "var it = [\"a\", \"b\"].entries(); var first = it.next(); var second = it.next(); var done = it.next().done; var r = first.value[0] + second.value[1] + done;",
+            "r"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "0btrue"));
+    }
+
+    #[test]
+    fn test_custom_iterable() {
+        let result = run_and_get_var(
+            "var range = {}; range[Symbol.iterator] = function() { var i = 0; return { next: function() { i = i + 1; return { value: i, done: i > 3 }; } }; }; var sum = 0; for (var n of range) { sum = sum + n; }",
+            "sum"
+        );
+        assert!(matches!(result, Value::Number(n) if n == 6.0));
+    }
+
+    #[test]
+    fn test_generator_function() {
+        let result = run_and_get_var(
+            "function* gen() { yield 1; yield 2; return 99; } var sum = 0; for (const v of gen()) { sum = sum + v; }",
+            "sum"
+        );
+        assert!(matches!(result, Value::Number(n) if n == 3.0));
+    }
+
+    #[test]
+    fn test_generator_next_and_delegate() {
+        let result = run_and_get_var(
+            "function* inner() { yield \"b\"; yield \"c\"; } function* outer() { yield \"a\"; yield* inner(); } var g = outer(); var r = g.next().value + g.next().value + g.next().value + g.next().done;",
+            "r"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "abctrue"));
+    }
+
+    #[test]
+    fn test_infinite_generator_runs_lazily() {
+        let result = run_and_get_var(
+            "function* naturals() { var n = 0; while (true) { yield n; n = n + 1; } } var taken = 0; for (const n of naturals()) { taken = n; if (n == 4) { break; } }",
+            "taken"
+        );
+        assert!(matches!(result, Value::Number(n) if n == 4.0));
+    }
+
+    #[test]
+    fn test_yield_evaluates_to_value_sent_by_next() {
+        let result = run_and_get_var(
+            "function* g() { const x = yield 1; const y = yield x + 1; return x + y; } var it = g(); var a = it.next(\"ignored\").value; var b = it.next(10).value; var c = it.next(20); var r = a + \",\" + b + \",\" + c.value + \",\" + c.done + \",\" + it.next().done;",
+            "r"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "1,11,30,true,true"));
+    }
+
+    #[test]
+    fn test_generator_side_effects_interleave_with_consumer() {
+        let result = run_and_get_var(
+            "var log = \"\"; function* g() { for (let i = 0; i < 2; i = i + 1) { log = log + \"g\" + i; yield i; } log = log + \"end\"; } for (const v of g()) { log = log + \"c\" + v; }",
+            "log"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "g0c0g1c1end"));
+    }
+
+    #[test]
+    fn test_generator_resumes_inside_nested_statements() {
+        let result = run_and_get_var(
+            "function* inner() { const v = yield \"i\"; return v; } function* g() { for (const k of [\"a\", \"b\"]) { if (k == \"b\") { var got = yield* inner(); yield got + k; } else { yield [k, yield k + \"?\"]; } } } var it = g(); var r = it.next().value; r = r + it.next(\"x\").value; r = r + it.next().value; r = r + it.next(\"y\").value + it.next().done;",
+            "r"
+        );
+        // "a?", then the array ["a", "x"] joined by + as "a,x", "i", "yb", done
+        assert!(matches!(result, Value::String(ref s) if s == "a?a,xiybtrue"));
+    }
+
+    #[test]
+    fn test_yield_is_an_identifier_outside_generators() {
+        let result = run_and_get_var("var yield = 2; function f() { return yield * 3; } var r = f();", "r");
+        assert!(matches!(result, Value::Number(n) if n == 6.0));
+    }
+
+    #[test]
+    fn test_method_call_binds_this() {
+        let result = run_and_get_var("var obj = { x: 21, get: function() { return this.x * 2; } }; var r = obj.get();", "r");
+        assert!(matches!(result, Value::Number(n) if n == 42.0));
+    }
+
+    #[test]
+    fn test_top_level_expression_runs_once() {
+        let result = run_and_get_var("var count = 0; var bump = function() { count = count + 1; }; bump();", "count");
+        assert!(matches!(result, Value::Number(n) if n == 1.0));
+    }
+
+    #[test]
+    fn test_object_literal_getter_setter() {
+        let result = run_and_get_var(
+            "var temp = { c: 10, get f() { return this.c * 2; }, set f(v) { this.c = v / 2; } }; temp.f = 50; var r = temp.f + temp.c;",
+            "r"
+        );
+        assert!(matches!(result, Value::Number(n) if n == 75.0));
+    }
+
+    #[test]
+    fn test_define_property_attributes() {
+        let result = run_and_get_var(
+            "var o = {}; Object.defineProperty(o, \"x\", { value: 1 }); o.x = 2; o.y = 3; var r = o.x + \":\" + Object.keys(o).length + \":\" + Object.getOwnPropertyDescriptor(o, \"x\").writable;",
+            "r"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "1:1:false"));
+    }
+
+    #[test]
+    fn test_define_property_accessor() {
+        let result = run_and_get_var(
+            "var calls = 0; var o = {}; Object.defineProperty(o, \"now\", { get: function() { calls = calls + 1; return calls; } }); o.now; var r = o.now;",
+            "r"
+        );
+        assert!(matches!(result, Value::Number(n) if n == 2.0));
+    }
+
+    #[test]
+    fn test_own_keys_insertion_order() {
+        let result = run_and_get_var(
+            "var o = { z: 1, a: 2 }; o[\"2\"] = 3; o.m = 4; o[\"1\"] = 5; var r = \"\"; for (const k of Object.keys(o)) { r = r + k; }",
+            "r"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "12zam"));
+    }
+
+    #[test]
+    fn test_class_methods_and_fields() {
+        let result = run_and_get_var(
+            "class Counter { count = 5; static created = 0; constructor(step) { this.step = step; Counter.created = Counter.created + 1; } tick() { this.count = this.count + this.step; return this; } get double() { return this.count * 2; } } var c = new Counter(2); c.tick().tick(); var r = c.double + \":\" + Counter.created;",
+            "r"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "18:1"));
+    }
+
+    #[test]
+    fn test_class_extends_and_super() {
+        let result = run_and_get_var(
+            "class Animal { constructor(name) { this.name = name; } speak() { return this.name + \" makes a sound\"; } static kind() { return \"animal\"; } } class Dog extends Animal { tricks = 1; constructor(name) { super(name); } speak() { return super.speak() + \" (woof)\"; } } var d = new Dog(\"Rex\"); var r = d.speak() + \":\" + d.tricks + \":\" + Dog.kind() + \":\" + (Object.getPrototypeOf(Dog.prototype) == Animal.prototype);",
+            "r"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "Rex makes a sound (woof):1:animal:true"));
+    }
+
+    #[test]
+    fn test_class_implicit_derived_constructor() {
+        let result = run_and_get_var(
+            "class Base { constructor(x) { this.x = x; } } class Child extends Base { y = 2; } var c = new Child(40); var r = c.x + c.y;",
+            "r"
+        );
+        assert!(matches!(result, Value::Number(n) if n == 42.0));
+    }
+
+    #[test]
+    fn test_class_methods_not_enumerable() {
+        let result = run_and_get_var(
+            "class P { constructor() { this.a = 1; } m() {} } var r = \"\"; for (const k in new P()) { r = r + k; }",
+            "r"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "a"));
+    }
+
+    #[test]
+    fn test_regex_literal_test_and_division() {
+        let result = run_and_get_var("var a = 10; var b = 2; var q = a / b / 5; var r = /^h.llo$/i.test(\"HeLLo\") + \":\" + q;", "r");
+        assert!(matches!(result, Value::String(ref s) if s == "true:1"));
+    }
+
+    #[test]
+    fn test_regex_exec_groups_and_last_index() {
+        let result = run_and_get_var(
+            "var re = /(\\d+)-(?<unit>[a-z]+)/g; var s = \"1-px, 20-em\"; var m1 = re.exec(s); var m2 = re.exec(s); var r = m1[1] + m2.groups.unit + m2.index + \":\" + re.lastIndex + \":\" + re.exec(s);",
+            "r"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "1em6:11:null"));
+    }
+
+    #[test]
+    fn test_string_match_and_match_all() {
+        let result = run_and_get_var(
+            "var s = \"a1b22c333\"; var all = s.match(/\\d+/g); var first = s.match(/(\\d)(\\d)/); var r = all.length + \":\" + all[2] + \":\" + first[2] + \":\" + first.index; for (const m of s.matchAll(/[a-z](\\d)/g)) { r = r + m[1]; }",
+            "r"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "3:333:2:3123"));
+    }
+
+    #[test]
+    fn test_string_replace() {
+        let result = run_and_get_var(
+            "var r = \"John Smith\".replace(/(\\w+)\\s(\\w+)/, \"$2, $1\") + \"|\" + \"aaa\".replace(\"a\", \"b\") + \"|\" + \"aaa\".replaceAll(\"a\", \"b\") + \"|\" + \"x-y-z\".replace(/-/g, function(m, offset) { return offset; });",
+            "r"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "Smith, John|baa|bbb|x1y3z"));
+    }
+
+    #[test]
+    fn test_string_split_and_search() {
+        let result = run_and_get_var(
+            "var parts = \"a1b2c\".split(/(\\d)/); var chars = \"abc\".split(\"\"); var r = parts.length + parts[1] + \":\" + chars.length + \":\" + \"a,b,,c\".split(\",\", 3).length + \":\" + \"hello\".search(/l+/) + \":\" + \"hello\".search(/z/);",
+            "r"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "51:3:3:2:-1"));
+    }
+
+    #[test]
+    fn test_regexp_constructor() {
+        let result = run_and_get_var(
+            "var re = new RegExp(\"b+\", \"g\"); var copy = RegExp(re); var r = re.source + \":\" + re.global + \":\" + copy.flags + \":\" + re + \":\" + new RegExp(\"(\");",
+            "r"
+        );
+        assert!(matches!(result, Value::String(ref s) if s == "b+:true:g:/b+/g:null"));
+    }
 }
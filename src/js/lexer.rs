@@ -7,6 +7,8 @@ pub enum Token {
     Null,
     Undefined,
     Identifier(String),
+    /// `/pattern/flags`
+    Regex(String, String),
 
     // Keywords
     Var,
@@ -24,6 +26,10 @@ pub enum Token {
     False,
     This,
     New,
+    In,
+    Class,
+    Extends,
+    Super,
 
     // Operators
     Plus,
@@ -65,11 +71,66 @@ pub enum Token {
     Eof,
 }
 
+impl Token {
+    /// Source text of a keyword token, for contexts where keywords are
+    /// valid names (`obj.new`, `{ default: 1 }`)
+    pub fn keyword_text(&self) -> Option<&'static str> {
+        Some(match self {
+            Token::Var => "var",
+            Token::Let => "let",
+            Token::Const => "const",
+            Token::Function => "function",
+            Token::Return => "return",
+            Token::If => "if",
+            Token::Else => "else",
+            Token::While => "while",
+            Token::For => "for",
+            Token::Break => "break",
+            Token::Continue => "continue",
+            Token::True => "true",
+            Token::False => "false",
+            Token::Null => "null",
+            Token::Undefined => "undefined",
+            Token::This => "this",
+            Token::New => "new",
+            Token::In => "in",
+            Token::Class => "class",
+            Token::Extends => "extends",
+            Token::Super => "super",
+            _ => return None,
+        })
+    }
+
+    /// Whether a `/` following this token starts a regex literal rather than
+    /// a division: it does wherever an operand is expected
+    fn allows_regex_after(&self) -> bool {
+        !matches!(
+            self,
+            Token::Number(_)
+                | Token::String(_)
+                | Token::Regex(_, _)
+                | Token::Identifier(_)
+                | Token::True
+                | Token::False
+                | Token::Null
+                | Token::Undefined
+                | Token::This
+                | Token::Super
+                | Token::RightParen
+                | Token::RightBracket
+                | Token::PlusPlus
+                | Token::MinusMinus
+        )
+    }
+}
+
 pub struct Lexer {
     source: Vec<char>,
     position: usize,
     line: usize,
     column: usize,
+    /// Set when the previous token leaves the parser expecting an operand
+    regex_allowed: bool,
 }
 
 impl Lexer {
@@ -79,6 +140,7 @@ impl Lexer {
             position: 0,
             line: 1,
             column: 1,
+            regex_allowed: true,
         }
     }
 
@@ -92,6 +154,7 @@ impl Lexer {
             }
 
             if let Some(token) = self.scan_token() {
+                self.regex_allowed = token.allows_regex_after();
                 tokens.push(token);
             }
         }
@@ -151,6 +214,8 @@ impl Lexer {
                         self.advance();
                     }
                     None
+                } else if self.regex_allowed {
+                    self.regex().or(Some(Token::Slash))
                 } else {
                     Some(Token::Slash)
                 }
@@ -247,6 +312,45 @@ impl Lexer {
         Token::String(value)
     }
 
+    /// Scans the rest of a regex literal after its opening `/`. The pattern is
+    /// kept verbatim for the regex engine; only its end has to be found here.
+    /// Returns `None` (consuming nothing) if the line ends first.
+    fn regex(&mut self) -> Option<Token> {
+        let start = (self.position, self.line, self.column);
+        let mut pattern = String::new();
+        let mut in_class = false;
+
+        loop {
+            if self.is_at_end() || self.peek() == '\n' {
+                (self.position, self.line, self.column) = start;
+                return None;
+            }
+            let c = self.advance();
+            match c {
+                '/' if !in_class => break,
+                '[' => in_class = true,
+                ']' => in_class = false,
+                '\\' => {
+                    pattern.push(c);
+                    if self.is_at_end() || self.peek() == '\n' {
+                        continue;
+                    }
+                    pattern.push(self.advance());
+                    continue;
+                }
+                _ => {}
+            }
+            pattern.push(c);
+        }
+
+        let mut flags = String::new();
+        while !self.is_at_end() && self.peek().is_ascii_alphabetic() {
+            flags.push(self.advance());
+        }
+
+        Some(Token::Regex(pattern, flags))
+    }
+
     fn number(&mut self, first: char) -> Token {
         let mut value = String::from(first);
 
@@ -293,6 +397,10 @@ impl Lexer {
             "undefined" => Token::Undefined,
             "this" => Token::This,
             "new" => Token::New,
+            "in" => Token::In,
+            "class" => Token::Class,
+            "extends" => Token::Extends,
+            "super" => Token::Super,
             _ => Token::Identifier(value),
         }
     }
@@ -532,6 +640,53 @@ mod tests {
         assert_eq!(tokens[1], Token::New);
     }
 
+    #[test]
+    fn test_in_keyword_and_contextual_yield() {
+        let mut lexer = Lexer::new("for (k in obj) yield of");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[3], Token::In);
+        // `yield` is only a keyword inside generators, which the parser knows about
+        assert_eq!(tokens[6], Token::Identifier("yield".to_string()));
+        assert_eq!(tokens[7], Token::Identifier("of".to_string()));
+    }
+
+    #[test]
+    fn test_class_keywords() {
+        let mut lexer = Lexer::new("class A extends B { constructor() { super(); } }");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[0], Token::Class);
+        assert_eq!(tokens[2], Token::Extends);
+        assert_eq!(tokens[9], Token::Super);
+    }
+
+    #[test]
+    fn test_regex_literal() {
+        let mut lexer = Lexer::new(r"var re = /a[/]b\/c/gi;");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[3], Token::Regex(r"a[/]b\/c".to_string(), "gi".to_string()));
+        assert_eq!(tokens[4], Token::Semicolon);
+    }
+
+    #[test]
+    fn test_slash_after_operand_is_division() {
+        let mut lexer = Lexer::new("a / b / c; (x) / 2; arr[0] /y/ 1");
+        let tokens = lexer.tokenize();
+
+        assert!(!tokens.iter().any(|t| matches!(t, Token::Regex(_, _))));
+        assert_eq!(tokens.iter().filter(|t| **t == Token::Slash).count(), 5);
+    }
+
+    #[test]
+    fn test_unterminated_regex_is_division() {
+        let mut lexer = Lexer::new("x = /a\n1");
+        let tokens = lexer.tokenize();
+
+        assert_eq!(tokens[2], Token::Slash);
+    }
+
     #[test]
     fn test_function_declaration() {
         let mut lexer = Lexer::new("function add(a, b) { return a + b; }");
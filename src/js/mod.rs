@@ -1,7 +1,11 @@
+pub mod builtins;
 pub mod dom_bindings;
+pub mod generator;
 pub mod interpreter;
 pub mod lexer;
+pub mod object;
 pub mod parser;
+pub mod regexp;
 
 pub use interpreter::Interpreter;
 pub use lexer::Lexer;
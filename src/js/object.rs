@@ -0,0 +1,246 @@
+//! The JavaScript object model: ordered own properties with attributes.
+
+use super::builtins::{ObjectKind, SYMBOL_KEY_PREFIX};
+use super::interpreter::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// What a property holds: a plain value or a getter/setter pair
+#[derive(Debug, Clone)]
+pub enum PropertySlot {
+    Data(Value),
+    Accessor {
+        get: Option<Value>,
+        set: Option<Value>,
+    },
+}
+
+/// A property together with its attributes (ECMA-262 property descriptor)
+#[derive(Debug, Clone)]
+pub struct Property {
+    pub slot: PropertySlot,
+    /// Ignored for accessors
+    pub writable: bool,
+    pub enumerable: bool,
+    pub configurable: bool,
+}
+
+impl Property {
+    /// A writable, enumerable, configurable data property, as created by assignment
+    pub fn data(value: Value) -> Self {
+        Self {
+            slot: PropertySlot::Data(value),
+            writable: true,
+            enumerable: true,
+            configurable: true,
+        }
+    }
+
+    /// A writable, non-enumerable data property, as used for methods
+    pub fn hidden(value: Value) -> Self {
+        Self {
+            enumerable: false,
+            ..Self::data(value)
+        }
+    }
+
+    /// A read-only, non-enumerable, non-configurable data property, for
+    /// internal state scripts must not change
+    pub fn internal(value: Value) -> Self {
+        Self {
+            writable: false,
+            configurable: false,
+            ..Self::hidden(value)
+        }
+    }
+
+    pub fn accessor(get: Option<Value>, set: Option<Value>, enumerable: bool) -> Self {
+        Self {
+            slot: PropertySlot::Accessor { get, set },
+            writable: false,
+            enumerable,
+            configurable: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JsObject {
+    properties: HashMap<String, Property>,
+    /// Own keys in insertion order
+    order: Vec<String>,
+    pub prototype: Option<Rc<RefCell<JsObject>>>,
+    /// Internal slots of built-in objects (Map entries, iterator state, ...)
+    pub kind: ObjectKind,
+}
+
+impl JsObject {
+    pub fn new() -> Self {
+        Self {
+            properties: HashMap::new(),
+            order: Vec::new(),
+            prototype: None,
+            kind: ObjectKind::Ordinary,
+        }
+    }
+
+    pub fn with_prototype(prototype: Rc<RefCell<JsObject>>, kind: ObjectKind) -> Self {
+        Self {
+            prototype: Some(prototype),
+            kind,
+            ..Self::new()
+        }
+    }
+
+    /// Reads a data property along the prototype chain. Accessors are not
+    /// invoked here (that needs an interpreter) and read as undefined.
+    pub fn get(&self, key: &str) -> Value {
+        match self.lookup(key) {
+            Some(Property {
+                slot: PropertySlot::Data(value),
+                ..
+            }) => value,
+            _ => Value::Undefined,
+        }
+    }
+
+    /// Finds a property on this object or its prototype chain
+    pub fn lookup(&self, key: &str) -> Option<Property> {
+        if let Some(property) = self.properties.get(key) {
+            Some(property.clone())
+        } else if let Some(proto) = &self.prototype {
+            proto.borrow().lookup(key)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_own(&self, key: &str) -> Option<&Property> {
+        self.properties.get(key)
+    }
+
+    pub fn has_own(&self, key: &str) -> bool {
+        self.properties.contains_key(key)
+    }
+
+    /// Stores a value in an own data property, keeping the attributes of an
+    /// existing one. Attribute checks are the caller's job.
+    pub fn set(&mut self, key: String, value: Value) {
+        match self.properties.get_mut(&key) {
+            Some(property) => property.slot = PropertySlot::Data(value),
+            None => self.define(key, Property::data(value)),
+        }
+    }
+
+    /// Creates or replaces an own property
+    pub fn define(&mut self, key: String, property: Property) {
+        if self.properties.insert(key.clone(), property).is_none() {
+            self.order.push(key);
+        }
+    }
+
+    pub fn delete(&mut self, key: &str) -> bool {
+        if self.properties.remove(key).is_some() {
+            self.order.retain(|k| k != key);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Own keys in spec order: array index keys ascending, then string keys
+    /// in insertion order, then symbol keys in insertion order
+    pub fn own_keys(&self) -> Vec<String> {
+        let mut integers: Vec<(u32, &String)> =
+            self.order.iter().filter_map(|key| array_index(key).map(|index| (index, key))).collect();
+        integers.sort_by_key(|(index, _)| *index);
+
+        let strings = self
+            .order
+            .iter()
+            .filter(|key| array_index(key).is_none() && !key.starts_with(SYMBOL_KEY_PREFIX));
+        let symbols = self.order.iter().filter(|key| key.starts_with(SYMBOL_KEY_PREFIX));
+
+        integers
+            .into_iter()
+            .map(|(_, key)| key)
+            .chain(strings)
+            .chain(symbols)
+            .cloned()
+            .collect()
+    }
+}
+
+/// The array index a key names: the canonical decimal form of an integer
+/// below 2^32 - 1, so "01" and "+1" are ordinary string keys
+fn array_index(key: &str) -> Option<u32> {
+    key.parse::<u32>()
+        .ok()
+        .filter(|&index| index != u32::MAX && index.to_string() == key)
+}
+
+impl Default for JsObject {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_own_keys_order() {
+        let mut obj = JsObject::new();
+        obj.set("b".to_string(), Value::Null);
+        obj.set("2".to_string(), Value::Null);
+        obj.set(format!("{}7", SYMBOL_KEY_PREFIX), Value::Null);
+        obj.set("a".to_string(), Value::Null);
+        obj.set("1".to_string(), Value::Null);
+
+        let keys = obj.own_keys();
+        assert_eq!(keys, vec!["1", "2", "b", "a", &format!("{}7", SYMBOL_KEY_PREFIX)]);
+    }
+
+    #[test]
+    fn test_own_keys_only_orders_canonical_indices() {
+        let mut obj = JsObject::new();
+        for key in ["01", "10", "4294967295", "+3", "2", "4294967294"] {
+            obj.set(key.to_string(), Value::Null);
+        }
+
+        assert_eq!(obj.own_keys(), vec!["2", "10", "4294967294", "01", "4294967295", "+3"]);
+    }
+
+    #[test]
+    fn test_set_keeps_attributes() {
+        let mut obj = JsObject::new();
+        obj.define("x".to_string(), Property::hidden(Value::Number(1.0)));
+        obj.set("x".to_string(), Value::Number(2.0));
+
+        let property = obj.get_own("x").unwrap();
+        assert!(!property.enumerable);
+        assert!(matches!(obj.get("x"), Value::Number(n) if n == 2.0));
+    }
+
+    #[test]
+    fn test_delete_removes_from_order() {
+        let mut obj = JsObject::new();
+        obj.set("a".to_string(), Value::Null);
+        obj.set("b".to_string(), Value::Null);
+
+        assert!(obj.delete("a"));
+        assert!(!obj.delete("a"));
+        assert_eq!(obj.own_keys(), vec!["b"]);
+    }
+
+    #[test]
+    fn test_accessor_reads_as_undefined_without_interpreter() {
+        let mut obj = JsObject::new();
+        obj.define("x".to_string(), Property::accessor(None, None, true));
+
+        assert!(matches!(obj.get("x"), Value::Undefined));
+        assert!(obj.lookup("x").is_some());
+    }
+}
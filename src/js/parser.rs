@@ -4,6 +4,8 @@ use super::lexer::Token;
 pub enum Expr {
     Number(f64),
     String(String),
+    /// Regex literal: pattern and flags
+    Regex(String, String),
     Boolean(bool),
     Null,
     Undefined,
@@ -14,11 +16,16 @@ pub enum Expr {
     Member(Box<Expr>, String),
     Index(Box<Expr>, Box<Expr>),
     Assignment(Box<Expr>, Box<Expr>),
-    Object(Vec<(String, Expr)>),
+    Object(Vec<(PropertyName, PropertyValue)>),
     Array(Vec<Expr>),
     Function(Option<String>, Vec<String>, Vec<Stmt>),
+    GeneratorFunction(Option<String>, Vec<String>, Vec<Stmt>),
+    Class(ClassDef),
     This,
+    Super,
     New(Box<Expr>, Vec<Expr>),
+    Yield(Option<Box<Expr>>),
+    YieldDelegate(Box<Expr>),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -56,21 +63,89 @@ pub enum Stmt {
     If(Expr, Box<Stmt>, Option<Box<Stmt>>),
     While(Expr, Box<Stmt>),
     For(Option<Box<Stmt>>, Option<Expr>, Option<Expr>, Box<Stmt>),
+    ForOf(ForBinding, Expr, Box<Stmt>),
+    ForIn(ForBinding, Expr, Box<Stmt>),
     Block(Vec<Stmt>),
     Return(Option<Expr>),
     Break,
     Continue,
     Function(String, Vec<String>, Vec<Stmt>),
+    GeneratorFunction(String, Vec<String>, Vec<Stmt>),
+    Class(ClassDef),
+}
+
+/// Key of an object literal or class member
+#[derive(Debug, Clone)]
+pub enum PropertyName {
+    Named(String),
+    /// `[expr]`, evaluated when the object or class is created
+    Computed(Box<Expr>),
+}
+
+impl PartialEq<&str> for PropertyName {
+    fn eq(&self, other: &&str) -> bool {
+        matches!(self, PropertyName::Named(name) if name == other)
+    }
+}
+
+/// Object literal member: `key: value` (also shorthand and methods), `get key()` or `set key(v)`
+#[derive(Debug, Clone)]
+pub enum PropertyValue {
+    Init(Expr),
+    /// Getter function expression
+    Getter(Expr),
+    /// Setter function expression
+    Setter(Expr),
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassDef {
+    pub name: Option<String>,
+    pub superclass: Option<Box<Expr>>,
+    /// The `constructor(...) { ... }` method as a function expression
+    pub constructor: Option<Box<Expr>>,
+    pub members: Vec<ClassMember>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassMember {
+    pub name: PropertyName,
+    pub is_static: bool,
+    pub kind: ClassMemberKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum ClassMemberKind {
+    Method(Expr),
+    Getter(Expr),
+    Setter(Expr),
+    /// Field with its optional initializer, evaluated per instance (or once if static)
+    Field(Option<Expr>),
+}
+
+/// Loop variable of a `for...of` / `for...in` head
+#[derive(Debug, Clone)]
+pub enum ForBinding {
+    /// `for (var x of ...)`, `let` or `const` - a fresh binding per iteration
+    Declaration(String),
+    /// `for (x of ...)` - assigns to an existing variable
+    Target(String),
 }
 
 pub struct Parser {
     tokens: Vec<Token>,
     position: usize,
+    /// Inside a generator body, where `yield` is an operator rather than an identifier
+    in_generator: bool,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, position: 0 }
+        Self {
+            tokens,
+            position: 0,
+            in_generator: false,
+        }
     }
 
     pub fn parse(&mut self) -> Vec<Stmt> {
@@ -94,6 +169,12 @@ impl Parser {
             Token::While => self.parse_while_statement(),
             Token::For => self.parse_for_statement(),
             Token::Function => self.parse_function_declaration(),
+            Token::Class => {
+                self.advance();
+                // Class declarations must be named
+                let class = self.parse_class().filter(|class| class.name.is_some())?;
+                Some(Stmt::Class(class))
+            }
             Token::Return => self.parse_return_statement(),
             Token::Break => {
                 self.advance();
@@ -170,6 +251,10 @@ impl Parser {
         self.advance(); // consume 'for'
         self.expect_token(&Token::LeftParen)?;
 
+        if let Some(stmt) = self.parse_for_in_of() {
+            return Some(stmt);
+        }
+
         let init = if self.match_token(&Token::Semicolon) {
             None
         } else if self.peek() == Token::Var {
@@ -202,16 +287,63 @@ impl Parser {
         Some(Stmt::For(init, condition, update, body))
     }
 
+    /// Parses the head of a `for...of` / `for...in` loop, leaving the
+    /// position untouched when it turns out to be a C-style loop.
+    fn parse_for_in_of(&mut self) -> Option<Stmt> {
+        let start = self.position;
+
+        let binding = match self.peek() {
+            Token::Var | Token::Let | Token::Const => {
+                self.advance();
+                match self.expect_identifier() {
+                    Some(name) => ForBinding::Declaration(name),
+                    None => {
+                        self.position = start;
+                        return None;
+                    }
+                }
+            }
+            Token::Identifier(name) => {
+                self.advance();
+                ForBinding::Target(name)
+            }
+            _ => return None,
+        };
+
+        let is_of = match self.peek() {
+            Token::In => false,
+            Token::Identifier(word) if word == "of" => true,
+            _ => {
+                self.position = start;
+                return None;
+            }
+        };
+        self.advance();
+
+        let iterable = self.parse_expression()?;
+        self.expect_token(&Token::RightParen)?;
+        let body = Box::new(self.parse_statement()?);
+
+        if is_of {
+            Some(Stmt::ForOf(binding, iterable, body))
+        } else {
+            Some(Stmt::ForIn(binding, iterable, body))
+        }
+    }
+
     fn parse_function_declaration(&mut self) -> Option<Stmt> {
         self.advance(); // consume 'function'
+        let is_generator = self.match_token(&Token::Star);
         let name = self.expect_identifier()?;
         self.expect_token(&Token::LeftParen)?;
         let params = self.parse_parameters();
         self.expect_token(&Token::RightParen)?;
-        self.expect_token(&Token::LeftBrace)?;
-        let body = self.parse_block_statements();
-        self.expect_token(&Token::RightBrace)?;
-        Some(Stmt::Function(name, params, body))
+        let body = self.parse_function_body(is_generator)?;
+        if is_generator {
+            Some(Stmt::GeneratorFunction(name, params, body))
+        } else {
+            Some(Stmt::Function(name, params, body))
+        }
     }
 
     fn parse_return_statement(&mut self) -> Option<Stmt> {
@@ -242,6 +374,16 @@ impl Parser {
         statements
     }
 
+    /// Parses `{ body }` of a function, where `yield` is contextual to generators
+    fn parse_function_body(&mut self, is_generator: bool) -> Option<Vec<Stmt>> {
+        self.expect_token(&Token::LeftBrace)?;
+        let was_generator = std::mem::replace(&mut self.in_generator, is_generator);
+        let body = self.parse_block_statements();
+        self.in_generator = was_generator;
+        self.expect_token(&Token::RightBrace)?;
+        Some(body)
+    }
+
     fn parse_expression_statement(&mut self) -> Option<Stmt> {
         let expr = self.parse_expression()?;
         self.consume_semicolon();
@@ -253,6 +395,10 @@ impl Parser {
     }
 
     fn parse_assignment(&mut self) -> Option<Expr> {
+        if self.in_generator && matches!(self.peek(), Token::Identifier(ref name) if name == "yield") {
+            return self.parse_yield();
+        }
+
         let expr = self.parse_or()?;
 
         if self.match_token(&Token::Equal) {
@@ -263,6 +409,29 @@ impl Parser {
         Some(expr)
     }
 
+    fn parse_yield(&mut self) -> Option<Expr> {
+        self.advance(); // consume 'yield'
+
+        if self.match_token(&Token::Star) {
+            let delegate = self.parse_assignment()?;
+            return Some(Expr::YieldDelegate(Box::new(delegate)));
+        }
+
+        match self.peek() {
+            Token::Semicolon
+            | Token::RightParen
+            | Token::RightBracket
+            | Token::RightBrace
+            | Token::Comma
+            | Token::Colon
+            | Token::Eof => Some(Expr::Yield(None)),
+            _ => {
+                let value = self.parse_assignment()?;
+                Some(Expr::Yield(Some(Box::new(value))))
+            }
+        }
+    }
+
     fn parse_or(&mut self) -> Option<Expr> {
         let mut expr = self.parse_and()?;
 
@@ -378,7 +547,7 @@ impl Parser {
                 self.expect_token(&Token::RightParen)?;
                 expr = Expr::Call(Box::new(expr), args);
             } else if self.match_token(&Token::Dot) {
-                let name = self.expect_identifier()?;
+                let name = self.expect_property_identifier()?;
                 expr = Expr::Member(Box::new(expr), name);
             } else if self.match_token(&Token::LeftBracket) {
                 let index = self.parse_expression()?;
@@ -398,11 +567,13 @@ impl Parser {
         match token {
             Token::Number(n) => Some(Expr::Number(n)),
             Token::String(s) => Some(Expr::String(s)),
+            Token::Regex(pattern, flags) => Some(Expr::Regex(pattern, flags)),
             Token::True => Some(Expr::Boolean(true)),
             Token::False => Some(Expr::Boolean(false)),
             Token::Null => Some(Expr::Null),
             Token::Undefined => Some(Expr::Undefined),
             Token::This => Some(Expr::This),
+            Token::Super => Some(Expr::Super),
             Token::Identifier(name) => Some(Expr::Identifier(name)),
 
             Token::LeftParen => {
@@ -417,6 +588,8 @@ impl Parser {
 
             Token::Function => self.parse_function_expression(),
 
+            Token::Class => self.parse_class().map(Expr::Class),
+
             Token::New => {
                 let callee = self.parse_call()?;
                 if let Expr::Call(callee, args) = callee {
@@ -434,15 +607,30 @@ impl Parser {
         let mut properties = Vec::new();
 
         while self.peek() != Token::RightBrace {
-            let key = match self.advance() {
-                Token::Identifier(name) => name,
-                Token::String(s) => s,
-                _ => return None,
-            };
-
-            self.expect_token(&Token::Colon)?;
-            let value = self.parse_expression()?;
-            properties.push((key, value));
+            if let Some(accessor) = self.parse_accessor_prefix() {
+                let name = self.parse_property_name()?;
+                let function = self.parse_method(&name, false)?;
+                let value = if accessor == "get" {
+                    PropertyValue::Getter(function)
+                } else {
+                    PropertyValue::Setter(function)
+                };
+                properties.push((name, value));
+            } else {
+                let is_generator = self.match_token(&Token::Star);
+                let name = self.parse_property_name()?;
+
+                let value = if self.peek() == Token::LeftParen {
+                    self.parse_method(&name, is_generator)?
+                } else if self.match_token(&Token::Colon) {
+                    self.parse_expression()?
+                } else if let PropertyName::Named(shorthand) = &name {
+                    Expr::Identifier(shorthand.clone())
+                } else {
+                    return None;
+                };
+                properties.push((name, PropertyValue::Init(value)));
+            }
 
             if !self.match_token(&Token::Comma) {
                 break;
@@ -453,6 +641,142 @@ impl Parser {
         Some(Expr::Object(properties))
     }
 
+    /// Consumes a `get` / `set` prefix when it introduces an accessor rather
+    /// than naming a property (`{ get: 1 }`, `{ get() {} }`)
+    fn parse_accessor_prefix(&mut self) -> Option<&'static str> {
+        let accessor = match self.peek() {
+            Token::Identifier(word) if word == "get" => "get",
+            Token::Identifier(word) if word == "set" => "set",
+            _ => return None,
+        };
+        if !Self::starts_property_name(&self.peek_at(1)) {
+            return None;
+        }
+        self.advance();
+        Some(accessor)
+    }
+
+    fn starts_property_name(token: &Token) -> bool {
+        matches!(
+            token,
+            Token::Identifier(_) | Token::String(_) | Token::Number(_) | Token::LeftBracket
+        ) || token.keyword_text().is_some()
+    }
+
+    fn parse_property_name(&mut self) -> Option<PropertyName> {
+        match self.advance() {
+            Token::Identifier(name) | Token::String(name) => Some(PropertyName::Named(name)),
+            Token::Number(n) => Some(PropertyName::Named(n.to_string())),
+            Token::LeftBracket => {
+                let key = self.parse_assignment()?;
+                self.expect_token(&Token::RightBracket)?;
+                Some(PropertyName::Computed(Box::new(key)))
+            }
+            token => token.keyword_text().map(|text| PropertyName::Named(text.to_string())),
+        }
+    }
+
+    /// Parses `(params) { body }` of a method into a function expression
+    fn parse_method(&mut self, name: &PropertyName, is_generator: bool) -> Option<Expr> {
+        let name = match name {
+            PropertyName::Named(name) => Some(name.clone()),
+            PropertyName::Computed(_) => None,
+        };
+
+        self.expect_token(&Token::LeftParen)?;
+        let params = self.parse_parameters();
+        self.expect_token(&Token::RightParen)?;
+        let body = self.parse_function_body(is_generator)?;
+
+        if is_generator {
+            Some(Expr::GeneratorFunction(name, params, body))
+        } else {
+            Some(Expr::Function(name, params, body))
+        }
+    }
+
+    /// Parses a class after the `class` keyword
+    fn parse_class(&mut self) -> Option<ClassDef> {
+        let name = match self.peek() {
+            Token::Identifier(name) => {
+                self.advance();
+                Some(name)
+            }
+            _ => None,
+        };
+
+        let superclass = if self.match_token(&Token::Extends) {
+            Some(Box::new(self.parse_call()?))
+        } else {
+            None
+        };
+
+        self.expect_token(&Token::LeftBrace)?;
+
+        let mut constructor = None;
+        let mut members = Vec::new();
+
+        while !self.is_at_end() && self.peek() != Token::RightBrace {
+            if self.match_token(&Token::Semicolon) {
+                continue;
+            }
+
+            let is_static = matches!(self.peek(), Token::Identifier(ref word) if word == "static")
+                && !matches!(self.peek_at(1), Token::LeftParen | Token::Equal | Token::Semicolon);
+            if is_static {
+                self.advance();
+            }
+
+            if let Some(accessor) = self.parse_accessor_prefix() {
+                let name = self.parse_property_name()?;
+                let function = self.parse_method(&name, false)?;
+                let kind = if accessor == "get" {
+                    ClassMemberKind::Getter(function)
+                } else {
+                    ClassMemberKind::Setter(function)
+                };
+                members.push(ClassMember { name, is_static, kind });
+                continue;
+            }
+
+            let is_generator = self.match_token(&Token::Star);
+            let name = self.parse_property_name()?;
+
+            if self.peek() == Token::LeftParen {
+                let method = self.parse_method(&name, is_generator)?;
+                if !is_static && !is_generator && name == "constructor" {
+                    constructor = Some(Box::new(method));
+                } else {
+                    members.push(ClassMember {
+                        name,
+                        is_static,
+                        kind: ClassMemberKind::Method(method),
+                    });
+                }
+            } else {
+                let initializer = if self.match_token(&Token::Equal) {
+                    Some(self.parse_expression()?)
+                } else {
+                    None
+                };
+                self.consume_semicolon();
+                members.push(ClassMember {
+                    name,
+                    is_static,
+                    kind: ClassMemberKind::Field(initializer),
+                });
+            }
+        }
+
+        self.expect_token(&Token::RightBrace)?;
+        Some(ClassDef {
+            name,
+            superclass,
+            constructor,
+            members,
+        })
+    }
+
     fn parse_array_literal(&mut self) -> Option<Expr> {
         let mut elements = Vec::new();
 
@@ -468,6 +792,7 @@ impl Parser {
     }
 
     fn parse_function_expression(&mut self) -> Option<Expr> {
+        let is_generator = self.match_token(&Token::Star);
         let name = if let Token::Identifier(_) = self.peek() {
             Some(self.expect_identifier()?)
         } else {
@@ -477,11 +802,13 @@ impl Parser {
         self.expect_token(&Token::LeftParen)?;
         let params = self.parse_parameters();
         self.expect_token(&Token::RightParen)?;
-        self.expect_token(&Token::LeftBrace)?;
-        let body = self.parse_block_statements();
-        self.expect_token(&Token::RightBrace)?;
+        let body = self.parse_function_body(is_generator)?;
 
-        Some(Expr::Function(name, params, body))
+        if is_generator {
+            Some(Expr::GeneratorFunction(name, params, body))
+        } else {
+            Some(Expr::Function(name, params, body))
+        }
     }
 
     fn parse_parameters(&mut self) -> Vec<String> {
@@ -522,6 +849,10 @@ impl Parser {
         self.tokens.get(self.position).cloned().unwrap_or(Token::Eof)
     }
 
+    fn peek_at(&self, offset: usize) -> Token {
+        self.tokens.get(self.position + offset).cloned().unwrap_or(Token::Eof)
+    }
+
     fn advance(&mut self) -> Token {
         let token = self.peek();
         if !self.is_at_end() {
@@ -554,6 +885,14 @@ impl Parser {
         }
     }
 
+    /// Identifier after `.`, where keywords are allowed as property names
+    fn expect_property_identifier(&mut self) -> Option<String> {
+        match self.advance() {
+            Token::Identifier(name) => Some(name),
+            token => token.keyword_text().map(str::to_string),
+        }
+    }
+
     fn consume_semicolon(&mut self) {
         self.match_token(&Token::Semicolon);
     }
@@ -857,6 +1196,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_regex_literal() {
+        let stmts = parse("var ok = /ab+c/i.test(s);");
+
+        if let Stmt::Var(_, Some(Expr::Call(callee, _))) = &stmts[0] {
+            assert!(matches!(&**callee, Expr::Member(re, m) if m == "test" && matches!(&**re, Expr::Regex(p, f) if p == "ab+c" && f == "i")));
+        } else {
+            panic!("Expected call on a regex literal");
+        }
+    }
+
     #[test]
     fn test_parse_boolean_literals() {
         let stmts = parse("var t = true; var f = false;");
@@ -933,4 +1283,128 @@ mod tests {
             panic!("Expected member access");
         }
     }
+
+    #[test]
+    fn test_parse_for_of_loop() {
+        let stmts = parse("for (const x of items) { total = total + x; }");
+
+        assert_eq!(stmts.len(), 1);
+        if let Stmt::ForOf(ForBinding::Declaration(name), iterable, body) = &stmts[0] {
+            assert_eq!(name, "x");
+            assert!(matches!(iterable, Expr::Identifier(_)));
+            assert!(matches!(body.as_ref(), Stmt::Block(_)));
+        } else {
+            panic!("Expected for...of loop");
+        }
+    }
+
+    #[test]
+    fn test_parse_for_in_loop() {
+        let stmts = parse("for (key in obj) { keys = keys + key; }");
+
+        if let Stmt::ForIn(ForBinding::Target(name), _, _) = &stmts[0] {
+            assert_eq!(name, "key");
+        } else {
+            panic!("Expected for...in loop");
+        }
+    }
+
+    #[test]
+    fn test_parse_c_style_for_with_identifier_init() {
+        let stmts = parse("for (i = 0; i < 3; i = i + 1) {}");
+
+        assert!(matches!(&stmts[0], Stmt::For(Some(_), Some(_), Some(_), _)));
+    }
+
+    #[test]
+    fn test_parse_generator_declaration() {
+        let stmts = parse("function* gen() { yield 1; yield; yield* other(); }");
+
+        if let Stmt::GeneratorFunction(name, _, body) = &stmts[0] {
+            assert_eq!(name, "gen");
+            assert_eq!(body.len(), 3);
+            assert!(matches!(&body[0], Stmt::Expr(Expr::Yield(Some(_)))));
+            assert!(matches!(&body[1], Stmt::Expr(Expr::Yield(None))));
+            assert!(matches!(&body[2], Stmt::Expr(Expr::YieldDelegate(_))));
+        } else {
+            panic!("Expected generator declaration");
+        }
+    }
+
+    #[test]
+    fn test_parse_generator_expression() {
+        let stmts = parse("var g = function*() { yield 1; };");
+
+        assert!(matches!(&stmts[0], Stmt::Var(_, Some(Expr::GeneratorFunction(None, _, _)))));
+    }
+
+    #[test]
+    fn test_parse_yield_is_contextual() {
+        let stmts = parse("var yield = 1; function* g() { function f() { return yield; } yield yield; }");
+
+        assert!(matches!(&stmts[0], Stmt::Var(name, Some(_)) if name == "yield"));
+        if let Stmt::GeneratorFunction(_, _, body) = &stmts[1] {
+            // A plain function nested in a generator reads the variable
+            if let Stmt::Function(_, _, inner) = &body[0] {
+                assert!(matches!(&inner[0], Stmt::Return(Some(Expr::Identifier(name))) if name == "yield"));
+            } else {
+                panic!("Expected nested function");
+            }
+            assert!(matches!(&body[1], Stmt::Expr(Expr::Yield(Some(value))) if matches!(**value, Expr::Yield(None))));
+        } else {
+            panic!("Expected generator declaration");
+        }
+    }
+
+    #[test]
+    fn test_parse_object_accessors_and_methods() {
+        let stmts = parse("var o = { get x() { return 1; }, set x(v) {}, m() {}, [k]: 2, short, get: 3 };");
+
+        if let Stmt::Var(_, Some(Expr::Object(props))) = &stmts[0] {
+            assert_eq!(props.len(), 6);
+            assert!(matches!(&props[0], (PropertyName::Named(n), PropertyValue::Getter(_)) if n == "x"));
+            assert!(matches!(&props[1].1, PropertyValue::Setter(_)));
+            assert!(matches!(&props[2].1, PropertyValue::Init(Expr::Function(Some(_), _, _))));
+            assert!(matches!(&props[3].0, PropertyName::Computed(_)));
+            assert!(matches!(&props[4].1, PropertyValue::Init(Expr::Identifier(n)) if n == "short"));
+            assert_eq!(props[5].0, "get");
+        } else {
+            panic!("Expected object literal");
+        }
+    }
+
+    #[test]
+    fn test_parse_class_declaration() {
+        let stmts = parse(
+            "class B extends A { static count = 0; name = 'b'; constructor(x) { super(x); } get size() { return 1; } static create() {} }"
+        );
+
+        if let Stmt::Class(class) = &stmts[0] {
+            assert_eq!(class.name.as_deref(), Some("B"));
+            assert!(class.superclass.is_some());
+            assert!(class.constructor.is_some());
+            assert_eq!(class.members.len(), 4);
+            assert!(class.members[0].is_static);
+            assert!(matches!(class.members[0].kind, ClassMemberKind::Field(Some(_))));
+            assert!(!class.members[1].is_static);
+            assert!(matches!(class.members[2].kind, ClassMemberKind::Getter(_)));
+            assert!(matches!(class.members[3].kind, ClassMemberKind::Method(_)));
+        } else {
+            panic!("Expected class declaration");
+        }
+    }
+
+    #[test]
+    fn test_parse_class_expression() {
+        let stmts = parse("var C = class { m() { return super.m(); } };");
+
+        assert!(matches!(&stmts[0], Stmt::Var(_, Some(Expr::Class(ClassDef { name: None, .. })))));
+    }
+
+    #[test]
+    fn test_parse_keyword_property_access() {
+        let stmts = parse("promise.catch; obj.new;");
+
+        assert!(matches!(&stmts[1], Stmt::Expr(Expr::Member(_, name)) if name == "new"));
+    }
 }
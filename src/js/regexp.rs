@@ -0,0 +1,620 @@
+//! RegExp objects and the String methods that take patterns.
+//!
+//! Matching is done by `regress`, an ECMAScript regex engine, so pattern
+//! syntax and semantics follow the spec. Indices seen by scripts
+//! (`lastIndex`, `match.index`, `search()`) count characters, matching how
+//! strings are indexed elsewhere in the interpreter.
+
+use super::builtins::{define_getter, prototype, IterationKind, IteratorSource, ObjectKind};
+use super::interpreter::{Interpreter, Value};
+use super::object::{JsObject, Property, PropertySlot};
+use std::cell::RefCell;
+use std::ops::Range;
+use std::rc::Rc;
+
+/// Flags accepted in a literal or by the constructor
+const VALID_FLAGS: &str = "dgimsuvy";
+
+/// The compiled pattern behind a RegExp object
+#[derive(Debug)]
+pub struct RegExpData {
+    pub source: String,
+    pub flags: String,
+    regex: regress::Regex,
+}
+
+impl RegExpData {
+    pub fn new(source: &str, flags: &str) -> Result<Self, String> {
+        for (i, flag) in flags.char_indices() {
+            if !VALID_FLAGS.contains(flag) || flags[..i].contains(flag) {
+                return Err(format!("invalid flags '{}'", flags));
+            }
+        }
+        // `g`, `y` and `d` are handled here; regress ignores them
+        let regex = regress::Regex::with_flags(source, flags).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            source: source.to_string(),
+            flags: flags.to_string(),
+            regex,
+        })
+    }
+
+    fn global(&self) -> bool {
+        self.flags.contains('g')
+    }
+
+    fn sticky(&self) -> bool {
+        self.flags.contains('y')
+    }
+
+    /// Every match in `input`, advancing past empty matches. Sticky patterns
+    /// stop at the first gap between matches.
+    fn find_all(&self, input: &str) -> Vec<MatchInfo> {
+        let mut matches = Vec::new();
+        let mut expected_start = 0;
+        for m in self.regex.find_iter(input) {
+            if self.sticky() && m.start() != expected_start {
+                break;
+            }
+            expected_start = m.end();
+            matches.push(MatchInfo::from(m));
+        }
+        matches
+    }
+}
+
+/// A match as byte ranges into the input
+struct MatchInfo {
+    range: Range<usize>,
+    captures: Vec<Option<Range<usize>>>,
+    named: Vec<(String, Option<Range<usize>>)>,
+}
+
+impl From<regress::Match> for MatchInfo {
+    fn from(m: regress::Match) -> Self {
+        let named = m
+            .named_groups()
+            .map(|(name, range)| (name.to_string(), range))
+            .collect();
+        Self {
+            range: m.range(),
+            captures: m.captures,
+            named,
+        }
+    }
+}
+
+impl MatchInfo {
+    /// A match of a plain string pattern
+    fn literal(range: Range<usize>) -> Self {
+        Self {
+            range,
+            captures: Vec::new(),
+            named: Vec::new(),
+        }
+    }
+
+    /// The whole match followed by the capture groups
+    fn captures_with_match(&self) -> Vec<Option<Range<usize>>> {
+        std::iter::once(Some(self.range.clone()))
+            .chain(self.captures.iter().cloned())
+            .collect()
+    }
+}
+
+/// `RegExp.prototype`
+pub(crate) fn regexp_prototype() -> Rc<RefCell<JsObject>> {
+    let object = prototype(&[
+        ("exec", "RegExp.prototype.exec"),
+        ("test", "RegExp.prototype.test"),
+        ("toString", "RegExp.prototype.toString"),
+    ]);
+    for flag in ["source", "flags", "global", "ignoreCase", "multiline", "sticky", "unicode", "dotAll", "hasIndices"] {
+        define_getter(&object, flag, &format!("get RegExp.prototype.{}", flag));
+    }
+    object
+}
+
+fn regexp_data(value: &Value) -> Option<Rc<RegExpData>> {
+    match value {
+        Value::Object(obj) => match &obj.borrow().kind {
+            ObjectKind::RegExp(data) => Some(data.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn arg(args: &[Value], index: usize) -> Value {
+    args.get(index).cloned().unwrap_or(Value::Undefined)
+}
+
+fn new_array(items: Vec<Value>) -> Value {
+    Value::Array(Rc::new(RefCell::new(items)))
+}
+
+/// Number of characters before byte offset `byte`
+fn char_index(input: &str, byte: usize) -> usize {
+    input[..byte].chars().count()
+}
+
+/// Byte offset of character `index`, or `None` past the end
+fn byte_index(input: &str, index: usize) -> Option<usize> {
+    input
+        .char_indices()
+        .map(|(byte, _)| byte)
+        .chain(std::iter::once(input.len()))
+        .nth(index)
+}
+
+fn slice_value(input: &str, range: &Option<Range<usize>>) -> Value {
+    match range {
+        Some(range) => Value::String(input[range.clone()].to_string()),
+        None => Value::Undefined,
+    }
+}
+
+/// Expands `$&`, `$1`, `$<name>`, `` $` ``, `$'` and `$$` in a replacement string
+fn expand_replacement(template: &str, input: &str, m: &MatchInfo) -> String {
+    let mut result = String::new();
+    let mut chars = template.char_indices().peekable();
+
+    while let Some((_, c)) = chars.next() {
+        if c != '$' {
+            result.push(c);
+            continue;
+        }
+        match chars.peek().map(|&(_, next)| next) {
+            Some('$') => {
+                chars.next();
+                result.push('$');
+            }
+            Some('&') => {
+                chars.next();
+                result.push_str(&input[m.range.clone()]);
+            }
+            Some('`') => {
+                chars.next();
+                result.push_str(&input[..m.range.start]);
+            }
+            Some('\'') => {
+                chars.next();
+                result.push_str(&input[m.range.end..]);
+            }
+            Some(d) if d.is_ascii_digit() => {
+                // Prefer a two-digit group number when that group exists
+                let (start, _) = chars.next().unwrap();
+                let one = d.to_digit(10).unwrap() as usize;
+                let two = chars
+                    .peek()
+                    .and_then(|&(_, d2)| d2.to_digit(10))
+                    .map(|d2| one * 10 + d2 as usize)
+                    .filter(|&n| n >= 1 && n <= m.captures.len());
+                let index = match two {
+                    Some(n) => {
+                        chars.next();
+                        n
+                    }
+                    None if one >= 1 && one <= m.captures.len() => one,
+                    None => {
+                        result.push('$');
+                        result.push_str(&template[start..start + 1]);
+                        continue;
+                    }
+                };
+                if let Some(range) = &m.captures[index - 1] {
+                    result.push_str(&input[range.clone()]);
+                }
+            }
+            Some('<') if !m.named.is_empty() => {
+                let rest: String = chars.clone().map(|(_, c)| c).collect();
+                match rest[1..].find('>') {
+                    Some(end) => {
+                        let name = &rest[1..end + 1];
+                        if let Some((_, Some(range))) = m.named.iter().find(|(n, _)| n == name) {
+                            result.push_str(&input[range.clone()]);
+                        }
+                        for _ in 0..name.chars().count() + 2 {
+                            chars.next();
+                        }
+                    }
+                    None => result.push('$'),
+                }
+            }
+            _ => result.push('$'),
+        }
+    }
+
+    result
+}
+
+impl Interpreter {
+    /// Creates a RegExp object, as for `/source/flags`. Invalid patterns
+    /// evaluate to null with a warning since scripts cannot catch errors.
+    pub(crate) fn create_regexp(&self, source: &str, flags: &str) -> Value {
+        match RegExpData::new(source, flags) {
+            Ok(data) => {
+                let mut object =
+                    JsObject::with_prototype(self.intrinsics.regexp_prototype.clone(), ObjectKind::RegExp(Rc::new(data)));
+                object.define(
+                    "lastIndex".to_string(),
+                    Property {
+                        slot: PropertySlot::Data(Value::Number(0.0)),
+                        writable: true,
+                        enumerable: false,
+                        configurable: false,
+                    },
+                );
+                Value::Object(Rc::new(RefCell::new(object)))
+            }
+            Err(err) => {
+                log::warn!("Invalid regular expression /{}/{}: {}", source, flags, err);
+                Value::Null
+            }
+        }
+    }
+
+    /// The pattern argument of a String method: RegExp objects are used as is,
+    /// anything else is compiled from its string value
+    fn coerce_regexp(&mut self, pattern: &Value) -> Option<(Value, Rc<RegExpData>)> {
+        if let Some(data) = regexp_data(pattern) {
+            return Some((pattern.clone(), data));
+        }
+        let source = match pattern {
+            Value::Undefined => String::new(),
+            other => self.to_string_value(other),
+        };
+        let regexp = self.create_regexp(&source, "");
+        regexp_data(&regexp).map(|data| (regexp, data))
+    }
+
+    fn last_index(&mut self, regexp: &Value) -> usize {
+        match self.get_property(regexp, "lastIndex") {
+            Value::Number(n) if n > 0.0 => n as usize,
+            _ => 0,
+        }
+    }
+
+    fn set_last_index(&mut self, regexp: &Value, index: usize) {
+        self.set_property(regexp, "lastIndex", Value::Number(index as f64));
+    }
+
+    /// RegExpBuiltinExec: one match, starting at `lastIndex` for global and
+    /// sticky patterns and updating it
+    fn regexp_exec(&mut self, regexp: &Value, data: &RegExpData, input: &str) -> Option<MatchInfo> {
+        let uses_last_index = data.global() || data.sticky();
+        let start = if uses_last_index {
+            self.last_index(regexp)
+        } else {
+            0
+        };
+
+        let found = byte_index(input, start).and_then(|byte| {
+            data.regex
+                .find_from(input, byte)
+                .next()
+                .filter(|m| !data.sticky() || m.start() == byte)
+        });
+
+        if uses_last_index {
+            let next = found.as_ref().map(|m| char_index(input, m.end())).unwrap_or(0);
+            self.set_last_index(regexp, next);
+        }
+        found.map(MatchInfo::from)
+    }
+
+    /// The array-like object `exec` and `match` return: the matched text and
+    /// captures at indices, plus `index`, `input` and `groups`
+    fn match_result(&self, input: &str, m: &MatchInfo) -> Value {
+        let mut object = JsObject::new();
+        for (i, range) in m.captures_with_match().iter().enumerate() {
+            object.set(i.to_string(), slice_value(input, range));
+        }
+        object.define(
+            "length".to_string(),
+            Property::hidden(Value::Number((m.captures.len() + 1) as f64)),
+        );
+        object.set("index".to_string(), Value::Number(char_index(input, m.range.start) as f64));
+        object.set("input".to_string(), Value::String(input.to_string()));
+
+        let groups = if m.named.is_empty() {
+            Value::Undefined
+        } else {
+            let mut groups = JsObject::new();
+            for (name, range) in &m.named {
+                groups.set(name.clone(), slice_value(input, range));
+            }
+            Value::Object(Rc::new(RefCell::new(groups)))
+        };
+        object.set("groups".to_string(), groups);
+
+        object.define(
+            self.intrinsics.symbol_iterator.property_key(),
+            Property::hidden(Value::NativeFunction("Array.prototype.values".to_string())),
+        );
+
+        Value::Object(Rc::new(RefCell::new(object)))
+    }
+
+    /// The replacement text for one match, from a template string or by
+    /// calling a replacer function
+    fn replacement_for(&mut self, replacement: &Value, input: &str, m: &MatchInfo) -> String {
+        if !matches!(replacement, Value::Function(_) | Value::NativeFunction(_)) {
+            let template = self.to_string_value(replacement);
+            return expand_replacement(&template, input, m);
+        }
+
+        let mut args: Vec<Value> = m.captures_with_match().iter().map(|r| slice_value(input, r)).collect();
+        args.push(Value::Number(char_index(input, m.range.start) as f64));
+        args.push(Value::String(input.to_string()));
+        if !m.named.is_empty() {
+            let mut groups = JsObject::new();
+            for (name, range) in &m.named {
+                groups.set(name.clone(), slice_value(input, range));
+            }
+            args.push(Value::Object(Rc::new(RefCell::new(groups))));
+        }
+        let result = self.call_value(replacement, Value::Undefined, args);
+        self.to_string_value(&result)
+    }
+
+    fn replace_matches(&mut self, input: &str, matches: &[MatchInfo], replacement: &Value) -> String {
+        let mut result = String::new();
+        let mut last = 0;
+        for m in matches {
+            result.push_str(&input[last..m.range.start]);
+            let replaced = self.replacement_for(replacement, input, m);
+            result.push_str(&replaced);
+            last = m.range.end;
+        }
+        result.push_str(&input[last..]);
+        result
+    }
+
+    /// `String.prototype.split` with a RegExp separator; captures are spliced
+    /// into the result
+    fn split_by_regexp(&self, input: &str, data: &RegExpData, limit: usize) -> Vec<Value> {
+        let mut parts = Vec::new();
+        if input.is_empty() {
+            if data.regex.find(input).is_none() {
+                parts.push(Value::String(String::new()));
+            }
+            return parts;
+        }
+
+        let mut last = 0;
+        for m in data.regex.find_iter(input) {
+            // An empty match cannot split at either end or right after the previous match
+            if m.end() == 0 || m.start() >= input.len() || m.end() == last {
+                continue;
+            }
+            parts.push(Value::String(input[last..m.start()].to_string()));
+            parts.extend(m.captures.iter().map(|range| slice_value(input, range)));
+            last = m.end();
+            if parts.len() >= limit {
+                parts.truncate(limit);
+                return parts;
+            }
+        }
+        parts.push(Value::String(input[last..].to_string()));
+        parts.truncate(limit);
+        parts
+    }
+
+    /// Dispatches RegExp methods and the pattern-taking String methods
+    pub(crate) fn call_regexp_builtin(&mut self, name: &str, this: Value, args: Vec<Value>) -> Option<Value> {
+        let result = match name {
+            "RegExp" => {
+                let pattern = arg(&args, 0);
+                let flags = arg(&args, 1);
+                let source = match (&pattern, regexp_data(&pattern)) {
+                    (_, Some(data)) => data.source.clone(),
+                    (Value::Undefined, None) => "(?:)".to_string(),
+                    (other, None) => self.to_string_value(other),
+                };
+                let flags = match (&flags, regexp_data(&pattern)) {
+                    (Value::Undefined, Some(data)) => data.flags.clone(),
+                    (Value::Undefined, None) => String::new(),
+                    (other, _) => self.to_string_value(other),
+                };
+                self.create_regexp(&source, &flags)
+            }
+
+            "RegExp.prototype.exec" => {
+                let data = regexp_data(&this)?;
+                let input = self.to_string_value(&arg(&args, 0));
+                match self.regexp_exec(&this, &data, &input) {
+                    Some(m) => self.match_result(&input, &m),
+                    None => Value::Null,
+                }
+            }
+            "RegExp.prototype.test" => {
+                let data = regexp_data(&this)?;
+                let input = self.to_string_value(&arg(&args, 0));
+                Value::Boolean(self.regexp_exec(&this, &data, &input).is_some())
+            }
+            "RegExp.prototype.toString" => {
+                let data = regexp_data(&this)?;
+                Value::String(format!("/{}/{}", data.source, data.flags))
+            }
+            "get RegExp.prototype.source" => Value::String(regexp_data(&this)?.source.clone()),
+            "get RegExp.prototype.flags" => Value::String(regexp_data(&this)?.flags.clone()),
+            "get RegExp.prototype.global"
+            | "get RegExp.prototype.ignoreCase"
+            | "get RegExp.prototype.multiline"
+            | "get RegExp.prototype.sticky"
+            | "get RegExp.prototype.unicode"
+            | "get RegExp.prototype.dotAll"
+            | "get RegExp.prototype.hasIndices" => {
+                let data = regexp_data(&this)?;
+                let flag = match name.rsplit('.').next() {
+                    Some("global") => 'g',
+                    Some("ignoreCase") => 'i',
+                    Some("multiline") => 'm',
+                    Some("sticky") => 'y',
+                    Some("unicode") => 'u',
+                    Some("dotAll") => 's',
+                    _ => 'd',
+                };
+                Value::Boolean(data.flags.contains(flag))
+            }
+
+            "String.prototype.match" => {
+                let input = self.to_string_value(&this);
+                let (regexp, data) = self.coerce_regexp(&arg(&args, 0))?;
+                if data.global() {
+                    self.set_last_index(&regexp, 0);
+                    let matches = data.find_all(&input);
+                    if matches.is_empty() {
+                        Value::Null
+                    } else {
+                        new_array(
+                            matches
+                                .iter()
+                                .map(|m| Value::String(input[m.range.clone()].to_string()))
+                                .collect(),
+                        )
+                    }
+                } else {
+                    match self.regexp_exec(&regexp, &data, &input) {
+                        Some(m) => self.match_result(&input, &m),
+                        None => Value::Null,
+                    }
+                }
+            }
+            "String.prototype.matchAll" => {
+                let input = self.to_string_value(&this);
+                let (_, data) = self.coerce_regexp(&arg(&args, 0))?;
+                if !data.global() {
+                    log::warn!("matchAll called with non-global RegExp /{}/{}", data.source, data.flags);
+                }
+                let results = data
+                    .find_all(&input)
+                    .iter()
+                    .map(|m| self.match_result(&input, m))
+                    .collect();
+                let results = Rc::new(RefCell::new(results));
+                self.create_iterator(IteratorSource::Array(results, IterationKind::Values))
+            }
+            "String.prototype.search" => {
+                let input = self.to_string_value(&this);
+                let (_, data) = self.coerce_regexp(&arg(&args, 0))?;
+                match data.regex.find(&input) {
+                    Some(m) => Value::Number(char_index(&input, m.start()) as f64),
+                    None => Value::Number(-1.0),
+                }
+            }
+            "String.prototype.replace" | "String.prototype.replaceAll" => {
+                let input = self.to_string_value(&this);
+                let pattern = arg(&args, 0);
+                let replacement = arg(&args, 1);
+                let all = name == "String.prototype.replaceAll";
+
+                let matches = match regexp_data(&pattern) {
+                    Some(data) if data.global() || all => {
+                        if all && !data.global() {
+                            log::warn!("replaceAll called with non-global RegExp /{}/{}", data.source, data.flags);
+                        }
+                        self.set_last_index(&pattern, 0);
+                        data.find_all(&input)
+                    }
+                    Some(data) => self.regexp_exec(&pattern, &data, &input).into_iter().collect(),
+                    None => {
+                        let needle = self.to_string_value(&pattern);
+                        let found: Vec<_> = if needle.is_empty() {
+                            // The empty string matches between every character
+                            let mut positions: Vec<_> = input.char_indices().map(|(i, _)| i).collect();
+                            positions.push(input.len());
+                            positions
+                        } else {
+                            input.match_indices(&needle).map(|(i, _)| i).collect()
+                        };
+                        let take = if all { found.len() } else { 1 };
+                        found
+                            .into_iter()
+                            .take(take)
+                            .map(|start| MatchInfo::literal(start..start + needle.len()))
+                            .collect()
+                    }
+                };
+                Value::String(self.replace_matches(&input, &matches, &replacement))
+            }
+            "String.prototype.split" => {
+                let input = self.to_string_value(&this);
+                let separator = arg(&args, 0);
+                let limit = match arg(&args, 1) {
+                    Value::Number(n) if n >= 0.0 => n as usize,
+                    Value::Undefined => usize::MAX,
+                    _ => 0,
+                };
+
+                let parts = match (&separator, regexp_data(&separator)) {
+                    (_, Some(data)) => self.split_by_regexp(&input, &data, limit),
+                    (Value::Undefined, None) => vec![Value::String(input)],
+                    (other, None) => {
+                        let separator = self.to_string_value(other);
+                        if separator.is_empty() {
+                            input.chars().map(|c| Value::String(c.to_string())).collect()
+                        } else {
+                            input.split(separator.as_str()).map(|p| Value::String(p.to_string())).collect()
+                        }
+                    }
+                };
+                new_array(parts.into_iter().take(limit).collect())
+            }
+
+            _ => return None,
+        };
+
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(input: &str, pattern: &str) -> MatchInfo {
+        let data = RegExpData::new(pattern, "").unwrap();
+        MatchInfo::from(data.regex.find(input).unwrap())
+    }
+
+    #[test]
+    fn test_invalid_flags_rejected() {
+        assert!(RegExpData::new("a", "gg").is_err());
+        assert!(RegExpData::new("a", "x").is_err());
+        assert!(RegExpData::new("a", "gimsuy").is_ok());
+    }
+
+    #[test]
+    fn test_invalid_pattern_rejected() {
+        assert!(RegExpData::new("(a", "").is_err());
+    }
+
+    #[test]
+    fn test_expand_replacement() {
+        let input = "John Smith";
+        let m = info(input, r"(\w+)\s(\w+)");
+
+        assert_eq!(expand_replacement("$2, $1", input, &m), "Smith, John");
+        assert_eq!(expand_replacement("[$&] $$ $3", input, &m), "[John Smith] $ $3");
+    }
+
+    #[test]
+    fn test_expand_named_group() {
+        let input = "2024-05";
+        let m = info(input, r"(?<year>\d+)-(?<month>\d+)");
+
+        assert_eq!(expand_replacement("$<month>/$<year>", input, &m), "05/2024");
+    }
+
+    #[test]
+    fn test_char_and_byte_indices() {
+        let input = "héllo";
+        assert_eq!(char_index(input, 3), 2);
+        assert_eq!(byte_index(input, 2), Some(3));
+        assert_eq!(byte_index(input, 5), Some(input.len()));
+        assert_eq!(byte_index(input, 6), None);
+    }
+}
@@ -0,0 +1,461 @@
+//! Background painting: the background color and each background layer,
+//! sized, positioned and tiled across its box, bottom layer first.
+
+use super::box_model::{BoxDimensions, EdgeSizes};
+use crate::css::background::{
+    BackgroundAttachment, BackgroundBox, BackgroundImage, BackgroundLayer, BackgroundSize, ColorStop, Gradient, GradientKind,
+    LinearDirection, RadialShape, RadialSize, RepeatStyle,
+};
+use crate::css::computed::ComputedStyle;
+use crate::render::painter::{Color, CornerRadii, DisplayList, GradientPaint, GradientShape, Rect, Tiling};
+use crate::render::ImageSize;
+use std::collections::HashMap;
+
+/// Push the background of a box with `dimensions`, offset by `offset`.
+/// `textures` holds the loaded `url()` images; layers whose image hasn't
+/// loaded are skipped. `viewport` is what `fixed` layers are positioned in.
+pub fn push_background(
+    list: &mut DisplayList,
+    style: &ComputedStyle,
+    dimensions: &BoxDimensions,
+    offset: (f32, f32),
+    viewport: Rect,
+    textures: &HashMap<String, (usize, ImageSize)>,
+) {
+    let background_box = |which: BackgroundBox| {
+        let rect = match which {
+            BackgroundBox::BorderBox => dimensions.border_box(),
+            BackgroundBox::PaddingBox => dimensions.padding_box(),
+            BackgroundBox::ContentBox => dimensions.content,
+        };
+        Rect::new(rect.x + offset.0, rect.y + offset.1, rect.width, rect.height)
+    };
+    if style.background_color.a > 0.0 {
+        // The corners of inner boxes curve less by the widths between them
+        let border_box = dimensions.border_box();
+        let radii = style.corner_radii(border_box.width, border_box.height);
+        let clip = style.background.color_clip();
+        let radii = match clip {
+            BackgroundBox::BorderBox => radii,
+            BackgroundBox::PaddingBox => inner_radii(radii, &dimensions.border),
+            BackgroundBox::ContentBox => inner_radii(inner_radii(radii, &dimensions.border), &dimensions.padding),
+        };
+        list.push_rect_with_radius(background_box(clip), style.background_color, radii, 1.0);
+    }
+
+    for layer in style.background.layers().iter().rev() {
+        let area = if layer.attachment == BackgroundAttachment::Fixed {
+            viewport
+        } else {
+            background_box(layer.origin)
+        };
+        let clip = background_box(layer.clip);
+
+        match layer.image {
+            BackgroundImage::Url(url) => {
+                let Some(&(texture_id, size)) = textures.get(url) else {
+                    continue;
+                };
+                let intrinsic = (size.width as f32, size.height as f32);
+                if let Some((clip, tiling)) = place_layer(layer, area, clip, Some(intrinsic)) {
+                    list.push_background_image(clip, tiling, texture_id, 1.0);
+                }
+            }
+            BackgroundImage::Gradient(gradient) => {
+                if let Some((clip, tiling)) = place_layer(layer, area, clip, None) {
+                    let paint = resolve_gradient(gradient, tiling.tile.width, tiling.tile.height);
+                    list.push_gradient(clip, tiling, paint, 1.0);
+                }
+            }
+        }
+    }
+}
+
+/// The corner radii of a box inset from one with `radii` by `edges`
+fn inner_radii(radii: CornerRadii, edges: &EdgeSizes) -> CornerRadii {
+    let inset = |[x, y]: [f32; 2], dx: f32, dy: f32| [(x - dx).max(0.0), (y - dy).max(0.0)];
+    CornerRadii {
+        top_left: inset(radii.top_left, edges.left, edges.top),
+        top_right: inset(radii.top_right, edges.right, edges.top),
+        bottom_right: inset(radii.bottom_right, edges.right, edges.bottom),
+        bottom_left: inset(radii.bottom_left, edges.left, edges.bottom),
+    }
+}
+
+/// Size, position and tile a layer in its positioning `area`, returning the
+/// part of `clip` it paints and its tiling; `None` if it paints nothing.
+/// Gradients have no `intrinsic` size.
+fn place_layer(
+    layer: &BackgroundLayer,
+    area: Rect,
+    clip: Rect,
+    intrinsic: Option<(f32, f32)>,
+) -> Option<(Rect, Tiling)> {
+    let (mut width, mut height) = tile_size(&layer.size, area, intrinsic);
+    if width <= 0.0 || height <= 0.0 {
+        return None;
+    }
+
+    // `round` scales the tile so a whole number of copies fill the area,
+    // keeping its aspect ratio when the other dimension is `auto`
+    let (auto_width, auto_height) = match &layer.size {
+        BackgroundSize::Explicit(w, h) => (w.is_none(), h.is_none()),
+        BackgroundSize::Cover | BackgroundSize::Contain => (false, false),
+    };
+    let round_x = layer.repeat.x == RepeatStyle::Round;
+    let round_y = layer.repeat.y == RepeatStyle::Round;
+    if round_x {
+        let rounded = area.width / (area.width / width).round().max(1.0);
+        if !round_y && auto_height {
+            height *= rounded / width;
+        }
+        width = rounded;
+    }
+    if round_y {
+        let rounded = area.height / (area.height / height).round().max(1.0);
+        if !round_x && auto_width {
+            width *= rounded / height;
+        }
+        height = rounded;
+    }
+
+    let mut x = area.x + layer.position.x.resolve(area.width - width);
+    let mut y = area.y + layer.position.y.resolve(area.height - height);
+    let mut spacing = [0.0, 0.0];
+
+    // `space` spreads out as many whole copies as fit; with fewer than two
+    // there's nothing to spread, and the one copy is positioned as usual
+    let mut repeat_x = layer.repeat.x != RepeatStyle::NoRepeat;
+    if layer.repeat.x == RepeatStyle::Space {
+        let count = (area.width / width).floor();
+        if count >= 2.0 {
+            spacing[0] = (area.width - count * width) / (count - 1.0);
+            x = area.x;
+        } else {
+            repeat_x = false;
+        }
+    }
+    let mut repeat_y = layer.repeat.y != RepeatStyle::NoRepeat;
+    if layer.repeat.y == RepeatStyle::Space {
+        let count = (area.height / height).floor();
+        if count >= 2.0 {
+            spacing[1] = (area.height - count * height) / (count - 1.0);
+            y = area.y;
+        } else {
+            repeat_y = false;
+        }
+    }
+
+    // On axes that don't repeat, only the one copy paints
+    let tile = Rect::new(x, y, width, height);
+    let mut clip = clip;
+    if !repeat_x {
+        clip = clip.intersect(&Rect::new(tile.x, clip.y, tile.width, clip.height))?;
+    }
+    if !repeat_y {
+        clip = clip.intersect(&Rect::new(clip.x, tile.y, clip.width, tile.height))?;
+    }
+
+    Some((clip, Tiling { tile, spacing, repeat_x, repeat_y }))
+}
+
+/// The size of one copy of a layer's image in `area`
+fn tile_size(size: &BackgroundSize, area: Rect, intrinsic: Option<(f32, f32)>) -> (f32, f32) {
+    let Some((image_width, image_height)) = intrinsic.filter(|(w, h)| *w > 0.0 && *h > 0.0) else {
+        // Without an intrinsic size, `auto` and `cover`/`contain` fill the area
+        return match size {
+            BackgroundSize::Explicit(w, h) => (
+                w.as_ref().map_or(area.width, |w| w.to_px(area.width)),
+                h.as_ref().map_or(area.height, |h| h.to_px(area.height)),
+            ),
+            BackgroundSize::Cover | BackgroundSize::Contain => (area.width, area.height),
+        };
+    };
+
+    match size {
+        BackgroundSize::Cover | BackgroundSize::Contain => {
+            let scale_x = area.width / image_width;
+            let scale_y = area.height / image_height;
+            let scale = if *size == BackgroundSize::Cover {
+                scale_x.max(scale_y)
+            } else {
+                scale_x.min(scale_y)
+            };
+            (image_width * scale, image_height * scale)
+        }
+        BackgroundSize::Explicit(w, h) => match (w, h) {
+            (Some(w), Some(h)) => (w.to_px(area.width), h.to_px(area.height)),
+            (Some(w), None) => {
+                let width = w.to_px(area.width);
+                (width, width * image_height / image_width)
+            }
+            (None, Some(h)) => {
+                let height = h.to_px(area.height);
+                (height * image_width / image_height, height)
+            }
+            (None, None) => (image_width, image_height),
+        },
+    }
+}
+
+/// The geometry and stop offsets of `gradient` painted in a `width` by
+/// `height` box
+pub fn resolve_gradient(gradient: &Gradient, width: f32, height: f32) -> GradientPaint {
+    let (shape, length) = match &gradient.kind {
+        GradientKind::Linear(direction) => {
+            let (dx, dy) = match *direction {
+                LinearDirection::Angle(degrees) => {
+                    let radians = degrees.to_radians();
+                    (radians.sin(), -radians.cos())
+                }
+                // Perpendicular to the diagonal between the other two
+                // corners, so those are where the gradient is half way
+                LinearDirection::Corner { right, bottom } => {
+                    let sx = if right { 1.0 } else { -1.0 };
+                    let sy = if bottom { 1.0 } else { -1.0 };
+                    let (dx, dy) = (height * sx, width * sy);
+                    let norm = dx.hypot(dy).max(f32::EPSILON);
+                    (dx / norm, dy / norm)
+                }
+            };
+            // Long enough that the corners get the first and last colors
+            let length = (width * dx).abs() + (height * dy).abs();
+            let (cx, cy) = (width / 2.0, height / 2.0);
+            let half = length / 2.0;
+            let shape = GradientShape::Linear {
+                start: [cx - dx * half, cy - dy * half],
+                end: [cx + dx * half, cy + dy * half],
+            };
+            (shape, length)
+        }
+        GradientKind::Radial { shape, size, position } => {
+            let cx = position.x.resolve(width);
+            let cy = position.y.resolve(height);
+            let (near_x, far_x) = (cx.abs().min((width - cx).abs()), cx.abs().max((width - cx).abs()));
+            let (near_y, far_y) = (cy.abs().min((height - cy).abs()), cy.abs().max((height - cy).abs()));
+            let circle = *shape == RadialShape::Circle;
+            let (rx, ry) = match size {
+                RadialSize::ClosestSide if circle => (near_x.min(near_y), near_x.min(near_y)),
+                RadialSize::ClosestSide => (near_x, near_y),
+                RadialSize::FarthestSide if circle => (far_x.max(far_y), far_x.max(far_y)),
+                RadialSize::FarthestSide => (far_x, far_y),
+                RadialSize::ClosestCorner if circle => (near_x.hypot(near_y), near_x.hypot(near_y)),
+                RadialSize::ClosestCorner => (near_x * std::f32::consts::SQRT_2, near_y * std::f32::consts::SQRT_2),
+                RadialSize::FarthestCorner if circle => (far_x.hypot(far_y), far_x.hypot(far_y)),
+                RadialSize::FarthestCorner => (far_x * std::f32::consts::SQRT_2, far_y * std::f32::consts::SQRT_2),
+                RadialSize::Radii(rx, ry) => (rx.to_px(width), ry.to_px(height)),
+            };
+            // A zero radius would divide by zero in the shader
+            let (rx, ry) = (rx.max(0.01), ry.max(0.01));
+            (GradientShape::Radial { center: [cx, cy], radii: [rx, ry] }, rx)
+        }
+        GradientKind::Conic { from, position } => {
+            let center = [position.x.resolve(width), position.y.resolve(height)];
+            // Stop positions are fractions of a turn
+            (GradientShape::Conic { center, from: from.to_radians() }, 1.0)
+        }
+    };
+
+    GradientPaint {
+        shape,
+        stops: resolve_stops(&gradient.stops, length),
+        repeating: gradient.repeating,
+    }
+}
+
+/// Offsets of `stops` along a gradient line `length` long, as fractions of
+/// it. The first and last default to the ends, positions never go backwards,
+/// and stops without one are spaced evenly between those around them.
+fn resolve_stops(stops: &[ColorStop], length: f32) -> Vec<(f32, Color)> {
+    let length = length.max(f32::EPSILON);
+    let mut offsets: Vec<Option<f32>> = stops
+        .iter()
+        .map(|stop| stop.position.as_ref().map(|position| position.to_px(length) / length))
+        .collect();
+
+    if let Some(first) = offsets.first_mut() {
+        first.get_or_insert(0.0);
+    }
+    if let Some(last) = offsets.last_mut() {
+        last.get_or_insert(1.0);
+    }
+
+    let mut max = f32::NEG_INFINITY;
+    for offset in offsets.iter_mut().flatten() {
+        max = max.max(*offset);
+        *offset = max;
+    }
+
+    let mut resolved = Vec::with_capacity(stops.len());
+    let mut i = 0;
+    while i < offsets.len() {
+        match offsets[i] {
+            Some(offset) => {
+                resolved.push(offset);
+                i += 1;
+            }
+            None => {
+                // A run of unpositioned stops between two positioned ones
+                let start = resolved[i - 1];
+                let end_index = (i..offsets.len()).find(|&j| offsets[j].is_some()).unwrap_or(offsets.len() - 1);
+                let end = offsets[end_index].unwrap_or(start);
+                let steps = (end_index - i + 1) as f32;
+                for (n, _) in (i..end_index).enumerate() {
+                    resolved.push(start + (end - start) * (n + 1) as f32 / steps);
+                }
+                i = end_index;
+            }
+        }
+    }
+
+    resolved.into_iter().zip(stops.iter().map(|stop| stop.color)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::background::{BackgroundRepeat, Position, PositionComponent};
+    use crate::css::computed::LengthOrPercentage;
+
+    fn layer<'a>(image: &'a BackgroundImage, size: BackgroundSize, repeat: RepeatStyle, position: Position) -> BackgroundLayer<'a> {
+        BackgroundLayer {
+            image,
+            position,
+            size,
+            repeat: BackgroundRepeat { x: repeat, y: repeat },
+            origin: BackgroundBox::PaddingBox,
+            clip: BackgroundBox::BorderBox,
+            attachment: BackgroundAttachment::Scroll,
+        }
+    }
+
+    fn stop(color: Color, position: Option<f32>) -> ColorStop {
+        ColorStop {
+            color,
+            position: position.map(LengthOrPercentage::Percent),
+        }
+    }
+
+    #[test]
+    fn test_place_no_repeat_centered() {
+        let image = BackgroundImage::Url("a.png".to_string());
+        let layer = layer(&image, BackgroundSize::default(), RepeatStyle::NoRepeat, Position::center());
+        let area = Rect::new(0.0, 0.0, 200.0, 100.0);
+        let (clip, tiling) = place_layer(&layer, area, area, Some((50.0, 20.0))).unwrap();
+        assert_eq!(tiling.tile, Rect::new(75.0, 40.0, 50.0, 20.0));
+        assert_eq!(clip, tiling.tile);
+        assert!(!tiling.repeat_x && !tiling.repeat_y);
+
+        // From the end edge: `right 10px bottom 0`
+        let position = Position {
+            x: PositionComponent { offset: LengthOrPercentage::Px(10.0), from_end: true },
+            y: PositionComponent { offset: LengthOrPercentage::Px(0.0), from_end: true },
+        };
+        let layer = BackgroundLayer { position, ..layer };
+        let (_, tiling) = place_layer(&layer, area, area, Some((50.0, 20.0))).unwrap();
+        assert_eq!(tiling.tile, Rect::new(140.0, 80.0, 50.0, 20.0));
+    }
+
+    #[test]
+    fn test_place_cover_contain() {
+        let image = BackgroundImage::Url("a.png".to_string());
+        let area = Rect::new(0.0, 0.0, 200.0, 100.0);
+        let cover = layer(&image, BackgroundSize::Cover, RepeatStyle::Repeat, Position::default());
+        let (_, tiling) = place_layer(&cover, area, area, Some((10.0, 10.0))).unwrap();
+        assert_eq!((tiling.tile.width, tiling.tile.height), (200.0, 200.0));
+
+        let contain = BackgroundLayer { size: BackgroundSize::Contain, ..cover };
+        let (_, tiling) = place_layer(&contain, area, area, Some((10.0, 10.0))).unwrap();
+        assert_eq!((tiling.tile.width, tiling.tile.height), (100.0, 100.0));
+
+        // One dimension given, the other from the aspect ratio
+        let half = BackgroundSize::Explicit(Some(LengthOrPercentage::Percent(50.0)), None);
+        let explicit = BackgroundLayer { size: half, ..contain };
+        let (_, tiling) = place_layer(&explicit, area, area, Some((20.0, 10.0))).unwrap();
+        assert_eq!((tiling.tile.width, tiling.tile.height), (100.0, 50.0));
+    }
+
+    #[test]
+    fn test_place_space_and_round() {
+        let image = BackgroundImage::Url("a.png".to_string());
+        let area = Rect::new(0.0, 0.0, 100.0, 30.0);
+        let space = layer(&image, BackgroundSize::default(), RepeatStyle::Space, Position::center());
+        let (_, tiling) = place_layer(&space, area, area, Some((30.0, 20.0))).unwrap();
+        // Three copies across with 5px between; only one fits down
+        assert_eq!(tiling.tile.x, 0.0);
+        assert_eq!(tiling.spacing[0], 5.0);
+        assert!(tiling.repeat_x && !tiling.repeat_y);
+        assert_eq!(tiling.tile.y, 5.0);
+
+        let round = BackgroundLayer {
+            repeat: BackgroundRepeat { x: RepeatStyle::Round, y: RepeatStyle::Repeat },
+            ..space
+        };
+        let (_, tiling) = place_layer(&round, area, area, Some((30.0, 20.0))).unwrap();
+        // 100 / 30 rounds to 3 copies, and the height keeps the ratio
+        assert!((tiling.tile.width - 100.0 / 3.0).abs() < 1e-4);
+        assert!((tiling.tile.height - 20.0 * (100.0 / 90.0)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_resolve_linear_gradient() {
+        let gradient = Gradient {
+            kind: GradientKind::Linear(LinearDirection::Angle(90.0)),
+            stops: vec![stop(Color::BLACK, None), stop(Color::WHITE, None)],
+            repeating: false,
+        };
+        let paint = resolve_gradient(&gradient, 200.0, 100.0);
+        let GradientShape::Linear { start, end } = paint.shape else {
+            panic!("expected a linear gradient");
+        };
+        assert!((start[0] - 0.0).abs() < 1e-3 && (start[1] - 50.0).abs() < 1e-3);
+        assert!((end[0] - 200.0).abs() < 1e-3 && (end[1] - 50.0).abs() < 1e-3);
+
+        // `to bottom right` passes half way through the other two corners
+        let gradient = Gradient {
+            kind: GradientKind::Linear(LinearDirection::Corner { right: true, bottom: true }),
+            ..gradient
+        };
+        let paint = resolve_gradient(&gradient, 200.0, 100.0);
+        let GradientShape::Linear { start, end } = paint.shape else {
+            panic!("expected a linear gradient");
+        };
+        let (dx, dy) = (end[0] - start[0], end[1] - start[1]);
+        let along = |x: f32, y: f32| ((x - start[0]) * dx + (y - start[1]) * dy) / (dx * dx + dy * dy);
+        assert!((along(200.0, 0.0) - 0.5).abs() < 1e-4);
+        assert!((along(0.0, 100.0) - 0.5).abs() < 1e-4);
+        assert!(along(200.0, 100.0).abs() - 1.0 < 1e-4);
+    }
+
+    #[test]
+    fn test_resolve_radial_gradient() {
+        let gradient = Gradient {
+            kind: GradientKind::Radial {
+                shape: RadialShape::Circle,
+                size: RadialSize::FarthestCorner,
+                position: Position::center(),
+            },
+            stops: vec![stop(Color::BLACK, None), stop(Color::WHITE, None)],
+            repeating: false,
+        };
+        let paint = resolve_gradient(&gradient, 60.0, 80.0);
+        assert_eq!(paint.shape, GradientShape::Radial { center: [30.0, 40.0], radii: [50.0, 50.0] });
+    }
+
+    #[test]
+    fn test_resolve_stops() {
+                let stops = vec![
+            stop(Color::BLACK, None),
+            stop(Color::RED, Some(50.0)),
+            stop(Color::WHITE, Some(20.0)),
+            stop(Color::BLACK, None),
+            stop(Color::RED, None),
+        ];
+        let offsets: Vec<f32> = resolve_stops(&stops, 100.0).iter().map(|(offset, _)| *offset).collect();
+        // The third stop can't go back before the second
+        assert_eq!(offsets, vec![0.0, 0.5, 0.5, 0.75, 1.0]);
+
+        let evenly: Vec<f32> = resolve_stops(&vec![stop(Color::BLACK, None); 5], 10.0).iter().map(|(offset, _)| *offset).collect();
+        assert_eq!(evenly, vec![0.0, 0.25, 0.5, 0.75, 1.0]);
+    }
+}
@@ -16,7 +16,7 @@ pub fn calculate_block_width(
 
     let total_horizontal = margin_left + margin_right + padding_left + padding_right + border_left + border_right;
 
-    let content_width = if let Some(w) = style.width {
+    let content_width = if let Some(w) = &style.width {
         w.to_px(containing_width)
     } else {
         (containing_width - total_horizontal).max(0.0)
@@ -57,7 +57,7 @@ pub fn calculate_block_position(
 }
 
 pub fn calculate_block_height(layout_box: &mut LayoutBox, containing_height: f32) {
-    if let Some(h) = layout_box.style.height {
+    if let Some(h) = &layout_box.style.height {
         layout_box.dimensions.content.height = h.to_px(containing_height);
     }
 }
@@ -0,0 +1,153 @@
+//! Text painting: a text box's shadows, its text, and the underlines,
+//! overlines and line-throughs decorating each of its lines.
+
+use crate::css::computed::ComputedStyle;
+use crate::render::painter::{DisplayList, Rect};
+use crate::render::text::TextRun;
+
+/// Where the lines run, in ems from the top of a line of text: just under
+/// the baseline, at the top of the em box, and through the middle of
+/// lowercase letters
+const UNDERLINE_TOP: f32 = 0.95;
+const OVERLINE_CENTER: f32 = 0.1;
+const LINE_THROUGH_CENTER: f32 = 0.62;
+
+/// Push a text box's shadows, text and decorations. `lines` holds the
+/// widths of the lines the run wraps into; the first starts after the
+/// indent.
+pub fn push_text(list: &mut DisplayList, run: TextRun, style: &ComputedStyle, lines: &[f32], opacity: f32) {
+    // The first shadow is drawn on top
+    for shadow in style.text_shadow.iter().rev() {
+        let shadow_run = TextRun {
+            x: run.x + shadow.offset_x,
+            y: run.y + shadow.offset_y,
+            color: shadow.color.unwrap_or(style.color),
+            ..run.clone()
+        };
+        list.push_text_shadow(shadow_run, shadow.blur_radius, opacity);
+    }
+
+    let decorations = decoration_rects(&run, style, lines);
+    let color = style.text_decoration_color.unwrap_or(style.color);
+    let decoration_style = style.text_decoration_style;
+
+    // Underlines and overlines go under the text, line-throughs over it
+    for rect in &decorations.under {
+        list.push_text_decoration(*rect, color, decoration_style, opacity);
+    }
+    list.push_text_run(run, opacity);
+    for rect in &decorations.through {
+        list.push_text_decoration(*rect, color, decoration_style, opacity);
+    }
+}
+
+/// The rects decoration lines fill
+#[derive(Debug, Default, PartialEq)]
+struct DecorationRects {
+    under: Vec<Rect>,
+    through: Vec<Rect>,
+}
+
+fn decoration_rects(run: &TextRun, style: &ComputedStyle, lines: &[f32]) -> DecorationRects {
+    let mut rects = DecorationRects::default();
+    let line = style.text_decoration_line;
+    if line.is_none() {
+        return rects;
+    }
+
+    let em = run.font_size;
+    let thickness = style.text_decoration_thickness.unwrap_or((em / 16.0).max(1.0));
+    let height = thickness * style.text_decoration_style.height_factor();
+    let line_height = em * 1.2;
+    for (i, width) in lines.iter().enumerate() {
+        let start = if i == 0 { run.spacing.indent } else { 0.0 };
+        if *width <= start {
+            continue;
+        }
+        let top = run.y + i as f32 * line_height;
+        let rect = |y: f32| Rect::new(run.x + start, y, width - start, height);
+        if line.underline {
+            rects.under.push(rect(top + em * UNDERLINE_TOP));
+        }
+        if line.overline {
+            rects.under.push(rect(top + em * OVERLINE_CENTER - height / 2.0));
+        }
+        if line.line_through {
+            rects.through.push(rect(top + em * LINE_THROUGH_CENTER - height / 2.0));
+        }
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::computed::{TextDecorationLine, TextShadow};
+    use crate::render::font::Font;
+    use crate::render::painter::{Color, DisplayCommand, TextDecorationStyle};
+    use crate::render::text::TextSpacing;
+
+    fn run(indent: f32) -> TextRun {
+        TextRun {
+            spacing: TextSpacing { indent, ..TextSpacing::default() },
+            ..TextRun::new("some text".to_string(), 10.0, 20.0, Color::BLACK, 20.0, Font::default())
+        }
+    }
+
+    #[test]
+    fn test_decoration_rects_per_line() {
+        let style = ComputedStyle {
+            text_decoration_line: TextDecorationLine {
+                underline: true,
+                line_through: true,
+                ..TextDecorationLine::default()
+            },
+            text_decoration_thickness: Some(2.0),
+            ..ComputedStyle::default()
+        };
+        let rects = decoration_rects(&run(5.0), &style, &[50.0, 30.0]);
+
+        // The first line starts after the indent; the second 1.2em lower
+        assert_eq!(rects.under, vec![Rect::new(15.0, 39.0, 45.0, 2.0), Rect::new(10.0, 63.0, 30.0, 2.0)]);
+        assert_eq!(rects.through.len(), 2);
+        assert!((rects.through[0].y - 31.4).abs() < 0.001);
+
+        // Wavy lines get room to swing
+        let style = ComputedStyle {
+            text_decoration_style: TextDecorationStyle::Wavy,
+            ..style
+        };
+        assert_eq!(decoration_rects(&run(0.0), &style, &[50.0]).under[0].height, 6.0);
+
+        let plain = ComputedStyle::default();
+        assert_eq!(decoration_rects(&run(0.0), &plain, &[50.0]), DecorationRects::default());
+    }
+
+    #[test]
+    fn test_push_text_order() {
+        let style = ComputedStyle {
+            color: Color::RED,
+            text_decoration_line: TextDecorationLine::UNDERLINE,
+            text_shadow: vec![
+                TextShadow { offset_x: 1.0, offset_y: 1.0, blur_radius: 0.0, color: None },
+                TextShadow { offset_x: 2.0, offset_y: 2.0, blur_radius: 3.0, color: Some(Color::BLACK) },
+            ],
+            ..ComputedStyle::default()
+        };
+        let mut list = DisplayList::new();
+        push_text(&mut list, run(0.0), &style, &[50.0], 1.0);
+
+        // Shadows last to first, then the underline, then the text
+        let kinds: Vec<_> = list
+            .commands
+            .iter()
+            .map(|command| match command {
+                DisplayCommand::TextShadow { run, .. } => format!("shadow {}", run.x),
+                DisplayCommand::TextDecoration { color, .. } => format!("line {:?}", *color == Color::RED),
+                DisplayCommand::Text { .. } => "text".to_string(),
+                _ => "other".to_string(),
+            })
+            .collect();
+        assert_eq!(kinds, vec!["shadow 12", "shadow 11", "line true", "text"]);
+    }
+}
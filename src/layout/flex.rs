@@ -67,7 +67,7 @@ pub fn layout_flex(
     );
 
     // Calculate container's content width
-    let content_width = style.width.map(|w| w.to_px(containing_width)).unwrap_or_else(|| {
+    let content_width = style.width.as_ref().map(|w| w.to_px(containing_width)).unwrap_or_else(|| {
         containing_width
             - layout_box.dimensions.margin.horizontal()
             - layout_box.dimensions.padding.horizontal()
@@ -208,7 +208,7 @@ pub fn layout_flex(
     apply_positions(&mut lines, is_row, &layout_box.dimensions.padding, &layout_box.dimensions.border);
 
     // Set container height
-    let container_height = style.height.map(|h| h.to_px(total_cross_size)).unwrap_or(total_cross_size);
+    let container_height = style.height.as_ref().map(|h| h.to_px(total_cross_size)).unwrap_or(total_cross_size);
     layout_box.dimensions.content.height = container_height;
 }
 
@@ -237,10 +237,10 @@ fn layout_child_intrinsic(child: &mut LayoutBox, containing_width: f32, text_ren
 
     // Calculate content size based on type
     if let Some(text) = &child.text_content {
-        let (width, height) = text_renderer.measure_text(text, style.font_size);
+        let (width, height) = text_renderer.measure_text(text, style.font_size, &style.font());
         child.dimensions.content.width = width;
         child.dimensions.content.height = height;
-    } else if let Some(w) = style.width {
+    } else if let Some(w) = &style.width {
         child.dimensions.content.width = w.to_px(containing_width);
     } else {
         // Use available width minus margins/padding/border for block-level
@@ -251,7 +251,7 @@ fn layout_child_intrinsic(child: &mut LayoutBox, containing_width: f32, text_ren
         child.dimensions.content.width = available.max(0.0);
     }
 
-    if let Some(h) = style.height {
+    if let Some(h) = &style.height {
         child.dimensions.content.height = h.to_px(child.dimensions.content.height);
     } else if child.text_content.is_none() {
         // For non-text elements without explicit height, calculate from children
@@ -0,0 +1,377 @@
+//! Generated content: the text of `::before`, `::after` and `::marker`
+//! boxes, with CSS counters and quote nesting tracked in document order.
+
+use crate::css::computed::{ComputedStyle, Content, ContentItem, Display, ListStyleType};
+use crate::css::selector::PseudoElement;
+use crate::css::StyleComputer;
+use crate::dom::node::ElementData;
+use crate::dom::{Document, NodeId};
+use std::collections::HashMap;
+
+/// The counter every list item increments, and `<ol>`/`<ul>` reset
+const LIST_ITEM: &str = "list-item";
+
+/// Counters and quote depth while walking the document in order
+#[derive(Debug, Default)]
+pub struct GeneratedContent {
+    /// Counter instances in scope by name, outermost first
+    counters: HashMap<String, Vec<i32>>,
+    /// Names of the counter instances created, in order
+    created: Vec<String>,
+    /// Length of `created` when each open element's children started; a
+    /// counter created by an element is in scope for its following siblings
+    /// too, so it is dropped when the parent's children end
+    scopes: Vec<usize>,
+    quote_depth: usize,
+}
+
+impl GeneratedContent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The state on reaching `target`: every element before it applied, and
+    /// its ancestors entered
+    pub fn at(document: &Document, style_computer: &StyleComputer, target: NodeId) -> Self {
+        let mut generated = Self::new();
+        generated.walk_to(document, style_computer, document.root, target);
+        generated
+    }
+
+    /// Applies `node_id`'s subtree, stopping at `target`. Returns true once
+    /// `target` is reached.
+    fn walk_to(&mut self, document: &Document, style_computer: &StyleComputer, node_id: NodeId, target: NodeId) -> bool {
+        if node_id == target {
+            return true;
+        }
+        let element = document.get_node(node_id).and_then(|node| node.as_element());
+        let style = style_computer.get_style(node_id);
+        if let (Some(element), Some(style)) = (element, style) {
+            if style.display == Display::None {
+                return false;
+            }
+            self.enter(element, style);
+        }
+
+        self.enter_children();
+        if let Some(element) = element {
+            for pseudo in [PseudoElement::Marker, PseudoElement::Before] {
+                if let Some(style) = style_computer.get_pseudo_style(node_id, pseudo) {
+                    self.text(element, style, pseudo);
+                }
+            }
+        }
+        for &child in document.children(node_id) {
+            if self.walk_to(document, style_computer, child, target) {
+                return true;
+            }
+        }
+        if let (Some(element), Some(style)) = (element, style_computer.get_pseudo_style(node_id, PseudoElement::After)) {
+            self.text(element, style, PseudoElement::After);
+        }
+        self.leave_children();
+        false
+    }
+
+    /// Applies an element's `counter-reset`, `counter-increment` and
+    /// `counter-set`, in that order, including the `list-item` counter of
+    /// list items and the `start` and `value` attributes of HTML lists
+    pub fn enter(&mut self, element: &ElementData, style: &ComputedStyle) {
+        for (name, value) in &style.counter_reset {
+            let value = match element.get_attribute("start") {
+                Some(start) if name == LIST_ITEM && element.tag_name == "ol" => {
+                    start.trim().parse::<i32>().map_or(*value, |start| start - 1)
+                }
+                _ => *value,
+            };
+            self.reset(name, value);
+        }
+
+        for (name, amount) in &style.counter_increment {
+            self.increment(name, *amount);
+        }
+        if style.display == Display::ListItem && !style.counter_increment.iter().any(|(name, _)| name == LIST_ITEM) {
+            self.increment(LIST_ITEM, 1);
+        }
+
+        for (name, value) in &style.counter_set {
+            *self.innermost(name) = *value;
+        }
+        if style.display == Display::ListItem {
+            if let Some(value) = element.get_attribute("value").and_then(|v| v.trim().parse::<i32>().ok()) {
+                *self.innermost(LIST_ITEM) = value;
+            }
+        }
+    }
+
+    /// Starts the children of the element last entered
+    pub fn enter_children(&mut self) {
+        self.scopes.push(self.created.len());
+    }
+
+    /// Ends the children of the element last entered, dropping the counters
+    /// they created
+    pub fn leave_children(&mut self) {
+        let start = self.scopes.pop().unwrap_or(0);
+        for name in self.created.drain(start..) {
+            if let Some(instances) = self.counters.get_mut(&name) {
+                instances.pop();
+            }
+        }
+    }
+
+    /// Creates a counter. A sibling's counter of the same name is replaced
+    /// rather than nested inside.
+    fn reset(&mut self, name: &str, value: i32) {
+        let start = self.scopes.last().copied().unwrap_or(0);
+        if self.created[start..].iter().any(|created| created == name) {
+            if let Some(innermost) = self.counters.get_mut(name).and_then(|instances| instances.last_mut()) {
+                *innermost = value;
+                return;
+            }
+        }
+        self.counters.entry(name.to_string()).or_default().push(value);
+        self.created.push(name.to_string());
+    }
+
+    fn increment(&mut self, name: &str, amount: i32) {
+        let innermost = self.innermost(name);
+        *innermost = innermost.saturating_add(amount);
+    }
+
+    /// The innermost counter named `name`, created at 0 if none is in scope
+    fn innermost(&mut self, name: &str) -> &mut i32 {
+        if self.counters.get(name).is_none_or(Vec::is_empty) {
+            self.reset(name, 0);
+        }
+        self.counters.get_mut(name).and_then(|instances| instances.last_mut()).unwrap()
+    }
+
+    fn counter(&self, name: &str) -> i32 {
+        self.counters
+            .get(name)
+            .and_then(|instances| instances.last())
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The text of a pseudo-element with the given style, after applying its
+    /// own counter properties. `None` when it generates no box.
+    pub fn text(&mut self, element: &ElementData, style: &ComputedStyle, pseudo: PseudoElement) -> Option<String> {
+        if style.display == Display::None {
+            return None;
+        }
+        self.enter(element, style);
+
+        match (&style.content, pseudo) {
+            (Content::Items(items), _) => Some(self.content_text(element, style, items)),
+            (Content::Normal, PseudoElement::Marker) => default_marker(style.list_style_type, self.counter(LIST_ITEM)),
+            (Content::Normal | Content::None, _) => None,
+        }
+    }
+
+    fn content_text(&mut self, element: &ElementData, style: &ComputedStyle, items: &[ContentItem]) -> String {
+        let mut text = String::new();
+        for item in items {
+            match item {
+                ContentItem::String(s) => text.push_str(s),
+                ContentItem::Attr(name) => text.push_str(element.get_attribute(name).unwrap_or("")),
+                ContentItem::Counter(name, list_style) => {
+                    text.push_str(&format_counter(self.counter(name), *list_style));
+                }
+                ContentItem::Counters(name, separator, list_style) => {
+                    let values = self.counters.get(name).filter(|instances| !instances.is_empty());
+                    let values: Vec<String> = match values {
+                        Some(instances) => instances.iter().map(|&v| format_counter(v, *list_style)).collect(),
+                        None => vec![format_counter(0, *list_style)],
+                    };
+                    text.push_str(&values.join(separator));
+                }
+                ContentItem::OpenQuote => {
+                    if let Some((open, _)) = style.quotes.pair(self.quote_depth) {
+                        text.push_str(open);
+                    }
+                    self.quote_depth += 1;
+                }
+                ContentItem::CloseQuote => {
+                    if self.quote_depth > 0 {
+                        self.quote_depth -= 1;
+                        if let Some((_, close)) = style.quotes.pair(self.quote_depth) {
+                            text.push_str(close);
+                        }
+                    }
+                }
+                ContentItem::NoOpenQuote => self.quote_depth += 1,
+                ContentItem::NoCloseQuote => self.quote_depth = self.quote_depth.saturating_sub(1),
+            }
+        }
+        text
+    }
+}
+
+/// A list item's marker when `content` doesn't replace it: the bullet, or
+/// the number followed by a period
+fn default_marker(list_style: ListStyleType, value: i32) -> Option<String> {
+    match list_style {
+        ListStyleType::None => None,
+        ListStyleType::Disc | ListStyleType::Circle | ListStyleType::Square => {
+            Some(format!("{} ", format_counter(value, list_style)))
+        }
+        _ => Some(format!("{}. ", format_counter(value, list_style))),
+    }
+}
+
+/// Formats a counter value in a list style. Values a style can't represent
+/// fall back to decimal.
+pub fn format_counter(value: i32, list_style: ListStyleType) -> String {
+    match list_style {
+        ListStyleType::None => String::new(),
+        ListStyleType::Disc => "\u{2022}".to_string(),
+        ListStyleType::Circle => "\u{25e6}".to_string(),
+        ListStyleType::Square => "\u{25aa}".to_string(),
+        ListStyleType::Decimal => value.to_string(),
+        ListStyleType::DecimalLeadingZero if (0..10).contains(&value) => format!("0{}", value),
+        ListStyleType::DecimalLeadingZero if (-9..0).contains(&value) => format!("-0{}", -value),
+        ListStyleType::DecimalLeadingZero => value.to_string(),
+        ListStyleType::LowerRoman => roman(value).map_or_else(|| value.to_string(), |r| r.to_lowercase()),
+        ListStyleType::UpperRoman => roman(value).unwrap_or_else(|| value.to_string()),
+        ListStyleType::LowerAlpha => alphabetic(value).unwrap_or_else(|| value.to_string()),
+        ListStyleType::UpperAlpha => alphabetic(value).map_or_else(|| value.to_string(), |a| a.to_uppercase()),
+    }
+}
+
+/// Upper-case roman numerals, for 1 to 3999
+fn roman(value: i32) -> Option<String> {
+    if !(1..=3999).contains(&value) {
+        return None;
+    }
+    const NUMERALS: [(i32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut remaining = value;
+    let mut result = String::new();
+    for (amount, numeral) in NUMERALS {
+        while remaining >= amount {
+            result.push_str(numeral);
+            remaining -= amount;
+        }
+    }
+    Some(result)
+}
+
+/// `a`, `b`, ... `z`, `aa`, `ab`, ... for positive values
+fn alphabetic(value: i32) -> Option<String> {
+    if value < 1 {
+        return None;
+    }
+    let mut remaining = value;
+    let mut letters = Vec::new();
+    while remaining > 0 {
+        remaining -= 1;
+        letters.push(b'a' + (remaining % 26) as u8);
+        remaining /= 26;
+    }
+    letters.reverse();
+    String::from_utf8(letters).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::{parse_css, Stylesheet};
+    use crate::dom::parse_html;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_format_counter() {
+        assert_eq!(format_counter(4, ListStyleType::LowerRoman), "iv");
+        assert_eq!(format_counter(1994, ListStyleType::UpperRoman), "MCMXCIV");
+        assert_eq!(format_counter(0, ListStyleType::UpperRoman), "0");
+        assert_eq!(format_counter(1, ListStyleType::LowerAlpha), "a");
+        assert_eq!(format_counter(27, ListStyleType::UpperAlpha), "AA");
+        assert_eq!(format_counter(7, ListStyleType::DecimalLeadingZero), "07");
+        assert_eq!(format_counter(-3, ListStyleType::Decimal), "-3");
+    }
+
+    /// The text of `pseudo` for each `tag` element, in document order
+    fn generated_texts(html: &str, css: &str, tag: &str, pseudo: PseudoElement) -> Vec<String> {
+        let doc = parse_html(html);
+        let mut computer = StyleComputer::new(800.0, 600.0);
+        computer.add_stylesheet(Rc::new(Stylesheet::user_agent()));
+        computer.add_stylesheet(Rc::new(parse_css(css)));
+        computer.compute_styles(&doc);
+
+        doc.get_elements_by_tag_name(tag)
+            .into_iter()
+            .filter_map(|id| {
+                let element = doc.get_node(id)?.as_element()?;
+                let mut generated = GeneratedContent::at(&doc, &computer, id);
+                generated.enter(element, computer.get_style(id)?);
+                generated.enter_children();
+                let marker = computer.get_pseudo_style(id, PseudoElement::Marker);
+                let marker = marker.and_then(|style| generated.text(element, style, PseudoElement::Marker));
+                match pseudo {
+                    PseudoElement::Marker => marker,
+                    _ => generated.text(element, computer.get_pseudo_style(id, pseudo)?, pseudo),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_list_markers() {
+        let html = r#"<ol start="3"><li>a</li><li value="10">b</li><li>c</li></ol>
+            <ul><li>d<ul><li>e</li></ul></li></ul>
+            <ol style="list-style-type: upper-roman"><li>f</li><li>g</li></ol>"#;
+        let markers = generated_texts(html, "", "li", PseudoElement::Marker);
+        assert_eq!(
+            markers,
+            vec!["3. ", "10. ", "11. ", "\u{2022} ", "\u{25e6} ", "I. ", "II. "]
+        );
+    }
+
+    #[test]
+    fn test_nested_counters_and_quotes() {
+        let css = r#"
+            ol { counter-reset: item; list-style: none; }
+            li { counter-increment: item; }
+            li::before { content: counters(item, ".") " " open-quote attr(title) close-quote; }
+        "#;
+        let html = r#"<ol><li title="x">a<ol><li title="y">b</li><li>c</li></ol></li><li>d</li></ol>"#;
+        let befores = generated_texts(html, css, "li", PseudoElement::Before);
+        assert_eq!(
+            befores,
+            vec![
+                "1 \u{201c}x\u{201d}",
+                "1.1 \u{201c}y\u{201d}",
+                "1.2 \u{201c}\u{201d}",
+                "2 \u{201c}\u{201d}",
+            ]
+        );
+        assert!(generated_texts(html, css, "li", PseudoElement::Marker).is_empty());
+    }
+
+    #[test]
+    fn test_sibling_resets_do_not_nest() {
+        let css = r#"
+            h2 { counter-reset: sub; counter-increment: sec; }
+            h3 { counter-increment: sub; }
+            h3::before { content: counters(sec, ".") "-" counter(sub, lower-alpha); }
+        "#;
+        let html = "<h2>A</h2><h3>a</h3><h3>b</h3><h2>B</h2><h3>c</h3>";
+        let befores = generated_texts(html, css, "h3", PseudoElement::Before);
+        assert_eq!(befores, vec!["1-a", "1-b", "2-a"]);
+    }
+}
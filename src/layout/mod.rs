@@ -1,6 +1,9 @@
+pub mod background;
 pub mod block;
 pub mod box_model;
+pub mod decoration;
 pub mod flex;
+pub mod generated;
 pub mod grid;
 pub mod inline;
 pub mod table;
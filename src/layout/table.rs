@@ -4,7 +4,7 @@
 //! This handles <table>, <tr>, <td>, <th>, <thead>, <tbody>, <tfoot> elements.
 
 use super::box_model::EdgeSizes;
-use super::tree::{BoxType, LayoutBox};
+use super::tree::{measure_text_box, BoxType, LayoutBox};
 use crate::render::text::TextRenderer;
 
 /// Layout a table element and all its children.
@@ -35,7 +35,7 @@ pub fn layout_table(layout_box: &mut LayoutBox, containing_width: f32, text_rend
     let border_collapse = style.border_collapse;
 
     // Calculate available content width
-    let available_width = style.width.map(|w| w.to_px(containing_width)).unwrap_or_else(|| {
+    let available_width = style.width.as_ref().map(|w| w.to_px(containing_width)).unwrap_or_else(|| {
         containing_width
             - layout_box.dimensions.margin.horizontal()
             - layout_box.dimensions.padding.horizontal()
@@ -133,7 +133,7 @@ pub fn layout_table(layout_box: &mut LayoutBox, containing_width: f32, text_rend
     }
 
     layout_box.dimensions.content.width = available_width;
-    layout_box.dimensions.content.height = style.height.map(|h| h.to_px(y_position)).unwrap_or(y_position);
+    layout_box.dimensions.content.height = style.height.as_ref().map(|h| h.to_px(y_position)).unwrap_or(y_position);
 }
 
 /// Collect row indices from table children (handles both direct rows and row groups)
@@ -283,7 +283,7 @@ fn layout_table_cell(cell: &mut LayoutBox, available_width: f32, text_renderer:
         child_y = child.dimensions.margin_box().bottom();
     }
 
-    cell.dimensions.content.height = style.height.map(|h| h.to_px(child_y)).unwrap_or(child_y);
+    cell.dimensions.content.height = style.height.as_ref().map(|h| h.to_px(child_y)).unwrap_or(child_y);
 }
 
 /// Layout a block-level child (fallback for non-table elements within tables)
@@ -309,7 +309,7 @@ fn layout_block_child(child: &mut LayoutBox, containing_width: f32, text_rendere
         style.border_left_width,
     );
 
-    let content_width = style.width.map(|w| w.to_px(containing_width)).unwrap_or_else(|| {
+    let content_width = style.width.as_ref().map(|w| w.to_px(containing_width)).unwrap_or_else(|| {
         containing_width
             - child.dimensions.margin.horizontal()
             - child.dimensions.padding.horizontal()
@@ -319,9 +319,11 @@ fn layout_block_child(child: &mut LayoutBox, containing_width: f32, text_rendere
     child.dimensions.content.width = content_width;
 
     // Handle text content
-    if let Some(ref text) = child.text_content {
-        let (_, height) = text_renderer.measure_text_fast(text, child.style.font_size, content_width);
-        child.dimensions.content.height = height;
+    if child.text_content.is_some() {
+        let indent = child.style.text_indent.to_px(content_width);
+        if let Some((_, height)) = measure_text_box(child, content_width, indent, text_renderer) {
+            child.dimensions.content.height = height;
+        }
     } else {
         // Layout nested children
         let mut child_y = 0.0;
@@ -331,6 +333,6 @@ fn layout_block_child(child: &mut LayoutBox, containing_width: f32, text_rendere
             nested.dimensions.content.y = child_y + nested.dimensions.margin.top;
             child_y = nested.dimensions.margin_box().bottom();
         }
-        child.dimensions.content.height = style.height.map(|h| h.to_px(child_y)).unwrap_or(child_y);
+        child.dimensions.content.height = style.height.as_ref().map(|h| h.to_px(child_y)).unwrap_or(child_y);
     }
 }
@@ -1,3 +1,4 @@
+use crate::render::font::Font;
 use crate::render::text::TextRenderer;
 
 pub struct TextLayout {
@@ -27,6 +28,7 @@ impl TextLayout {
         text: &str,
         max_width: f32,
         font_size: f32,
+        font: &Font,
         line_height: f32,
         text_renderer: &mut TextRenderer,
     ) -> Self {
@@ -37,10 +39,10 @@ impl TextLayout {
         let mut current_line = String::new();
         let mut line_width = 0.0;
 
-        let space_width = text_renderer.measure_text(" ", font_size).0;
+        let space_width = text_renderer.measure_text(" ", font_size, font).0;
 
         for word in words {
-            let (word_width, _) = text_renderer.measure_text(word, font_size);
+            let (word_width, _) = text_renderer.measure_text(word, font_size, font);
 
             if line_width + word_width > max_width && !current_line.is_empty() {
                 let height = font_size * line_height;
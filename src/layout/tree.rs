@@ -1,10 +1,19 @@
+use super::background::push_background;
 use super::box_model::{BoxDimensions, EdgeSizes};
-use crate::css::computed::{BoxSizing, Clear, ComputedStyle, Display, Float, LengthOrPercentage};
+use super::decoration::push_text;
+use super::generated::GeneratedContent;
+use crate::css::animation::{all_animatable, Invalidation};
+use crate::css::background::BackgroundImage;
+use crate::css::computed::{BoxSizing, Clear, ComputedStyle, Display, Float, LengthOrPercentage, ListStylePosition};
+use crate::css::properties;
+use crate::css::selector::PseudoElement;
 use crate::css::StyleComputer;
 use crate::dom::{Document, NodeData, NodeId};
-use crate::render::painter::{Color, DisplayList, Rect};
-use crate::render::text::TextRenderer;
+use crate::render::painter::{BorderSides, Color, CornerRadii, DisplayList, Rect};
+use crate::render::text::{TextRenderer, TextRun, TextSpacing};
 use crate::render::ImageSize;
+use std::collections::HashMap;
+use std::rc::Rc;
 
 /// Handles smooth scroll animation with momentum
 #[derive(Debug, Clone)]
@@ -125,6 +134,9 @@ pub enum BoxType {
     TableRow,
     TableCell,
     TableRowGroup,
+    /// An outside list marker: text placed before its list item's content
+    /// box, out of the flow
+    Marker,
 }
 
 pub struct LayoutBox {
@@ -133,7 +145,12 @@ pub struct LayoutBox {
     pub node_id: Option<NodeId>,
     pub children: Vec<LayoutBox>,
     pub text_content: Option<String>,
-    pub style: ComputedStyle,
+    /// The widths of the lines text content wraps into, and the spacing
+    /// it was measured with
+    pub text_lines: Vec<f32>,
+    pub text_spacing: TextSpacing,
+    /// Shared with the style computer and with boxes of the same style
+    pub style: Rc<ComputedStyle>,
     /// Image source URL (for img elements)
     pub image_src: Option<String>,
     /// Intrinsic image dimensions (for img elements)
@@ -150,20 +167,24 @@ impl LayoutBox {
             node_id,
             children: Vec::new(),
             text_content: None,
-            style: ComputedStyle::default(),
+            text_lines: Vec::new(),
+            text_spacing: TextSpacing::default(),
+            style: Rc::default(),
             image_src: None,
             intrinsic_size: None,
             texture_id: None,
         }
     }
 
-    pub fn new_text(text: String, style: ComputedStyle) -> Self {
+    pub fn new_text(text: String, style: Rc<ComputedStyle>) -> Self {
         Self {
             box_type: BoxType::Text,
             dimensions: BoxDimensions::new(),
             node_id: None,
             children: Vec::new(),
-            text_content: Some(text),
+            text_content: Some(style.text_transform.apply(&text)),
+            text_lines: Vec::new(),
+            text_spacing: TextSpacing::default(),
             style,
             image_src: None,
             intrinsic_size: None,
@@ -171,13 +192,15 @@ impl LayoutBox {
         }
     }
 
-    pub fn new_image(src: String, style: ComputedStyle, node_id: Option<NodeId>) -> Self {
+    pub fn new_image(src: String, style: Rc<ComputedStyle>, node_id: Option<NodeId>) -> Self {
         Self {
             box_type: BoxType::Image,
             dimensions: BoxDimensions::new(),
             node_id,
             children: Vec::new(),
             text_content: None,
+            text_lines: Vec::new(),
+            text_spacing: TextSpacing::default(),
             style,
             image_src: Some(src),
             intrinsic_size: None,
@@ -186,6 +209,77 @@ impl LayoutBox {
     }
 }
 
+/// The box of a `::before` or `::after` pseudo-element: a box of its own
+/// display type holding the generated text
+fn generated_box(text: String, style: Rc<ComputedStyle>) -> LayoutBox {
+    let box_type = match style.display {
+        Display::Block | Display::ListItem => BoxType::Block,
+        Display::InlineBlock => BoxType::InlineBlock,
+        Display::Flex => BoxType::Flex,
+        Display::Grid => BoxType::Grid,
+        _ => BoxType::Inline,
+    };
+    let mut layout_box = LayoutBox::new(box_type, None);
+    if !text.is_empty() {
+        layout_box.children.push(LayoutBox::new_text(text, Rc::clone(&style)));
+    }
+    layout_box.style = style;
+    layout_box
+}
+
+/// Inserts generated content as the first or last child. Inline content is
+/// wrapped in an anonymous inline box together with the inline content next
+/// to it, so the two share a line.
+fn insert_generated(children: &mut Vec<LayoutBox>, generated: LayoutBox, at_start: bool) {
+    let is_inline = |child: &LayoutBox| match child.box_type {
+        BoxType::Inline | BoxType::InlineBlock | BoxType::Image => true,
+        BoxType::Text => child.text_content.is_some(),
+        _ => false,
+    };
+    // Whitespace-only text leaves empty boxes, which don't separate lines
+    let is_empty = |child: &LayoutBox| child.box_type == BoxType::Text && child.text_content.is_none();
+
+    let neighbour = if at_start {
+        children.iter().position(|child| !is_empty(child))
+    } else {
+        children.iter().rposition(|child| !is_empty(child))
+    };
+    match neighbour {
+        Some(index) if is_inline(&generated) && is_inline(&children[index]) => {
+            let neighbour = children.remove(index);
+            let mut line = LayoutBox::new(BoxType::Inline, None);
+            line.children = if at_start {
+                vec![generated, neighbour]
+            } else {
+                vec![neighbour, generated]
+            };
+            children.insert(index, line);
+        }
+        _ if at_start => children.insert(0, generated),
+        _ => children.push(generated),
+    }
+}
+
+/// Breaks a text box's text into lines no wider than `max_width`, the
+/// first indented by `indent`, recording them for painting. Returns the
+/// width and height of the text.
+pub(super) fn measure_text_box(
+    layout_box: &mut LayoutBox,
+    max_width: f32,
+    indent: f32,
+    text_renderer: &mut TextRenderer,
+) -> Option<(f32, f32)> {
+    let text = layout_box.text_content.as_ref()?;
+    let style = &layout_box.style;
+    let spacing = style.text_spacing(indent);
+    let lines = text_renderer.text_lines_fast(text, style.font_size, &style.font(), spacing, max_width);
+    let width = lines.iter().fold(0.0f32, |max, width| max.max(*width)).min(max_width);
+    let height = style.font_size * 1.2 * lines.len() as f32;
+    layout_box.text_lines = lines;
+    layout_box.text_spacing = spacing;
+    Some((width, height))
+}
+
 /// Represents a positioned float
 #[derive(Debug, Clone)]
 struct FloatBox {
@@ -386,6 +480,8 @@ pub struct LayoutTree {
     pub scroll_animator: ScrollAnimator,
     pub scrollbar_config: ScrollbarConfig,
     pub scrollbar_state: ScrollbarState,
+    /// Texture IDs and sizes of loaded images by URL, for background layers
+    pub background_textures: HashMap<String, (usize, ImageSize)>,
 }
 
 impl LayoutTree {
@@ -398,6 +494,7 @@ impl LayoutTree {
             scroll_animator: ScrollAnimator::new(),
             scrollbar_config: ScrollbarConfig::default(),
             scrollbar_state: ScrollbarState::default(),
+            background_textures: HashMap::new(),
         }
     }
 
@@ -411,22 +508,12 @@ impl LayoutTree {
 
         if let Some(body) = document.get_body() {
             let t0 = Instant::now();
-            let mut root_box = self.build_layout_tree(document, body, style_computer);
+            let mut generated = GeneratedContent::at(document, style_computer, body);
+            let root_box = self.build_layout_tree(document, body, style_computer, &mut generated);
             let t1 = Instant::now();
-            let mut float_ctx = FloatContext::new();
-            self.layout(&mut root_box, self.viewport_width, text_renderer, &mut float_ctx);
+            self.layout_root(root_box, text_renderer);
             let t2 = Instant::now();
 
-            // Position root so border_box starts at (0, 0)
-            root_box.dimensions.content.x = root_box.dimensions.margin.left
-                + root_box.dimensions.border.left
-                + root_box.dimensions.padding.left;
-            root_box.dimensions.content.y = root_box.dimensions.margin.top
-                + root_box.dimensions.border.top
-                + root_box.dimensions.padding.top;
-
-            self.root = Some(root_box);
-
             println!(
                 "[Layout breakdown] build_tree={:.2}ms layout={:.2}ms",
                 (t1 - t0).as_secs_f32() * 1000.0,
@@ -435,11 +522,125 @@ impl LayoutTree {
         }
     }
 
+    /// Rebuilds only the boxes generated by the given nodes, then lays the
+    /// tree out again. Falls back to a full build when a node has no box of
+    /// its own to replace (e.g. it was `display: none` or is above `<body>`).
+    pub fn rebuild_subtrees(
+        &mut self,
+        document: &Document,
+        style_computer: &StyleComputer,
+        roots: &[NodeId],
+        text_renderer: &mut TextRenderer,
+    ) {
+        let Some(mut root_box) = self.root.take() else {
+            self.build(document, style_computer, text_renderer);
+            return;
+        };
+
+        for &node_id in roots {
+            // Counters and quotes continue from what precedes the subtree
+            let mut generated = GeneratedContent::at(document, style_computer, node_id);
+            let fresh = self.build_layout_tree(document, node_id, style_computer, &mut generated);
+            match Self::find_box_mut(&mut root_box, node_id) {
+                Some(existing) if fresh.node_id == Some(node_id) => *existing = fresh,
+                _ => {
+                    self.build(document, style_computer, text_renderer);
+                    return;
+                }
+            }
+        }
+
+        self.layout_root(root_box, text_renderer);
+    }
+
+    /// The box generated by `node_id`, searching depth-first
+    fn find_box_mut(layout_box: &mut LayoutBox, node_id: NodeId) -> Option<&mut LayoutBox> {
+        if layout_box.node_id == Some(node_id) {
+            return Some(layout_box);
+        }
+        layout_box
+            .children
+            .iter_mut()
+            .find_map(|child| Self::find_box_mut(child, node_id))
+    }
+
+    /// Gives the boxes of the given subtrees their nodes' current styles
+    /// without laying them out again, for changes that only affect painting
+    pub fn restyle_boxes(&mut self, style_computer: &StyleComputer, roots: &[NodeId]) {
+        if let Some(root) = &mut self.root {
+            Self::restyle_box(root, style_computer, roots, false, None);
+        }
+    }
+
+    /// `inherited` holds the old and new styles of the nearest element when
+    /// it was restyled. Boxes without a node of their own, such as text,
+    /// take its new values of the inherited properties painting depends on
+    /// where they had its old ones.
+    fn restyle_box(
+        layout_box: &mut LayoutBox,
+        style_computer: &StyleComputer,
+        roots: &[NodeId],
+        inside: bool,
+        inherited: Option<(&ComputedStyle, &ComputedStyle)>,
+    ) {
+        match layout_box.node_id {
+            Some(node_id) => {
+                let inside = inside || roots.contains(&node_id);
+                match style_computer.get_style(node_id).filter(|_| inside) {
+                    Some(style) => {
+                        let old = std::mem::replace(&mut layout_box.style, Rc::clone(style));
+                        for child in &mut layout_box.children {
+                            Self::restyle_box(child, style_computer, roots, true, Some((&old, style)));
+                        }
+                    }
+                    None => {
+                        for child in &mut layout_box.children {
+                            Self::restyle_box(child, style_computer, roots, inside, None);
+                        }
+                    }
+                }
+            }
+            None => {
+                if let Some((old, new)) = inherited {
+                    let painted = all_animatable()
+                        .iter()
+                        .filter(|property| property.invalidation == Invalidation::Repaint)
+                        .filter(|property| !property.differs(&layout_box.style, old))
+                        .filter_map(|property| properties::property(property.name))
+                        .filter(|property| property.inherited)
+                        .collect::<Vec<_>>();
+                    for property in painted {
+                        property.copy(Rc::make_mut(&mut layout_box.style), new);
+                    }
+                }
+                for child in &mut layout_box.children {
+                    Self::restyle_box(child, style_computer, roots, inside, inherited);
+                }
+            }
+        }
+    }
+
+    fn layout_root(&mut self, mut root_box: LayoutBox, text_renderer: &mut TextRenderer) {
+        let mut float_ctx = FloatContext::new();
+        self.layout(&mut root_box, self.viewport_width, text_renderer, &mut float_ctx);
+
+        // Position root so border_box starts at (0, 0)
+        root_box.dimensions.content.x = root_box.dimensions.margin.left
+            + root_box.dimensions.border.left
+            + root_box.dimensions.padding.left;
+        root_box.dimensions.content.y = root_box.dimensions.margin.top
+            + root_box.dimensions.border.top
+            + root_box.dimensions.padding.top;
+
+        self.root = Some(root_box);
+    }
+
     fn build_layout_tree(
         &self,
         document: &Document,
         node_id: NodeId,
         style_computer: &StyleComputer,
+        generated: &mut GeneratedContent,
     ) -> LayoutBox {
         let node = document.get_node(node_id).unwrap();
         let style = style_computer
@@ -501,8 +702,21 @@ impl LayoutTree {
                     Display::TableCell => BoxType::TableCell,
                     Display::TableRowGroup | Display::TableHeaderGroup | Display::TableFooterGroup => BoxType::TableRowGroup,
                     Display::TableCaption | Display::TableColumn | Display::TableColumnGroup => BoxType::Block,
+                    Display::ListItem => BoxType::Block,
                 };
 
+                generated.enter(elem, &style);
+                generated.enter_children();
+
+                // Generated content is worked out in document order, so
+                // counters see the element's children after ::before
+                let mut pseudo_text = |pseudo| {
+                    let style = style_computer.get_pseudo_style(node_id, pseudo)?;
+                    Some((generated.text(elem, style, pseudo)?, Rc::clone(style)))
+                };
+                let marker = pseudo_text(PseudoElement::Marker);
+                let before = pseudo_text(PseudoElement::Before);
+
                 let mut layout_box = LayoutBox::new(box_type, Some(node_id));
                 layout_box.style = style;
 
@@ -516,10 +730,36 @@ impl LayoutTree {
                         }
                     }
 
-                    let child_box = self.build_layout_tree(document, child_id, style_computer);
+                    let child_box = self.build_layout_tree(document, child_id, style_computer, generated);
                     layout_box.children.push(child_box);
                 }
 
+                let after = style_computer
+                    .get_pseudo_style(node_id, PseudoElement::After)
+                    .and_then(|style| Some((generated.text(elem, style, PseudoElement::After)?, Rc::clone(style))));
+                generated.leave_children();
+
+                if let Some((text, style)) = before {
+                    insert_generated(&mut layout_box.children, generated_box(text, style), true);
+                }
+                if let Some((text, style)) = after {
+                    insert_generated(&mut layout_box.children, generated_box(text, style), false);
+                }
+                if let Some((text, style)) = marker {
+                    match style.list_style_position {
+                        ListStylePosition::Outside => layout_box.children.insert(
+                            0,
+                            LayoutBox {
+                                box_type: BoxType::Marker,
+                                ..LayoutBox::new_text(text, style)
+                            },
+                        ),
+                        ListStylePosition::Inside => {
+                            insert_generated(&mut layout_box.children, LayoutBox::new_text(text, style), true)
+                        }
+                    }
+                }
+
                 layout_box
             }
             NodeData::Text(text) => {
@@ -542,8 +782,8 @@ impl LayoutTree {
             BoxType::Inline => {
                 self.layout_inline(layout_box, containing_width, text_renderer, float_ctx);
             }
-            BoxType::Text => {
-                self.layout_text(layout_box, containing_width, text_renderer);
+            BoxType::Text | BoxType::Marker => {
+                self.layout_text(layout_box, containing_width, 0.0, text_renderer);
             }
             BoxType::Anonymous => {
                 self.layout_block(layout_box, containing_width, text_renderer, float_ctx);
@@ -590,7 +830,7 @@ impl LayoutTree {
         );
 
         // Calculate content width based on box-sizing
-        let content_width = match style.width {
+        let content_width = match &style.width {
             Some(len_pct) => {
                 let specified_width = len_pct.to_px(containing_width);
                 match style.box_sizing {
@@ -614,11 +854,11 @@ impl LayoutTree {
 
         // Apply min/max width constraints
         let mut final_width = content_width;
-        if let Some(min_width) = style.min_width {
-            final_width = final_width.max(min_width);
+        if let Some(min_width) = &style.min_width {
+            final_width = final_width.max(min_width.to_px(containing_width));
         }
-        if let Some(max_width) = style.max_width {
-            final_width = final_width.min(max_width);
+        if let Some(max_width) = &style.max_width {
+            final_width = final_width.min(max_width.to_px(containing_width));
         }
 
         layout_box.dimensions.content.width = final_width;
@@ -629,7 +869,19 @@ impl LayoutTree {
         let mut child_float_ctx = FloatContext::new();
         let mut child_y = 0.0;
 
+        // text-indent indents the first line, here the first text in flow
+        let indent = style.text_indent.to_px(final_width);
+        let mut first_line = true;
+
         for child in &mut layout_box.children {
+            // An outside marker hangs to the left of the first line
+            if child.box_type == BoxType::Marker {
+                self.layout(child, final_width, text_renderer, &mut child_float_ctx);
+                child.dimensions.content.x = -child.dimensions.content.width;
+                child.dimensions.content.y = child_y;
+                continue;
+            }
+
             let child_float = child.style.float;
             let child_clear = child.style.clear;
 
@@ -642,7 +894,11 @@ impl LayoutTree {
             }
 
             // Layout the child first to get its dimensions
-            self.layout(child, final_width, text_renderer, &mut child_float_ctx);
+            if first_line && child.box_type == BoxType::Text && child_float == Float::None {
+                self.layout_text(child, final_width, indent, text_renderer);
+            } else {
+                self.layout(child, final_width, text_renderer, &mut child_float_ctx);
+            }
 
             let child_margin_box_width = child.dimensions.margin_box().width;
             let child_margin_box_height = child.dimensions.margin_box().height;
@@ -722,6 +978,9 @@ impl LayoutTree {
 
                     // Non-floated elements advance the Y cursor
                     child_y += child_margin_box_height;
+                    if child.box_type != BoxType::Text || child.text_content.is_some() {
+                        first_line = false;
+                    }
                 }
             }
         }
@@ -734,7 +993,7 @@ impl LayoutTree {
 
         // Calculate content height based on box-sizing
         // Note: percentage heights resolve against containing block height, but for now use a reasonable default
-        let content_height = match style.height {
+        let content_height = match &style.height {
             Some(len_pct) => {
                 let specified_height = len_pct.to_px(children_bottom); // Use children height as containing block for percentages
                 match style.box_sizing {
@@ -753,10 +1012,12 @@ impl LayoutTree {
 
         // Apply min/max height constraints
         let mut final_height = content_height;
-        if let Some(min_height) = style.min_height {
+        // The containing block's height isn't known here, so percentages
+        // are treated as for an indefinite height: no constraint
+        if let Some(min_height) = style.min_height.as_ref().and_then(LengthOrPercentage::as_px) {
             final_height = final_height.max(min_height);
         }
-        if let Some(max_height) = style.max_height {
+        if let Some(max_height) = style.max_height.as_ref().and_then(LengthOrPercentage::as_px) {
             final_height = final_height.min(max_height);
         }
 
@@ -778,11 +1039,8 @@ impl LayoutTree {
         layout_box.dimensions.content.height = max_height;
     }
 
-    fn layout_text(&self, layout_box: &mut LayoutBox, containing_width: f32, text_renderer: &mut TextRenderer) {
-        if let Some(text) = &layout_box.text_content {
-            let font_size = layout_box.style.font_size;
-            let (width, height) = text_renderer.measure_text_fast(text, font_size, containing_width);
-
+    fn layout_text(&self, layout_box: &mut LayoutBox, containing_width: f32, indent: f32, text_renderer: &mut TextRenderer) {
+        if let Some((width, height)) = measure_text_box(layout_box, containing_width, indent, text_renderer) {
             layout_box.dimensions.content.width = width;
             layout_box.dimensions.content.height = height;
         }
@@ -824,7 +1082,7 @@ impl LayoutTree {
             - layout_box.dimensions.border.horizontal();
 
         // Determine final dimensions based on CSS properties and intrinsic size
-        let (final_width, final_height) = match (style.width, style.height) {
+        let (final_width, final_height) = match (&style.width, &style.height) {
             (Some(w), Some(h)) => {
                 let w_px = w.to_px(containing_width);
                 let h_px = h.to_px(intrinsic_height); // height percentage relative to intrinsic
@@ -878,71 +1136,82 @@ impl LayoutTree {
     fn render_layout_box(&self, layout_box: &LayoutBox, list: &mut DisplayList, offset_x: f32, offset_y: f32) {
         let x = offset_x + layout_box.dimensions.content.x;
         let y = offset_y + layout_box.dimensions.content.y;
-        let opacity = layout_box.style.opacity;
-        let border_radius = layout_box.style.border_radius;
+        let border_box = layout_box.dimensions.border_box();
+        let border_rect = Rect::new(
+            offset_x + border_box.x,
+            offset_y + border_box.y,
+            border_box.width,
+            border_box.height,
+        );
+        let radii = layout_box.style.corner_radii(border_box.width, border_box.height);
+
+        // A transformed box is drawn with its descendants, then transformed;
+        // one squashed flat isn't drawn at all
+        let transform = layout_box.style.transform_matrix(&border_rect);
+        if let Some(transform) = transform {
+            if !transform.is_invertible() {
+                return;
+            }
+            list.push_transform(transform, paint_bounds(layout_box, offset_x, offset_y));
+        }
+
+        // Opacity, filters and blending apply to the box and its
+        // descendants as a group, so its commands are drawn opaque
+        let effects = layout_box.style.effects();
+        if let Some(effects) = &effects {
+            list.push_effects(effects.clone(), paint_bounds(layout_box, offset_x, offset_y), border_rect);
+        }
 
         // Render box shadow first (behind the element)
         if let Some(shadow) = &layout_box.style.box_shadow {
-            let border_box = layout_box.dimensions.border_box();
             list.push_box_shadow(
-                Rect::new(
-                    offset_x + border_box.x,
-                    offset_y + border_box.y,
-                    border_box.width,
-                    border_box.height,
-                ),
+                border_rect,
                 shadow.color,
                 shadow.offset_x,
                 shadow.offset_y,
                 shadow.blur_radius,
                 shadow.spread_radius,
-                border_radius,
+                radii,
             );
         }
 
-        if layout_box.style.background_color.a > 0.0 {
-            let border_box = layout_box.dimensions.border_box();
-            list.push_rect_with_radius(
-                Rect::new(
-                    offset_x + border_box.x,
-                    offset_y + border_box.y,
-                    border_box.width,
-                    border_box.height,
-                ),
-                layout_box.style.background_color,
-                border_radius,
-                opacity,
-            );
+        push_background(
+            list,
+            &layout_box.style,
+            &layout_box.dimensions,
+            (offset_x, offset_y),
+            Rect::new(0.0, 0.0, self.viewport_width, self.viewport_height),
+            &self.background_textures,
+        );
+
+        // Widths as laid out, which collapsed table borders may change
+        let border = &layout_box.dimensions.border;
+        let sides = BorderSides {
+            widths: [border.top, border.right, border.bottom, border.left],
+            colors: layout_box.style.border_colors(),
+            styles: layout_box.style.border_styles(),
+        };
+        if sides.is_visible() {
+            list.push_border_sides(border_rect, sides, radii, 1.0);
         }
 
-        if layout_box.dimensions.border.top > 0.0
-            || layout_box.dimensions.border.right > 0.0
-            || layout_box.dimensions.border.bottom > 0.0
-            || layout_box.dimensions.border.left > 0.0
-        {
-            let border_box = layout_box.dimensions.border_box();
-            list.push_border_with_radius(
-                Rect::new(
-                    offset_x + border_box.x,
-                    offset_y + border_box.y,
-                    border_box.width,
-                    border_box.height,
-                ),
-                layout_box.style.border_color,
-                layout_box.dimensions.border.top,
-                border_radius,
-            );
+        let (outline, reach) = layout_box.style.outline();
+        if outline.is_visible() {
+            list.push_border_sides(inflate(border_rect, reach), outline, CornerRadii::default(), 1.0);
         }
 
         if let Some(text) = &layout_box.text_content {
-            list.push_text_with_opacity(
-                text.clone(),
-                x,
-                y,
-                layout_box.style.color,
-                layout_box.style.font_size,
-                opacity,
-            );
+            let style = &layout_box.style;
+            let run = TextRun {
+                spacing: layout_box.text_spacing,
+                // Spaced text is broken into the same lines again when drawn
+                max_width: layout_box.dimensions.content.width + 0.01,
+                ..TextRun::new(text.clone(), x, y, style.color, style.font_size, style.font())
+            };
+            // Boxes sized other than by `measure_text_box` are one line
+            let one_line = [layout_box.dimensions.content.width];
+            let lines = if layout_box.text_lines.is_empty() { &one_line[..] } else { &layout_box.text_lines[..] };
+            push_text(list, run, style, lines, 1.0);
         }
 
         // Render image if this is an image box with a loaded texture
@@ -954,13 +1223,20 @@ impl LayoutTree {
                     layout_box.dimensions.content.width,
                     layout_box.dimensions.content.height,
                 );
-                list.push_image_with_opacity(content_rect, texture_id, opacity);
+                list.push_image_with_opacity(content_rect, texture_id, 1.0);
             }
         }
 
         for child in &layout_box.children {
             self.render_layout_box(child, list, x, y);
         }
+
+        if effects.is_some() {
+            list.pop_effects();
+        }
+        if transform.is_some() {
+            list.pop_transform();
+        }
     }
 
     pub fn hit_test(&self, x: f32, y: f32) -> Option<NodeId> {
@@ -982,6 +1258,13 @@ impl LayoutTree {
             border_box.height,
         );
 
+        // The point is taken back through the box's transform, into the
+        // space it and its descendants were laid out in
+        let (x, y) = match layout_box.style.transform_matrix(&rect) {
+            Some(transform) => transform.inverse_map(x, y)?,
+            None => (x, y),
+        };
+
         if rect.contains(x, y) {
             for child in layout_box.children.iter().rev() {
                 if let Some(node_id) = self.hit_test_box(child, x, y, box_x, box_y) {
@@ -1209,7 +1492,11 @@ impl LayoutTree {
     }
 
     fn collect_image_urls_recursive(layout_box: &LayoutBox, urls: &mut Vec<String>) {
-        if let Some(src) = &layout_box.image_src {
+        let backgrounds = layout_box.style.background.image.iter().flatten().filter_map(|image| match image {
+            BackgroundImage::Url(url) => Some(url),
+            BackgroundImage::Gradient(_) => None,
+        });
+        for src in layout_box.image_src.iter().chain(backgrounds) {
             if !urls.contains(src) {
                 urls.push(src.clone());
             }
@@ -1252,17 +1539,11 @@ impl LayoutTree {
             if visible_bottom < 0.0 || visible_y > vh {
                 // Still need to resolve URLs for the tree, just don't add to load list
                 if let Some(src) = &layout_box.image_src {
-                    let resolved = if src.starts_with("http://") || src.starts_with("https://") || src.starts_with("data:") {
-                        Some(src.clone())
-                    } else if let Some(base) = base_url {
-                        base.join(src).ok().map(|u| u.to_string())
-                    } else {
-                        None
-                    };
-                    if let Some(resolved_url) = resolved {
+                    if let Some(resolved_url) = resolve_url(src, base_url) {
                         layout_box.image_src = Some(resolved_url);
                     }
                 }
+                Self::resolve_background_urls(layout_box, base_url, &mut Vec::new());
                 // Recursively resolve children but don't add their URLs either
                 for child in &mut layout_box.children {
                     // For off-screen containers, check if any child might be visible
@@ -1273,16 +1554,7 @@ impl LayoutTree {
         }
 
         if let Some(src) = &layout_box.image_src {
-            // Resolve the URL
-            let resolved = if src.starts_with("http://") || src.starts_with("https://") || src.starts_with("data:") {
-                Some(src.clone())
-            } else if let Some(base) = base_url {
-                base.join(src).ok().map(|u| u.to_string())
-            } else {
-                None
-            };
-
-            if let Some(resolved_url) = resolved {
+            if let Some(resolved_url) = resolve_url(src, base_url) {
                 // Update the image_src to the resolved URL
                 layout_box.image_src = Some(resolved_url.clone());
                 if !urls.contains(&resolved_url) {
@@ -1290,13 +1562,41 @@ impl LayoutTree {
                 }
             }
         }
+        Self::resolve_background_urls(layout_box, base_url, urls);
         for child in &mut layout_box.children {
             Self::resolve_image_urls_recursive(child, base_url, urls, viewport_height, scroll_offset);
         }
     }
 
+    /// Resolve the `url()` background images of a box in place, adding them
+    /// to `urls`
+    fn resolve_background_urls(layout_box: &mut LayoutBox, base_url: Option<&url::Url>, urls: &mut Vec<String>) {
+        let mut relative = false;
+        for image in layout_box.style.background.image.iter().flatten() {
+            if let BackgroundImage::Url(src) = image {
+                if let Some(resolved_url) = resolve_url(src, base_url) {
+                    relative |= resolved_url != *src;
+                    if !urls.contains(&resolved_url) {
+                        urls.push(resolved_url);
+                    }
+                }
+            }
+        }
+        // Styles are shared, so only a box whose urls change gets a copy
+        if relative {
+            for image in Rc::make_mut(&mut layout_box.style).background.image.iter_mut().flatten() {
+                if let BackgroundImage::Url(src) = image {
+                    if let Some(resolved_url) = resolve_url(src, base_url) {
+                        *src = resolved_url;
+                    }
+                }
+            }
+        }
+    }
+
     /// Update texture IDs for images with a given URL
     pub fn update_image_texture(&mut self, url: &str, texture_id: usize, size: ImageSize) {
+        self.background_textures.insert(url.to_string(), (texture_id, size));
         if let Some(root) = &mut self.root {
             Self::update_image_texture_recursive(root, url, texture_id, size);
         }
@@ -1322,3 +1622,56 @@ impl LayoutTree {
         }
     }
 }
+
+/// `rect` grown by `amount` on every side
+fn inflate(rect: Rect, amount: f32) -> Rect {
+    Rect::new(rect.x - amount, rect.y - amount, rect.width + amount * 2.0, rect.height + amount * 2.0)
+}
+
+/// The area a box and its descendants draw in, before the box's own
+/// transform; descendants' transforms and filters are taken into account
+fn paint_bounds(layout_box: &LayoutBox, offset_x: f32, offset_y: f32) -> Rect {
+    let border_box = layout_box.dimensions.border_box();
+    let border_rect = Rect::new(offset_x + border_box.x, offset_y + border_box.y, border_box.width, border_box.height);
+    let mut bounds = border_rect;
+    let (outline, reach) = layout_box.style.outline();
+    if outline.is_visible() {
+        bounds = bounds.union(&inflate(border_rect, reach));
+    }
+    if let Some(shadow) = &layout_box.style.box_shadow {
+        let grow = shadow.spread_radius + shadow.blur_radius;
+        bounds = bounds.union(&Rect::new(
+            border_rect.x + shadow.offset_x - grow,
+            border_rect.y + shadow.offset_y - grow,
+            border_rect.width + grow * 2.0,
+            border_rect.height + grow * 2.0,
+        ));
+    }
+
+    let x = offset_x + layout_box.dimensions.content.x;
+    let y = offset_y + layout_box.dimensions.content.y;
+    for child in &layout_box.children {
+        let child_bounds = paint_bounds(child, x, y);
+        let child_box = child.dimensions.border_box();
+        let child_rect = Rect::new(x + child_box.x, y + child_box.y, child_box.width, child_box.height);
+        let child_bounds = match child.style.transform_matrix(&child_rect) {
+            Some(transform) => child_bounds.transformed(&transform).unwrap_or(child_bounds),
+            None => child_bounds,
+        };
+        bounds = bounds.union(&child_bounds);
+    }
+    match layout_box.style.effects() {
+        Some(effects) => effects.filter_bounds(&bounds),
+        None => bounds,
+    }
+}
+
+/// `src` made absolute against `base_url`; `None` if it's relative and
+/// there's no base
+fn resolve_url(src: &str, base_url: Option<&url::Url>) -> Option<String> {
+    if src.starts_with("http://") || src.starts_with("https://") || src.starts_with("data:") {
+        Some(src.to_string())
+    } else {
+        base_url.and_then(|base| base.join(src).ok().map(|u| u.to_string()))
+    }
+}
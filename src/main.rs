@@ -49,6 +49,27 @@ struct Args {
     /// Screenshot height (default: 768)
     #[arg(long = "height", default_value_t = 768)]
     height: u32,
+
+    /// Profile directory for persisted cookies (default: ~/.local/share/rust-browser)
+    #[arg(long = "profile")]
+    profile: Option<std::path::PathBuf>,
+
+    /// Time in seconds to stop transitions and animations at for the
+    /// screenshot (default: 0)
+    #[arg(long = "animation-time", default_value_t = 0.0)]
+    animation_time: f32,
+
+    /// Keep cookies in memory only
+    #[arg(long = "private", default_value_t = false, action = clap::ArgAction::SetTrue)]
+    private: bool,
+
+    /// Ask pages for their dark color scheme
+    #[arg(long = "dark", default_value_t = false, action = clap::ArgAction::SetTrue)]
+    dark: bool,
+
+    /// Override page colors with a high-contrast palette
+    #[arg(long = "forced-colors", default_value_t = false, action = clap::ArgAction::SetTrue)]
+    forced_colors: bool,
 }
 
 fn main() {
@@ -59,12 +80,19 @@ fn main() {
     let settings = BrowserSettings {
         js_enabled: args.js && !args.no_js,
         css_enabled: args.css && !args.no_css,
+        profile_dir: if args.private {
+            None
+        } else {
+            args.profile.clone().or_else(BrowserSettings::default_profile_dir)
+        },
+        dark_theme: args.dark,
+        forced_colors: args.forced_colors,
     };
 
     // Handle screenshot mode (headless rendering)
     if let Some(screenshot_path) = &args.screenshot {
         if let Some(url) = &args.url {
-            run_screenshot_mode(url, screenshot_path, args.width, args.height, args.debug, &settings);
+            run_screenshot_mode(url, screenshot_path, args.width, args.height, args.animation_time, args.debug, &settings);
             return;
         } else {
             eprintln!("Error: --screenshot requires a URL argument");
@@ -215,11 +243,11 @@ fn main() {
                 Event::MouseMotion { x, y, .. } => {
                     let logical_x = (x as f32 / scale_factor) as i32;
                     let logical_y = (y as f32 / scale_factor) as i32;
-                    browser.handle_mouse_move(logical_x, logical_y);
+                    browser.handle_mouse_move(logical_x, logical_y, &mut text_renderer);
                 }
 
                 Event::MouseButtonUp { .. } => {
-                    browser.handle_mouse_up();
+                    browser.handle_mouse_up(&mut text_renderer);
                 }
 
                 Event::Window {
@@ -244,8 +272,12 @@ fn main() {
 
         // Poll for async loading updates
         browser.poll_loading(&mut text_renderer);
+        browser.save_cookies_if_idle();
 
-        // Load pending images
+        // Load web fonts, then pending images
+        if browser.has_pending_fonts() {
+            browser.load_pending_fonts(&mut text_renderer);
+        }
         if browser.has_pending_images() {
             browser.load_pending_images(&gpu, &mut text_renderer);
         }
@@ -254,8 +286,9 @@ fn main() {
         let frame_time = last_frame_time.elapsed();
         let dt = frame_time.as_secs_f32();
 
-        // Update scroll animation
+        // Update scroll animation, then CSS transitions and animations
         browser.update_scroll(dt);
+        browser.update_animations(dt, &mut text_renderer);
 
         // Measure render time
         let render_start = Instant::now();
@@ -296,7 +329,15 @@ fn main() {
 }
 
 /// Run in screenshot mode - load URL and save screenshot to file
-fn run_screenshot_mode(url: &str, output_path: &str, width: u32, height: u32, debug: bool, settings: &BrowserSettings) {
+fn run_screenshot_mode(
+    url: &str,
+    output_path: &str,
+    width: u32,
+    height: u32,
+    animation_time: f32,
+    debug: bool,
+    settings: &BrowserSettings,
+) {
     use wgpu::*;
 
     let total_start = Instant::now();
@@ -365,6 +406,11 @@ fn run_screenshot_mode(url: &str, output_path: &str, width: u32, height: u32, de
         eprintln!("Warning: Loading timed out after 30 seconds");
     }
 
+    // Web fonts change layout, so they go before picking visible images
+    if browser.has_pending_fonts() {
+        browser.load_pending_fonts(&mut text_renderer);
+    }
+
     // Load only visible images (viewport culling + no re-layout for screenshot mode)
     let t5 = Instant::now();
     browser.collect_visible_images();
@@ -374,6 +420,9 @@ fn run_screenshot_mode(url: &str, output_path: &str, width: u32, height: u32, de
     }
     println!("[Timing] Image loading: {:.0}ms", t5.elapsed().as_secs_f32() * 1000.0);
 
+    // Freeze animations at a fixed time so screenshots are reproducible
+    browser.seek_animations(animation_time, &mut text_renderer);
+
     println!("Page loaded, rendering...");
 
     // Debug output
@@ -637,10 +686,10 @@ fn print_debug_info(browser: &Browser) {
                     i, rect.x, rect.y, rect.width, rect.height,
                     color.r, color.g, color.b, color.a);
             }
-            rust_browser::render::painter::DisplayCommand::Text { text, x, y, font_size, .. } => {
-                let preview: String = text.chars().take(30).collect();
+            rust_browser::render::painter::DisplayCommand::Text { run, .. } => {
+                let preview: String = run.text.chars().take(30).collect();
                 println!("  {}. Text({:.0},{:.0}) size={:.0} \"{}{}\"",
-                    i, x, y, font_size, preview, if text.len() > 30 { "..." } else { "" });
+                    i, run.x, run.y, run.font_size, preview, if run.text.len() > 30 { "..." } else { "" });
             }
             rust_browser::render::painter::DisplayCommand::Border { rect, .. } => {
                 println!("  {}. Border({:.0},{:.0} {}x{})",
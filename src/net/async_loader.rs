@@ -1,4 +1,8 @@
+use super::cookies::CookieJar;
+use super::http::{redirect_target, MAX_REDIRECTS};
 use futures_util::StreamExt;
+use reqwest::header::COOKIE;
+use reqwest::redirect::Policy;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -62,19 +66,31 @@ pub enum LoadProgress {
 /// Async HTTP loader that streams content and reports progress
 pub struct AsyncLoader {
     client: reqwest::Client,
+    cookie_jar: Arc<CookieJar>,
 }
 
 impl AsyncLoader {
     pub fn new() -> Self {
+        Self::with_cookie_jar(Arc::new(CookieJar::new()))
+    }
+
+    pub fn with_cookie_jar(cookie_jar: Arc<CookieJar>) -> Self {
         Self {
             client: reqwest::Client::builder()
                 .user_agent("RustBrowser/1.0")
+                // Redirects are followed in `send`, so each hop gets its own cookies
+                .redirect(Policy::none())
                 .build()
                 .expect("Failed to create HTTP client"),
+            cookie_jar,
         }
     }
 
-    /// Start loading a URL asynchronously
+    pub fn cookie_jar(&self) -> &Arc<CookieJar> {
+        &self.cookie_jar
+    }
+
+    /// Start loading a URL asynchronously as a top-level navigation
     /// Returns a channel receiver for progress updates
     pub fn load(
         &self,
@@ -83,16 +99,37 @@ impl AsyncLoader {
     ) -> mpsc::UnboundedReceiver<LoadProgress> {
         let (tx, rx) = mpsc::unbounded_channel();
         let client = self.client.clone();
+        let cookie_jar = Arc::clone(&self.cookie_jar);
 
         tokio::spawn(async move {
-            Self::load_internal(client, url, cancel_token, tx).await;
+            Self::load_internal(client, cookie_jar, url, cancel_token, tx).await;
         });
 
         rx
     }
 
+    /// GETs `url`, following redirects. Every hop sends the jar's cookies
+    /// for it and stores the cookies it sets.
+    async fn send(client: &reqwest::Client, cookie_jar: &CookieJar, url: &Url) -> Result<reqwest::Response, String> {
+        let mut url = url.clone();
+        for _ in 0..=MAX_REDIRECTS {
+            let mut request = client.get(url.clone());
+            if let Some(cookies) = cookie_jar.cookie_header(&url, None) {
+                request = request.header(COOKIE, cookies);
+            }
+            let response = request.send().await.map_err(|e| e.to_string())?;
+            cookie_jar.store_response_cookies(response.headers(), &url);
+            match redirect_target(&url, response.status(), response.headers()) {
+                Some(next) => url = next,
+                None => return Ok(response),
+            }
+        }
+        Err(format!("too many redirects, last to {}", url))
+    }
+
     async fn load_internal(
         client: reqwest::Client,
+        cookie_jar: Arc<CookieJar>,
         url: Url,
         cancel_token: CancelToken,
         tx: mpsc::UnboundedSender<LoadProgress>,
@@ -101,7 +138,7 @@ impl AsyncLoader {
         let start = Instant::now();
 
         // Send started event
-        let response = match client.get(url.clone()).send().await {
+        let response = match Self::send(&client, &cookie_jar, &url).await {
             Ok(resp) => resp,
             Err(e) => {
                 let _ = tx.send(LoadProgress::Error {
@@ -0,0 +1,664 @@
+use reqwest::header::{HeaderMap, SET_COOKIE};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use url::{Host, Url};
+
+/// File name of the persisted jar inside the profile directory
+pub const COOKIE_FILE: &str = "cookies.txt";
+
+/// How long the jar must go unchanged before it is written out, so a burst
+/// of `Set-Cookie` headers costs one write
+pub const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SameSite {
+    Strict,
+    /// Also used when the attribute is missing, as current browsers do
+    #[default]
+    Lax,
+    None,
+}
+
+/// Who is reading or writing cookies. Scripts never see `HttpOnly` cookies
+/// and cannot create them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CookieSource {
+    Http,
+    Script,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// Lowercase host or domain, without a leading dot
+    pub domain: String,
+    /// Set without a `Domain` attribute, so only sent to that exact host
+    pub host_only: bool,
+    pub path: String,
+    /// Unix time in seconds; `None` for session cookies
+    pub expires: Option<i64>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: SameSite,
+    /// Unix time in milliseconds, used to order the `Cookie` header
+    pub creation: i64,
+}
+
+impl Cookie {
+    /// Parses a `Set-Cookie` header received from `url` (RFC 6265 §5.2-5.3)
+    pub fn parse(header: &str, url: &Url) -> Option<Self> {
+        let mut parts = header.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let (name, value) = (name.trim(), value.trim());
+        // Tabs and newlines would also corrupt the lines of the cookie file
+        if name.is_empty()
+            || name.contains(['\t', '\n'])
+            || value.contains(['\t', '\n'])
+            || header.chars().any(|c| c.is_control() && c != '\t')
+        {
+            return None;
+        }
+
+        let host = url.host_str()?.to_ascii_lowercase();
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url),
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: SameSite::default(),
+            creation: now_millis(),
+        };
+
+        let mut max_age = None;
+        let mut expires = None;
+        let mut domain = None;
+        for attribute in parts {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "expires" => expires = parse_cookie_date(value),
+                "max-age" => {
+                    let valid = value.starts_with(|c: char| c.is_ascii_digit() || c == '-');
+                    if let Some(seconds) = value.parse::<i64>().ok().filter(|_| valid) {
+                        max_age = Some(seconds);
+                    }
+                }
+                "domain" => {
+                    let value = value.trim_start_matches('.').to_ascii_lowercase();
+                    if !value.is_empty() {
+                        domain = Some(value);
+                    }
+                }
+                "path" if value.starts_with('/') && !value.contains('\t') => cookie.path = value.to_string(),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                "samesite" => {
+                    cookie.same_site = match value.to_ascii_lowercase().as_str() {
+                        "strict" => SameSite::Strict,
+                        "none" => SameSite::None,
+                        _ => SameSite::Lax,
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Max-Age wins over Expires wherever it appears
+        cookie.expires = match max_age {
+            Some(seconds) if seconds <= 0 => Some(i64::MIN),
+            Some(seconds) => Some(now_seconds().saturating_add(seconds)),
+            None => expires,
+        };
+
+        if let Some(domain) = domain {
+            let is_ip = !matches!(url.host(), Some(Host::Domain(_)));
+            // Without a public suffix list, refuse at least single-label domains
+            if !domain_match(&host, &domain) || (domain != host && (is_ip || !domain.contains('.'))) {
+                return None;
+            }
+            cookie.domain = domain;
+            cookie.host_only = false;
+        }
+
+        Some(cookie)
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    fn matches(&self, url: &Url, source: CookieSource) -> bool {
+        let Some(host) = url.host_str().map(str::to_ascii_lowercase) else {
+            return false;
+        };
+        let host_ok = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(&host, &self.domain)
+        };
+        host_ok
+            && path_match(url.path(), &self.path)
+            && (!self.secure || is_secure(url))
+            && (!self.http_only || source == CookieSource::Http)
+    }
+
+    /// Same name, domain and path: a new cookie like this replaces the old one
+    fn same_identity(&self, other: &Cookie) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+}
+
+/// Cookie storage shared by every loader and tab. Persistent cookies are
+/// written back to the profile file once changes settle, and when the jar
+/// is dropped.
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    cookies: Mutex<Vec<Cookie>>,
+    file: Option<PathBuf>,
+    /// When the jar last changed, if it has not been saved since
+    unsaved: Mutex<Option<Instant>>,
+}
+
+impl CookieJar {
+    /// An in-memory jar that forgets everything on exit
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A jar backed by `file`, loading whatever it already holds
+    pub fn open(file: impl Into<PathBuf>) -> Self {
+        let file = file.into();
+        let now = now_seconds();
+        let cookies = match std::fs::read_to_string(&file) {
+            Ok(contents) => contents
+                .lines()
+                .filter(|line| !line.starts_with('#'))
+                .filter_map(deserialize)
+                .filter(|cookie| !cookie.is_expired(now))
+                .collect(),
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Failed to read cookies from {}: {}", file.display(), e);
+                }
+                Vec::new()
+            }
+        };
+        log::info!("Loaded {} cookies from {}", cookies.len(), file.display());
+
+        Self {
+            cookies: Mutex::new(cookies),
+            file: Some(file),
+            unsaved: Mutex::new(None),
+        }
+    }
+
+    /// Stores a `Set-Cookie` header (or a `document.cookie` assignment)
+    /// received from `url`. Returns whether the jar accepted it.
+    pub fn set_cookie(&self, header: &str, url: &Url, source: CookieSource) -> bool {
+        let Some(mut cookie) = Cookie::parse(header, url) else {
+            return false;
+        };
+
+        let secure_origin = is_secure(url);
+        let rejected = (cookie.secure && !secure_origin)
+            || (cookie.http_only && source == CookieSource::Script)
+            || (cookie.same_site == SameSite::None && !cookie.secure)
+            || (cookie.name.starts_with("__Secure-") && !cookie.secure)
+            || (cookie.name.starts_with("__Host-") && !(cookie.secure && cookie.host_only && cookie.path == "/"));
+        if rejected {
+            log::debug!("Rejected cookie {} from {}", cookie.name, url);
+            return false;
+        }
+
+        let mut cookies = self.cookies.lock().unwrap();
+        if let Some(index) = cookies.iter().position(|old| old.same_identity(&cookie)) {
+            let old = &cookies[index];
+            // Scripts cannot overwrite HttpOnly cookies, nor insecure pages secure ones
+            if (old.http_only && source == CookieSource::Script) || (old.secure && !secure_origin) {
+                return false;
+            }
+            cookie.creation = old.creation;
+            cookies.remove(index);
+        }
+
+        let now = now_seconds();
+        if !cookie.is_expired(now) {
+            cookies.push(cookie);
+        }
+        cookies.retain(|cookie| !cookie.is_expired(now));
+        drop(cookies);
+        self.mark_unsaved();
+        true
+    }
+
+    /// Stores the `Set-Cookie` headers of a response from `url`
+    pub fn store_response_cookies(&self, headers: &HeaderMap, url: &Url) {
+        for header in headers.get_all(SET_COOKIE) {
+            if let Ok(header) = header.to_str() {
+                self.set_cookie(header, url, CookieSource::Http);
+            }
+        }
+    }
+
+    /// Cookies to send to `url`, longest path first. `site` is the page that
+    /// made the request, or `None` for top-level navigations.
+    pub fn cookies_for(&self, url: &Url, site: Option<&Url>, source: CookieSource) -> Vec<Cookie> {
+        let now = now_seconds();
+        let cross_site = site.is_some_and(|site| !same_site(site, url));
+
+        let mut cookies: Vec<Cookie> = self
+            .cookies
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now) && cookie.matches(url, source))
+            .filter(|cookie| !cross_site || cookie.same_site == SameSite::None)
+            .cloned()
+            .collect();
+        cookies.sort_by(|a, b| b.path.len().cmp(&a.path.len()).then(a.creation.cmp(&b.creation)));
+        cookies
+    }
+
+    /// The `Cookie` request header value for `url`
+    pub fn cookie_header(&self, url: &Url, site: Option<&Url>) -> Option<String> {
+        let cookies = self.cookies_for(url, site, CookieSource::Http);
+        (!cookies.is_empty()).then(|| serialize_pairs(&cookies))
+    }
+
+    /// What `document.cookie` reads on a page at `url`
+    pub fn document_cookie(&self, url: &Url) -> String {
+        serialize_pairs(&self.cookies_for(url, None, CookieSource::Script))
+    }
+
+    pub fn clear(&self) {
+        self.cookies.lock().unwrap().clear();
+        self.mark_unsaved();
+    }
+
+    pub fn len(&self) -> usize {
+        self.cookies.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn mark_unsaved(&self) {
+        if self.file.is_some() {
+            *self.unsaved.lock().unwrap() = Some(Instant::now());
+        }
+    }
+
+    /// Saves the jar once it has gone `SAVE_DELAY` without changes; meant
+    /// to be called regularly from the event loop
+    pub fn save_if_idle(&self) {
+        let idle = self
+            .unsaved
+            .lock()
+            .unwrap()
+            .is_some_and(|changed| changed.elapsed() >= SAVE_DELAY);
+        if idle {
+            self.flush();
+        }
+    }
+
+    /// Writes unsaved changes to the profile file now. Only persistent
+    /// cookies are written; session cookies are dropped on exit.
+    pub fn flush(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        if self.unsaved.lock().unwrap().take().is_none() {
+            return;
+        }
+
+        let mut contents = String::from("# rust-browser cookie jar\n");
+        for cookie in self.cookies.lock().unwrap().iter().filter(|cookie| cookie.expires.is_some()) {
+            contents.push_str(&serialize(cookie));
+            contents.push('\n');
+        }
+
+        // Written next to the file and renamed over it, so the jar on disk
+        // is never half written
+        let temp = file.with_extension("txt.tmp");
+        let result = file
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&temp, contents))
+            .and_then(|_| std::fs::rename(&temp, file));
+        if let Err(e) = result {
+            log::warn!("Failed to save cookies to {}: {}", file.display(), e);
+        }
+    }
+}
+
+impl Drop for CookieJar {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+fn serialize_pairs(cookies: &[Cookie]) -> String {
+    cookies
+        .iter()
+        .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// One tab-separated line of the cookie file
+fn serialize(cookie: &Cookie) -> String {
+    let same_site = match cookie.same_site {
+        SameSite::Strict => "Strict",
+        SameSite::Lax => "Lax",
+        SameSite::None => "None",
+    };
+    [
+        cookie.domain.as_str(),
+        bool_field(cookie.host_only),
+        cookie.path.as_str(),
+        bool_field(cookie.secure),
+        bool_field(cookie.http_only),
+        same_site,
+        &cookie.expires.unwrap_or_default().to_string(),
+        &cookie.creation.to_string(),
+        cookie.name.as_str(),
+        cookie.value.as_str(),
+    ]
+    .join("\t")
+}
+
+fn deserialize(line: &str) -> Option<Cookie> {
+    let fields: Vec<&str> = line.splitn(10, '\t').collect();
+    let [domain, host_only, path, secure, http_only, same_site, expires, creation, name, value] = fields[..] else {
+        return None;
+    };
+    Some(Cookie {
+        name: name.to_string(),
+        value: value.to_string(),
+        domain: domain.to_string(),
+        host_only: host_only == "TRUE",
+        path: path.to_string(),
+        expires: Some(expires.parse().ok()?),
+        secure: secure == "TRUE",
+        http_only: http_only == "TRUE",
+        same_site: match same_site {
+            "Strict" => SameSite::Strict,
+            "None" => SameSite::None,
+            _ => SameSite::Lax,
+        },
+        creation: creation.parse().ok()?,
+    })
+}
+
+fn bool_field(value: bool) -> &'static str {
+    if value {
+        "TRUE"
+    } else {
+        "FALSE"
+    }
+}
+
+fn is_secure(url: &Url) -> bool {
+    matches!(url.scheme(), "https" | "wss")
+}
+
+/// RFC 6265 §5.1.3
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && host.parse::<std::net::IpAddr>().is_err())
+}
+
+/// RFC 6265 §5.1.4
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+/// The directory of the request path, used when there is no `Path` attribute
+fn default_path(url: &Url) -> String {
+    match url.path().rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => url.path()[..index].to_string(),
+    }
+}
+
+/// Approximates "same registrable domain" by the last two host labels
+fn same_site(a: &Url, b: &Url) -> bool {
+    fn site(url: &Url) -> Option<String> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        if !matches!(url.host(), Some(Host::Domain(_))) {
+            return Some(host);
+        }
+        let labels: Vec<&str> = host.rsplitn(3, '.').collect();
+        Some(labels.iter().take(2).rev().copied().collect::<Vec<_>>().join("."))
+    }
+    site(a) == site(b)
+}
+
+/// Parses a cookie date with the lenient algorithm of RFC 6265 §5.1.1,
+/// returning Unix seconds
+pub fn parse_cookie_date(input: &str) -> Option<i64> {
+    let is_delimiter = |c: char| {
+        matches!(c, '\t' | ' '..='/' | ';'..='@' | '['..='`' | '{'..='~')
+    };
+
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+    for token in input.split(is_delimiter).filter(|token| !token.is_empty()) {
+        if time.is_none() {
+            if let Some(parsed) = parse_time(token) {
+                time = Some(parsed);
+                continue;
+            }
+        }
+        if day.is_none() {
+            if let Some(parsed) = leading_number(token, 1, 2) {
+                day = Some(parsed);
+                continue;
+            }
+        }
+        if month.is_none() {
+            if let Some(parsed) = parse_month(token) {
+                month = Some(parsed);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some(parsed) = leading_number(token, 2, 4) {
+                year = Some(parsed);
+            }
+        }
+    }
+
+    let (hour, minute, second) = time?;
+    let (day, month) = (day?, month?);
+    let year = match year? {
+        year @ 70..=99 => year + 1900,
+        year @ 0..=69 => year + 2000,
+        year => year,
+    };
+    if !(1..=31).contains(&day) || year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    Some(days * 86_400 + hour * 3600 + minute * 60 + second)
+}
+
+fn parse_time(token: &str) -> Option<(i64, i64, i64)> {
+    let mut parts = token.splitn(3, ':');
+    let hour = parts.next()?;
+    let minute = parts.next()?;
+    let second = parts.next()?;
+    let exact = |part: &str| {
+        (1..=2).contains(&part.len()) && part.bytes().all(|b| b.is_ascii_digit())
+    };
+    if !exact(hour) || !exact(minute) {
+        return None;
+    }
+    Some((hour.parse().ok()?, minute.parse().ok()?, leading_number(second, 1, 2)?))
+}
+
+/// Leading digits of `token` if there are between `min` and `max` of them
+fn leading_number(token: &str, min: usize, max: usize) -> Option<i64> {
+    let digits = token.bytes().take_while(u8::is_ascii_digit).count();
+    if (min..=max).contains(&digits) {
+        token[..digits].parse().ok()
+    } else {
+        None
+    }
+}
+
+fn parse_month(token: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    let prefix = token.get(..3)?.to_ascii_lowercase();
+    MONTHS.iter().position(|m| *m == prefix).map(|i| i as u32 + 1)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date
+fn days_from_civil(year: i64, month: u32, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn now_seconds() -> i64 {
+    now_millis() / 1000
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_parse_cookie_date() {
+        assert_eq!(parse_cookie_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(784_111_777));
+        assert_eq!(parse_cookie_date("Sunday, 06-Nov-94 08:49:37 GMT"), Some(784_111_777));
+        assert_eq!(parse_cookie_date("Sun Nov  6 08:49:37 1994"), Some(784_111_777));
+        assert_eq!(parse_cookie_date("not a date"), None);
+        assert_eq!(parse_cookie_date("Sun, 32 Nov 1994 08:49:37 GMT"), None);
+    }
+
+    #[test]
+    fn test_domain_and_path_matching() {
+        let jar = CookieJar::new();
+        let origin = url("https://www.example.com/app/page");
+        assert!(jar.set_cookie("a=1", &origin, CookieSource::Http));
+        assert!(jar.set_cookie("b=2; Domain=.example.com; Path=/", &origin, CookieSource::Http));
+        assert!(!jar.set_cookie("c=3; Domain=other.com", &origin, CookieSource::Http));
+        assert!(!jar.set_cookie("d=4; Domain=com", &origin, CookieSource::Http));
+
+        // `a` defaults to the /app path and stays on its own host
+        assert_eq!(jar.cookie_header(&url("https://www.example.com/app/x"), None).as_deref(), Some("a=1; b=2"));
+        assert_eq!(jar.cookie_header(&url("https://api.example.com/app/x"), None).as_deref(), Some("b=2"));
+        assert_eq!(jar.cookie_header(&url("https://www.example.com/application"), None).as_deref(), Some("b=2"));
+        assert_eq!(jar.cookie_header(&url("https://example.org/"), None), None);
+    }
+
+    #[test]
+    fn test_expiry_replacement_and_secure() {
+        let jar = CookieJar::new();
+        let origin = url("https://example.com/");
+        jar.set_cookie("id=1; Max-Age=3600; Expires=Thu, 01 Jan 1970 00:00:00 GMT", &origin, CookieSource::Http);
+        assert_eq!(jar.document_cookie(&origin), "id=1");
+
+        jar.set_cookie("id=2", &origin, CookieSource::Http);
+        assert_eq!(jar.document_cookie(&origin), "id=2");
+
+        jar.set_cookie("id=gone; Max-Age=0", &origin, CookieSource::Http);
+        assert!(jar.is_empty());
+
+        jar.set_cookie("token=x; Secure", &origin, CookieSource::Http);
+        assert_eq!(jar.document_cookie(&url("http://example.com/")), "");
+        assert!(!jar.set_cookie("token=y", &url("http://example.com/"), CookieSource::Http));
+        assert!(!jar.set_cookie("other=z; Secure", &url("http://example.com/"), CookieSource::Http));
+    }
+
+    #[test]
+    fn test_http_only_hidden_from_scripts() {
+        let jar = CookieJar::new();
+        let origin = url("https://example.com/");
+        jar.set_cookie("session=secret; HttpOnly", &origin, CookieSource::Http);
+        jar.set_cookie("theme=dark", &origin, CookieSource::Script);
+
+        assert_eq!(jar.document_cookie(&origin), "theme=dark");
+        assert_eq!(jar.cookie_header(&origin, None).as_deref(), Some("session=secret; theme=dark"));
+        assert!(!jar.set_cookie("session=stolen", &origin, CookieSource::Script));
+        assert!(!jar.set_cookie("x=1; HttpOnly", &origin, CookieSource::Script));
+    }
+
+    #[test]
+    fn test_tabs_and_newlines_rejected() {
+        let jar = CookieJar::new();
+        let origin = url("https://example.com/");
+        assert!(!jar.set_cookie("na\tme=1", &origin, CookieSource::Http));
+        assert!(!jar.set_cookie("name=va\tlue", &origin, CookieSource::Script));
+        assert!(!jar.set_cookie("name=value\n", &origin, CookieSource::Script));
+        assert!(jar.set_cookie("name=value;\tPath=/a\tb", &origin, CookieSource::Http));
+        assert_eq!(jar.cookies_for(&origin, None, CookieSource::Http)[0].path, "/");
+    }
+
+    #[test]
+    fn test_same_site() {
+        let jar = CookieJar::new();
+        let origin = url("https://shop.example.com/");
+        jar.set_cookie("lax=1", &origin, CookieSource::Http);
+        jar.set_cookie("strict=2; SameSite=Strict", &origin, CookieSource::Http);
+        jar.set_cookie("none=3; SameSite=None; Secure", &origin, CookieSource::Http);
+        assert!(!jar.set_cookie("insecure=4; SameSite=None", &origin, CookieSource::Http));
+
+        let same_site_page = url("https://www.example.com/");
+        let cross_site_page = url("https://tracker.test/");
+        assert_eq!(jar.cookies_for(&origin, Some(&same_site_page), CookieSource::Http).len(), 3);
+        let cross: Vec<_> = jar.cookies_for(&origin, Some(&cross_site_page), CookieSource::Http);
+        assert_eq!(cross.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), ["none"]);
+    }
+
+    #[test]
+    fn test_persistent_cookies_survive_reopen() {
+        let file = std::env::temp_dir().join(format!("rust-browser-cookies-{}.txt", std::process::id()));
+        let _ = std::fs::remove_file(&file);
+        let origin = url("https://example.com/");
+
+        let jar = CookieJar::open(&file);
+        jar.set_cookie("kept=yes; Max-Age=3600; HttpOnly", &origin, CookieSource::Http);
+        jar.set_cookie("session=temporary", &origin, CookieSource::Http);
+        // Saving waits for the jar to settle or be dropped
+        jar.save_if_idle();
+        assert!(!file.exists());
+        drop(jar);
+
+        let reopened = CookieJar::open(&file);
+        let cookies = reopened.cookies_for(&origin, None, CookieSource::Http);
+        std::fs::remove_file(&file).unwrap();
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(cookies[0].name, "kept");
+        assert!(cookies[0].http_only);
+    }
+}
@@ -1,9 +1,18 @@
+use super::cookies::CookieJar;
 use super::loader::LoadError;
-use reqwest::blocking::Client;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{HeaderMap, ACCEPT, COOKIE, LOCATION};
+use reqwest::redirect::Policy;
+use reqwest::StatusCode;
+use std::sync::Arc;
 use url::Url;
 
+/// Redirects followed before a load fails, as in reqwest's default policy
+pub const MAX_REDIRECTS: usize = 10;
+
 pub struct HttpClient {
     client: Client,
+    cookie_jar: Arc<CookieJar>,
 }
 
 impl HttpClient {
@@ -11,31 +20,36 @@ impl HttpClient {
     const USER_AGENT: &'static str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
 
     pub fn new() -> Self {
+        Self::with_cookie_jar(Arc::new(CookieJar::new()))
+    }
+
+    pub fn with_cookie_jar(cookie_jar: Arc<CookieJar>) -> Self {
         let client = Client::builder()
             .user_agent(Self::USER_AGENT)
             .timeout(std::time::Duration::from_secs(30))
-            .cookie_store(true)
+            // Redirects are followed in `send`, so each hop gets its own cookies
+            .redirect(Policy::none())
             .build()
             .expect("Failed to create HTTP client");
 
-        Self { client }
+        Self { client, cookie_jar }
     }
 
-    pub fn get(&self, url: &Url) -> Result<String, LoadError> {
-        let response = self.client
-            .get(url.as_str())
-            .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
-            .send()?;
+    pub fn cookie_jar(&self) -> &Arc<CookieJar> {
+        &self.cookie_jar
+    }
+
+    /// Fetches `url` for the page at `site`, or for a top-level navigation
+    /// when `site` is `None`
+    pub fn get(&self, url: &Url, site: Option<&Url>) -> Result<String, LoadError> {
+        let response = self.send(url, site, "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")?;
         let text = response.text()?;
         Ok(text)
     }
 
-    pub fn get_bytes(&self, url: &Url) -> Result<Vec<u8>, LoadError> {
+    pub fn get_bytes(&self, url: &Url, site: Option<&Url>) -> Result<Vec<u8>, LoadError> {
         log::info!("Fetching bytes from: {}", url);
-        let response = self.client
-            .get(url.as_str())
-            .header("Accept", "image/avif,image/webp,image/apng,image/svg+xml,image/*,*/*;q=0.8")
-            .send()?;
+        let response = self.send(url, site, "image/avif,image/webp,image/apng,image/svg+xml,image/*,*/*;q=0.8")?;
 
         let status = response.status();
         let final_url = response.url().to_string();
@@ -51,6 +65,25 @@ impl HttpClient {
 
         Ok(bytes)
     }
+
+    /// GETs `url`, following redirects. Every hop sends the cookies the jar
+    /// has for it as seen from `site`, and stores the cookies it sets.
+    fn send(&self, url: &Url, site: Option<&Url>, accept: &str) -> Result<Response, LoadError> {
+        let mut url = url.clone();
+        for _ in 0..=MAX_REDIRECTS {
+            let mut request = self.client.get(url.as_str()).header(ACCEPT, accept);
+            if let Some(cookies) = self.cookie_jar.cookie_header(&url, site) {
+                request = request.header(COOKIE, cookies);
+            }
+            let response = request.send()?;
+            self.cookie_jar.store_response_cookies(response.headers(), &url);
+            match redirect_target(&url, response.status(), response.headers()) {
+                Some(next) => url = next,
+                None => return Ok(response),
+            }
+        }
+        Err(LoadError::TooManyRedirects(url.to_string()))
+    }
 }
 
 impl Default for HttpClient {
@@ -58,3 +91,84 @@ impl Default for HttpClient {
         Self::new()
     }
 }
+
+/// Where a redirect response from `url` sends the request next
+pub fn redirect_target(url: &Url, status: StatusCode, headers: &HeaderMap) -> Option<Url> {
+    let redirects = matches!(status.as_u16(), 301 | 302 | 303 | 307 | 308);
+    let location = headers.get(LOCATION)?.to_str().ok()?;
+    redirects.then(|| url.join(location).ok()).flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::CookieSource;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Serves `responses` in turn on a local port, returning its base URL
+    /// and the `Cookie` header of each request
+    fn serve(responses: Vec<&'static str>) -> (Url, std::thread::JoinHandle<Vec<Option<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = Url::parse(&format!("http://127.0.0.1:{}/", listener.local_addr().unwrap().port())).unwrap();
+        let handle = std::thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|response| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut cookie = None;
+                    for line in BufReader::new(&stream).lines() {
+                        let line = line.unwrap();
+                        if line.is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(": ") {
+                            if name.eq_ignore_ascii_case("cookie") {
+                                cookie = Some(value.to_string());
+                            }
+                        }
+                    }
+                    stream.write_all(response.as_bytes()).unwrap();
+                    cookie
+                })
+                .collect()
+        });
+        (base, handle)
+    }
+
+    #[test]
+    fn test_cross_site_subresource_omits_strict_cookies() {
+        const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok";
+        let (base, server) = serve(vec![OK, OK]);
+        let jar = Arc::new(CookieJar::new());
+        jar.set_cookie("strict=1; SameSite=Strict", &base, CookieSource::Http);
+        jar.set_cookie("lax=2", &base, CookieSource::Http);
+        let client = HttpClient::with_cookie_jar(Arc::clone(&jar));
+
+        let page = base.join("page").unwrap();
+        client.get(&base.join("same.css").unwrap(), Some(&page)).unwrap();
+        client.get_bytes(&base.join("cross.png").unwrap(), Some(&Url::parse("https://other.test/").unwrap())).unwrap();
+
+        let cookies = server.join().unwrap();
+        assert_eq!(cookies[0].as_deref(), Some("strict=1; lax=2"));
+        assert_eq!(cookies[1], None);
+    }
+
+    #[test]
+    fn test_redirect_hops_send_and_store_cookies() {
+        let (base, server) = serve(vec![
+            concat!(
+                "HTTP/1.1 302 Found\r\nLocation: /next\r\nSet-Cookie: hop=1\r\n",
+                "Content-Length: 0\r\nConnection: close\r\n\r\n",
+            ),
+            "HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\ndone",
+        ]);
+        let client = HttpClient::new();
+
+        assert_eq!(client.get(&base.join("start").unwrap(), None).unwrap(), "done");
+
+        let cookies = server.join().unwrap();
+        assert_eq!(cookies, vec![None, Some("hop=1".to_string())]);
+        assert_eq!(client.cookie_jar().len(), 1);
+    }
+}
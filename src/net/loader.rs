@@ -1,5 +1,7 @@
 use super::cache::Cache;
+use super::cookies::CookieJar;
 use super::http::HttpClient;
+use std::sync::Arc;
 use thiserror::Error;
 use url::Url;
 
@@ -13,6 +15,8 @@ pub enum LoadError {
     UnsupportedScheme(String),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("Too many redirects, last to {0}")]
+    TooManyRedirects(String),
 }
 
 pub struct Loader {
@@ -22,24 +26,34 @@ pub struct Loader {
 
 impl Loader {
     pub fn new() -> Self {
+        Self::with_cookie_jar(Arc::new(CookieJar::new()))
+    }
+
+    pub fn with_cookie_jar(cookie_jar: Arc<CookieJar>) -> Self {
         Self {
-            http_client: HttpClient::new(),
+            http_client: HttpClient::with_cookie_jar(cookie_jar),
             cache: Cache::new(),
         }
     }
 
-    pub fn fetch(&self, url: &Url) -> Result<String, LoadError> {
+    pub fn cookie_jar(&self) -> &Arc<CookieJar> {
+        self.http_client.cookie_jar()
+    }
+
+    /// Fetches `url` for the page at `site`, which decides whether
+    /// `SameSite` cookies are sent; `None` for top-level navigations
+    pub fn fetch(&self, url: &Url, site: Option<&Url>) -> Result<String, LoadError> {
         match url.scheme() {
-            "http" | "https" => self.http_client.get(url),
+            "http" | "https" => self.http_client.get(url, site),
             "file" => self.fetch_file(url),
             "data" => self.fetch_data(url),
             scheme => Err(LoadError::UnsupportedScheme(scheme.to_string())),
         }
     }
 
-    pub fn fetch_bytes(&self, url: &Url) -> Result<Vec<u8>, LoadError> {
+    pub fn fetch_bytes(&self, url: &Url, site: Option<&Url>) -> Result<Vec<u8>, LoadError> {
         match url.scheme() {
-            "http" | "https" => self.http_client.get_bytes(url),
+            "http" | "https" => self.http_client.get_bytes(url, site),
             "file" => {
                 let path = url.to_file_path().map_err(|_| {
                     LoadError::InvalidUrl("Cannot convert to file path".to_string())
@@ -1,7 +1,9 @@
 pub mod async_loader;
 pub mod cache;
+pub mod cookies;
 pub mod http;
 pub mod loader;
 
 pub use async_loader::{AsyncLoader, CancelToken, LoadProgress};
+pub use cookies::{CookieJar, CookieSource};
 pub use loader::Loader;
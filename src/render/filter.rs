@@ -0,0 +1,309 @@
+//! Filter effects and blend modes: how a group of content is changed as a
+//! whole before it is composited, and how it is mixed with what is behind
+//! it.
+
+use super::painter::{Color, Rect};
+
+/// How a group's colors mix with the colors behind it, as `mix-blend-mode`
+/// names them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 16] = [
+        BlendMode::Normal,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Overlay,
+        BlendMode::Darken,
+        BlendMode::Lighten,
+        BlendMode::ColorDodge,
+        BlendMode::ColorBurn,
+        BlendMode::HardLight,
+        BlendMode::SoftLight,
+        BlendMode::Difference,
+        BlendMode::Exclusion,
+        BlendMode::Hue,
+        BlendMode::Saturation,
+        BlendMode::Color,
+        BlendMode::Luminosity,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BlendMode::Normal => "normal",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Screen => "screen",
+            BlendMode::Overlay => "overlay",
+            BlendMode::Darken => "darken",
+            BlendMode::Lighten => "lighten",
+            BlendMode::ColorDodge => "color-dodge",
+            BlendMode::ColorBurn => "color-burn",
+            BlendMode::HardLight => "hard-light",
+            BlendMode::SoftLight => "soft-light",
+            BlendMode::Difference => "difference",
+            BlendMode::Exclusion => "exclusion",
+            BlendMode::Hue => "hue",
+            BlendMode::Saturation => "saturation",
+            BlendMode::Color => "color",
+            BlendMode::Luminosity => "luminosity",
+        }
+    }
+
+    /// The number the effects shader knows it by, its place in `ALL`
+    pub fn index(self) -> u32 {
+        BlendMode::ALL.iter().position(|mode| *mode == self).unwrap_or(0) as u32
+    }
+}
+
+/// A row-major 4x5 matrix taking a color's red, green, blue, alpha and 1
+/// to new red, green, blue and alpha, on colors not premultiplied by alpha
+pub type ColorMatrix = [[f32; 5]; 4];
+
+pub const IDENTITY_MATRIX: ColorMatrix = [
+    [1.0, 0.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0, 0.0],
+];
+
+/// One step of a filter chain, with lengths in logical pixels
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterOp {
+    /// Each pixel's color taken through a matrix, then clamped
+    ColorMatrix(ColorMatrix),
+    /// A gaussian blur with this standard deviation
+    Blur(f32),
+    /// The content's shape in `color`, blurred and offset, drawn behind it
+    DropShadow { offset_x: f32, offset_y: f32, blur: f32, color: Color },
+}
+
+impl FilterOp {
+    /// Scales the color channels, from black at 0
+    pub fn brightness(amount: f32) -> Self {
+        Self::linear([amount; 3], [0.0; 3])
+    }
+
+    /// Scales the color channels away from mid grey, to it at 0
+    pub fn contrast(amount: f32) -> Self {
+        Self::linear([amount; 3], [0.5 - 0.5 * amount; 3])
+    }
+
+    /// Towards the colors' luminance, entirely at 1
+    pub fn grayscale(amount: f32) -> Self {
+        let a = 1.0 - amount.clamp(0.0, 1.0);
+        Self::rgb([
+            [0.2126 + 0.7874 * a, 0.7152 - 0.7152 * a, 0.0722 - 0.0722 * a],
+            [0.2126 - 0.2126 * a, 0.7152 + 0.2848 * a, 0.0722 - 0.0722 * a],
+            [0.2126 - 0.2126 * a, 0.7152 - 0.7152 * a, 0.0722 + 0.9278 * a],
+        ])
+    }
+
+    /// Towards a brownish tint, entirely at 1
+    pub fn sepia(amount: f32) -> Self {
+        let a = 1.0 - amount.clamp(0.0, 1.0);
+        Self::rgb([
+            [0.393 + 0.607 * a, 0.769 - 0.769 * a, 0.189 - 0.189 * a],
+            [0.349 - 0.349 * a, 0.686 + 0.314 * a, 0.168 - 0.168 * a],
+            [0.272 - 0.272 * a, 0.534 - 0.534 * a, 0.131 + 0.869 * a],
+        ])
+    }
+
+    /// Scales saturation, from grey at 0
+    pub fn saturate(amount: f32) -> Self {
+        let s = amount;
+        Self::rgb([
+            [0.213 + 0.787 * s, 0.715 - 0.715 * s, 0.072 - 0.072 * s],
+            [0.213 - 0.213 * s, 0.715 + 0.285 * s, 0.072 - 0.072 * s],
+            [0.213 - 0.213 * s, 0.715 - 0.715 * s, 0.072 + 0.928 * s],
+        ])
+    }
+
+    /// Turns hues around the color wheel by `degrees`
+    pub fn hue_rotate(degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self::rgb([
+            [0.213 + cos * 0.787 - sin * 0.213, 0.715 - cos * 0.715 - sin * 0.715, 0.072 - cos * 0.072 + sin * 0.928],
+            [0.213 - cos * 0.213 + sin * 0.143, 0.715 + cos * 0.285 + sin * 0.140, 0.072 - cos * 0.072 - sin * 0.283],
+            [0.213 - cos * 0.213 - sin * 0.787, 0.715 - cos * 0.715 + sin * 0.715, 0.072 + cos * 0.928 + sin * 0.072],
+        ])
+    }
+
+    /// Towards the opposite colors, entirely at 1
+    pub fn invert(amount: f32) -> Self {
+        let a = amount.clamp(0.0, 1.0);
+        Self::linear([1.0 - 2.0 * a; 3], [a; 3])
+    }
+
+    /// Scales alpha
+    pub fn opacity(amount: f32) -> Self {
+        let mut matrix = IDENTITY_MATRIX;
+        matrix[3][3] = amount.clamp(0.0, 1.0);
+        FilterOp::ColorMatrix(matrix)
+    }
+
+    /// Each color channel times `scale`, plus `offset`
+    fn linear(scale: [f32; 3], offset: [f32; 3]) -> Self {
+        let mut matrix = IDENTITY_MATRIX;
+        for channel in 0..3 {
+            matrix[channel][channel] = scale[channel];
+            matrix[channel][4] = offset[channel];
+        }
+        FilterOp::ColorMatrix(matrix)
+    }
+
+    /// The color channels mixed by a 3x3 matrix, alpha kept
+    fn rgb(rows: [[f32; 3]; 3]) -> Self {
+        let mut matrix = IDENTITY_MATRIX;
+        for (row, values) in rows.iter().enumerate() {
+            matrix[row][..3].copy_from_slice(values);
+        }
+        FilterOp::ColorMatrix(matrix)
+    }
+
+    /// Where content within `bounds` may be drawn once filtered
+    pub fn filter_bounds(&self, bounds: &Rect) -> Rect {
+        // A gaussian fades to nothing three standard deviations out
+        let blurred = |rect: &Rect, sigma: f32| {
+            let grow = 3.0 * sigma.max(0.0);
+            Rect::new(rect.x - grow, rect.y - grow, rect.width + grow * 2.0, rect.height + grow * 2.0)
+        };
+        match self {
+            FilterOp::ColorMatrix(_) => *bounds,
+            FilterOp::Blur(sigma) => blurred(bounds, *sigma),
+            FilterOp::DropShadow { offset_x, offset_y, blur, .. } => {
+                let shadow = Rect::new(bounds.x + offset_x, bounds.y + offset_y, bounds.width, bounds.height);
+                bounds.union(&blurred(&shadow, *blur))
+            }
+        }
+    }
+
+    /// The color a pixel of `color` becomes, for color matrices
+    pub fn apply_to(&self, color: Color) -> Color {
+        let FilterOp::ColorMatrix(matrix) = self else {
+            return color;
+        };
+        let input = [color.r, color.g, color.b, color.a, 1.0];
+        let [r, g, b, a] = matrix.map(|row| row.iter().zip(input).map(|(m, c)| m * c).sum::<f32>().clamp(0.0, 1.0));
+        Color { r, g, b, a }
+    }
+}
+
+/// What a group of content is drawn with as a whole: it is drawn on its
+/// own, filtered, faded by `opacity`, then blended with what is behind it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Effects {
+    pub opacity: f32,
+    pub filter: Vec<FilterOp>,
+    /// Applied to what is behind the group, where the group's box is
+    pub backdrop_filter: Vec<FilterOp>,
+    pub blend_mode: BlendMode,
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self {
+            opacity: 1.0,
+            filter: Vec::new(),
+            backdrop_filter: Vec::new(),
+            blend_mode: BlendMode::Normal,
+        }
+    }
+}
+
+impl Effects {
+    /// Whether drawing the group as a whole changes nothing
+    pub fn is_none(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether only the opacity is changed, which a group of one command
+    /// can take directly
+    pub fn is_opacity_only(&self) -> bool {
+        Effects { opacity: 1.0, ..self.clone() }.is_none()
+    }
+
+    /// Whether compositing the group reads what is behind it
+    pub fn needs_backdrop(&self) -> bool {
+        self.blend_mode != BlendMode::Normal || !self.backdrop_filter.is_empty()
+    }
+
+    /// Where content within `bounds` may be drawn once filtered
+    pub fn filter_bounds(&self, bounds: &Rect) -> Rect {
+        self.filter.iter().fold(*bounds, |bounds, op| op.filter_bounds(&bounds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_color(color: Color, [r, g, b, a]: [f32; 4]) {
+        let close = |x: f32, y: f32| (x - y).abs() < 1e-3;
+        assert!(
+            close(color.r, r) && close(color.g, g) && close(color.b, b) && close(color.a, a),
+            "{:?} != {:?}",
+            color,
+            [r, g, b, a]
+        );
+    }
+
+    #[test]
+    fn test_color_matrices() {
+        let orange = Color { r: 1.0, g: 0.5, b: 0.0, a: 0.8 };
+        assert_color(FilterOp::grayscale(1.0).apply_to(orange), [0.5702, 0.5702, 0.5702, 0.8]);
+        assert_color(FilterOp::grayscale(0.0).apply_to(orange), [1.0, 0.5, 0.0, 0.8]);
+        assert_color(FilterOp::invert(1.0).apply_to(orange), [0.0, 0.5, 1.0, 0.8]);
+        assert_color(FilterOp::brightness(0.5).apply_to(orange), [0.5, 0.25, 0.0, 0.8]);
+        assert_color(FilterOp::contrast(0.0).apply_to(orange), [0.5, 0.5, 0.5, 0.8]);
+        assert_color(FilterOp::opacity(0.5).apply_to(orange), [1.0, 0.5, 0.0, 0.4]);
+        assert_color(FilterOp::saturate(1.0).apply_to(orange), [1.0, 0.5, 0.0, 0.8]);
+        assert_color(FilterOp::hue_rotate(0.0).apply_to(orange), [1.0, 0.5, 0.0, 0.8]);
+        assert_color(FilterOp::hue_rotate(360.0).apply_to(orange), [1.0, 0.5, 0.0, 0.8]);
+
+        // Results are clamped
+        assert_color(FilterOp::brightness(3.0).apply_to(orange), [1.0, 1.0, 0.0, 0.8]);
+    }
+
+    #[test]
+    fn test_effects() {
+        assert!(Effects::default().is_none());
+        let faded = Effects { opacity: 0.5, ..Effects::default() };
+        assert!(!faded.is_none() && faded.is_opacity_only() && !faded.needs_backdrop());
+        let multiplied = Effects { blend_mode: BlendMode::Multiply, ..Effects::default() };
+        assert!(!multiplied.is_opacity_only() && multiplied.needs_backdrop());
+        assert_eq!(BlendMode::Luminosity.index(), 15);
+
+        // A blur spreads three standard deviations; a shadow reaches past
+        // the content by its offset
+        let effects = Effects {
+            filter: vec![
+                FilterOp::Blur(2.0),
+                FilterOp::DropShadow { offset_x: 10.0, offset_y: 0.0, blur: 0.0, color: Color::BLACK },
+            ],
+            ..Effects::default()
+        };
+        assert_eq!(
+            effects.filter_bounds(&Rect::new(0.0, 0.0, 100.0, 50.0)),
+            Rect::new(-6.0, -6.0, 122.0, 62.0)
+        );
+    }
+}
@@ -0,0 +1,500 @@
+//! Fonts as computed styles describe them, how they map onto cosmic-text,
+//! and decoding of downloaded font files for `@font-face`
+
+use cosmic_text::fontdb::{Database, FaceInfo, Language, Source};
+use cosmic_text::{Attrs, Family, Stretch, Style, Weight};
+use flate2::read::ZlibDecoder;
+use std::io::Read;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// An entry of a `font-family` list
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FontFamily {
+    Named(String),
+    Serif,
+    SansSerif,
+    Monospace,
+    Cursive,
+    Fantasy,
+}
+
+impl FontFamily {
+    /// A family from an unquoted name, where the generic families are
+    /// keywords
+    pub fn from_keyword(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "serif" | "ui-serif" => FontFamily::Serif,
+            "sans-serif" | "system-ui" | "ui-sans-serif" | "ui-rounded" => FontFamily::SansSerif,
+            "monospace" | "ui-monospace" => FontFamily::Monospace,
+            "cursive" => FontFamily::Cursive,
+            "fantasy" => FontFamily::Fantasy,
+            _ => FontFamily::Named(name.to_string()),
+        }
+    }
+
+    fn family(&self) -> Family<'_> {
+        match self {
+            FontFamily::Named(name) => Family::Name(name),
+            FontFamily::Serif => Family::Serif,
+            FontFamily::SansSerif => Family::SansSerif,
+            FontFamily::Monospace => Family::Monospace,
+            FontFamily::Cursive => Family::Cursive,
+            FontFamily::Fantasy => Family::Fantasy,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FontStyle {
+    #[default]
+    Normal,
+    Italic,
+    Oblique,
+}
+
+impl FontStyle {
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword.to_ascii_lowercase().as_str() {
+            "normal" => Some(FontStyle::Normal),
+            "italic" => Some(FontStyle::Italic),
+            "oblique" => Some(FontStyle::Oblique),
+            _ => None,
+        }
+    }
+
+    fn style(self) -> Style {
+        match self {
+            FontStyle::Normal => Style::Normal,
+            FontStyle::Italic => Style::Italic,
+            FontStyle::Oblique => Style::Oblique,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FontStretch {
+    UltraCondensed,
+    ExtraCondensed,
+    Condensed,
+    SemiCondensed,
+    #[default]
+    Normal,
+    SemiExpanded,
+    Expanded,
+    ExtraExpanded,
+    UltraExpanded,
+}
+
+impl FontStretch {
+    const ALL: [(FontStretch, &'static str, f32); 9] = [
+        (FontStretch::UltraCondensed, "ultra-condensed", 50.0),
+        (FontStretch::ExtraCondensed, "extra-condensed", 62.5),
+        (FontStretch::Condensed, "condensed", 75.0),
+        (FontStretch::SemiCondensed, "semi-condensed", 87.5),
+        (FontStretch::Normal, "normal", 100.0),
+        (FontStretch::SemiExpanded, "semi-expanded", 112.5),
+        (FontStretch::Expanded, "expanded", 125.0),
+        (FontStretch::ExtraExpanded, "extra-expanded", 150.0),
+        (FontStretch::UltraExpanded, "ultra-expanded", 200.0),
+    ];
+
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        let keyword = keyword.to_ascii_lowercase();
+        Self::ALL.iter().find(|(_, name, _)| *name == keyword).map(|(stretch, _, _)| *stretch)
+    }
+
+    /// The keyword nearest to a width percentage, as fonts only come in
+    /// these widths
+    pub fn from_percentage(percent: f32) -> Self {
+        Self::ALL
+            .iter()
+            .min_by(|a, b| (a.2 - percent).abs().total_cmp(&(b.2 - percent).abs()))
+            .map(|(stretch, _, _)| *stretch)
+            .unwrap_or_default()
+    }
+
+    fn stretch(self) -> Stretch {
+        match self {
+            FontStretch::UltraCondensed => Stretch::UltraCondensed,
+            FontStretch::ExtraCondensed => Stretch::ExtraCondensed,
+            FontStretch::Condensed => Stretch::Condensed,
+            FontStretch::SemiCondensed => Stretch::SemiCondensed,
+            FontStretch::Normal => Stretch::Normal,
+            FontStretch::SemiExpanded => Stretch::SemiExpanded,
+            FontStretch::Expanded => Stretch::Expanded,
+            FontStretch::ExtraExpanded => Stretch::ExtraExpanded,
+            FontStretch::UltraExpanded => Stretch::UltraExpanded,
+        }
+    }
+}
+
+/// Everything about the font of a run of text but its size
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Font {
+    /// `font-family` in order of preference; the first one with faces
+    /// installed is used
+    pub families: Rc<[FontFamily]>,
+    pub weight: u16,
+    pub style: FontStyle,
+    pub stretch: FontStretch,
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self {
+            families: Rc::from([FontFamily::SansSerif]),
+            weight: 400,
+            style: FontStyle::Normal,
+            stretch: FontStretch::Normal,
+        }
+    }
+}
+
+impl Font {
+    /// The first of the families `db` has faces for, named as `db` names
+    /// it. Generic families always match; with none available this falls
+    /// back to sans-serif.
+    pub fn available_family(&self, db: &Database) -> FontFamily {
+        self.families
+            .iter()
+            .find_map(|family| match family {
+                FontFamily::Named(name) => installed_name(db, name).map(FontFamily::Named),
+                generic => Some(generic.clone()),
+            })
+            .unwrap_or(FontFamily::SansSerif)
+    }
+
+    /// Attributes to shape text in this font with, in `family` as picked
+    /// by `available_family`
+    pub fn attrs<'a>(&self, family: &'a FontFamily) -> Attrs<'a> {
+        Attrs::new()
+            .family(family.family())
+            .weight(Weight(self.weight))
+            .style(self.style.style())
+            .stretch(self.stretch.stretch())
+    }
+}
+
+/// How `db` spells a family name, which CSS compares case-insensitively
+fn installed_name(db: &Database, name: &str) -> Option<String> {
+    db.faces()
+        .flat_map(|face| face.families.iter())
+        .find(|(family, _)| family.eq_ignore_ascii_case(name))
+        .map(|(family, _)| family.clone())
+}
+
+/// Points the generic families at installed fonts, keeping each one that
+/// already is
+pub fn map_generic_families(db: &mut Database) {
+    const SERIF: &[&str] = &["DejaVu Serif", "Liberation Serif", "Noto Serif", "Times New Roman", "Georgia"];
+    const SANS_SERIF: &[&str] = &["DejaVu Sans", "Liberation Sans", "Noto Sans", "Arial", "Helvetica"];
+    const MONOSPACE: &[&str] = &["DejaVu Sans Mono", "Liberation Mono", "Noto Sans Mono", "Courier New", "Menlo"];
+    const CURSIVE: &[&str] = &["Comic Sans MS", "Comic Neue", "URW Chancery L"];
+    const FANTASY: &[&str] = &["Impact", "Papyrus", "Luminari"];
+
+    let pick = |db: &Database, family: Family, candidates: &[&str]| {
+        let current = db.family_name(&family).to_string();
+        std::iter::once(current.as_str())
+            .chain(candidates.iter().copied())
+            .find_map(|name| installed_name(db, name))
+    };
+    if let Some(name) = pick(db, Family::Serif, SERIF) {
+        db.set_serif_family(name);
+    }
+    if let Some(name) = pick(db, Family::SansSerif, SANS_SERIF) {
+        db.set_sans_serif_family(name);
+    }
+    if let Some(name) = pick(db, Family::Monospace, MONOSPACE) {
+        db.set_monospace_family(name);
+    }
+    if let Some(name) = pick(db, Family::Cursive, CURSIVE) {
+        db.set_cursive_family(name);
+    }
+    if let Some(name) = pick(db, Family::Fantasy, FANTASY) {
+        db.set_fantasy_family(name);
+    }
+}
+
+/// What an `@font-face` rule says about the faces its font provides
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct FaceDescriptors {
+    pub family: String,
+    /// These override what the font file says when given
+    pub weight: Option<u16>,
+    pub style: Option<FontStyle>,
+    pub stretch: Option<FontStretch>,
+}
+
+impl FaceDescriptors {
+    /// Files `info` under the rule's family, with the rule's descriptors
+    fn describe(&self, info: &mut FaceInfo) {
+        info.families = vec![(self.family.clone(), Language::English_UnitedStates)];
+        if let Some(weight) = self.weight {
+            info.weight = Weight(weight);
+        }
+        if let Some(style) = self.style {
+            info.style = style.style();
+        }
+        if let Some(stretch) = self.stretch {
+            info.stretch = stretch.stretch();
+        }
+    }
+}
+
+/// Adds the faces of a decoded font to `db` under the rule's family name.
+/// Returns how many faces it had.
+pub fn register_face(db: &mut Database, data: Arc<Vec<u8>>, descriptors: &FaceDescriptors) -> usize {
+    let ids = db.load_font_source(Source::Binary(data));
+    for &id in &ids {
+        let Some(mut info) = db.face(id).cloned() else {
+            continue;
+        };
+        db.remove_face(id);
+        descriptors.describe(&mut info);
+        db.push_face_info(info);
+    }
+    ids.len()
+}
+
+/// Makes the installed faces `local(name)` refers to, by family or
+/// PostScript name, available under the rule's family as well. Returns
+/// how many there were.
+pub fn register_local_face(db: &mut Database, name: &str, descriptors: &FaceDescriptors) -> usize {
+    let faces: Vec<FaceInfo> = db
+        .faces()
+        .filter(|face| {
+            face.post_script_name.eq_ignore_ascii_case(name)
+                || face.families.iter().any(|(family, _)| family.eq_ignore_ascii_case(name))
+        })
+        .cloned()
+        .collect();
+    let count = faces.len();
+    for mut info in faces {
+        descriptors.describe(&mut info);
+        db.push_face_info(info);
+    }
+    count
+}
+
+/// Whether a `format()` hint names a format `decode_font` reads
+pub fn is_supported_format(format: &str) -> bool {
+    matches!(format, "woff" | "truetype" | "opentype" | "collection")
+}
+
+/// The sfnt data of a downloaded font file: TrueType and OpenType data as
+/// it is, WOFF decompressed. WOFF2 needs a Brotli decoder, which we lack,
+/// so a rule should list another format to fall back on.
+pub fn decode_font(data: &[u8]) -> Result<Vec<u8>, String> {
+    match data.get(..4) {
+        Some(b"wOFF") => decode_woff(data),
+        Some(b"wOF2") => Err("WOFF2 fonts are not supported".to_string()),
+        Some([0, 1, 0, 0] | b"OTTO" | b"true" | b"ttcf") => Ok(data.to_vec()),
+        _ => Err("Unrecognized font format".to_string()),
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, String> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| "Truncated WOFF file".to_string())
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, String> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| "Truncated WOFF file".to_string())
+}
+
+/// Rebuilds the sfnt a WOFF 1.0 file wraps: the same tables, each
+/// inflated if it was compressed
+fn decode_woff(data: &[u8]) -> Result<Vec<u8>, String> {
+    const HEADER_SIZE: usize = 44;
+    const ENTRY_SIZE: usize = 20;
+
+    let flavor = read_u32(data, 4)?;
+    let num_tables = read_u16(data, 12)? as usize;
+
+    let mut tables = Vec::with_capacity(num_tables);
+    for i in 0..num_tables {
+        let entry = HEADER_SIZE + i * ENTRY_SIZE;
+        let tag = read_u32(data, entry)?;
+        let offset = read_u32(data, entry + 4)? as usize;
+        let compressed_length = read_u32(data, entry + 8)? as usize;
+        let length = read_u32(data, entry + 12)? as usize;
+        let checksum = read_u32(data, entry + 16)?;
+
+        let stored = data
+            .get(offset..offset + compressed_length)
+            .ok_or_else(|| "Truncated WOFF file".to_string())?;
+        let table = if compressed_length < length {
+            let mut table = Vec::with_capacity(length);
+            ZlibDecoder::new(stored)
+                .read_to_end(&mut table)
+                .map_err(|e| format!("Corrupt WOFF table: {}", e))?;
+            table
+        } else {
+            stored.to_vec()
+        };
+        if table.len() != length {
+            return Err("Corrupt WOFF table".to_string());
+        }
+        tables.push((tag, checksum, table));
+    }
+
+    // Offset table, then the table records, then 4-byte aligned table data
+    let entry_selector = (num_tables.max(1) as u32).ilog2() as u16;
+    let search_range = (1u16 << entry_selector) * 16;
+    let mut sfnt = Vec::new();
+    sfnt.extend_from_slice(&flavor.to_be_bytes());
+    sfnt.extend_from_slice(&(num_tables as u16).to_be_bytes());
+    sfnt.extend_from_slice(&search_range.to_be_bytes());
+    sfnt.extend_from_slice(&entry_selector.to_be_bytes());
+    sfnt.extend_from_slice(&(num_tables as u16 * 16 - search_range).to_be_bytes());
+
+    let mut offset = 12 + num_tables * 16;
+    for (tag, checksum, table) in &tables {
+        sfnt.extend_from_slice(&tag.to_be_bytes());
+        sfnt.extend_from_slice(&checksum.to_be_bytes());
+        sfnt.extend_from_slice(&(offset as u32).to_be_bytes());
+        sfnt.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        offset += table.len().next_multiple_of(4);
+    }
+    for (_, _, table) in &tables {
+        sfnt.extend_from_slice(table);
+        sfnt.resize(sfnt.len().next_multiple_of(4), 0);
+    }
+    Ok(sfnt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// A WOFF file of the given tables, compressing those that shrink
+    fn woff(tables: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(b"wOFF");
+        header.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        header.extend_from_slice(&0u32.to_be_bytes());
+        header.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+        header.resize(44, 0);
+
+        let mut directory = Vec::new();
+        let mut body = Vec::new();
+        let data_start = 44 + tables.len() * 20;
+        for (tag, table) in tables {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(table).unwrap();
+            let compressed = encoder.finish().unwrap();
+            let stored = if compressed.len() < table.len() { compressed } else { table.to_vec() };
+
+            directory.extend_from_slice(*tag);
+            directory.extend_from_slice(&((data_start + body.len()) as u32).to_be_bytes());
+            directory.extend_from_slice(&(stored.len() as u32).to_be_bytes());
+            directory.extend_from_slice(&(table.len() as u32).to_be_bytes());
+            directory.extend_from_slice(&7u32.to_be_bytes());
+            body.extend_from_slice(&stored);
+            body.resize(body.len().next_multiple_of(4), 0);
+        }
+        [header, directory, body].concat()
+    }
+
+    #[test]
+    fn test_decode_woff() {
+        let repetitive = [b'a'; 100];
+        let sfnt = decode_font(&woff(&[(b"cmap", &repetitive), (b"head", b"xyz")])).unwrap();
+
+        assert_eq!(read_u32(&sfnt, 0), Ok(0x0001_0000));
+        assert_eq!(read_u16(&sfnt, 4), Ok(2));
+        assert_eq!(read_u16(&sfnt, 6), Ok(32));
+        // Records: tag, checksum, offset, length
+        assert_eq!(&sfnt[12..16], b"cmap");
+        assert_eq!(read_u32(&sfnt, 16), Ok(7));
+        let cmap_offset = read_u32(&sfnt, 20).unwrap() as usize;
+        assert_eq!(cmap_offset, 44);
+        assert_eq!(&sfnt[cmap_offset..cmap_offset + 100], &repetitive[..]);
+        assert_eq!(&sfnt[28..32], b"head");
+        let head_offset = read_u32(&sfnt, 36).unwrap() as usize;
+        assert_eq!(head_offset, 144);
+        assert_eq!(read_u32(&sfnt, 40), Ok(3));
+        assert_eq!(&sfnt[head_offset..head_offset + 3], b"xyz");
+        assert_eq!(sfnt.len(), 148);
+    }
+
+    #[test]
+    fn test_decode_font_formats() {
+        assert_eq!(decode_font(b"OTTO rest"), Ok(b"OTTO rest".to_vec()));
+        assert!(decode_font(b"wOF2....").is_err());
+        assert!(decode_font(b"<html>").is_err());
+        // A table running past the end of the file
+        let mut truncated = woff(&[(b"head", b"xyz")]);
+        truncated.truncate(60);
+        assert!(decode_font(&truncated).is_err());
+    }
+
+    #[test]
+    fn test_font_keywords() {
+        assert_eq!(FontFamily::from_keyword("Monospace"), FontFamily::Monospace);
+        assert_eq!(FontFamily::from_keyword("system-ui"), FontFamily::SansSerif);
+        assert_eq!(FontFamily::from_keyword("Inter"), FontFamily::Named("Inter".to_string()));
+        assert_eq!(FontStyle::from_keyword("ITALIC"), Some(FontStyle::Italic));
+        assert_eq!(FontStretch::from_keyword("semi-condensed"), Some(FontStretch::SemiCondensed));
+        assert_eq!(FontStretch::from_percentage(80.0), FontStretch::Condensed);
+        assert_eq!(FontStretch::from_percentage(300.0), FontStretch::UltraExpanded);
+    }
+
+    #[test]
+    fn test_register_face_under_css_name() {
+        let mut system = Database::new();
+        system.load_system_fonts();
+        let Some(face) = system.faces().next() else {
+            return;
+        };
+        let data = system.with_face_data(face.id, |data, _| data.to_vec()).unwrap();
+
+        let mut db = Database::new();
+        let descriptors = FaceDescriptors {
+            family: "My Web Font".to_string(),
+            weight: Some(700),
+            style: Some(FontStyle::Italic),
+            stretch: None,
+        };
+        assert!(register_face(&mut db, Arc::new(decode_font(&data).unwrap()), &descriptors) > 0);
+
+        let font = Font {
+            families: Rc::from([FontFamily::Named("my web font".to_string()), FontFamily::Serif]),
+            ..Font::default()
+        };
+        let family = font.available_family(&db);
+        assert_eq!(family, FontFamily::Named("My Web Font".to_string()));
+        let info = db.faces().next().unwrap();
+        assert_eq!(info.weight, Weight(700));
+        assert_eq!(info.style, Style::Italic);
+
+        // local() finds installed faces by family name
+        let local_family = face.families[0].0.clone();
+        let descriptors = FaceDescriptors {
+            family: "Local Alias".to_string(),
+            ..FaceDescriptors::default()
+        };
+        assert!(register_local_face(&mut system, &local_family.to_uppercase(), &descriptors) > 0);
+        assert_eq!(installed_name(&system, "local alias"), Some("Local Alias".to_string()));
+        assert_eq!(register_local_face(&mut system, "Not Installed", &descriptors), 0);
+
+        // Missing families fall through to the next, then to sans-serif
+        let font = Font {
+            families: Rc::from([FontFamily::Named("Not Installed".to_string()), FontFamily::Monospace]),
+            ..Font::default()
+        };
+        assert_eq!(font.available_family(&db), FontFamily::Monospace);
+        let font = Font {
+            families: Rc::from([FontFamily::Named("Not Installed".to_string())]),
+            ..Font::default()
+        };
+        assert_eq!(font.available_family(&db), FontFamily::SansSerif);
+    }
+}
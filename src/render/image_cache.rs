@@ -230,6 +230,11 @@ pub fn resolve_image_url(src: &str, base: Option<&Url>) -> Option<String> {
 
 /// Decode a data URL image
 pub fn decode_data_url(data_url: &str) -> Result<DynamicImage, String> {
+    decode_image(&data_url_bytes(data_url)?)
+}
+
+/// The payload of a data URL
+pub fn data_url_bytes(data_url: &str) -> Result<Vec<u8>, String> {
     // Format: data:[<mediatype>][;base64],<data>
     let data_url = data_url.strip_prefix("data:").ok_or("Invalid data URL")?;
 
@@ -245,7 +250,7 @@ pub fn decode_data_url(data_url: &str) -> Result<DynamicImage, String> {
         urlencoding::decode_binary(data.as_bytes()).into_owned()
     };
 
-    decode_image(&bytes)
+    Ok(bytes)
 }
 
 #[cfg(test)]
@@ -1,10 +1,14 @@
+pub mod filter;
+pub mod font;
 pub mod gpu;
 pub mod image_cache;
 pub mod painter;
 pub mod text;
 pub mod texture;
+pub mod transform;
 
 pub use gpu::GpuContext;
-pub use image_cache::{ImageCache, ImageState, ImageSize, decode_image, resolve_image_url, decode_data_url};
+pub use image_cache::{ImageCache, ImageState, ImageSize, decode_image, resolve_image_url, decode_data_url, data_url_bytes};
 pub use painter::{DisplayList, DisplayCommand, Painter};
 pub use text::TextRenderer;
+pub use transform::Transform;
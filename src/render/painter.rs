@@ -1,5 +1,10 @@
+use super::filter::{BlendMode, Effects, FilterOp};
+use super::font::Font;
 use super::gpu::GpuContext;
+use super::text::TextRun;
+use super::transform::Transform;
 use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
 use wgpu::*;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -73,7 +78,7 @@ impl Color {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct Rect {
     pub x: f32,
     pub y: f32,
@@ -97,6 +102,215 @@ impl Rect {
     pub fn bottom(&self) -> f32 {
         self.y + self.height
     }
+
+    /// The overlap of two rects, if they overlap
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        (right > x && bottom > y).then(|| Rect::new(x, y, right - x, bottom - y))
+    }
+
+    /// The smallest rect covering both
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect::new(x, y, self.right().max(other.right()) - x, self.bottom().max(other.bottom()) - y)
+    }
+
+    /// The bounding box of the rect's corners under `transform`, if none of
+    /// them goes behind the viewer
+    pub fn transformed(&self, transform: &Transform) -> Option<Rect> {
+        let corners = [
+            transform.map_point(self.x, self.y)?,
+            transform.map_point(self.right(), self.y)?,
+            transform.map_point(self.right(), self.bottom())?,
+            transform.map_point(self.x, self.bottom())?,
+        ];
+        let (left, top, right, bottom) = corners.iter().fold(
+            (f32::INFINITY, f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+            |(left, top, right, bottom), &(x, y)| (left.min(x), top.min(y), right.max(x), bottom.max(y)),
+        );
+        Some(Rect::new(left, top, right - left, bottom - top))
+    }
+}
+
+/// Horizontal and vertical radii of each corner of a rect, in pixels
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CornerRadii {
+    pub top_left: [f32; 2],
+    pub top_right: [f32; 2],
+    pub bottom_right: [f32; 2],
+    pub bottom_left: [f32; 2],
+}
+
+impl CornerRadii {
+    pub fn uniform(radius: f32) -> Self {
+        Self {
+            top_left: [radius; 2],
+            top_right: [radius; 2],
+            bottom_right: [radius; 2],
+            bottom_left: [radius; 2],
+        }
+    }
+
+    /// Scaled down together, if need be, so that the corners of a `width`
+    /// by `height` rect don't overlap
+    pub fn fit(self, width: f32, height: f32) -> Self {
+        let ratio = |length: f32, a: f32, b: f32| if a + b > length { length.max(0.0) / (a + b) } else { 1.0 };
+        let scale = ratio(width, self.top_left[0], self.top_right[0])
+            .min(ratio(width, self.bottom_left[0], self.bottom_right[0]))
+            .min(ratio(height, self.top_left[1], self.bottom_left[1]))
+            .min(ratio(height, self.top_right[1], self.bottom_right[1]));
+        self.map(|[x, y]| [x * scale, y * scale])
+    }
+
+    /// The radii of the rect grown by `amount` on every side, or shrunk for
+    /// a negative one; square corners stay square
+    pub fn grow(self, amount: f32) -> Self {
+        self.map(|[x, y]| {
+            if x > 0.0 && y > 0.0 {
+                [(x + amount).max(0.0), (y + amount).max(0.0)]
+            } else {
+                [0.0, 0.0]
+            }
+        })
+    }
+
+    fn map(self, f: impl Fn([f32; 2]) -> [f32; 2]) -> Self {
+        Self {
+            top_left: f(self.top_left),
+            top_right: f(self.top_right),
+            bottom_right: f(self.bottom_right),
+            bottom_left: f(self.bottom_left),
+        }
+    }
+}
+
+/// The line a border side is drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BorderStyle {
+    #[default]
+    None,
+    Solid,
+    Dashed,
+    Dotted,
+    Double,
+    Groove,
+    Ridge,
+    Inset,
+    Outset,
+    Hidden,
+}
+
+/// The line text decorations are drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TextDecorationStyle {
+    #[default]
+    Solid,
+    Double,
+    Dotted,
+    Dashed,
+    Wavy,
+}
+
+impl TextDecorationStyle {
+    pub fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "solid" => Some(TextDecorationStyle::Solid),
+            "double" => Some(TextDecorationStyle::Double),
+            "dotted" => Some(TextDecorationStyle::Dotted),
+            "dashed" => Some(TextDecorationStyle::Dashed),
+            "wavy" => Some(TextDecorationStyle::Wavy),
+            _ => None,
+        }
+    }
+
+    /// How much taller than the line's thickness its rect is: room for
+    /// two lines and the gap between them, or for the wave to swing
+    pub fn height_factor(self) -> f32 {
+        match self {
+            TextDecorationStyle::Double | TextDecorationStyle::Wavy => 3.0,
+            _ => 1.0,
+        }
+    }
+}
+
+/// The four sides of a border: top, right, bottom, left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BorderSides {
+    pub widths: [f32; 4],
+    pub colors: [Color; 4],
+    pub styles: [BorderStyle; 4],
+}
+
+impl BorderSides {
+    /// The same solid line on every side
+    pub fn solid(width: f32, color: Color) -> Self {
+        Self {
+            widths: [width; 4],
+            colors: [color; 4],
+            styles: [BorderStyle::Solid; 4],
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        (0..4).any(|side| {
+            self.widths[side] > 0.0
+                && self.colors[side].a > 0.0
+                && !matches!(self.styles[side], BorderStyle::None | BorderStyle::Hidden)
+        })
+    }
+}
+
+/// How a background image covers an area: `tile` is one copy of it, and on
+/// the axes that repeat, copies follow every tile size plus `spacing`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tiling {
+    pub tile: Rect,
+    pub spacing: [f32; 2],
+    pub repeat_x: bool,
+    pub repeat_y: bool,
+}
+
+impl Tiling {
+    /// A single copy filling `rect`
+    pub fn single(rect: Rect) -> Self {
+        Self {
+            tile: rect,
+            spacing: [0.0; 2],
+            repeat_x: false,
+            repeat_y: false,
+        }
+    }
+}
+
+/// A gradient ready to draw, with its geometry in pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradientPaint {
+    pub shape: GradientShape,
+    /// Offsets along the gradient, from 0 at its start to 1 at its end, and
+    /// their colors, in order
+    pub stops: Vec<(f32, Color)>,
+    /// Whether the stops repeat before the first and after the last
+    pub repeating: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GradientShape {
+    /// Along the line from `start` to `end`
+    Linear { start: [f32; 2], end: [f32; 2] },
+    /// Out from `center` to the ellipse with `radii`
+    Radial { center: [f32; 2], radii: [f32; 2] },
+    /// Clockwise around `center`, starting `from` radians clockwise from
+    /// the top
+    Conic { center: [f32; 2], from: f32 },
+}
+
+impl GradientPaint {
+    /// The most stops the gradient shader takes; later ones are dropped
+    pub const MAX_STOPS: usize = 16;
 }
 
 #[derive(Debug, Clone)]
@@ -104,21 +318,31 @@ pub enum DisplayCommand {
     SolidRect {
         rect: Rect,
         color: Color,
-        border_radius: f32,
+        radii: CornerRadii,
         opacity: f32,
     },
+    /// A border drawn inside `rect`, its outer edge
     Border {
         rect: Rect,
-        color: Color,
-        width: f32,
-        border_radius: f32,
+        sides: BorderSides,
+        radii: CornerRadii,
+        opacity: f32,
     },
     Text {
-        text: String,
-        x: f32,
-        y: f32,
+        run: TextRun,
+        opacity: f32,
+    },
+    /// A `text-shadow`: the run again in the shadow's color, offset
+    TextShadow {
+        run: TextRun,
+        blur_radius: f32,
+        opacity: f32,
+    },
+    /// An underline, overline or line-through filling `rect`
+    TextDecoration {
+        rect: Rect,
         color: Color,
-        font_size: f32,
+        style: TextDecorationStyle,
         opacity: f32,
     },
     Image {
@@ -133,8 +357,39 @@ pub enum DisplayCommand {
         offset_y: f32,
         blur_radius: f32,
         spread_radius: f32,
-        border_radius: f32,
+        radii: CornerRadii,
+    },
+    /// A background image layer, tiled across `clip`
+    BackgroundImage {
+        clip: Rect,
+        tiling: Tiling,
+        texture_id: usize,
+        opacity: f32,
+    },
+    /// A gradient background layer, tiled across `clip`; the gradient's
+    /// geometry is relative to the tile's origin
+    Gradient {
+        clip: Rect,
+        tiling: Tiling,
+        gradient: GradientPaint,
+        opacity: f32,
+    },
+    /// Commands up to the matching `PopTransform` are drawn transformed.
+    /// `bounds` covers what they draw, before transforming.
+    PushTransform {
+        transform: Transform,
+        bounds: Rect,
     },
+    PopTransform,
+    /// Commands up to the matching `PopEffects` are drawn as a group, then
+    /// changed by `effects` as a whole. `bounds` covers what they draw once
+    /// filtered; the backdrop filter shows through `backdrop`.
+    PushEffects {
+        effects: Effects,
+        bounds: Rect,
+        backdrop: Rect,
+    },
+    PopEffects,
 }
 
 #[derive(Debug, Default)]
@@ -151,56 +406,54 @@ impl DisplayList {
         self.commands.push(DisplayCommand::SolidRect {
             rect,
             color,
-            border_radius: 0.0,
+            radii: CornerRadii::default(),
             opacity: 1.0,
         });
     }
 
-    pub fn push_rect_with_radius(&mut self, rect: Rect, color: Color, border_radius: f32, opacity: f32) {
+    pub fn push_rect_with_radius(&mut self, rect: Rect, color: Color, radii: CornerRadii, opacity: f32) {
         self.commands.push(DisplayCommand::SolidRect {
             rect,
             color,
-            border_radius,
+            radii,
             opacity,
         });
     }
 
     pub fn push_border(&mut self, rect: Rect, color: Color, width: f32) {
-        self.commands.push(DisplayCommand::Border {
-            rect,
-            color,
-            width,
-            border_radius: 0.0,
-        });
+        self.push_border_sides(rect, BorderSides::solid(width, color), CornerRadii::default(), 1.0);
     }
 
-    pub fn push_border_with_radius(&mut self, rect: Rect, color: Color, width: f32, border_radius: f32) {
+    pub fn push_border_sides(&mut self, rect: Rect, sides: BorderSides, radii: CornerRadii, opacity: f32) {
         self.commands.push(DisplayCommand::Border {
             rect,
-            color,
-            width,
-            border_radius,
+            sides,
+            radii,
+            opacity,
         });
     }
 
     pub fn push_text(&mut self, text: String, x: f32, y: f32, color: Color, font_size: f32) {
-        self.commands.push(DisplayCommand::Text {
-            text,
-            x,
-            y,
-            color,
-            font_size,
-            opacity: 1.0,
+        self.push_text_run(TextRun::new(text, x, y, color, font_size, Font::default()), 1.0);
+    }
+
+    pub fn push_text_run(&mut self, run: TextRun, opacity: f32) {
+        self.commands.push(DisplayCommand::Text { run, opacity });
+    }
+
+    pub fn push_text_shadow(&mut self, run: TextRun, blur_radius: f32, opacity: f32) {
+        self.commands.push(DisplayCommand::TextShadow {
+            run,
+            blur_radius,
+            opacity,
         });
     }
 
-    pub fn push_text_with_opacity(&mut self, text: String, x: f32, y: f32, color: Color, font_size: f32, opacity: f32) {
-        self.commands.push(DisplayCommand::Text {
-            text,
-            x,
-            y,
+    pub fn push_text_decoration(&mut self, rect: Rect, color: Color, style: TextDecorationStyle, opacity: f32) {
+        self.commands.push(DisplayCommand::TextDecoration {
+            rect,
             color,
-            font_size,
+            style,
             opacity,
         });
     }
@@ -229,7 +482,7 @@ impl DisplayList {
         offset_y: f32,
         blur_radius: f32,
         spread_radius: f32,
-        border_radius: f32,
+        radii: CornerRadii,
     ) {
         self.commands.push(DisplayCommand::BoxShadow {
             rect,
@@ -238,10 +491,44 @@ impl DisplayList {
             offset_y,
             blur_radius,
             spread_radius,
-            border_radius,
+            radii,
+        });
+    }
+
+    pub fn push_background_image(&mut self, clip: Rect, tiling: Tiling, texture_id: usize, opacity: f32) {
+        self.commands.push(DisplayCommand::BackgroundImage {
+            clip,
+            tiling,
+            texture_id,
+            opacity,
+        });
+    }
+
+    pub fn push_gradient(&mut self, clip: Rect, tiling: Tiling, gradient: GradientPaint, opacity: f32) {
+        self.commands.push(DisplayCommand::Gradient {
+            clip,
+            tiling,
+            gradient,
+            opacity,
         });
     }
 
+    pub fn push_transform(&mut self, transform: Transform, bounds: Rect) {
+        self.commands.push(DisplayCommand::PushTransform { transform, bounds });
+    }
+
+    pub fn pop_transform(&mut self) {
+        self.commands.push(DisplayCommand::PopTransform);
+    }
+
+    pub fn push_effects(&mut self, effects: Effects, bounds: Rect, backdrop: Rect) {
+        self.commands.push(DisplayCommand::PushEffects { effects, bounds, backdrop });
+    }
+
+    pub fn pop_effects(&mut self) {
+        self.commands.push(DisplayCommand::PopEffects);
+    }
+
     pub fn clear(&mut self) {
         self.commands.clear();
     }
@@ -252,22 +539,274 @@ impl DisplayList {
 struct RectVertex {
     position: [f32; 2],
     color: [f32; 4],
+    /// Position in the rect, then the rect's size, in physical pixels
+    local: [f32; 4],
+    /// Corner radii, clockwise from the top left
+    radii_x: [f32; 4],
+    radii_y: [f32; 4],
+    /// Border widths, top, right, bottom, left; all 0 to fill the rect
+    widths: [f32; 4],
+    styles: [u32; 4],
+    side_colors: [[f32; 4]; 4],
+}
+
+const RECT_ATTRIBUTES: [VertexAttribute; 11] = vertex_attr_array![
+    0 => Float32x2,
+    1 => Float32x4,
+    2 => Float32x4,
+    3 => Float32x4,
+    4 => Float32x4,
+    5 => Float32x4,
+    6 => Uint32x4,
+    7 => Float32x4,
+    8 => Float32x4,
+    9 => Float32x4,
+    10 => Float32x4,
+];
+
+/// A rect for the rect shader: filled, or with a border drawn along its
+/// inside edge, and with rounded corners
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RectPaint {
+    pub rect: Rect,
+    /// The fill color, when there's no border
+    pub color: Color,
+    pub radii: CornerRadii,
+    pub border: Option<BorderSides>,
+    /// A text decoration line drawn across the rect instead
+    pub line: Option<TextDecorationStyle>,
+}
+
+impl RectPaint {
+    pub fn solid(rect: Rect, color: Color) -> Self {
+        Self::rounded(rect, color, CornerRadii::default())
+    }
+
+    pub fn rounded(rect: Rect, color: Color, radii: CornerRadii) -> Self {
+        Self {
+            rect,
+            color,
+            radii,
+            border: None,
+            line: None,
+        }
+    }
+
+    pub fn border(rect: Rect, sides: BorderSides, radii: CornerRadii) -> Self {
+        Self {
+            rect,
+            color: Color::TRANSPARENT,
+            radii,
+            border: Some(sides),
+            line: None,
+        }
+    }
+
+    pub fn line(rect: Rect, color: Color, style: TextDecorationStyle) -> Self {
+        Self {
+            rect,
+            color,
+            radii: CornerRadii::default(),
+            border: None,
+            line: Some(style),
+        }
+    }
+}
+
+impl TextDecorationStyle {
+    /// The number the rect shader knows the style by, shared with borders
+    fn shader_index(self) -> u32 {
+        match self {
+            TextDecorationStyle::Solid => 1,
+            TextDecorationStyle::Dashed => 2,
+            TextDecorationStyle::Dotted => 3,
+            TextDecorationStyle::Double => 4,
+            TextDecorationStyle::Wavy => 9,
+        }
+    }
+}
+
+impl BorderStyle {
+    /// The number the rect shader knows the style by
+    fn shader_index(self) -> u32 {
+        match self {
+            BorderStyle::None | BorderStyle::Hidden => 0,
+            BorderStyle::Solid => 1,
+            BorderStyle::Dashed => 2,
+            BorderStyle::Dotted => 3,
+            BorderStyle::Double => 4,
+            BorderStyle::Groove => 5,
+            BorderStyle::Ridge => 6,
+            BorderStyle::Inset => 7,
+            BorderStyle::Outset => 8,
+        }
+    }
 }
 
+/// A corner of a quad that image and gradient shaders fill
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
-struct ImageVertex {
+struct QuadVertex {
     position: [f32; 2],
-    tex_coords: [f32; 2],
+}
+
+/// Where tiles go, in physical pixels, as the image and gradient shaders
+/// take it
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct TileUniforms {
+    tile: [f32; 4],
+    period: [f32; 4],
+}
+
+impl TileUniforms {
+    fn new(tiling: &Tiling, scale_factor: f32) -> Self {
+        let tile = tiling.tile;
+        Self {
+            tile: [
+                tile.x * scale_factor,
+                tile.y * scale_factor,
+                tile.width * scale_factor,
+                tile.height * scale_factor,
+            ],
+            period: [
+                (tile.width + tiling.spacing[0]) * scale_factor,
+                (tile.height + tiling.spacing[1]) * scale_factor,
+                tiling.repeat_x as u32 as f32,
+                tiling.repeat_y as u32 as f32,
+            ],
+        }
+    }
 }
 
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 struct ImageUniforms {
+    tiles: TileUniforms,
+    opacity: f32,
+    _padding: [f32; 3],
+}
+
+const GRADIENT_LINEAR: u32 = 0;
+const GRADIENT_RADIAL: u32 = 1;
+const GRADIENT_CONIC: u32 = 2;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GradientUniforms {
+    tiles: TileUniforms,
+    geometry: [f32; 4],
+    kind: u32,
+    repeating: u32,
+    stop_count: u32,
+    opacity: f32,
+    colors: [[f32; 4]; GradientPaint::MAX_STOPS],
+    offsets: [[f32; 4]; GradientPaint::MAX_STOPS / 4],
+}
+
+impl GradientUniforms {
+    fn new(gradient: &GradientPaint, tiling: &Tiling, opacity: f32, scale_factor: f32) -> Self {
+        let scale = |[x, y]: [f32; 2]| [x * scale_factor, y * scale_factor];
+        let (kind, geometry) = match gradient.shape {
+            GradientShape::Linear { start, end } => {
+                let ([x0, y0], [x1, y1]) = (scale(start), scale(end));
+                (GRADIENT_LINEAR, [x0, y0, x1, y1])
+            }
+            GradientShape::Radial { center, radii } => {
+                let ([x, y], [rx, ry]) = (scale(center), scale(radii));
+                (GRADIENT_RADIAL, [x, y, rx, ry])
+            }
+            GradientShape::Conic { center, from } => {
+                let [x, y] = scale(center);
+                (GRADIENT_CONIC, [x, y, from, 0.0])
+            }
+        };
+
+        let stops = &gradient.stops[..gradient.stops.len().min(GradientPaint::MAX_STOPS)];
+        let mut colors = [[0.0; 4]; GradientPaint::MAX_STOPS];
+        let mut offsets = [[0.0; 4]; GradientPaint::MAX_STOPS / 4];
+        for (i, (offset, color)) in stops.iter().enumerate() {
+            colors[i] = color.to_array();
+            offsets[i / 4][i % 4] = *offset;
+        }
+
+        Self {
+            tiles: TileUniforms::new(tiling, scale_factor),
+            geometry,
+            kind,
+            repeating: gradient.repeating as u32,
+            stop_count: stops.len() as u32,
+            opacity,
+            colors,
+            offsets,
+        }
+    }
+}
+
+/// A corner of a composited layer: in clip space, before dividing by w
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct LayerVertex {
+    position: [f32; 4],
+    tex_coords: [f32; 2],
+    opacity: f32,
+}
+
+const LAYER_ATTRIBUTES: [VertexAttribute; 3] = vertex_attr_array![
+    0 => Float32x4,
+    1 => Float32x2,
+    2 => Float32,
+];
+
+/// An offscreen texture that content is drawn into, to be composited
+/// transformed or with effects
+pub struct Layer {
+    texture: Texture,
+    pub view: TextureView,
+    /// In physical pixels
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Uniforms for the effects shader; see `shaders/effects.wgsl`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct EffectUniforms {
+    /// The color matrix's first four columns
+    matrix: [[f32; 4]; 4],
+    offset: [f32; 4],
+    color: [f32; 4],
+    quad: [f32; 4],
+    source_uv: [f32; 4],
+    step: [f32; 2],
+    sigma: f32,
+    mode: u32,
     opacity: f32,
     _padding: [f32; 3],
 }
 
+impl EffectUniforms {
+    /// Drawing all of a target from all of the source
+    fn whole() -> Self {
+        Self {
+            quad: [-1.0, 1.0, 1.0, -1.0],
+            source_uv: [0.0, 0.0, 1.0, 1.0],
+            opacity: 1.0,
+            ..Zeroable::zeroed()
+        }
+    }
+}
+
+const QUAD_VERTEX_LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+    array_stride: std::mem::size_of::<QuadVertex>() as BufferAddress,
+    step_mode: VertexStepMode::Vertex,
+    attributes: &[VertexAttribute {
+        offset: 0,
+        shader_location: 0,
+        format: VertexFormat::Float32x2,
+    }],
+};
+
 pub struct Painter {
     rect_pipeline: RenderPipeline,
     rect_vertex_buffer: Buffer,
@@ -277,9 +816,18 @@ pub struct Painter {
     image_pipeline: RenderPipeline,
     image_bind_group_layout: BindGroupLayout,
     image_sampler: Sampler,
-    image_vertex_buffer: Buffer,
-    image_index_buffer: Buffer,
-    image_uniform_buffer: Buffer,
+    // Gradient rendering
+    gradient_pipeline: RenderPipeline,
+    gradient_bind_group_layout: BindGroupLayout,
+    // Layer compositing
+    layer_pipeline: RenderPipeline,
+    layer_bind_group_layout: BindGroupLayout,
+    // Filters and blending
+    effect_bind_group_layout: BindGroupLayout,
+    color_matrix_pipeline: RenderPipeline,
+    blur_pipeline: RenderPipeline,
+    shadow_pipeline: RenderPipeline,
+    blend_pipeline: RenderPipeline,
 }
 
 impl Painter {
@@ -305,18 +853,7 @@ impl Painter {
                 buffers: &[VertexBufferLayout {
                     array_stride: std::mem::size_of::<RectVertex>() as BufferAddress,
                     step_mode: VertexStepMode::Vertex,
-                    attributes: &[
-                        VertexAttribute {
-                            offset: 0,
-                            shader_location: 0,
-                            format: VertexFormat::Float32x2,
-                        },
-                        VertexAttribute {
-                            offset: 8,
-                            shader_location: 1,
-                            format: VertexFormat::Float32x4,
-                        },
-                    ],
+                    attributes: &RECT_ATTRIBUTES,
                 }],
             },
             fragment: Some(FragmentState {
@@ -410,22 +947,7 @@ impl Painter {
                 module: &image_shader,
                 entry_point: "vs_main",
                 compilation_options: Default::default(),
-                buffers: &[VertexBufferLayout {
-                    array_stride: std::mem::size_of::<ImageVertex>() as BufferAddress,
-                    step_mode: VertexStepMode::Vertex,
-                    attributes: &[
-                        VertexAttribute {
-                            offset: 0,
-                            shader_location: 0,
-                            format: VertexFormat::Float32x2,
-                        },
-                        VertexAttribute {
-                            offset: 8,
-                            shader_location: 1,
-                            format: VertexFormat::Float32x2,
-                        },
-                    ],
-                }],
+                buffers: &[QUAD_VERTEX_LAYOUT],
             },
             fragment: Some(FragmentState {
                 module: &image_shader,
@@ -463,63 +985,637 @@ impl Painter {
             ..Default::default()
         });
 
-        let image_vertex_buffer = gpu.device.create_buffer(&BufferDescriptor {
-            label: Some("Image Vertex Buffer"),
-            size: (4 * std::mem::size_of::<ImageVertex>()) as u64,
-            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
+        // Create gradient rendering pipeline
+        let gradient_shader = gpu.device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Gradient Shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/gradient.wgsl").into()),
         });
 
-        let image_index_buffer = gpu.device.create_buffer(&BufferDescriptor {
-            label: Some("Image Index Buffer"),
-            size: (6 * std::mem::size_of::<u32>()) as u64,
-            usage: BufferUsages::INDEX | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
+        let gradient_bind_group_layout = gpu.device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Gradient Bind Group Layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
         });
 
-        let image_uniform_buffer = gpu.device.create_buffer(&BufferDescriptor {
-            label: Some("Image Uniform Buffer"),
-            size: std::mem::size_of::<ImageUniforms>() as u64,
-            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
+        let gradient_pipeline_layout = gpu.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Gradient Pipeline Layout"),
+            bind_group_layouts: &[&gradient_bind_group_layout],
+            push_constant_ranges: &[],
         });
 
-        Self {
-            rect_pipeline,
-            rect_vertex_buffer,
-            rect_index_buffer,
-            max_rects,
-            image_pipeline,
-            image_bind_group_layout,
-            image_sampler,
-            image_vertex_buffer,
-            image_index_buffer,
-            image_uniform_buffer,
-        }
-    }
-
-    pub fn draw_rects(
-        &self,
-        gpu: &GpuContext,
-        encoder: &mut CommandEncoder,
-        view: &TextureView,
-        rects: &[(Rect, Color)],
-        viewport_width: f32,
-        viewport_height: f32,
-        scale_factor: f32,
-    ) {
-        if rects.is_empty() {
-            return;
-        }
+        let gradient_pipeline = gpu.device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Gradient Pipeline"),
+            layout: Some(&gradient_pipeline_layout),
+            vertex: VertexState {
+                module: &gradient_shader,
+                entry_point: "vs_main",
+                compilation_options: Default::default(),
+                buffers: &[QUAD_VERTEX_LAYOUT],
+            },
+            fragment: Some(FragmentState {
+                module: &gradient_shader,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(ColorTargetState {
+                    format: gpu.format(),
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
 
-        // Physical viewport dimensions
-        let physical_w = viewport_width * scale_factor;
-        let physical_h = viewport_height * scale_factor;
+        // Create layer compositing pipeline
+        let layer_shader = gpu.device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Layer Shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/layer.wgsl").into()),
+        });
+
+        let layer_bind_group_layout = gpu.device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Layer Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let layer_pipeline_layout = gpu.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Layer Pipeline Layout"),
+            bind_group_layouts: &[&layer_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let layer_pipeline = gpu.device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Layer Pipeline"),
+            layout: Some(&layer_pipeline_layout),
+            vertex: VertexState {
+                module: &layer_shader,
+                entry_point: "vs_main",
+                compilation_options: Default::default(),
+                buffers: &[VertexBufferLayout {
+                    array_stride: std::mem::size_of::<LayerVertex>() as BufferAddress,
+                    step_mode: VertexStepMode::Vertex,
+                    attributes: &LAYER_ATTRIBUTES,
+                }],
+            },
+            fragment: Some(FragmentState {
+                module: &layer_shader,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(ColorTargetState {
+                    format: gpu.format(),
+                    // Drawn over transparent, the layer's colors are premultiplied
+                    blend: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                unclipped_depth: false,
+                polygon_mode: PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        // Create effect pipelines, which each draw a whole target and
+        // differ only in their fragment shader
+        let effect_shader = gpu.device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Effect Shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/effects.wgsl").into()),
+        });
+
+        let texture_entry = |binding: u32| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let effect_bind_group_layout = gpu.device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Effect Bind Group Layout"),
+            entries: &[
+                texture_entry(0),
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::VERTEX_FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_entry(3),
+            ],
+        });
+
+        let effect_pipeline_layout = gpu.device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Effect Pipeline Layout"),
+            bind_group_layouts: &[&effect_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let effect_pipeline = |label: &str, entry_point: &str| {
+            gpu.device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&effect_pipeline_layout),
+                vertex: VertexState {
+                    module: &effect_shader,
+                    entry_point: "vs_main",
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                fragment: Some(FragmentState {
+                    module: &effect_shader,
+                    entry_point,
+                    compilation_options: Default::default(),
+                    targets: &[Some(ColorTargetState {
+                        format: gpu.format(),
+                        // Each pass works out the final color itself
+                        blend: Some(BlendState::REPLACE),
+                        write_mask: ColorWrites::ALL,
+                    })],
+                }),
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        };
+        let color_matrix_pipeline = effect_pipeline("Color Matrix Pipeline", "fs_color_matrix");
+        let blur_pipeline = effect_pipeline("Blur Pipeline", "fs_blur");
+        let shadow_pipeline = effect_pipeline("Shadow Pipeline", "fs_shadow");
+        let blend_pipeline = effect_pipeline("Blend Pipeline", "fs_blend");
+
+        Self {
+            rect_pipeline,
+            rect_vertex_buffer,
+            rect_index_buffer,
+            max_rects,
+            image_pipeline,
+            image_bind_group_layout,
+            image_sampler,
+            gradient_pipeline,
+            gradient_bind_group_layout,
+            layer_pipeline,
+            layer_bind_group_layout,
+            effect_bind_group_layout,
+            color_matrix_pipeline,
+            blur_pipeline,
+            shadow_pipeline,
+            blend_pipeline,
+        }
+    }
+
+    /// The largest layer, in physical pixels on each side, the device takes
+    pub fn max_layer_size(gpu: &GpuContext) -> u32 {
+        gpu.device.limits().max_texture_dimension_2d
+    }
+
+    /// A transparent layer `width` by `height` physical pixels, for content
+    /// drawn in `encoder`
+    pub fn create_layer(&self, gpu: &GpuContext, encoder: &mut CommandEncoder, width: u32, height: u32) -> Layer {
+        let layer = Self::layer_texture(gpu, width, height);
+
+        encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Layer Clear Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &layer.view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        layer
+    }
+
+    /// A layer with undefined contents, for a pass that draws all of it
+    fn layer_texture(gpu: &GpuContext, width: u32, height: u32) -> Layer {
+        let (width, height) = (width.max(1), height.max(1));
+        let texture = gpu.device.create_texture(&TextureDescriptor {
+            label: Some("Layer Texture"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: gpu.format(),
+            // Regions are copied out, for what is behind blended content
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC
+                | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        Layer { texture, view, width, height }
+    }
+
+    /// A copy of the part of `layer` from `(x, y)`, `width` by `height`
+    /// physical pixels, which must be inside it
+    pub fn copy_layer_region(
+        &self,
+        gpu: &GpuContext,
+        encoder: &mut CommandEncoder,
+        layer: &Layer,
+        (x, y, width, height): (u32, u32, u32, u32),
+    ) -> Layer {
+        let copy = Self::layer_texture(gpu, width, height);
+        encoder.copy_texture_to_texture(
+            ImageCopyTexture {
+                texture: &layer.texture,
+                mip_level: 0,
+                origin: Origin3d { x, y, z: 0 },
+                aspect: TextureAspect::All,
+            },
+            ImageCopyTexture {
+                texture: &copy.texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            Extent3d {
+                width: copy.width,
+                height: copy.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        copy
+    }
+
+    /// The part of `layer`, in physical pixels, that content covering
+    /// `rect` at `scale` lands on; `None` if it's all outside
+    pub fn layer_region(layer: &Layer, rect: &Rect, scale: f32) -> Option<(u32, u32, u32, u32)> {
+        let x0 = (rect.x * scale).floor().max(0.0) as u32;
+        let y0 = (rect.y * scale).floor().max(0.0) as u32;
+        let x1 = (((rect.x + rect.width) * scale).ceil().max(0.0) as u32).min(layer.width);
+        let y1 = (((rect.y + rect.height) * scale).ceil().max(0.0) as u32).min(layer.height);
+        (x0 < x1 && y0 < y1).then(|| (x0, y0, x1 - x0, y1 - y0))
+    }
+
+    /// `layer` taken through a filter chain, with lengths scaled by `scale`
+    /// to physical pixels. Passes are drawn in `encoder`; the layer they
+    /// end in is returned.
+    pub fn apply_filters(
+        &self,
+        gpu: &GpuContext,
+        encoder: &mut CommandEncoder,
+        layer: Layer,
+        filters: &[FilterOp],
+        scale: f32,
+    ) -> Layer {
+        let mut layer = layer;
+        for filter in filters {
+            layer = match filter {
+                FilterOp::ColorMatrix(matrix) => {
+                    let mut uniforms = EffectUniforms::whole();
+                    for (row, values) in matrix.iter().enumerate() {
+                        for (column, value) in values[..4].iter().enumerate() {
+                            uniforms.matrix[column][row] = *value;
+                        }
+                        uniforms.offset[row] = values[4];
+                    }
+                    self.effect_layer(gpu, encoder, &self.color_matrix_pipeline, &layer, uniforms)
+                }
+                FilterOp::Blur(sigma) => self.blur(gpu, encoder, layer, sigma * scale),
+                FilterOp::DropShadow { offset_x, offset_y, blur, color } => {
+                    // The shape in the shadow's color, moved and blurred,
+                    // with the content drawn back over it
+                    let uniforms = EffectUniforms {
+                        color: [color.r * color.a, color.g * color.a, color.b * color.a, color.a],
+                        step: [offset_x * scale / layer.width as f32, offset_y * scale / layer.height as f32],
+                        ..EffectUniforms::whole()
+                    };
+                    let shadow = self.effect_layer(gpu, encoder, &self.shadow_pipeline, &layer, uniforms);
+                    let shadow = self.blur(gpu, encoder, shadow, blur * scale);
+                    let size = (layer.width as f32, layer.height as f32);
+                    let viewport = (size.0, size.1, 1.0);
+                    self.draw_layer(gpu, encoder, &shadow.view, &layer, &Transform::IDENTITY, size, 1.0, viewport);
+                    shadow
+                }
+            };
+        }
+        layer
+    }
+
+    /// A gaussian blur with standard deviation `sigma` in physical pixels,
+    /// across then down
+    fn blur(&self, gpu: &GpuContext, encoder: &mut CommandEncoder, layer: Layer, sigma: f32) -> Layer {
+        if sigma < 0.01 {
+            return layer;
+        }
+        let across = EffectUniforms {
+            step: [1.0 / layer.width as f32, 0.0],
+            sigma,
+            ..EffectUniforms::whole()
+        };
+        let blurred = self.effect_layer(gpu, encoder, &self.blur_pipeline, &layer, across);
+        let down = EffectUniforms {
+            step: [0.0, 1.0 / layer.height as f32],
+            ..across
+        };
+        self.effect_layer(gpu, encoder, &self.blur_pipeline, &blurred, down)
+    }
+
+    /// A new layer the size of `source`, drawn entirely by `pipeline`
+    fn effect_layer(
+        &self,
+        gpu: &GpuContext,
+        encoder: &mut CommandEncoder,
+        pipeline: &RenderPipeline,
+        source: &Layer,
+        uniforms: EffectUniforms,
+    ) -> Layer {
+        let target = Self::layer_texture(gpu, source.width, source.height);
+        self.effect_pass(gpu, encoder, pipeline, &target.view, source, source, uniforms);
+        target
+    }
+
+    /// Composite `layer`, faded by `opacity`, over `area` of `canvas` in
+    /// logical pixels at `scale`, blended with what is there by `mode`
+    #[allow(clippy::too_many_arguments)]
+    pub fn blend_layer(
+        &self,
+        gpu: &GpuContext,
+        encoder: &mut CommandEncoder,
+        canvas: &Layer,
+        layer: &Layer,
+        area: &Rect,
+        opacity: f32,
+        mode: BlendMode,
+        scale: f32,
+    ) {
+        // What is behind is read from a copy, as the canvas is drawn into
+        let Some(region) = Self::layer_region(canvas, area, scale) else {
+            return;
+        };
+        let backdrop = self.copy_layer_region(gpu, encoder, canvas, region);
+
+        let (x, y, width, height) = region;
+        let [x0, y0, x1, y1] = [x, y, x + width, y + height].map(|v| v as f32);
+        let (canvas_width, canvas_height) = (canvas.width as f32, canvas.height as f32);
+        let uv = |v: f32, start: f32, length: f32| (v / scale - start) / length;
+        let uniforms = EffectUniforms {
+            quad: [
+                x0 / canvas_width * 2.0 - 1.0,
+                1.0 - y0 / canvas_height * 2.0,
+                x1 / canvas_width * 2.0 - 1.0,
+                1.0 - y1 / canvas_height * 2.0,
+            ],
+            source_uv: [
+                uv(x0, area.x, area.width),
+                uv(y0, area.y, area.height),
+                uv(x1, area.x, area.width),
+                uv(y1, area.y, area.height),
+            ],
+            mode: mode.index(),
+            opacity,
+            ..EffectUniforms::whole()
+        };
+        self.effect_pass(gpu, encoder, &self.blend_pipeline, &canvas.view, layer, &backdrop, uniforms);
+    }
+
+    /// Draws `uniforms.quad` of `target` with `pipeline`, keeping the rest
+    #[allow(clippy::too_many_arguments)]
+    fn effect_pass(
+        &self,
+        gpu: &GpuContext,
+        encoder: &mut CommandEncoder,
+        pipeline: &RenderPipeline,
+        target: &TextureView,
+        source: &Layer,
+        backdrop: &Layer,
+        uniforms: EffectUniforms,
+    ) {
+        let uniform_buffer = gpu.device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("Effect Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[uniforms]),
+            usage: BufferUsages::UNIFORM,
+        });
+        let bind_group = gpu.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Effect Bind Group"),
+            layout: &self.effect_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&source.view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.image_sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::TextureView(&backdrop.view),
+                },
+            ],
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Effect Render Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..6, 0..1);
+    }
+
+    /// Composite a layer `size` logical pixels across, its top left corner
+    /// at the origin before `transform`, faded by `opacity`. `viewport` is
+    /// the viewport's width, height and scale factor.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_layer(
+        &self,
+        gpu: &GpuContext,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        layer: &Layer,
+        transform: &Transform,
+        size: (f32, f32),
+        opacity: f32,
+        (viewport_width, viewport_height, _): (f32, f32, f32),
+    ) {
+        // In clip space with w kept, so the GPU divides after interpolating
+        let vertex = |x: f32, y: f32, tex_coords: [f32; 2]| {
+            let [x, y, _, w] = transform.apply(x, y);
+            LayerVertex {
+                position: [x / viewport_width * 2.0 - w, w - y / viewport_height * 2.0, 0.0, w],
+                tex_coords,
+                opacity,
+            }
+        };
+        let (width, height) = size;
+        let vertices = [
+            vertex(0.0, 0.0, [0.0, 0.0]),
+            vertex(width, 0.0, [1.0, 0.0]),
+            vertex(width, height, [1.0, 1.0]),
+            vertex(0.0, height, [0.0, 1.0]),
+        ];
+        let indices: [u32; 6] = [0, 1, 2, 0, 2, 3];
+
+        let vertex_buffer = gpu.device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("Layer Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: BufferUsages::VERTEX,
+        });
+        let index_buffer = gpu.device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("Layer Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: BufferUsages::INDEX,
+        });
+        let bind_group = gpu.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Layer Bind Group"),
+            layout: &self.layer_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&layer.view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.image_sampler),
+                },
+            ],
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Layer Render Pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        render_pass.set_pipeline(&self.layer_pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), IndexFormat::Uint32);
+        render_pass.draw_indexed(0..6, 0, 0..1);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_rects(
+        &self,
+        gpu: &GpuContext,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        rects: &[RectPaint],
+        viewport_width: f32,
+        viewport_height: f32,
+        scale_factor: f32,
+    ) {
+        if rects.is_empty() {
+            return;
+        }
+        self.upload_rects(gpu, rects, viewport_width, viewport_height, scale_factor);
+        self.draw_rect_range(encoder, view, 0..rects.len());
+    }
+
+    /// Writes the rects for `draw_rect_range` to draw. All writes land
+    /// before the frame's commands run, so a frame uploads its rects once.
+    pub fn upload_rects(
+        &self,
+        gpu: &GpuContext,
+        rects: &[RectPaint],
+        viewport_width: f32,
+        viewport_height: f32,
+        scale_factor: f32,
+    ) {
+        if rects.is_empty() {
+            return;
+        }
+        let rects = &rects[..rects.len().min(self.max_rects)];
+
+        // Physical viewport dimensions
+        let physical_w = viewport_width * scale_factor;
+        let physical_h = viewport_height * scale_factor;
 
         let mut vertices = Vec::with_capacity(rects.len() * 4);
         let mut indices = Vec::with_capacity(rects.len() * 6);
 
-        for (i, (rect, color)) in rects.iter().enumerate() {
+        for (i, paint) in rects.iter().enumerate() {
+            let rect = &paint.rect;
             // Scale logical rect coordinates to physical
             let px = rect.x * scale_factor;
             let py = rect.y * scale_factor;
@@ -531,13 +1627,34 @@ impl Painter {
             let x1 = ((px + pw) / physical_w) * 2.0 - 1.0;
             let y1 = 1.0 - ((py + ph) / physical_h) * 2.0;
 
-            let color_arr = color.to_array();
+            let radii = paint.radii.fit(rect.width, rect.height);
+            let corners = [radii.top_left, radii.top_right, radii.bottom_right, radii.bottom_left];
+            // A line is drawn as the top side of a border filling the rect
+            let (widths, styles, side_colors) = match (&paint.border, paint.line) {
+                (Some(sides), _) => (
+                    sides.widths.map(|width| width * scale_factor),
+                    sides.styles.map(BorderStyle::shader_index),
+                    sides.colors.map(|color| color.to_array()),
+                ),
+                (None, Some(style)) => ([ph, 0.0, 0.0, 0.0], [style.shader_index(), 0, 0, 0], [paint.color.to_array(); 4]),
+                (None, None) => ([0.0; 4], [0; 4], [[0.0; 4]; 4]),
+            };
+            let vertex = |position: [f32; 2], local: [f32; 2]| RectVertex {
+                position,
+                color: paint.color.to_array(),
+                local: [local[0], local[1], pw, ph],
+                radii_x: corners.map(|[x, _]| x * scale_factor),
+                radii_y: corners.map(|[_, y]| y * scale_factor),
+                widths,
+                styles,
+                side_colors,
+            };
             let base_idx = (i * 4) as u32;
 
-            vertices.push(RectVertex { position: [x0, y0], color: color_arr });
-            vertices.push(RectVertex { position: [x1, y0], color: color_arr });
-            vertices.push(RectVertex { position: [x1, y1], color: color_arr });
-            vertices.push(RectVertex { position: [x0, y1], color: color_arr });
+            vertices.push(vertex([x0, y0], [0.0, 0.0]));
+            vertices.push(vertex([x1, y0], [pw, 0.0]));
+            vertices.push(vertex([x1, y1], [pw, ph]));
+            vertices.push(vertex([x0, y1], [0.0, ph]));
 
             indices.push(base_idx);
             indices.push(base_idx + 1);
@@ -549,6 +1666,14 @@ impl Painter {
 
         gpu.queue.write_buffer(&self.rect_vertex_buffer, 0, bytemuck::cast_slice(&vertices));
         gpu.queue.write_buffer(&self.rect_index_buffer, 0, bytemuck::cast_slice(&indices));
+    }
+
+    /// Draws the uploaded rects with indices in `range`
+    pub fn draw_rect_range(&self, encoder: &mut CommandEncoder, view: &TextureView, range: std::ops::Range<usize>) {
+        let range = range.start.min(self.max_rects)..range.end.min(self.max_rects);
+        if range.is_empty() {
+            return;
+        }
 
         let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
             label: Some("Rect Render Pass"),
@@ -568,10 +1693,11 @@ impl Painter {
         render_pass.set_pipeline(&self.rect_pipeline);
         render_pass.set_vertex_buffer(0, self.rect_vertex_buffer.slice(..));
         render_pass.set_index_buffer(self.rect_index_buffer.slice(..), IndexFormat::Uint32);
-        render_pass.draw_indexed(0..indices.len() as u32, 0, 0..1);
+        render_pass.draw_indexed((range.start * 6) as u32..(range.end * 6) as u32, 0, 0..1);
     }
 
     /// Draw a single image with the given texture view
+    #[allow(clippy::too_many_arguments)]
     pub fn draw_image(
         &self,
         gpu: &GpuContext,
@@ -584,38 +1710,34 @@ impl Painter {
         viewport_height: f32,
         scale_factor: f32,
     ) {
-        // Physical viewport dimensions
-        let physical_w = viewport_width * scale_factor;
-        let physical_h = viewport_height * scale_factor;
-
-        // Scale logical rect coordinates to physical
-        let px = rect.x * scale_factor;
-        let py = rect.y * scale_factor;
-        let pw = rect.width * scale_factor;
-        let ph = rect.height * scale_factor;
-
-        let x0 = (px / physical_w) * 2.0 - 1.0;
-        let y0 = 1.0 - (py / physical_h) * 2.0;
-        let x1 = ((px + pw) / physical_w) * 2.0 - 1.0;
-        let y1 = 1.0 - ((py + ph) / physical_h) * 2.0;
-
-        let vertices = [
-            ImageVertex { position: [x0, y0], tex_coords: [0.0, 0.0] },
-            ImageVertex { position: [x1, y0], tex_coords: [1.0, 0.0] },
-            ImageVertex { position: [x1, y1], tex_coords: [1.0, 1.0] },
-            ImageVertex { position: [x0, y1], tex_coords: [0.0, 1.0] },
-        ];
-
-        let indices: [u32; 6] = [0, 1, 2, 0, 2, 3];
-
-        gpu.queue.write_buffer(&self.image_vertex_buffer, 0, bytemuck::cast_slice(&vertices));
-        gpu.queue.write_buffer(&self.image_index_buffer, 0, bytemuck::cast_slice(&indices));
+        let viewport = (viewport_width, viewport_height, scale_factor);
+        self.draw_tiled_image(gpu, encoder, view, texture_view, rect, &Tiling::single(*rect), opacity, viewport);
+    }
 
+    /// Draw an image tiled across `clip`. `viewport` is the viewport's
+    /// width, height and scale factor.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_tiled_image(
+        &self,
+        gpu: &GpuContext,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        texture_view: &TextureView,
+        clip: &Rect,
+        tiling: &Tiling,
+        opacity: f32,
+        viewport: (f32, f32, f32),
+    ) {
         let uniforms = ImageUniforms {
+            tiles: TileUniforms::new(tiling, viewport.2),
             opacity,
             _padding: [0.0; 3],
         };
-        gpu.queue.write_buffer(&self.image_uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+        let uniform_buffer = gpu.device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("Image Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[uniforms]),
+            usage: BufferUsages::UNIFORM,
+        });
 
         let bind_group = gpu.device.create_bind_group(&BindGroupDescriptor {
             label: Some("Image Bind Group"),
@@ -631,13 +1753,103 @@ impl Painter {
                 },
                 BindGroupEntry {
                     binding: 2,
-                    resource: self.image_uniform_buffer.as_entire_binding(),
+                    resource: uniform_buffer.as_entire_binding(),
                 },
             ],
         });
 
+        self.draw_quad(gpu, encoder, view, "Image Render Pass", &self.image_pipeline, &bind_group, clip, viewport);
+    }
+
+    /// Draw a gradient tiled across `clip`. `viewport` is the viewport's
+    /// width, height and scale factor.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_gradient(
+        &self,
+        gpu: &GpuContext,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        clip: &Rect,
+        tiling: &Tiling,
+        gradient: &GradientPaint,
+        opacity: f32,
+        viewport: (f32, f32, f32),
+    ) {
+        if gradient.stops.is_empty() {
+            return;
+        }
+
+        // The shader takes the geometry relative to the tile, in physical pixels
+        let uniforms = GradientUniforms::new(gradient, tiling, opacity, viewport.2);
+        let uniform_buffer = gpu.device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("Gradient Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[uniforms]),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let bind_group = gpu.device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Gradient Bind Group"),
+            layout: &self.gradient_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        self.draw_quad(gpu, encoder, view, "Gradient Render Pass", &self.gradient_pipeline, &bind_group, clip, viewport);
+    }
+
+    /// Fill `rect` with `pipeline`, in a render pass of its own. Each draw
+    /// has its own buffers, as writes to a shared one would all land before
+    /// any of the frame's passes run.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_quad(
+        &self,
+        gpu: &GpuContext,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        label: &str,
+        pipeline: &RenderPipeline,
+        bind_group: &BindGroup,
+        rect: &Rect,
+        (viewport_width, viewport_height, scale_factor): (f32, f32, f32),
+    ) {
+        // Physical viewport dimensions
+        let physical_w = viewport_width * scale_factor;
+        let physical_h = viewport_height * scale_factor;
+
+        // Scale logical rect coordinates to physical
+        let px = rect.x * scale_factor;
+        let py = rect.y * scale_factor;
+        let pw = rect.width * scale_factor;
+        let ph = rect.height * scale_factor;
+
+        let x0 = (px / physical_w) * 2.0 - 1.0;
+        let y0 = 1.0 - (py / physical_h) * 2.0;
+        let x1 = ((px + pw) / physical_w) * 2.0 - 1.0;
+        let y1 = 1.0 - ((py + ph) / physical_h) * 2.0;
+
+        let vertices = [
+            QuadVertex { position: [x0, y0] },
+            QuadVertex { position: [x1, y0] },
+            QuadVertex { position: [x1, y1] },
+            QuadVertex { position: [x0, y1] },
+        ];
+        let indices: [u32; 6] = [0, 1, 2, 0, 2, 3];
+
+        let vertex_buffer = gpu.device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("Quad Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: BufferUsages::VERTEX,
+        });
+        let index_buffer = gpu.device.create_buffer_init(&util::BufferInitDescriptor {
+            label: Some("Quad Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: BufferUsages::INDEX,
+        });
+
         let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-            label: Some("Image Render Pass"),
+            label: Some(label),
             color_attachments: &[Some(RenderPassColorAttachment {
                 view,
                 resolve_target: None,
@@ -651,10 +1863,10 @@ impl Painter {
             occlusion_query_set: None,
         });
 
-        render_pass.set_pipeline(&self.image_pipeline);
-        render_pass.set_bind_group(0, &bind_group, &[]);
-        render_pass.set_vertex_buffer(0, self.image_vertex_buffer.slice(..));
-        render_pass.set_index_buffer(self.image_index_buffer.slice(..), IndexFormat::Uint32);
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), IndexFormat::Uint32);
         render_pass.draw_indexed(0..6, 0, 0..1);
     }
 }
@@ -0,0 +1,193 @@
+// Effects shader: post-processing passes over layers, which hold
+// premultiplied colors. Color matrices, blurs and drop shadows each draw a
+// whole layer from another; blending draws a layer into part of the canvas
+// it mixes with, from a copy of what was there.
+
+struct EffectUniforms {
+    // The color matrix's first four columns, then its last
+    matrix: mat4x4<f32>,
+    offset: vec4<f32>,
+    // A drop shadow's color, premultiplied
+    color: vec4<f32>,
+    // What is drawn, in clip space: left, top, right, bottom
+    quad: vec4<f32>,
+    // Where the quad's corners are in the source: left, top, right, bottom
+    source_uv: vec4<f32>,
+    // Blur: one texel along the direction blurred. Shadow: the offset.
+    step: vec2<f32>,
+    // Blur: the standard deviation, in texels
+    sigma: f32,
+    mode: u32,
+    opacity: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) source_uv: vec2<f32>,
+    @location(1) backdrop_uv: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+
+@group(0) @binding(1)
+var s_source: sampler;
+
+@group(0) @binding(2)
+var<uniform> effect: EffectUniforms;
+
+@group(0) @binding(3)
+var t_backdrop: texture_2d<f32>;
+
+const MAX_BLUR_TAPS: i32 = 64;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // Two triangles covering the quad
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[index];
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(mix(effect.quad.xy, effect.quad.zw, corner), 0.0, 1.0);
+    out.source_uv = mix(effect.source_uv.xy, effect.source_uv.zw, corner);
+    out.backdrop_uv = corner;
+    return out;
+}
+
+fn unpremultiply(color: vec4<f32>) -> vec4<f32> {
+    if (color.a <= 0.0) {
+        return vec4<f32>(0.0);
+    }
+    return vec4<f32>(color.rgb / color.a, color.a);
+}
+
+@fragment
+fn fs_color_matrix(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = unpremultiply(textureSample(t_source, s_source, in.source_uv));
+    let result = clamp(effect.matrix * color + effect.offset, vec4<f32>(0.0), vec4<f32>(1.0));
+    return vec4<f32>(result.rgb * result.a, result.a);
+}
+
+// One direction of a gaussian blur. Wide blurs take taps spread further
+// apart, between texels, rather than more of them.
+@fragment
+fn fs_blur(in: VertexOutput) -> @location(0) vec4<f32> {
+    let radius = ceil(3.0 * effect.sigma);
+    let taps = min(i32(radius), MAX_BLUR_TAPS);
+    let spacing = radius / f32(max(taps, 1));
+    var sum = vec4<f32>(0.0);
+    var total = 0.0;
+    for (var i = -taps; i <= taps; i++) {
+        let distance = f32(i) * spacing;
+        let weight = exp(-0.5 * distance * distance / (effect.sigma * effect.sigma));
+        sum += weight * textureSample(t_source, s_source, in.source_uv + effect.step * distance);
+        total += weight;
+    }
+    return sum / total;
+}
+
+// The source's shape in the shadow color, moved by the offset
+@fragment
+fn fs_shadow(in: VertexOutput) -> @location(0) vec4<f32> {
+    let alpha = textureSample(t_source, s_source, in.source_uv - effect.step).a;
+    return effect.color * alpha;
+}
+
+fn luminance(c: vec3<f32>) -> f32 {
+    return dot(c, vec3<f32>(0.3, 0.59, 0.11));
+}
+
+fn clip_color(c: vec3<f32>) -> vec3<f32> {
+    let l = luminance(c);
+    let n = min(c.r, min(c.g, c.b));
+    let x = max(c.r, max(c.g, c.b));
+    var result = c;
+    if (n < 0.0) {
+        result = l + (result - l) * l / (l - n);
+    }
+    if (x > 1.0) {
+        result = l + (result - l) * (1.0 - l) / (x - l);
+    }
+    return result;
+}
+
+fn set_luminance(c: vec3<f32>, l: f32) -> vec3<f32> {
+    return clip_color(c + (l - luminance(c)));
+}
+
+fn saturation(c: vec3<f32>) -> f32 {
+    return max(c.r, max(c.g, c.b)) - min(c.r, min(c.g, c.b));
+}
+
+fn set_saturation(c: vec3<f32>, s: f32) -> vec3<f32> {
+    let n = min(c.r, min(c.g, c.b));
+    let x = max(c.r, max(c.g, c.b));
+    if (x <= n) {
+        return vec3<f32>(0.0);
+    }
+    return (c - n) * s / (x - n);
+}
+
+fn hard_light(cb: vec3<f32>, cs: vec3<f32>) -> vec3<f32> {
+    let multiply = cb * 2.0 * cs;
+    let screen = cb + (2.0 * cs - 1.0) - cb * (2.0 * cs - 1.0);
+    return select(screen, multiply, cs <= vec3<f32>(0.5));
+}
+
+fn soft_light(cb: vec3<f32>, cs: vec3<f32>) -> vec3<f32> {
+    let d = select(sqrt(cb), ((16.0 * cb - 12.0) * cb + 4.0) * cb, cb <= vec3<f32>(0.25));
+    let darker = cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb);
+    let lighter = cb + (2.0 * cs - 1.0) * (d - cb);
+    return select(lighter, darker, cs <= vec3<f32>(0.5));
+}
+
+fn color_dodge(cb: vec3<f32>, cs: vec3<f32>) -> vec3<f32> {
+    let dodged = min(vec3<f32>(1.0), cb / max(1.0 - cs, vec3<f32>(1e-6)));
+    return select(select(dodged, vec3<f32>(1.0), cs >= vec3<f32>(1.0)), vec3<f32>(0.0), cb <= vec3<f32>(0.0));
+}
+
+fn color_burn(cb: vec3<f32>, cs: vec3<f32>) -> vec3<f32> {
+    let burned = 1.0 - min(vec3<f32>(1.0), (1.0 - cb) / max(cs, vec3<f32>(1e-6)));
+    return select(select(burned, vec3<f32>(0.0), cs <= vec3<f32>(0.0)), vec3<f32>(1.0), cb >= vec3<f32>(1.0));
+}
+
+// The mixed color of the source over the backdrop, both not premultiplied,
+// in `BlendMode::ALL` order
+fn blend(mode: u32, cb: vec3<f32>, cs: vec3<f32>) -> vec3<f32> {
+    switch mode {
+        case 1u: { return cb * cs; }
+        case 2u: { return cb + cs - cb * cs; }
+        case 3u: { return hard_light(cs, cb); }
+        case 4u: { return min(cb, cs); }
+        case 5u: { return max(cb, cs); }
+        case 6u: { return color_dodge(cb, cs); }
+        case 7u: { return color_burn(cb, cs); }
+        case 8u: { return hard_light(cb, cs); }
+        case 9u: { return soft_light(cb, cs); }
+        case 10u: { return abs(cb - cs); }
+        case 11u: { return cb + cs - 2.0 * cb * cs; }
+        case 12u: { return set_luminance(set_saturation(cs, saturation(cb)), luminance(cb)); }
+        case 13u: { return set_luminance(set_saturation(cb, saturation(cs)), luminance(cb)); }
+        case 14u: { return set_luminance(cs, luminance(cb)); }
+        case 15u: { return set_luminance(cb, luminance(cs)); }
+        default: { return cs; }
+    }
+}
+
+// The source faded by the opacity, blended with the backdrop, then
+// composited over it; written in place of it
+@fragment
+fn fs_blend(in: VertexOutput) -> @location(0) vec4<f32> {
+    let source = textureSample(t_source, s_source, in.source_uv) * effect.opacity;
+    let backdrop = textureSample(t_backdrop, s_source, in.backdrop_uv);
+    let cs = unpremultiply(source).rgb;
+    let cb = unpremultiply(backdrop).rgb;
+    let mixed = (1.0 - backdrop.a) * cs + backdrop.a * clamp(blend(effect.mode, cb, cs), vec3<f32>(0.0), vec3<f32>(1.0));
+    return vec4<f32>(source.a * mixed + (1.0 - source.a) * backdrop.rgb, source.a + backdrop.a * (1.0 - source.a));
+}
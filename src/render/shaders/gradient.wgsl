@@ -0,0 +1,107 @@
+// Gradient shader: linear, radial and conic gradients, tiled across the
+// quad the same way as images.
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+const KIND_LINEAR: u32 = 0u;
+const KIND_RADIAL: u32 = 1u;
+const KIND_CONIC: u32 = 2u;
+const TAU: f32 = 6.2831853;
+
+struct GradientUniforms {
+    // One copy of the gradient: origin and size, in physical pixels
+    tile: vec4<f32>,
+    // Distance between copies, then whether x and y repeat (0 or 1)
+    period: vec4<f32>,
+    // Linear: start and end points. Radial: center and radii. Conic:
+    // center and starting angle. Relative to the tile's origin.
+    geometry: vec4<f32>,
+    kind: u32,
+    repeating: u32,
+    stop_count: u32,
+    opacity: f32,
+    colors: array<vec4<f32>, 16>,
+    // Stop offsets, four to a vector
+    offsets: array<vec4<f32>, 4>,
+}
+
+@group(0) @binding(0)
+var<uniform> uniforms: GradientUniforms;
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(in.position, 0.0, 1.0);
+    return out;
+}
+
+fn stop_offset(i: u32) -> f32 {
+    return uniforms.offsets[i / 4u][i % 4u];
+}
+
+fn premultiply(color: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(color.rgb * color.a, color.a);
+}
+
+// Colors between stops mix premultiplied, so fading to transparent
+// doesn't darken
+fn color_at(t: f32) -> vec4<f32> {
+    let count = uniforms.stop_count;
+    var color = uniforms.colors[count - 1u];
+    if (t <= stop_offset(0u)) {
+        color = uniforms.colors[0];
+    } else {
+        for (var i = 1u; i < count; i = i + 1u) {
+            let end = stop_offset(i);
+            if (t <= end) {
+                let start = stop_offset(i - 1u);
+                let f = select((t - start) / (end - start), 1.0, end <= start);
+                let mixed = mix(premultiply(uniforms.colors[i - 1u]), premultiply(uniforms.colors[i]), f);
+                color = vec4<f32>(mixed.rgb / max(mixed.a, 0.0001), mixed.a);
+                break;
+            }
+        }
+    }
+    return color;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let offset = in.clip_position.xy - uniforms.tile.xy;
+    let repeat = uniforms.period.zw > vec2<f32>(0.5);
+    let wrapped = offset - floor(offset / uniforms.period.xy) * uniforms.period.xy;
+    let local = select(offset, wrapped, repeat);
+    let inside = all(local >= vec2<f32>(0.0)) && all(local < uniforms.tile.zw);
+
+    var t: f32;
+    if (uniforms.kind == KIND_LINEAR) {
+        let start = uniforms.geometry.xy;
+        let line = uniforms.geometry.zw - start;
+        t = dot(local - start, line) / max(dot(line, line), 0.0001);
+    } else if (uniforms.kind == KIND_RADIAL) {
+        t = length((local - uniforms.geometry.xy) / max(uniforms.geometry.zw, vec2<f32>(0.0001)));
+    } else {
+        let v = local - uniforms.geometry.xy;
+        // Clockwise from the top, with y pointing down
+        let angle = atan2(v.x, -v.y) - uniforms.geometry.z;
+        t = (angle - floor(angle / TAU) * TAU) / TAU;
+    }
+
+    if (uniforms.repeating != 0u) {
+        let first = stop_offset(0u);
+        let length = stop_offset(uniforms.stop_count - 1u) - first;
+        if (length > 0.0) {
+            t = first + (t - first) - floor((t - first) / length) * length;
+        }
+    }
+
+    let color = color_at(t);
+    let alpha = select(0.0, color.a * uniforms.opacity, inside);
+    return vec4<f32>(color.rgb, alpha);
+}
@@ -1,16 +1,19 @@
-// Image/textured quad shader
+// Image/textured quad shader. The quad covers the clip rect; the image is
+// placed by tiling, repeating on the axes that repeat.
 
 struct VertexInput {
     @location(0) position: vec2<f32>,
-    @location(1) tex_coords: vec2<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) tex_coords: vec2<f32>,
 }
 
 struct ImageUniforms {
+    // One copy of the image: origin and size, in physical pixels
+    tile: vec4<f32>,
+    // Distance between copies, then whether x and y repeat (0 or 1)
+    period: vec4<f32>,
     opacity: f32,
     _pad1: f32,
     _pad2: f32,
@@ -30,12 +33,22 @@ var<uniform> uniforms: ImageUniforms;
 fn vs_main(in: VertexInput) -> VertexOutput {
     var out: VertexOutput;
     out.clip_position = vec4<f32>(in.position, 0.0, 1.0);
-    out.tex_coords = in.tex_coords;
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let tex_color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
-    return vec4<f32>(tex_color.rgb, tex_color.a * uniforms.opacity);
+    let offset = in.clip_position.xy - uniforms.tile.xy;
+    let repeat = uniforms.period.zw > vec2<f32>(0.5);
+    let wrapped = offset - floor(offset / uniforms.period.xy) * uniforms.period.xy;
+    let local = select(offset, wrapped, repeat);
+    let inside = all(local >= vec2<f32>(0.0)) && all(local < uniforms.tile.zw);
+
+    // Gradients of the unwrapped coordinates keep the sampler from seeing
+    // a jump at each seam
+    let tex_coords = local / uniforms.tile.zw;
+    let unwrapped = offset / uniforms.tile.zw;
+    let tex_color = textureSampleGrad(t_diffuse, s_diffuse, tex_coords, dpdx(unwrapped), dpdy(unwrapped));
+    let alpha = select(0.0, tex_color.a * uniforms.opacity, inside);
+    return vec4<f32>(tex_color.rgb, alpha);
 }
@@ -0,0 +1,36 @@
+// Layer shader: composites a layer of transformed content. Positions come
+// in clip space with their w, so the layer is sampled with perspective
+// correction. Layers hold premultiplied colors, so fading them scales every
+// channel.
+
+struct VertexInput {
+    @location(0) position: vec4<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) opacity: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) opacity: f32,
+}
+
+@group(0) @binding(0)
+var t_layer: texture_2d<f32>;
+
+@group(0) @binding(1)
+var s_layer: sampler;
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = in.position;
+    out.tex_coords = in.tex_coords;
+    out.opacity = in.opacity;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_layer, s_layer, in.tex_coords) * in.opacity;
+}
@@ -1,22 +1,219 @@
+// Rect shader: fills rects, or draws borders along their inside edge, with
+// elliptical corners. Edges are antialiased from signed distances in
+// physical pixels. Text decoration lines are drawn as a top border as
+// tall as the rect.
+
 struct VertexInput {
     @location(0) position: vec2<f32>,
     @location(1) color: vec4<f32>,
+    // Position in the rect, then the rect's size
+    @location(2) local: vec4<f32>,
+    // Corner radii, clockwise from the top left
+    @location(3) radii_x: vec4<f32>,
+    @location(4) radii_y: vec4<f32>,
+    // Border widths: top, right, bottom, left; all 0 to fill the rect
+    @location(5) widths: vec4<f32>,
+    @location(6) styles: vec4<u32>,
+    @location(7) top_color: vec4<f32>,
+    @location(8) right_color: vec4<f32>,
+    @location(9) bottom_color: vec4<f32>,
+    @location(10) left_color: vec4<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) local: vec4<f32>,
+    @location(2) @interpolate(flat) radii_x: vec4<f32>,
+    @location(3) @interpolate(flat) radii_y: vec4<f32>,
+    @location(4) @interpolate(flat) widths: vec4<f32>,
+    @location(5) @interpolate(flat) styles: vec4<u32>,
+    @location(6) @interpolate(flat) top_color: vec4<f32>,
+    @location(7) @interpolate(flat) right_color: vec4<f32>,
+    @location(8) @interpolate(flat) bottom_color: vec4<f32>,
+    @location(9) @interpolate(flat) left_color: vec4<f32>,
 }
 
+const STYLE_NONE: u32 = 0u;
+const STYLE_DASHED: u32 = 2u;
+const STYLE_DOTTED: u32 = 3u;
+const STYLE_DOUBLE: u32 = 4u;
+const STYLE_GROOVE: u32 = 5u;
+const STYLE_RIDGE: u32 = 6u;
+const STYLE_INSET: u32 = 7u;
+const STYLE_OUTSET: u32 = 8u;
+// Text decorations only
+const STYLE_WAVY: u32 = 9u;
+
 @vertex
 fn vs_main(in: VertexInput) -> VertexOutput {
     var out: VertexOutput;
     out.clip_position = vec4<f32>(in.position, 0.0, 1.0);
     out.color = in.color;
+    out.local = in.local;
+    out.radii_x = in.radii_x;
+    out.radii_y = in.radii_y;
+    out.widths = in.widths;
+    out.styles = in.styles;
+    out.top_color = in.top_color;
+    out.right_color = in.right_color;
+    out.bottom_color = in.bottom_color;
+    out.left_color = in.left_color;
     return out;
 }
 
+// Approximate signed distance from p to the ellipse with radii r around
+// the origin
+fn ellipse_distance(p: vec2<f32>, r: vec2<f32>) -> f32 {
+    let k0 = length(p / r);
+    let k1 = length(p / (r * r));
+    return k0 * (k0 - 1.0) / max(k1, 0.0001);
+}
+
+// The radii of the corner nearest p
+fn corner_radii(q: vec2<f32>, rx: vec4<f32>, ry: vec4<f32>) -> vec2<f32> {
+    if (q.x < 0.0) {
+        return select(vec2<f32>(rx.w, ry.w), vec2<f32>(rx.x, ry.x), q.y < 0.0);
+    }
+    return select(vec2<f32>(rx.z, ry.z), vec2<f32>(rx.y, ry.y), q.y < 0.0);
+}
+
+// Signed distance from p to the rect at origin of size with rounded
+// corners, negative inside
+fn rounded_rect_distance(p: vec2<f32>, origin: vec2<f32>, size: vec2<f32>, rx: vec4<f32>, ry: vec4<f32>) -> f32 {
+    if (any(size <= vec2<f32>(0.0))) {
+        return 1.0e6;
+    }
+    let half = size * 0.5;
+    let q = p - origin - half;
+    let r = corner_radii(q, rx, ry);
+    let corner = abs(q) - (half - r);
+    if (all(corner > vec2<f32>(0.0)) && all(r > vec2<f32>(0.0))) {
+        return ellipse_distance(corner, r);
+    }
+    let d = abs(q) - half;
+    return length(max(d, vec2<f32>(0.0))) + min(max(d.x, d.y), 0.0);
+}
+
+// How much of the pixel is inside an edge the given distance away
+fn coverage(distance: f32) -> f32 {
+    return clamp(0.5 - distance, 0.0, 1.0);
+}
+
+// The darker or lighter shade of a groove, ridge, inset or outset border
+fn shade(color: vec4<f32>, dark: bool) -> vec4<f32> {
+    if (dark) {
+        return vec4<f32>(color.rgb * 0.6, color.a);
+    }
+    return vec4<f32>(mix(color.rgb, vec3<f32>(1.0), 0.25), color.a);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return in.color;
+    let p = in.local.xy;
+    let size = in.local.zw;
+    let outer = rounded_rect_distance(p, vec2<f32>(0.0), size, in.radii_x, in.radii_y);
+    let w = in.widths;
+    if (all(w == vec4<f32>(0.0))) {
+        return vec4<f32>(in.color.rgb, in.color.a * coverage(outer));
+    }
+
+    // The padding edge: inset by the widths, its radii reduced by them
+    let inner_rx = max(in.radii_x - vec4<f32>(w.w, w.y, w.y, w.w), vec4<f32>(0.0));
+    let inner_ry = max(in.radii_y - vec4<f32>(w.x, w.x, w.z, w.z), vec4<f32>(0.0));
+    let inner_size = size - vec2<f32>(w.w + w.y, w.x + w.z);
+    let inner = rounded_rect_distance(p, vec2<f32>(w.w, w.x), inner_size, inner_rx, inner_ry);
+    let mask = coverage(outer) * clamp(0.5 + inner, 0.0, 1.0);
+    if (mask <= 0.0) {
+        discard;
+    }
+
+    // The pixel belongs to the side it's the smallest fraction of the way
+    // across, which splits corners along the line from the outer corner
+    // to the inner one
+    let across = vec4<f32>(p.y, size.x - p.x, size.y - p.y, p.x) / max(w, vec4<f32>(0.0001));
+    var side = 0u;
+    var t = across.x;
+    for (var i = 1u; i < 4u; i = i + 1u) {
+        if (across[i] < t) {
+            side = i;
+            t = across[i];
+        }
+    }
+
+    // In a rounded corner, how far across is measured between the curves
+    let q = p - size * 0.5;
+    let r = corner_radii(q, in.radii_x, in.radii_y);
+    if (all(abs(q) > size * 0.5 - r) && all(r > vec2<f32>(0.0))) {
+        t = -outer / max(inner - outer, 0.0001);
+    }
+    t = clamp(t, 0.0, 1.0);
+
+    var colors = array<vec4<f32>, 4>(in.top_color, in.right_color, in.bottom_color, in.left_color);
+    var color = colors[side];
+    let width = w[side];
+    let depth = t * width;
+    let style = in.styles[side];
+    let horizontal = side == 0u || side == 2u;
+    let along = select(p.y, p.x, horizontal);
+    let length_along = select(size.y, size.x, horizontal);
+    let top_or_left = side == 0u || side == 3u;
+    var alpha = 1.0;
+
+    switch style {
+        case STYLE_NONE: {
+            discard;
+        }
+        // Dashes three times as long as the border is wide, spaced to fit
+        // the side, with half a dash at each corner
+        case STYLE_DASHED: {
+            let dash = 3.0 * width;
+            let count = max(round(length_along / (2.0 * dash)), 1.0);
+            let period = length_along / count;
+            alpha = select(0.0, 1.0, fract(along / period + 0.25) < 0.5);
+        }
+        // Round dots as wide as the border, spaced to fit between the
+        // middles of the sides either side, with one at each corner
+        case STYLE_DOTTED: {
+            let ends = select(vec2<f32>(w.x, w.z), vec2<f32>(w.w, w.y), horizontal) * 0.5;
+            let span = length_along - ends.x - ends.y;
+            let count = max(round(span / (2.0 * width)), 1.0);
+            let period = span / count;
+            let center = ends.x + round((along - ends.x) / period) * period;
+            let distance = length(vec2<f32>(along - center, depth - width * 0.5));
+            alpha = clamp(width * 0.5 - distance + 0.5, 0.0, 1.0);
+        }
+        // Two lines, each a third of the width
+        case STYLE_DOUBLE: {
+            if (width >= 3.0) {
+                let third = width / 3.0;
+                let outer_line = clamp(third - depth + 0.5, 0.0, 1.0);
+                let inner_line = clamp(depth - (width - third) + 0.5, 0.0, 1.0);
+                alpha = clamp(outer_line + inner_line, 0.0, 1.0);
+            }
+        }
+        // Carved in or raised: the outer and inner halves shaded opposite ways
+        case STYLE_GROOVE, STYLE_RIDGE: {
+            let outer_half = depth < width * 0.5;
+            color = shade(color, (outer_half == top_or_left) == (style == STYLE_GROOVE));
+        }
+        // Sunk in or raised: the top and left sides shaded opposite to the
+        // bottom and right
+        case STYLE_INSET, STYLE_OUTSET: {
+            color = shade(color, top_or_left == (style == STYLE_INSET));
+        }
+        // A sine wave a third of the width thick, swinging across the rest
+        case STYLE_WAVY: {
+            let thickness = width / 3.0;
+            let wavelength = 4.0 * width;
+            let phase = along / wavelength * 6.2831853;
+            let center = width * 0.5 + thickness * sin(phase);
+            let slope = thickness * cos(phase) * 6.2831853 / wavelength;
+            let distance = abs(depth - center) / sqrt(1.0 + slope * slope);
+            alpha = clamp(thickness * 0.5 - distance + 0.5, 0.0, 1.0);
+        }
+        default: {}
+    }
+
+    return vec4<f32>(color.rgb, color.a * mask * alpha);
 }
@@ -1,18 +1,66 @@
+use super::font::{map_generic_families, register_face, register_local_face, FaceDescriptors, Font};
 use super::gpu::GpuContext;
 use super::painter::Color;
-use cosmic_text::{Attrs, Buffer, Family, FontSystem, Metrics, Shaping, SwashCache};
+use cosmic_text::{Buffer, FontSystem, Metrics, Shaping, SwashCache};
 use glyphon::{
     Cache, FontSystem as GlyphonFontSystem, SwashCache as GlyphonSwashCache,
     TextArea, TextAtlas, TextBounds, TextRenderer as GlyphonRenderer, Viewport,
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use wgpu::*;
 
+/// Space added to text as it's laid out: `letter-spacing` after every
+/// character, `word-spacing` after every space, and `text-indent` before
+/// the first line
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TextSpacing {
+    pub letter: f32,
+    pub word: f32,
+    pub indent: f32,
+}
+
+impl TextSpacing {
+    pub fn is_none(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// A positioned run of text to draw
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRun {
+    pub text: String,
+    pub x: f32,
+    pub y: f32,
+    pub color: Color,
+    pub font_size: f32,
+    pub font: Font,
+    pub spacing: TextSpacing,
+    /// Where spaced text wraps, as it was measured
+    pub max_width: f32,
+}
+
+impl TextRun {
+    pub fn new(text: String, x: f32, y: f32, color: Color, font_size: f32, font: Font) -> Self {
+        Self {
+            text,
+            x,
+            y,
+            color,
+            font_size,
+            font,
+            spacing: TextSpacing::default(),
+            max_width: f32::INFINITY,
+        }
+    }
+}
+
 /// Key for caching shaped text buffers
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 struct TextCacheKey {
     text: String,
     font_size_bits: u32, // f32 as bits for hashing
+    font: Font,
 }
 
 pub struct TextRenderer {
@@ -24,16 +72,18 @@ pub struct TextRenderer {
     text_atlas: TextAtlas,
     text_renderer: GlyphonRenderer,
     viewport: Viewport,
-    /// Cache of shaped text buffers keyed by (text, font_size)
+    /// Cache of shaped text buffers keyed by (text, font_size, font)
     buffer_cache: HashMap<TextCacheKey, usize>,
     /// Pool of buffers (indices into this vec are stored in buffer_cache)
     buffers: Vec<glyphon::Buffer>,
-    /// Cache of text measurements keyed by (text, font_size) -> (width, height)
+    /// Cache of text measurements keyed by (text, font_size, font) -> (width, height)
     measure_cache: HashMap<TextCacheKey, (f32, f32)>,
-    /// Character width lookup table (ASCII 0-127) at reference font size 16.0
-    char_widths: [f32; 128],
-    /// Average width for non-ASCII characters at reference font size
-    default_char_width: f32,
+    /// Per font: character width lookup table (ASCII 0-127) at reference
+    /// font size 16.0, and the width used for non-ASCII characters
+    char_widths: HashMap<Font, ([f32; 128], f32)>,
+    /// `@font-face` sources registered so far, with the rule they were
+    /// registered for
+    web_fonts: HashSet<(FaceDescriptors, String)>,
     scale_factor: f32,
 }
 
@@ -43,15 +93,21 @@ impl TextRenderer {
         let t0 = Instant::now();
 
         let mut font_system = FontSystem::new();
+        map_generic_families(font_system.db_mut());
         let t1 = Instant::now();
 
         let swash_cache = SwashCache::new();
 
         // Pre-compute character widths for fast measurement (reuse font_system)
-        let (char_widths, default_char_width) = Self::compute_char_widths(&mut font_system);
+        let default_font = Font::default();
+        let char_widths = HashMap::from([(
+            default_font.clone(),
+            Self::compute_char_widths(&mut font_system, &default_font),
+        )]);
         let t2 = Instant::now();
 
-        let glyphon_font_system = GlyphonFontSystem::new();
+        let mut glyphon_font_system = GlyphonFontSystem::new();
+        map_generic_families(glyphon_font_system.db_mut());
         let t3 = Instant::now();
 
         let glyphon_swash_cache = GlyphonSwashCache::new();
@@ -86,24 +142,24 @@ impl TextRenderer {
             buffers: Vec::new(),
             measure_cache: HashMap::new(),
             char_widths,
-            default_char_width,
+            web_fonts: HashSet::new(),
             scale_factor,
         }
     }
 
-    /// Pre-compute character widths at reference font size (16.0)
-    fn compute_char_widths(font_system: &mut FontSystem) -> ([f32; 128], f32) {
+    /// Pre-compute character widths of a font at reference font size (16.0)
+    fn compute_char_widths(font_system: &mut FontSystem, font: &Font) -> ([f32; 128], f32) {
         const REF_SIZE: f32 = 16.0;
         let metrics = Metrics::new(REF_SIZE, REF_SIZE * 1.2);
         let mut widths = [0.0f32; 128];
+        let family = font.available_family(font_system.db());
 
         // Measure each ASCII character
         for i in 32u8..127u8 {
             let ch = i as char;
             let text = ch.to_string();
             let mut buffer = Buffer::new(font_system, metrics);
-            let attrs = Attrs::new().family(Family::SansSerif);
-            buffer.set_text(font_system, &text, attrs, Shaping::Basic);
+            buffer.set_text(font_system, &text, font.attrs(&family), Shaping::Basic);
             buffer.shape_until_scroll(font_system, false);
 
             let mut width = 0.0f32;
@@ -138,6 +194,45 @@ impl TextRenderer {
         self.measure_cache.clear();
     }
 
+    /// Whether `source` was already registered for an `@font-face` rule
+    /// with these descriptors
+    pub fn has_web_font(&self, descriptors: &FaceDescriptors, source: &str) -> bool {
+        self.web_fonts.contains(&(descriptors.clone(), source.to_string()))
+    }
+
+    /// Registers a font decoded from `source` under the rule's family.
+    /// Returns whether it had any faces.
+    pub fn register_font(&mut self, source: &str, data: Vec<u8>, descriptors: &FaceDescriptors) -> bool {
+        let data = Arc::new(data);
+        let count = register_face(self.font_system.db_mut(), data.clone(), descriptors);
+        if count == 0 {
+            return false;
+        }
+        register_face(self.glyphon_font_system.db_mut(), data, descriptors);
+        self.fonts_changed(descriptors, source);
+        true
+    }
+
+    /// Registers the installed font a `local()` source names under the
+    /// rule's family. Returns whether it is installed.
+    pub fn register_local_font(&mut self, name: &str, descriptors: &FaceDescriptors) -> bool {
+        let count = register_local_face(self.font_system.db_mut(), name, descriptors);
+        if count == 0 {
+            return false;
+        }
+        register_local_face(self.glyphon_font_system.db_mut(), name, descriptors);
+        self.fonts_changed(descriptors, name);
+        true
+    }
+
+    /// Records a registered source and drops everything measured or
+    /// shaped with the old set of fonts
+    fn fonts_changed(&mut self, descriptors: &FaceDescriptors, source: &str) {
+        self.web_fonts.insert((descriptors.clone(), source.to_string()));
+        self.clear_cache();
+        self.char_widths.clear();
+    }
+
     /// Check if text is ASCII-only (can use Basic shaping)
     #[inline]
     fn is_ascii_text(text: &str) -> bool {
@@ -147,14 +242,18 @@ impl TextRenderer {
     /// Render all text groups in a single pass
     /// Each group has its own clip_top value (in physical pixels)
     /// Groups are rendered in order: first group's texts, then second group's texts, etc.
+    /// `scale_factor` is the target's, which differs from the window's for
+    /// layers of transformed content.
+    #[allow(clippy::too_many_arguments)]
     pub fn render_all(
         &mut self,
         gpu: &GpuContext,
         encoder: &mut CommandEncoder,
         view: &TextureView,
-        text_groups: &[(&[(String, f32, f32, Color, f32)], u32)], // (texts, clip_top in physical pixels)
+        text_groups: &[(&[TextRun], u32)], // (texts, clip_top in physical pixels)
         viewport_width: u32,
         viewport_height: u32,
+        scale_factor: f32,
     ) {
         // Count total texts
         let total_texts: usize = text_groups.iter().map(|(texts, _)| texts.len()).sum();
@@ -183,69 +282,40 @@ impl TextRenderer {
         let viewport_height_f = viewport_height as f32;
 
         for (texts, clip_top) in text_groups.iter() {
-            let clip_top_logical = (*clip_top as f32) / self.scale_factor;
-
-            for (text, x, y, color, font_size) in texts.iter() {
-                // Viewport culling: skip texts that are completely outside the visible area
-                // Text is visible if: y + line_height > clip_top AND y < viewport_height
-                let line_height = *font_size * 1.2;
-                if *y + line_height < clip_top_logical || *y * self.scale_factor > viewport_height_f {
-                    continue;
-                }
-
-                let physical_font_size = *font_size * self.scale_factor;
-                let cache_key = TextCacheKey {
-                    text: text.clone(),
-                    font_size_bits: physical_font_size.to_bits(),
-                };
+            let clip_top_logical = (*clip_top as f32) / scale_factor;
 
-                let buffer_idx = if let Some(&idx) = self.buffer_cache.get(&cache_key) {
-                    idx
+            for run in texts.iter() {
+                // Spaced text is drawn a word or a character at a time, where
+                // it was placed when measured
+                let pieces = if run.spacing.is_none() {
+                    vec![(run.text.clone(), 0.0, 0)]
                 } else {
-                    let new_idx = self.buffers.len();
-                    self.buffers.push(glyphon::Buffer::new(
-                        &mut self.glyphon_font_system,
-                        glyphon::Metrics::new(16.0, 20.0),
-                    ));
-
-                    let buffer = &mut self.buffers[new_idx];
-                    buffer.set_metrics(
-                        &mut self.glyphon_font_system,
-                        glyphon::Metrics::new(physical_font_size, physical_font_size * 1.2),
-                    );
-                    let attrs = glyphon::Attrs::new().family(glyphon::Family::SansSerif);
-
-                    // Use Basic shaping for ASCII text (much faster)
-                    let shaping = if Self::is_ascii_text(text) {
-                        glyphon::Shaping::Basic
-                    } else {
-                        glyphon::Shaping::Advanced
-                    };
-
-                    buffer.set_text(
-                        &mut self.glyphon_font_system,
-                        text,
-                        attrs,
-                        shaping,
-                    );
-                    buffer.set_size(
-                        &mut self.glyphon_font_system,
-                        Some(viewport_width as f32),
-                        None,
-                    );
-                    buffer.shape_until_scroll(&mut self.glyphon_font_system, false);
-
-                    self.buffer_cache.insert(cache_key, new_idx);
-                    new_idx
+                    let mut pieces = Vec::new();
+                    self.break_lines_fast(&run.text, run.font_size, &run.font, run.spacing, run.max_width, |piece, x, line| {
+                        pieces.push((piece.to_string(), x, line))
+                    });
+                    pieces
                 };
 
-                all_text_data.push(TextData {
-                    buffer_idx,
-                    x: *x,
-                    y: *y,
-                    color: *color,
-                    clip_top: *clip_top,
-                });
+                let line_height = run.font_size * 1.2;
+                for (text, x, line) in pieces {
+                    let x = run.x + x;
+                    let y = run.y + line as f32 * line_height;
+                    // Viewport culling: skip texts that are completely outside the visible area
+                    // Text is visible if: y + line_height > clip_top AND y < viewport_height
+                    if y + line_height < clip_top_logical || y * scale_factor > viewport_height_f {
+                        continue;
+                    }
+
+                    let buffer_idx = self.shaped_buffer(&text, run.font_size * scale_factor, &run.font, viewport_width);
+                    all_text_data.push(TextData {
+                        buffer_idx,
+                        x,
+                        y,
+                        color: run.color,
+                        clip_top: *clip_top,
+                    });
+                }
             }
         }
 
@@ -258,8 +328,8 @@ impl TextRenderer {
             .iter()
             .map(|data| TextArea {
                 buffer: &self.buffers[data.buffer_idx],
-                left: data.x * self.scale_factor,
-                top: data.y * self.scale_factor,
+                left: data.x * scale_factor,
+                top: data.y * scale_factor,
                 scale: 1.0,
                 bounds: TextBounds {
                     left: 0,
@@ -315,79 +385,116 @@ impl TextRenderer {
         self.text_atlas.trim();
     }
 
-    /// Fast text measurement using pre-computed character width table
-    /// O(n) where n is string length - no buffer creation or shaping
-    /// Accounts for text wrapping at max_width
-    pub fn measure_text_fast(&self, text: &str, font_size: f32, max_width: f32) -> (f32, f32) {
-        const REF_SIZE: f32 = 16.0;
-        let scale = font_size / REF_SIZE;
-        let line_height = font_size * 1.2;
+    /// The index of a buffer with `text` shaped at a physical font size,
+    /// shaping it if it isn't cached
+    fn shaped_buffer(&mut self, text: &str, physical_font_size: f32, font: &Font, viewport_width: u32) -> usize {
+        let cache_key = TextCacheKey {
+            text: text.to_string(),
+            font_size_bits: physical_font_size.to_bits(),
+            font: font.clone(),
+        };
+        if let Some(&idx) = self.buffer_cache.get(&cache_key) {
+            return idx;
+        }
 
-        let mut max_line_width = 0.0f32;
-        let mut current_line_width = 0.0f32;
-        let mut lines = 1;
+        let new_idx = self.buffers.len();
+        self.buffers.push(glyphon::Buffer::new(
+            &mut self.glyphon_font_system,
+            glyphon::Metrics::new(16.0, 20.0),
+        ));
 
-        // Track word for wrapping
-        let mut word_width = 0.0f32;
+        let buffer = &mut self.buffers[new_idx];
+        buffer.set_metrics(
+            &mut self.glyphon_font_system,
+            glyphon::Metrics::new(physical_font_size, physical_font_size * 1.2),
+        );
+        let family = font.available_family(self.glyphon_font_system.db());
+        let attrs = font.attrs(&family);
+
+        // Use Basic shaping for ASCII text (much faster)
+        let shaping = if Self::is_ascii_text(text) {
+            glyphon::Shaping::Basic
+        } else {
+            glyphon::Shaping::Advanced
+        };
 
-        for ch in text.chars() {
-            if ch == '\n' {
-                // Explicit newline
-                current_line_width += word_width;
-                max_line_width = max_line_width.max(current_line_width);
-                current_line_width = 0.0;
-                word_width = 0.0;
-                lines += 1;
-            } else if ch == ' ' || ch == '\t' {
-                // Space - commit word and add space
-                current_line_width += word_width;
-                word_width = 0.0;
-
-                let space_width = if ch == '\t' {
-                    self.char_widths[' ' as usize] * scale * 4.0
-                } else {
-                    self.char_widths[' ' as usize] * scale
-                };
+        buffer.set_text(
+            &mut self.glyphon_font_system,
+            text,
+            attrs,
+            shaping,
+        );
+        buffer.set_size(
+            &mut self.glyphon_font_system,
+            Some(viewport_width as f32),
+            None,
+        );
+        buffer.shape_until_scroll(&mut self.glyphon_font_system, false);
 
-                // Check if we need to wrap before adding space
-                if current_line_width + space_width > max_width && current_line_width > 0.0 {
-                    max_line_width = max_line_width.max(current_line_width);
-                    current_line_width = 0.0;
-                    lines += 1;
-                } else {
-                    current_line_width += space_width;
-                }
-            } else {
-                // Regular character - add to current word
-                let char_width = if (ch as u32) < 128 {
-                    self.char_widths[ch as usize]
-                } else {
-                    self.default_char_width
-                };
-                word_width += char_width * scale;
-
-                // Check if word + current line exceeds max width
-                if current_line_width + word_width > max_width && current_line_width > 0.0 {
-                    // Wrap: start new line with current word
-                    max_line_width = max_line_width.max(current_line_width);
-                    current_line_width = 0.0;
-                    lines += 1;
-                }
-            }
-        }
+        self.buffer_cache.insert(cache_key, new_idx);
+        new_idx
+    }
+
+    /// Fast text measurement using pre-computed character width table
+    /// O(n) where n is string length - no buffer creation or shaping
+    /// Accounts for text wrapping at max_width
+    pub fn measure_text_fast(
+        &mut self,
+        text: &str,
+        font_size: f32,
+        font: &Font,
+        spacing: TextSpacing,
+        max_width: f32,
+    ) -> (f32, f32) {
+        let lines = self.text_lines_fast(text, font_size, font, spacing, max_width);
+        let width = lines.iter().fold(0.0f32, |max, width| max.max(*width));
+        (width.min(max_width), font_size * 1.2 * lines.len() as f32)
+    }
 
-        // Don't forget the last word
-        current_line_width += word_width;
-        max_line_width = max_line_width.max(current_line_width);
+    /// The widths of the lines `measure_text_fast` breaks text into
+    pub fn text_lines_fast(
+        &mut self,
+        text: &str,
+        font_size: f32,
+        font: &Font,
+        spacing: TextSpacing,
+        max_width: f32,
+    ) -> Vec<f32> {
+        self.break_lines_fast(text, font_size, font, spacing, max_width, |_, _, _| {})
+    }
 
-        (max_line_width.min(max_width), line_height * lines as f32)
+    /// Breaks text into lines with `break_lines`, using the character
+    /// width table
+    fn break_lines_fast(
+        &mut self,
+        text: &str,
+        font_size: f32,
+        font: &Font,
+        spacing: TextSpacing,
+        max_width: f32,
+        place: impl FnMut(&str, f32, usize),
+    ) -> Vec<f32> {
+        const REF_SIZE: f32 = 16.0;
+        if !self.char_widths.contains_key(font) {
+            let widths = Self::compute_char_widths(&mut self.font_system, font);
+            self.char_widths.insert(font.clone(), widths);
+        }
+        let (char_widths, default_char_width) = &self.char_widths[font];
+        let scale = font_size / REF_SIZE;
+        let advance = |ch: char| match ch {
+            '\t' => char_widths[' ' as usize] * scale * 4.0,
+            ch if (ch as u32) < 128 => char_widths[ch as usize] * scale,
+            _ => default_char_width * scale,
+        };
+        break_lines(text, advance, spacing, max_width, place)
     }
 
-    pub fn measure_text(&mut self, text: &str, font_size: f32) -> (f32, f32) {
+    pub fn measure_text(&mut self, text: &str, font_size: f32, font: &Font) -> (f32, f32) {
         // Check cache first
         let cache_key = TextCacheKey {
             text: text.to_string(),
             font_size_bits: font_size.to_bits(),
+            font: font.clone(),
         };
 
         if let Some(&dims) = self.measure_cache.get(&cache_key) {
@@ -398,8 +505,8 @@ impl TextRenderer {
         let metrics = Metrics::new(font_size, font_size * 1.2);
         let mut buffer = Buffer::new(&mut self.font_system, metrics);
 
-        let attrs = Attrs::new().family(Family::SansSerif);
-        buffer.set_text(&mut self.font_system, text, attrs, Shaping::Advanced);
+        let family = font.available_family(self.font_system.db());
+        buffer.set_text(&mut self.font_system, text, font.attrs(&family), Shaping::Advanced);
         buffer.shape_until_scroll(&mut self.font_system, false);
 
         let mut width = 0.0f32;
@@ -419,6 +526,88 @@ impl TextRenderer {
     }
 }
 
+/// Breaks text into lines at spaces, given each character's advance,
+/// calling `place` with each word, or each character when letters are
+/// spaced, its x and its line. Returns the width of each line.
+fn break_lines(
+    text: &str,
+    advance: impl Fn(char) -> f32,
+    spacing: TextSpacing,
+    max_width: f32,
+    mut place: impl FnMut(&str, f32, usize),
+) -> Vec<f32> {
+    let mut lines = Vec::new();
+    // Where the current line's content starts: the indent on the first
+    // line, so a word too wide for it still wraps
+    let mut line_start = spacing.indent;
+    let mut current_line_width = line_start;
+
+    // Track word for wrapping: its characters' byte offsets and x within it
+    let mut word: Vec<(usize, f32)> = Vec::new();
+    let mut word_width = 0.0f32;
+
+    // Places the word at the end of the line
+    let mut commit = |word: &mut Vec<(usize, f32)>, end: usize, x: f32, line: usize| {
+        if let Some(&(start, _)) = word.first() {
+            if spacing.letter == 0.0 {
+                place(&text[start..end], x, line);
+            } else {
+                let ends = word.iter().skip(1).map(|(i, _)| *i).chain([end]);
+                for (&(i, offset), end) in word.iter().zip(ends) {
+                    place(&text[i..end], x + offset, line);
+                }
+            }
+        }
+        word.clear();
+    };
+
+    for (i, ch) in text.char_indices() {
+        if ch == '\n' {
+            // Explicit newline
+            commit(&mut word, i, current_line_width, lines.len());
+            current_line_width += word_width;
+            lines.push(current_line_width);
+            current_line_width = 0.0;
+            line_start = 0.0;
+            word_width = 0.0;
+        } else if ch == ' ' || ch == '\t' {
+            // Space - commit word and add space
+            commit(&mut word, i, current_line_width, lines.len());
+            current_line_width += word_width;
+            word_width = 0.0;
+
+            let space_width = advance(ch) + spacing.letter + spacing.word;
+
+            // Check if we need to wrap before adding space
+            if current_line_width + space_width > max_width && current_line_width > line_start {
+                lines.push(current_line_width);
+                current_line_width = 0.0;
+                line_start = 0.0;
+            } else {
+                current_line_width += space_width;
+            }
+        } else {
+            // Regular character - add to current word
+            word.push((i, word_width));
+            word_width += advance(ch) + spacing.letter;
+
+            // Check if word + current line exceeds max width
+            if current_line_width + word_width > max_width && current_line_width > line_start {
+                // Wrap: start new line with current word
+                lines.push(current_line_width);
+                current_line_width = 0.0;
+                line_start = 0.0;
+            }
+        }
+    }
+
+    // Don't forget the last word
+    commit(&mut word, text.len(), current_line_width, lines.len());
+    current_line_width += word_width;
+    lines.push(current_line_width);
+    lines
+}
+
 /// Standalone text measurement without GPU context (for testing and layout)
 pub struct TextMeasurer {
     font_system: FontSystem,
@@ -426,17 +615,18 @@ pub struct TextMeasurer {
 
 impl TextMeasurer {
     pub fn new() -> Self {
-        Self {
-            font_system: FontSystem::new(),
-        }
+        let mut font_system = FontSystem::new();
+        map_generic_families(font_system.db_mut());
+        Self { font_system }
     }
 
     pub fn measure(&mut self, text: &str, font_size: f32) -> (f32, f32) {
         let metrics = Metrics::new(font_size, font_size * 1.2);
         let mut buffer = Buffer::new(&mut self.font_system, metrics);
 
-        let attrs = Attrs::new().family(Family::SansSerif);
-        buffer.set_text(&mut self.font_system, text, attrs, Shaping::Advanced);
+        let font = Font::default();
+        let family = font.available_family(self.font_system.db());
+        buffer.set_text(&mut self.font_system, text, font.attrs(&family), Shaping::Advanced);
         buffer.shape_until_scroll(&mut self.font_system, false);
 
         let mut width = 0.0f32;
@@ -455,8 +645,9 @@ impl TextMeasurer {
         let mut buffer = Buffer::new(&mut self.font_system, metrics);
 
         buffer.set_size(&mut self.font_system, Some(max_width), None);
-        let attrs = Attrs::new().family(Family::SansSerif);
-        buffer.set_text(&mut self.font_system, text, attrs, Shaping::Advanced);
+        let font = Font::default();
+        let family = font.available_family(self.font_system.db());
+        buffer.set_text(&mut self.font_system, text, font.attrs(&family), Shaping::Advanced);
         buffer.shape_until_scroll(&mut self.font_system, false);
 
         let mut width = 0.0f32;
@@ -759,10 +950,12 @@ mod tests {
         let key1 = TextCacheKey {
             text: "Hello".to_string(),
             font_size_bits: 16.0f32.to_bits(),
+            font: Font::default(),
         };
         let key2 = TextCacheKey {
             text: "Hello".to_string(),
             font_size_bits: 16.0f32.to_bits(),
+            font: Font::default(),
         };
         assert_eq!(key1, key2);
     }
@@ -772,10 +965,12 @@ mod tests {
         let key1 = TextCacheKey {
             text: "Hello".to_string(),
             font_size_bits: 16.0f32.to_bits(),
+            font: Font::default(),
         };
         let key2 = TextCacheKey {
             text: "World".to_string(),
             font_size_bits: 16.0f32.to_bits(),
+            font: Font::default(),
         };
         assert_ne!(key1, key2);
     }
@@ -785,10 +980,12 @@ mod tests {
         let key1 = TextCacheKey {
             text: "Hello".to_string(),
             font_size_bits: 16.0f32.to_bits(),
+            font: Font::default(),
         };
         let key2 = TextCacheKey {
             text: "Hello".to_string(),
             font_size_bits: 24.0f32.to_bits(),
+            font: Font::default(),
         };
         assert_ne!(key1, key2);
     }
@@ -801,10 +998,12 @@ mod tests {
         let key1 = TextCacheKey {
             text: "Test".to_string(),
             font_size_bits: 14.0f32.to_bits(),
+            font: Font::default(),
         };
         let key2 = TextCacheKey {
             text: "Test".to_string(),
             font_size_bits: 14.0f32.to_bits(),
+            font: Font::default(),
         };
 
         let mut hasher1 = DefaultHasher::new();
@@ -823,10 +1022,12 @@ mod tests {
         let key1 = TextCacheKey {
             text: "Test".to_string(),
             font_size_bits: 14.0f32.to_bits(),
+            font: Font::default(),
         };
         let key2 = TextCacheKey {
             text: "Different".to_string(),
             font_size_bits: 14.0f32.to_bits(),
+            font: Font::default(),
         };
 
         let mut hasher1 = DefaultHasher::new();
@@ -845,6 +1046,7 @@ mod tests {
         let key1 = TextCacheKey {
             text: "Hello".to_string(),
             font_size_bits: 16.0f32.to_bits(),
+            font: Font::default(),
         };
         cache.insert(key1.clone(), 0);
 
@@ -852,6 +1054,7 @@ mod tests {
         let lookup_key = TextCacheKey {
             text: "Hello".to_string(),
             font_size_bits: 16.0f32.to_bits(),
+            font: Font::default(),
         };
         assert_eq!(cache.get(&lookup_key), Some(&0));
 
@@ -859,6 +1062,7 @@ mod tests {
         let different_text = TextCacheKey {
             text: "World".to_string(),
             font_size_bits: 16.0f32.to_bits(),
+            font: Font::default(),
         };
         assert_eq!(cache.get(&different_text), None);
 
@@ -866,6 +1070,7 @@ mod tests {
         let different_size = TextCacheKey {
             text: "Hello".to_string(),
             font_size_bits: 24.0f32.to_bits(),
+            font: Font::default(),
         };
         assert_eq!(cache.get(&different_size), None);
     }
@@ -888,6 +1093,7 @@ mod tests {
             let key = TextCacheKey {
                 text: text.to_string(),
                 font_size_bits: (*size).to_bits(),
+                font: Font::default(),
             };
             cache.insert(key, *idx);
         }
@@ -899,6 +1105,7 @@ mod tests {
             let key = TextCacheKey {
                 text: text.to_string(),
                 font_size_bits: (*size).to_bits(),
+                font: Font::default(),
             };
             assert_eq!(cache.get(&key), Some(idx));
         }
@@ -909,10 +1116,12 @@ mod tests {
         let key1 = TextCacheKey {
             text: String::new(),
             font_size_bits: 16.0f32.to_bits(),
+            font: Font::default(),
         };
         let key2 = TextCacheKey {
             text: String::new(),
             font_size_bits: 16.0f32.to_bits(),
+            font: Font::default(),
         };
         assert_eq!(key1, key2);
 
@@ -920,6 +1129,7 @@ mod tests {
         let key3 = TextCacheKey {
             text: " ".to_string(),
             font_size_bits: 16.0f32.to_bits(),
+            font: Font::default(),
         };
         assert_ne!(key1, key3);
     }
@@ -941,6 +1151,7 @@ mod tests {
             let key = TextCacheKey {
                 text: text.to_string(),
                 font_size_bits: 14.0f32.to_bits(),
+                font: Font::default(),
             };
             cache.insert(key, idx);
         }
@@ -952,6 +1163,7 @@ mod tests {
             let key = TextCacheKey {
                 text: text.to_string(),
                 font_size_bits: 14.0f32.to_bits(),
+                font: Font::default(),
             };
             assert_eq!(cache.get(&key), Some(&idx));
         }
@@ -963,10 +1175,12 @@ mod tests {
         let key1 = TextCacheKey {
             text: "Test".to_string(),
             font_size_bits: 16.0f32.to_bits(),
+            font: Font::default(),
         };
         let key2 = TextCacheKey {
             text: "Test".to_string(),
             font_size_bits: 16.001f32.to_bits(),
+            font: Font::default(),
         };
 
         // These should be different because the bits are different
@@ -979,6 +1193,7 @@ mod tests {
         let key1 = TextCacheKey {
             text: "Clone Test".to_string(),
             font_size_bits: 18.0f32.to_bits(),
+            font: Font::default(),
         };
         let key2 = key1.clone();
 
@@ -994,6 +1209,7 @@ mod tests {
         let key = TextCacheKey {
             text: "Test".to_string(),
             font_size_bits: 16.0f32.to_bits(),
+            font: Font::default(),
         };
 
         cache.insert(key.clone(), 0);
@@ -1004,4 +1220,38 @@ mod tests {
         assert_eq!(cache.get(&key), Some(&99));
         assert_eq!(cache.len(), 1);  // Still just one entry
     }
+
+    #[test]
+    fn test_break_lines_with_spacing() {
+        let advance = |_| 10.0;
+        let pieces = |text, spacing, max_width| {
+            let mut pieces = Vec::new();
+            let lines = break_lines(text, advance, spacing, max_width, |piece: &str, x, line| {
+                pieces.push((piece.to_string(), x, line))
+            });
+            (lines, pieces)
+        };
+
+        let (lines, placed) = pieces("ab cd", TextSpacing::default(), 100.0);
+        assert_eq!(lines, vec![50.0]);
+        assert_eq!(placed, vec![("ab".to_string(), 0.0, 0), ("cd".to_string(), 30.0, 0)]);
+
+        // Letter spacing follows every character, spaces included
+        let letters = TextSpacing { letter: 2.0, ..TextSpacing::default() };
+        let (lines, placed) = pieces("ab cd", letters, 100.0);
+        assert_eq!(lines, vec![60.0]);
+        let xs: Vec<f32> = placed.iter().map(|(_, x, _)| *x).collect();
+        assert_eq!(xs, vec![0.0, 12.0, 36.0, 48.0]);
+
+        // The indent and word spacing push the second word onto a new line
+        let spaced = TextSpacing { word: 5.0, indent: 20.0, ..TextSpacing::default() };
+        let (lines, placed) = pieces("ab cd", spaced, 50.0);
+        assert_eq!(lines, vec![40.0, 20.0]);
+        assert_eq!(placed[0], ("ab".to_string(), 20.0, 0));
+        assert_eq!(placed[1], ("cd".to_string(), 0.0, 1));
+
+        // A word too wide for the indented line doesn't leave it empty
+        let (lines, _) = pieces("abcdef", spaced, 50.0);
+        assert_eq!(lines, vec![80.0]);
+    }
 }
@@ -0,0 +1,226 @@
+//! 4x4 transform matrices, as CSS transforms build them. Content is drawn
+//! flat: a transformed point is always one on the z = 0 plane, and only
+//! where it lands on the screen matters.
+
+/// A 3D transform acting on column vectors, `m[row][column]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub m: [[f32; 4]; 4],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        m: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]],
+    };
+
+    /// The 2D matrix `matrix(a, b, c, d, e, f)`
+    pub fn affine(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Self {
+        Self {
+            m: [[a, c, 0.0, e], [b, d, 0.0, f], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]],
+        }
+    }
+
+    /// A matrix from its sixteen values a column at a time, as
+    /// `matrix3d()` lists them
+    pub fn from_columns(values: [f32; 16]) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (i, value) in values.into_iter().enumerate() {
+            m[i % 4][i / 4] = value;
+        }
+        Self { m }
+    }
+
+    pub fn translate(x: f32, y: f32, z: f32) -> Self {
+        let mut transform = Self::IDENTITY;
+        transform.m[0][3] = x;
+        transform.m[1][3] = y;
+        transform.m[2][3] = z;
+        transform
+    }
+
+    pub fn scale(x: f32, y: f32, z: f32) -> Self {
+        let mut transform = Self::IDENTITY;
+        transform.m[0][0] = x;
+        transform.m[1][1] = y;
+        transform.m[2][2] = z;
+        transform
+    }
+
+    /// A rotation by `angle` radians around the axis `[x, y, z]`, clockwise
+    /// on the screen around the z axis; none around a zero axis
+    pub fn rotate(x: f32, y: f32, z: f32, angle: f32) -> Self {
+        let length = (x * x + y * y + z * z).sqrt();
+        if length == 0.0 {
+            return Self::IDENTITY;
+        }
+        let (x, y, z) = (x / length, y / length, z / length);
+        let (sin, cos) = angle.sin_cos();
+        let t = 1.0 - cos;
+        Self {
+            m: [
+                [cos + x * x * t, x * y * t - z * sin, x * z * t + y * sin, 0.0],
+                [y * x * t + z * sin, cos + y * y * t, y * z * t - x * sin, 0.0],
+                [z * x * t - y * sin, z * y * t + x * sin, cos + z * z * t, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// A skew by angles in radians along each axis
+    pub fn skew(x: f32, y: f32) -> Self {
+        Self::affine(1.0, y.tan(), x.tan(), 1.0, 0.0, 0.0)
+    }
+
+    /// A perspective projection with the viewer `distance` pixels in front
+    /// of the z = 0 plane
+    pub fn perspective(distance: f32) -> Self {
+        let mut transform = Self::IDENTITY;
+        transform.m[3][2] = -1.0 / distance.max(1.0);
+        transform
+    }
+
+    /// This transform applied after `other`
+    pub fn multiply(&self, other: &Transform) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (row, out) in m.iter_mut().enumerate() {
+            for (column, value) in out.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[row][k] * other.m[k][column]).sum();
+            }
+        }
+        Self { m }
+    }
+
+    /// This transform applied around `(x, y)` instead of the origin
+    pub fn around(&self, x: f32, y: f32) -> Self {
+        Self::translate(x, y, 0.0)
+            .multiply(self)
+            .multiply(&Self::translate(-x, -y, 0.0))
+    }
+
+    /// Where `(x, y)` goes, before dividing by the last coordinate
+    pub fn apply(&self, x: f32, y: f32) -> [f32; 4] {
+        let m = &self.m;
+        [0, 1, 2, 3].map(|row| m[row][0] * x + m[row][1] * y + m[row][3])
+    }
+
+    /// Where `(x, y)` lands on the screen; `None` if it goes behind the
+    /// viewer
+    pub fn map_point(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let [x, y, _, w] = self.apply(x, y);
+        (w > 0.0).then(|| (x / w, y / w))
+    }
+
+    /// The point that lands on `(x, y)` on the screen, if one does
+    pub fn inverse_map(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let inverse = invert3(&self.flatten())?;
+        let [px, py, pw] = [0, 1, 2].map(|row| inverse[row][0] * x + inverse[row][1] * y + inverse[row][2]);
+        (pw > 0.0).then(|| (px / pw, py / pw))
+    }
+
+    /// Whether anything drawn with it stays visible, rather than being
+    /// squashed flat
+    pub fn is_invertible(&self) -> bool {
+        invert3(&self.flatten()).is_some()
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    /// The offset, if all it does to the screen is move things by one
+    pub fn as_translation(&self) -> Option<(f32, f32)> {
+        let [[a, c, e], [b, d, f], [p, q, w]] = self.flatten();
+        (a == 1.0 && b == 0.0 && c == 0.0 && d == 1.0 && p == 0.0 && q == 0.0 && w == 1.0).then_some((e, f))
+    }
+
+    /// Whether it keeps parallel lines parallel, without perspective
+    pub fn is_affine(&self) -> bool {
+        let [_, _, [p, q, w]] = self.flatten();
+        p == 0.0 && q == 0.0 && w == 1.0
+    }
+
+    /// How much it enlarges things at most, roughly, for drawing them
+    /// sharp enough before transforming
+    pub fn max_scale(&self) -> f32 {
+        let [[a, c, _], [b, d, _], [_, _, w]] = self.flatten();
+        let w = if w > 0.0 { w } else { 1.0 };
+        (a * a + b * b).sqrt().max((c * c + d * d).sqrt()) / w
+    }
+
+    /// The 3x3 matrix taking `(x, y, 1)` on the z = 0 plane to where it
+    /// lands on the screen, before dividing by the last coordinate
+    fn flatten(&self) -> [[f32; 3]; 3] {
+        [0, 1, 3].map(|row| [0, 1, 3].map(|column| self.m[row][column]))
+    }
+}
+
+fn invert3(m: &[[f32; 3]; 3]) -> Option<[[f32; 3]; 3]> {
+    let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    let adjugate = [
+        [cofactor(1, 2, 1, 2), -cofactor(0, 2, 1, 2), cofactor(0, 1, 1, 2)],
+        [-cofactor(1, 2, 0, 2), cofactor(0, 2, 0, 2), -cofactor(0, 1, 0, 2)],
+        [cofactor(1, 2, 0, 1), -cofactor(0, 2, 0, 1), cofactor(0, 1, 0, 1)],
+    ];
+    let determinant = m[0][0] * adjugate[0][0] + m[0][1] * adjugate[1][0] + m[0][2] * adjugate[2][0];
+    if determinant.abs() < 1e-9 {
+        return None;
+    }
+    Some(adjugate.map(|row| row.map(|value| value / determinant)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_point(point: Option<(f32, f32)>, x: f32, y: f32) {
+        let (px, py) = point.expect("the point should be visible");
+        assert!((px - x).abs() < 1e-3 && (py - y).abs() < 1e-3, "({}, {}) != ({}, {})", px, py, x, y);
+    }
+
+    #[test]
+    fn test_compose_and_map() {
+        // A quarter turn clockwise around the middle of a 100x50 box at (10, 20)
+        let rotation = Transform::rotate(0.0, 0.0, 1.0, std::f32::consts::FRAC_PI_2).around(60.0, 45.0);
+        assert_point(rotation.map_point(10.0, 20.0), 85.0, -5.0);
+        assert_point(rotation.inverse_map(85.0, -5.0), 10.0, 20.0);
+        assert!(rotation.is_affine());
+        assert_eq!(rotation.as_translation(), None);
+
+        let translation = Transform::translate(5.0, -3.0, 100.0).multiply(&Transform::IDENTITY);
+        assert_eq!(translation.as_translation(), Some((5.0, -3.0)));
+        assert_eq!(Transform::affine(1.0, 0.0, 0.0, 1.0, 2.0, 3.0), Transform::translate(2.0, 3.0, 0.0));
+        assert_eq!(
+            Transform::from_columns([2.0, 0.0, 0.0, 0.0, 0.0, 3.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 4.0, 5.0, 0.0, 1.0]),
+            Transform::translate(4.0, 5.0, 0.0).multiply(&Transform::scale(2.0, 3.0, 1.0))
+        );
+        assert!((Transform::scale(2.0, 3.0, 1.0).max_scale() - 3.0).abs() < 1e-6);
+
+        // Squashed flat, nothing shows
+        assert!(!Transform::scale(0.0, 1.0, 1.0).is_invertible());
+        assert!(Transform::scale(1.0, 1.0, 0.0).is_invertible());
+    }
+
+    #[test]
+    fn test_perspective() {
+        // Tilted back around the x axis, the top edge recedes and shrinks
+        let tilt = Transform::perspective(500.0)
+            .multiply(&Transform::rotate(1.0, 0.0, 0.0, 0.5))
+            .around(100.0, 100.0);
+        assert!(!tilt.is_affine());
+        let (left, top) = tilt.map_point(0.0, 0.0).unwrap();
+        let (right, _) = tilt.map_point(200.0, 0.0).unwrap();
+        assert!(right - left < 200.0);
+        assert!(top > 0.0);
+        assert_point(tilt.inverse_map(left, top), 0.0, 0.0);
+
+        // Pushed behind the viewer
+        let behind = Transform::perspective(100.0).multiply(&Transform::translate(0.0, 0.0, 200.0));
+        assert_eq!(behind.map_point(0.0, 0.0), None);
+    }
+}